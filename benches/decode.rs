@@ -0,0 +1,51 @@
+//! Benchmarks for the hot path, from packet bytes to frames.
+//!
+//! Run with `cargo bench`.
+
+#[macro_use]
+extern crate criterion;
+extern crate velodyne;
+
+use criterion::{Criterion, Throughput};
+use std::hint::black_box;
+use velodyne::fixtures::VLP_16_DATA_PACKET;
+use velodyne::frame::Assembler;
+use velodyne::vlp_16::{Decoder, Packet};
+
+fn decode(c: &mut Criterion) {
+    let mut group = c.benchmark_group("decode");
+    group.throughput(Throughput::Bytes(VLP_16_DATA_PACKET.len() as u64));
+    group.bench_function("packet", |b| b.iter(|| Packet::new(black_box(&VLP_16_DATA_PACKET))));
+    group.finish();
+}
+
+fn reconstruct(c: &mut Criterion) {
+    let packet = Packet::new(&VLP_16_DATA_PACKET).unwrap();
+    let count = packet.points().unwrap().len() as u64;
+    let mut group = c.benchmark_group("reconstruct");
+    group.throughput(Throughput::Elements(count));
+    group.bench_function("points", |b| b.iter(|| black_box(&packet).points()));
+    group.bench_function("decoder", |b| {
+        let mut decoder = Decoder::new();
+        b.iter(|| decoder.push(black_box(packet.clone())))
+    });
+    group.finish();
+}
+
+fn assemble(c: &mut Criterion) {
+    let points = Packet::new(&VLP_16_DATA_PACKET).unwrap().points().unwrap();
+    let mut group = c.benchmark_group("assemble");
+    group.throughput(Throughput::Elements(points.len() as u64));
+    group.bench_function("frames", |b| {
+        let mut assembler = Assembler::new();
+        b.iter(|| {
+                   for point in &points {
+                       black_box(assembler.push(*point));
+                   }
+               })
+    });
+    group.finish();
+}
+
+criterion_group!(benches, decode, reconstruct, assemble);
+criterion_main!(benches);
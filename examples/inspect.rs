@@ -0,0 +1,55 @@
+//! Summarizes a capture held in memory.
+//!
+//! `summarize` only needs the bytes of a pcap file, so it works anywhere the crate builds without
+//! the `pcap` feature, including in the browser. To build a web inspector, compile a crate that
+//! depends on `velodyne` with `default-features = false, features = ["std"]` for
+//! `wasm32-unknown-unknown`, export `summarize` with `wasm-bindgen`, and pass it the contents of
+//! an uploaded file:
+//!
+//! ```text
+//! const bytes = new Uint8Array(await input.files[0].arrayBuffer());
+//! output.textContent = summarize(bytes);
+//! ```
+//!
+//! This example reads the bytes from a file instead:
+//!
+//! ```text
+//! cargo run --example inspect data/single.pcap
+//! ```
+
+extern crate velodyne;
+
+use std::env;
+use std::fs::File;
+use std::io::{Cursor, Read};
+use velodyne::Result;
+use velodyne::info::Info;
+use velodyne::io::PcapStream;
+
+fn summarize(bytes: &[u8]) -> Result<String> {
+    let info = Info::from_read(PcapStream::new(Cursor::new(bytes))?, true)?;
+    let mut summary = format!("Data packets: {}\nPosition packets: {}\nPoints: {}\n",
+                              info.data_packets,
+                              info.position_packets,
+                              info.points);
+    if let Some(extent) = info.extent {
+        if let Some(bounds) = extent.bounds {
+            summary += &format!("X: {} to {}\nY: {} to {}\nZ: {} to {}\n",
+                                bounds.min_x,
+                                bounds.max_x,
+                                bounds.min_y,
+                                bounds.max_y,
+                                bounds.min_z,
+                                bounds.max_z);
+        }
+        summary += &format!("Azimuth coverage: {}/360 degrees\n", extent.azimuth_coverage());
+    }
+    Ok(summary)
+}
+
+fn main() {
+    let path = env::args().nth(1).expect("usage: inspect <infile>");
+    let mut bytes = Vec::new();
+    File::open(path).and_then(|mut file| file.read_to_end(&mut bytes)).unwrap();
+    print!("{}", summarize(&bytes).unwrap());
+}
@@ -0,0 +1,17 @@
+//! Position packets echo whatever the GNSS receiver sends, so parsing any text must not panic.
+
+#![no_main]
+#[macro_use]
+extern crate libfuzzer_sys;
+extern crate velodyne;
+
+use velodyne::nmea::{Fix, Position, Sentence, Validation};
+
+fuzz_target!(|data: &[u8]| {
+    let text = String::from_utf8_lossy(data);
+    for &validation in &[Validation::Strict, Validation::Lenient] {
+        let _ = Sentence::parse(&text, validation);
+        let _ = Position::parse(&text, validation);
+        let _ = Fix::parse(&text, validation);
+    }
+});
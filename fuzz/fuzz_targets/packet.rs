@@ -0,0 +1,19 @@
+//! Packets come straight off of the network, so parsing any bytes must not panic.
+
+#![no_main]
+#[macro_use]
+extern crate libfuzzer_sys;
+extern crate velodyne;
+
+use velodyne::vlp_16::{self, Packet};
+
+fuzz_target!(|data: &[u8]| {
+    let _ = vlp_16::classify(data);
+    if let Ok(packet) = Packet::new(data) {
+        let _ = packet.points();
+        let _ = packet.firings();
+        let _ = packet.position();
+        let _ = packet.sentences();
+        let _ = packet.position_status();
+    }
+});
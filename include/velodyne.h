@@ -0,0 +1,79 @@
+/* C interface to the velodyne decoder.
+ *
+ * Build the shared library with:
+ *
+ *     cargo rustc --release --features ffi --lib --crate-type cdylib
+ *
+ * Every pointer returned here is owned by the caller and must be released with the matching
+ * `_free` function, except for error messages, which belong to their source. Keep this file in
+ * sync with `src/ffi.rs`; the `ffi` feature's tests check that it declares every exported
+ * function.
+ */
+
+#ifndef VELODYNE_H
+#define VELODYNE_H
+
+#include <stdbool.h>
+#include <stddef.h>
+#include <stdint.h>
+
+#ifdef __cplusplus
+extern "C" {
+#endif
+
+/* An open source of packets. */
+typedef struct VelodyneSource VelodyneSource;
+
+/* A single decoded packet. */
+typedef struct VelodynePacket VelodynePacket;
+
+/* A point. */
+typedef struct VelodynePoint {
+    /* The coordinates, in meters. */
+    float x;
+    float y;
+    float z;
+    /* The azimuth, in degrees. */
+    float azimuth;
+    /* The time of the point, in microseconds since the top of the hour. */
+    int64_t time;
+    /* The calibrated reflectivity. */
+    uint8_t reflectivity;
+    /* The laser channel. */
+    uint8_t channel;
+} VelodynePoint;
+
+/* Opens a pcap file, returning NULL on error. */
+VelodyneSource *velodyne_pcap_open(const char *path);
+
+/* Reads the next packet from a source, returning NULL at the end of the source or on error.
+ *
+ * After a NULL, `velodyne_source_last_error` tells an error from the end of the source. */
+VelodynePacket *velodyne_source_next_packet(VelodyneSource *source);
+
+/* Returns the error from the last call to `velodyne_source_next_packet`, or NULL if it succeeded
+ * or reached the end of the source.
+ *
+ * The message belongs to the source, and is valid until the next call with the source. */
+const char *velodyne_source_last_error(const VelodyneSource *source);
+
+/* Returns true if the packet is a data packet. */
+bool velodyne_packet_is_data(const VelodynePacket *packet);
+
+/* Decodes a packet's points into a buffer, returning the number of points in the packet.
+ *
+ * At most `capacity` points are written. Position packets have no points. */
+size_t velodyne_packet_points(const VelodynePacket *packet, VelodynePoint *points,
+                              size_t capacity);
+
+/* Frees a packet. */
+void velodyne_packet_free(VelodynePacket *packet);
+
+/* Frees a source. */
+void velodyne_source_free(VelodyneSource *source);
+
+#ifdef __cplusplus
+}
+#endif
+
+#endif /* VELODYNE_H */
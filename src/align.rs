@@ -0,0 +1,329 @@
+//! Pair data packets with the position packets that precede them.
+//!
+//! Georeferencing and logging both need to know where the sensor was when each data packet was
+//! sent, which means joining every data packet with the latest position packet. An `Aligner`
+//! does that join over any stream of packets:
+//!
+//! ```
+//! use velodyne::align::Aligner;
+//! use velodyne::io::{Pcap, Read};
+//! let pcap = Pcap::open("data/single.pcap").unwrap();
+//! let mut aligner = Aligner::new(pcap.vlp_16_packets());
+//! while let Some(result) = aligner.next_pair() {
+//!     let (data_packet, fix) = result.unwrap();
+//!     if let Some(fix) = fix {
+//!         println!("{} µs since the last fix", fix.age.num_microseconds().unwrap());
+//!     }
+//! }
+//! ```
+//!
+//! A fix is only as good as the receiver's solution, and georeferencing points with an invalid or
+//! dead-reckoned fix smears them across the map. Every fix has a `FixQuality`, from the $GPRMC
+//! validity flag and the $GPGGA fix quality if the packet has one, so callers can down-weight
+//! points by it, or the aligner can skip data packets whose fix isn't valid with
+//! `Aligner::skip_invalid`. Either way, the aligner reports the spans of data packets without a
+//! valid fix, so the bad parts of a trajectory can be listed with the map.
+
+use Result;
+use chrono::Duration;
+use nmea::{self, Fix, Position};
+use time::{HOUR_US, HourOffset};
+use vlp_16::{DataPacket, Packet, PositionPacket};
+
+/// The latest position packet, as seen from a data packet.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct PositionFix {
+    /// The position packet.
+    pub packet: PositionPacket,
+    /// The packet's $GPRMC position, or `None` if it doesn't have a valid sentence.
+    pub position: Option<Position>,
+    /// The packet's $GPGGA fix, or `None` if it doesn't have a valid sentence.
+    pub gga: Option<Fix>,
+    /// The time from the position packet to the data packet, by their timestamps.
+    ///
+    /// Timestamps roll over every hour, so this is always less than an hour.
+    pub age: Duration,
+}
+
+/// How good a position fix is.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum FixQuality {
+    /// The receiver has a solution.
+    Valid,
+    /// The receiver is dead reckoning, i.e. estimating its position without satellites.
+    DeadReckoning,
+    /// The receiver has no solution, or the packet has no readable $GPRMC sentence.
+    Invalid,
+}
+
+/// A run of consecutive data packets whose fixes weren't valid.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct InvalidSpan {
+    /// The timestamp of the first data packet.
+    pub start: HourOffset,
+    /// The timestamp of the last data packet.
+    pub end: HourOffset,
+    /// The number of data packets.
+    pub data_packets: usize,
+    /// The quality of their fixes.
+    pub quality: FixQuality,
+}
+
+/// Pairs each data packet with the most recent position packet before it.
+#[derive(Clone, Debug)]
+pub struct Aligner<I> {
+    packets: I,
+    fix: Option<PositionFix>,
+    skip_invalid: bool,
+    invalid_spans: Vec<InvalidSpan>,
+    in_span: bool,
+}
+
+impl PositionFix {
+    /// Returns the quality of this fix.
+    ///
+    /// A $GPRMC sentence that isn't marked valid makes the fix invalid. If there's a $GPGGA
+    /// sentence too, its fix quality of zero is invalid, six is dead reckoning, and seven and
+    /// eight, manual input and simulation, are invalid too.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use velodyne::align::{Aligner, FixQuality};
+    /// use velodyne::fixtures::{VLP_16_DATA_PACKET, VLP_16_POSITION_PACKET};
+    /// use velodyne::vlp_16::Packet;
+    /// let packets = vec![Packet::new(&VLP_16_POSITION_PACKET), Packet::new(&VLP_16_DATA_PACKET)];
+    /// let mut aligner = Aligner::new(packets.into_iter());
+    /// let (_, fix) = aligner.next_pair().unwrap().unwrap();
+    /// assert_eq!(FixQuality::Valid, fix.unwrap().quality());
+    /// ```
+    pub fn quality(&self) -> FixQuality {
+        if !self.position.is_some_and(|position| position.valid) {
+            return FixQuality::Invalid;
+        }
+        match self.gga.map(|gga| gga.quality) {
+            Some(0) | Some(7) | Some(8) => FixQuality::Invalid,
+            Some(6) => FixQuality::DeadReckoning,
+            _ => FixQuality::Valid,
+        }
+    }
+}
+
+impl<I: Iterator<Item = Result<Packet>>> Aligner<I> {
+    /// Creates a new aligner over packets, e.g. a `vlp_16::Packets` iterator.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use velodyne::align::Aligner;
+    /// use velodyne::fixtures::VLP_16_DATA_PACKET;
+    /// use velodyne::vlp_16::Packet;
+    /// let aligner = Aligner::new(vec![Packet::new(&VLP_16_DATA_PACKET)].into_iter());
+    /// ```
+    pub fn new(packets: I) -> Aligner<I> {
+        Aligner {
+            packets,
+            fix: None,
+            skip_invalid: false,
+            invalid_spans: Vec::new(),
+            in_span: false,
+        }
+    }
+
+    /// Sets whether to skip data packets whose latest fix isn't valid, instead of returning them.
+    ///
+    /// Data packets before the first position packet are still returned, without a fix.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use velodyne::align::Aligner;
+    /// use velodyne::fixtures::VLP_16_DATA_PACKET;
+    /// use velodyne::vlp_16::Packet;
+    /// let mut aligner = Aligner::new(vec![Packet::new(&VLP_16_DATA_PACKET)].into_iter());
+    /// aligner.skip_invalid(true);
+    /// ```
+    pub fn skip_invalid(&mut self, skip_invalid: bool) -> &mut Aligner<I> {
+        self.skip_invalid = skip_invalid;
+        self
+    }
+
+    /// Returns the next data packet and the latest position fix before it, if there is one.
+    ///
+    /// Position packets are consumed as they're passed. Errors from the packets are returned
+    /// as they come, and don't change the fix. Data packets whose fix isn't valid are added to
+    /// the invalid spans, and skipped if the aligner skips invalid fixes.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use velodyne::align::Aligner;
+    /// use velodyne::fixtures::{VLP_16_DATA_PACKET, VLP_16_POSITION_PACKET};
+    /// use velodyne::vlp_16::Packet;
+    /// let packets = vec![Packet::new(&VLP_16_DATA_PACKET),
+    ///                    Packet::new(&VLP_16_POSITION_PACKET),
+    ///                    Packet::new(&VLP_16_DATA_PACKET)];
+    /// let mut aligner = Aligner::new(packets.into_iter());
+    /// assert!(aligner.next_pair().unwrap().unwrap().1.is_none());
+    /// assert!(aligner.next_pair().unwrap().unwrap().1.is_some());
+    /// assert!(aligner.next_pair().is_none());
+    /// ```
+    pub fn next_pair(&mut self) -> Option<Result<(DataPacket, Option<&PositionFix>)>> {
+        loop {
+            let packet = match self.packets.next()? {
+                Ok(packet) => packet,
+                Err(err) => return Some(Err(err)),
+            };
+            let position = packet.position().and_then(|result| result.ok());
+            match packet {
+                Packet::Data(data_packet) => {
+                    let quality = self.fix.as_mut().map(|fix| {
+                        fix.age = age(fix.packet.timestamp, data_packet.timestamp);
+                        fix.quality()
+                    });
+                    match quality {
+                        Some(FixQuality::Valid) | None => self.in_span = false,
+                        Some(quality) => {
+                            self.add_invalid(data_packet.timestamp, quality);
+                            if self.skip_invalid {
+                                continue;
+                            }
+                        }
+                    }
+                    return Some(Ok((data_packet, self.fix.as_ref())));
+                }
+                Packet::Position(packet) => {
+                    let gga = nmea::split(&packet.nmea)
+                        .find(|sentence| sentence.get(3..6) == Some("GGA"))
+                        .and_then(|sentence| Fix::new(sentence).ok());
+                    self.fix = Some(PositionFix {
+                        packet,
+                        position,
+                        gga,
+                        age: Duration::zero(),
+                    })
+                }
+            }
+        }
+    }
+
+    /// Returns the latest position fix, if one has been seen.
+    ///
+    /// Its age is as of the last data packet that was returned.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use velodyne::align::Aligner;
+    /// use velodyne::fixtures::VLP_16_POSITION_PACKET;
+    /// use velodyne::vlp_16::Packet;
+    /// let mut aligner = Aligner::new(vec![Packet::new(&VLP_16_POSITION_PACKET)].into_iter());
+    /// assert!(aligner.next_pair().is_none());
+    /// assert!(aligner.fix().unwrap().position.is_some());
+    /// ```
+    pub fn fix(&self) -> Option<&PositionFix> {
+        self.fix.as_ref()
+    }
+
+    /// Returns the runs of data packets, so far, whose fixes weren't valid.
+    ///
+    /// A run ends at a data packet with a valid fix, or whose fix has a different quality.
+    pub fn invalid_spans(&self) -> &[InvalidSpan] {
+        &self.invalid_spans
+    }
+
+    fn add_invalid(&mut self, timestamp: HourOffset, quality: FixQuality) {
+        if let Some(span) = self.invalid_spans.last_mut() {
+            if self.in_span && span.quality == quality {
+                span.end = timestamp;
+                span.data_packets += 1;
+                return;
+            }
+        }
+        self.invalid_spans.push(InvalidSpan {
+                                    start: timestamp,
+                                    end: timestamp,
+                                    data_packets: 1,
+                                    quality,
+                                });
+        self.in_span = true;
+    }
+}
+
+fn age(position: HourOffset, data: HourOffset) -> Duration {
+    let microseconds = i64::from(data.micros()) - i64::from(position.micros());
+    Duration::microseconds(microseconds.rem_euclid(HOUR_US))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use fixtures::{VLP_16_DATA_PACKET, VLP_16_POSITION_PACKET};
+
+    #[test]
+    fn age_wraps() {
+        let mut position = Packet::new(&VLP_16_POSITION_PACKET).unwrap().into_position().unwrap();
+        let data = Packet::new(&VLP_16_DATA_PACKET).unwrap().into_data().unwrap();
+        let fix_age = |position| {
+            let packets = vec![Ok(Packet::Position(position)), Ok(Packet::Data(data.clone()))];
+            let mut aligner = Aligner::new(packets.into_iter());
+            let age = aligner.next_pair().unwrap().unwrap().1.unwrap().age;
+            age
+        };
+        position.timestamp = HourOffset::from_micros(data.timestamp.micros() - 1_000);
+        assert_eq!(Duration::microseconds(1_000), fix_age(position));
+        position.timestamp = HourOffset::from_micros(data.timestamp.micros() + 10);
+        assert_eq!(Duration::microseconds(HOUR_US - 10), fix_age(position));
+    }
+
+    #[test]
+    fn invalid_spans() {
+        let data = Packet::new(&VLP_16_DATA_PACKET).unwrap().into_data().unwrap();
+        let template = Packet::new(&VLP_16_POSITION_PACKET).unwrap().into_position().unwrap();
+        let position = |nmea: &str| {
+            let mut packet = template;
+            packet.nmea = nmea::Buffer::from_bytes(nmea.as_bytes());
+            Packet::Position(packet)
+        };
+        let data_at = |micros| {
+            let mut data = data.clone();
+            data.timestamp = HourOffset::from_micros(micros);
+            Packet::Data(data)
+        };
+        let valid = "$GPRMC,214106,A,3707.8178,N,12139.2690,W,010.3,188.2,230715,013.8,E,D*05";
+        let invalid = "$GPRMC,214106,V,3707.8178,N,12139.2690,W,010.3,188.2,230715,013.8,E,D*12";
+        let dead_reckoning = format!("{}\r\n{}",
+                                     valid,
+                                     "$GPGGA,214106.00,3707.8178,N,12139.2690,W,6,09,0.9,12.3,M,\
+                                      -25.6,M,1.0,0000*4A");
+        let packets = vec![position(invalid),
+                           data_at(1),
+                           data_at(2),
+                           position(&dead_reckoning),
+                           data_at(3),
+                           position(valid),
+                           data_at(4)];
+        let mut aligner = Aligner::new(packets.clone().into_iter().map(Ok));
+        let mut qualities = Vec::new();
+        while let Some(result) = aligner.next_pair() {
+            qualities.push(result.unwrap().1.unwrap().quality());
+        }
+        assert_eq!(vec![FixQuality::Invalid,
+                        FixQuality::Invalid,
+                        FixQuality::DeadReckoning,
+                        FixQuality::Valid],
+                   qualities);
+        let spans = aligner.invalid_spans();
+        assert_eq!(2, spans.len());
+        assert_eq!((HourOffset::from_micros(1), HourOffset::from_micros(2), 2),
+                   (spans[0].start, spans[0].end, spans[0].data_packets));
+        assert_eq!(FixQuality::DeadReckoning, spans[1].quality);
+
+        let mut aligner = Aligner::new(packets.into_iter().map(Ok));
+        aligner.skip_invalid(true);
+        let (data_packet, _) = aligner.next_pair().unwrap().unwrap();
+        assert_eq!(HourOffset::from_micros(4), data_packet.timestamp);
+        assert!(aligner.next_pair().is_none());
+        assert_eq!(2, aligner.invalid_spans().len());
+    }
+}
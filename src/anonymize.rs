@@ -0,0 +1,279 @@
+//! Strip the location out of captures, so they can be shared.
+//!
+//! Position packets echo the NMEA sentences of a GNSS receiver, which give away where a capture
+//! was recorded. An `Anonymizer` blanks those sentences, drops position packets altogether, or
+//! shifts every position by a fixed offset, which keeps the shape of the trajectory. Packet and
+//! capture timestamps are never changed, so an anonymized capture decodes just like the original:
+//!
+//! ```
+//! use velodyne::anonymize::Anonymizer;
+//! use velodyne::io::{Pcap, PcapWriter};
+//! let pcap = Pcap::open("data/single.pcap").unwrap();
+//! let mut writer = PcapWriter::new(Vec::new()).unwrap();
+//! assert_eq!(1, Anonymizer::new().copy(pcap, &mut writer).unwrap());
+//! ```
+
+use Result;
+use chrono::{TimeZone, UTC};
+use io::{PcapWriter, Read};
+use nmea;
+use std::io;
+use vlp_16::{self, PacketKind};
+use wire;
+
+/// What happens to position packets.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub enum Gnss {
+    /// Keep position packets, with their timestamps and status, but zero their NMEA sentences.
+    #[default]
+    Blank,
+    /// Drop position packets.
+    Drop,
+    /// Shift the positions of $GPRMC and $GPGGA sentences, and blank any other sentences.
+    ///
+    /// Latitudes are clamped to the poles and longitudes wrap around, so small offsets keep
+    /// distances and headings close to the original's.
+    Offset {
+        /// The degrees added to every latitude.
+        latitude: f64,
+        /// The degrees added to every longitude.
+        longitude: f64,
+    },
+}
+
+/// Rewrites packets so that they don't disclose where they were recorded.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct Anonymizer {
+    gnss: Gnss,
+}
+
+impl Anonymizer {
+    /// Creates an anonymizer that blanks the NMEA sentences of position packets.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use velodyne::anonymize::Anonymizer;
+    /// let anonymizer = Anonymizer::new();
+    /// ```
+    pub fn new() -> Anonymizer {
+        Anonymizer::default()
+    }
+
+    /// Sets what happens to position packets.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use velodyne::anonymize::{Anonymizer, Gnss};
+    /// let mut anonymizer = Anonymizer::new();
+    /// anonymizer.gnss(Gnss::Offset { latitude: 1.5, longitude: -20. });
+    /// ```
+    pub fn gnss(&mut self, gnss: Gnss) -> &mut Anonymizer {
+        self.gnss = gnss;
+        self
+    }
+
+    /// Anonymizes a packet in place, returning false if it should be dropped.
+    ///
+    /// Data packets are never changed. Packets that aren't Velodyne packets are dropped, since
+    /// they could hold anything.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use velodyne::anonymize::Anonymizer;
+    /// use velodyne::fixtures::VLP_16_POSITION_PACKET;
+    /// use velodyne::vlp_16::Packet;
+    /// let mut bytes = VLP_16_POSITION_PACKET;
+    /// assert!(Anonymizer::new().anonymize(&mut bytes));
+    /// assert_eq!(Some(""), Packet::new(&bytes).unwrap().nmea());
+    /// ```
+    pub fn anonymize(&self, bytes: &mut [u8]) -> bool {
+        match vlp_16::classify(bytes) {
+            Some(PacketKind::Data) => return true,
+            Some(PacketKind::Position) => {}
+            None => return false,
+        }
+        let header = bytes.len() - vlp_16::payload(bytes).len();
+        let nmea = &mut bytes[header + wire::NMEA.offset..header + wire::NMEA.end()];
+        match self.gnss {
+            Gnss::Blank => blank(nmea),
+            Gnss::Drop => return false,
+            Gnss::Offset { latitude, longitude } => offset(nmea, latitude, longitude),
+        }
+        true
+    }
+
+    /// Copies packets from a source to a pcap writer, anonymizing them, and returns the number of
+    /// packets written.
+    ///
+    /// Packets keep their capture times, or get the Unix epoch if the source doesn't know them.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use velodyne::anonymize::{Anonymizer, Gnss};
+    /// use velodyne::io::{Pcap, PcapWriter};
+    /// let pcap = Pcap::open("data/single.pcap").unwrap();
+    /// let mut writer = PcapWriter::new(Vec::new()).unwrap();
+    /// Anonymizer::new().gnss(Gnss::Drop).copy(pcap, &mut writer).unwrap();
+    /// ```
+    pub fn copy<R, W>(&self, mut read: R, writer: &mut PcapWriter<W>) -> Result<usize>
+        where R: Read,
+              W: io::Write
+    {
+        let mut bytes = Vec::new();
+        let mut written = 0;
+        while let Some(result) = read.read() {
+            bytes.clear();
+            bytes.extend_from_slice(result?);
+            if self.anonymize(&mut bytes) {
+                let time = read.timestamp().unwrap_or_else(|| UTC.timestamp(0, 0));
+                writer.write(&bytes, time)?;
+                written += 1;
+            }
+        }
+        writer.flush()?;
+        Ok(written)
+    }
+}
+
+fn blank(nmea: &mut [u8]) {
+    for n in nmea {
+        *n = 0;
+    }
+}
+
+/// Shifts the positions of a NMEA buffer's sentences, dropping the sentences it can't shift.
+fn offset(nmea: &mut [u8], latitude: f64, longitude: f64) {
+    let text = String::from_utf8_lossy(nmea).into_owned();
+    blank(nmea);
+    let mut len = 0;
+    for sentence in nmea::split(&text).filter_map(|s| offset_sentence(s, latitude, longitude)) {
+        let sentence = sentence + "\r\n";
+        if len + sentence.len() > nmea.len() {
+            break;
+        }
+        nmea[len..len + sentence.len()].copy_from_slice(sentence.as_bytes());
+        len += sentence.len();
+    }
+}
+
+fn offset_sentence(sentence: &str, latitude: f64, longitude: f64) -> Option<String> {
+    let (data, has_checksum) = match sentence.rfind('*') {
+        Some(index) => (&sentence[..index], true),
+        None => (sentence, false),
+    };
+    let mut fields = data.split(',').map(String::from).collect::<Vec<_>>();
+    let index = if nmea::is_type(&fields[0], "RMC") {
+        3
+    } else if nmea::is_type(&fields[0], "GGA") {
+        2
+    } else {
+        return None;
+    };
+    if fields.len() < index + 4 {
+        return None;
+    }
+    shift(&mut fields[index..index + 2], latitude, true)?;
+    shift(&mut fields[index + 2..index + 4], longitude, false)?;
+    let mut sentence = fields.join(",");
+    if has_checksum {
+        let checksum = nmea::checksum(&sentence[1..]);
+        sentence += &format!("*{:02X}", checksum);
+    }
+    Some(sentence)
+}
+
+/// Shifts a coordinate and its hemisphere, keeping the coordinate's precision.
+fn shift(fields: &mut [String], offset: f64, latitude: bool) -> Option<()> {
+    if fields[0].is_empty() {
+        return Some(());
+    }
+    let decimals = fields[0].find('.').map_or(0, |index| fields[0].len() - index - 1);
+    let mut degrees = nmea::to_decimal_degrees(fields[0].parse().ok()?);
+    if fields[1] == "S" || fields[1] == "W" {
+        degrees = -degrees;
+    }
+    let (width, positive, negative) = if latitude {
+        degrees = (degrees + offset).clamp(-90., 90.);
+        (2, "N", "S")
+    } else {
+        degrees = (degrees + offset + 180.).rem_euclid(360.) - 180.;
+        (3, "E", "W")
+    };
+    let scale = 10u64.pow(decimals as u32);
+    let minutes = (degrees.abs() * 60. * scale as f64).round() as u64;
+    let whole = minutes / scale;
+    fields[0] = format!("{:0width$}{:02}", whole / 60, whole % 60, width = width);
+    if decimals > 0 {
+        fields[0] += &format!(".{:0decimals$}", minutes % scale, decimals = decimals);
+    }
+    fields[1] = if degrees < 0. { negative } else { positive }.to_string();
+    Some(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use fixtures::{VLP_16_DATA_PACKET, VLP_16_POSITION_PACKET};
+    use io::PcapSlice;
+    use nmea::Position;
+    use vlp_16::Packet;
+
+    #[test]
+    fn blank() {
+        let mut bytes = VLP_16_POSITION_PACKET;
+        assert!(Anonymizer::new().anonymize(&mut bytes));
+        let original = Packet::new(&VLP_16_POSITION_PACKET).unwrap().into_position().unwrap();
+        let position = Packet::new(&bytes).unwrap().into_position().unwrap();
+        assert_eq!(original.timestamp, position.timestamp);
+        assert_eq!(original.status, position.status);
+        assert_eq!("", position.nmea.as_str());
+        assert!(bytes[wire::PACKET_HEADER_LEN + wire::NMEA.offset..].iter().all(|&n| n == 0));
+
+        let mut bytes = VLP_16_DATA_PACKET;
+        assert!(Anonymizer::new().anonymize(&mut bytes));
+        assert_eq!(&VLP_16_DATA_PACKET[..], &bytes[..]);
+        assert!(!Anonymizer::new().anonymize(&mut [0; 100]));
+    }
+
+    #[test]
+    fn offset() {
+        let mut bytes = VLP_16_POSITION_PACKET;
+        let gnss = Gnss::Offset {
+            latitude: -40.,
+            longitude: 300.,
+        };
+        assert!(Anonymizer::new().gnss(gnss).anonymize(&mut bytes));
+        let original = Packet::new(&VLP_16_POSITION_PACKET).unwrap().position().unwrap().unwrap();
+        let packet = Packet::new(&bytes).unwrap();
+        let nmea = packet.nmea().unwrap();
+        assert_eq!("$GPRMC,214106,A,0252.1822,S,17820.7310,E,010.3,188.2,230715,013.8,E,D*0F",
+                   nmea);
+        let position = Position::new(nmea).unwrap();
+        assert_eq!(original.datetime, position.datetime);
+        assert!((original.latitude - 40. - position.latitude).abs() < 1e-5);
+        assert!((original.longitude + 300. - position.longitude).abs() < 1e-5);
+    }
+
+    #[test]
+    fn copy() {
+        let mut writer = PcapWriter::new(Vec::new()).unwrap();
+        for bytes in &[&VLP_16_DATA_PACKET[..], &VLP_16_POSITION_PACKET[..], &[0; 100][..]] {
+            writer.write(bytes, UTC.timestamp(1_437_687_666, 0)).unwrap();
+        }
+        let pcap = writer.into_inner();
+        let mut writer = PcapWriter::new(Vec::new()).unwrap();
+        let mut anonymizer = Anonymizer::new();
+        assert_eq!(2, anonymizer.copy(PcapSlice::new(&pcap).unwrap(), &mut writer).unwrap());
+        let mut anonymized = PcapSlice::new(writer.into_inner()).unwrap();
+        assert_eq!(&VLP_16_DATA_PACKET[..], anonymized.read().unwrap().unwrap());
+        assert_eq!(Some(UTC.timestamp(1_437_687_666, 0)), anonymized.timestamp());
+
+        let mut writer = PcapWriter::new(Vec::new()).unwrap();
+        anonymizer.gnss(Gnss::Drop);
+        assert_eq!(1, anonymizer.copy(PcapSlice::new(&pcap).unwrap(), &mut writer).unwrap());
+    }
+}
@@ -0,0 +1,192 @@
+//! Export points as Arrow record batches and Parquet files.
+//!
+//! Every point becomes one row with the columns `x`, `y`, `z`, `intensity`, `ring`, `time`, and
+//! `return`. The resulting files can be queried directly by tools like DuckDB or Spark.
+
+use Result;
+use arrow_array::{ArrayRef, Float32Array, Int64Array, RecordBatch, StringArray, UInt8Array};
+use arrow_schema::{DataType, Field, Schema, SchemaRef};
+use parquet::arrow::ArrowWriter;
+use parquet::basic::Compression;
+use parquet::errors::ParquetError;
+use parquet::file::properties::WriterProperties;
+use point::{Point, ReturnType, Time};
+use sink::PointSink;
+use std::io::Write;
+use std::sync::Arc;
+
+/// Writes points to a Parquet file.
+///
+/// Each call to `write` becomes one row group, so write a frame (or more) at a time rather than a
+/// packet at a time.
+#[allow(missing_debug_implementations)]
+pub struct ParquetWriter<W: Write + Send> {
+    writer: Option<ArrowWriter<W>>,
+    // Once the footer is written, only the underlying writer is left.
+    write: Option<W>,
+}
+
+/// Returns the schema of the record batches produced by this module.
+///
+/// `time` is in microseconds: past the hour for offset times, or since the Unix epoch for absolute
+/// times.
+///
+/// # Examples
+///
+/// ```
+/// let schema = velodyne::arrow::schema();
+/// assert_eq!(7, schema.fields().len());
+/// ```
+pub fn schema() -> SchemaRef {
+    Arc::new(Schema::new(vec![Field::new("x", DataType::Float32, false),
+                              Field::new("y", DataType::Float32, false),
+                              Field::new("z", DataType::Float32, false),
+                              Field::new("intensity", DataType::UInt8, false),
+                              Field::new("ring", DataType::UInt8, false),
+                              Field::new("time", DataType::Int64, false),
+                              Field::new("return", DataType::Utf8, false)]))
+}
+
+/// Converts points into a record batch.
+///
+/// # Examples
+///
+/// ```
+/// use velodyne::fixtures::VLP_16_DATA_PACKET;
+/// use velodyne::vlp_16::Packet;
+/// let points = Packet::new(&VLP_16_DATA_PACKET).unwrap().points().unwrap();
+/// let batch = velodyne::arrow::record_batch(&points).unwrap();
+/// assert_eq!(384, batch.num_rows());
+/// ```
+pub fn record_batch(points: &[Point]) -> Result<RecordBatch> {
+    let columns: Vec<ArrayRef> =
+        vec![Arc::new(points.iter().map(|p| p.x).collect::<Float32Array>()),
+             Arc::new(points.iter().map(|p| p.y).collect::<Float32Array>()),
+             Arc::new(points.iter().map(|p| p.z).collect::<Float32Array>()),
+             Arc::new(points.iter().map(|p| p.reflectivity).collect::<UInt8Array>()),
+             Arc::new(points.iter().map(|p| p.channel).collect::<UInt8Array>()),
+             Arc::new(points.iter().map(|p| microseconds(p.time)).collect::<Int64Array>()),
+             Arc::new(points
+                          .iter()
+                          .map(|p| Some(return_name(p.return_type)))
+                          .collect::<StringArray>())];
+    RecordBatch::try_new(schema(), columns).map_err(Into::into)
+}
+
+impl<W: Write + Send> ParquetWriter<W> {
+    /// Creates a new writer, compressing columns with Snappy.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use velodyne::arrow::ParquetWriter;
+    /// let writer = ParquetWriter::new(Vec::new()).unwrap();
+    /// ```
+    pub fn new(write: W) -> Result<ParquetWriter<W>> {
+        let properties = WriterProperties::builder()
+            .set_compression(Compression::SNAPPY)
+            .build();
+        Ok(ParquetWriter {
+               writer: Some(ArrowWriter::try_new(write, schema(), Some(properties))?),
+               write: None,
+           })
+    }
+
+    /// Writes points as a row group.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use velodyne::arrow::ParquetWriter;
+    /// use velodyne::fixtures::VLP_16_DATA_PACKET;
+    /// use velodyne::vlp_16::Packet;
+    /// let mut writer = ParquetWriter::new(Vec::new()).unwrap();
+    /// writer.write(&Packet::new(&VLP_16_DATA_PACKET).unwrap().points().unwrap()).unwrap();
+    /// ```
+    pub fn write(&mut self, points: &[Point]) -> Result<()> {
+        let writer = match self.writer {
+            Some(ref mut writer) => writer,
+            None => return Err(ParquetError::General("the file is closed".to_string()).into()),
+        };
+        writer.write(&record_batch(points)?)?;
+        writer.flush().map_err(Into::into)
+    }
+
+    /// Writes the file footer and returns the underlying writer.
+    ///
+    /// A Parquet file isn't readable until it is closed.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use velodyne::arrow::ParquetWriter;
+    /// let bytes = ParquetWriter::new(Vec::new()).unwrap().close().unwrap();
+    /// assert_eq!(b"PAR1", &bytes[bytes.len() - 4..]);
+    /// ```
+    pub fn close(mut self) -> Result<W> {
+        self.finish()?;
+        Ok(self.write.take().expect("a finished writer keeps its underlying writer"))
+    }
+}
+
+impl<W: Write + Send> PointSink for ParquetWriter<W> {
+    fn write_points(&mut self, points: &[Point]) -> Result<()> {
+        self.write(points)
+    }
+
+    /// Writes the file footer, after which points can't be written.
+    fn finish(&mut self) -> Result<()> {
+        if let Some(writer) = self.writer.take() {
+            self.write = Some(writer.into_inner()?);
+        }
+        Ok(())
+    }
+}
+
+fn microseconds(time: Time) -> i64 {
+    match time {
+        Time::Offset(offset) => offset.num_microseconds().unwrap_or(0),
+        Time::Absolute(time) => {
+            time.timestamp() * 1_000_000 + i64::from(time.timestamp_subsec_micros())
+        }
+    }
+}
+
+fn return_name(return_type: ReturnType) -> &'static str {
+    match return_type {
+        ReturnType::Strongest => "strongest",
+        ReturnType::Last => "last",
+        ReturnType::Secondary => "secondary",
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use bytes::Bytes;
+    use fixtures::VLP_16_DATA_PACKET;
+    use parquet::arrow::arrow_reader::ParquetRecordBatchReaderBuilder;
+    use vlp_16::Packet;
+
+    #[test]
+    fn round_trip() {
+        let points = Packet::new(&VLP_16_DATA_PACKET).unwrap().points().unwrap();
+        let mut writer = ParquetWriter::new(Vec::new()).unwrap();
+        writer.write(&points).unwrap();
+        writer.write_points(&points).unwrap();
+        writer.finish().unwrap();
+        assert!(writer.write(&points).is_err());
+        let bytes = writer.close().unwrap();
+        let reader = ParquetRecordBatchReaderBuilder::try_new(Bytes::from(bytes))
+            .unwrap()
+            .with_batch_size(points.len())
+            .build()
+            .unwrap();
+        let batches = reader.collect::<::std::result::Result<Vec<_>, _>>().unwrap();
+        assert_eq!(2, batches.len());
+        let batch = &batches[1];
+        assert_eq!(record_batch(&points).unwrap().columns(), batch.columns());
+        let time = batch.column(5).as_any().downcast_ref::<Int64Array>().unwrap();
+        assert_eq!(2_467_108_343, time.value(0));
+    }
+}
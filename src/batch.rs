@@ -0,0 +1,760 @@
+//! Decode many packets with one call.
+//!
+//! These functions reuse one decoder and one output buffer for all of their packets, so they're
+//! cheaper than decoding packets one by one. They're also convenient in tests.
+//!
+//! `read_pcap_points` and `read_pcap_frames` decode a whole capture file in one call, for scripts
+//! that just want the data. They hold everything in memory, so use a reader and a `Decoder` for
+//! long captures, or `spill_pcap_points`, which only keeps so many points in memory and spills
+//! the rest to disk. `process_chunks` sits in between: it hands a capture to a closure in chunks
+//! of bounded size, so whole-chunk operations like sorting run in constant memory.
+
+use Result;
+use cloud::PointCloud;
+use frame::{Assembler, Frame};
+use io::{PcapStream, Read};
+use point::Point;
+use sink::PointSink;
+use spill::Spill;
+use std::fs::File;
+use std::io::BufReader;
+use std::iter;
+use std::mem;
+use std::path::Path;
+use timing;
+use transform::Transform;
+use units::Degrees;
+use vlp_16::{self, Axes, AzimuthDirection, Decimation, Decoder, FiringSequences, Interpolation,
+              ElevationTable, MAX_LASERS, Packet, PacketKind, Precision, ReturnMode,
+              Sensor};
+use wire;
+
+/// The size of the chunks handed out by `process_chunks`.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum ChunkSize {
+    /// The points of this many data packets.
+    Packets(usize),
+    /// The points of this many frames.
+    Frames(usize),
+    /// As many points as fit into this many bytes of memory.
+    ///
+    /// Chunks are cut between packets, so a chunk can exceed the budget by one packet's points.
+    Bytes(usize),
+}
+
+/// Configures batch decoding.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct Config {
+    sensor: Option<Sensor>,
+    lenient: bool,
+    resync: bool,
+    return_mode: Option<ReturnMode>,
+    interpolation: Interpolation,
+    transform: Option<Transform>,
+    decimation: Decimation,
+    firing_sequences: FiringSequences,
+    channels: Option<u32>,
+    deterministic: bool,
+    precision: Precision,
+    azimuth_offsets: Option<[Degrees; MAX_LASERS]>,
+    elevations: Option<ElevationTable>,
+    axes: Axes,
+    azimuth_direction: AzimuthDirection,
+}
+
+impl Config {
+    /// Creates a new, default configuration.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use velodyne::batch::Config;
+    /// let config = Config::new();
+    /// ```
+    pub fn new() -> Config {
+        Config::default()
+    }
+
+    /// Sets how azimuths are interpolated.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use velodyne::batch::Config;
+    /// use velodyne::vlp_16::Interpolation;
+    /// let mut config = Config::new();
+    /// config.interpolation(Interpolation::Timestamp);
+    /// ```
+    pub fn interpolation(&mut self, interpolation: Interpolation) -> &mut Config {
+        self.interpolation = interpolation;
+        self
+    }
+
+    /// Sets the sensor's pose, which is applied to every decoded point.
+    ///
+    /// See `Decoder::transform`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use velodyne::batch::Config;
+    /// use velodyne::transform::Transform;
+    /// let mut config = Config::new();
+    /// config.transform(Transform::from_euler(0., 10., 0., [0., 0., 1.8]));
+    /// ```
+    pub fn transform<T: Into<Transform>>(&mut self, transform: T) -> &mut Config {
+        self.transform = Some(transform.into());
+        self
+    }
+
+    /// Sets which points are kept.
+    ///
+    /// See `Decoder::decimation`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use velodyne::batch::Config;
+    /// use velodyne::vlp_16::Decimation;
+    /// let mut config = Config::new();
+    /// config.decimation(Decimation::Points(4));
+    /// ```
+    pub fn decimation(&mut self, decimation: Decimation) -> &mut Config {
+        self.decimation = decimation;
+        self
+    }
+
+    /// Sets what happens to the two firing sequences of single-return data blocks.
+    ///
+    /// See `Decoder::firing_sequences`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use velodyne::batch::Config;
+    /// use velodyne::vlp_16::FiringSequences;
+    /// let mut config = Config::new();
+    /// config.firing_sequences(FiringSequences::Averaged);
+    /// ```
+    pub fn firing_sequences(&mut self, firing_sequences: FiringSequences) -> &mut Config {
+        self.firing_sequences = firing_sequences;
+        self
+    }
+
+    /// Only decodes these channels.
+    ///
+    /// See `Decoder::channels`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use velodyne::batch::Config;
+    /// let mut config = Config::new();
+    /// config.channels(&[4, 5, 6, 7, 8, 9, 10, 11]);
+    /// ```
+    pub fn channels(&mut self, channels: &[u8]) -> &mut Config {
+        let mask = channels.iter()
+            .filter(|&&channel| (channel as usize) < MAX_LASERS)
+            .fold(0, |mask, &channel| mask | 1 << channel);
+        self.channels = Some(mask);
+        self
+    }
+
+    /// Overrides the return mode that packets report.
+    ///
+    /// See `Decoder::return_mode`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use velodyne::batch::Config;
+    /// use velodyne::vlp_16::ReturnMode;
+    /// let mut config = Config::new();
+    /// config.return_mode(ReturnMode::DualReturn);
+    /// ```
+    pub fn return_mode(&mut self, return_mode: ReturnMode) -> &mut Config {
+        self.return_mode = Some(return_mode);
+        self
+    }
+
+    /// Decodes data packets as if they came from this sensor, whatever their factory byte says.
+    ///
+    /// This keeps captures decodable when the sensor byte is corrupt or comes from unsupported
+    /// firmware. A warning is logged the first time a packet's byte doesn't match. See
+    /// `Packet::with_sensor`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use velodyne::batch::Config;
+    /// use velodyne::vlp_16::Sensor;
+    /// let mut config = Config::new();
+    /// config.sensor(Sensor::VLP_16);
+    /// ```
+    pub fn sensor(&mut self, sensor: Sensor) -> &mut Config {
+        self.sensor = Some(sensor);
+        self
+    }
+
+    /// Sets whether unknown sensor and return mode factory bytes are accepted.
+    ///
+    /// See `Packet::new_lenient`. The default is false, so unknown bytes are errors.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use velodyne::batch::Config;
+    /// let mut config = Config::new();
+    /// config.lenient(true);
+    /// ```
+    pub fn lenient(&mut self, lenient: bool) -> &mut Config {
+        self.lenient = lenient;
+        self
+    }
+
+    /// Sets whether misaligned data packets are salvaged by scanning for their data blocks.
+    ///
+    /// See `Packet::resync`. Each salvaged packet logs a warning with the number of bytes that
+    /// were skipped. The default is false, so records that aren't packets are skipped.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use velodyne::batch::Config;
+    /// let mut config = Config::new();
+    /// config.resync(true);
+    /// ```
+    pub fn resync(&mut self, resync: bool) -> &mut Config {
+        self.resync = resync;
+        self
+    }
+
+    /// Sets whether points are bit-identical on every platform and every run.
+    ///
+    /// See `Decoder::deterministic`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use velodyne::batch::Config;
+    /// let mut config = Config::new();
+    /// config.deterministic(true);
+    /// ```
+    pub fn deterministic(&mut self, deterministic: bool) -> &mut Config {
+        self.deterministic = deterministic;
+        self
+    }
+
+    /// Sets the precision of azimuths and coordinates.
+    ///
+    /// See `Decoder::precision`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use velodyne::batch::Config;
+    /// use velodyne::vlp_16::Precision;
+    /// let mut config = Config::new();
+    /// config.precision(Precision::Double);
+    /// ```
+    pub fn precision(&mut self, precision: Precision) -> &mut Config {
+        self.precision = precision;
+        self
+    }
+
+    /// Sets per-laser azimuth corrections, indexed by channel.
+    ///
+    /// See `Decoder::azimuth_offsets`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use velodyne::batch::Config;
+    /// use velodyne::units::Degrees;
+    /// let mut config = Config::new();
+    /// config.azimuth_offsets(&[Degrees(-1.2), Degrees(1.2)]);
+    /// ```
+    pub fn azimuth_offsets(&mut self, offsets: &[Degrees]) -> &mut Config {
+        let mut table = [Degrees(0.); MAX_LASERS];
+        for (entry, &offset) in table.iter_mut().zip(offsets) {
+            *entry = offset;
+        }
+        self.azimuth_offsets = Some(table);
+        self
+    }
+
+    /// Sets per-laser elevations, indexed by channel.
+    ///
+    /// See `Decoder::elevations`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use velodyne::batch::Config;
+    /// use velodyne::units::Degrees;
+    /// let mut config = Config::new();
+    /// config.elevations(&[Degrees(-15.1), Degrees(1.02)]);
+    /// ```
+    pub fn elevations(&mut self, elevations: &[Degrees]) -> &mut Config {
+        self.elevations = Some(vlp_16::elevation_table(elevations));
+        self
+    }
+
+    /// Sets the axes of the points' coordinates.
+    ///
+    /// See `Decoder::axes`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use velodyne::batch::Config;
+    /// use velodyne::vlp_16::Axes;
+    /// let mut config = Config::new();
+    /// config.axes(Axes::ForwardLeftUp);
+    /// ```
+    pub fn axes(&mut self, axes: Axes) -> &mut Config {
+        self.axes = axes;
+        self
+    }
+
+    /// Sets which way the points' azimuths increase.
+    ///
+    /// See `Decoder::azimuth_direction`. Frames are assembled in the same direction.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use velodyne::batch::Config;
+    /// use velodyne::vlp_16::AzimuthDirection;
+    /// let mut config = Config::new();
+    /// config.azimuth_direction(AzimuthDirection::Counterclockwise);
+    /// ```
+    pub fn azimuth_direction(&mut self, azimuth_direction: AzimuthDirection) -> &mut Config {
+        self.azimuth_direction = azimuth_direction;
+        self
+    }
+
+    fn decoder(&self) -> Decoder {
+        let mut decoder = Decoder::new();
+        if let Some(return_mode) = self.return_mode {
+            decoder.return_mode(return_mode);
+        }
+        decoder.interpolation(self.interpolation)
+            .decimation(self.decimation)
+            .firing_sequences(self.firing_sequences)
+            .deterministic(self.deterministic)
+            .precision(self.precision)
+            .axes(self.axes)
+            .azimuth_direction(self.azimuth_direction);
+        if let Some(transform) = self.transform {
+            decoder.transform(transform);
+        }
+        if let Some(ref offsets) = self.azimuth_offsets {
+            decoder.azimuth_offsets(offsets);
+        }
+        if let Some(table) = self.elevations {
+            decoder.elevation_table(table);
+        }
+        if let Some(mask) = self.channels {
+            let channels = (0..MAX_LASERS as u8)
+                .filter(|&channel| mask & 1 << channel != 0)
+                .collect::<Vec<_>>();
+            decoder.channels(&channels);
+        }
+        decoder
+    }
+
+    /// Parses a packet, honoring the sensor override and warning about the first mismatch.
+    fn packet(&self, bytes: &[u8], warned: &mut bool) -> Result<Packet> {
+        let (bytes, skipped) = self.realign(bytes);
+        if skipped > 0 {
+            warn!("skipped {} bytes to resynchronize a data packet", skipped);
+        }
+        let sensor = match self.sensor {
+            Some(sensor) => sensor,
+            None => return Packet::parse(bytes, None, self.lenient),
+        };
+        if !*warned && vlp_16::classify(bytes) == Some(PacketKind::Data) {
+            let n = vlp_16::payload(bytes)[wire::SENSOR.offset];
+            if Sensor::from_u8(n).ok() != Some(sensor) {
+                warn!("sensor factory byte {:#04x} doesn't match the {:?} override",
+                      n,
+                      sensor);
+                *warned = true;
+            }
+        }
+        Packet::parse(bytes, Some(sensor), self.lenient)
+    }
+
+    /// Returns the data payload of misaligned bytes, and the bytes skipped, if resyncing.
+    fn realign<'a>(&self, bytes: &'a [u8]) -> (&'a [u8], usize) {
+        if self.resync && vlp_16::classify(bytes).is_none() {
+            if let Some(offset) = vlp_16::resync(bytes) {
+                return (&bytes[offset..offset + wire::DATA_PAYLOAD_LEN], offset);
+            }
+        }
+        (bytes, 0)
+    }
+
+    fn assembler(&self) -> Assembler {
+        let mut assembler = Assembler::new();
+        assembler.azimuth_direction(self.azimuth_direction);
+        assembler
+    }
+}
+
+/// Decodes the points of consecutive packets.
+///
+/// Position packets are skipped. Azimuths are interpolated across packet boundaries, as with a
+/// `Decoder`.
+///
+/// # Examples
+///
+/// ```
+/// use velodyne::batch::{self, Config};
+/// use velodyne::fixtures::{VLP_16_DATA_PACKET, VLP_16_POSITION_PACKET};
+/// let packets: [&[u8]; 2] = [&VLP_16_DATA_PACKET, &VLP_16_POSITION_PACKET];
+/// let points = batch::decode_packets(&packets, &Config::new()).unwrap();
+/// assert_eq!(384, points.len());
+/// ```
+pub fn decode_packets(packets: &[&[u8]], config: &Config) -> Result<Vec<Point>> {
+    let mut points = Vec::with_capacity(packets.len() * timing::POINTS_PER_PACKET);
+    let mut warned = false;
+    decode(packets.iter().map(|bytes| config.packet(bytes, &mut warned)),
+           config,
+           |decoded| {
+               points.extend(decoded);
+               Ok(())
+           })?;
+    Ok(points)
+}
+
+/// Decodes consecutive packets into frames.
+///
+/// The last frame is returned even if it is incomplete.
+///
+/// # Examples
+///
+/// ```
+/// use velodyne::batch::{self, Config};
+/// use velodyne::fixtures::VLP_16_DATA_PACKET;
+/// let frames = batch::decode_frames(&[&VLP_16_DATA_PACKET], &Config::new()).unwrap();
+/// assert_eq!(1, frames.len());
+/// ```
+pub fn decode_frames(packets: &[&[u8]], config: &Config) -> Result<Vec<Frame>> {
+    let mut assembler = config.assembler();
+    let mut frames = Vec::new();
+    let mut warned = false;
+    let packets = packets.iter().map(|bytes| config.packet(bytes, &mut warned));
+    decode(packets, config, |decoded| {
+        frames.extend(decoded.into_iter().filter_map(|point| assembler.push(point)));
+        Ok(())
+    })?;
+    frames.extend(assembler.finish());
+    Ok(frames)
+}
+
+/// Reads every point in a pcap file.
+///
+/// Records that aren't Velodyne packets, e.g. other traffic on the network, are skipped. A
+/// Velodyne packet that can't be parsed is an error.
+///
+/// # Examples
+///
+/// ```
+/// use velodyne::batch::Config;
+/// let cloud = velodyne::read_pcap_points("data/single.pcap", &Config::new()).unwrap();
+/// assert_eq!(384, cloud.len());
+/// ```
+pub fn read_pcap_points<P: AsRef<Path>>(path: P, config: &Config) -> Result<PointCloud> {
+    let mut cloud = PointCloud::new();
+    read_pcap(path, config, |decoded| {
+        cloud.extend(decoded);
+        Ok(())
+    })?;
+    Ok(cloud)
+}
+
+/// Reads every point in a pcap file into a `Spill`, which holds at most `limit` bytes of points
+/// in memory and writes the rest to a temporary file.
+///
+/// This is `read_pcap_points` for captures whose points don't fit into memory. Export the points
+/// with `Spill::write_to`.
+///
+/// # Examples
+///
+/// ```
+/// use velodyne::batch::{self, Config};
+/// let spill = batch::spill_pcap_points("data/single.pcap", &Config::new(), 1 << 20).unwrap();
+/// assert_eq!(384, spill.len());
+/// ```
+pub fn spill_pcap_points<P: AsRef<Path>>(path: P, config: &Config, limit: usize) -> Result<Spill> {
+    let mut spill = Spill::new(limit);
+    read_pcap(path, config, |decoded| spill.write_points(&decoded))?;
+    Ok(spill)
+}
+
+/// Reads every frame in a pcap file.
+///
+/// The last frame is returned even if it is incomplete. Non-Velodyne records are skipped, as
+/// with `read_pcap_points`.
+///
+/// # Examples
+///
+/// ```
+/// use velodyne::batch::Config;
+/// let frames = velodyne::read_pcap_frames("data/single.pcap", &Config::new()).unwrap();
+/// assert_eq!(1, frames.len());
+/// ```
+pub fn read_pcap_frames<P: AsRef<Path>>(path: P, config: &Config) -> Result<Vec<Frame>> {
+    let mut assembler = config.assembler();
+    let mut frames = Vec::new();
+    read_pcap(path, config, |decoded| {
+        frames.extend(decoded.into_iter().filter_map(|point| assembler.push(point)));
+        Ok(())
+    })?;
+    frames.extend(assembler.finish());
+    Ok(frames)
+}
+
+/// Decodes every packet from a source and hands the points to a closure in chunks.
+///
+/// Only one chunk is held in memory at a time. Chunks are never empty, and the last chunk may be
+/// smaller than the others. Non-Velodyne records are skipped, as with `read_pcap_points`. An error
+/// from the source or from the closure stops processing. Returns the number of chunks.
+///
+/// # Examples
+///
+/// ```
+/// use velodyne::batch::{self, ChunkSize, Config};
+/// use velodyne::io::Pcap;
+/// let pcap = Pcap::open("data/single.pcap").unwrap();
+/// let mut total = 0;
+/// let chunks = batch::process_chunks(pcap, ChunkSize::Packets(1), &Config::new(), |mut points| {
+///     points.sort_by(|a, b| a.range().partial_cmp(&b.range()).unwrap());
+///     total += points.len();
+///     Ok(())
+/// }).unwrap();
+/// assert_eq!((1, 384), (chunks, total));
+/// ```
+pub fn process_chunks<R, F>(mut read: R,
+                            size: ChunkSize,
+                            config: &Config,
+                            mut f: F)
+                            -> Result<usize>
+    where R: Read,
+          F: FnMut(Vec<Point>) -> Result<()>
+{
+    let mut decoder = config.decoder();
+    let mut assembler = config.assembler();
+    let mut chunk = Vec::new();
+    let mut count = 0;
+    let mut chunks = 0;
+    let mut end = false;
+    let mut warned = false;
+    while !end {
+        let decoded = match read.read() {
+            Some(Ok(bytes)) => {
+                if vlp_16::classify(config.realign(bytes).0).is_none() {
+                    continue;
+                }
+                decoder.push(config.packet(bytes, &mut warned)?)
+            }
+            Some(Err(err)) => return Err(err),
+            None => {
+                end = true;
+                decoder.finish()
+            }
+        };
+        if let Some(points) = decoded {
+            match size {
+                ChunkSize::Frames(_) => {
+                    for frame in points.into_iter().filter_map(|point| assembler.push(point)) {
+                        chunk.extend(frame.points);
+                        count += 1;
+                    }
+                }
+                ChunkSize::Packets(_) => {
+                    chunk.extend(points);
+                    count += 1;
+                }
+                ChunkSize::Bytes(_) => {
+                    chunk.extend(points);
+                    count = chunk.len() * size_of::<Point>();
+                }
+            }
+        }
+        if end {
+            if let Some(frame) = assembler.finish() {
+                chunk.extend(frame.points);
+            }
+        }
+        let full = match size {
+            ChunkSize::Packets(n) | ChunkSize::Frames(n) | ChunkSize::Bytes(n) => count >= n,
+        };
+        if (full || end) && !chunk.is_empty() {
+            f(mem::take(&mut chunk))?;
+            chunks += 1;
+            count = 0;
+        }
+    }
+    Ok(chunks)
+}
+
+fn read_pcap<P, F>(path: P, config: &Config, f: F) -> Result<()>
+    where P: AsRef<Path>,
+          F: FnMut(Vec<Point>) -> Result<()>
+{
+    let mut stream = PcapStream::new(BufReader::new(File::open(path)?))?;
+    let mut warned = false;
+    let packets = iter::from_fn(|| loop {
+        match stream.read()? {
+            Ok(bytes) if vlp_16::classify(config.realign(bytes).0).is_none() => continue,
+            result => return Some(result.and_then(|bytes| config.packet(bytes, &mut warned))),
+        }
+    });
+    decode(packets, config, f)
+}
+
+fn decode<I, F>(packets: I, config: &Config, mut f: F) -> Result<()>
+    where I: IntoIterator<Item = Result<Packet>>,
+          F: FnMut(Vec<Point>) -> Result<()>
+{
+    let mut decoder = config.decoder();
+    for packet in packets {
+        if let Some(points) = decoder.push(packet?) {
+            f(points)?;
+        }
+    }
+    if let Some(points) = decoder.finish() {
+        f(points)?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::UTC;
+    use fixtures::VLP_16_DATA_PACKET;
+    use io::{PcapSlice, PcapWriter};
+    use point::ReturnType;
+    use vlp_16::PACKET_HEADER_LEN;
+
+    #[test]
+    fn matches_decoder() {
+        let packets: [&[u8]; 2] = [&VLP_16_DATA_PACKET, &VLP_16_DATA_PACKET];
+        let points = decode_packets(&packets, &Config::new()).unwrap();
+        let mut decoder = Decoder::new();
+        let mut expected = Vec::new();
+        for bytes in &packets {
+            expected.extend(decoder.push(Packet::new(bytes).unwrap()).unwrap_or_default());
+        }
+        expected.extend(decoder.finish().unwrap());
+        assert_eq!(expected.len(), points.len());
+        for (expected, actual) in expected.iter().zip(&points) {
+            assert_eq!(format!("{:?}", expected), format!("{:?}", actual));
+        }
+    }
+
+    #[test]
+    fn transform() {
+        let packets: [&[u8]; 1] = [&VLP_16_DATA_PACKET];
+        let points = decode_packets(&packets, &Config::new()).unwrap();
+        let mut config = Config::new();
+        config.transform(Transform::from_euler(180., 0., 0., [0., 0., 2.]));
+        let transformed = decode_packets(&packets, &config).unwrap();
+        for (point, transformed) in points.iter().zip(&transformed) {
+            if point.range() == 0. {
+                assert_eq!(0., transformed.range());
+            } else {
+                assert_eq!(point.x, transformed.x);
+                assert!((2. - point.z - transformed.z).abs() < 1e-5);
+            }
+        }
+    }
+
+    #[test]
+    fn read_pcap_file() {
+        let cloud = read_pcap_points("data/single.pcap", &Config::new()).unwrap();
+        let packets: [&[u8]; 1] = [&VLP_16_DATA_PACKET];
+        let points = decode_packets(&packets, &Config::new()).unwrap();
+        assert_eq!(points.len(), cloud.len());
+        assert!(read_pcap_points("data/position.pcap", &Config::new()).unwrap().is_empty());
+        assert!(read_pcap_points("data/missing.pcap", &Config::new()).is_err());
+    }
+
+    #[test]
+    fn chunks() {
+        let packets: [&[u8]; 3] = [&VLP_16_DATA_PACKET; 3];
+        let points = decode_packets(&packets, &Config::new()).unwrap();
+        let mut writer = PcapWriter::new(Vec::new()).unwrap();
+        for bytes in &packets {
+            writer.write(&bytes[PACKET_HEADER_LEN..], UTC::now()).unwrap();
+        }
+        let pcap = writer.into_inner();
+        let process = |size| {
+            let mut lengths = Vec::new();
+            let mut processed = Vec::new();
+            let chunks = process_chunks(PcapSlice::new(&pcap[..]).unwrap(),
+                                        size,
+                                        &Config::new(),
+                                        |chunk| {
+                                            lengths.push(chunk.len());
+                                            processed.extend(chunk);
+                                            Ok(())
+                                        })
+                .unwrap();
+            assert_eq!(chunks, lengths.len());
+            assert_eq!(points.len(), processed.len());
+            lengths
+        };
+        assert_eq!(vec![384, 384, 384], process(ChunkSize::Packets(1)));
+        assert_eq!(vec![768, 384], process(ChunkSize::Packets(2)));
+        assert_eq!(vec![1152], process(ChunkSize::Frames(1)));
+        let bytes = 400 * size_of::<Point>();
+        assert_eq!(vec![768, 384], process(ChunkSize::Bytes(bytes)));
+    }
+
+    #[test]
+    fn invalid_packet() {
+        let mut bytes = VLP_16_DATA_PACKET;
+        bytes[42] = 0;
+        assert!(decode_packets(&[&bytes], &Config::new()).is_err());
+    }
+
+    #[test]
+    fn lenient() {
+        let mut bytes = VLP_16_DATA_PACKET;
+        bytes[PACKET_HEADER_LEN + wire::RETURN_MODE.offset] = 0x3a;
+        assert!(decode_packets(&[&bytes], &Config::new()).is_err());
+        let expected = decode_packets(&[&VLP_16_DATA_PACKET], &Config::new()).unwrap();
+        let points = decode_packets(&[&bytes], Config::new().lenient(true)).unwrap();
+        assert_eq!(expected.len(), points.len());
+        assert!(points.iter().all(|point| matches!(point.return_type, ReturnType::Strongest)));
+    }
+
+    #[test]
+    fn resync() {
+        let mut bytes = vec![0; 10];
+        bytes.extend_from_slice(&VLP_16_DATA_PACKET[PACKET_HEADER_LEN..]);
+        bytes.extend_from_slice(&[0; 3]);
+        assert!(decode_packets(&[&bytes], &Config::new()).is_err());
+        let expected = decode_packets(&[&VLP_16_DATA_PACKET], &Config::new()).unwrap();
+        let points = decode_packets(&[&bytes], Config::new().resync(true)).unwrap();
+        assert_eq!(expected.len(), points.len());
+    }
+
+    #[test]
+    fn sensor_override() {
+        let mut bytes = VLP_16_DATA_PACKET;
+        bytes[PACKET_HEADER_LEN + wire::SENSOR.offset] = 0x99;
+        assert!(decode_packets(&[&bytes], &Config::new()).is_err());
+        let expected = decode_packets(&[&VLP_16_DATA_PACKET], &Config::new()).unwrap();
+        let points = decode_packets(&[&bytes], Config::new().sensor(Sensor::VLP_16)).unwrap();
+        assert_eq!(expected.len(), points.len());
+        for (expected, point) in expected.iter().zip(&points) {
+            assert_eq!((expected.x, expected.y, expected.z), (point.x, point.y, point.z));
+        }
+    }
+}
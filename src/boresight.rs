@@ -0,0 +1,333 @@
+//! Estimate a sensor's mounting pose from retro-reflective targets at surveyed positions.
+//!
+//! Retro-reflectors return with a reflectivity above `point::MAX_DIFFUSE_REFLECTIVITY`, so they
+//! stand out from everything else in a capture. The bright returns are clustered into targets in
+//! the sensor's frame, and the mounting transform, from the sensor's frame to the vehicle's, is
+//! found by moving roll, pitch, yaw, and the translation one at a time, with shrinking steps, until
+//! the targets line up with their surveyed positions. It's a coarse solver that needs a starting
+//! guess within about a meter at the targets, e.g. from a tape measure, and at least three targets
+//! that aren't in a line:
+//!
+//! ```
+//! use velodyne::boresight::Calibrator;
+//! use velodyne::units::Degrees;
+//! let frames = velodyne::read_pcap_frames("data/single.pcap", &Default::default()).unwrap();
+//! let mut calibrator = Calibrator::new(vec![[10., 0., 1.], [0., 8., 1.5], [-7., -3., 0.5]]);
+//! calibrator.initial(Degrees(0.), Degrees(0.), Degrees(90.), [0., 0., 1.8]);
+//! if let Some(calibration) = calibrator.calibrate(&frames[0].points) {
+//!     println!("yaw: {}°", calibration.yaw.0);
+//! }
+//! ```
+
+use Point;
+use point::MAX_DIFFUSE_REFLECTIVITY;
+use transform::Transform;
+use units::{Degrees, Meters};
+
+const DEFAULT_CLUSTER_RADIUS: f32 = 0.3;
+const DEFAULT_MAX_DISTANCE: f32 = 1.;
+const INITIAL_ANGLE_STEP: f32 = 2.;
+const INITIAL_TRANSLATION_STEP: f32 = 0.2;
+const MIN_ANGLE_STEP: f32 = 0.001;
+const MIN_TRANSLATION_STEP: f32 = 0.0001;
+const MAX_PASSES: usize = 10_000;
+
+/// Estimates a sensor's mounting pose from a capture of surveyed targets.
+#[derive(Clone, Debug)]
+pub struct Calibrator {
+    targets: Vec<[f32; 3]>,
+    min_reflectivity: u8,
+    cluster_radius: f32,
+    max_distance: f32,
+    initial: [f32; 6],
+}
+
+/// A mounting pose that lines the detected targets up with the surveyed ones.
+#[derive(Clone, Copy, Debug)]
+pub struct Calibration {
+    /// The transform from the sensor's frame to the vehicle's.
+    pub transform: Transform,
+    /// The roll, about the x axis.
+    pub roll: Degrees,
+    /// The pitch, about the y axis.
+    pub pitch: Degrees,
+    /// The yaw, about the z axis.
+    pub yaw: Degrees,
+    /// The sensor's position in the vehicle's frame.
+    pub translation: [f32; 3],
+    /// The number of detected targets that are within the maximum distance of a surveyed one.
+    pub matched: usize,
+    /// The root mean square distance between the matched targets and the surveyed ones.
+    pub rms: Meters,
+}
+
+impl Calibrator {
+    /// Creates a calibrator for targets at these positions in the vehicle's frame.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use velodyne::boresight::Calibrator;
+    /// let calibrator = Calibrator::new(vec![[10., 0., 1.], [0., 8., 1.5], [-7., -3., 0.5]]);
+    /// ```
+    pub fn new(targets: Vec<[f32; 3]>) -> Calibrator {
+        Calibrator {
+            targets,
+            min_reflectivity: MAX_DIFFUSE_REFLECTIVITY + 1,
+            cluster_radius: DEFAULT_CLUSTER_RADIUS,
+            max_distance: DEFAULT_MAX_DISTANCE,
+            initial: [0.; 6],
+        }
+    }
+
+    /// Sets the lowest reflectivity of a target's returns.
+    ///
+    /// The default is the lowest retro-reflector reflectivity.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use velodyne::boresight::Calibrator;
+    /// let mut calibrator = Calibrator::new(Vec::new());
+    /// calibrator.min_reflectivity(200);
+    /// ```
+    pub fn min_reflectivity(&mut self, min_reflectivity: u8) -> &mut Calibrator {
+        self.min_reflectivity = min_reflectivity;
+        self
+    }
+
+    /// Sets how close a bright return has to be to a target's centroid to be part of it.
+    ///
+    /// The default is 0.3 meters.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use velodyne::boresight::Calibrator;
+    /// use velodyne::units::Meters;
+    /// let mut calibrator = Calibrator::new(Vec::new());
+    /// calibrator.cluster_radius(Meters(0.5));
+    /// ```
+    pub fn cluster_radius(&mut self, cluster_radius: Meters) -> &mut Calibrator {
+        self.cluster_radius = cluster_radius.0;
+        self
+    }
+
+    /// Sets how far a detected target can be from a surveyed one and still pull the pose
+    /// towards it.
+    ///
+    /// Targets that are farther away, e.g. road signs, are ignored. The default is one meter.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use velodyne::boresight::Calibrator;
+    /// use velodyne::units::Meters;
+    /// let mut calibrator = Calibrator::new(Vec::new());
+    /// calibrator.max_distance(Meters(2.));
+    /// ```
+    pub fn max_distance(&mut self, max_distance: Meters) -> &mut Calibrator {
+        self.max_distance = max_distance.0;
+        self
+    }
+
+    /// Sets the pose to start searching from.
+    ///
+    /// The default is the identity.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use velodyne::boresight::Calibrator;
+    /// use velodyne::units::Degrees;
+    /// let mut calibrator = Calibrator::new(Vec::new());
+    /// calibrator.initial(Degrees(0.), Degrees(0.), Degrees(90.), [0., 0., 1.8]);
+    /// ```
+    pub fn initial(&mut self,
+                   roll: Degrees,
+                   pitch: Degrees,
+                   yaw: Degrees,
+                   translation: [f32; 3])
+                   -> &mut Calibrator {
+        self.initial = [roll.0,
+                        pitch.0,
+                        yaw.0,
+                        translation[0],
+                        translation[1],
+                        translation[2]];
+        self
+    }
+
+    /// Returns the centroids of the targets in these points, in the sensor's frame.
+    ///
+    /// Each centroid is weighted by its returns' reflectivity.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use velodyne::boresight::Calibrator;
+    /// let frames = velodyne::read_pcap_frames("data/single.pcap", &Default::default()).unwrap();
+    /// let targets = Calibrator::new(Vec::new()).detect(&frames[0].points);
+    /// ```
+    pub fn detect(&self, points: &[Point]) -> Vec<[f32; 3]> {
+        let radius_squared = self.cluster_radius * self.cluster_radius;
+        let mut clusters: Vec<([f32; 3], f32)> = Vec::new();
+        for point in points.iter().filter(|point| point.reflectivity >= self.min_reflectivity) {
+            let xyz = [point.x, point.y, point.z];
+            let weight = f32::from(point.reflectivity);
+            let cluster = clusters.iter_mut()
+                .find(|&&mut (sum, total)| distance_squared(centroid(sum, total), xyz) <=
+                                           radius_squared);
+            match cluster {
+                Some(&mut (ref mut sum, ref mut total)) => {
+                    for (value, coordinate) in sum.iter_mut().zip(&xyz) {
+                        *value += weight * coordinate;
+                    }
+                    *total += weight;
+                }
+                None => {
+                    clusters.push(([weight * xyz[0], weight * xyz[1], weight * xyz[2]], weight))
+                }
+            }
+        }
+        clusters.into_iter().map(|(sum, total)| centroid(sum, total)).collect()
+    }
+
+    /// Estimates the mounting pose from these points.
+    ///
+    /// Returns `None` if fewer than three targets end up within the maximum distance of a
+    /// surveyed one.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use velodyne::boresight::Calibrator;
+    /// let frames = velodyne::read_pcap_frames("data/single.pcap", &Default::default()).unwrap();
+    /// assert!(Calibrator::new(Vec::new()).calibrate(&frames[0].points).is_none());
+    /// ```
+    pub fn calibrate(&self, points: &[Point]) -> Option<Calibration> {
+        let detected = self.detect(points);
+        let mut pose = self.initial;
+        let mut cost = self.cost(&detected, pose);
+        let mut steps = [INITIAL_ANGLE_STEP,
+                         INITIAL_ANGLE_STEP,
+                         INITIAL_ANGLE_STEP,
+                         INITIAL_TRANSLATION_STEP,
+                         INITIAL_TRANSLATION_STEP,
+                         INITIAL_TRANSLATION_STEP];
+        for _ in 0..MAX_PASSES {
+            let mut improved = false;
+            for i in 0..pose.len() {
+                for &sign in &[1., -1.] {
+                    let mut candidate = pose;
+                    candidate[i] += sign * steps[i];
+                    let candidate_cost = self.cost(&detected, candidate);
+                    if candidate_cost < cost {
+                        pose = candidate;
+                        cost = candidate_cost;
+                        improved = true;
+                        break;
+                    }
+                }
+            }
+            if !improved {
+                if steps[0] < MIN_ANGLE_STEP && steps[3] < MIN_TRANSLATION_STEP {
+                    break;
+                }
+                for step in &mut steps {
+                    *step /= 2.;
+                }
+            }
+        }
+        let transform = transform(pose);
+        let max_distance_squared = self.max_distance * self.max_distance;
+        let matched = detected.iter()
+            .map(|&xyz| self.nearest(transform.apply_xyz(xyz)))
+            .filter(|&distance_squared| distance_squared <= max_distance_squared)
+            .collect::<Vec<_>>();
+        if matched.len() < 3 {
+            return None;
+        }
+        let rms = (matched.iter().sum::<f32>() / matched.len() as f32).sqrt();
+        Some(Calibration {
+                 transform,
+                 roll: Degrees(pose[0]),
+                 pitch: Degrees(pose[1]),
+                 yaw: Degrees(pose[2]),
+                 translation: [pose[3], pose[4], pose[5]],
+                 matched: matched.len(),
+                 rms: Meters(rms),
+             })
+    }
+
+    fn cost(&self, detected: &[[f32; 3]], pose: [f32; 6]) -> f32 {
+        let transform = transform(pose);
+        let max_distance_squared = self.max_distance * self.max_distance;
+        detected.iter()
+            .map(|&xyz| self.nearest(transform.apply_xyz(xyz)).min(max_distance_squared))
+            .sum()
+    }
+
+    fn nearest(&self, xyz: [f32; 3]) -> f32 {
+        self.targets
+            .iter()
+            .map(|&target| distance_squared(target, xyz))
+            .fold(f32::INFINITY, f32::min)
+    }
+}
+
+fn transform(pose: [f32; 6]) -> Transform {
+    Transform::from_euler(pose[0], pose[1], pose[2], [pose[3], pose[4], pose[5]])
+}
+
+fn centroid(sum: [f32; 3], total: f32) -> [f32; 3] {
+    [sum[0] / total, sum[1] / total, sum[2] / total]
+}
+
+fn distance_squared(a: [f32; 3], b: [f32; 3]) -> f32 {
+    a.iter().zip(&b).map(|(a, b)| (a - b) * (a - b)).sum()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::Duration;
+    use point::{Azimuth, ReturnType, Time};
+
+    fn point(xyz: [f32; 3], reflectivity: u8) -> Point {
+        let mut point = Point::from_spherical(Meters(1.),
+                                              Azimuth::Measured(Degrees(0.)),
+                                              Degrees(0.),
+                                              reflectivity,
+                                              0,
+                                              ReturnType::Strongest,
+                                              Time::Offset(Duration::zero()));
+        point.x = xyz[0];
+        point.y = xyz[1];
+        point.z = xyz[2];
+        point
+    }
+
+    #[test]
+    fn calibrate() {
+        let targets = vec![[10., 0., 1.], [0., 8., 1.5], [-7., -3., 0.5], [4., -9., 2.]];
+        let mounting = Transform::from_euler(1.5, -1., 25., [0.4, -0.1, 1.8]);
+        let mut points = Vec::new();
+        for &target in &targets {
+            let [x, y, z] = mounting.inverse().apply_xyz(target);
+            for &(dx, dz) in &[(0.05, 0.), (-0.05, 0.), (0., 0.05), (0., -0.05)] {
+                points.push(point([x + dx, y, z + dz], 200));
+            }
+            points.push(point([x + 1., y, z], 20));
+        }
+        points.push(point([20., 20., 0.], 255)); // A road sign.
+        let mut calibrator = Calibrator::new(targets);
+        assert_eq!(5, calibrator.detect(&points).len());
+        calibrator.initial(Degrees(0.), Degrees(0.), Degrees(22.), [0., 0., 1.5]);
+        let calibration = calibrator.calibrate(&points).unwrap();
+        assert_eq!(4, calibration.matched);
+        assert!(calibration.rms.0 < 0.01, "{:?}", calibration);
+        assert!((calibration.yaw.0 - 25.).abs() < 0.1, "{:?}", calibration);
+        assert!((calibration.translation[2] - 1.8).abs() < 0.01, "{:?}", calibration);
+    }
+}
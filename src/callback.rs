@@ -0,0 +1,190 @@
+//! Push-style decoding with callbacks.
+//!
+//! Instead of driving iterators by hand, register callbacks for the events you care about and
+//! then `run` the callbacks over a source.
+
+use {Error, Result};
+use frame::{Assembler, Frame};
+use io::Read;
+use vlp_16::Packet;
+
+type PacketCallback<'a> = Box<dyn FnMut(&Packet) + 'a>;
+
+/// A set of callbacks that are invoked as a source is decoded.
+#[allow(missing_debug_implementations)]
+#[derive(Default)]
+pub struct Callbacks<'a> {
+    data: Option<PacketCallback<'a>>,
+    position: Option<PacketCallback<'a>>,
+    frame: Option<Box<dyn FnMut(Frame) + 'a>>,
+    error: Option<Box<dyn FnMut(Error) + 'a>>,
+}
+
+impl<'a> Callbacks<'a> {
+    /// Creates a new set of callbacks that does nothing.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use velodyne::callback::Callbacks;
+    /// let callbacks = Callbacks::new();
+    /// ```
+    pub fn new() -> Callbacks<'a> {
+        Callbacks::default()
+    }
+
+    /// Registers a callback to be called for every data packet.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use velodyne::callback::Callbacks;
+    /// let mut callbacks = Callbacks::new();
+    /// callbacks.on_data(|packet| println!("{:?}", packet.timestamp()));
+    /// ```
+    pub fn on_data<F: FnMut(&Packet) + 'a>(&mut self, f: F) -> &mut Callbacks<'a> {
+        self.data = Some(Box::new(f));
+        self
+    }
+
+    /// Registers a callback to be called for every position packet.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use velodyne::callback::Callbacks;
+    /// let mut callbacks = Callbacks::new();
+    /// callbacks.on_position(|packet| println!("{}", packet.nmea().unwrap()));
+    /// ```
+    pub fn on_position<F: FnMut(&Packet) + 'a>(&mut self, f: F) -> &mut Callbacks<'a> {
+        self.position = Some(Box::new(f));
+        self
+    }
+
+    /// Registers a callback to be called for every completed frame.
+    ///
+    /// The last, possibly partial, frame is delivered when the source is exhausted. Points are
+    /// only computed if a frame callback is registered.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use velodyne::callback::Callbacks;
+    /// let mut callbacks = Callbacks::new();
+    /// callbacks.on_frame(|frame| println!("{}", frame.points.len()));
+    /// ```
+    pub fn on_frame<F: FnMut(Frame) + 'a>(&mut self, f: F) -> &mut Callbacks<'a> {
+        self.frame = Some(Box::new(f));
+        self
+    }
+
+    /// Registers a callback to be called for every error.
+    ///
+    /// If an error callback is registered, decoding continues after an error. Otherwise, `run`
+    /// stops and returns the first error.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use velodyne::callback::Callbacks;
+    /// let mut callbacks = Callbacks::new();
+    /// callbacks.on_error(|err| println!("{:?}", err));
+    /// ```
+    pub fn on_error<F: FnMut(Error) + 'a>(&mut self, f: F) -> &mut Callbacks<'a> {
+        self.error = Some(Box::new(f));
+        self
+    }
+
+    /// Decodes all packets from the source, invoking callbacks along the way.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use velodyne::callback::Callbacks;
+    /// use velodyne::io::Pcap;
+    /// let mut npackets = 0;
+    /// Callbacks::new()
+    ///     .on_data(|_| npackets += 1)
+    ///     .run(Pcap::open("data/single.pcap").unwrap())
+    ///     .unwrap();
+    /// assert_eq!(1, npackets);
+    /// ```
+    pub fn run<R: Read>(&mut self, read: R) -> Result<()> {
+        let mut assembler = Assembler::new();
+        for result in read.vlp_16_packets() {
+            match result {
+                Ok(packet) => self.packet(&packet, &mut assembler),
+                Err(err) => self.error(err)?,
+            }
+        }
+        if let Some(frame) = assembler.finish() {
+            self.frame(frame);
+        }
+        Ok(())
+    }
+
+    fn packet(&mut self, packet: &Packet, assembler: &mut Assembler) {
+        if packet.is_position() {
+            if let Some(ref mut position) = self.position {
+                position(packet);
+            }
+            return;
+        }
+        if let Some(ref mut data) = self.data {
+            data(packet);
+        }
+        if self.frame.is_some() {
+            for point in packet.points().unwrap_or_default() {
+                if let Some(frame) = assembler.push(point) {
+                    self.frame(frame);
+                }
+            }
+        }
+    }
+
+    fn frame(&mut self, frame: Frame) {
+        if let Some(ref mut f) = self.frame {
+            f(frame);
+        }
+    }
+
+    fn error(&mut self, err: Error) -> Result<()> {
+        match self.error {
+            Some(ref mut f) => {
+                warn!("skipping packet: {:?}", err);
+                f(err);
+                Ok(())
+            }
+            None => Err(err),
+        }
+    }
+}
+
+#[cfg(all(test, feature = "pcap"))]
+mod tests {
+    use super::*;
+    use io::Pcap;
+
+    #[test]
+    fn position() {
+        let mut npositions = 0;
+        let mut ndata = 0;
+        Callbacks::new()
+            .on_position(|_| npositions += 1)
+            .on_data(|_| ndata += 1)
+            .run(Pcap::open("data/position.pcap").unwrap())
+            .unwrap();
+        assert_eq!(1, npositions);
+        assert_eq!(0, ndata);
+    }
+
+    #[test]
+    fn frame() {
+        let mut npoints = 0;
+        Callbacks::new()
+            .on_frame(|frame| npoints += frame.points.len())
+            .run(Pcap::open("data/single.pcap").unwrap())
+            .unwrap();
+        assert_eq!(384, npoints);
+    }
+}
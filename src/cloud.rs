@@ -0,0 +1,281 @@
+//! Collections of points.
+
+use Point;
+use frame::Frame;
+use std::iter::FromIterator;
+use std::slice::Iter;
+use std::vec::IntoIter;
+use vlp_16::Packet;
+
+/// An owned collection of points, e.g. a whole decoded capture.
+#[derive(Clone, Debug, Default)]
+pub struct PointCloud {
+    /// The points.
+    pub points: Vec<Point>,
+}
+
+/// An axis-aligned bounding box.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Bounds {
+    /// The minimum x value.
+    pub min_x: f32,
+    /// The minimum y value.
+    pub min_y: f32,
+    /// The minimum z value.
+    pub min_z: f32,
+    /// The maximum x value.
+    pub max_x: f32,
+    /// The maximum y value.
+    pub max_y: f32,
+    /// The maximum z value.
+    pub max_z: f32,
+}
+
+impl PointCloud {
+    /// Creates a new, empty point cloud.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use velodyne::cloud::PointCloud;
+    /// let cloud = PointCloud::new();
+    /// assert!(cloud.is_empty());
+    /// ```
+    pub fn new() -> PointCloud {
+        PointCloud::default()
+    }
+
+    /// Returns the number of points in this cloud.
+    pub fn len(&self) -> usize {
+        self.points.len()
+    }
+
+    /// Returns true if there are no points in this cloud.
+    pub fn is_empty(&self) -> bool {
+        self.points.is_empty()
+    }
+
+    /// Adds a point to this cloud.
+    pub fn push(&mut self, point: Point) {
+        self.points.push(point)
+    }
+
+    /// Adds all of a packet's points to this cloud.
+    ///
+    /// Position packets have no points, so they are ignored.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use velodyne::cloud::PointCloud;
+    /// use velodyne::fixtures::VLP_16_DATA_PACKET;
+    /// use velodyne::vlp_16::Packet;
+    /// let mut cloud = PointCloud::new();
+    /// cloud.extend_from_packet(&Packet::new(&VLP_16_DATA_PACKET).unwrap());
+    /// assert_eq!(384, cloud.len());
+    /// ```
+    pub fn extend_from_packet(&mut self, packet: &Packet) {
+        if let Some(points) = packet.points() {
+            self.points.extend(points);
+        }
+    }
+
+    /// Moves all of a frame's points into this cloud.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use velodyne::cloud::PointCloud;
+    /// use velodyne::frame::Frame;
+    /// let mut cloud = PointCloud::new();
+    /// cloud.append_frame(Frame::default());
+    /// ```
+    pub fn append_frame(&mut self, mut frame: Frame) {
+        self.points.append(&mut frame.points);
+    }
+
+    /// Returns the bounding box of this cloud, or `None` if it is empty.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use velodyne::cloud::PointCloud;
+    /// assert!(PointCloud::new().bounds().is_none());
+    /// ```
+    pub fn bounds(&self) -> Option<Bounds> {
+        let mut points = self.points.iter();
+        points.next().map(|first| {
+            let mut bounds = Bounds::from_point(first);
+            for point in points {
+                bounds.grow(point);
+            }
+            bounds
+        })
+    }
+
+    /// Returns the centroid of this cloud as `[x, y, z]`, or `None` if it is empty.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use velodyne::cloud::PointCloud;
+    /// assert!(PointCloud::new().centroid().is_none());
+    /// ```
+    pub fn centroid(&self) -> Option<[f32; 3]> {
+        if self.points.is_empty() {
+            return None;
+        }
+        let mut sum = [0f64; 3];
+        for point in &self.points {
+            sum[0] += f64::from(point.x);
+            sum[1] += f64::from(point.y);
+            sum[2] += f64::from(point.z);
+        }
+        let n = self.points.len() as f64;
+        Some([(sum[0] / n) as f32, (sum[1] / n) as f32, (sum[2] / n) as f32])
+    }
+
+    /// Returns an iterator over this cloud's points.
+    pub fn iter(&self) -> Iter<'_, Point> {
+        self.points.iter()
+    }
+}
+
+impl From<Vec<Point>> for PointCloud {
+    fn from(points: Vec<Point>) -> PointCloud {
+        PointCloud { points }
+    }
+}
+
+impl From<Frame> for PointCloud {
+    fn from(frame: Frame) -> PointCloud {
+        PointCloud { points: frame.points }
+    }
+}
+
+impl FromIterator<Point> for PointCloud {
+    fn from_iter<I: IntoIterator<Item = Point>>(iter: I) -> PointCloud {
+        PointCloud { points: iter.into_iter().collect() }
+    }
+}
+
+impl Extend<Point> for PointCloud {
+    fn extend<I: IntoIterator<Item = Point>>(&mut self, iter: I) {
+        self.points.extend(iter)
+    }
+}
+
+impl IntoIterator for PointCloud {
+    type Item = Point;
+    type IntoIter = IntoIter<Point>;
+
+    fn into_iter(self) -> IntoIter<Point> {
+        self.points.into_iter()
+    }
+}
+
+impl<'a> IntoIterator for &'a PointCloud {
+    type Item = &'a Point;
+    type IntoIter = Iter<'a, Point>;
+
+    fn into_iter(self) -> Iter<'a, Point> {
+        self.points.iter()
+    }
+}
+
+impl Bounds {
+    /// Creates a bounding box from its minimum and maximum corners.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use velodyne::cloud::Bounds;
+    /// let bounds = Bounds::new([-1., -2., -3.], [1., 2., 3.]);
+    /// assert_eq!(-2., bounds.min_y);
+    /// ```
+    pub fn new(min: [f32; 3], max: [f32; 3]) -> Bounds {
+        Bounds {
+            min_x: min[0],
+            min_y: min[1],
+            min_z: min[2],
+            max_x: max[0],
+            max_y: max[1],
+            max_z: max[2],
+        }
+    }
+
+    /// Creates a bounding box that contains only a single point.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use velodyne::cloud::Bounds;
+    /// use velodyne::fixtures::VLP_16_DATA_PACKET;
+    /// use velodyne::vlp_16::Packet;
+    /// let points = Packet::new(&VLP_16_DATA_PACKET).unwrap().points().unwrap();
+    /// let bounds = Bounds::from_point(&points[0]);
+    /// assert_eq!(bounds.min_x, bounds.max_x);
+    /// ```
+    pub fn from_point(point: &Point) -> Bounds {
+        Bounds {
+            min_x: point.x,
+            min_y: point.y,
+            min_z: point.z,
+            max_x: point.x,
+            max_y: point.y,
+            max_z: point.z,
+        }
+    }
+
+    /// Grows this bounding box to include a point.
+    pub fn grow(&mut self, point: &Point) {
+        self.min_x = self.min_x.min(point.x);
+        self.min_y = self.min_y.min(point.y);
+        self.min_z = self.min_z.min(point.z);
+        self.max_x = self.max_x.max(point.x);
+        self.max_y = self.max_y.max(point.y);
+        self.max_z = self.max_z.max(point.z);
+    }
+
+    /// Returns true if the point is inside of, or on the edge of, this bounding box.
+    pub fn contains(&self, point: &Point) -> bool {
+        point.x >= self.min_x && point.x <= self.max_x && point.y >= self.min_y &&
+        point.y <= self.max_y && point.z >= self.min_z && point.z <= self.max_z
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::Duration;
+    use point::{Azimuth, ReturnType, Time};
+    use units::Degrees;
+
+    fn point(x: f32, y: f32, z: f32) -> Point {
+        Point {
+            x,
+            y,
+            z,
+            reflectivity: 0,
+            channel: 0,
+            sequence: 0,
+            return_type: ReturnType::Strongest,
+            azimuth: Azimuth::Measured(Degrees(0.)),
+            time: Time::Offset(Duration::zero()),
+            sensor_id: None,
+        }
+    }
+
+    #[test]
+    fn bounds_and_centroid() {
+        let cloud = vec![point(1., -2., 3.), point(-1., 4., 0.)].into_iter().collect::<PointCloud>();
+        let bounds = cloud.bounds().unwrap();
+        assert_eq!(-1., bounds.min_x);
+        assert_eq!(4., bounds.max_y);
+        assert_eq!(0., bounds.min_z);
+        assert!(bounds.contains(&point(0., 0., 1.)));
+        assert!(!bounds.contains(&point(0., 0., 4.)));
+        assert_eq!([0., 1., 1.5], cloud.centroid().unwrap());
+        assert_eq!(2, cloud.iter().count());
+    }
+}
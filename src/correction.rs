@@ -0,0 +1,119 @@
+//! Corrections to measured distances.
+//!
+//! The sensor's distances have small biases that depend on the laser, the range, and the
+//! temperature. A `DistanceCorrection` applies a calibration to every return before it is turned
+//! into a point, so a decoder doesn't have to be forked to use one:
+//!
+//! ```
+//! use velodyne::correction::RangeBias;
+//! use velodyne::units::Meters;
+//! use velodyne::vlp_16::Decoder;
+//! let mut decoder = Decoder::new();
+//! decoder.distance_correction(RangeBias::new(&[Meters(0.012), Meters(-0.004)]));
+//! ```
+
+use std::fmt::Debug;
+use units::Meters;
+use vlp_16::{MAX_LASERS, Packet};
+
+/// Corrects the distances of returns.
+///
+/// # Examples
+///
+/// A correction that depends on the temperature, which is read from position packets:
+///
+/// ```
+/// use velodyne::correction::DistanceCorrection;
+/// use velodyne::units::Meters;
+/// use velodyne::vlp_16::Packet;
+///
+/// #[derive(Debug)]
+/// struct Thermal {
+///     temperature: f32,
+/// }
+///
+/// impl DistanceCorrection for Thermal {
+///     fn observe(&mut self, packet: &Packet) {
+///         if let Some(status) = packet.position_status() {
+///             self.temperature = f32::from(status.top_board_temperature);
+///         }
+///     }
+///
+///     fn correct(&self, _: usize, distance: Meters) -> Meters {
+///         distance - Meters(0.0005 * (self.temperature - 40.))
+///     }
+/// }
+/// ```
+pub trait DistanceCorrection: Debug {
+    /// Observes a packet, e.g. to track the sensor's temperature.
+    ///
+    /// Decoders show every packet to their correction before decoding. The default
+    /// implementation does nothing.
+    fn observe(&mut self, packet: &Packet) {
+        let _ = packet;
+    }
+
+    /// Returns the corrected distance of a return from a laser.
+    ///
+    /// Only called for actual returns, never for a zero distance.
+    fn correct(&self, channel: usize, distance: Meters) -> Meters;
+}
+
+/// Adds a fixed bias to each laser's distances.
+#[derive(Clone, Copy, Debug)]
+pub struct RangeBias {
+    biases: [Meters; MAX_LASERS],
+}
+
+impl RangeBias {
+    /// Creates a correction from per-laser biases, indexed by channel.
+    ///
+    /// Channels without a bias aren't corrected.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use velodyne::correction::{DistanceCorrection, RangeBias};
+    /// use velodyne::units::Meters;
+    /// let range_bias = RangeBias::new(&[Meters(0.5)]);
+    /// assert_eq!(Meters(10.5), range_bias.correct(0, Meters(10.)));
+    /// assert_eq!(Meters(10.), range_bias.correct(1, Meters(10.)));
+    /// ```
+    pub fn new(biases: &[Meters]) -> RangeBias {
+        let mut table = [Meters(0.); MAX_LASERS];
+        for (entry, &bias) in table.iter_mut().zip(biases) {
+            *entry = bias;
+        }
+        RangeBias { biases: table }
+    }
+}
+
+impl DistanceCorrection for RangeBias {
+    fn correct(&self, channel: usize, distance: Meters) -> Meters {
+        distance + self.biases.get(channel).cloned().unwrap_or_default()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use fixtures::VLP_16_DATA_PACKET;
+    use vlp_16::Decoder;
+
+    #[test]
+    fn decoder() {
+        let packet = Packet::new(&VLP_16_DATA_PACKET).unwrap();
+        let uncorrected = packet.points().unwrap();
+        let mut decoder = Decoder::new();
+        decoder.distance_correction(RangeBias::new(&[Meters(1.); MAX_LASERS]));
+        decoder.push(packet);
+        let corrected = decoder.finish().unwrap();
+        for (before, after) in uncorrected.iter().zip(&corrected) {
+            if before.range() > 0. {
+                assert!((before.range() + 1. - after.range()).abs() < 1e-4);
+            } else {
+                assert_eq!(0., after.range());
+            }
+        }
+    }
+}
@@ -0,0 +1,81 @@
+//! Coordinate reference systems for georeferenced exports.
+//!
+//! Points come out of the decoder in the sensor's frame, but once a transform puts them into a
+//! map's frame, e.g. UTM, the files they're written to should say so, or GIS tools will load them
+//! in the wrong place. A `Crs` is either an EPSG code or a WKT definition, and the writers that
+//! can record one take it as an option: `pcd::Writer::crs` writes it into the PCD header, and
+//! `las::Vlr::from_crs` builds the LAS record that holds it.
+//!
+//! ```
+//! use velodyne::crs::Crs;
+//! let crs = Crs::parse("EPSG:32633").unwrap();
+//! assert_eq!(Crs::Epsg(32633), crs);
+//! assert_eq!("EPSG:32633", crs.to_string());
+//! ```
+
+use {Error, Result};
+use std::fmt;
+
+/// A coordinate reference system.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Crs {
+    /// A code from the EPSG registry, e.g. 32633 for UTM zone 33N.
+    Epsg(u32),
+    /// A well-known text definition, e.g. `PROJCS["WGS 84 / UTM zone 33N", ...]`.
+    Wkt(String),
+}
+
+impl Crs {
+    /// Parses an EPSG code, written like `EPSG:32633`, or a WKT definition.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use velodyne::crs::Crs;
+    /// assert_eq!(Crs::Epsg(4978), Crs::parse("epsg:4978").unwrap());
+    /// assert!(Crs::parse("GEOGCS[\"WGS 84\"]").is_ok());
+    /// assert!(Crs::parse("UTM 33N").is_err());
+    /// ```
+    pub fn parse(s: &str) -> Result<Crs> {
+        let s = s.trim();
+        if s.len() > 5 && s[..5].eq_ignore_ascii_case("epsg:") {
+            return Ok(Crs::Epsg(s[5..].parse()?));
+        }
+        match s.find('[') {
+            Some(i) if i > 0 && s.ends_with(']') => Ok(Crs::Wkt(s.to_string())),
+            _ => Err(Error::Crs(format!("not an EPSG code or WKT: {}", s))),
+        }
+    }
+}
+
+/// Writes the EPSG code like `EPSG:32633`, or the WKT on a single line.
+impl fmt::Display for Crs {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            Crs::Epsg(code) => write!(f, "EPSG:{}", code),
+            Crs::Wkt(ref wkt) => {
+                let mut words = wkt.split_whitespace();
+                if let Some(word) = words.next() {
+                    f.write_str(word)?;
+                }
+                for word in words {
+                    write!(f, " {}", word)?;
+                }
+                Ok(())
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn wkt_on_one_line() {
+        let crs = Crs::parse("GEOCCS[\"WGS 84\",\n    DATUM[\"WGS_1984\"]]").unwrap();
+        assert_eq!("GEOCCS[\"WGS 84\", DATUM[\"WGS_1984\"]]", crs.to_string());
+        assert!(Crs::parse("EPSG:").is_err());
+        assert!(Crs::parse("[]").is_err());
+    }
+}
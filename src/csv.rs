@@ -0,0 +1,119 @@
+//! Write points as comma-separated values.
+//!
+//! Every point becomes one row with the same fields as a PCD file, `x`, `y`, `z`, `intensity`,
+//! `ring`, `azimuth`, `time`, and `return`, after a header row. `time` is in seconds, past the hour
+//! for offset times or since the Unix epoch for absolute times, and `return` is the return type's
+//! name:
+//!
+//! ```
+//! use velodyne::csv;
+//! use velodyne::fixtures::VLP_16_DATA_PACKET;
+//! use velodyne::sink::PointSink;
+//! use velodyne::vlp_16::Packet;
+//! let points = Packet::new(&VLP_16_DATA_PACKET).unwrap().points().unwrap();
+//! let mut writer = csv::Writer::new(Vec::new());
+//! writer.write_points(&points).unwrap();
+//! let text = String::from_utf8(writer.into_inner()).unwrap();
+//! assert_eq!(points.len() + 1, text.lines().count());
+//! ```
+
+use {Point, Result};
+use pcd;
+use point::ReturnType;
+use sink::PointSink;
+use std::io::Write;
+
+const HEADER: &str = "x,y,z,intensity,ring,azimuth,time,return";
+
+/// Writes points as CSV rows.
+#[derive(Debug)]
+pub struct Writer<W: Write> {
+    write: W,
+    header: bool,
+}
+
+impl<W: Write> Writer<W> {
+    /// Creates a new writer, which writes the header row before the first point.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use velodyne::csv::Writer;
+    /// let writer = Writer::new(Vec::new());
+    /// ```
+    pub fn new(write: W) -> Writer<W> {
+        Writer {
+            write,
+            header: false,
+        }
+    }
+
+    /// Returns the underlying writer.
+    pub fn into_inner(self) -> W {
+        self.write
+    }
+
+    fn write_header(&mut self) -> Result<()> {
+        if !self.header {
+            writeln!(self.write, "{}", HEADER)?;
+            self.header = true;
+        }
+        Ok(())
+    }
+}
+
+impl<W: Write> PointSink for Writer<W> {
+    fn write_points(&mut self, points: &[Point]) -> Result<()> {
+        self.write_header()?;
+        for point in points {
+            writeln!(self.write,
+                     "{},{},{},{},{},{},{},{}",
+                     point.x,
+                     point.y,
+                     point.z,
+                     point.reflectivity,
+                     point.channel,
+                     point.azimuth.degrees(),
+                     pcd::seconds(point.time),
+                     match point.return_type {
+                         ReturnType::Strongest => "strongest",
+                         ReturnType::Last => "last",
+                         ReturnType::Secondary => "secondary",
+                     })?;
+        }
+        Ok(())
+    }
+
+    /// Writes the header row if no points were written, and flushes.
+    fn finish(&mut self) -> Result<()> {
+        self.write_header()?;
+        self.write.flush().map_err(Into::into)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::Duration;
+    use point::{Azimuth, Time};
+    use units::{Degrees, Meters};
+
+    #[test]
+    fn rows() {
+        let point = Point::from_spherical(Meters(2.),
+                                          Azimuth::Measured(Degrees(90.)),
+                                          Degrees(0.),
+                                          7,
+                                          3,
+                                          ReturnType::Last,
+                                          Time::Offset(Duration::milliseconds(1_500)));
+        let mut writer = Writer::new(Vec::new());
+        writer.finish().unwrap();
+        assert_eq!(format!("{}\n", HEADER).as_bytes(), &writer.into_inner()[..]);
+        let mut writer = Writer::new(Vec::new());
+        writer.write_points(&[point]).unwrap();
+        let text = String::from_utf8(writer.into_inner()).unwrap();
+        let row = text.lines().nth(1).unwrap();
+        assert!(row.ends_with(",7,3,90,1.5,last"), "{}", row);
+    }
+}
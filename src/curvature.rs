@@ -0,0 +1,284 @@
+//! Ring-wise curvature, for the feature extraction of LOAM-style SLAM front-ends.
+//!
+//! Each point is scored by how far it is from the centroid of its neighbors on the same ring,
+//! relative to its range. Sharp corners score high and flat surfaces score low, so the highest
+//! scores are edge candidates and the lowest are planar candidates:
+//!
+//! ```
+//! use velodyne::curvature::{Class, Extractor};
+//! let frames = velodyne::read_pcap_frames("data/single.pcap", &Default::default()).unwrap();
+//! let features = Extractor::new().extract(&frames[0].points);
+//! let edges = features.iter().filter(|feature| feature.class == Class::Edge).count();
+//! ```
+
+use Point;
+use point::ReturnType;
+use vlp_16::MAX_LASERS;
+
+const DEFAULT_NEIGHBORS: usize = 5;
+const DEFAULT_EDGE_THRESHOLD: f32 = 0.002;
+const DEFAULT_PLANAR_THRESHOLD: f32 = 0.0005;
+const DEFAULT_SECTORS: usize = 6;
+const DEFAULT_MAX_EDGES: usize = 2;
+const DEFAULT_MAX_PLANAR: usize = 4;
+
+/// What a point was selected as.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Class {
+    /// A sharp point, e.g. on a corner or a pole.
+    Edge,
+    /// A flat point, e.g. on a wall or the ground.
+    Planar,
+    /// Neither, or not scored.
+    Other,
+}
+
+/// A point's curvature and class.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Feature {
+    /// The point's curvature, or `None` if it has no return or too few neighbors on its ring.
+    pub curvature: Option<f32>,
+    /// Whether the point was selected as an edge or planar feature.
+    pub class: Class,
+}
+
+/// Scores points and selects edge and planar features.
+///
+/// Like LOAM, each ring is split into sectors and a few features are selected in each, so
+/// features are spread around the scan. Selecting a feature suppresses its neighbors, so
+/// features don't bunch up.
+#[derive(Clone, Copy, Debug)]
+pub struct Extractor {
+    neighbors: usize,
+    edge_threshold: f32,
+    planar_threshold: f32,
+    sectors: usize,
+    max_edges: usize,
+    max_planar: usize,
+}
+
+impl Extractor {
+    /// Creates a new extractor.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use velodyne::curvature::Extractor;
+    /// let extractor = Extractor::new();
+    /// ```
+    pub fn new() -> Extractor {
+        Extractor::default()
+    }
+
+    /// Sets the number of neighbors on each side of a point that its curvature is computed from.
+    ///
+    /// The default is five.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `neighbors` is zero.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use velodyne::curvature::Extractor;
+    /// let mut extractor = Extractor::new();
+    /// extractor.neighbors(3);
+    /// ```
+    pub fn neighbors(&mut self, neighbors: usize) -> &mut Extractor {
+        assert!(neighbors > 0, "curvature needs at least one neighbor");
+        self.neighbors = neighbors;
+        self
+    }
+
+    /// Sets the curvatures above which points can be edges, and below which they can be planar.
+    ///
+    /// Curvatures grow with the spacing of points along the ring, so the defaults of 0.002 and
+    /// 0.0005 suit a VLP-16's 0.2° resolution at 600 rpm.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use velodyne::curvature::Extractor;
+    /// let mut extractor = Extractor::new();
+    /// extractor.thresholds(0.2, 0.05);
+    /// ```
+    pub fn thresholds(&mut self, edge: f32, planar: f32) -> &mut Extractor {
+        self.edge_threshold = edge;
+        self.planar_threshold = planar;
+        self
+    }
+
+    /// Sets the number of sectors each ring is split into, and the most edge and planar features
+    /// selected in each.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `sectors` is zero.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use velodyne::curvature::Extractor;
+    /// let mut extractor = Extractor::new();
+    /// extractor.sectors(8, 4, 8);
+    /// ```
+    pub fn sectors(&mut self,
+                   sectors: usize,
+                   max_edges: usize,
+                   max_planar: usize)
+                   -> &mut Extractor {
+        assert!(sectors > 0, "rings need at least one sector");
+        self.sectors = sectors;
+        self.max_edges = max_edges;
+        self.max_planar = max_planar;
+        self
+    }
+
+    /// Scores and classifies points, e.g. a frame's, returning one feature per point.
+    ///
+    /// Rings are read in the points' order, which is the order they were fired in for frames.
+    /// Points without a return are skipped, and so are the secondary returns of dual-return
+    /// data, so each ring is a single line of returns.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use velodyne::curvature::Extractor;
+    /// use velodyne::fixtures::VLP_16_DATA_PACKET;
+    /// use velodyne::vlp_16::Packet;
+    /// let points = Packet::new(&VLP_16_DATA_PACKET).unwrap().points().unwrap();
+    /// assert_eq!(points.len(), Extractor::new().extract(&points).len());
+    /// ```
+    pub fn extract(&self, points: &[Point]) -> Vec<Feature> {
+        let mut features = vec![Feature { curvature: None, class: Class::Other }; points.len()];
+        let mut rings = vec![Vec::new(); MAX_LASERS];
+        for (index, point) in points.iter().enumerate() {
+            let secondary = matches!(point.return_type, ReturnType::Secondary);
+            if point.range() > 0. && !secondary && (point.channel as usize) < MAX_LASERS {
+                rings[point.channel as usize].push(index);
+            }
+        }
+        for ring in rings.iter().filter(|ring| ring.len() > 2 * self.neighbors) {
+            let scored = self.neighbors..ring.len() - self.neighbors;
+            let mut curvatures = vec![0.; ring.len()];
+            for k in scored.clone() {
+                curvatures[k] = self.curvature(points, ring, k);
+                features[ring[k]].curvature = Some(curvatures[k]);
+            }
+            let mut picked = vec![false; ring.len()];
+            for sector in 0..self.sectors {
+                let start = scored.start + scored.len() * sector / self.sectors;
+                let end = scored.start + scored.len() * (sector + 1) / self.sectors;
+                let mut sorted = (start..end).collect::<Vec<_>>();
+                sorted.sort_by(|&a, &b| curvatures[b].partial_cmp(&curvatures[a]).unwrap());
+                let edges = sorted.iter().filter(|&&k| curvatures[k] > self.edge_threshold);
+                for k in self.pick(&mut picked, edges, self.max_edges) {
+                    features[ring[k]].class = Class::Edge;
+                }
+                let planar = sorted.iter()
+                    .rev()
+                    .filter(|&&k| curvatures[k] < self.planar_threshold);
+                for k in self.pick(&mut picked, planar, self.max_planar) {
+                    features[ring[k]].class = Class::Planar;
+                }
+            }
+        }
+        features
+    }
+
+    fn curvature(&self, points: &[Point], ring: &[usize], k: usize) -> f32 {
+        let point = &points[ring[k]];
+        let mut sum = [0.; 3];
+        for &index in &ring[k - self.neighbors..k + self.neighbors + 1] {
+            let other = &points[index];
+            sum[0] += other.x - point.x;
+            sum[1] += other.y - point.y;
+            sum[2] += other.z - point.z;
+        }
+        let norm = (sum[0].powi(2) + sum[1].powi(2) + sum[2].powi(2)).sqrt();
+        norm / (2 * self.neighbors) as f32 / point.range()
+    }
+
+    /// Picks up to `max` candidates that haven't been picked, and marks their neighbors as
+    /// picked.
+    fn pick<'a, I>(&self, picked: &mut [bool], candidates: I, max: usize) -> Vec<usize>
+        where I: Iterator<Item = &'a usize>
+    {
+        let mut picks = Vec::new();
+        for &k in candidates {
+            if picks.len() == max {
+                break;
+            }
+            if picked[k] {
+                continue;
+            }
+            picks.push(k);
+            let start = k.saturating_sub(self.neighbors);
+            let end = (k + self.neighbors + 1).min(picked.len());
+            for picked in &mut picked[start..end] {
+                *picked = true;
+            }
+        }
+        picks
+    }
+}
+
+impl Default for Extractor {
+    fn default() -> Extractor {
+        Extractor {
+            neighbors: DEFAULT_NEIGHBORS,
+            edge_threshold: DEFAULT_EDGE_THRESHOLD,
+            planar_threshold: DEFAULT_PLANAR_THRESHOLD,
+            sectors: DEFAULT_SECTORS,
+            max_edges: DEFAULT_MAX_EDGES,
+            max_planar: DEFAULT_MAX_PLANAR,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::Duration;
+    use point::{Azimuth, Time};
+    use units::{Degrees, Meters};
+
+    /// One ring around the corner of a room, with walls at x = 5 and y = 5.
+    fn corner() -> Vec<Point> {
+        (1..90)
+            .map(|step| {
+                     let azimuth = step as f32;
+                     let radians = azimuth.to_radians();
+                     let range = (5. / radians.sin()).min(5. / radians.cos());
+                     Point::from_spherical(Meters(range),
+                                           Azimuth::Measured(Degrees(azimuth)),
+                                           Degrees(0.),
+                                           0,
+                                           0,
+                                           ReturnType::Strongest,
+                                           Time::Offset(Duration::zero()))
+                 })
+            .collect()
+    }
+
+    #[test]
+    fn corner_is_an_edge() {
+        let points = corner();
+        let mut extractor = Extractor::new();
+        // The synthetic ring is much coarser than a real one.
+        extractor.thresholds(0.01, 0.005).sectors(1, 1, 4);
+        let features = extractor.extract(&points);
+        assert_eq!(None, features[0].curvature);
+        assert_eq!(None, features[88].curvature);
+        let corner = 44;
+        assert_eq!(45., points[corner].azimuth.degrees());
+        assert_eq!(Class::Edge, features[corner].class);
+        assert!(features[corner].curvature.unwrap() > 0.03);
+        assert!(features[20].curvature.unwrap() < 0.005);
+        let planar = features.iter().filter(|feature| feature.class == Class::Planar).count();
+        assert_eq!(4, planar);
+        let edges = features.iter().filter(|feature| feature.class == Class::Edge).count();
+        assert_eq!(1, edges);
+    }
+}
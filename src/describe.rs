@@ -0,0 +1,246 @@
+//! Work out what a stream of packets is from its first few packets.
+//!
+//! Applications that open whatever capture or socket they are handed, e.g. a viewer, shouldn't
+//! have to ask which sensor sent it or how it was configured. `describe` reads a handful of
+//! packets and returns a `StreamDescription`, which can then configure a `Decoder`.
+//!
+//! ```
+//! use velodyne::describe;
+//! use velodyne::io::Pcap;
+//! use velodyne::vlp_16::Sensor;
+//! let description = describe::describe(Pcap::open("data/single.pcap").unwrap()).unwrap();
+//! assert_eq!(Some(Sensor::VLP_16), description.sensor);
+//! let decoder = description.decoder();
+//! ```
+
+use Result;
+use byteorder::{BigEndian, ByteOrder};
+use info::Scan;
+use io::Read;
+use std::net::Ipv4Addr;
+use vlp_16::{self, Decoder, Packet, PacketKind, ReturnMode, Sensor};
+
+/// The most packets that `describe` reads.
+pub const DEFAULT_PACKET_LIMIT: usize = 1000;
+
+/// The number of data packets after which `describe` stops early, once it has seen a position
+/// packet too.
+const ENOUGH_DATA_PACKETS: usize = 100;
+
+/// The length of an Ethernet header.
+const ETHERNET_HEADER_LEN: usize = 14;
+
+/// What a stream of packets is, as far as its first few packets tell.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct StreamDescription {
+    /// The sensor model, as reported by the factory byte of the first data packet.
+    pub sensor: Option<Sensor>,
+    /// The return mode of the first data packet.
+    pub return_mode: Option<ReturnMode>,
+    /// The rotation rate, in revolutions per minute, if two consecutive data packets were read.
+    pub rpm: Option<f64>,
+    /// The addresses and ports that the Velodyne packets were sent from and to, in the order they
+    /// were first seen. Empty if the packets don't have network headers.
+    pub endpoints: Vec<Endpoint>,
+    /// Whether a position packet had an NMEA sentence, i.e. a GNSS receiver is connected.
+    pub has_gnss: bool,
+    /// The number of packets that were read.
+    pub packets: usize,
+    /// The number of data packets that were read.
+    pub data_packets: usize,
+    /// The number of position packets that were read.
+    pub position_packets: usize,
+}
+
+/// A source address and destination port that Velodyne packets were seen on.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Endpoint {
+    /// The address the packets came from.
+    pub source: Ipv4Addr,
+    /// The UDP port the packets were sent to.
+    pub port: u16,
+    /// The number of packets.
+    pub packets: usize,
+}
+
+/// Reads up to `DEFAULT_PACKET_LIMIT` packets and describes the stream.
+///
+/// The reader is left wherever describing stopped, so open the source again, or keep the packets
+/// some other way, to decode it from the start.
+///
+/// # Examples
+///
+/// ```
+/// use velodyne::describe::describe;
+/// use velodyne::io::Pcap;
+/// let description = describe(Pcap::open("data/single.pcap").unwrap()).unwrap();
+/// assert_eq!(1, description.data_packets);
+/// ```
+pub fn describe<R: Read>(read: R) -> Result<StreamDescription> {
+    describe_packets(read, DEFAULT_PACKET_LIMIT)
+}
+
+/// Reads up to `limit` packets and describes the stream.
+///
+/// Reading stops early once there have been enough data packets to estimate the rotation rate
+/// and at least one position packet.
+///
+/// # Examples
+///
+/// ```
+/// use velodyne::describe::describe_packets;
+/// use velodyne::io::Pcap;
+/// let description = describe_packets(Pcap::open("data/position.pcap").unwrap(), 1).unwrap();
+/// assert_eq!(1, description.packets);
+/// ```
+pub fn describe_packets<R: Read>(mut read: R, limit: usize) -> Result<StreamDescription> {
+    let mut description = StreamDescription::default();
+    let mut scan = Scan::default();
+    while description.packets < limit {
+        let bytes = match read.read() {
+            Some(result) => result?,
+            None => break,
+        };
+        description.packets += 1;
+        let kind = match vlp_16::classify(bytes) {
+            Some(kind) => kind,
+            None => continue,
+        };
+        scan.add(bytes);
+        if let Some((source, port)) = endpoint(bytes) {
+            description.add_endpoint(source, port);
+        }
+        if kind == PacketKind::Position && !description.has_gnss {
+            description.has_gnss = Packet::new(bytes)
+                .ok()
+                .and_then(|packet| packet.nmea().map(has_sentence))
+                .unwrap_or(false);
+        }
+        if scan.data_packets >= ENOUGH_DATA_PACKETS && scan.position_packets > 0 {
+            break;
+        }
+    }
+    description.sensor = scan.sensor;
+    description.return_mode = scan.return_mode;
+    description.rpm = scan.rpm();
+    description.data_packets = scan.data_packets;
+    description.position_packets = scan.position_packets;
+    Ok(description)
+}
+
+impl StreamDescription {
+    /// Returns a decoder that is configured for this stream.
+    ///
+    /// The return mode is set from the stream, so that packets whose data blocks don't match it
+    /// are decoded the same way as the rest.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use velodyne::describe::StreamDescription;
+    /// let decoder = StreamDescription::default().decoder();
+    /// ```
+    pub fn decoder(&self) -> Decoder {
+        let mut decoder = Decoder::new();
+        if let Some(return_mode) = self.return_mode {
+            decoder.return_mode(return_mode);
+        }
+        decoder
+    }
+
+    /// Returns true if any Velodyne packets were read.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use velodyne::describe::StreamDescription;
+    /// assert!(!StreamDescription::default().is_velodyne());
+    /// ```
+    pub fn is_velodyne(&self) -> bool {
+        self.data_packets + self.position_packets > 0
+    }
+
+    fn add_endpoint(&mut self, source: Ipv4Addr, port: u16) {
+        match self.endpoints.iter_mut().find(|e| e.source == source && e.port == port) {
+            Some(endpoint) => endpoint.packets += 1,
+            None => {
+                self.endpoints.push(Endpoint {
+                                        source,
+                                        port,
+                                        packets: 1,
+                                    })
+            }
+        }
+    }
+}
+
+/// Returns the source address and destination port of an Ethernet frame with a UDP datagram.
+fn endpoint(bytes: &[u8]) -> Option<(Ipv4Addr, u16)> {
+    if vlp_16::payload(bytes).len() == bytes.len() || bytes.get(12..14)? != [0x08, 0x00] {
+        return None;
+    }
+    let ip = &bytes[ETHERNET_HEADER_LEN..];
+    let ip_len = usize::from(ip.first()? & 0x0f) * 4;
+    if ip_len < 20 || ip.len() < ip_len + 4 || ip[9] != 17 {
+        return None;
+    }
+    let source = Ipv4Addr::new(ip[12], ip[13], ip[14], ip[15]);
+    Some((source, BigEndian::read_u16(&ip[ip_len + 2..])))
+}
+
+/// Returns true if an NMEA field has more than padding in it.
+fn has_sentence(nmea: &str) -> bool {
+    !nmea.trim_matches(|c: char| c == '\0' || c.is_whitespace()).is_empty()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use byteorder::LittleEndian;
+    use chrono::UTC;
+    use fixtures::{VLP_16_DATA_PACKET, VLP_16_POSITION_PACKET};
+    use io::{DATA_PORT, POSITION_PORT, PcapSlice, PcapWriter};
+    use vlp_16::PACKET_HEADER_LEN;
+
+    #[test]
+    fn stops_after_a_position_packet() {
+        let mut writer = PcapWriter::new(Vec::new()).unwrap();
+        writer.write(&[0; 64], UTC::now()).unwrap();
+        // 600 RPM turns about 477.7 centidegrees from one packet to the next.
+        for i in 0..300u32 {
+            let mut bytes = VLP_16_DATA_PACKET.to_vec();
+            LittleEndian::write_u16(&mut bytes[44..], (i * 478 % 36_000) as u16);
+            LittleEndian::write_u32(&mut bytes[1242..], i * 1327);
+            writer.write(&bytes[PACKET_HEADER_LEN..], UTC::now()).unwrap();
+            if i == 150 {
+                writer.write(&VLP_16_POSITION_PACKET[PACKET_HEADER_LEN..], UTC::now()).unwrap();
+            }
+        }
+        let pcap = writer.into_inner();
+        let description = describe(PcapSlice::new(&pcap[..]).unwrap()).unwrap();
+        assert_eq!(153, description.packets);
+        assert_eq!(151, description.data_packets);
+        assert_eq!(1, description.position_packets);
+        assert_eq!(Some(Sensor::VLP_16), description.sensor);
+        assert_eq!(Some(ReturnMode::StrongestReturn), description.return_mode);
+        let rpm = description.rpm.unwrap();
+        assert!((rpm - 600.).abs() < 1., "{}", rpm);
+        assert!(description.has_gnss);
+        let source = Ipv4Addr::new(192, 168, 1, 201);
+        assert_eq!(vec![Endpoint {
+                            source,
+                            port: DATA_PORT,
+                            packets: 151,
+                        },
+                        Endpoint {
+                            source,
+                            port: POSITION_PORT,
+                            packets: 1,
+                        }],
+                   description.endpoints);
+        assert!(description.is_velodyne());
+
+        let description = describe_packets(PcapSlice::new(&pcap[..]).unwrap(), 1).unwrap();
+        assert!(!description.is_velodyne());
+    }
+}
@@ -0,0 +1,190 @@
+//! Find sensors on the local network.
+//!
+//! Sensors broadcast their data and position packets, so listening on the standard ports for a
+//! few seconds is enough to tell which sensors are out there and how they are configured.
+
+use Result;
+use io::{DATA_PORT, POSITION_PORT};
+use std::collections::BTreeMap;
+use std::io::ErrorKind;
+use std::net::{IpAddr, Ipv4Addr, SocketAddr, ToSocketAddrs, UdpSocket};
+use std::time::{Duration, Instant};
+use vlp_16::{DataPacket, Packet, ReturnMode, Sensor};
+
+/// How long to block on each socket before checking the other one.
+const POLL_INTERVAL_MS: u64 = 10;
+
+/// Listens for sensors on a data and a position port.
+#[derive(Debug)]
+pub struct Listener {
+    data: UdpSocket,
+    position: UdpSocket,
+}
+
+/// A sensor that was heard from during discovery.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Detected {
+    /// The address the sensor's packets came from.
+    pub address: IpAddr,
+    /// The sensor model, as reported by the factory byte of the last valid data packet.
+    pub sensor: Option<Sensor>,
+    /// The return mode of the last valid data packet.
+    pub return_mode: Option<ReturnMode>,
+    /// The number of data packets received.
+    pub data_packets: usize,
+    /// The number of position packets received.
+    pub position_packets: usize,
+    /// The number of packets that couldn't be decoded.
+    pub invalid_packets: usize,
+    /// The number of data packets received per second.
+    pub data_packet_rate: f64,
+}
+
+/// Listens on the standard ports for the given duration and returns the sensors that were heard.
+///
+/// # Examples
+///
+/// ```no_run
+/// use std::time::Duration;
+/// let sensors = velodyne::discovery::discover(Duration::from_secs(3)).unwrap();
+/// for sensor in sensors {
+///     println!("{} {:?}", sensor.address, sensor.sensor);
+/// }
+/// ```
+pub fn discover(duration: Duration) -> Result<Vec<Detected>> {
+    Listener::bind_default()?.listen(duration)
+}
+
+impl Listener {
+    /// Binds to the standard data and position ports on all interfaces.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// use velodyne::discovery::Listener;
+    /// let listener = Listener::bind_default().unwrap();
+    /// ```
+    pub fn bind_default() -> Result<Listener> {
+        let any = IpAddr::V4(Ipv4Addr::new(0, 0, 0, 0));
+        Listener::bind((any, DATA_PORT), (any, POSITION_PORT))
+    }
+
+    /// Binds to the given data and position addresses.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use velodyne::discovery::Listener;
+    /// let listener = Listener::bind("127.0.0.1:0", "127.0.0.1:0").unwrap();
+    /// ```
+    pub fn bind<A: ToSocketAddrs, B: ToSocketAddrs>(data: A, position: B) -> Result<Listener> {
+        let data = UdpSocket::bind(data)?;
+        let position = UdpSocket::bind(position)?;
+        let poll_interval = Some(Duration::from_millis(POLL_INTERVAL_MS));
+        data.set_read_timeout(poll_interval)?;
+        position.set_read_timeout(poll_interval)?;
+        Ok(Listener { data, position })
+    }
+
+    /// Returns the local address of the data socket.
+    pub fn data_addr(&self) -> Result<SocketAddr> {
+        self.data.local_addr().map_err(|err| err.into())
+    }
+
+    /// Returns the local address of the position socket.
+    pub fn position_addr(&self) -> Result<SocketAddr> {
+        self.position.local_addr().map_err(|err| err.into())
+    }
+
+    /// Listens for the given duration and returns the sensors that were heard, ordered by address.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use velodyne::discovery::Listener;
+    /// use std::time::Duration;
+    /// let listener = Listener::bind("127.0.0.1:0", "127.0.0.1:0").unwrap();
+    /// let sensors = listener.listen(Duration::from_millis(10)).unwrap();
+    /// assert!(sensors.is_empty());
+    /// ```
+    pub fn listen(&self, duration: Duration) -> Result<Vec<Detected>> {
+        let mut detected = BTreeMap::new();
+        let mut buffer = [0; 2048];
+        let start = Instant::now();
+        while start.elapsed() < duration {
+            for socket in &[&self.data, &self.position] {
+                let (n, address) = match socket.recv_from(&mut buffer) {
+                    Ok(received) => received,
+                    Err(ref err) if err.kind() == ErrorKind::WouldBlock ||
+                                    err.kind() == ErrorKind::TimedOut => continue,
+                    Err(err) => return Err(err.into()),
+                };
+                detected.entry(address.ip())
+                    .or_insert_with(|| Detected::new(address.ip()))
+                    .add(&buffer[..n]);
+            }
+        }
+        let seconds = start.elapsed().as_secs_f64();
+        Ok(detected.into_values()
+               .map(|mut detected| {
+                        detected.data_packet_rate = detected.data_packets as f64 / seconds;
+                        detected
+                    })
+               .collect())
+    }
+}
+
+impl Detected {
+    fn new(address: IpAddr) -> Detected {
+        Detected {
+            address,
+            sensor: None,
+            return_mode: None,
+            data_packets: 0,
+            position_packets: 0,
+            invalid_packets: 0,
+            data_packet_rate: 0.,
+        }
+    }
+
+    fn add(&mut self, bytes: &[u8]) {
+        match Packet::new(bytes) {
+            Ok(Packet::Position(_)) => self.position_packets += 1,
+            Ok(Packet::Data(DataPacket { sensor, return_mode, .. })) => {
+                self.data_packets += 1;
+                self.sensor = Some(sensor);
+                self.return_mode = Some(return_mode);
+            }
+            Err(err) => {
+                debug!("invalid packet from {}: {:?}", self.address, err);
+                self.invalid_packets += 1;
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use fixtures::{VLP_16_DATA_PACKET, VLP_16_POSITION_PACKET};
+
+    #[test]
+    fn detect() {
+        let listener = Listener::bind("127.0.0.1:0", "127.0.0.1:0").unwrap();
+        let sender = UdpSocket::bind("127.0.0.1:0").unwrap();
+        for _ in 0..3 {
+            sender.send_to(&VLP_16_DATA_PACKET[42..], listener.data_addr().unwrap()).unwrap();
+        }
+        sender.send_to(&VLP_16_POSITION_PACKET[42..], listener.position_addr().unwrap())
+            .unwrap();
+        let detected = listener.listen(Duration::from_millis(100)).unwrap();
+        assert_eq!(1, detected.len());
+        let detected = detected[0];
+        assert_eq!(IpAddr::V4(Ipv4Addr::new(127, 0, 0, 1)), detected.address);
+        assert_eq!(Some(Sensor::VLP_16), detected.sensor);
+        assert_eq!(Some(ReturnMode::StrongestReturn), detected.return_mode);
+        assert_eq!(3, detected.data_packets);
+        assert_eq!(1, detected.position_packets);
+        assert!(detected.data_packet_rate > 0.);
+    }
+}
@@ -0,0 +1,408 @@
+//! A compact binary format for caching decoded frames.
+//!
+//! Re-reading a dump is much faster than re-decoding the packets it came from, so pipeline stages
+//! can cache their input. A dump is a sequence of frames, each of which is a 16 byte header
+//! followed by packed point records:
+//!
+//! | Bytes | Contents                                                 |
+//! |-------|----------------------------------------------------------|
+//! | 0-3   | The magic bytes `VLDF`                                   |
+//! | 4     | The format version, currently 1                          |
+//! | 5     | Flags; see below                                         |
+//! | 6-7   | Reserved                                                 |
+//! | 8-11  | The number of points                                     |
+//! | 12-15 | The length of the (possibly compressed) records          |
+//!
+//! Bit zero of the flags is set if the records are LZ4-compressed, bit one is set if the frame is
+//! complete, and bit two is set if the records are Zstandard-compressed. All numbers are
+//! little-endian. Compression requires the `lz4` or `zstd` feature.
+//!
+//! Each frame is compressed on its own, so dumps can be written and read as a stream. LZ4 is
+//! fast enough to keep up with a live sensor, and Zstandard makes smaller dumps.
+
+use {Error, Result};
+use byteorder::{ByteOrder, LittleEndian};
+use chrono::{Duration, TimeZone, UTC};
+use frame::Frame;
+#[cfg(feature = "lz4")]
+use lz4_flex;
+use point::{Azimuth, Point, ReturnType, Time};
+use sink::PointSink;
+use std::io;
+use units::Degrees;
+#[cfg(feature = "zstd")]
+use zstd;
+
+const MAGIC: &[u8; 4] = b"VLDF";
+const VERSION: u8 = 1;
+const HEADER_LEN: usize = 16;
+const RECORD_LEN: usize = 29;
+const LZ4_FLAG: u8 = 1;
+const COMPLETE_FLAG: u8 = 2;
+const ZSTD_FLAG: u8 = 4;
+
+/// Writes frames to a dump.
+#[derive(Debug)]
+pub struct Writer<W: io::Write> {
+    write: W,
+    compression: Compression,
+    buffer: Vec<u8>,
+}
+
+/// How point records are compressed.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Compression {
+    /// Records aren't compressed.
+    None,
+    /// LZ4 compression.
+    #[cfg(feature = "lz4")]
+    Lz4,
+    /// Zstandard compression at a level, e.g. 3, which is Zstandard's default.
+    #[cfg(feature = "zstd")]
+    Zstd(i32),
+}
+
+/// Reads frames from a dump.
+#[derive(Debug)]
+pub struct Reader<R: io::Read> {
+    read: R,
+    buffer: Vec<u8>,
+}
+
+impl<W: io::Write> Writer<W> {
+    /// Creates a new, uncompressed writer.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use velodyne::dump::Writer;
+    /// let writer = Writer::new(Vec::new());
+    /// ```
+    pub fn new(write: W) -> Writer<W> {
+        Writer {
+            write,
+            compression: Compression::None,
+            buffer: Vec::new(),
+        }
+    }
+
+    /// Sets whether point records are LZ4-compressed.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use velodyne::dump::Writer;
+    /// let mut writer = Writer::new(Vec::new());
+    /// writer.compress(true);
+    /// ```
+    #[cfg(feature = "lz4")]
+    pub fn compress(&mut self, compress: bool) -> &mut Writer<W> {
+        self.compression = if compress { Compression::Lz4 } else { Compression::None };
+        self
+    }
+
+    /// Sets how point records are compressed.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use velodyne::dump::{Compression, Writer};
+    /// let mut writer = Writer::new(Vec::new());
+    /// writer.compression(Compression::None);
+    /// ```
+    pub fn compression(&mut self, compression: Compression) -> &mut Writer<W> {
+        self.compression = compression;
+        self
+    }
+
+    /// Writes a frame.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use velodyne::dump::Writer;
+    /// use velodyne::frame::Frame;
+    /// let mut writer = Writer::new(Vec::new());
+    /// writer.write(&Frame::default()).unwrap();
+    /// ```
+    pub fn write(&mut self, frame: &Frame) -> Result<()> {
+        self.buffer.clear();
+        self.buffer.resize(frame.points.len() * RECORD_LEN, 0);
+        for (point, record) in frame.points.iter().zip(self.buffer.chunks_mut(RECORD_LEN)) {
+            write_record(point, record);
+        }
+        let mut header = [0; HEADER_LEN];
+        header[0..4].copy_from_slice(MAGIC);
+        header[4] = VERSION;
+        if frame.complete {
+            header[5] |= COMPLETE_FLAG;
+        }
+        LittleEndian::write_u32(&mut header[8..], frame.points.len() as u32);
+        match self.compression {
+            Compression::None => {}
+            #[cfg(feature = "lz4")]
+            Compression::Lz4 => {
+                self.buffer = lz4_flex::compress(&self.buffer);
+                header[5] |= LZ4_FLAG;
+            }
+            #[cfg(feature = "zstd")]
+            Compression::Zstd(level) => {
+                self.buffer = zstd::bulk::compress(&self.buffer, level)?;
+                header[5] |= ZSTD_FLAG;
+            }
+        }
+        LittleEndian::write_u32(&mut header[12..], self.buffer.len() as u32);
+        self.write.write_all(&header)?;
+        self.write.write_all(&self.buffer)?;
+        Ok(())
+    }
+
+    /// Returns the underlying writer.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use velodyne::dump::Writer;
+    /// let bytes = Writer::new(Vec::new()).into_inner();
+    /// ```
+    pub fn into_inner(self) -> W {
+        self.write
+    }
+}
+
+/// Each frame is written as is, and each batch of points as an incomplete frame.
+impl<W: io::Write> PointSink for Writer<W> {
+    fn write_points(&mut self, points: &[Point]) -> Result<()> {
+        self.write(&Frame::new(points.to_vec()))
+    }
+
+    fn write_frame(&mut self, frame: &Frame) -> Result<()> {
+        self.write(frame)
+    }
+
+    fn finish(&mut self) -> Result<()> {
+        self.write.flush().map_err(Into::into)
+    }
+}
+
+impl<R: io::Read> Reader<R> {
+    /// Creates a new reader.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use velodyne::dump::Reader;
+    /// let reader = Reader::new(&[][..]);
+    /// ```
+    pub fn new(read: R) -> Reader<R> {
+        Reader {
+            read,
+            buffer: Vec::new(),
+        }
+    }
+
+    /// Reads the next frame, returning `None` at the end of the dump.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use velodyne::dump::{Reader, Writer};
+    /// use velodyne::frame::Frame;
+    /// let mut writer = Writer::new(Vec::new());
+    /// writer.write(&Frame::default()).unwrap();
+    /// let bytes = writer.into_inner();
+    /// let mut reader = Reader::new(&bytes[..]);
+    /// assert!(reader.read().unwrap().unwrap().points.is_empty());
+    /// assert!(reader.read().unwrap().is_none());
+    /// ```
+    pub fn read(&mut self) -> Result<Option<Frame>> {
+        let mut header = [0; HEADER_LEN];
+        let mut len = 0;
+        while len < HEADER_LEN {
+            match self.read.read(&mut header[len..])? {
+                0 if len == 0 => return Ok(None),
+                0 => return Err(io::Error::from(io::ErrorKind::UnexpectedEof).into()),
+                n => len += n,
+            }
+        }
+        if &header[0..4] != MAGIC {
+            return Err(Error::FrameDump(format!("invalid magic bytes: {:?}", &header[0..4])));
+        }
+        if header[4] != VERSION {
+            return Err(Error::FrameDump(format!("unsupported version: {}", header[4])));
+        }
+        let npoints = LittleEndian::read_u32(&header[8..]) as usize;
+        self.buffer.resize(LittleEndian::read_u32(&header[12..]) as usize, 0);
+        self.read.read_exact(&mut self.buffer)?;
+        if header[5] & LZ4_FLAG != 0 {
+            self.buffer = decompress_lz4(&self.buffer, npoints * RECORD_LEN)?;
+        } else if header[5] & ZSTD_FLAG != 0 {
+            self.buffer = decompress_zstd(&self.buffer, npoints * RECORD_LEN)?;
+        }
+        if self.buffer.len() != npoints * RECORD_LEN {
+            return Err(Error::FrameDump(format!("expected {} bytes of records, found {}",
+                                                npoints * RECORD_LEN,
+                                                self.buffer.len())));
+        }
+        let points = self.buffer.chunks(RECORD_LEN).map(read_record).collect::<Result<_>>()?;
+        Ok(Some(Frame {
+                    points,
+                    complete: header[5] & COMPLETE_FLAG != 0,
+                }))
+    }
+}
+
+impl<R: io::Read> Iterator for Reader<R> {
+    type Item = Result<Frame>;
+    fn next(&mut self) -> Option<Result<Frame>> {
+        match self.read() {
+            Ok(frame) => frame.map(Ok),
+            Err(err) => Some(Err(err)),
+        }
+    }
+}
+
+#[cfg(feature = "lz4")]
+fn decompress_lz4(bytes: &[u8], len: usize) -> Result<Vec<u8>> {
+    lz4_flex::decompress(bytes, len).map_err(|err| Error::FrameDump(err.to_string()))
+}
+
+#[cfg(not(feature = "lz4"))]
+fn decompress_lz4(_: &[u8], _: usize) -> Result<Vec<u8>> {
+    Err(Error::FrameDump("LZ4-compressed frames require the lz4 feature".to_string()))
+}
+
+#[cfg(feature = "zstd")]
+fn decompress_zstd(bytes: &[u8], len: usize) -> Result<Vec<u8>> {
+    zstd::bulk::decompress(bytes, len).map_err(|err| Error::FrameDump(err.to_string()))
+}
+
+#[cfg(not(feature = "zstd"))]
+fn decompress_zstd(_: &[u8], _: usize) -> Result<Vec<u8>> {
+    Err(Error::FrameDump("Zstandard-compressed frames require the zstd feature".to_string()))
+}
+
+fn write_record(point: &Point, record: &mut [u8]) {
+    LittleEndian::write_f32(&mut record[0..], point.x);
+    LittleEndian::write_f32(&mut record[4..], point.y);
+    LittleEndian::write_f32(&mut record[8..], point.z);
+    record[12] = point.reflectivity;
+    record[13] = point.channel;
+    // The firing sequence is the top bit of the return type, which older dumps leave clear.
+    record[14] = match point.return_type {
+        ReturnType::Strongest => 0,
+        ReturnType::Last => 1,
+        ReturnType::Secondary => 2,
+    } | point.sequence << 7;
+    let (kind, degrees) = match point.azimuth {
+        Azimuth::Measured(degrees) => (0, degrees),
+        Azimuth::Interpolated(degrees) => (1, degrees),
+        Azimuth::Extrapolated(degrees) => (2, degrees),
+    };
+    record[15] = kind;
+    LittleEndian::write_f32(&mut record[16..], degrees.0);
+    let (kind, nanoseconds) = match point.time {
+        Time::Offset(offset) => (0, offset.num_nanoseconds().unwrap_or(0)),
+        Time::Absolute(time) => {
+            (1, time.timestamp() * 1_000_000_000 + i64::from(time.timestamp_subsec_nanos()))
+        }
+    };
+    record[20] = kind;
+    LittleEndian::write_i64(&mut record[21..], nanoseconds);
+}
+
+fn read_record(record: &[u8]) -> Result<Point> {
+    let invalid = |what| Error::FrameDump(format!("invalid {}", what));
+    let degrees = Degrees(LittleEndian::read_f32(&record[16..]));
+    let nanoseconds = LittleEndian::read_i64(&record[21..]);
+    Ok(Point {
+           x: LittleEndian::read_f32(&record[0..]),
+           y: LittleEndian::read_f32(&record[4..]),
+           z: LittleEndian::read_f32(&record[8..]),
+           reflectivity: record[12],
+           channel: record[13],
+           sequence: record[14] >> 7,
+           return_type: match record[14] & 0x7f {
+               0 => ReturnType::Strongest,
+               1 => ReturnType::Last,
+               2 => ReturnType::Secondary,
+               _ => return Err(invalid("return type")),
+           },
+           azimuth: match record[15] {
+               0 => Azimuth::Measured(degrees),
+               1 => Azimuth::Interpolated(degrees),
+               2 => Azimuth::Extrapolated(degrees),
+               _ => return Err(invalid("azimuth")),
+           },
+           time: match record[20] {
+               0 => Time::Offset(Duration::nanoseconds(nanoseconds)),
+               1 => {
+                   Time::Absolute(UTC.timestamp(nanoseconds.div_euclid(1_000_000_000),
+                                                nanoseconds.rem_euclid(1_000_000_000) as u32))
+               }
+               _ => return Err(invalid("time")),
+           },
+           sensor_id: None,
+       })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use fixtures::VLP_16_DATA_PACKET;
+    use vlp_16::Packet;
+
+    fn round_trip(compression: Compression) {
+        let mut frame = Frame::new(Packet::new(&VLP_16_DATA_PACKET).unwrap().points().unwrap());
+        frame.complete = true;
+        let mut writer = Writer::new(Vec::new());
+        writer.compression(compression);
+        writer.write(&frame).unwrap();
+        writer.write(&frame).unwrap();
+        let bytes = writer.into_inner();
+        let frames = Reader::new(&bytes[..]).collect::<Result<Vec<_>>>().unwrap();
+        assert_eq!(2, frames.len());
+        assert!(frames[1].complete);
+        for (expected, actual) in frame.points.iter().zip(&frames[1].points) {
+            assert_eq!(format!("{:?}", expected), format!("{:?}", actual));
+        }
+    }
+
+    #[test]
+    fn uncompressed() {
+        round_trip(Compression::None);
+    }
+
+    #[test]
+    #[cfg(feature = "lz4")]
+    fn compressed() {
+        round_trip(Compression::Lz4);
+    }
+
+    #[test]
+    #[cfg(feature = "zstd")]
+    fn zstd() {
+        round_trip(Compression::Zstd(3));
+    }
+
+    #[test]
+    fn absolute_time() {
+        let mut point = Packet::new(&VLP_16_DATA_PACKET).unwrap().points().unwrap()[0];
+        point.time = Time::Absolute(UTC.ymd(2017, 3, 1).and_hms_micro(12, 0, 0, 123_456));
+        let mut record = [0; RECORD_LEN];
+        write_record(&point, &mut record);
+        assert_eq!(format!("{:?}", point), format!("{:?}", read_record(&record).unwrap()));
+    }
+
+    #[test]
+    fn invalid_magic_bytes() {
+        let mut reader = Reader::new(&[0; HEADER_LEN][..]);
+        assert!(reader.read().is_err());
+    }
+
+    #[test]
+    fn truncated_header() {
+        let mut reader = Reader::new(&MAGIC[..]);
+        assert!(reader.read().is_err());
+    }
+}
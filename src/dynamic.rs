@@ -0,0 +1,225 @@
+//! Flag the points that moved since the previous frame.
+//!
+//! This differences the organized range images of consecutive frames: a cell whose range changed
+//! by more than a threshold is dynamic, e.g. a passing vehicle or pedestrian. It's cheap and works
+//! well for a stationary sensor, but the range images are in the sensor's frame, so everything
+//! looks dynamic while the sensor itself moves or turns.
+//!
+//! ```
+//! use velodyne::dynamic::{Detector, Label};
+//! let frames = velodyne::read_pcap_frames("data/single.pcap", &Default::default()).unwrap();
+//! let mut detector = Detector::new();
+//! let labels = detector.push(&frames[0].points);
+//! assert!(labels.iter().all(|&label| label != Label::Dynamic)); // Nothing to compare with yet.
+//! ```
+
+use Point;
+use range_image::RangeImage;
+use units::Meters;
+
+const DEFAULT_THRESHOLD: f32 = 0.5;
+const DEFAULT_COLUMNS: usize = 1800;
+
+/// What a point was labeled as.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Label {
+    /// The point's range is about the same as in the previous frame.
+    Static,
+    /// The point's range changed by more than the threshold since the previous frame.
+    Dynamic,
+    /// The previous frame had no return where this point is, so it can't be compared.
+    Unknown,
+    /// The point has no return, so it can't be labeled.
+    NoReturn,
+}
+
+/// Labels the points of consecutive frames as static or dynamic.
+#[derive(Clone, Debug)]
+pub struct Detector {
+    threshold: f32,
+    columns: usize,
+    previous: Option<RangeImage>,
+}
+
+impl Detector {
+    /// Creates a new detector without a previous frame.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use velodyne::dynamic::Detector;
+    /// let detector = Detector::new();
+    /// ```
+    pub fn new() -> Detector {
+        Detector::default()
+    }
+
+    /// Sets how far a cell's range has to change for its points to be dynamic.
+    ///
+    /// The default is half a meter.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use velodyne::dynamic::Detector;
+    /// use velodyne::units::Meters;
+    /// let mut detector = Detector::new();
+    /// detector.threshold(Meters(0.2));
+    /// ```
+    pub fn threshold(&mut self, threshold: Meters) -> &mut Detector {
+        self.threshold = threshold.0;
+        self
+    }
+
+    /// Sets the number of azimuth bins of the range images.
+    ///
+    /// Changing the number of columns forgets the previous frame. The default is 1800, i.e. 0.2°
+    /// bins.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `columns` is zero.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use velodyne::dynamic::Detector;
+    /// let mut detector = Detector::new();
+    /// detector.columns(3600);
+    /// ```
+    pub fn columns(&mut self, columns: usize) -> &mut Detector {
+        assert!(columns > 0, "a range image needs at least one column");
+        if columns != self.columns {
+            self.previous = None;
+        }
+        self.columns = columns;
+        self
+    }
+
+    /// Labels a frame's points against the previous frame, returning one label per point, and
+    /// keeps the frame to compare the next one with.
+    ///
+    /// Points share the label of their range image cell, so all returns in a cell are labeled
+    /// alike. Every point of the first frame is `Unknown` or `NoReturn`, as is every point from a
+    /// laser that the range image has no row for, e.g. the HDL-32E's upper channels.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use velodyne::dynamic::{Detector, Label};
+    /// use velodyne::fixtures::VLP_16_DATA_PACKET;
+    /// use velodyne::vlp_16::Packet;
+    /// let points = Packet::new(&VLP_16_DATA_PACKET).unwrap().points().unwrap();
+    /// let mut detector = Detector::new();
+    /// detector.push(&points);
+    /// let labels = detector.push(&points);
+    /// assert!(labels.iter().all(|&label| label == Label::Static || label == Label::NoReturn));
+    /// ```
+    pub fn push(&mut self, points: &[Point]) -> Vec<Label> {
+        let range_image = RangeImage::from_points(points, self.columns);
+        let labels = points.iter()
+            .map(|point| {
+                if point.range() == 0. {
+                    return Label::NoReturn;
+                }
+                let row = match RangeImage::point_row(point) {
+                    Some(row) => row,
+                    None => return Label::Unknown,
+                };
+                let column = range_image.column(point.azimuth.degrees());
+                let previous = self.previous
+                    .as_ref()
+                    .and_then(|previous| previous.get(row, column));
+                match (range_image.get(row, column), previous) {
+                    (Some(cell), Some(previous)) => {
+                        if (cell.range - previous.range).abs() > self.threshold {
+                            Label::Dynamic
+                        } else {
+                            Label::Static
+                        }
+                    }
+                    _ => Label::Unknown,
+                }
+            })
+            .collect();
+        self.previous = Some(range_image);
+        labels
+    }
+
+    /// Forgets the previous frame, e.g. after the sensor has moved.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use velodyne::dynamic::{Detector, Label};
+    /// use velodyne::fixtures::VLP_16_DATA_PACKET;
+    /// use velodyne::vlp_16::Packet;
+    /// let points = Packet::new(&VLP_16_DATA_PACKET).unwrap().points().unwrap();
+    /// let mut detector = Detector::new();
+    /// detector.push(&points);
+    /// detector.reset();
+    /// assert!(!detector.push(&points).contains(&Label::Static));
+    /// ```
+    pub fn reset(&mut self) {
+        self.previous = None;
+    }
+}
+
+impl Default for Detector {
+    fn default() -> Detector {
+        Detector {
+            threshold: DEFAULT_THRESHOLD,
+            columns: DEFAULT_COLUMNS,
+            previous: None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::Duration;
+    use point::{Azimuth, ReturnType, Time};
+    use units::Degrees;
+
+    fn point(range: f32, azimuth: f32) -> Point {
+        Point::from_spherical(Meters(range),
+                              Azimuth::Measured(Degrees(azimuth)),
+                              Degrees(0.),
+                              0,
+                              1,
+                              ReturnType::Strongest,
+                              Time::Offset(Duration::zero()))
+    }
+
+    #[test]
+    fn push() {
+        let mut detector = Detector::new();
+        let labels = detector.push(&[point(10., 0.1), point(10., 90.1), point(0., 180.1)]);
+        assert_eq!(vec![Label::Unknown, Label::Unknown, Label::NoReturn], labels);
+        let labels = detector.push(&[point(10.2, 0.1), point(4., 90.1), point(5., 180.1)]);
+        assert_eq!(vec![Label::Static, Label::Dynamic, Label::Unknown], labels);
+        detector.threshold(Meters(0.1));
+        let labels = detector.push(&[point(10., 0.1)]);
+        assert_eq!(vec![Label::Dynamic], labels);
+    }
+
+    #[test]
+    fn push_hdl_32e() {
+        use fixtures::RELABELLED_HDL_32E_DATA_PACKET;
+        use vlp_16::Packet;
+        let points = Packet::new(&RELABELLED_HDL_32E_DATA_PACKET).unwrap().points().unwrap();
+        let mut detector = Detector::new();
+        detector.push(&points);
+        let labels = detector.push(&points);
+        for (point, &label) in points.iter().zip(&labels) {
+            if point.range() == 0. {
+                assert_eq!(Label::NoReturn, label);
+            } else if point.channel >= 16 {
+                assert_eq!(Label::Unknown, label);
+            } else {
+                assert_eq!(Label::Static, label);
+            }
+        }
+    }
+}
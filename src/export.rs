@@ -0,0 +1,404 @@
+//! Write each frame to its own file, with a manifest of what was written.
+//!
+//! Files are named by a template, whose placeholders are filled in for every frame:
+//!
+//! - `{index}` is the frame's index, counting from zero. `{index:06}` pads it with zeros to six
+//!   digits.
+//! - `{timestamp}` is the time of the frame's first point in seconds, past the hour for offset
+//!   times or since the Unix epoch for absolute times, with microsecond precision.
+//! - `{sensor}` is the sensor's identifier, which is set on the exporter.
+//!
+//! Dataset-publishing workflows usually want a list of the files too, so the exporter keeps a
+//! manifest of each file's path, time range, and point count, which can be written as CSV or JSON:
+//!
+//! ```
+//! use velodyne::csv;
+//! use velodyne::export::{FrameExporter, Template};
+//! use velodyne::io::{Pcap, Read};
+//! use velodyne::sink;
+//! use velodyne::stream::PointStream;
+//! let template = Template::new("frames/{sensor}-{index:04}.csv").unwrap();
+//! let mut exporter = FrameExporter::new(template, |_path| Ok(csv::Writer::new(Vec::new())));
+//! exporter.sensor("front");
+//! let frames = Pcap::open("data/single.pcap").unwrap().points().frames();
+//! sink::write_frames(frames, &mut exporter).unwrap();
+//! assert_eq!("frames/front-0000.csv", exporter.manifest().entries[0].path);
+//! let mut json = Vec::new();
+//! exporter.manifest().write_json(&mut json).unwrap();
+//! ```
+
+use {Error, Point, Result};
+use frame::Frame;
+use pcd;
+use point::Time;
+use sink::PointSink;
+use std::fmt::Write as FmtWrite;
+use std::io::Write;
+
+const MANIFEST_HEADER: &str = "path,index,sensor,start,end,points";
+
+/// A filename template.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Template {
+    parts: Vec<Part>,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+enum Part {
+    Literal(String),
+    Index(usize),
+    Timestamp,
+    Sensor,
+}
+
+/// Writes each frame to a new sink, named by a template, and records it in a manifest.
+///
+/// Frames without points are skipped, since they have no time range, and they don't use up an
+/// index. A batch of points written with `write_points` becomes a file of its own, like a frame.
+#[derive(Debug)]
+pub struct FrameExporter<F> {
+    template: Template,
+    create: F,
+    sensor: String,
+    manifest: Manifest,
+}
+
+/// The files written by an exporter.
+#[derive(Clone, Debug, Default)]
+pub struct Manifest {
+    /// The files, in the order they were written.
+    pub entries: Vec<Entry>,
+}
+
+/// One file in a manifest.
+#[derive(Clone, Debug)]
+pub struct Entry {
+    /// The file's path, as rendered from the template.
+    pub path: String,
+    /// The frame's index.
+    pub index: usize,
+    /// The sensor's identifier.
+    pub sensor: String,
+    /// The time of the frame's first point.
+    pub start: Time,
+    /// The time of the frame's last point.
+    pub end: Time,
+    /// The number of points in the file.
+    pub points: usize,
+}
+
+impl Template {
+    /// Parses a template.
+    ///
+    /// Use `{{` and `}}` for literal braces.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use velodyne::export::Template;
+    /// assert!(Template::new("frame-{index:06}.pcd").is_ok());
+    /// assert!(Template::new("frame-{color}.pcd").is_err());
+    /// ```
+    pub fn new(template: &str) -> Result<Template> {
+        let mut parts = Vec::new();
+        let mut literal = String::new();
+        let mut chars = template.chars();
+        while let Some(c) = chars.next() {
+            match c {
+                '{' => {
+                    if chars.as_str().starts_with('{') {
+                        chars.next();
+                        literal.push('{');
+                        continue;
+                    }
+                    let rest = chars.as_str();
+                    let end = rest.find('}').ok_or_else(|| {
+                        Error::Template(format!("unclosed placeholder in {}", template))
+                    })?;
+                    if !literal.is_empty() {
+                        parts.push(Part::Literal(literal.split_off(0)));
+                    }
+                    parts.push(Template::placeholder(&rest[..end])?);
+                    chars = rest[end + 1..].chars();
+                }
+                '}' => {
+                    if !chars.as_str().starts_with('}') {
+                        return Err(Error::Template(format!("unmatched }} in {}", template)));
+                    }
+                    chars.next();
+                    literal.push('}');
+                }
+                _ => literal.push(c),
+            }
+        }
+        if !literal.is_empty() {
+            parts.push(Part::Literal(literal));
+        }
+        Ok(Template { parts })
+    }
+
+    /// Renders the path of a frame.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # extern crate chrono;
+    /// # extern crate velodyne;
+    /// # fn main() {
+    /// use chrono::Duration;
+    /// use velodyne::export::Template;
+    /// use velodyne::point::Time;
+    /// let template = Template::new("{sensor}/{index:03}-{timestamp}.pcd").unwrap();
+    /// let start = Time::Offset(Duration::milliseconds(1_500));
+    /// assert_eq!("top/007-1.500000.pcd", template.render(7, start, "top"));
+    /// # }
+    /// ```
+    pub fn render(&self, index: usize, start: Time, sensor: &str) -> String {
+        let mut path = String::new();
+        for part in &self.parts {
+            // Writing to a string can't fail.
+            let _ = match *part {
+                Part::Literal(ref literal) => write!(path, "{}", literal),
+                Part::Index(width) => write!(path, "{:0width$}", index, width = width),
+                Part::Timestamp => write!(path, "{:.6}", pcd::seconds(start)),
+                Part::Sensor => write!(path, "{}", sensor),
+            };
+        }
+        path
+    }
+
+    fn placeholder(placeholder: &str) -> Result<Part> {
+        let (name, width) = match placeholder.find(':') {
+            Some(i) => (&placeholder[..i], Some(&placeholder[i + 1..])),
+            None => (placeholder, None),
+        };
+        match (name, width) {
+            ("index", None) => Ok(Part::Index(0)),
+            ("index", Some(width)) => Ok(Part::Index(width.parse()?)),
+            ("timestamp", None) => Ok(Part::Timestamp),
+            ("sensor", None) => Ok(Part::Sensor),
+            _ => Err(Error::Template(format!("unknown placeholder: {{{}}}", placeholder))),
+        }
+    }
+}
+
+impl<F, S> FrameExporter<F>
+    where F: FnMut(&str) -> Result<S>,
+          S: PointSink
+{
+    /// Creates an exporter that calls `create` with each frame's path to get its sink.
+    ///
+    /// Each sink is finished as soon as its frame is written.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use std::fs::File;
+    /// use std::io::BufWriter;
+    /// use velodyne::export::{FrameExporter, Template};
+    /// use velodyne::pcd::{Encoding, Writer};
+    /// let template = Template::new("frame-{index:04}.pcd").unwrap();
+    /// let exporter = FrameExporter::new(template, |path| {
+    ///     Ok(Writer::new(BufWriter::new(File::create(path)?), Encoding::Binary))
+    /// });
+    /// ```
+    pub fn new(template: Template, create: F) -> FrameExporter<F> {
+        FrameExporter {
+            template,
+            create,
+            sensor: String::new(),
+            manifest: Manifest::default(),
+        }
+    }
+
+    /// Sets the sensor's identifier, for the `{sensor}` placeholder and the manifest.
+    pub fn sensor(&mut self, sensor: &str) -> &mut FrameExporter<F> {
+        self.sensor = sensor.to_string();
+        self
+    }
+
+    /// Returns the manifest of the files written so far.
+    pub fn manifest(&self) -> &Manifest {
+        &self.manifest
+    }
+
+    /// Returns the manifest of the files written.
+    pub fn into_manifest(self) -> Manifest {
+        self.manifest
+    }
+}
+
+impl<F, S> PointSink for FrameExporter<F>
+    where F: FnMut(&str) -> Result<S>,
+          S: PointSink
+{
+    fn write_points(&mut self, points: &[Point]) -> Result<()> {
+        self.write_frame(&Frame::new(points.to_vec()))
+    }
+
+    fn write_frame(&mut self, frame: &Frame) -> Result<()> {
+        let (start, end) = match (frame.points.first(), frame.points.last()) {
+            (Some(first), Some(last)) => (first.time, last.time),
+            _ => return Ok(()),
+        };
+        let index = self.manifest.entries.len();
+        let path = self.template.render(index, start, &self.sensor);
+        let mut sink = (self.create)(&path)?;
+        sink.write_frame(frame)?;
+        sink.finish()?;
+        self.manifest
+            .entries
+            .push(Entry {
+                      path,
+                      index,
+                      sensor: self.sensor.clone(),
+                      start,
+                      end,
+                      points: frame.points.len(),
+                  });
+        Ok(())
+    }
+}
+
+impl Manifest {
+    /// Writes the manifest as CSV, with a header row.
+    ///
+    /// Times are in seconds, like in `csv::Writer`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use velodyne::export::Manifest;
+    /// let mut csv = Vec::new();
+    /// Manifest::default().write_csv(&mut csv).unwrap();
+    /// assert_eq!(b"path,index,sensor,start,end,points\n", &csv[..]);
+    /// ```
+    pub fn write_csv<W: Write>(&self, mut write: W) -> Result<()> {
+        writeln!(write, "{}", MANIFEST_HEADER)?;
+        for entry in &self.entries {
+            writeln!(write,
+                     "{},{},{},{},{},{}",
+                     csv_field(&entry.path),
+                     entry.index,
+                     csv_field(&entry.sensor),
+                     pcd::seconds(entry.start),
+                     pcd::seconds(entry.end),
+                     entry.points)?;
+        }
+        write.flush().map_err(Into::into)
+    }
+
+    /// Writes the manifest as a JSON array of objects, one per file.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use velodyne::export::Manifest;
+    /// let mut json = Vec::new();
+    /// Manifest::default().write_json(&mut json).unwrap();
+    /// assert_eq!(b"[]\n", &json[..]);
+    /// ```
+    pub fn write_json<W: Write>(&self, mut write: W) -> Result<()> {
+        write!(write, "[")?;
+        for (i, entry) in self.entries.iter().enumerate() {
+            if i > 0 {
+                write!(write, ",")?;
+            }
+            write!(write,
+                   "\n  {{\"path\": {}, \"index\": {}, \"sensor\": {}, \"start\": {}, \"end\": {}, \
+                    \"points\": {}}}",
+                   json_string(&entry.path),
+                   entry.index,
+                   json_string(&entry.sensor),
+                   pcd::seconds(entry.start),
+                   pcd::seconds(entry.end),
+                   entry.points)?;
+        }
+        if !self.entries.is_empty() {
+            writeln!(write)?;
+        }
+        writeln!(write, "]")?;
+        write.flush().map_err(Into::into)
+    }
+}
+
+/// Quotes a CSV field if it needs it.
+fn csv_field(field: &str) -> String {
+    if field.contains([',', '"', '\n']) {
+        format!("\"{}\"", field.replace('"', "\"\""))
+    } else {
+        field.to_string()
+    }
+}
+
+fn json_string(s: &str) -> String {
+    let mut json = String::from("\"");
+    for c in s.chars() {
+        match c {
+            '"' => json.push_str("\\\""),
+            '\\' => json.push_str("\\\\"),
+            '\n' => json.push_str("\\n"),
+            c if (c as u32) < 0x20 => {
+                let _ = write!(json, "\\u{:04x}", c as u32);
+            }
+            c => json.push(c),
+        }
+    }
+    json.push('"');
+    json
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::Duration;
+    use fixtures::VLP_16_DATA_PACKET;
+    use vlp_16::Packet;
+
+    #[test]
+    fn template() {
+        assert!(Template::new("{index").is_err());
+        assert!(Template::new("index}").is_err());
+        assert!(Template::new("{index:x}").is_err());
+        let template = Template::new("{{{sensor}}}_{index}").unwrap();
+        let time = Time::Offset(Duration::zero());
+        assert_eq!("{a,b}_12", template.render(12, time, "a,b"));
+    }
+
+    #[test]
+    fn manifest() {
+        let points = Packet::new(&VLP_16_DATA_PACKET).unwrap().points().unwrap();
+        let frames = vec![Frame::new(points.clone()), Frame::default(), Frame::new(points.clone())];
+        let mut paths = Vec::new();
+        let mut exporter = FrameExporter::new(Template::new("{index:02}.csv").unwrap(), |path| {
+            paths.push(path.to_string());
+            Ok(Vec::new())
+        });
+        exporter.sensor("a,b");
+        for frame in &frames {
+            exporter.write_frame(frame).unwrap();
+        }
+        let manifest = exporter.into_manifest();
+        assert_eq!(vec!["00.csv", "01.csv"], paths);
+        assert_eq!(2, manifest.entries.len());
+        let entry = &manifest.entries[1];
+        assert_eq!(1, entry.index);
+        assert_eq!(points.len(), entry.points);
+        assert_eq!(pcd::seconds(points[0].time), pcd::seconds(entry.start));
+        assert_eq!(pcd::seconds(points[points.len() - 1].time), pcd::seconds(entry.end));
+
+        let mut csv = Vec::new();
+        manifest.write_csv(&mut csv).unwrap();
+        let csv = String::from_utf8(csv).unwrap();
+        assert_eq!(3, csv.lines().count());
+        assert!(csv.lines().nth(1).unwrap().starts_with("00.csv,0,\"a,b\","), "{}", csv);
+
+        let mut json = Vec::new();
+        manifest.write_json(&mut json).unwrap();
+        let json = String::from_utf8(json).unwrap();
+        assert!(json.starts_with("[\n  {\"path\": \"00.csv\", \"index\": 0, \"sensor\": \"a,b\""),
+                "{}",
+                json);
+        assert!(json.ends_with("\"points\": 384}\n]\n"), "{}", json);
+    }
+}
@@ -0,0 +1,278 @@
+//! A C interface to the decoder.
+//!
+//! The matching header is `include/velodyne.h`. To build a shared library:
+//!
+//! ```text
+//! cargo rustc --release --features ffi --lib --crate-type cdylib
+//! ```
+//!
+//! Every pointer returned by this interface is owned by the caller and must be released with the
+//! matching `_free` function, except for error messages, which belong to their source.
+
+use io::{Pcap, Read};
+use point::Time;
+use std::ffi::{CStr, CString};
+use std::os::raw::c_char;
+use std::ptr;
+use std::slice;
+use vlp_16::{self, Packet};
+
+/// An open source of packets.
+#[allow(missing_debug_implementations)]
+pub struct VelodyneSource {
+    read: Box<dyn Read>,
+    last_error: Option<CString>,
+}
+
+/// A single decoded packet.
+#[allow(missing_debug_implementations)]
+pub struct VelodynePacket {
+    packet: Packet,
+}
+
+/// A point, laid out for C.
+#[repr(C)]
+#[derive(Clone, Copy, Debug, Default)]
+pub struct VelodynePoint {
+    /// The x coordinate, in meters.
+    pub x: f32,
+    /// The y coordinate, in meters.
+    pub y: f32,
+    /// The z coordinate, in meters.
+    pub z: f32,
+    /// The azimuth, in degrees.
+    pub azimuth: f32,
+    /// The time of the point, in microseconds since the top of the hour.
+    pub time: i64,
+    /// The calibrated reflectivity.
+    pub reflectivity: u8,
+    /// The laser channel.
+    pub channel: u8,
+}
+
+/// Opens a pcap file, returning null on error.
+///
+/// # Safety
+///
+/// `path` must be a valid, null-terminated string.
+#[no_mangle]
+pub unsafe extern "C" fn velodyne_pcap_open(path: *const c_char) -> *mut VelodyneSource {
+    if path.is_null() {
+        return ptr::null_mut();
+    }
+    let path = match CStr::from_ptr(path).to_str() {
+        Ok(path) => path,
+        Err(_) => return ptr::null_mut(),
+    };
+    match Pcap::open(path) {
+        Ok(pcap) => {
+            Box::into_raw(Box::new(VelodyneSource {
+                                       read: Box::new(pcap),
+                                       last_error: None,
+                                   }))
+        }
+        Err(_) => ptr::null_mut(),
+    }
+}
+
+/// Reads the next packet from a source, returning null at the end of the source or on error.
+///
+/// Anything that isn't a Velodyne packet, e.g. ARP or mDNS traffic, is skipped. After a null,
+/// `velodyne_source_last_error` tells an error from the end of the source.
+///
+/// # Safety
+///
+/// `source` must have been returned by `velodyne_pcap_open` and not yet freed.
+#[no_mangle]
+pub unsafe extern "C" fn velodyne_source_next_packet(source: *mut VelodyneSource)
+                                                     -> *mut VelodynePacket {
+    let source = match source.as_mut() {
+        Some(source) => source,
+        None => return ptr::null_mut(),
+    };
+    source.last_error = None;
+    let result = loop {
+        match source.read.read() {
+            Some(Ok(bytes)) if vlp_16::classify(bytes).is_none() => {}
+            Some(Ok(bytes)) => break Packet::new(bytes),
+            Some(Err(err)) => break Err(err),
+            None => return ptr::null_mut(),
+        }
+    };
+    match result {
+        Ok(packet) => Box::into_raw(Box::new(VelodynePacket { packet })),
+        Err(err) => {
+            // A message with an interior null is still an error, just without its text.
+            source.last_error = Some(CString::new(err.to_string()).unwrap_or_default());
+            ptr::null_mut()
+        }
+    }
+}
+
+/// Returns the error from the last call to `velodyne_source_next_packet`, or null if it
+/// succeeded or reached the end of the source.
+///
+/// The message belongs to the source, and is valid until the next call with the source.
+///
+/// # Safety
+///
+/// `source` must have been returned by `velodyne_pcap_open` and not yet freed.
+#[no_mangle]
+pub unsafe extern "C" fn velodyne_source_last_error(source: *const VelodyneSource)
+                                                    -> *const c_char {
+    source.as_ref()
+        .and_then(|source| source.last_error.as_ref())
+        .map_or(ptr::null(), |message| message.as_ptr())
+}
+
+/// Returns true if the packet is a data packet.
+///
+/// # Safety
+///
+/// `packet` must have been returned by `velodyne_source_next_packet` and not yet freed.
+#[no_mangle]
+pub unsafe extern "C" fn velodyne_packet_is_data(packet: *const VelodynePacket) -> bool {
+    packet.as_ref().map(|packet| packet.packet.is_data()).unwrap_or(false)
+}
+
+/// Decodes a packet's points into a buffer, returning the number of points in the packet.
+///
+/// At most `capacity` points are written. Position packets have no points.
+///
+/// # Safety
+///
+/// `packet` must have been returned by `velodyne_source_next_packet` and not yet freed, and
+/// `points` must be valid for `capacity` writes.
+#[no_mangle]
+pub unsafe extern "C" fn velodyne_packet_points(packet: *const VelodynePacket,
+                                                points: *mut VelodynePoint,
+                                                capacity: usize)
+                                                -> usize {
+    let decoded = match packet.as_ref().and_then(|packet| packet.packet.points()) {
+        Some(decoded) => decoded,
+        None => return 0,
+    };
+    if !points.is_null() {
+        let points = slice::from_raw_parts_mut(points, capacity);
+        for (point, decoded) in points.iter_mut().zip(&decoded) {
+            *point = VelodynePoint {
+                x: decoded.x,
+                y: decoded.y,
+                z: decoded.z,
+                azimuth: decoded.azimuth.degrees(),
+                time: match decoded.time {
+                    Time::Offset(offset) => offset.num_microseconds().unwrap_or(0),
+                    Time::Absolute(_) => 0,
+                },
+                reflectivity: decoded.reflectivity,
+                channel: decoded.channel,
+            };
+        }
+    }
+    decoded.len()
+}
+
+/// Frees a packet.
+///
+/// # Safety
+///
+/// `packet` must be null or have been returned by `velodyne_source_next_packet`, and must not be
+/// used afterwards.
+#[no_mangle]
+pub unsafe extern "C" fn velodyne_packet_free(packet: *mut VelodynePacket) {
+    if !packet.is_null() {
+        drop(Box::from_raw(packet));
+    }
+}
+
+/// Frees a source.
+///
+/// # Safety
+///
+/// `source` must be null or have been returned by `velodyne_pcap_open`, and must not be used
+/// afterwards.
+#[no_mangle]
+pub unsafe extern "C" fn velodyne_source_free(source: *mut VelodyneSource) {
+    if !source.is_null() {
+        drop(Box::from_raw(source));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use io::PcapSlice;
+
+    #[test]
+    fn decode_pcap() {
+        let path = CString::new("data/single.pcap").unwrap();
+        unsafe {
+            let source = velodyne_pcap_open(path.as_ptr());
+            assert!(!source.is_null());
+            let packet = velodyne_source_next_packet(source);
+            assert!(velodyne_packet_is_data(packet));
+            assert_eq!(384, velodyne_packet_points(packet, ptr::null_mut(), 0));
+            let mut points = vec![VelodynePoint::default(); 384];
+            assert_eq!(384, velodyne_packet_points(packet, points.as_mut_ptr(), points.len()));
+            assert_eq!(4, points[0].reflectivity);
+            assert_eq!(2_467_108_343, points[0].time);
+            velodyne_packet_free(packet);
+            assert!(velodyne_source_next_packet(source).is_null());
+            assert!(velodyne_source_last_error(source).is_null());
+            velodyne_source_free(source);
+        }
+    }
+
+    #[test]
+    fn last_error() {
+        let mut bytes = include_bytes!("../data/single.pcap").to_vec();
+        let record = bytes[24..].to_vec();
+        bytes.extend_from_slice(&record[..100]);
+        let mut source = VelodyneSource {
+            read: Box::new(PcapSlice::new(bytes).unwrap()),
+            last_error: None,
+        };
+        unsafe {
+            let packet = velodyne_source_next_packet(&mut source);
+            assert!(!packet.is_null());
+            assert!(velodyne_source_last_error(&source).is_null());
+            velodyne_packet_free(packet);
+            assert!(velodyne_source_next_packet(&mut source).is_null());
+            let message = velodyne_source_last_error(&source);
+            assert!(!message.is_null());
+            assert!(!CStr::from_ptr(message).to_bytes().is_empty());
+            assert!(velodyne_source_next_packet(&mut source).is_null());
+            assert!(velodyne_source_last_error(&source).is_null());
+        }
+    }
+
+    #[test]
+    fn header_matches_exports() {
+        let name = |declaration: &str| {
+            let start = declaration.find("velodyne_").unwrap();
+            let end = start + declaration[start..].find('(').unwrap();
+            declaration[start..end].to_string()
+        };
+        let mut exported = include_str!("ffi.rs")
+            .lines()
+            .filter(|line| line.starts_with("pub unsafe extern \"C\" fn velodyne_"))
+            .map(name)
+            .collect::<Vec<_>>();
+        let mut declared = include_str!("../include/velodyne.h")
+            .lines()
+            .filter(|line| !line.starts_with(' ') && !line.starts_with('/'))
+            .filter(|line| line.contains(" velodyne_") || line.contains("*velodyne_"))
+            .map(name)
+            .collect::<Vec<_>>();
+        exported.sort();
+        declared.sort();
+        assert!(!exported.is_empty());
+        assert_eq!(exported, declared);
+    }
+
+    #[test]
+    fn open_missing_file() {
+        let path = CString::new("notafile").unwrap();
+        assert!(unsafe { velodyne_pcap_open(path.as_ptr()) }.is_null());
+    }
+}
@@ -0,0 +1,361 @@
+//! Remove unwanted points.
+//!
+//! Filters work on any list of points, so they can be applied to frames, clouds, or (with
+//! `Decoder::filter`) to each packet as it is decoded.
+
+use Point;
+use cloud::Bounds;
+use range_image::RangeImage;
+use std::fmt::Debug;
+use units::{Degrees, Meters};
+
+const DEFAULT_NOISE_RADIUS: f32 = 0.5;
+const DEFAULT_NOISE_WINDOW: usize = 2;
+const DEFAULT_NOISE_MIN_NEIGHBORS: usize = 1;
+const DEFAULT_NOISE_COLUMNS: usize = 1800;
+
+/// Something that removes points.
+pub trait Filter: Debug {
+    /// Removes the unwanted points, preserving the order of the rest.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use velodyne::cloud::Bounds;
+    /// use velodyne::filter::{CropBox, Filter};
+    /// use velodyne::fixtures::VLP_16_DATA_PACKET;
+    /// use velodyne::vlp_16::Packet;
+    /// let mut points = Packet::new(&VLP_16_DATA_PACKET).unwrap().points().unwrap();
+    /// CropBox::new(Bounds::new([-10., -10., -1.], [10., 10., 1.])).apply(&mut points);
+    /// assert!(points.iter().all(|point| point.x.abs() <= 10.));
+    /// ```
+    fn apply(&self, points: &mut Vec<Point>);
+}
+
+/// Keeps, or removes, the points inside of a box.
+///
+/// The box can be rotated about the sensor's z axis. A typical use is removing the vehicle that
+/// the sensor is mounted on.
+#[derive(Clone, Copy, Debug)]
+pub struct CropBox {
+    bounds: Bounds,
+    sin_yaw: f32,
+    cos_yaw: f32,
+    keep: Keep,
+}
+
+/// Which points a crop box keeps.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Keep {
+    /// Keep the points inside of the box.
+    Inside,
+    /// Keep the points outside of the box, e.g. to remove a vehicle's body.
+    Outside,
+}
+
+/// Removes isolated points, e.g. sun noise or veiling returns.
+///
+/// Points are organized into a range image. A point is kept if enough of the points in the
+/// nearby cells of the image are within a radius of it. Points without a return are always kept,
+/// as are points from lasers that the range image has no row for, e.g. the HDL-32E's upper
+/// channels.
+/// Apply this to whole frames, since the points at the edge of a packet are missing half of
+/// their neighbors.
+#[derive(Clone, Copy, Debug)]
+pub struct NoiseFilter {
+    radius: f32,
+    window: usize,
+    min_neighbors: usize,
+    columns: usize,
+}
+
+impl CropBox {
+    /// Creates a new crop box that keeps the points inside of the bounds.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use velodyne::cloud::Bounds;
+    /// use velodyne::filter::CropBox;
+    /// let crop_box = CropBox::new(Bounds::new([-50., -50., -3.], [50., 50., 10.]));
+    /// ```
+    pub fn new(bounds: Bounds) -> CropBox {
+        CropBox {
+            bounds,
+            sin_yaw: 0.,
+            cos_yaw: 1.,
+            keep: Keep::Inside,
+        }
+    }
+
+    /// Sets which points are kept.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use velodyne::cloud::Bounds;
+    /// use velodyne::filter::{CropBox, Keep};
+    /// let mut vehicle = CropBox::new(Bounds::new([-1., -3., -2.], [1., 1., 0.5]));
+    /// vehicle.keep(Keep::Outside);
+    /// ```
+    pub fn keep(&mut self, keep: Keep) -> &mut CropBox {
+        self.keep = keep;
+        self
+    }
+
+    /// Sets the rotation of the box about the sensor's z axis.
+    ///
+    /// The bounds are in the box's frame, which is rotated clockwise (when seen from above) by
+    /// this angle, the same direction as the sensor's azimuth.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use velodyne::cloud::Bounds;
+    /// # use velodyne::filter::CropBox;
+    /// use velodyne::units::Degrees;
+    /// let mut crop_box = CropBox::new(Bounds::new([-1., 0., -1.], [1., 10., 1.]));
+    /// crop_box.yaw(Degrees(90.));
+    /// ```
+    pub fn yaw(&mut self, yaw: Degrees) -> &mut CropBox {
+        let yaw = yaw.radians().0;
+        self.sin_yaw = yaw.sin();
+        self.cos_yaw = yaw.cos();
+        self
+    }
+
+    /// Returns true if the point is inside of, or on the edge of, this box.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use velodyne::cloud::Bounds;
+    /// # use velodyne::filter::CropBox;
+    /// use velodyne::fixtures::VLP_16_DATA_PACKET;
+    /// use velodyne::vlp_16::Packet;
+    /// let points = Packet::new(&VLP_16_DATA_PACKET).unwrap().points().unwrap();
+    /// let crop_box = CropBox::new(Bounds::new([-100., -100., -100.], [100., 100., 100.]));
+    /// assert!(crop_box.contains(&points[0]));
+    /// ```
+    pub fn contains(&self, point: &Point) -> bool {
+        let mut point = *point;
+        let (x, y) = (point.x, point.y);
+        point.x = x * self.cos_yaw - y * self.sin_yaw;
+        point.y = x * self.sin_yaw + y * self.cos_yaw;
+        self.bounds.contains(&point)
+    }
+}
+
+impl NoiseFilter {
+    /// Creates a new noise filter.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use velodyne::filter::NoiseFilter;
+    /// let noise_filter = NoiseFilter::new();
+    /// ```
+    pub fn new() -> NoiseFilter {
+        NoiseFilter::default()
+    }
+
+    /// Sets the distance within which another point counts as a neighbor.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use velodyne::filter::NoiseFilter;
+    /// use velodyne::units::Meters;
+    /// let mut noise_filter = NoiseFilter::new();
+    /// noise_filter.radius(Meters(1.));
+    /// ```
+    pub fn radius(&mut self, radius: Meters) -> &mut NoiseFilter {
+        self.radius = radius.0;
+        self
+    }
+
+    /// Sets how many cells on each side of a point are searched for neighbors.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use velodyne::filter::NoiseFilter;
+    /// let mut noise_filter = NoiseFilter::new();
+    /// noise_filter.window(3);
+    /// ```
+    pub fn window(&mut self, window: usize) -> &mut NoiseFilter {
+        self.window = window;
+        self
+    }
+
+    /// Sets the number of neighbors that a point needs to be kept.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use velodyne::filter::NoiseFilter;
+    /// let mut noise_filter = NoiseFilter::new();
+    /// noise_filter.min_neighbors(2);
+    /// ```
+    pub fn min_neighbors(&mut self, min_neighbors: usize) -> &mut NoiseFilter {
+        self.min_neighbors = min_neighbors;
+        self
+    }
+
+    /// Sets the number of azimuth bins in the range image.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `columns` is zero.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use velodyne::filter::NoiseFilter;
+    /// let mut noise_filter = NoiseFilter::new();
+    /// noise_filter.columns(3600);
+    /// ```
+    pub fn columns(&mut self, columns: usize) -> &mut NoiseFilter {
+        assert!(columns > 0, "a range image needs at least one column");
+        self.columns = columns;
+        self
+    }
+
+    fn neighbors(&self, points: &[Point], range_image: &RangeImage, index: usize) -> usize {
+        let point = &points[index];
+        let row = match RangeImage::point_row(point) {
+            Some(row) => row,
+            None => return self.min_neighbors,
+        };
+        let column = range_image.column(point.azimuth.degrees());
+        let rows = row.saturating_sub(self.window)..(row + self.window + 1).min(range_image.rows());
+        let columns = range_image.columns();
+        let window = self.window.min(columns / 2);
+        let mut neighbors = 0;
+        for row in rows {
+            for offset in 0..(2 * window + 1) {
+                let column = (column + columns + offset - window) % columns;
+                if let Some(cell) = range_image.get(row, column) {
+                    let other = &points[cell.point];
+                    if cell.point != index && distance(point, other) <= self.radius {
+                        neighbors += 1;
+                    }
+                }
+            }
+        }
+        neighbors
+    }
+}
+
+impl Default for NoiseFilter {
+    fn default() -> NoiseFilter {
+        NoiseFilter {
+            radius: DEFAULT_NOISE_RADIUS,
+            window: DEFAULT_NOISE_WINDOW,
+            min_neighbors: DEFAULT_NOISE_MIN_NEIGHBORS,
+            columns: DEFAULT_NOISE_COLUMNS,
+        }
+    }
+}
+
+impl Filter for NoiseFilter {
+    fn apply(&self, points: &mut Vec<Point>) {
+        let keep = {
+            let range_image = RangeImage::from_points(points, self.columns);
+            (0..points.len())
+                .map(|index| {
+                         points[index].range() == 0. ||
+                         self.neighbors(points, &range_image, index) >= self.min_neighbors
+                     })
+                .collect::<Vec<_>>()
+        };
+        let mut keep = keep.into_iter();
+        points.retain(|_| keep.next().unwrap_or(true));
+    }
+}
+
+fn distance(a: &Point, b: &Point) -> f32 {
+    ((a.x - b.x).powi(2) + (a.y - b.y).powi(2) + (a.z - b.z).powi(2)).sqrt()
+}
+
+impl Filter for CropBox {
+    fn apply(&self, points: &mut Vec<Point>) {
+        let keep_inside = self.keep == Keep::Inside;
+        points.retain(|point| self.contains(point) == keep_inside);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::Duration;
+    use point::{Azimuth, ReturnType, Time};
+
+    fn point(x: f32, y: f32) -> Point {
+        Point {
+            x,
+            y,
+            z: 0.,
+            reflectivity: 0,
+            channel: 0,
+            sequence: 0,
+            return_type: ReturnType::Strongest,
+            azimuth: Azimuth::Measured(Degrees(0.)),
+            time: Time::Offset(Duration::zero()),
+            sensor_id: None,
+        }
+    }
+
+    fn spherical(range: f32, azimuth: f32, channel: u8) -> Point {
+        Point::from_spherical(Meters(range),
+                              Azimuth::Measured(Degrees(azimuth)),
+                              Degrees(::vlp_16::vertical_angle(channel as usize)),
+                              0,
+                              channel,
+                              ReturnType::Strongest,
+                              Time::Offset(Duration::zero()))
+    }
+
+    #[test]
+    fn noise() {
+        let mut points = vec![spherical(10., 90., 0),
+                              spherical(10., 90.2, 0),
+                              spherical(10., 90.4, 0),
+                              spherical(3., 180., 0),
+                              spherical(10., 180.2, 0),
+                              spherical(0., 270., 0)];
+        NoiseFilter::new().apply(&mut points);
+        assert_eq!(4, points.len());
+        assert_eq!(270., points[3].azimuth.degrees());
+    }
+
+    #[test]
+    fn yaw() {
+        // A box straight ahead of the sensor, rotated to point along positive x.
+        let mut crop_box = CropBox::new(Bounds::new([-1., 5., -1.], [1., 10., 1.]));
+        crop_box.yaw(Degrees(90.));
+        assert!(crop_box.contains(&point(7., 0.)));
+        assert!(!crop_box.contains(&point(0., 7.)));
+        assert!(!crop_box.contains(&point(-7., 0.)));
+    }
+
+    #[test]
+    fn keep_outside() {
+        let mut points = vec![point(0., 0.), point(5., 5.)];
+        CropBox::new(Bounds::new([-1., -1., -1.], [1., 1., 1.]))
+            .keep(Keep::Outside)
+            .apply(&mut points);
+        assert_eq!(1, points.len());
+        assert_eq!(5., points[0].x);
+    }
+
+    #[test]
+    fn noise_hdl_32e() {
+        use fixtures::RELABELLED_HDL_32E_DATA_PACKET;
+        use vlp_16::Packet;
+        let mut points = Packet::new(&RELABELLED_HDL_32E_DATA_PACKET).unwrap().points().unwrap();
+        let upper = points.iter().filter(|point| point.channel >= 16).count();
+        NoiseFilter::new().apply(&mut points);
+        assert_eq!(upper, points.iter().filter(|point| point.channel >= 16).count());
+    }
+}
@@ -1,4 +1,18 @@
 //! Real-world data to be used for testing and examples.
+//!
+//! The packets that aren't straight captures are derived from the VLP-16 captures by editing
+//! their factory bytes, azimuths, or NMEA sentences, and they are stored as binary files under
+//! `data/fixtures`. Use `load` to read other fixture files at test time, rather than pasting their
+//! bytes into the source.
+
+#[cfg(feature = "std")]
+use Result;
+#[cfg(feature = "std")]
+use std::fs::File;
+#[cfg(feature = "std")]
+use std::io::Read;
+#[cfg(feature = "std")]
+use std::path::{Path, PathBuf};
 
 /// A real-world data packet from a VLP-16 system.
 // Pardon the crappy interspersed comments, this was a Wireshark export and I'm too lazy ATM to
@@ -176,3 +190,93 @@ pub const VLP_16_POSITION_PACKET: [u8; 554] =
      0x00, 0x00, 0x00, 0x00 /* ........ */, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
      0x00 /* ........ */, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00 /* ........ */,
      0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00 /* ........ */, 0x00, 0x00 /* .. */];
+
+/// A synthetic data packet: `VLP_16_DATA_PACKET` relabelled with the HDL-32E's factory byte.
+///
+/// It isn't an HDL-32E capture. Its returns were measured by a VLP-16, so it only exercises what
+/// the factory byte selects, e.g. the HDL-32E's distance resolution, timing, and laser table, and
+/// says nothing about decoding what a real HDL-32E sends.
+pub const RELABELLED_HDL_32E_DATA_PACKET: [u8; 1248] =
+    *include_bytes!("../data/fixtures/relabelled-hdl-32e-data.bin");
+
+/// A VLP-16 data packet in dual-return mode.
+///
+/// Each pair of data blocks shares an azimuth, as with a real dual-return packet.
+pub const VLP_16_DUAL_RETURN_PACKET: [u8; 1248] =
+    *include_bytes!("../data/fixtures/vlp-16-dual-return.bin");
+
+/// A VLP-16 data packet in last-return mode.
+pub const VLP_16_LAST_RETURN_PACKET: [u8; 1248] =
+    *include_bytes!("../data/fixtures/vlp-16-last-return.bin");
+
+/// A VLP-16 position packet with a $GPGGA sentence instead of a $GPRMC sentence.
+pub const VLP_16_POSITION_GGA_PACKET: [u8; 554] =
+    *include_bytes!("../data/fixtures/vlp-16-position-gga.bin");
+
+/// Returns the path to a fixture file in this crate's `data/fixtures` directory.
+///
+/// # Examples
+///
+/// ```
+/// use velodyne::fixtures;
+/// assert!(fixtures::path("relabelled-hdl-32e-data.bin").exists());
+/// ```
+#[cfg(feature = "std")]
+pub fn path<P: AsRef<Path>>(name: P) -> PathBuf {
+    Path::new(env!("CARGO_MANIFEST_DIR")).join("data").join("fixtures").join(name)
+}
+
+/// Reads a fixture file from this crate's `data/fixtures` directory.
+///
+/// # Examples
+///
+/// ```
+/// use velodyne::fixtures::{self, RELABELLED_HDL_32E_DATA_PACKET};
+/// let bytes = fixtures::load("relabelled-hdl-32e-data.bin").unwrap();
+/// assert_eq!(&RELABELLED_HDL_32E_DATA_PACKET[..], &bytes[..]);
+/// ```
+#[cfg(feature = "std")]
+pub fn load<P: AsRef<Path>>(name: P) -> Result<Vec<u8>> {
+    let mut bytes = Vec::new();
+    File::open(path(name))?.read_to_end(&mut bytes)?;
+    Ok(bytes)
+}
+
+#[cfg(all(test, feature = "std"))]
+mod tests {
+    use super::*;
+    use nmea::Sentence;
+    use vlp_16::{Packet, ReturnMode, Sensor};
+
+    #[test]
+    fn relabelled_hdl_32e() {
+        let packet = Packet::new(&RELABELLED_HDL_32E_DATA_PACKET).unwrap();
+        assert_eq!(Some(Sensor::HDL_32E), packet.sensor());
+    }
+
+    #[test]
+    fn return_modes() {
+        let packet = Packet::new(&VLP_16_LAST_RETURN_PACKET).unwrap();
+        assert_eq!(Some(ReturnMode::LastReturn), packet.return_mode());
+        let packet = Packet::new(&VLP_16_DUAL_RETURN_PACKET).unwrap();
+        assert_eq!(Some(ReturnMode::DualReturn), packet.return_mode());
+        let data_blocks = packet.data_blocks().unwrap();
+        for pair in data_blocks.chunks(2) {
+            assert_eq!(pair[0].azimuth, pair[1].azimuth);
+        }
+    }
+
+    #[test]
+    fn gga() {
+        let packet = Packet::new(&VLP_16_POSITION_GGA_PACKET).unwrap();
+        match packet.sentences().unwrap().unwrap()[..] {
+            [Sentence::Gga(fix)] => assert_eq!(9, fix.satellites),
+            ref sentences => panic!("unexpected sentences: {:?}", sentences),
+        }
+    }
+
+    #[test]
+    fn load_missing() {
+        assert!(load("missing.bin").is_err());
+    }
+}
@@ -0,0 +1,453 @@
+//! Full rotations of the sensor.
+
+use Point;
+use chrono::Duration;
+use point::ReturnType;
+use timing::Timing;
+use units::Degrees;
+use vlp_16::{AzimuthDirection, ReturnMode, Sensor};
+
+/// If the azimuth drops by more than this many degrees between two points, the sensor has started
+/// a new rotation.
+const WRAP_THRESHOLD: f32 = 180.;
+
+/// One full rotation's worth of points.
+#[derive(Clone, Debug, Default)]
+pub struct Frame {
+    /// The points in this frame, in the order they were fired.
+    pub points: Vec<Point>,
+    /// Whether this frame covers a full rotation.
+    ///
+    /// The first and last frames of a capture are usually truncated.
+    pub complete: bool,
+}
+
+/// A summary of a frame, for deciding whether it's good enough to use.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Metadata {
+    /// The number of points.
+    pub points: usize,
+    /// The estimated number of packets that are missing from the middle of the frame.
+    pub dropped_packets: usize,
+    /// The smallest azimuth, in degrees.
+    pub min_azimuth: Option<f32>,
+    /// The largest azimuth, in degrees.
+    pub max_azimuth: Option<f32>,
+    /// The time between the first and last points.
+    pub duration: Duration,
+    /// The average rotation rate, in revolutions per minute.
+    pub rpm: Option<f32>,
+    /// Whether the frame covers a full rotation.
+    pub complete: bool,
+}
+
+/// Assembles a stream of points into frames.
+///
+/// A frame is complete when the azimuth wraps back around past the cut angle, which is zero by
+/// default.
+#[derive(Clone, Debug, Default)]
+pub struct Assembler {
+    points: Vec<Point>,
+    cut_angle: f32,
+    azimuth_direction: AzimuthDirection,
+    last_azimuth: Option<f32>,
+    started_at_wrap: bool,
+    keep_latest: bool,
+    latest: Option<Frame>,
+    dropped: usize,
+}
+
+impl Frame {
+    /// Creates a frame from points, which isn't known to be complete.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use velodyne::frame::Frame;
+    /// let frame = Frame::new(Vec::new());
+    /// assert!(!frame.complete);
+    /// ```
+    pub fn new(points: Vec<Point>) -> Frame {
+        Frame {
+            points,
+            complete: false,
+        }
+    }
+
+    /// Summarizes this frame from a VLP-16.
+    ///
+    /// See `metadata_for` for other sensors.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use velodyne::fixtures::VLP_16_DATA_PACKET;
+    /// use velodyne::frame::Frame;
+    /// use velodyne::vlp_16::Packet;
+    /// let frame = Frame::new(Packet::new(&VLP_16_DATA_PACKET).unwrap().points().unwrap());
+    /// let metadata = frame.metadata();
+    /// assert_eq!(384, metadata.points);
+    /// assert_eq!(0, metadata.dropped_packets);
+    /// assert!(metadata.rpm.unwrap() > 500.);
+    /// ```
+    pub fn metadata(&self) -> Metadata {
+        self.metadata_for(Sensor::VLP_16)
+    }
+
+    /// Summarizes this frame from a sensor.
+    ///
+    /// Dropped packets are estimated from gaps in the points' times, using the sensor's packet
+    /// duration.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use velodyne::fixtures::RELABELLED_HDL_32E_DATA_PACKET;
+    /// use velodyne::frame::Frame;
+    /// use velodyne::vlp_16::{Packet, Sensor};
+    /// let packet = Packet::new(&RELABELLED_HDL_32E_DATA_PACKET).unwrap();
+    /// let frame = Frame::new(packet.points().unwrap());
+    /// let metadata = frame.metadata_for(Sensor::HDL_32E);
+    /// assert_eq!(0, metadata.dropped_packets);
+    /// ```
+    pub fn metadata_for(&self, sensor: Sensor) -> Metadata {
+        let dual = self.points.iter().any(|p| matches!(p.return_type, ReturnType::Secondary));
+        let return_mode = if dual {
+            ReturnMode::DualReturn
+        } else {
+            ReturnMode::StrongestReturn
+        };
+        let packet_us = Timing::for_sensor(sensor).packet_duration_ns(return_mode) as f32 / 1000.;
+        let mut metadata = Metadata {
+            points: self.points.len(),
+            dropped_packets: 0,
+            min_azimuth: None,
+            max_azimuth: None,
+            duration: Duration::zero(),
+            rpm: None,
+            complete: self.complete,
+        };
+        // Azimuths can increase either way, see `AzimuthDirection`, so sweeps are counted both
+        // ways and the larger one is the rotation.
+        let mut swept = [0.; 2];
+        for (i, point) in self.points.iter().enumerate() {
+            let azimuth = point.azimuth.degrees();
+            metadata.min_azimuth = Some(metadata.min_azimuth.map_or(azimuth, |a| a.min(azimuth)));
+            metadata.max_azimuth = Some(metadata.max_azimuth.map_or(azimuth, |a| a.max(azimuth)));
+            if i == 0 {
+                continue;
+            }
+            let previous = &self.points[i - 1];
+            let step = (azimuth - previous.azimuth.degrees()).rem_euclid(360.);
+            if step < WRAP_THRESHOLD {
+                swept[0] += step;
+            } else {
+                swept[1] += 360. - step;
+            }
+            if let Some(gap) = point.time.duration_since(previous.time) {
+                let gap_us = gap.num_microseconds().unwrap_or(0) as f32;
+                if gap_us > 1.5 * packet_us {
+                    metadata.dropped_packets += (gap_us / packet_us).round() as usize - 1;
+                }
+            }
+        }
+        if let (Some(first), Some(last)) = (self.points.first(), self.points.last()) {
+            metadata.duration = last.time.duration_since(first.time).unwrap_or_else(Duration::zero);
+        }
+        let minutes = metadata.duration.num_microseconds().unwrap_or(0) as f32 / 60e6;
+        if minutes > 0. {
+            metadata.rpm = Some(swept[0].max(swept[1]) / 360. / minutes);
+        }
+        metadata
+    }
+}
+
+impl Assembler {
+    /// Creates a new, empty assembler.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use velodyne::frame::Assembler;
+    /// let assembler = Assembler::new();
+    /// ```
+    pub fn new() -> Assembler {
+        Assembler::default()
+    }
+
+    /// Creates an assembler that only keeps the most recent complete frame.
+    ///
+    /// Use this for live operation when the consumer might be slower than the sensor. Completed
+    /// frames are not returned from `push`, instead they are held until retrieved with
+    /// `latest_frame`. If a new frame completes before the previous one was retrieved, the
+    /// previous one is dropped. The partial frame before the first wrap, which started
+    /// mid-rotation, is never kept.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use velodyne::frame::Assembler;
+    /// let assembler = Assembler::keep_latest();
+    /// ```
+    pub fn keep_latest() -> Assembler {
+        Assembler { keep_latest: true, ..Default::default() }
+    }
+
+    /// Sets the azimuth at which rotations are split into frames.
+    ///
+    /// The seam between frames falls at this azimuth, so put it somewhere uninteresting, e.g.
+    /// behind a vehicle.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use velodyne::frame::Assembler;
+    /// use velodyne::units::Degrees;
+    /// let mut assembler = Assembler::new();
+    /// assembler.cut_angle(Degrees(180.));
+    /// ```
+    pub fn cut_angle(&mut self, cut_angle: Degrees) -> &mut Assembler {
+        self.cut_angle = cut_angle.0.rem_euclid(360.);
+        self
+    }
+
+    /// Sets which way the points' azimuths increase, so rotations are detected in decoders'
+    /// counterclockwise azimuths too.
+    ///
+    /// The cut angle is in the same direction as the points' azimuths.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use velodyne::frame::Assembler;
+    /// use velodyne::vlp_16::AzimuthDirection;
+    /// let mut assembler = Assembler::new();
+    /// assembler.azimuth_direction(AzimuthDirection::Counterclockwise);
+    /// ```
+    pub fn azimuth_direction(&mut self, azimuth_direction: AzimuthDirection) -> &mut Assembler {
+        self.azimuth_direction = azimuth_direction;
+        self
+    }
+
+    /// Adds a point to the assembler, returning the previous frame if this point starts a new one.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use velodyne::frame::Assembler;
+    /// use velodyne::fixtures::VLP_16_DATA_PACKET;
+    /// use velodyne::vlp_16::Packet;
+    /// let mut assembler = Assembler::new();
+    /// for point in Packet::new(&VLP_16_DATA_PACKET).unwrap().points().unwrap() {
+    ///     assert!(assembler.push(point).is_none());
+    /// }
+    /// ```
+    pub fn push(&mut self, point: Point) -> Option<Frame> {
+        let azimuth = (point.azimuth.degrees() - self.cut_angle).rem_euclid(360.);
+        let azimuth = self.azimuth_direction.convert_degrees(azimuth);
+        let frame = match self.last_azimuth {
+            Some(last_azimuth) if last_azimuth - azimuth > WRAP_THRESHOLD => {
+                let complete = self.started_at_wrap;
+                let frame = self.finish().map(|frame| Frame { complete, ..frame });
+                self.started_at_wrap = true;
+                frame
+            }
+            _ => None,
+        };
+        self.last_azimuth = Some(azimuth);
+        self.points.push(point);
+        if self.keep_latest {
+            if let Some(frame) = frame.filter(|frame| frame.complete) {
+                if self.latest.replace(frame).is_some() {
+                    self.dropped += 1;
+                }
+            }
+            None
+        } else {
+            frame
+        }
+    }
+
+    /// Takes the most recent complete frame, if there is one.
+    ///
+    /// Only useful for assemblers created with `keep_latest`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use velodyne::frame::Assembler;
+    /// let mut assembler = Assembler::keep_latest();
+    /// assert!(assembler.latest_frame().is_none());
+    /// ```
+    pub fn latest_frame(&mut self) -> Option<Frame> {
+        self.latest.take()
+    }
+
+    /// Returns the number of complete frames that were dropped without being retrieved.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use velodyne::frame::Assembler;
+    /// let assembler = Assembler::keep_latest();
+    /// assert_eq!(0, assembler.dropped());
+    /// ```
+    pub fn dropped(&self) -> usize {
+        self.dropped
+    }
+
+    /// Returns the partially-assembled frame, if there is one, and resets the assembler.
+    ///
+    /// The returned frame is never complete.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use velodyne::frame::Assembler;
+    /// # use velodyne::fixtures::VLP_16_DATA_PACKET;
+    /// # use velodyne::vlp_16::Packet;
+    /// let mut assembler = Assembler::new();
+    /// for point in Packet::new(&VLP_16_DATA_PACKET).unwrap().points().unwrap() {
+    ///     assembler.push(point);
+    /// }
+    /// let frame = assembler.finish().unwrap();
+    /// assert_eq!(384, frame.points.len());
+    /// ```
+    pub fn finish(&mut self) -> Option<Frame> {
+        self.last_azimuth = None;
+        self.started_at_wrap = false;
+        if self.points.is_empty() {
+            None
+        } else {
+            Some(Frame::new(self.points.split_off(0)))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::Duration;
+    use point::{Azimuth, ReturnType, Time};
+
+    fn point(azimuth: f32) -> Point {
+        Point {
+            x: 0.,
+            y: 0.,
+            z: 0.,
+            reflectivity: 0,
+            channel: 0,
+            sequence: 0,
+            return_type: ReturnType::Strongest,
+            azimuth: Azimuth::Measured(Degrees(azimuth)),
+            time: Time::Offset(Duration::zero()),
+            sensor_id: None,
+        }
+    }
+
+    #[test]
+    fn wrap() {
+        let mut assembler = Assembler::new();
+        assert!(assembler.push(point(358.)).is_none());
+        assert!(assembler.push(point(359.)).is_none());
+        let frame = assembler.push(point(0.5)).unwrap();
+        assert_eq!(2, frame.points.len());
+        assert_eq!(1, assembler.finish().unwrap().points.len());
+        assert!(assembler.finish().is_none());
+    }
+
+    #[test]
+    fn cut_angle() {
+        let mut assembler = Assembler::new();
+        assembler.cut_angle(Degrees(-270.));
+        assert!(assembler.push(point(359.)).is_none());
+        assert!(assembler.push(point(0.5)).is_none());
+        assert!(assembler.push(point(89.)).is_none());
+        let frame = assembler.push(point(91.)).unwrap();
+        assert_eq!(3, frame.points.len());
+    }
+
+    #[test]
+    fn counterclockwise() {
+        let mut assembler = Assembler::new();
+        assembler.azimuth_direction(AzimuthDirection::Counterclockwise).cut_angle(Degrees(90.));
+        assert!(assembler.push(point(92.)).is_none());
+        assert!(assembler.push(point(91.)).is_none());
+        let frame = assembler.push(point(89.)).unwrap();
+        assert_eq!(2, frame.points.len());
+        assert!(assembler.push(point(89.1)).is_none());
+        assert!(assembler.push(point(2.)).is_none());
+        assert!(assembler.push(point(358.)).is_none());
+        assert!(assembler.push(point(91.)).is_none());
+        assert!(assembler.push(point(89.)).unwrap().complete);
+    }
+
+    #[test]
+    fn complete() {
+        let mut assembler = Assembler::new();
+        assert!(assembler.push(point(350.)).is_none());
+        assert!(!assembler.push(point(10.)).unwrap().complete);
+        assert!(assembler.push(point(350.)).is_none());
+        assert!(assembler.push(point(20.)).unwrap().complete);
+        assert!(!assembler.finish().unwrap().complete);
+    }
+
+    #[test]
+    fn metadata() {
+        let mut points = Vec::new();
+        for (i, &azimuth) in [10., 20., 30., 40.].iter().enumerate() {
+            let mut point = point(azimuth);
+            let us = if i < 3 { i as i64 * 1000 } else { 6000 };
+            point.time = Time::Offset(Duration::microseconds(us));
+            points.push(point);
+        }
+        let metadata = Frame::new(points.clone()).metadata();
+        assert_eq!(Some(10.), metadata.min_azimuth);
+        assert_eq!(Some(40.), metadata.max_azimuth);
+        assert_eq!(Duration::microseconds(6000), metadata.duration);
+        assert_eq!(2, metadata.dropped_packets);
+        // HDL-32E packets are 553 µs apart, so even the millisecond steps drop one.
+        assert_eq!(8, Frame::new(points.clone()).metadata_for(Sensor::HDL_32E).dropped_packets);
+        assert!((metadata.rpm.unwrap() - 30. / 360. / (6e-3 / 60.)).abs() < 1e-2);
+        for point in &mut points {
+            point.azimuth = AzimuthDirection::Counterclockwise.convert(point.azimuth);
+        }
+        let counterclockwise = Frame::new(points).metadata();
+        assert!((metadata.rpm.unwrap() - counterclockwise.rpm.unwrap()).abs() < 1e-2);
+    }
+
+    #[test]
+    fn keep_latest() {
+        let mut assembler = Assembler::keep_latest();
+        for &azimuth in &[350., 10., 350., 20., 350., 30.] {
+            assert!(assembler.push(point(azimuth)).is_none());
+        }
+        let frame = assembler.latest_frame().unwrap();
+        assert_eq!(20., frame.points[0].azimuth.degrees());
+        assert_eq!(1, assembler.dropped());
+        assert!(assembler.latest_frame().is_none());
+    }
+
+    #[test]
+    fn keep_latest_skips_partial_frame() {
+        let mut assembler = Assembler::keep_latest();
+        for &azimuth in &[180., 350., 10.] {
+            assert!(assembler.push(point(azimuth)).is_none());
+        }
+        assert!(assembler.latest_frame().is_none());
+        assert_eq!(0, assembler.dropped());
+        for &azimuth in &[180., 350., 10.] {
+            assert!(assembler.push(point(azimuth)).is_none());
+        }
+        let frame = assembler.latest_frame().unwrap();
+        assert!(frame.complete);
+        assert_eq!(10., frame.points[0].azimuth.degrees());
+    }
+
+    #[test]
+    fn small_backwards_step() {
+        let mut assembler = Assembler::new();
+        assert!(assembler.push(point(10.)).is_none());
+        assert!(assembler.push(point(9.9)).is_none());
+    }
+}
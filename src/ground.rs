@@ -0,0 +1,253 @@
+//! Separate the ground from everything else.
+//!
+//! This is a rough, per-frame heuristic on the organized range image: each column is walked from
+//! the lowest laser up, and a point is ground as long as the slope from the ground point below it
+//! stays shallow. It's fast and needs no tuning for a level sensor, but it doesn't model steep
+//! terrain or curbs.
+//!
+//! ```
+//! use velodyne::ground::{Label, Segmenter};
+//! let frames = velodyne::read_pcap_frames("data/single.pcap", &Default::default()).unwrap();
+//! let labels = Segmenter::new().segment(&frames[0].points);
+//! let ground = labels.iter().filter(|&&label| label == Label::Ground).count();
+//! ```
+
+use Point;
+use range_image::RangeImage;
+use units::{Degrees, Meters};
+
+const DEFAULT_MAX_SLOPE: f32 = 10.;
+const DEFAULT_COLUMNS: usize = 1800;
+
+/// What a point was labeled as.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Label {
+    /// The point is on the ground.
+    Ground,
+    /// The point is above the ground, e.g. on a wall, a vehicle, or vegetation.
+    NonGround,
+    /// The point has no return, so it can't be labeled.
+    NoReturn,
+}
+
+/// Labels points as ground or non-ground.
+#[derive(Clone, Copy, Debug)]
+pub struct Segmenter {
+    max_slope: f32,
+    ground_height: Option<(f32, f32)>,
+    columns: usize,
+}
+
+impl Segmenter {
+    /// Creates a new segmenter.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use velodyne::ground::Segmenter;
+    /// let segmenter = Segmenter::new();
+    /// ```
+    pub fn new() -> Segmenter {
+        Segmenter::default()
+    }
+
+    /// Sets the steepest slope between vertically adjacent points that is still ground.
+    ///
+    /// The default is ten degrees.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use velodyne::ground::Segmenter;
+    /// use velodyne::units::Degrees;
+    /// let mut segmenter = Segmenter::new();
+    /// segmenter.max_slope(Degrees(15.));
+    /// ```
+    pub fn max_slope(&mut self, max_slope: Degrees) -> &mut Segmenter {
+        self.max_slope = max_slope.0;
+        self
+    }
+
+    /// Sets the height of the ground in the points' frame, e.g. `Meters(-1.8)` for a level sensor
+    /// 1.8 meters up, and how far from it the lowest point of a column can be.
+    ///
+    /// Without a ground height, the lowest point of every column is assumed to be ground, so
+    /// columns that only hit an obstacle have a single misplaced ground point.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use velodyne::ground::Segmenter;
+    /// use velodyne::units::Meters;
+    /// let mut segmenter = Segmenter::new();
+    /// segmenter.ground_height(Meters(-1.8), Meters(0.3));
+    /// ```
+    pub fn ground_height(&mut self, height: Meters, tolerance: Meters) -> &mut Segmenter {
+        self.ground_height = Some((height.0, tolerance.0));
+        self
+    }
+
+    /// Sets the number of azimuth bins in the range image.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `columns` is zero.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use velodyne::ground::Segmenter;
+    /// let mut segmenter = Segmenter::new();
+    /// segmenter.columns(3600);
+    /// ```
+    pub fn columns(&mut self, columns: usize) -> &mut Segmenter {
+        assert!(columns > 0, "a range image needs at least one column");
+        self.columns = columns;
+        self
+    }
+
+    /// Labels points, returning one label per point.
+    ///
+    /// Points share the label of their range image cell, so all returns in a cell are labeled
+    /// alike. Points from lasers that the range image has no row for, e.g. the HDL-32E's upper
+    /// channels, are non-ground.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use velodyne::ground::Segmenter;
+    /// use velodyne::fixtures::VLP_16_DATA_PACKET;
+    /// use velodyne::vlp_16::Packet;
+    /// let points = Packet::new(&VLP_16_DATA_PACKET).unwrap().points().unwrap();
+    /// assert_eq!(points.len(), Segmenter::new().segment(&points).len());
+    /// ```
+    pub fn segment(&self, points: &[Point]) -> Vec<Label> {
+        let range_image = RangeImage::from_points(points, self.columns);
+        let rows = range_image.rows();
+        let mut cells = vec![Label::NonGround; rows * self.columns];
+        for column in 0..self.columns {
+            let mut below: Option<(&Point, bool)> = None;
+            for row in (0..rows).rev() {
+                let point = match range_image.get(row, column) {
+                    Some(cell) => &points[cell.point],
+                    None => continue,
+                };
+                let ground = match below {
+                    None => {
+                        self.ground_height
+                            .is_none_or(|(height, tolerance)| (point.z - height).abs() <= tolerance)
+                    }
+                    Some((below, ground)) => ground && self.slope(below, point) <= self.max_slope,
+                };
+                if ground {
+                    cells[row * self.columns + column] = Label::Ground;
+                }
+                below = Some((point, ground));
+            }
+        }
+        points.iter()
+            .map(|point| if point.range() == 0. {
+                     Label::NoReturn
+                 } else if let Some(row) = RangeImage::point_row(point) {
+                     cells[row * self.columns + range_image.column(point.azimuth.degrees())]
+                 } else {
+                     Label::NonGround
+                 })
+            .collect()
+    }
+
+    fn slope(&self, below: &Point, above: &Point) -> f32 {
+        let horizontal = ((above.x - below.x).powi(2) + (above.y - below.y).powi(2)).sqrt();
+        (above.z - below.z).abs().atan2(horizontal).to_degrees()
+    }
+}
+
+impl Default for Segmenter {
+    fn default() -> Segmenter {
+        Segmenter {
+            max_slope: DEFAULT_MAX_SLOPE,
+            ground_height: None,
+            columns: DEFAULT_COLUMNS,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::Duration;
+    use point::{Azimuth, ReturnType, Time};
+    use vlp_16::vertical_angle;
+
+    /// A level sensor 2 m up, with a wall 8 m away at zero azimuth.
+    fn scene() -> Vec<Point> {
+        let mut points = Vec::new();
+        for &azimuth in &[0.5, 180.5] {
+            for channel in 0..16 {
+                let elevation = vertical_angle(channel);
+                let ground = if elevation < 0. {
+                    2. / (-elevation).to_radians().sin()
+                } else {
+                    0.
+                };
+                let range = if azimuth < 90. {
+                    let wall = 8. / elevation.to_radians().cos();
+                    if ground > 0. { ground.min(wall) } else { wall }
+                } else {
+                    ground
+                };
+                points.push(Point::from_spherical(Meters(range),
+                                                  Azimuth::Measured(Degrees(azimuth)),
+                                                  Degrees(elevation),
+                                                  0,
+                                                  channel as u8,
+                                                  ReturnType::Strongest,
+                                                  Time::Offset(Duration::zero())));
+            }
+        }
+        points
+    }
+
+    #[test]
+    fn segment() {
+        let points = scene();
+        let labels = Segmenter::new().segment(&points);
+        for (point, &label) in points.iter().zip(&labels) {
+            let expected = if point.range() == 0. {
+                Label::NoReturn
+            } else if point.z < -1.99 {
+                Label::Ground
+            } else {
+                Label::NonGround
+            };
+            assert_eq!(expected, label, "{:?}", point);
+        }
+        assert!(labels.contains(&Label::NonGround));
+    }
+
+    #[test]
+    fn ground_height() {
+        let points = scene();
+        let mut segmenter = Segmenter::new();
+        segmenter.ground_height(Meters(-5.), Meters(0.5));
+        assert!(segmenter.segment(&points).iter().all(|&label| label != Label::Ground));
+        segmenter.ground_height(Meters(-2.), Meters(0.5));
+        assert!(segmenter.segment(&points).contains(&Label::Ground));
+    }
+
+    #[test]
+    fn segment_hdl_32e() {
+        use fixtures::RELABELLED_HDL_32E_DATA_PACKET;
+        use vlp_16::Packet;
+        let points = Packet::new(&RELABELLED_HDL_32E_DATA_PACKET).unwrap().points().unwrap();
+        let labels = Segmenter::new().segment(&points);
+        for (point, &label) in points.iter().zip(&labels).filter(|(p, _)| p.channel >= 16) {
+            let expected = if point.range() == 0. {
+                Label::NoReturn
+            } else {
+                Label::NonGround
+            };
+            assert_eq!(expected, label);
+        }
+    }
+}
@@ -0,0 +1,228 @@
+//! Per-laser statistics for spotting dirty windows and failing lasers.
+//!
+//! A laser that returns far less often than the lasers above and below it usually means that
+//! part of the sensor's window needs cleaning, or that the laser is failing.
+
+use {Point, Result};
+use io::Read;
+use range_image::RangeImage;
+use vlp_16::NUM_LASERS;
+
+/// Statistics for every laser, accumulated over many points.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct Health {
+    channels: [Channel; NUM_LASERS],
+}
+
+/// Statistics for one laser.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct Channel {
+    /// The number of points, including those without a return.
+    pub points: usize,
+    /// The number of points with a return.
+    pub returns: usize,
+    intensity_sum: u64,
+    range_sum: f64,
+}
+
+impl Health {
+    /// Creates new, empty statistics.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use velodyne::health::Health;
+    /// let health = Health::new();
+    /// ```
+    pub fn new() -> Health {
+        Health::default()
+    }
+
+    /// Accumulates statistics over all data packets from a reader.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use velodyne::health::Health;
+    /// use velodyne::io::Pcap;
+    /// let health = Health::from_read(Pcap::open("data/single.pcap").unwrap()).unwrap();
+    /// assert_eq!(24, health.channel(0).points);
+    /// ```
+    pub fn from_read<R: Read>(read: R) -> Result<Health> {
+        let mut health = Health::new();
+        for packet in read.vlp_16_packets() {
+            if let Some(points) = packet?.points() {
+                health.add(&points);
+            }
+        }
+        Ok(health)
+    }
+
+    /// Adds points to these statistics.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use velodyne::health::Health;
+    /// use velodyne::fixtures::VLP_16_DATA_PACKET;
+    /// use velodyne::vlp_16::Packet;
+    /// let mut health = Health::new();
+    /// health.add(&Packet::new(&VLP_16_DATA_PACKET).unwrap().points().unwrap());
+    /// ```
+    pub fn add(&mut self, points: &[Point]) {
+        for point in points {
+            if let Some(channel) = self.channels.get_mut(point.channel as usize) {
+                channel.add(point);
+            }
+        }
+    }
+
+    /// Returns the statistics for a laser channel.
+    ///
+    /// # Panics
+    ///
+    /// Panics if there is no such channel.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use velodyne::health::Health;
+    /// let health = Health::new();
+    /// assert_eq!(0, health.channel(15).points);
+    /// ```
+    pub fn channel(&self, channel: usize) -> &Channel {
+        &self.channels[channel]
+    }
+
+    /// Returns the channels whose return rate is less than `ratio` times the average return rate
+    /// of the lasers directly above and below them.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use velodyne::health::Health;
+    /// use velodyne::io::Pcap;
+    /// let health = Health::from_read(Pcap::open("data/single.pcap").unwrap()).unwrap();
+    /// let suspects = health.suspect_channels(0.25);
+    /// ```
+    pub fn suspect_channels(&self, ratio: f32) -> Vec<usize> {
+        let mut by_row = [0; NUM_LASERS];
+        for channel in 0..NUM_LASERS {
+            by_row[RangeImage::row(channel)] = channel;
+        }
+        (0..NUM_LASERS)
+            .filter(|&channel| {
+                let row = RangeImage::row(channel);
+                let neighbors = [row.checked_sub(1), Some(row + 1).filter(|&row| row < NUM_LASERS)];
+                let rates = neighbors
+                    .iter()
+                    .filter_map(|row| row.and_then(|row| self.channels[by_row[row]].return_rate()))
+                    .collect::<Vec<_>>();
+                match self.channels[channel].return_rate() {
+                    Some(rate) if !rates.is_empty() => {
+                        rate < ratio * rates.iter().sum::<f32>() / rates.len() as f32
+                    }
+                    _ => false,
+                }
+            })
+            .collect()
+    }
+}
+
+impl Channel {
+    fn add(&mut self, point: &Point) {
+        self.points += 1;
+        let range = point.range();
+        if range > 0. {
+            self.returns += 1;
+            self.intensity_sum += u64::from(point.reflectivity);
+            self.range_sum += f64::from(range);
+        }
+    }
+
+    /// Returns the fraction of points that have a return, or `None` if there are no points.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use velodyne::health::Health;
+    /// assert!(Health::new().channel(0).return_rate().is_none());
+    /// ```
+    pub fn return_rate(&self) -> Option<f32> {
+        if self.points == 0 {
+            None
+        } else {
+            Some(self.returns as f32 / self.points as f32)
+        }
+    }
+
+    /// Returns the mean calibrated reflectivity of the points with a return.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use velodyne::health::Health;
+    /// assert!(Health::new().channel(0).mean_intensity().is_none());
+    /// ```
+    pub fn mean_intensity(&self) -> Option<f32> {
+        if self.returns == 0 {
+            None
+        } else {
+            Some(self.intensity_sum as f32 / self.returns as f32)
+        }
+    }
+
+    /// Returns the mean range of the points with a return, in meters.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use velodyne::health::Health;
+    /// assert!(Health::new().channel(0).mean_range().is_none());
+    /// ```
+    pub fn mean_range(&self) -> Option<f32> {
+        if self.returns == 0 {
+            None
+        } else {
+            Some((self.range_sum / self.returns as f64) as f32)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::Duration;
+    use point::{Azimuth, ReturnType, Time};
+    use units::{Degrees, Meters};
+
+    fn point(channel: u8, range: f32) -> Point {
+        Point::from_spherical(Meters(range),
+                              Azimuth::Measured(Degrees(0.)),
+                              Degrees(0.),
+                              10,
+                              channel,
+                              ReturnType::Strongest,
+                              Time::Offset(Duration::zero()))
+    }
+
+    #[test]
+    fn suspect_channels() {
+        let mut health = Health::new();
+        for _ in 0..20 {
+            for channel in 0..NUM_LASERS as u8 {
+                health.add(&[point(channel, 10.)]);
+            }
+        }
+        for _ in 0..80 {
+            health.add(&[point(3, 0.)]);
+        }
+        let channel = health.channel(3);
+        assert_eq!(100, channel.points);
+        assert_eq!(Some(0.2), channel.return_rate());
+        assert_eq!(Some(10.), channel.mean_intensity());
+        assert!((channel.mean_range().unwrap() - 10.).abs() < 1e-4);
+        assert_eq!(vec![3], health.suspect_channels(0.25));
+        assert!(health.suspect_channels(0.1).is_empty());
+    }
+}
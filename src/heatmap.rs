@@ -0,0 +1,327 @@
+//! Accumulate points into top-down intensity and density heatmaps.
+//!
+//! A heatmap grids every point of a capture onto the ground plane, so road markings stand out in
+//! the mean intensity and coverage gaps stand out in the density:
+//!
+//! ```
+//! use velodyne::heatmap::{Heatmap, Value};
+//! use velodyne::io::{Pcap, Read};
+//! let mut heatmap = Heatmap::new(0.5);
+//! for point in Pcap::open("data/single.pcap").unwrap().points() {
+//!     heatmap.add(&point);
+//! }
+//! let raster = heatmap.raster(Value::Density).unwrap();
+//! let mut raw = Vec::new();
+//! raster.write_raw(&mut raw).unwrap();
+//! assert_eq!(raster.width * raster.height * 4, raw.len());
+//! ```
+
+use Point;
+use Result;
+use byteorder::{LittleEndian, WriteBytesExt};
+#[cfg(feature = "png")]
+use panorama::Colormap;
+#[cfg(feature = "png")]
+use png::{BitDepth, ColorType, Encoder};
+use std::collections::HashMap;
+use std::f32;
+use std::io::Write;
+
+/// Accumulates points onto a grid of square cells.
+///
+/// Only occupied cells are stored, so the grid grows with the area that the capture covers.
+#[derive(Clone, Debug)]
+pub struct Heatmap {
+    resolution: f32,
+    cells: HashMap<(i64, i64), Cell>,
+}
+
+/// The value of each cell of a raster.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Value {
+    /// The mean calibrated reflectivity of the points in the cell.
+    Intensity,
+    /// The number of points per square meter.
+    Density,
+}
+
+/// A rectangular grid of values, ready to be written out.
+///
+/// Rows run from north to south, i.e. row zero is the largest y, as in most image and GIS formats.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Raster {
+    /// The number of columns.
+    pub width: usize,
+    /// The number of rows.
+    pub height: usize,
+    /// The x and y coordinates of the upper-left corner of the upper-left cell, in meters.
+    pub origin: [f64; 2],
+    /// The size of a cell, in meters.
+    pub resolution: f32,
+    /// The values, row by row. Cells without points are NaN.
+    pub data: Vec<f32>,
+}
+
+#[derive(Clone, Copy, Debug, Default)]
+struct Cell {
+    count: u32,
+    reflectivity: u64,
+}
+
+impl Heatmap {
+    /// Creates an empty heatmap with the given cell size, in meters.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the resolution is not positive.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use velodyne::heatmap::Heatmap;
+    /// let heatmap = Heatmap::new(0.1);
+    /// assert!(heatmap.is_empty());
+    /// ```
+    pub fn new(resolution: f32) -> Heatmap {
+        assert!(resolution > 0., "heatmap resolution must be positive");
+        Heatmap {
+            resolution,
+            cells: HashMap::new(),
+        }
+    }
+
+    /// Adds a point to the heatmap.
+    ///
+    /// Points without a return are skipped.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use velodyne::fixtures::VLP_16_DATA_PACKET;
+    /// use velodyne::heatmap::Heatmap;
+    /// use velodyne::vlp_16::Packet;
+    /// let points = Packet::new(&VLP_16_DATA_PACKET).unwrap().points().unwrap();
+    /// let mut heatmap = Heatmap::new(0.1);
+    /// heatmap.add(&points[0]);
+    /// assert_eq!(1, heatmap.len());
+    /// ```
+    pub fn add(&mut self, point: &Point) {
+        if point.range() == 0. {
+            return;
+        }
+        let key = (self.index(point.x), self.index(point.y));
+        let cell = self.cells.entry(key).or_default();
+        cell.count += 1;
+        cell.reflectivity += u64::from(point.reflectivity);
+    }
+
+    /// Adds points to the heatmap, e.g. a packet's or a frame's.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use velodyne::fixtures::VLP_16_DATA_PACKET;
+    /// use velodyne::heatmap::Heatmap;
+    /// use velodyne::vlp_16::Packet;
+    /// let points = Packet::new(&VLP_16_DATA_PACKET).unwrap().points().unwrap();
+    /// let mut heatmap = Heatmap::new(0.1);
+    /// heatmap.add_points(&points);
+    /// assert!(!heatmap.is_empty());
+    /// ```
+    pub fn add_points(&mut self, points: &[Point]) {
+        for point in points {
+            self.add(point);
+        }
+    }
+
+    /// Returns the number of occupied cells.
+    pub fn len(&self) -> usize {
+        self.cells.len()
+    }
+
+    /// Returns true if no points have been added.
+    pub fn is_empty(&self) -> bool {
+        self.cells.is_empty()
+    }
+
+    /// Returns a raster that covers every occupied cell, or `None` if the heatmap is empty.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use velodyne::fixtures::VLP_16_DATA_PACKET;
+    /// use velodyne::heatmap::{Heatmap, Value};
+    /// use velodyne::vlp_16::Packet;
+    /// let points = Packet::new(&VLP_16_DATA_PACKET).unwrap().points().unwrap();
+    /// let mut heatmap = Heatmap::new(1.);
+    /// assert!(heatmap.raster(Value::Intensity).is_none());
+    /// heatmap.add_points(&points);
+    /// let raster = heatmap.raster(Value::Intensity).unwrap();
+    /// assert_eq!(raster.width * raster.height, raster.data.len());
+    /// ```
+    pub fn raster(&self, value: Value) -> Option<Raster> {
+        let mut keys = self.cells.keys();
+        let &(x, y) = keys.next()?;
+        let (mut min_x, mut min_y, mut max_x, mut max_y) = (x, y, x, y);
+        for &(x, y) in keys {
+            min_x = min_x.min(x);
+            min_y = min_y.min(y);
+            max_x = max_x.max(x);
+            max_y = max_y.max(y);
+        }
+        let width = (max_x - min_x + 1) as usize;
+        let height = (max_y - min_y + 1) as usize;
+        let area = f64::from(self.resolution) * f64::from(self.resolution);
+        let mut data = vec![f32::NAN; width * height];
+        for (&(x, y), cell) in &self.cells {
+            let column = (x - min_x) as usize;
+            let row = (max_y - y) as usize;
+            data[row * width + column] = match value {
+                Value::Intensity => (cell.reflectivity as f64 / f64::from(cell.count)) as f32,
+                Value::Density => (f64::from(cell.count) / area) as f32,
+            };
+        }
+        Some(Raster {
+                 width,
+                 height,
+                 origin: [min_x as f64 * f64::from(self.resolution),
+                          (max_y + 1) as f64 * f64::from(self.resolution)],
+                 resolution: self.resolution,
+                 data,
+             })
+    }
+
+    fn index(&self, coordinate: f32) -> i64 {
+        (f64::from(coordinate) / f64::from(self.resolution)).floor() as i64
+    }
+}
+
+impl Raster {
+    /// Returns the value of a cell, or `None` if it has no points.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the row or column is out of bounds.
+    pub fn get(&self, row: usize, column: usize) -> Option<f32> {
+        assert!(row < self.height && column < self.width);
+        let value = self.data[row * self.width + column];
+        if value.is_nan() { None } else { Some(value) }
+    }
+
+    /// Writes the values as little-endian 32-bit floats, row by row, without a header.
+    ///
+    /// Together with `world_file`, GIS tools such as GDAL can read this as an ENVI-style raw
+    /// raster.
+    pub fn write_raw<W: Write>(&self, mut write: W) -> Result<()> {
+        for &value in &self.data {
+            write.write_f32::<LittleEndian>(value)?;
+        }
+        Ok(())
+    }
+
+    /// Returns an ESRI world file, which georeferences the raster.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use velodyne::heatmap::Raster;
+    /// let raster = Raster {
+    ///     width: 1,
+    ///     height: 1,
+    ///     origin: [-1., 2.],
+    ///     resolution: 0.5,
+    ///     data: vec![1.],
+    /// };
+    /// assert_eq!("0.5\n0\n0\n-0.5\n-0.75\n1.75\n", raster.world_file());
+    /// ```
+    pub fn world_file(&self) -> String {
+        let resolution = f64::from(self.resolution);
+        format!("{}\n0\n0\n{}\n{}\n{}\n",
+                resolution,
+                -resolution,
+                self.origin[0] + resolution / 2.,
+                self.origin[1] - resolution / 2.)
+    }
+
+    /// Writes the raster as a PNG, scaling values from zero to the largest value.
+    ///
+    /// Cells without points are black.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use velodyne::fixtures::VLP_16_DATA_PACKET;
+    /// use velodyne::heatmap::{Heatmap, Value};
+    /// use velodyne::panorama::Colormap;
+    /// use velodyne::vlp_16::Packet;
+    /// let points = Packet::new(&VLP_16_DATA_PACKET).unwrap().points().unwrap();
+    /// let mut heatmap = Heatmap::new(0.5);
+    /// heatmap.add_points(&points);
+    /// let mut png = Vec::new();
+    /// heatmap.raster(Value::Density).unwrap().write_png(Colormap::Jet, &mut png).unwrap();
+    /// ```
+    #[cfg(feature = "png")]
+    pub fn write_png<W: Write>(&self, colormap: Colormap, write: W) -> Result<()> {
+        let max = self.data.iter().cloned().filter(|value| !value.is_nan()).fold(0., f32::max);
+        let mut data = Vec::with_capacity(self.data.len() * 3);
+        for &value in &self.data {
+            let rgb = if value.is_nan() {
+                [0, 0, 0]
+            } else if max > 0. {
+                colormap.rgb(value / max)
+            } else {
+                colormap.rgb(0.)
+            };
+            data.extend_from_slice(&rgb);
+        }
+        let mut encoder = Encoder::new(write, self.width as u32, self.height as u32);
+        encoder.set_color(ColorType::Rgb);
+        encoder.set_depth(BitDepth::Eight);
+        let mut writer = encoder.write_header()?;
+        writer.write_image_data(&data)?;
+        writer.finish()?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use fixtures::VLP_16_DATA_PACKET;
+    use vlp_16::Packet;
+
+    fn point(x: f32, y: f32, reflectivity: u8) -> Point {
+        let mut point = Packet::new(&VLP_16_DATA_PACKET).unwrap().points().unwrap()[0];
+        point.x = x;
+        point.y = y;
+        point.z = 1.;
+        point.reflectivity = reflectivity;
+        point
+    }
+
+    #[test]
+    fn accumulate() {
+        let mut heatmap = Heatmap::new(1.);
+        heatmap.add(&point(0.5, 0.5, 10));
+        heatmap.add(&point(0.25, 0.75, 30));
+        heatmap.add(&point(-1.5, 2.5, 100));
+        assert_eq!(2, heatmap.len());
+        let raster = heatmap.raster(Value::Intensity).unwrap();
+        assert_eq!((3, 3), (raster.width, raster.height));
+        assert_eq!([-2., 3.], raster.origin);
+        assert_eq!(Some(100.), raster.get(0, 0));
+        assert_eq!(Some(20.), raster.get(2, 2));
+        assert_eq!(None, raster.get(1, 1));
+        let raster = heatmap.raster(Value::Density).unwrap();
+        assert_eq!(Some(2.), raster.get(2, 2));
+    }
+
+    #[test]
+    fn write_raw() {
+        let mut heatmap = Heatmap::new(0.5);
+        heatmap.add(&point(0., 0., 4));
+        let mut raw = Vec::new();
+        heatmap.raster(Value::Density).unwrap().write_raw(&mut raw).unwrap();
+        assert_eq!(vec![0, 0, 128, 64], raw);
+    }
+}
@@ -0,0 +1,971 @@
+//! Summarize the contents of a capture.
+
+use {Point, Result};
+use byteorder::{ByteOrder, LittleEndian};
+use chrono::{DateTime, Duration, UTC};
+use cloud::Bounds;
+use frame::{Assembler, Frame};
+use io::Read;
+use nmea::Position;
+use point::Time;
+use std::collections::{BTreeMap, VecDeque};
+use time::HourOffset;
+use timing::Timing;
+use vlp_16::{self, AzimuthSpan, Decoder, Packet, PacketKind, ReturnMode, Sensor};
+use wire;
+
+/// The number of azimuth bins used to measure coverage, one per degree.
+const AZIMUTH_BINS: usize = 360;
+
+/// The number of microseconds in an hour, after which the sensor's timestamps roll over.
+const HOUR_US: i64 = 3_600_000_000;
+
+/// The number of gaps that `Loss` remembers.
+const RECENT_GAPS: usize = 100;
+
+/// The number of standard errors on either side of an estimate for 95% confidence.
+const Z_95: f64 = 1.96;
+
+/// A summary of a stream of packets.
+#[derive(Clone, Debug, Default)]
+pub struct Info {
+    /// The number of data packets.
+    pub data_packets: usize,
+    /// The number of position packets.
+    pub position_packets: usize,
+    /// The number of points, including those without a return.
+    pub points: usize,
+    /// The spatial extent of the points, if it was requested.
+    pub extent: Option<Extent>,
+}
+
+/// The spatial extent of the points with a return.
+#[derive(Clone, Copy, Debug)]
+pub struct Extent {
+    /// The bounding box of the points, or `None` if no point had a return.
+    pub bounds: Option<Bounds>,
+    azimuths: [bool; AZIMUTH_BINS],
+}
+
+/// A summary of a stream of packets that only looks at a few bytes of each packet.
+///
+/// Packets are classified by their length, so a scan is much faster than decoding every data
+/// block of every packet. Position packets are the exception, since their NMEA sentences are
+/// short and they only arrive about once a second.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct Scan {
+    /// The number of data packets.
+    pub data_packets: usize,
+    /// The number of position packets.
+    pub position_packets: usize,
+    /// The number of packets that are neither data nor position packets.
+    pub other_packets: usize,
+    /// The timestamp of the first data or position packet.
+    pub first_timestamp: Option<HourOffset>,
+    /// The timestamp of the last data or position packet.
+    pub last_timestamp: Option<HourOffset>,
+    /// The return mode of the first data packet.
+    pub return_mode: Option<ReturnMode>,
+    /// The sensor of the first data packet.
+    pub sensor: Option<Sensor>,
+    /// The capture time of the first packet, e.g. from a pcap file, if the reader has one.
+    pub first_capture_time: Option<DateTime<UTC>>,
+    /// The capture time of the last packet.
+    pub last_capture_time: Option<DateTime<UTC>>,
+    /// The first position reported by the GNSS receiver, whether or not it was valid.
+    pub first_position: Option<Position>,
+    /// The last position reported by the GNSS receiver.
+    pub last_position: Option<Position>,
+    /// The number of times that consecutive data packets were too far apart, or out of order.
+    pub gaps: usize,
+    /// The longest time between consecutive data packets that were too far apart.
+    pub largest_gap: Option<Duration>,
+    last_data: Option<(i64, u16)>,
+    device_us: i64,
+    rotation_us: i64,
+    swept: i64,
+}
+
+/// Running packet-loss statistics, e.g. for a live stream.
+///
+/// Data packets are sent at a fixed rate that only depends on the return mode, so missing packets
+/// are counted from the sensor's timestamps: a packet that arrives three packet periods after the
+/// previous one means that two packets were lost.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Loss {
+    /// The number of data packets that were received.
+    pub received: usize,
+    /// The estimated number of data packets that were lost.
+    pub missing: usize,
+    /// The number of data packets whose timestamp wasn't after the previous packet's.
+    pub out_of_order: usize,
+    /// The most recent gaps, oldest first, up to a hundred of them.
+    pub gaps: VecDeque<Gap>,
+    last: Option<i64>,
+    device_us: i64,
+}
+
+/// A summary of a capture, estimated from every nth packet.
+///
+/// The packets in between are skipped without being read, where the source allows it, so even a
+/// huge capture can be sampled quickly. Packets are still counted exactly, since skipping a packet
+/// means reading its length, and the counts of data packets and points are scaled up from the
+/// sample. The estimates are only as good as the sample is representative, so a capture that
+/// changes return mode or sensor partway through needs a smaller stride.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Sample {
+    /// The number of packets, sampled or not.
+    pub packets: usize,
+    /// The number of packets that were sampled.
+    pub sampled: usize,
+    /// The estimated number of data packets.
+    pub data_packets: Estimate,
+    /// The estimated number of position packets.
+    pub position_packets: Estimate,
+    /// The estimated number of points, including those without a return.
+    pub points: Estimate,
+    /// The estimated fraction of data packets that were lost, or `None` if fewer than two data
+    /// packets were sampled.
+    pub loss: Option<Estimate>,
+}
+
+/// An estimate and its 95% confidence interval.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Estimate {
+    /// The most likely value.
+    pub value: f64,
+    /// The low end of the confidence interval.
+    pub low: f64,
+    /// The high end of the confidence interval.
+    pub high: f64,
+}
+
+/// The azimuth coverage of every frame in a capture, and the times between frames.
+///
+/// A frame that doesn't cover every degree of azimuth is missing packets, or the sensor's rotation
+/// glitched, so it probably shouldn't be used for mapping. The first and last frames of a capture
+/// are usually partial too.
+#[derive(Clone, Debug, Default)]
+pub struct Coverage {
+    /// The number of frames.
+    pub frames: usize,
+    /// The indexes of the frames, counting from zero, that cover less than 360 degrees.
+    pub partial_frames: Vec<usize>,
+    /// A histogram of the times between the starts of consecutive frames, as the number of gaps
+    /// of each length in whole milliseconds.
+    pub gaps: BTreeMap<i64, usize>,
+    last_start: Option<Time>,
+}
+
+/// The coverage of one frame.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct FrameCoverage {
+    /// The number of degrees of azimuth that contain at least one point, with or without a
+    /// return.
+    pub degrees: usize,
+    /// The time since the start of the previous frame.
+    pub gap: Option<Duration>,
+}
+
+/// Where some data packets were lost.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Gap {
+    /// The timestamp of the packet before the gap.
+    pub after: Duration,
+    /// The estimated number of packets that were lost.
+    pub missing: usize,
+    /// The capture time of the packet after the gap, if known.
+    pub capture_time: Option<DateTime<UTC>>,
+}
+
+impl Info {
+    /// Creates a new, empty summary that only counts packets and points.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use velodyne::info::Info;
+    /// let info = Info::new();
+    /// assert!(info.extent.is_none());
+    /// ```
+    pub fn new() -> Info {
+        Info::default()
+    }
+
+    /// Creates a new, empty summary that also tracks the spatial extent of the points.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use velodyne::info::Info;
+    /// let info = Info::with_extent();
+    /// assert!(info.extent.is_some());
+    /// ```
+    pub fn with_extent() -> Info {
+        Info { extent: Some(Extent::new()), ..Default::default() }
+    }
+
+    /// Summarizes all packets from a reader.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use velodyne::info::Info;
+    /// use velodyne::io::Pcap;
+    /// let info = Info::from_read(Pcap::open("data/single.pcap").unwrap(), true).unwrap();
+    /// assert_eq!(384, info.points);
+    /// ```
+    pub fn from_read<R: Read>(read: R, extent: bool) -> Result<Info> {
+        let mut info = if extent { Info::with_extent() } else { Info::new() };
+        for packet in read.vlp_16_packets() {
+            info.add(&packet?);
+        }
+        Ok(info)
+    }
+
+    /// Adds a packet to this summary.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use velodyne::info::Info;
+    /// use velodyne::fixtures::VLP_16_DATA_PACKET;
+    /// use velodyne::vlp_16::Packet;
+    /// let mut info = Info::new();
+    /// info.add(&Packet::new(&VLP_16_DATA_PACKET).unwrap());
+    /// assert_eq!(1, info.data_packets);
+    /// ```
+    pub fn add(&mut self, packet: &Packet) {
+        match *packet {
+            Packet::Data(ref data_packet) => {
+                self.data_packets += 1;
+                self.points += data_packet.point_count(false);
+                if let Some(ref mut extent) = self.extent {
+                    for point in &packet.points().unwrap_or_default() {
+                        extent.add(point);
+                    }
+                }
+            }
+            Packet::Position(_) => self.position_packets += 1,
+        }
+    }
+}
+
+impl Scan {
+    /// Scans all packets from a reader.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use velodyne::info::Scan;
+    /// use velodyne::io::Pcap;
+    /// let scan = Scan::from_read(Pcap::open("data/single.pcap").unwrap()).unwrap();
+    /// assert_eq!(1, scan.data_packets);
+    /// ```
+    pub fn from_read<R: Read>(mut read: R) -> Result<Scan> {
+        let mut scan = Scan::default();
+        while let Some(result) = read.read() {
+            scan.add(result?);
+            if let Some(time) = read.timestamp() {
+                scan.add_capture_time(time);
+            }
+        }
+        Ok(scan)
+    }
+
+    /// Adds the bytes of one packet to this scan.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use velodyne::fixtures::VLP_16_DATA_PACKET;
+    /// use velodyne::info::Scan;
+    /// let mut scan = Scan::default();
+    /// scan.add(&VLP_16_DATA_PACKET);
+    /// assert_eq!(1, scan.data_packets);
+    /// ```
+    pub fn add(&mut self, bytes: &[u8]) {
+        let is_data = match vlp_16::classify(bytes) {
+            Some(kind) => kind == PacketKind::Data,
+            None => {
+                debug!("skipping {} byte packet", bytes.len());
+                self.other_packets += 1;
+                return;
+            }
+        };
+        let payload = vlp_16::payload(bytes);
+        let timestamp_offset = if is_data {
+            self.data_packets += 1;
+            if self.return_mode.is_none() {
+                self.return_mode = ReturnMode::from_u8(payload[wire::RETURN_MODE.offset]).ok();
+                self.sensor = Sensor::from_u8(payload[wire::SENSOR.offset]).ok();
+            }
+            wire::DATA_TIMESTAMP.offset
+        } else {
+            self.position_packets += 1;
+            if let Some(Ok(position)) = Packet::new(bytes).ok().and_then(|p| p.position()) {
+                if self.first_position.is_none() {
+                    self.first_position = Some(position);
+                }
+                self.last_position = Some(position);
+            }
+            wire::POSITION_TIMESTAMP.offset
+        };
+        let micros = LittleEndian::read_u32(&payload[timestamp_offset..]);
+        let timestamp = HourOffset::from_micros(micros);
+        if is_data {
+            self.add_data_timestamp(i64::from(micros), AzimuthSpan::from_payload(payload).first.0);
+        }
+        if self.first_timestamp.is_none() {
+            self.first_timestamp = Some(timestamp);
+        }
+        self.last_timestamp = Some(timestamp);
+    }
+
+    /// Adds the capture time of a packet, e.g. from `Read::timestamp`.
+    ///
+    /// `from_read` does this for every packet.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # extern crate chrono;
+    /// # extern crate velodyne;
+    /// # fn main() {
+    /// use chrono::{TimeZone, UTC};
+    /// use velodyne::info::Scan;
+    /// let mut scan = Scan::default();
+    /// scan.add_capture_time(UTC.timestamp(1_500_000_000, 0));
+    /// scan.add_capture_time(UTC.timestamp(1_500_000_060, 0));
+    /// assert_eq!(60, scan.capture_duration().unwrap().num_seconds());
+    /// # }
+    /// ```
+    pub fn add_capture_time(&mut self, time: DateTime<UTC>) {
+        if self.first_capture_time.is_none() {
+            self.first_capture_time = Some(time);
+        }
+        self.last_capture_time = Some(time);
+    }
+
+    /// Returns the time between the first and last captured packets, by the capture clock.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use velodyne::info::Scan;
+    /// use velodyne::io::Pcap;
+    /// let scan = Scan::from_read(Pcap::open("data/single.pcap").unwrap()).unwrap();
+    /// assert_eq!(0, scan.capture_duration().unwrap().num_microseconds().unwrap());
+    /// ```
+    pub fn capture_duration(&self) -> Option<Duration> {
+        match (self.first_capture_time, self.last_capture_time) {
+            (Some(first), Some(last)) => Some(last.signed_duration_since(first)),
+            _ => None,
+        }
+    }
+
+    /// Returns the time between the first and last data packets, by the sensor's clock.
+    ///
+    /// The sensor's timestamps roll over at the top of every hour, so the duration is added up
+    /// from one packet to the next. Time that runs backwards isn't counted.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use velodyne::fixtures::VLP_16_DATA_PACKET;
+    /// use velodyne::info::Scan;
+    /// let mut scan = Scan::default();
+    /// assert!(scan.device_duration().is_none());
+    /// scan.add(&VLP_16_DATA_PACKET);
+    /// assert_eq!(0, scan.device_duration().unwrap().num_microseconds().unwrap());
+    /// ```
+    pub fn device_duration(&self) -> Option<Duration> {
+        self.last_data.map(|_| Duration::microseconds(self.device_us))
+    }
+
+    /// Returns the sensor's rotation rate, in revolutions per minute.
+    ///
+    /// The rate is estimated from how far the azimuth turns between consecutive data packets, so
+    /// it needs at least two data packets without a gap between them.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use velodyne::fixtures::VLP_16_DATA_PACKET;
+    /// use velodyne::info::Scan;
+    /// let mut scan = Scan::default();
+    /// scan.add(&VLP_16_DATA_PACKET);
+    /// assert!(scan.rpm().is_none());
+    /// ```
+    pub fn rpm(&self) -> Option<f64> {
+        if self.rotation_us == 0 {
+            None
+        } else {
+            Some(self.swept as f64 / 36_000. / (self.rotation_us as f64 / 60_000_000.))
+        }
+    }
+
+    /// Returns the number of points that a full rotation should have, from the measured rotation
+    /// rate and the first data packet's sensor and return mode.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use velodyne::info::Scan;
+    /// use velodyne::io::Pcap;
+    /// let scan = Scan::from_read(Pcap::open("data/single.pcap").unwrap()).unwrap();
+    /// assert!(scan.expected_points_per_rotation().is_none());
+    /// ```
+    pub fn expected_points_per_rotation(&self) -> Option<f64> {
+        let timing = Timing::for_sensor(self.sensor?);
+        Some(timing.points_per_rotation(self.rpm()?, self.return_mode?))
+    }
+
+    fn add_data_timestamp(&mut self, timestamp: i64, azimuth: u16) {
+        if let Some((last_timestamp, last_azimuth)) = self.last_data {
+            let mut delta = timestamp - last_timestamp;
+            if delta < -HOUR_US / 2 {
+                delta += HOUR_US;
+            }
+            if delta > 0 {
+                self.device_us += delta;
+            }
+            let max_gap = Timing::for_sensor(self.sensor.unwrap_or(Sensor::VLP_16))
+                .max_packet_gap_us();
+            if delta > 0 && delta <= max_gap {
+                self.rotation_us += delta;
+                self.swept += (i64::from(azimuth) - i64::from(last_azimuth)).rem_euclid(36_000);
+            } else {
+                self.gaps += 1;
+                let gap = Duration::microseconds(delta);
+                if delta > 0 && self.largest_gap.is_none_or(|largest| gap > largest) {
+                    self.largest_gap = Some(gap);
+                }
+            }
+        }
+        self.last_data = Some((timestamp, azimuth));
+    }
+}
+
+impl Loss {
+    /// Creates new, empty statistics.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use velodyne::info::Loss;
+    /// let loss = Loss::new();
+    /// assert!(loss.fraction().is_none());
+    /// ```
+    pub fn new() -> Loss {
+        Loss::default()
+    }
+
+    /// Adds the bytes of one packet, along with its capture time if known.
+    ///
+    /// Packets other than data packets are ignored.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use velodyne::fixtures::VLP_16_DATA_PACKET;
+    /// use velodyne::info::Loss;
+    /// let mut loss = Loss::new();
+    /// loss.add(&VLP_16_DATA_PACKET, None);
+    /// assert_eq!(1, loss.received);
+    /// ```
+    pub fn add(&mut self, bytes: &[u8], capture_time: Option<DateTime<UTC>>) {
+        if vlp_16::classify(bytes) != Some(PacketKind::Data) {
+            return;
+        }
+        let (timestamp, period_ns) = data_timing(bytes);
+        self.received += 1;
+        if let Some(last) = self.last {
+            let mut delta = timestamp - last;
+            if delta < -HOUR_US / 2 {
+                delta += HOUR_US;
+            }
+            if delta <= 0 {
+                self.out_of_order += 1;
+                return;
+            }
+            self.device_us += delta;
+            let periods = (delta * 1000 + period_ns / 2) / period_ns;
+            if periods > 1 {
+                let missing = (periods - 1) as usize;
+                self.missing += missing;
+                if self.gaps.len() == RECENT_GAPS {
+                    self.gaps.pop_front();
+                }
+                self.gaps.push_back(Gap {
+                                        after: Duration::microseconds(last),
+                                        missing,
+                                        capture_time,
+                                    });
+            }
+        }
+        self.last = Some(timestamp);
+    }
+
+    /// Returns the number of data packets that the sensor sent, by the estimate.
+    pub fn expected(&self) -> usize {
+        self.received + self.missing
+    }
+
+    /// Returns the fraction of data packets that were lost, or `None` if none were expected.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use velodyne::info::Loss;
+    /// let mut loss = Loss::new();
+    /// loss.received = 3;
+    /// loss.missing = 1;
+    /// assert_eq!(Some(0.25), loss.fraction());
+    /// ```
+    pub fn fraction(&self) -> Option<f64> {
+        match self.expected() {
+            0 => None,
+            expected => Some(self.missing as f64 / expected as f64),
+        }
+    }
+
+    /// Returns the number of data packets received and expected per second of sensor time, or
+    /// `None` until two packets have been received in order.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use velodyne::fixtures::VLP_16_DATA_PACKET;
+    /// use velodyne::info::Loss;
+    /// let mut loss = Loss::new();
+    /// loss.add(&VLP_16_DATA_PACKET, None);
+    /// assert!(loss.per_second().is_none());
+    /// ```
+    pub fn per_second(&self) -> Option<(f64, f64)> {
+        if self.device_us == 0 {
+            return None;
+        }
+        let seconds = self.device_us as f64 / 1e6;
+        // The first packet starts the clock, so it isn't counted.
+        Some(((self.received - self.out_of_order - 1) as f64 / seconds,
+              (self.expected() - self.out_of_order - 1) as f64 / seconds))
+    }
+}
+
+impl Sample {
+    /// Samples every nth packet from a reader, starting with the first.
+    ///
+    /// A stride of one reads every packet, and the estimates are exact.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `every` is zero.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use velodyne::info::Sample;
+    /// use velodyne::io::Pcap;
+    /// let sample = Sample::from_read(Pcap::open("data/single.pcap").unwrap(), 100).unwrap();
+    /// assert_eq!(384., sample.points.value);
+    /// ```
+    pub fn from_read<R: Read>(mut read: R, every: usize) -> Result<Sample> {
+        assert!(every > 0, "the sampling stride must be at least one");
+        let mut packets = 0;
+        let mut sampled = 0;
+        let mut data = 0;
+        let mut position = 0;
+        let mut points = 0;
+        // The index of the first sampled data packet, and the timestamp and index of the latest.
+        let mut first: Option<usize> = None;
+        let mut last: Option<(i64, usize)> = None;
+        let mut spanned = 0;
+        while let Some(result) = read.read() {
+            let bytes = result?;
+            match vlp_16::classify(bytes) {
+                Some(PacketKind::Data) => {
+                    let (timestamp, period_ns) = data_timing(bytes);
+                    if let Some((last_timestamp, _)) = last {
+                        let mut delta = timestamp - last_timestamp;
+                        if delta < -HOUR_US / 2 {
+                            delta += HOUR_US;
+                        }
+                        spanned += ((delta.max(0) * 1000 + period_ns / 2) / period_ns) as usize;
+                    }
+                    first.get_or_insert(packets);
+                    last = Some((timestamp, packets));
+                    if let Packet::Data(ref data_packet) = Packet::new(bytes)? {
+                        points += data_packet.point_count(false);
+                    }
+                    data += 1;
+                }
+                Some(PacketKind::Position) => position += 1,
+                None => {}
+            }
+            sampled += 1;
+            packets += 1;
+            packets += read.skip_packets(every - 1)?;
+        }
+        let data_packets = proportion(data, sampled, packets);
+        let loss = match (first, last) {
+            (Some(first), Some((_, last))) if last > first => {
+                // Packets before the first and after the last sampled data packet are assumed to
+                // have arrived without loss.
+                let expected = (spanned + 1 + packets - (last - first + 1)) as f64;
+                let fraction = |received: f64| (1. - received / expected).clamp(0., 1.);
+                Some(Estimate {
+                         value: fraction(data_packets.value),
+                         low: fraction(data_packets.high),
+                         high: fraction(data_packets.low),
+                     })
+            }
+            _ => None,
+        };
+        // Every data packet of a return mode has the same number of points.
+        let points_per_packet = if data == 0 { 0. } else { points as f64 / data as f64 };
+        Ok(Sample {
+               packets,
+               sampled,
+               data_packets,
+               position_packets: proportion(position, sampled, packets),
+               points: Estimate {
+                   value: data_packets.value * points_per_packet,
+                   low: data_packets.low * points_per_packet,
+                   high: data_packets.high * points_per_packet,
+               },
+               loss,
+           })
+    }
+}
+
+impl Coverage {
+    /// Creates new, empty coverage statistics.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use velodyne::info::Coverage;
+    /// let coverage = Coverage::new();
+    /// assert_eq!(0, coverage.frames);
+    /// ```
+    pub fn new() -> Coverage {
+        Coverage::default()
+    }
+
+    /// Decodes all frames from a reader and measures their coverage.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use velodyne::info::Coverage;
+    /// use velodyne::io::Pcap;
+    /// let coverage = Coverage::from_read(Pcap::open("data/single.pcap").unwrap()).unwrap();
+    /// assert_eq!(vec![0], coverage.partial_frames);
+    /// ```
+    pub fn from_read<R: Read>(read: R) -> Result<Coverage> {
+        let mut coverage = Coverage::new();
+        let mut decoder = Decoder::new();
+        let mut assembler = Assembler::new();
+        for packet in read.vlp_16_packets() {
+            if let Some(points) = decoder.push(packet?) {
+                coverage.add_points(&mut assembler, points);
+            }
+        }
+        if let Some(points) = decoder.finish() {
+            coverage.add_points(&mut assembler, points);
+        }
+        if let Some(frame) = assembler.finish() {
+            coverage.add(&frame);
+        }
+        Ok(coverage)
+    }
+
+    /// Adds a frame, returning its coverage.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use velodyne::fixtures::VLP_16_DATA_PACKET;
+    /// use velodyne::frame::Frame;
+    /// use velodyne::info::Coverage;
+    /// use velodyne::vlp_16::Packet;
+    /// let mut coverage = Coverage::new();
+    /// let frame = Frame::new(Packet::new(&VLP_16_DATA_PACKET).unwrap().points().unwrap());
+    /// let frame_coverage = coverage.add(&frame);
+    /// assert!(!frame_coverage.is_full());
+    /// ```
+    pub fn add(&mut self, frame: &Frame) -> FrameCoverage {
+        let mut azimuths = [false; AZIMUTH_BINS];
+        for point in &frame.points {
+            azimuths[point.azimuth.degrees() as usize % AZIMUTH_BINS] = true;
+        }
+        let start = frame.points.first().map(|point| point.time);
+        let gap = match (start, self.last_start) {
+            (Some(start), Some(last_start)) => start.duration_since(last_start),
+            _ => None,
+        };
+        if let Some(gap) = gap {
+            *self.gaps.entry(gap.num_milliseconds()).or_insert(0) += 1;
+        }
+        let frame_coverage = FrameCoverage {
+            degrees: azimuths.iter().filter(|&&covered| covered).count(),
+            gap,
+        };
+        if !frame_coverage.is_full() {
+            self.partial_frames.push(self.frames);
+        }
+        self.frames += 1;
+        self.last_start = start.or(self.last_start);
+        frame_coverage
+    }
+
+    fn add_points(&mut self, assembler: &mut Assembler, points: Vec<Point>) {
+        for point in points {
+            if let Some(frame) = assembler.push(point) {
+                self.add(&frame);
+            }
+        }
+    }
+}
+
+impl FrameCoverage {
+    /// Returns true if the frame covers all 360 degrees.
+    pub fn is_full(&self) -> bool {
+        self.degrees == AZIMUTH_BINS
+    }
+}
+
+impl Extent {
+    fn new() -> Extent {
+        Extent {
+            bounds: None,
+            azimuths: [false; AZIMUTH_BINS],
+        }
+    }
+
+    fn add(&mut self, point: &Point) {
+        if point.x == 0. && point.y == 0. && point.z == 0. {
+            return;
+        }
+        match self.bounds {
+            Some(ref mut bounds) => bounds.grow(point),
+            None => self.bounds = Some(Bounds::from_point(point)),
+        }
+        let bin = point.azimuth.degrees() as usize % AZIMUTH_BINS;
+        self.azimuths[bin] = true;
+    }
+
+    /// Returns the number of degrees of azimuth that contain at least one point.
+    ///
+    /// A full rotation should cover all 360 degrees, unless something is blocking part of the
+    /// sensor's view.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use velodyne::info::Info;
+    /// use velodyne::io::Pcap;
+    /// let info = Info::from_read(Pcap::open("data/single.pcap").unwrap(), true).unwrap();
+    /// let coverage = info.extent.unwrap().azimuth_coverage();
+    /// assert!(coverage > 0 && coverage < 360);
+    /// ```
+    pub fn azimuth_coverage(&self) -> usize {
+        self.azimuths.iter().filter(|&&covered| covered).count()
+    }
+}
+
+/// Returns a data packet's timestamp and the nanoseconds between packets in its return mode.
+fn data_timing(bytes: &[u8]) -> (i64, i64) {
+    let payload = vlp_16::payload(bytes);
+    let timestamp = i64::from(LittleEndian::read_u32(&payload[wire::DATA_TIMESTAMP.range()]));
+    // Dual-return packets hold half as many firing sequences.
+    let return_mode = ReturnMode::from_u8(payload[wire::RETURN_MODE.offset])
+        .unwrap_or(ReturnMode::StrongestReturn);
+    let sensor = Sensor::from_u8(payload[wire::SENSOR.offset]).unwrap_or(Sensor::VLP_16);
+    (timestamp, Timing::for_sensor(sensor).packet_duration_ns(return_mode))
+}
+
+/// Scales `hits` of `sampled` packets up to `count` packets, with a Wilson score interval.
+///
+/// The interval is narrowed by the finite population correction, so it closes up when every
+/// packet was sampled, and unlike the normal approximation it doesn't when none or all of the
+/// sampled packets were hits.
+fn proportion(hits: usize, sampled: usize, count: usize) -> Estimate {
+    if sampled == 0 {
+        return Estimate { value: 0., low: 0., high: 0. };
+    }
+    let n = sampled as f64;
+    let count = count as f64;
+    let p = hits as f64 / n;
+    let z2 = Z_95 * Z_95 * (1. - n / count).max(0.);
+    let center = (p + z2 / (2. * n)) / (1. + z2 / n);
+    let margin = (z2 * (p * (1. - p) / n + z2 / (4. * n * n))).sqrt() / (1. + z2 / n);
+    Estimate {
+        value: count * p,
+        low: count * (center - margin).max(0.),
+        high: count * (center + margin).min(1.),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use fixtures::{VLP_16_DATA_PACKET, VLP_16_POSITION_PACKET};
+    use point::Azimuth;
+    use units::Degrees;
+
+    #[test]
+    fn extent() {
+        let mut info = Info::with_extent();
+        info.add(&Packet::new(&VLP_16_DATA_PACKET).unwrap());
+        info.add(&Packet::new(&VLP_16_POSITION_PACKET).unwrap());
+        assert_eq!(1, info.data_packets);
+        assert_eq!(1, info.position_packets);
+        assert_eq!(384, info.points);
+        let extent = info.extent.unwrap();
+        let bounds = extent.bounds.unwrap();
+        assert!(bounds.min_x < bounds.max_x);
+        assert!(bounds.min_z < 0. && bounds.max_z > 0.);
+        assert!(extent.azimuth_coverage() > 0);
+    }
+
+    #[test]
+    fn scan() {
+        let mut scan = Scan::default();
+        scan.add(&VLP_16_DATA_PACKET);
+        scan.add(&VLP_16_POSITION_PACKET[42..]);
+        scan.add(&[0; 10]);
+        assert_eq!(1, scan.data_packets);
+        assert_eq!(1, scan.position_packets);
+        assert_eq!(1, scan.other_packets);
+        assert_eq!(Some(HourOffset::from_micros(2_467_108_343)), scan.first_timestamp);
+        assert_eq!(Some(HourOffset::from_micros(2_467_110_195)), scan.last_timestamp);
+        assert_eq!(Some(ReturnMode::StrongestReturn), scan.return_mode);
+        assert_eq!(Some(Sensor::VLP_16), scan.sensor);
+        assert!(scan.first_position.is_some());
+        assert_eq!(scan.first_position, scan.last_position);
+    }
+
+    fn data_packet(timestamp: u32, azimuth: u16) -> Vec<u8> {
+        let mut bytes = VLP_16_DATA_PACKET.to_vec();
+        LittleEndian::write_u16(&mut bytes[44..], azimuth);
+        LittleEndian::write_u32(&mut bytes[1242..], timestamp);
+        bytes
+    }
+
+    #[test]
+    fn coverage() {
+        let frame = |start_ms: i64, skip: Option<(f32, f32)>| {
+            let mut points = Packet::new(&VLP_16_DATA_PACKET).unwrap().points().unwrap();
+            points.truncate(1);
+            let template = points.pop().unwrap();
+            let mut frame = Frame::new(Vec::new());
+            for i in 0..720 {
+                let degrees = i as f32 / 2.;
+                if skip.is_some_and(|(from, to)| degrees >= from && degrees < to) {
+                    continue;
+                }
+                let mut point = template;
+                point.azimuth = Azimuth::Measured(Degrees(degrees));
+                point.time = Time::Offset(Duration::milliseconds(start_ms));
+                frame.points.push(point);
+            }
+            frame
+        };
+        let mut coverage = Coverage::new();
+        assert!(coverage.add(&frame(0, None)).is_full());
+        let partial = coverage.add(&frame(100, Some((10., 20.))));
+        assert_eq!(350, partial.degrees);
+        assert_eq!(Some(Duration::milliseconds(100)), partial.gap);
+        assert!(coverage.add(&frame(250, None)).is_full());
+        assert_eq!(3, coverage.frames);
+        assert_eq!(vec![1], coverage.partial_frames);
+        assert_eq!(vec![(100, 1), (150, 1)],
+                   coverage.gaps.into_iter().collect::<Vec<_>>());
+    }
+
+    #[test]
+    fn rpm_and_gaps() {
+        let mut scan = Scan::default();
+        scan.add(&data_packet(3_599_998_000, 35_800));
+        scan.add(&data_packet(3_599_999_327, 278));
+        scan.add(&data_packet(654, 756));
+        scan.add(&data_packet(20_654, 0));
+        scan.add(&data_packet(10_000, 0));
+        assert_eq!(2, scan.gaps);
+        assert_eq!(Some(Duration::microseconds(20_000)), scan.largest_gap);
+        assert_eq!(Some(Duration::microseconds(22_654)), scan.device_duration());
+        let rpm = scan.rpm().unwrap();
+        assert!((rpm - 600.).abs() < 1., "{}", rpm);
+        let points = scan.expected_points_per_rotation().unwrap();
+        assert!((points - 28_935.).abs() < 50., "{}", points);
+    }
+
+    #[test]
+    fn loss() {
+        let mut loss = Loss::new();
+        loss.add(&data_packet(3_599_998_000, 0), None);
+        loss.add(&data_packet(3_599_999_327, 0), None);
+        // Two packets lost across the top of the hour.
+        loss.add(&data_packet(3_308, 0), None);
+        loss.add(&data_packet(1_000, 0), None);
+        loss.add(&VLP_16_POSITION_PACKET, None);
+        assert_eq!(4, loss.received);
+        assert_eq!(2, loss.missing);
+        assert_eq!(1, loss.out_of_order);
+        assert_eq!(vec![Gap {
+                            after: Duration::microseconds(3_599_999_327),
+                            missing: 2,
+                            capture_time: None,
+                        }],
+                   loss.gaps.iter().cloned().collect::<Vec<_>>());
+        let (received, expected) = loss.per_second().unwrap();
+        assert!((received - 2. / 5.308e-3).abs() < 1e-6);
+        assert!((expected - 4. / 5.308e-3).abs() < 1e-6);
+    }
+
+    #[test]
+    fn sample() {
+        use io::{PcapSlice, PcapWriter};
+        use vlp_16::PACKET_HEADER_LEN;
+        let mut writer = PcapWriter::new(Vec::new()).unwrap();
+        // 900 of 1000 data packets arrive, with a position packet after every hundredth.
+        for i in 0..1000 {
+            if i % 10 == 9 {
+                continue;
+            }
+            let bytes = data_packet(i * 1327 + 1, 0);
+            writer.write(&bytes[PACKET_HEADER_LEN..], UTC::now()).unwrap();
+            if i % 100 == 0 {
+                writer.write(&VLP_16_POSITION_PACKET[PACKET_HEADER_LEN..], UTC::now()).unwrap();
+            }
+        }
+        let pcap = writer.into_inner();
+        let exact = Sample::from_read(PcapSlice::new(&pcap[..]).unwrap(), 1).unwrap();
+        assert_eq!(910, exact.packets);
+        assert_eq!(910, exact.sampled);
+        assert_eq!(Estimate { value: 900., low: 900., high: 900. }, exact.data_packets);
+        assert_eq!(10., exact.position_packets.value);
+        assert_eq!(900. * 384., exact.points.value);
+        let loss = exact.loss.unwrap();
+        assert!((loss.value - 0.1).abs() < 0.01, "{:?}", loss);
+
+        let sample = Sample::from_read(PcapSlice::new(&pcap[..]).unwrap(), 7).unwrap();
+        assert_eq!(910, sample.packets);
+        assert_eq!(130, sample.sampled);
+        let position = sample.position_packets;
+        assert!(position.low <= 10. && 10. <= position.high, "{:?}", position);
+        let points = sample.points;
+        assert!(points.low <= 900. * 384. && 900. * 384. <= points.high, "{:?}", points);
+        let loss = sample.loss.unwrap();
+        assert!(loss.low <= 0.1 && 0.1 <= loss.high, "{:?}", loss);
+    }
+
+    #[test]
+    fn no_extent() {
+        let mut info = Info::new();
+        info.add(&Packet::new(&VLP_16_DATA_PACKET).unwrap());
+        assert!(info.extent.is_none());
+    }
+}
@@ -0,0 +1,192 @@
+//! Print the structure and bytes of single packets, e.g. to diagnose firmware quirks.
+//!
+//! `Report` shows what the decoder sees in a packet, next to a hex view of the raw bytes:
+//!
+//! ```
+//! use velodyne::fixtures::VLP_16_DATA_PACKET;
+//! use velodyne::inspect::Report;
+//! let mut report = Report::new(&VLP_16_DATA_PACKET);
+//! report.channels(&[0, 15]);
+//! println!("{}", report);
+//! ```
+
+use std::fmt;
+use vlp_16::{self, NUM_LASERS, Packet, PacketKind};
+use wire;
+
+/// The number of bytes on each line of a hex view.
+const HEX_LINE_LEN: usize = 16;
+
+/// A human-readable report of one packet.
+///
+/// Bytes that aren't a Velodyne packet, or that can't be parsed, are still shown in hex along with
+/// the reason.
+#[derive(Clone, Copy, Debug)]
+pub struct Report<'a> {
+    bytes: &'a [u8],
+    channels: [bool; NUM_LASERS],
+}
+
+/// A hex view of some bytes, with offsets and an ASCII column.
+///
+/// # Examples
+///
+/// ```
+/// use velodyne::inspect::Hex;
+/// assert_eq!("0000  24 47 50 52                                      |$GPR|\n",
+///            Hex(b"$GPR").to_string());
+/// ```
+#[derive(Clone, Copy, Debug)]
+pub struct Hex<'a>(pub &'a [u8]);
+
+impl<'a> Report<'a> {
+    /// Creates a report of a packet's bytes, including its network headers.
+    ///
+    /// By default, the report doesn't include any data records.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use velodyne::fixtures::VLP_16_POSITION_PACKET;
+    /// use velodyne::inspect::Report;
+    /// let report = Report::new(&VLP_16_POSITION_PACKET).to_string();
+    /// assert!(report.contains("$GPRMC"));
+    /// ```
+    pub fn new(bytes: &'a [u8]) -> Report<'a> {
+        Report {
+            bytes,
+            channels: [false; NUM_LASERS],
+        }
+    }
+
+    /// Sets the laser channels whose data records are included in the report.
+    ///
+    /// Channels that the sensor doesn't have are ignored.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use velodyne::fixtures::VLP_16_DATA_PACKET;
+    /// use velodyne::inspect::Report;
+    /// let report = Report::new(&VLP_16_DATA_PACKET).channels(&[0]).to_string();
+    /// assert!(report.contains("Channel 0, block 11"));
+    /// ```
+    pub fn channels(&mut self, channels: &[u8]) -> &mut Report<'a> {
+        self.channels = [false; NUM_LASERS];
+        for &channel in channels {
+            if let Some(keep) = self.channels.get_mut(channel as usize) {
+                *keep = true;
+            }
+        }
+        self
+    }
+
+    fn fmt_packet(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let kind = match vlp_16::classify(self.bytes) {
+            Some(PacketKind::Data) => "Data",
+            Some(PacketKind::Position) => "Position",
+            None => return writeln!(f, "Not a Velodyne packet: {} bytes", self.bytes.len()),
+        };
+        writeln!(f, "{} packet: {} bytes", kind, self.bytes.len())?;
+        let packet = match Packet::new(self.bytes) {
+            Ok(packet) => packet,
+            Err(err) => return writeln!(f, "Error: {:?}", err),
+        };
+        writeln!(f,
+                 "Timestamp: {} microseconds past the hour",
+                 packet.timestamp().micros())?;
+        if let Some(blocks) = packet.data_blocks() {
+            let payload = vlp_16::payload(self.bytes);
+            writeln!(f,
+                     "Return mode: {:?} (0x{:02x})",
+                     packet.return_mode().expect("data packet"),
+                     payload[wire::RETURN_MODE.offset])?;
+            writeln!(f,
+                     "Sensor: {:?} (0x{:02x})",
+                     packet.sensor().expect("data packet"),
+                     payload[wire::SENSOR.offset])?;
+            write!(f, "Azimuths (centidegrees):")?;
+            for block in blocks.iter() {
+                write!(f, " {}", block.azimuth.0)?;
+            }
+            writeln!(f)?;
+            for channel in (0..NUM_LASERS).filter(|&channel| self.channels[channel]) {
+                for (i, block) in blocks.iter().enumerate() {
+                    let [first, second] = block.data_records;
+                    writeln!(f,
+                             "Channel {}, block {}: {} m ({}), {} m ({})",
+                             channel,
+                             i,
+                             first[channel].return_distance,
+                             first[channel].calibrated_reflectivity,
+                             second[channel].return_distance,
+                             second[channel].calibrated_reflectivity)?;
+                }
+            }
+        }
+        if let Some(status) = packet.position_status() {
+            writeln!(f, "Status: {:?}", status)?;
+        }
+        if let Some(nmea) = packet.nmea() {
+            for sentence in nmea.lines() {
+                writeln!(f, "NMEA: {}", sentence.trim_end())?;
+            }
+        }
+        Ok(())
+    }
+}
+
+impl<'a> fmt::Display for Report<'a> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.fmt_packet(f)?;
+        writeln!(f)?;
+        fmt::Display::fmt(&Hex(self.bytes), f)
+    }
+}
+
+impl<'a> fmt::Display for Hex<'a> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for (i, line) in self.0.chunks(HEX_LINE_LEN).enumerate() {
+            write!(f, "{:04x} ", i * HEX_LINE_LEN)?;
+            for n in 0..HEX_LINE_LEN {
+                match line.get(n) {
+                    Some(byte) => write!(f, " {:02x}", byte)?,
+                    None => write!(f, "   ")?,
+                }
+            }
+            write!(f, "  |")?;
+            for &byte in line {
+                let c = if byte.is_ascii_graphic() || byte == b' ' { byte as char } else { '.' };
+                write!(f, "{}", c)?;
+            }
+            writeln!(f, "|")?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use fixtures::{VLP_16_DATA_PACKET, VLP_16_POSITION_PACKET};
+
+    #[test]
+    fn data_packet() {
+        let report = Report::new(&VLP_16_DATA_PACKET).channels(&[3, 200]).to_string();
+        assert!(report.starts_with("Data packet: 1248 bytes\n"));
+        assert!(report.contains("Sensor: VLP_16 (0x22)"));
+        assert_eq!(12, report.lines().filter(|line| line.starts_with("Channel 3,")).count());
+        assert_eq!(1248 / HEX_LINE_LEN, report.lines().filter(|line| line.ends_with('|')).count());
+    }
+
+    #[test]
+    fn invalid_packets() {
+        let report = Report::new(&[0; 10]).to_string();
+        assert!(report.starts_with("Not a Velodyne packet: 10 bytes\n"));
+        let mut bytes = VLP_16_DATA_PACKET;
+        // An unknown return mode.
+        bytes[1246] = 0;
+        assert!(Report::new(&bytes).to_string().contains("Error: "));
+        assert!(Report::new(&VLP_16_POSITION_PACKET).to_string().contains("Status: "));
+    }
+}
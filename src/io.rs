@@ -1,11 +1,88 @@
 //! Read Velodyne data from sources.
+//!
+//! Captures with a small snap length cut packets short. Every pcap reader returns a record that
+//! holds only part of a Velodyne packet as an `Error::TruncatedRecord`, rather than bytes that
+//! don't parse, and carries on with the next record. Datagrams that were fragmented on the way,
+//! e.g. by a link with a small MTU, can be put back together with `Read::reassemble`.
 
-use Result;
+use {Error, Result};
+use byteorder::{BigEndian, ByteOrder, LittleEndian, WriteBytesExt};
+use chrono::{DateTime, TimeZone, UTC};
+#[cfg(feature = "socket")]
+use info::Loss;
+use info::Scan;
+use latency::TimestampPairs;
+use monitor::Monitor;
+#[cfg(feature = "pcap")]
 use pcap::{self, Capture, Offline};
+use std::collections::VecDeque;
+use std::io::{self, BufWriter, ErrorKind, SeekFrom};
+#[cfg(feature = "socket")]
+use std::net::{IpAddr, Ipv4Addr, ToSocketAddrs, UdpSocket};
+#[cfg(feature = "mmap")]
+use memmap::Mmap;
+use std::fs::{File, OpenOptions};
+use std::ops::Range;
 use std::path::Path;
+use stream::Points;
+use time::HourOffset;
+use vlp_16::{self, Packet};
 use vlp_16::Packets as Vlp16Packets;
+use wire;
+
+/// The default UDP port for data packets.
+pub const DATA_PORT: u16 = 2368;
+
+/// The default UDP port for position packets.
+pub const POSITION_PORT: u16 = 8308;
+
+/// The magic number of a pcap stream with microsecond timestamps.
+const PCAP_MAGIC_NUMBER: u32 = 0xa1b2_c3d4;
+
+/// The magic number of a pcap stream with nanosecond timestamps.
+const PCAP_NANOSECOND_MAGIC_NUMBER: u32 = 0xa1b2_3c4d;
+
+const DEFAULT_PROGRESS_INTERVAL: usize = 1000;
+
+const HOUR_US: i64 = 3_600_000_000;
+
+const PCAP_GLOBAL_HEADER_LEN: usize = 24;
+const ETHERNET_HEADER_LEN: usize = 14;
+/// The most datagrams that are reassembled at once. The oldest is dropped to make room.
+const MAX_PENDING_DATAGRAMS: usize = 16;
+const PCAP_RECORD_HEADER_LEN: usize = 16;
+/// The largest packet that a pcap writer records in full.
+const PCAP_SNAPLEN: u32 = 65_535;
+const LINKTYPE_ETHERNET: u32 = 1;
+
+/// The default source address of Velodyne sensors.
+const SENSOR_ADDRESS: [u8; 4] = [192, 168, 1, 201];
+/// A MAC address with Velodyne's vendor prefix.
+const SENSOR_MAC: [u8; 6] = [0x60, 0x76, 0x88, 0, 0, 0];
+
+/// Big enough to hold any Velodyne payload.
+#[cfg(feature = "socket")]
+const SOCKET_BUFFER_LEN: usize = 2048;
+
+/// How long a socket with data and position ports blocks on one port before checking the other.
+#[cfg(feature = "socket")]
+const SOCKET_POLL_INTERVAL_MS: u64 = 10;
 
 /// A trait for things that can produce Velodyne packets.
+///
+/// The trait is object-safe, and boxes and mutable references to sources are sources too, so an
+/// application can pick a source at runtime:
+///
+/// ```
+/// use std::fs::File;
+/// use velodyne::io::{Pcap, PcapStream, Read};
+/// let sources: Vec<Box<dyn Read>> =
+///     vec![Box::new(Pcap::open("data/single.pcap").unwrap()),
+///          Box::new(PcapStream::new(File::open("data/single.pcap").unwrap()).unwrap())];
+/// for source in sources {
+///     assert_eq!(1, source.vlp_16_packets().count());
+/// }
+/// ```
 pub trait Read {
     /// Get the next group of bytes that can be turned into Velodyne data.
     ///
@@ -20,8 +97,28 @@ pub trait Read {
     /// ```
     fn read(&mut self) -> Option<Result<&[u8]>>;
 
+    /// Returns the time at which the last group of bytes was captured, if this source knows it.
+    ///
+    /// # Examples
+    ///
+    /// `Pcap` knows the capture time of each packet:
+    ///
+    /// ```
+    /// # use velodyne::io::{Pcap, Read};
+    /// let mut pcap = Pcap::open("data/single.pcap").unwrap();
+    /// assert!(pcap.timestamp().is_none());
+    /// pcap.read().unwrap().unwrap();
+    /// assert!(pcap.timestamp().is_some());
+    /// ```
+    fn timestamp(&self) -> Option<DateTime<UTC>> {
+        None
+    }
+
     /// Returns an iterator over VLP-16 packets.
     ///
+    /// A packet that can't be parsed is returned as an error, and the iterator moves on to the
+    /// next packet, so callers can decide whether to skip bad packets or to stop.
+    ///
     /// # Examples
     ///
     /// `Pcap` implements `Read`:
@@ -36,53 +133,2309 @@ pub trait Read {
     {
         Vlp16Packets::new(self)
     }
+
+    /// Returns an iterator over the decoded points, with azimuths interpolated across packets.
+    ///
+    /// The iterator stops at the first error, see `stream::Points`. Combine it with the adapters
+    /// in `stream::PointStream`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use velodyne::io::{Pcap, Read};
+    /// let points = Pcap::open("data/single.pcap").unwrap().points();
+    /// assert_eq!(384, points.count());
+    /// ```
+    fn points(self) -> Points<Self>
+        where Self: Sized
+    {
+        Points::new(self)
+    }
+
+    /// Wraps this source so that a callback is called as packets are read.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use velodyne::io::{Pcap, Read};
+    /// use velodyne::info::Info;
+    /// let pcap = Pcap::open("data/single.pcap").unwrap();
+    /// let mut progress = pcap.with_progress(|progress| println!("{:?}", progress.fraction()));
+    /// progress.total_bytes(1290);
+    /// let info = Info::from_read(progress, false).unwrap();
+    /// ```
+    fn with_progress<F: FnMut(&Progress)>(self, callback: F) -> WithProgress<Self, F>
+        where Self: Sized
+    {
+        WithProgress::new(self, callback)
+    }
+
+    /// Wraps this source so that consecutive duplicate packets are dropped.
+    ///
+    /// Network taps and some recorders duplicate datagrams. By default, a packet is a duplicate if
+    /// its payload is byte-identical to the previous packet's, see `Dedup::compare`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use velodyne::io::{Pcap, Read};
+    /// let mut pcap = Pcap::open("data/single.pcap").unwrap().dedup();
+    /// while let Some(result) = pcap.read() {
+    ///     result.unwrap();
+    /// }
+    /// assert_eq!(0, pcap.duplicates());
+    /// ```
+    fn dedup(self) -> Dedup<Self>
+        where Self: Sized
+    {
+        Dedup::new(self)
+    }
+
+    /// Wraps this source so that packets are put back in device timestamp order.
+    ///
+    /// On busy networks, UDP packets can arrive slightly out of order, which breaks azimuth
+    /// interpolation and frame assembly. Up to `window` packets are held back, so packets that
+    /// arrive at most that many places late are reordered, at the cost of that much latency.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use velodyne::io::{Pcap, Read};
+    /// let pcap = Pcap::open("data/single.pcap").unwrap().reorder(8);
+    /// assert_eq!(1, pcap.vlp_16_packets().count());
+    /// ```
+    fn reorder(self, window: usize) -> Reorder<Self>
+        where Self: Sized
+    {
+        Reorder::new(self, window)
+    }
+
+    /// Wraps this source so that fragmented IPv4 datagrams are put back together.
+    ///
+    /// A link with an MTU below a data packet's 1234 bytes of IP datagram splits every packet in
+    /// two, and neither half parses. Fragments are held until their datagram is complete, and
+    /// then the whole Ethernet frame is returned, with the capture time of its last fragment.
+    /// Everything else passes through unchanged.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use velodyne::io::{Pcap, Read};
+    /// let pcap = Pcap::open("data/single.pcap").unwrap().reassemble();
+    /// assert_eq!(1, pcap.vlp_16_packets().count());
+    /// ```
+    fn reassemble(self) -> Reassemble<Self>
+        where Self: Sized
+    {
+        Reassemble::new(self)
+    }
+
+    /// Wraps this source so that packets are counted into a monitor as they're read.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use velodyne::io::{Pcap, Read};
+    /// use velodyne::monitor::Monitor;
+    /// let monitor = Monitor::new();
+    /// let pcap = Pcap::open("data/single.pcap").unwrap().monitor(monitor.clone());
+    /// assert_eq!(384, pcap.points().count());
+    /// assert_eq!(1, monitor.totals().packets);
+    /// ```
+    fn monitor(self, monitor: Monitor) -> Monitored<Self>
+        where Self: Sized
+    {
+        Monitored { read: self, monitor }
+    }
+
+    /// Wraps this source so that every packet is recorded to a pcap file as it's read.
+    ///
+    /// Packets are written before they're handed on, so the recording is complete even if
+    /// whatever consumes them lags behind or fails. If a packet can't be written, the error is
+    /// returned in place of the packet.
+    ///
+    /// # Examples
+    ///
+    /// Record a live stream while decoding it on a pipeline:
+    ///
+    /// ```no_run
+    /// use velodyne::io::{PcapWriter, Read, Socket};
+    /// use velodyne::pipeline::Pipeline;
+    /// let output = Pipeline::new().start(|| {
+    ///     let recording = PcapWriter::create("recording.pcap")?;
+    ///     Ok(Socket::bind_default()?.tee(recording))
+    /// });
+    /// ```
+    fn tee<W: io::Write>(self, writer: PcapWriter<W>) -> Tee<Self, W>
+        where Self: Sized
+    {
+        Tee {
+            read: self,
+            writer,
+            buffer: Vec::new(),
+        }
+    }
+
+    /// Returns an iterator over the device and capture timestamps of each packet, e.g. to measure
+    /// latency, see `latency::Analyzer`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use velodyne::io::{Pcap, Read};
+    /// let mut pairs = Pcap::open("data/single.pcap").unwrap().timestamp_pairs();
+    /// let pair = pairs.next().unwrap().unwrap();
+    /// println!("{}", pair.latency());
+    /// ```
+    fn timestamp_pairs(self) -> TimestampPairs<Self>
+        where Self: Sized
+    {
+        TimestampPairs::new(self)
+    }
+
+    /// Counts packets and reads their metadata without decoding them.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use velodyne::io::{Pcap, Read};
+    /// let scan = Pcap::open("data/single.pcap").unwrap().quick_scan().unwrap();
+    /// assert_eq!(1, scan.data_packets);
+    /// ```
+    fn quick_scan(self) -> Result<Scan>
+        where Self: Sized
+    {
+        Scan::from_read(self)
+    }
+
+    /// Skips up to `n` groups of bytes without decoding them, returning the number skipped.
+    ///
+    /// Fewer than `n` are skipped only if the source runs out.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use velodyne::io::{Pcap, Read};
+    /// let mut pcap = Pcap::open("data/single.pcap").unwrap();
+    /// assert_eq!(1, pcap.skip_packets(10).unwrap());
+    /// assert!(pcap.read().is_none());
+    /// ```
+    fn skip_packets(&mut self, n: usize) -> Result<usize> {
+        for skipped in 0..n {
+            match self.read() {
+                Some(Ok(_)) => {}
+                Some(Err(err)) => return Err(err),
+                None => return Ok(skipped),
+            }
+        }
+        Ok(n)
+    }
 }
 
-/// Reads Velodyne data from pcap files.
+impl<R: Read + ?Sized> Read for Box<R> {
+    fn read(&mut self) -> Option<Result<&[u8]>> {
+        (**self).read()
+    }
+
+    fn timestamp(&self) -> Option<DateTime<UTC>> {
+        (**self).timestamp()
+    }
+
+    fn skip_packets(&mut self, n: usize) -> Result<usize> {
+        (**self).skip_packets(n)
+    }
+}
+
+impl<R: Read + ?Sized> Read for &mut R {
+    fn read(&mut self) -> Option<Result<&[u8]>> {
+        (**self).read()
+    }
+
+    fn timestamp(&self) -> Option<DateTime<UTC>> {
+        (**self).timestamp()
+    }
+
+    fn skip_packets(&mut self, n: usize) -> Result<usize> {
+        (**self).skip_packets(n)
+    }
+}
+
+/// How far along a source is.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct Progress {
+    /// The number of packets read so far.
+    pub packets: usize,
+    /// The number of packet bytes read so far.
+    ///
+    /// This doesn't include any file-format overhead, e.g. pcap headers.
+    pub bytes: u64,
+    /// The total number of bytes, if known, e.g. the size of the file being read.
+    pub total_bytes: Option<u64>,
+    /// True if the source has been exhausted.
+    pub done: bool,
+}
+
+/// A source that reports its progress to a callback.
+///
+/// The callback is called every `interval` packets, and once more when the source is exhausted.
 #[allow(missing_debug_implementations)]
-pub struct Pcap {
-    capture: Capture<Offline>,
+pub struct WithProgress<R: Read, F: FnMut(&Progress)> {
+    read: R,
+    callback: F,
+    progress: Progress,
+    interval: usize,
 }
 
-impl Pcap {
-    /// Opens a pcap file for reading.
+/// A source that counts packets into a monitor.
+#[derive(Debug)]
+pub struct Monitored<R: Read> {
+    read: R,
+    monitor: Monitor,
+}
+
+/// A source that records its packets to a pcap file.
+#[derive(Debug)]
+pub struct Tee<R: Read, W: io::Write> {
+    read: R,
+    writer: PcapWriter<W>,
+    buffer: Vec<u8>,
+}
+
+/// A source that drops consecutive duplicate packets.
+#[derive(Debug)]
+pub struct Dedup<R: Read> {
+    read: R,
+    compare: Compare,
+    last: Vec<u8>,
+    key: Option<(HourOffset, Option<u16>)>,
+    timestamp: Option<DateTime<UTC>>,
+    duplicates: usize,
+}
+
+/// A source that reorders packets by their device timestamps, within a window.
+#[derive(Debug)]
+pub struct Reorder<R: Read> {
+    read: R,
+    window: usize,
+    buffer: VecDeque<Buffered>,
+    last: Option<i64>,
+    current: Buffered,
+    done: bool,
+}
+
+#[derive(Clone, Debug, Default)]
+struct Buffered {
+    bytes: Vec<u8>,
+    microseconds: Option<i64>,
+    timestamp: Option<DateTime<UTC>>,
+}
+
+/// A source that reassembles fragmented IPv4 datagrams.
+#[derive(Debug)]
+pub struct Reassemble<R: Read> {
+    read: R,
+    pending: VecDeque<Datagram>,
+    current: Vec<u8>,
+    timestamp: Option<DateTime<UTC>>,
+    dropped: usize,
+}
+
+/// The fragments of a datagram that isn't complete yet.
+#[derive(Clone, Debug)]
+struct Datagram {
+    key: FragmentKey,
+    /// The Ethernet and IP headers of the first fragment, once it has arrived.
+    headers: Vec<u8>,
+    /// The offset and data of each fragment.
+    fragments: Vec<(usize, Vec<u8>)>,
+    /// The length of the datagram's data, once the last fragment has arrived.
+    len: Option<usize>,
+}
+
+/// The source, destination, identification, and protocol that fragments of a datagram share.
+type FragmentKey = ([u8; 4], [u8; 4], u16, u8);
+
+/// Where a fragment fits in its datagram.
+#[derive(Clone, Copy, Debug)]
+struct Fragment {
+    key: FragmentKey,
+    offset: usize,
+    more: bool,
+    /// The length of the Ethernet and IP headers.
+    headers_len: usize,
+    /// The end of the fragment's data, which leaves out any Ethernet padding.
+    end: usize,
+}
+
+/// How packets are compared to find duplicates.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Compare {
+    /// Packets are duplicates if their payloads are byte-identical, regardless of their network
+    /// headers.
+    Payload,
+    /// Packets are duplicates if they have the same timestamp and, for data packets, the same
+    /// first azimuth.
+    ///
+    /// This also catches duplicates that were modified along the way, e.g. with a different status
+    /// byte. Packets that can't be parsed are compared by payload.
+    Timestamp,
+}
+
+/// Offline sources that can jump to a byte offset.
+pub trait Seekable: Read {
+    /// Returns the byte offset of the next group of bytes.
     ///
     /// # Examples
     ///
     /// ```
-    /// # use velodyne::io::Pcap;
-    /// let reader = Pcap::open("data/single.pcap").unwrap();
+    /// use std::fs::File;
+    /// use velodyne::io::{PcapStream, Seekable};
+    /// let mut stream = PcapStream::new(File::open("data/single.pcap").unwrap()).unwrap();
+    /// assert_eq!(24, stream.position().unwrap());
     /// ```
-    pub fn open<P: AsRef<Path>>(path: P) -> Result<Pcap> {
-        Ok(Pcap { capture: Capture::from_file(path)? })
+    fn position(&mut self) -> Result<u64>;
+
+    /// Seeks to a byte offset, which must be the start of a group of bytes, e.g. as returned by
+    /// `position`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use std::fs::File;
+    /// use velodyne::io::{PcapStream, Read, Seekable};
+    /// let mut stream = PcapStream::new(File::open("data/single.pcap").unwrap()).unwrap();
+    /// let position = stream.position().unwrap();
+    /// stream.read().unwrap().unwrap();
+    /// stream.seek(position).unwrap();
+    /// assert!(stream.read().is_some());
+    /// ```
+    fn seek(&mut self, position: u64) -> Result<()>;
+}
+
+impl Progress {
+    /// Returns the fraction of the source that has been read, between zero and one.
+    ///
+    /// Returns `None` if the total is unknown and the source isn't done.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use velodyne::io::Progress;
+    /// let progress = Progress { bytes: 50, total_bytes: Some(200), ..Default::default() };
+    /// assert_eq!(Some(0.25), progress.fraction());
+    /// ```
+    pub fn fraction(&self) -> Option<f64> {
+        if self.done {
+            Some(1.)
+        } else {
+            self.total_bytes
+                .map(|total_bytes| (self.bytes as f64 / total_bytes as f64).min(1.))
+        }
     }
 }
 
-impl Read for Pcap {
+impl<R: Read, F: FnMut(&Progress)> WithProgress<R, F> {
+    /// Wraps a source, calling the callback for every thousand packets.
+    pub fn new(read: R, callback: F) -> WithProgress<R, F> {
+        WithProgress {
+            read,
+            callback,
+            progress: Progress::default(),
+            interval: DEFAULT_PROGRESS_INTERVAL,
+        }
+    }
+
+    /// Sets the total number of bytes, so that progress can be reported as a fraction.
+    pub fn total_bytes(&mut self, total_bytes: u64) -> &mut WithProgress<R, F> {
+        self.progress.total_bytes = Some(total_bytes);
+        self
+    }
+
+    /// Sets the number of packets between calls to the callback.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the interval is zero.
+    pub fn interval(&mut self, interval: usize) -> &mut WithProgress<R, F> {
+        assert!(interval > 0, "the progress interval must be positive");
+        self.interval = interval;
+        self
+    }
+}
+
+impl<R: Read, F: FnMut(&Progress)> Read for WithProgress<R, F> {
     fn read(&mut self) -> Option<Result<&[u8]>> {
-        match self.capture.next() {
-            Ok(packet) => Some(Ok(packet.data)),
-            Err(err) => {
-                match err {
-                    pcap::Error::NoMorePackets => None,
-                    _ => Some(Err(err.into())),
+        match self.read.read() {
+            Some(Ok(bytes)) => {
+                self.progress.packets += 1;
+                self.progress.bytes += bytes.len() as u64;
+                if self.progress.packets.is_multiple_of(self.interval) {
+                    (self.callback)(&self.progress);
+                }
+                Some(Ok(bytes))
+            }
+            Some(Err(err)) => Some(Err(err)),
+            None => {
+                if !self.progress.done {
+                    self.progress.done = true;
+                    (self.callback)(&self.progress);
                 }
+                None
             }
         }
     }
+
+    fn timestamp(&self) -> Option<DateTime<UTC>> {
+        self.read.timestamp()
+    }
 }
 
-#[cfg(test)]
-mod tests {
-    use super::*;
+impl<R: Read> Read for Monitored<R> {
+    fn read(&mut self) -> Option<Result<&[u8]>> {
+        let result = self.read.read();
+        if let Some(Ok(_)) = result {
+            self.monitor.add_packets(1);
+        }
+        result
+    }
 
-    #[test]
-    fn pcap_single() {
-        Pcap::open("data/single.pcap").unwrap();
+    fn timestamp(&self) -> Option<DateTime<UTC>> {
+        self.read.timestamp()
     }
+}
 
-    #[test]
-    fn pcap_invalid_file() {
-        assert!(Pcap::open("notafile").is_err());
+impl<R: Read, W: io::Write> Tee<R, W> {
+    /// Returns a mutable reference to the pcap writer, e.g. to flush it.
+    pub fn writer_mut(&mut self) -> &mut PcapWriter<W> {
+        &mut self.writer
+    }
+}
+
+impl<R: Read, W: io::Write> Read for Tee<R, W> {
+    fn read(&mut self) -> Option<Result<&[u8]>> {
+        match self.read.read()? {
+            Ok(bytes) => {
+                self.buffer.clear();
+                self.buffer.extend_from_slice(bytes);
+            }
+            Err(err) => return Some(Err(err)),
+        }
+        let time = self.read.timestamp().unwrap_or_else(UTC::now);
+        match self.writer.write(&self.buffer, time) {
+            Ok(()) => Some(Ok(&self.buffer)),
+            Err(err) => Some(Err(err)),
+        }
+    }
+
+    fn timestamp(&self) -> Option<DateTime<UTC>> {
+        self.read.timestamp()
+    }
+}
+
+impl<R: Read> Dedup<R> {
+    /// Wraps a source, comparing packets by payload.
+    pub fn new(read: R) -> Dedup<R> {
+        Dedup {
+            read,
+            compare: Compare::Payload,
+            last: Vec::new(),
+            key: None,
+            timestamp: None,
+            duplicates: 0,
+        }
+    }
+
+    /// Sets how packets are compared.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use velodyne::io::{Pcap, Read};
+    /// use velodyne::io::Compare;
+    /// let mut pcap = Pcap::open("data/single.pcap").unwrap().dedup();
+    /// pcap.compare(Compare::Timestamp);
+    /// ```
+    pub fn compare(&mut self, compare: Compare) -> &mut Dedup<R> {
+        self.compare = compare;
+        self
+    }
+
+    /// Returns the number of duplicate packets that have been dropped so far.
+    pub fn duplicates(&self) -> usize {
+        self.duplicates
+    }
+}
+
+impl<R: Read> Read for Dedup<R> {
+    fn read(&mut self) -> Option<Result<&[u8]>> {
+        loop {
+            let bytes = match self.read.read() {
+                Some(Ok(bytes)) => bytes,
+                Some(Err(err)) => return Some(Err(err)),
+                None => return None,
+            };
+            let payload = vlp_16::payload(bytes);
+            let key = match self.compare {
+                Compare::Payload => None,
+                Compare::Timestamp => {
+                    Packet::new(bytes).ok().map(|packet| {
+                        let azimuth = packet.data_blocks().map(|blocks| blocks[0].azimuth.0);
+                        (packet.timestamp(), azimuth)
+                    })
+                }
+            };
+            let duplicate = match key {
+                Some(_) => key == self.key,
+                None => !self.last.is_empty() && payload == vlp_16::payload(&self.last),
+            };
+            if duplicate {
+                self.duplicates += 1;
+                continue;
+            }
+            self.last.clear();
+            self.last.extend_from_slice(bytes);
+            self.key = key;
+            break;
+        }
+        self.timestamp = self.read.timestamp();
+        Some(Ok(&self.last))
+    }
+
+    fn timestamp(&self) -> Option<DateTime<UTC>> {
+        self.timestamp
+    }
+}
+
+impl<R: Read> Reorder<R> {
+    /// Wraps a source, holding back up to `window` packets.
+    ///
+    /// A window of zero passes packets through unchanged.
+    pub fn new(read: R, window: usize) -> Reorder<R> {
+        Reorder {
+            read,
+            window,
+            buffer: VecDeque::with_capacity(window + 1),
+            last: None,
+            current: Buffered::default(),
+            done: false,
+        }
+    }
+
+    /// Removes the earliest buffered packet.
+    ///
+    /// Timestamps roll over at the top of the hour, so they're compared relative to the last
+    /// packet that was handed out. Packets without a timestamp are handed out first.
+    fn pop(&mut self) -> Option<Buffered> {
+        let reference = self.last
+            .or_else(|| self.buffer.iter().filter_map(|buffered| buffered.microseconds).next());
+        // Maps the reference to half an hour, and earlier timestamps below that.
+        let key = |buffered: &Buffered| match (buffered.microseconds, reference) {
+            (Some(microseconds), Some(reference)) => {
+                (microseconds - reference + HOUR_US / 2).rem_euclid(HOUR_US)
+            }
+            _ => -1,
+        };
+        let (index, earliest) = self.buffer
+            .iter()
+            .enumerate()
+            .map(|(index, buffered)| (index, key(buffered)))
+            .min_by_key(|&(_, key)| key)?;
+        let buffered = self.buffer.remove(index)?;
+        if let Some(microseconds) = buffered.microseconds {
+            if self.last.is_none() || earliest >= HOUR_US / 2 {
+                self.last = Some(microseconds);
+            }
+        }
+        Some(buffered)
+    }
+}
+
+impl<R: Read> Read for Reorder<R> {
+    fn read(&mut self) -> Option<Result<&[u8]>> {
+        while !self.done && self.buffer.len() <= self.window {
+            match self.read.read() {
+                Some(Ok(bytes)) => {
+                    let microseconds = Packet::new(bytes)
+                        .ok()
+                        .map(|packet| i64::from(packet.timestamp().micros()));
+                    let buffered = Buffered {
+                        bytes: bytes.to_vec(),
+                        microseconds,
+                        timestamp: self.read.timestamp(),
+                    };
+                    self.buffer.push_back(buffered);
+                }
+                Some(Err(err)) => return Some(Err(err)),
+                None => self.done = true,
+            }
+        }
+        self.current = self.pop()?;
+        Some(Ok(&self.current.bytes))
+    }
+
+    fn timestamp(&self) -> Option<DateTime<UTC>> {
+        self.current.timestamp
+    }
+}
+
+impl<R: Read> Reassemble<R> {
+    /// Wraps a source.
+    pub fn new(read: R) -> Reassemble<R> {
+        Reassemble {
+            read,
+            pending: VecDeque::new(),
+            current: Vec::new(),
+            timestamp: None,
+            dropped: 0,
+        }
+    }
+
+    /// Returns the number of datagrams that were given up on, because too many others were being
+    /// reassembled before they were complete.
+    pub fn dropped(&self) -> usize {
+        self.dropped
+    }
+
+    /// Adds a fragment, putting its datagram in `current` and returning true if it's complete.
+    fn push(&mut self, fragment: Fragment, bytes: &[u8]) -> bool {
+        let index = match self.pending.iter().position(|datagram| datagram.key == fragment.key) {
+            Some(index) => index,
+            None => {
+                if self.pending.len() == MAX_PENDING_DATAGRAMS {
+                    self.pending.pop_front();
+                    self.dropped += 1;
+                    warn!("dropped an incomplete IPv4 datagram");
+                }
+                self.pending.push_back(Datagram {
+                                           key: fragment.key,
+                                           headers: Vec::new(),
+                                           fragments: Vec::new(),
+                                           len: None,
+                                       });
+                self.pending.len() - 1
+            }
+        };
+        let datagram = &mut self.pending[index];
+        let data = &bytes[fragment.headers_len..fragment.end];
+        if fragment.offset == 0 {
+            datagram.headers = bytes[..fragment.headers_len].to_vec();
+        }
+        if !fragment.more {
+            datagram.len = Some(fragment.offset + data.len());
+        }
+        datagram.fragments.push((fragment.offset, data.to_vec()));
+        if !datagram.is_complete() {
+            return false;
+        }
+        let datagram = self.pending.remove(index).expect("the datagram was just found");
+        datagram.assemble(&mut self.current);
+        true
+    }
+}
+
+impl<R: Read> Read for Reassemble<R> {
+    fn read(&mut self) -> Option<Result<&[u8]>> {
+        loop {
+            let (fragment, bytes) = match self.read.read()? {
+                Ok(bytes) => {
+                    match fragment(bytes) {
+                        Some(fragment) => (fragment, bytes.to_vec()),
+                        None => {
+                            self.current.clear();
+                            self.current.extend_from_slice(bytes);
+                            break;
+                        }
+                    }
+                }
+                Err(err) => return Some(Err(err)),
+            };
+            if self.push(fragment, &bytes) {
+                break;
+            }
+        }
+        self.timestamp = self.read.timestamp();
+        Some(Ok(&self.current))
+    }
+
+    fn timestamp(&self) -> Option<DateTime<UTC>> {
+        self.timestamp
+    }
+}
+
+impl Datagram {
+    /// Returns true if the fragments cover the whole datagram.
+    fn is_complete(&mut self) -> bool {
+        let len = match self.len {
+            Some(len) if !self.headers.is_empty() => len,
+            _ => return false,
+        };
+        self.fragments.sort_by_key(|&(offset, _)| offset);
+        let mut covered = 0;
+        for &(offset, ref data) in &self.fragments {
+            if offset > covered {
+                return false;
+            }
+            covered = covered.max(offset + data.len());
+        }
+        covered >= len
+    }
+
+    /// Writes the reassembled Ethernet frame, with its IP header fixed up to match.
+    fn assemble(self, frame: &mut Vec<u8>) {
+        let len = self.len.unwrap_or(0);
+        frame.clear();
+        frame.extend_from_slice(&self.headers);
+        frame.resize(self.headers.len() + len, 0);
+        for (offset, data) in self.fragments.into_iter().filter(|&(offset, _)| offset < len) {
+            let end = (offset + data.len()).min(len);
+            frame[self.headers.len() + offset..self.headers.len() + end]
+                .copy_from_slice(&data[..end - offset]);
+        }
+        let ip = &mut frame[ETHERNET_HEADER_LEN..self.headers.len()];
+        let ip_len = ip.len();
+        BigEndian::write_u16(&mut ip[2..], (ip_len + len) as u16);
+        // Keeps the don't fragment flag and clears the rest.
+        let flags = BigEndian::read_u16(&ip[6..]) & 0x4000;
+        BigEndian::write_u16(&mut ip[6..], flags);
+        BigEndian::write_u16(&mut ip[10..], 0);
+        let checksum = ipv4_checksum(ip);
+        BigEndian::write_u16(&mut ip[10..], checksum);
+    }
+}
+
+/// Reads Velodyne data from pcap files.
+///
+/// This uses libpcap, and so is only available with the `pcap` feature.
+#[cfg(feature = "pcap")]
+#[allow(missing_debug_implementations)]
+pub struct Pcap {
+    capture: Capture<Offline>,
+    timestamp: Option<DateTime<UTC>>,
+}
+
+#[cfg(feature = "pcap")]
+impl Pcap {
+    /// Opens a pcap file for reading.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use velodyne::io::Pcap;
+    /// let reader = Pcap::open("data/single.pcap").unwrap();
+    /// ```
+    pub fn open<P: AsRef<Path>>(path: P) -> Result<Pcap> {
+        Ok(Pcap {
+               capture: Capture::from_file(path)?,
+               timestamp: None,
+           })
+    }
+}
+
+#[cfg(feature = "pcap")]
+impl Read for Pcap {
+    // `tv_sec` isn't an `i64` on every platform.
+    #[allow(clippy::useless_conversion)]
+    fn read(&mut self) -> Option<Result<&[u8]>> {
+        match self.capture.next() {
+            Ok(packet) => {
+                let ts = packet.header.ts;
+                self.timestamp = Some(UTC.timestamp(i64::from(ts.tv_sec),
+                                                    ts.tv_usec as u32 * 1000));
+                let original = packet.header.len as usize;
+                Some(check_truncated(packet.data.len(), original).map(|()| packet.data))
+            }
+            Err(err) => {
+                match err {
+                    pcap::Error::NoMorePackets => None,
+                    _ => Some(Err(err.into())),
+                }
+            }
+        }
+    }
+
+    fn timestamp(&self) -> Option<DateTime<UTC>> {
+        self.timestamp
+    }
+}
+
+/// Reads Velodyne data from a pcap stream, e.g. standard input.
+///
+/// Unlike `Pcap`, which needs a path, this reads from anything that implements `std::io::Read`,
+/// so captures can be piped in (`tcpdump -w - | velodyne info -`).
+///
+/// A record that the stream ends in the middle of, or whose header is corrupt, ends the stream,
+/// since there's no way to find the start of the next record. The error is returned once, and
+/// then `read` returns `None`. A record that holds only part of a packet because of the snap
+/// length, an `Error::TruncatedRecord`, is whole in the stream, so reading carries on after it.
+#[derive(Debug)]
+pub struct PcapStream<R: io::Read> {
+    read: R,
+    big_endian: bool,
+    nanoseconds: bool,
+    buffer: Vec<u8>,
+    max_record_len: usize,
+    timestamp: Option<DateTime<UTC>>,
+    done: bool,
+}
+
+impl<R: io::Read> PcapStream<R> {
+    /// Creates a new pcap stream, reading the pcap global header.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use std::fs::File;
+    /// use velodyne::io::PcapStream;
+    /// let stream = PcapStream::new(File::open("data/single.pcap").unwrap()).unwrap();
+    /// ```
+    pub fn new(mut read: R) -> Result<PcapStream<R>> {
+        let mut header = [0; PCAP_GLOBAL_HEADER_LEN];
+        read.read_exact(&mut header)?;
+        let (big_endian, nanoseconds) = read_global_header(&header)?;
+        let snaplen = if big_endian {
+            BigEndian::read_u32(&header[16..])
+        } else {
+            LittleEndian::read_u32(&header[16..])
+        };
+        Ok(PcapStream {
+               read,
+               big_endian,
+               nanoseconds,
+               buffer: Vec::new(),
+               // Some writers record a smaller snap length than they use, so this allows at least
+               // our own.
+               max_record_len: snaplen.max(PCAP_SNAPLEN) as usize,
+               timestamp: None,
+               done: false,
+           })
+    }
+
+    /// Reads a record into the buffer, returning the packet's length on the wire, or `None` at
+    /// the end of the stream.
+    fn read_record(&mut self) -> Result<Option<usize>> {
+        match self.read_record_header()? {
+            Some((len, original)) => {
+                self.buffer.resize(len, 0);
+                self.read.read_exact(&mut self.buffer)?;
+                Ok(Some(original))
+            }
+            None => Ok(None),
+        }
+    }
+
+    /// Reads a record header, returning the length of the record's data and of the packet on the
+    /// wire, or `None` at the end of the stream.
+    ///
+    /// Returns an error if the record is longer than the snap length, before anything is
+    /// allocated for it.
+    fn read_record_header(&mut self) -> Result<Option<(usize, usize)>> {
+        let mut header = [0; PCAP_RECORD_HEADER_LEN];
+        let mut filled = 0;
+        while filled < header.len() {
+            match self.read.read(&mut header[filled..]) {
+                Ok(0) if filled == 0 => return Ok(None),
+                Ok(0) => return Err(io::Error::from(ErrorKind::UnexpectedEof).into()),
+                Ok(n) => filled += n,
+                Err(ref err) if err.kind() == ErrorKind::Interrupted => {}
+                Err(err) => return Err(err.into()),
+            }
+        }
+        let (timestamp, len, original) =
+            read_record_header(&header, self.big_endian, self.nanoseconds)?;
+        if len > self.max_record_len {
+            return Err(Error::OversizedRecord { len, max: self.max_record_len });
+        }
+        self.timestamp = Some(timestamp);
+        Ok(Some((len, original)))
+    }
+}
+
+impl<R: io::Read> Read for PcapStream<R> {
+    fn read(&mut self) -> Option<Result<&[u8]>> {
+        if self.done {
+            return None;
+        }
+        match self.read_record() {
+            Ok(Some(original)) => {
+                Some(check_truncated(self.buffer.len(), original).map(move |()| &self.buffer[..]))
+            }
+            Ok(None) => None,
+            Err(err) => {
+                self.done = true;
+                Some(Err(err))
+            }
+        }
+    }
+
+    fn timestamp(&self) -> Option<DateTime<UTC>> {
+        self.timestamp
+    }
+
+    fn skip_packets(&mut self, n: usize) -> Result<usize> {
+        for skipped in 0..n {
+            match self.read_record_header()? {
+                Some((len, _)) => {
+                    let len = len as u64;
+                    let mut record = io::Read::take(&mut self.read, len);
+                    if io::copy(&mut record, &mut io::sink())? < len {
+                        return Err(io::Error::from(ErrorKind::UnexpectedEof).into());
+                    }
+                }
+                None => return Ok(skipped),
+            }
+        }
+        Ok(n)
+    }
+}
+
+impl<R: io::Read + io::Seek> Seekable for PcapStream<R> {
+    fn position(&mut self) -> Result<u64> {
+        self.read.stream_position().map_err(Error::from)
+    }
+
+    fn seek(&mut self, position: u64) -> Result<()> {
+        self.read.seek(SeekFrom::Start(position))?;
+        Ok(())
+    }
+}
+
+/// Reads Velodyne data from a pcap file that is already in memory.
+///
+/// Packets are returned as slices of the underlying bytes, so nothing is copied. With the `mmap`
+/// feature, `PcapSlice::map` memory-maps a file, which is the fastest way to read large captures.
+#[derive(Debug)]
+pub struct PcapSlice<B: AsRef<[u8]>> {
+    bytes: B,
+    position: usize,
+    big_endian: bool,
+    nanoseconds: bool,
+    timestamp: Option<DateTime<UTC>>,
+}
+
+impl<B: AsRef<[u8]>> PcapSlice<B> {
+    /// Creates a new pcap slice, reading the pcap global header.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use std::fs;
+    /// use velodyne::io::PcapSlice;
+    /// let slice = PcapSlice::new(fs::read("data/single.pcap").unwrap()).unwrap();
+    /// ```
+    pub fn new(bytes: B) -> Result<PcapSlice<B>> {
+        let (big_endian, nanoseconds) = {
+            let header = bytes.as_ref();
+            if header.len() < PCAP_GLOBAL_HEADER_LEN {
+                return Err(io::Error::from(ErrorKind::UnexpectedEof).into());
+            }
+            read_global_header(header)?
+        };
+        Ok(PcapSlice {
+               bytes,
+               position: PCAP_GLOBAL_HEADER_LEN,
+               big_endian,
+               nanoseconds,
+               timestamp: None,
+           })
+    }
+
+    /// Returns the range of the next record's data and the packet's length on the wire, advancing
+    /// past it.
+    fn next_record(&mut self) -> Result<Option<(Range<usize>, usize)>> {
+        let bytes = self.bytes.as_ref();
+        if self.position == bytes.len() {
+            return Ok(None);
+        }
+        let start = self.position + PCAP_RECORD_HEADER_LEN;
+        let len = if start > bytes.len() {
+            None
+        } else {
+            let header = read_record_header(&bytes[self.position..start],
+                                            self.big_endian,
+                                            self.nanoseconds);
+            let (timestamp, len, original) = match header {
+                Ok(header) => header,
+                Err(err) => {
+                    // Nothing after a corrupt header can be trusted either.
+                    self.position = bytes.len();
+                    return Err(err);
+                }
+            };
+            self.timestamp = Some(timestamp);
+            Some((len, original)).filter(|&(len, _)| start + len <= bytes.len())
+        };
+        let (len, original) = match len {
+            Some(len) => len,
+            None => {
+                // A truncated record is the end of the slice.
+                self.position = bytes.len();
+                return Err(io::Error::from(ErrorKind::UnexpectedEof).into());
+            }
+        };
+        self.position = start + len;
+        Ok(Some((start..start + len, original)))
+    }
+}
+
+#[cfg(feature = "mmap")]
+impl PcapSlice<Mmap> {
+    /// Memory-maps a pcap file.
+    ///
+    /// The file must not be modified while it is mapped.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use velodyne::io::PcapSlice;
+    /// let slice = PcapSlice::map("data/single.pcap").unwrap();
+    /// ```
+    #[allow(unsafe_code)]
+    pub fn map<P: AsRef<Path>>(path: P) -> Result<PcapSlice<Mmap>> {
+        let file = File::open(path)?;
+        // Safe as long as nobody truncates or rewrites the file while it is mapped, which is the
+        // documented contract of this function.
+        let mmap = unsafe { Mmap::map(&file)? };
+        PcapSlice::new(mmap)
+    }
+}
+
+impl<B: AsRef<[u8]>> Read for PcapSlice<B> {
+    fn read(&mut self) -> Option<Result<&[u8]>> {
+        match self.next_record() {
+            Ok(Some((range, original))) => {
+                let bytes = &self.bytes.as_ref()[range];
+                Some(check_truncated(bytes.len(), original).map(|()| bytes))
+            }
+            Ok(None) => None,
+            Err(err) => Some(Err(err)),
+        }
+    }
+
+    fn timestamp(&self) -> Option<DateTime<UTC>> {
+        self.timestamp
+    }
+
+    fn skip_packets(&mut self, n: usize) -> Result<usize> {
+        for skipped in 0..n {
+            if self.next_record()?.is_none() {
+                return Ok(skipped);
+            }
+        }
+        Ok(n)
+    }
+}
+
+impl<B: AsRef<[u8]>> Seekable for PcapSlice<B> {
+    fn position(&mut self) -> Result<u64> {
+        Ok(self.position as u64)
+    }
+
+    fn seek(&mut self, position: u64) -> Result<()> {
+        if position > self.bytes.as_ref().len() as u64 {
+            return Err(io::Error::from(ErrorKind::UnexpectedEof).into());
+        }
+        self.position = position as usize;
+        Ok(())
+    }
+}
+
+/// Writes packets to a pcap stream.
+///
+/// Packets with network headers, e.g. from a pcap file, are written as they are. Bare payloads,
+/// e.g. from a socket, get Ethernet, IPv4, and UDP headers as if they came from a sensor at its
+/// default address, so that other tools can read the recording too. The payload bytes are never
+/// changed.
+#[derive(Debug)]
+pub struct PcapWriter<W: io::Write> {
+    write: W,
+    header: Vec<u8>,
+    nanoseconds: bool,
+}
+
+/// How much a split writer writes to each stream.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum SplitLimit {
+    /// At most this many packets.
+    Packets(usize),
+    /// At most this many bytes, including the pcap headers.
+    ///
+    /// A packet that doesn't fit into an empty stream gets a stream to itself.
+    Bytes(u64),
+}
+
+/// Writes packets to a sequence of pcap streams, starting a new one whenever the current one is
+/// full, e.g. to split a capture into chunks that can be processed in parallel.
+///
+/// Every stream is a standalone pcap with its own global header, and packets are written to them
+/// as a `PcapWriter` would.
+#[allow(missing_debug_implementations)]
+pub struct SplitWriter<W: io::Write, F: FnMut(usize) -> Result<W>> {
+    create: F,
+    limit: SplitLimit,
+    writer: Option<PcapWriter<W>>,
+    packets: usize,
+    bytes: u64,
+    streams: usize,
+}
+
+impl PcapWriter<BufWriter<File>> {
+    /// Creates a pcap file.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// use velodyne::io::PcapWriter;
+    /// let writer = PcapWriter::create("recording.pcap").unwrap();
+    /// ```
+    pub fn create<P: AsRef<Path>>(path: P) -> Result<PcapWriter<BufWriter<File>>> {
+        PcapWriter::new(BufWriter::new(File::create(path)?))
+    }
+
+    /// Opens a pcap file to append packets to, creating it if it doesn't exist.
+    ///
+    /// See `PcapWriter::append_to`.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// use velodyne::io::PcapWriter;
+    /// let writer = PcapWriter::append("recording.pcap").unwrap();
+    /// ```
+    pub fn append<P: AsRef<Path>>(path: P) -> Result<PcapWriter<BufWriter<File>>> {
+        let mut file = OpenOptions::new()
+            .read(true)
+            .write(true)
+            .create(true)
+            .truncate(false)
+            .open(path)?;
+        match read_append_header(&mut file)? {
+            Some(nanoseconds) => Ok(PcapWriter::resume(BufWriter::new(file), nanoseconds)),
+            None => PcapWriter::new(BufWriter::new(file)),
+        }
+    }
+}
+
+impl<W: io::Write> PcapWriter<W> {
+    /// Creates a new pcap writer, writing the pcap global header.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use velodyne::io::PcapWriter;
+    /// let writer = PcapWriter::new(Vec::new()).unwrap();
+    /// assert_eq!(24, writer.into_inner().len());
+    /// ```
+    pub fn new(mut write: W) -> Result<PcapWriter<W>> {
+        write.write_u32::<LittleEndian>(PCAP_MAGIC_NUMBER)?;
+        write.write_u16::<LittleEndian>(2)?;
+        write.write_u16::<LittleEndian>(4)?;
+        write.write_i32::<LittleEndian>(0)?;
+        write.write_u32::<LittleEndian>(0)?;
+        write.write_u32::<LittleEndian>(PCAP_SNAPLEN)?;
+        write.write_u32::<LittleEndian>(LINKTYPE_ETHERNET)?;
+        Ok(PcapWriter::resume(write, false))
+    }
+
+    /// Creates a pcap writer that appends packets to an existing pcap stream.
+    ///
+    /// The stream's global header is checked, and packets are written after its last record. An
+    /// empty stream gets a new global header. Streams that use nanosecond timestamps keep them, but
+    /// big-endian streams and streams of anything but Ethernet frames can't be appended to.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # extern crate chrono;
+    /// # extern crate velodyne;
+    /// # fn main() {
+    /// use chrono::UTC;
+    /// use std::io::Cursor;
+    /// use velodyne::fixtures::VLP_16_DATA_PACKET;
+    /// use velodyne::io::{PcapSlice, PcapWriter, Read};
+    /// let single = include_bytes!("../data/single.pcap").to_vec();
+    /// let mut writer = PcapWriter::append_to(Cursor::new(single)).unwrap();
+    /// writer.write(&VLP_16_DATA_PACKET, UTC::now()).unwrap();
+    /// let pcap = PcapSlice::new(writer.into_inner().into_inner()).unwrap();
+    /// assert_eq!(2, pcap.vlp_16_packets().count());
+    /// # }
+    /// ```
+    pub fn append_to(mut write: W) -> Result<PcapWriter<W>>
+        where W: io::Read + io::Seek
+    {
+        match read_append_header(&mut write)? {
+            Some(nanoseconds) => Ok(PcapWriter::resume(write, nanoseconds)),
+            None => PcapWriter::new(write),
+        }
+    }
+
+    fn resume(write: W, nanoseconds: bool) -> PcapWriter<W> {
+        PcapWriter {
+            write,
+            header: Vec::with_capacity(vlp_16::PACKET_HEADER_LEN),
+            nanoseconds,
+        }
+    }
+
+    /// Writes a packet that was captured at the given time.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # extern crate chrono;
+    /// # extern crate velodyne;
+    /// # fn main() {
+    /// use chrono::UTC;
+    /// use velodyne::fixtures::VLP_16_DATA_PACKET;
+    /// use velodyne::io::{PcapSlice, PcapWriter, Read};
+    /// let mut writer = PcapWriter::new(Vec::new()).unwrap();
+    /// writer.write(&VLP_16_DATA_PACKET[42..], UTC::now()).unwrap();
+    /// let mut slice = PcapSlice::new(writer.into_inner()).unwrap();
+    /// assert_eq!(&VLP_16_DATA_PACKET[42..], &slice.read().unwrap().unwrap()[42..]);
+    /// # }
+    /// ```
+    pub fn write(&mut self, bytes: &[u8], time: DateTime<UTC>) -> Result<()> {
+        self.header.clear();
+        if let Some(port) = bare_payload_port(bytes) {
+            write_network_headers(&mut self.header, bytes.len(), port);
+        }
+        let len = (self.header.len() + bytes.len()) as u32;
+        let fraction = if self.nanoseconds {
+            time.timestamp_subsec_nanos()
+        } else {
+            time.timestamp_subsec_micros()
+        };
+        self.write.write_u32::<LittleEndian>(time.timestamp() as u32)?;
+        self.write.write_u32::<LittleEndian>(fraction)?;
+        self.write.write_u32::<LittleEndian>(len)?;
+        self.write.write_u32::<LittleEndian>(len)?;
+        self.write.write_all(&self.header)?;
+        self.write.write_all(bytes)?;
+        Ok(())
+    }
+
+    /// Copies a range of packets from a source without decoding them, returning the number of
+    /// packets copied.
+    ///
+    /// The range counts packets from the source's current position, and ends early if the source
+    /// runs out. Packets keep their bytes and their capture times, at this writer's resolution,
+    /// or get the Unix epoch if the source doesn't know them.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use velodyne::io::{Pcap, PcapWriter};
+    /// let mut pcap = Pcap::open("data/single.pcap").unwrap();
+    /// let mut writer = PcapWriter::new(Vec::new()).unwrap();
+    /// assert_eq!(1, writer.copy_packets_from(&mut pcap, 0..100).unwrap());
+    /// ```
+    pub fn copy_packets_from<R>(&mut self, read: &mut R, range: Range<usize>) -> Result<usize>
+        where R: Read + ?Sized
+    {
+        if read.skip_packets(range.start)? < range.start {
+            return Ok(0);
+        }
+        let mut bytes = Vec::new();
+        let mut copied = 0;
+        while copied < range.len() {
+            match read.read() {
+                Some(result) => {
+                    bytes.clear();
+                    bytes.extend_from_slice(result?);
+                }
+                None => break,
+            }
+            let time = read.timestamp().unwrap_or_else(|| UTC.timestamp(0, 0));
+            self.write(&bytes, time)?;
+            copied += 1;
+        }
+        Ok(copied)
+    }
+
+    /// Flushes the underlying writer.
+    pub fn flush(&mut self) -> Result<()> {
+        Ok(self.write.flush()?)
+    }
+
+    /// Returns the underlying writer.
+    pub fn into_inner(self) -> W {
+        self.write
+    }
+}
+
+impl<W: io::Write, F: FnMut(usize) -> Result<W>> SplitWriter<W, F> {
+    /// Creates a split writer, which calls `create` with the index of each new stream, counting
+    /// from zero.
+    ///
+    /// Streams are only created when a packet is written to them, so there are no empty streams.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the limit is zero packets.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use std::fs::File;
+    /// use velodyne::io::{SplitLimit, SplitWriter};
+    /// let writer = SplitWriter::new(SplitLimit::Packets(1000),
+    ///                               |n| Ok(File::create(format!("chunk-{:04}.pcap", n))?));
+    /// ```
+    pub fn new(limit: SplitLimit, create: F) -> SplitWriter<W, F> {
+        assert!(limit != SplitLimit::Packets(0),
+                "a stream needs room for at least one packet");
+        SplitWriter {
+            create,
+            limit,
+            writer: None,
+            packets: 0,
+            bytes: 0,
+            streams: 0,
+        }
+    }
+
+    /// Writes a packet that was captured at the given time, starting a new stream if the current
+    /// one is full.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # extern crate chrono;
+    /// # extern crate velodyne;
+    /// # fn main() {
+    /// use chrono::UTC;
+    /// use velodyne::fixtures::VLP_16_DATA_PACKET;
+    /// use velodyne::io::{SplitLimit, SplitWriter};
+    /// let mut writer = SplitWriter::new(SplitLimit::Packets(2), |_| Ok(Vec::new()));
+    /// for _ in 0..3 {
+    ///     writer.write(&VLP_16_DATA_PACKET, UTC::now()).unwrap();
+    /// }
+    /// assert_eq!(2, writer.streams());
+    /// # }
+    /// ```
+    pub fn write(&mut self, bytes: &[u8], time: DateTime<UTC>) -> Result<()> {
+        let len = (PCAP_RECORD_HEADER_LEN + bytes.len() +
+                   bare_payload_port(bytes).map_or(0, |_| vlp_16::PACKET_HEADER_LEN)) as u64;
+        let full = match self.limit {
+            SplitLimit::Packets(packets) => self.packets >= packets,
+            SplitLimit::Bytes(bytes) => self.bytes + len > bytes,
+        };
+        if self.writer.is_none() || full {
+            if let Some(mut writer) = self.writer.take() {
+                writer.flush()?;
+            }
+            self.writer = Some(PcapWriter::new((self.create)(self.streams)?)?);
+            self.streams += 1;
+            self.packets = 0;
+            self.bytes = PCAP_GLOBAL_HEADER_LEN as u64;
+        }
+        if let Some(ref mut writer) = self.writer {
+            writer.write(bytes, time)?;
+        }
+        self.packets += 1;
+        self.bytes += len;
+        Ok(())
+    }
+
+    /// Returns the number of streams that have been created.
+    pub fn streams(&self) -> usize {
+        self.streams
+    }
+
+    /// Flushes the current stream.
+    ///
+    /// Earlier streams are flushed when they're full.
+    pub fn flush(&mut self) -> Result<()> {
+        match self.writer {
+            Some(ref mut writer) => writer.flush(),
+            None => Ok(()),
+        }
+    }
+}
+
+/// Returns the port that a bare payload, i.e. one without network headers, was sent to.
+fn bare_payload_port(bytes: &[u8]) -> Option<u16> {
+    match vlp_16::classify(bytes) {
+        Some(kind) if vlp_16::payload(bytes).len() == bytes.len() => {
+            Some(match kind {
+                     vlp_16::PacketKind::Data => DATA_PORT,
+                     vlp_16::PacketKind::Position => POSITION_PORT,
+                 })
+        }
+        _ => None,
+    }
+}
+
+/// Writes Ethernet, IPv4, and UDP headers for a broadcast from a sensor.
+fn write_network_headers(header: &mut Vec<u8>, payload_len: usize, port: u16) {
+    header.extend_from_slice(&[0xff; 6]);
+    header.extend_from_slice(&SENSOR_MAC);
+    header.extend_from_slice(&[0x08, 0x00]);
+    let mut ip = [0u8; 20];
+    ip[0] = 0x45;
+    BigEndian::write_u16(&mut ip[2..], (20 + 8 + payload_len) as u16);
+    // Don't fragment.
+    ip[6] = 0x40;
+    ip[8] = 255;
+    ip[9] = 17;
+    ip[12..16].copy_from_slice(&SENSOR_ADDRESS);
+    ip[16..20].copy_from_slice(&[255; 4]);
+    let checksum = ipv4_checksum(&ip);
+    BigEndian::write_u16(&mut ip[10..], checksum);
+    header.extend_from_slice(&ip);
+    let mut udp = [0u8; 8];
+    BigEndian::write_u16(&mut udp[0..], port);
+    BigEndian::write_u16(&mut udp[2..], port);
+    BigEndian::write_u16(&mut udp[4..], (8 + payload_len) as u16);
+    header.extend_from_slice(&udp);
+}
+
+/// Returns the checksum of an IPv4 header whose checksum field is zero.
+fn ipv4_checksum(ip: &[u8]) -> u16 {
+    let sum = ip.chunks(2).map(|word| u32::from(BigEndian::read_u16(word))).sum::<u32>();
+    let sum = (sum & 0xffff) + (sum >> 16);
+    !(sum as u16)
+}
+
+/// Returns where an Ethernet frame fits in its IPv4 datagram, or `None` if it isn't a fragment.
+fn fragment(bytes: &[u8]) -> Option<Fragment> {
+    if bytes.get(12..14)? != [0x08, 0x00] {
+        return None;
+    }
+    let ip = &bytes[ETHERNET_HEADER_LEN..];
+    let headers_len = ETHERNET_HEADER_LEN + usize::from(ip.first()? & 0x0f) * 4;
+    if ip.len() < 20 || headers_len < ETHERNET_HEADER_LEN + 20 || bytes.len() < headers_len {
+        return None;
+    }
+    let flags = BigEndian::read_u16(&ip[6..]);
+    let more = flags & 0x2000 != 0;
+    let offset = usize::from(flags & 0x1fff) * 8;
+    if !more && offset == 0 {
+        return None;
+    }
+    let end = (ETHERNET_HEADER_LEN + usize::from(BigEndian::read_u16(&ip[2..]))).min(bytes.len());
+    let mut source = [0; 4];
+    source.copy_from_slice(&ip[12..16]);
+    let mut destination = [0; 4];
+    destination.copy_from_slice(&ip[16..20]);
+    Some(Fragment {
+             key: (source, destination, BigEndian::read_u16(&ip[4..]), ip[9]),
+             offset,
+             more,
+             headers_len,
+             end: end.max(headers_len),
+         })
+}
+
+/// Reads Velodyne data from a UDP socket.
+///
+/// Sockets never run out of packets, so `read` only returns `None` if the socket is closed. Read
+/// timeouts, if set on the socket, are returned as errors.
+///
+/// A socket can also listen on separate data and position ports, in which case packets from both
+/// are interleaved as they arrive. Use `Read::reorder` if packets arrive out of order.
+#[cfg(feature = "socket")]
+#[derive(Debug)]
+pub struct Socket {
+    socket: UdpSocket,
+    position: Option<UdpSocket>,
+    position_first: bool,
+    buffer: Vec<u8>,
+    timestamp: Option<DateTime<UTC>>,
+    loss: Loss,
+}
+
+#[cfg(feature = "socket")]
+impl Socket {
+    /// Binds a socket to the given address.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use velodyne::io::Socket;
+    /// let socket = Socket::bind("127.0.0.1:0").unwrap();
+    /// ```
+    pub fn bind<A: ToSocketAddrs>(address: A) -> Result<Socket> {
+        Ok(Socket::from(UdpSocket::bind(address)?))
+    }
+
+    /// Binds to the default data and position ports on all interfaces.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// # use velodyne::io::Socket;
+    /// let socket = Socket::bind_default().unwrap();
+    /// ```
+    pub fn bind_default() -> Result<Socket> {
+        Socket::bind_ports(DATA_PORT, POSITION_PORT)
+    }
+
+    /// Binds to the given data and position ports on all interfaces.
+    ///
+    /// Use this if the sensor has been configured to send to non-default ports.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// # use velodyne::io::Socket;
+    /// let socket = Socket::bind_ports(2369, 8309).unwrap();
+    /// ```
+    pub fn bind_ports(data_port: u16, position_port: u16) -> Result<Socket> {
+        let any = IpAddr::V4(Ipv4Addr::new(0, 0, 0, 0));
+        Socket::bind_data_and_position((any, data_port), (any, position_port))
+    }
+
+    /// Binds to the given data and position addresses.
+    ///
+    /// The sockets' read timeouts are used to poll them, so they shouldn't be changed.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use velodyne::io::Socket;
+    /// let socket = Socket::bind_data_and_position("127.0.0.1:0", "127.0.0.1:0").unwrap();
+    /// ```
+    pub fn bind_data_and_position<A, B>(data: A, position: B) -> Result<Socket>
+        where A: ToSocketAddrs,
+              B: ToSocketAddrs
+    {
+        Socket::from_data_and_position(UdpSocket::bind(data)?, UdpSocket::bind(position)?)
+    }
+
+    /// Returns the size of the data socket's receive buffer, in bytes.
+    ///
+    /// This is the size that the operating system actually uses, which may differ from the
+    /// requested size, see `SocketOptions::recv_buffer_size`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use velodyne::io::Socket;
+    /// let socket = Socket::bind("127.0.0.1:0").unwrap();
+    /// assert!(socket.recv_buffer_size().unwrap() > 0);
+    /// ```
+    pub fn recv_buffer_size(&self) -> Result<usize> {
+        Ok(socket2::SockRef::from(&self.socket).recv_buffer_size()?)
+    }
+
+    /// Returns a reference to the underlying UDP socket, e.g. to set a read timeout.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use velodyne::io::Socket;
+    /// use std::time::Duration;
+    /// let socket = Socket::bind("127.0.0.1:0").unwrap();
+    /// socket.get_ref().set_read_timeout(Some(Duration::from_secs(1))).unwrap();
+    /// ```
+    pub fn get_ref(&self) -> &UdpSocket {
+        &self.socket
+    }
+
+    /// Returns a reference to the position socket, if there is one.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use velodyne::io::Socket;
+    /// let socket = Socket::bind("127.0.0.1:0").unwrap();
+    /// assert!(socket.position_ref().is_none());
+    /// ```
+    pub fn position_ref(&self) -> Option<&UdpSocket> {
+        self.position.as_ref()
+    }
+
+    /// Returns the packet-loss statistics of the data packets received so far.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use velodyne::io::Socket;
+    /// let socket = Socket::bind("127.0.0.1:0").unwrap();
+    /// assert_eq!(0, socket.loss().received);
+    /// ```
+    pub fn loss(&self) -> &Loss {
+        &self.loss
+    }
+
+    fn from_data_and_position(data: UdpSocket, position: UdpSocket) -> Result<Socket> {
+        let mut socket = Socket::from(data);
+        let poll_interval = Some(std::time::Duration::from_millis(SOCKET_POLL_INTERVAL_MS));
+        socket.socket.set_nonblocking(false)?;
+        socket.socket.set_read_timeout(poll_interval)?;
+        position.set_nonblocking(false)?;
+        position.set_read_timeout(poll_interval)?;
+        socket.position = Some(position);
+        Ok(socket)
+    }
+}
+
+/// Operating system options for binding sockets.
+///
+/// The default receive buffer is often too small for a sensor's full data rate, especially on
+/// small embedded systems, so packets get dropped before they're read.
+///
+/// # Examples
+///
+/// ```
+/// use velodyne::io::SocketOptions;
+/// let socket = SocketOptions::new()
+///     .recv_buffer_size(4 * 1024 * 1024)
+///     .reuse_address(true)
+///     .bind("127.0.0.1:0")
+///     .unwrap();
+/// ```
+#[cfg(feature = "socket")]
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct SocketOptions {
+    recv_buffer_size: Option<usize>,
+    reuse_address: bool,
+    reuse_port: bool,
+    read_timeout: Option<std::time::Duration>,
+    nonblocking: bool,
+}
+
+#[cfg(feature = "socket")]
+impl SocketOptions {
+    /// Creates options that leave everything at the operating system's defaults.
+    pub fn new() -> SocketOptions {
+        SocketOptions::default()
+    }
+
+    /// Sets the size of the receive buffer (`SO_RCVBUF`), in bytes.
+    ///
+    /// The operating system may round or cap the size, e.g. at `net.core.rmem_max` on Linux.
+    pub fn recv_buffer_size(&mut self, size: usize) -> &mut SocketOptions {
+        self.recv_buffer_size = Some(size);
+        self
+    }
+
+    /// Sets whether other sockets can bind to the same address (`SO_REUSEADDR`).
+    pub fn reuse_address(&mut self, reuse_address: bool) -> &mut SocketOptions {
+        self.reuse_address = reuse_address;
+        self
+    }
+
+    /// Sets whether other sockets can bind to the same port (`SO_REUSEPORT`), e.g. so that two
+    /// processes can listen to the same sensor.
+    ///
+    /// This is only supported on Unix, and binding fails elsewhere.
+    pub fn reuse_port(&mut self, reuse_port: bool) -> &mut SocketOptions {
+        self.reuse_port = reuse_port;
+        self
+    }
+
+    /// Sets the read timeout, after which reading returns an error.
+    ///
+    /// Sockets that listen on separate data and position ports poll with their own timeouts, so
+    /// this only applies to single sockets.
+    pub fn read_timeout(&mut self, timeout: std::time::Duration) -> &mut SocketOptions {
+        self.read_timeout = Some(timeout);
+        self
+    }
+
+    /// Sets whether reads return an error immediately instead of waiting for a packet.
+    ///
+    /// Like the read timeout, this only applies to single sockets.
+    pub fn nonblocking(&mut self, nonblocking: bool) -> &mut SocketOptions {
+        self.nonblocking = nonblocking;
+        self
+    }
+
+    /// Binds a socket to the given address with these options.
+    pub fn bind<A: ToSocketAddrs>(&self, address: A) -> Result<Socket> {
+        let socket = self.udp_socket(address)?;
+        socket.set_read_timeout(self.read_timeout)?;
+        socket.set_nonblocking(self.nonblocking)?;
+        Ok(Socket::from(socket))
+    }
+
+    /// Binds to the given data and position addresses with these options.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use velodyne::io::SocketOptions;
+    /// let socket = SocketOptions::new()
+    ///     .recv_buffer_size(1024 * 1024)
+    ///     .bind_data_and_position("127.0.0.1:0", "127.0.0.1:0")
+    ///     .unwrap();
+    /// ```
+    pub fn bind_data_and_position<A, B>(&self, data: A, position: B) -> Result<Socket>
+        where A: ToSocketAddrs,
+              B: ToSocketAddrs
+    {
+        let data = self.udp_socket(data)?;
+        let position = self.udp_socket(position)?;
+        Socket::from_data_and_position(data, position)
+    }
+
+    fn udp_socket<A: ToSocketAddrs>(&self, address: A) -> Result<UdpSocket> {
+        let address = address.to_socket_addrs()?
+            .next()
+            .ok_or_else(|| io::Error::new(ErrorKind::InvalidInput, "no address to bind to"))?;
+        let socket = socket2::Socket::new(socket2::Domain::for_address(address),
+                                          socket2::Type::DGRAM,
+                                          Some(socket2::Protocol::UDP))?;
+        if let Some(size) = self.recv_buffer_size {
+            socket.set_recv_buffer_size(size)?;
+        }
+        socket.set_reuse_address(self.reuse_address)?;
+        if self.reuse_port {
+            set_reuse_port(&socket)?;
+        }
+        socket.bind(&address.into())?;
+        Ok(socket.into())
+    }
+}
+
+#[cfg(all(feature = "socket", unix))]
+fn set_reuse_port(socket: &socket2::Socket) -> io::Result<()> {
+    socket.set_reuse_port(true)
+}
+
+#[cfg(all(feature = "socket", not(unix)))]
+fn set_reuse_port(_: &socket2::Socket) -> io::Result<()> {
+    Err(io::Error::new(ErrorKind::Other, "SO_REUSEPORT is only supported on Unix"))
+}
+
+#[cfg(feature = "socket")]
+impl From<UdpSocket> for Socket {
+    fn from(socket: UdpSocket) -> Socket {
+        Socket {
+            socket,
+            position: None,
+            position_first: false,
+            buffer: vec![0; SOCKET_BUFFER_LEN],
+            timestamp: None,
+            loss: Loss::new(),
+        }
+    }
+}
+
+#[cfg(feature = "socket")]
+impl Read for Socket {
+    fn read(&mut self) -> Option<Result<&[u8]>> {
+        let received = match self.position {
+            Some(ref position) => {
+                // Alternate which socket goes first, so a busy data port can't starve the
+                // position port.
+                self.position_first = !self.position_first;
+                let sockets = if self.position_first {
+                    [position, &self.socket]
+                } else {
+                    [&self.socket, position]
+                };
+                poll(&sockets, &mut self.buffer)
+            }
+            None => self.socket.recv(&mut self.buffer),
+        };
+        match received {
+            Ok(n) => {
+                self.timestamp = Some(UTC::now());
+                self.loss.add(&self.buffer[..n], self.timestamp);
+                Some(Ok(&self.buffer[..n]))
+            }
+            Err(err) => Some(Err(err.into())),
+        }
+    }
+
+    fn timestamp(&self) -> Option<DateTime<UTC>> {
+        self.timestamp
+    }
+}
+
+/// Receives from whichever socket has a packet first.
+#[cfg(feature = "socket")]
+fn poll(sockets: &[&UdpSocket], buffer: &mut [u8]) -> io::Result<usize> {
+    loop {
+        for socket in sockets {
+            match socket.recv(buffer) {
+                Err(ref err) if err.kind() == ErrorKind::WouldBlock ||
+                                err.kind() == ErrorKind::TimedOut => {}
+                result => return result,
+            }
+        }
+    }
+}
+
+/// Reads a pcap global header, returning whether the file is big-endian and whether its
+/// timestamps have nanosecond resolution.
+fn read_global_header(header: &[u8]) -> Result<(bool, bool)> {
+    match LittleEndian::read_u32(header) {
+        PCAP_MAGIC_NUMBER => Ok((false, false)),
+        PCAP_NANOSECOND_MAGIC_NUMBER => Ok((false, true)),
+        magic_number => {
+            match BigEndian::read_u32(header) {
+                PCAP_MAGIC_NUMBER => Ok((true, false)),
+                PCAP_NANOSECOND_MAGIC_NUMBER => Ok((true, true)),
+                _ => Err(Error::InvalidPcapMagicNumber(magic_number)),
+            }
+        }
+    }
+}
+
+/// Checks the global header of a pcap stream that's being appended to, and seeks to its end.
+///
+/// Returns whether the stream has nanosecond timestamps, or `None` if it's empty.
+fn read_append_header<R: io::Read + io::Seek>(read: &mut R) -> Result<Option<bool>> {
+    if read.seek(SeekFrom::End(0))? == 0 {
+        return Ok(None);
+    }
+    read.seek(SeekFrom::Start(0))?;
+    let mut header = [0; PCAP_GLOBAL_HEADER_LEN];
+    read.read_exact(&mut header)?;
+    let (big_endian, nanoseconds) = read_global_header(&header)?;
+    if big_endian || LittleEndian::read_u32(&header[20..]) != LINKTYPE_ETHERNET {
+        let message = "only little-endian pcaps of Ethernet frames can be appended to";
+        return Err(io::Error::new(ErrorKind::InvalidInput, message).into());
+    }
+    read.seek(SeekFrom::End(0))?;
+    Ok(Some(nanoseconds))
+}
+
+/// Reads a pcap record header, returning the record's timestamp, the length of its data, and the
+/// packet's length on the wire.
+///
+/// Returns an error if the timestamp is out of range, since the header can't be trusted.
+fn read_record_header(header: &[u8],
+                      big_endian: bool,
+                      nanoseconds: bool)
+                      -> Result<(DateTime<UTC>, usize, usize)> {
+    let read_u32 = |bytes| if big_endian {
+        BigEndian::read_u32(bytes)
+    } else {
+        LittleEndian::read_u32(bytes)
+    };
+    let seconds = read_u32(&header[0..4]);
+    let fraction = read_u32(&header[4..8]);
+    let nanoseconds = if nanoseconds {
+        Some(fraction)
+    } else {
+        fraction.checked_mul(1000)
+    };
+    let timestamp = nanoseconds
+        .and_then(|nanoseconds| UTC.timestamp_opt(i64::from(seconds), nanoseconds).single())
+        .ok_or(Error::InvalidPcapTimestamp { seconds, fraction })?;
+    Ok((timestamp, read_u32(&header[8..12]) as usize, read_u32(&header[12..16]) as usize))
+}
+
+/// Returns an error if a record holds only part of a packet that would have been a Velodyne
+/// packet.
+///
+/// Other traffic that was cut short is passed through, to be skipped like any other record that
+/// isn't a Velodyne packet.
+fn check_truncated(captured: usize, original: usize) -> Result<()> {
+    let velodyne = [wire::DATA_PAYLOAD_LEN, wire::POSITION_PAYLOAD_LEN]
+        .iter()
+        .any(|&len| original == len || original == len + wire::PACKET_HEADER_LEN);
+    if captured < original && velodyne {
+        Err(Error::TruncatedRecord { captured, original })
+    } else {
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use fixtures::VLP_16_DATA_PACKET;
+    use std::fs::File;
+    use std::io::Read as IoRead;
+    use wire;
+
+    #[test]
+    #[cfg(feature = "pcap")]
+    fn pcap_single() {
+        Pcap::open("data/single.pcap").unwrap();
+    }
+
+    #[test]
+    #[cfg(feature = "pcap")]
+    fn pcap_invalid_file() {
+        assert!(Pcap::open("notafile").is_err());
+    }
+
+    #[test]
+    #[cfg(feature = "pcap")]
+    fn pcap_stream() {
+        let mut pcap = Pcap::open("data/single.pcap").unwrap();
+        let mut stream = PcapStream::new(File::open("data/single.pcap").unwrap()).unwrap();
+        assert_eq!(pcap.read().unwrap().unwrap(), stream.read().unwrap().unwrap());
+        assert_eq!(pcap.timestamp(), stream.timestamp());
+        assert!(stream.read().is_none());
+    }
+
+    /// Returns a pcap file with a good packet, a short packet, a good packet, and then a truncated
+    /// record.
+    fn with_bad_packets() -> Vec<u8> {
+        let mut single = Vec::new();
+        File::open("data/single.pcap").unwrap().read_to_end(&mut single).unwrap();
+        let record = &single[PCAP_GLOBAL_HEADER_LEN..];
+        let mut short = record[..PCAP_RECORD_HEADER_LEN + 10].to_vec();
+        LittleEndian::write_u32(&mut short[8..12], 10);
+        LittleEndian::write_u32(&mut short[12..16], 10);
+        let mut bytes = single.clone();
+        bytes.extend_from_slice(&short);
+        bytes.extend_from_slice(record);
+        bytes.extend_from_slice(&record[..100]);
+        bytes
+    }
+
+    #[test]
+    fn resume_after_bad_packets() {
+        use std::io::Cursor;
+        let bytes = with_bad_packets();
+        // Strict, so that the short packet is an error rather than skipped.
+        let mut packets = PcapSlice::new(&bytes[..]).unwrap().vlp_16_packets();
+        let results = packets.strict(true).map(|result| result.is_ok()).collect::<Vec<_>>();
+        assert_eq!(vec![true, false, true, false], results);
+        let mut packets = PcapStream::new(Cursor::new(bytes)).unwrap().vlp_16_packets();
+        let results = packets.strict(true).map(|result| result.is_ok()).collect::<Vec<_>>();
+        assert_eq!(vec![true, false, true, false], results);
+    }
+
+    #[test]
+    fn truncated_record() {
+        use std::io::Cursor;
+        let single = include_bytes!("../data/single.pcap");
+        let record = &single[PCAP_GLOBAL_HEADER_LEN..];
+        let mut bytes = single.to_vec();
+        let start = bytes.len();
+        bytes.extend_from_slice(&record[..PCAP_RECORD_HEADER_LEN + 96]);
+        LittleEndian::write_u32(&mut bytes[start + 8..], 96);
+        bytes.extend_from_slice(record);
+        let truncated = |result: Option<Result<&[u8]>>| match result {
+            Some(Err(Error::TruncatedRecord { captured: 96, original: 1248 })) => {}
+            result => panic!("should be a truncated record: {:?}", result.map(|r| r.is_ok())),
+        };
+        let mut slice = PcapSlice::new(&bytes[..]).unwrap();
+        assert!(slice.read().unwrap().is_ok());
+        truncated(slice.read());
+        assert!(slice.read().unwrap().is_ok());
+        let mut stream = PcapStream::new(Cursor::new(&bytes)).unwrap();
+        assert!(stream.read().unwrap().is_ok());
+        truncated(stream.read());
+        assert!(stream.read().unwrap().is_ok());
+        assert!(stream.read().is_none());
+    }
+
+    #[test]
+    fn stream_ends_mid_record() {
+        use std::io::Cursor;
+        let single = include_bytes!("../data/single.pcap");
+        let mut bytes = single.to_vec();
+        bytes.extend_from_slice(&single[PCAP_GLOBAL_HEADER_LEN..][..PCAP_RECORD_HEADER_LEN + 100]);
+        let mut stream = PcapStream::new(Cursor::new(&bytes)).unwrap();
+        assert!(stream.read().unwrap().is_ok());
+        match stream.read() {
+            Some(Err(Error::Io(ref err))) if err.kind() == ErrorKind::UnexpectedEof => {}
+            result => panic!("should be an unexpected end: {:?}", result.map(|r| r.is_ok())),
+        }
+        assert!(stream.read().is_none());
+    }
+
+    #[test]
+    fn invalid_record_timestamp() {
+        use std::io::Cursor;
+        let single = include_bytes!("../data/single.pcap");
+        let record = &single[PCAP_GLOBAL_HEADER_LEN..];
+        let mut bytes = single.to_vec();
+        let start = bytes.len();
+        bytes.extend_from_slice(record);
+        LittleEndian::write_u32(&mut bytes[start + 4..], u32::MAX);
+        let invalid = |result: Option<Result<&[u8]>>| match result {
+            Some(Err(Error::InvalidPcapTimestamp { fraction: u32::MAX, .. })) => {}
+            result => panic!("should be an invalid timestamp: {:?}", result.map(|r| r.is_ok())),
+        };
+        let mut slice = PcapSlice::new(&bytes[..]).unwrap();
+        assert!(slice.read().unwrap().is_ok());
+        invalid(slice.read());
+        assert!(slice.read().is_none());
+        let mut stream = PcapStream::new(Cursor::new(&bytes)).unwrap();
+        assert!(stream.read().unwrap().is_ok());
+        invalid(stream.read());
+        assert!(stream.read().is_none());
+    }
+
+    #[test]
+    fn oversized_record() {
+        use std::io::Cursor;
+        let single = include_bytes!("../data/single.pcap");
+        let record = &single[PCAP_GLOBAL_HEADER_LEN..];
+        let mut bytes = single.to_vec();
+        let start = bytes.len();
+        bytes.extend_from_slice(record);
+        LittleEndian::write_u32(&mut bytes[start + 8..], u32::MAX);
+        let mut stream = PcapStream::new(Cursor::new(&bytes)).unwrap();
+        assert!(stream.read().unwrap().is_ok());
+        match stream.read() {
+            Some(Err(Error::OversizedRecord { len, max: 65_535 })) => {
+                assert_eq!(u32::MAX as usize, len);
+            }
+            result => panic!("should be an oversized record: {:?}", result.map(|r| r.is_ok())),
+        }
+        assert!(stream.read().is_none());
+    }
+
+    #[test]
+    fn reassemble() {
+        let frame = &VLP_16_DATA_PACKET;
+        let data = &frame[34..];
+        let mut writer = PcapWriter::new(Vec::new()).unwrap();
+        for &(start, end) in &[(600, data.len()), (0, 600)] {
+            let mut fragment = frame[..34].to_vec();
+            BigEndian::write_u16(&mut fragment[16..], (20 + end - start) as u16);
+            let more = if end < data.len() { 0x2000 } else { 0 };
+            BigEndian::write_u16(&mut fragment[20..], more | (start / 8) as u16);
+            fragment.extend_from_slice(&data[start..end]);
+            writer.write(&fragment, UTC::now()).unwrap();
+        }
+        let bytes = writer.into_inner();
+        assert!(PcapSlice::new(&bytes[..]).unwrap().vlp_16_packets().all(|r| r.is_err()));
+        let mut reassemble = PcapSlice::new(&bytes[..]).unwrap().reassemble();
+        let reassembled = reassemble.read().unwrap().unwrap().to_vec();
+        assert_eq!(frame.len(), reassembled.len());
+        assert_eq!(&frame[..20], &reassembled[..20]);
+        assert_eq!(&frame[34..], &reassembled[34..]);
+        assert_eq!(0, ipv4_checksum(&reassembled[14..34]));
+        assert!(reassemble.read().is_none());
+    }
+
+    #[test]
+    fn dedup() {
+        let mut bytes = include_bytes!("../data/single.pcap").to_vec();
+        let record = bytes[PCAP_GLOBAL_HEADER_LEN..].to_vec();
+        bytes.extend_from_slice(&record);
+        let mut modified = record.clone();
+        // A distance in the first data block, which leaves the timestamp and azimuths alone.
+        modified[PCAP_RECORD_HEADER_LEN + vlp_16::PACKET_HEADER_LEN + 4] ^= 1;
+        bytes.extend_from_slice(&modified);
+        let mut dedup = PcapSlice::new(&bytes[..]).unwrap().dedup();
+        assert_eq!(2, (&mut dedup).vlp_16_packets().count());
+        assert_eq!(1, dedup.duplicates());
+        let mut dedup = PcapSlice::new(&bytes[..]).unwrap().dedup();
+        dedup.compare(Compare::Timestamp);
+        assert_eq!(1, (&mut dedup).vlp_16_packets().count());
+        assert_eq!(2, dedup.duplicates());
+    }
+
+    #[test]
+    fn reorder() {
+        let mut bytes = include_bytes!("../data/single.pcap").to_vec();
+        let record = bytes.split_off(PCAP_GLOBAL_HEADER_LEN);
+        let timestamp = PCAP_RECORD_HEADER_LEN + wire::PACKET_HEADER_LEN +
+                        wire::DATA_TIMESTAMP.offset;
+        // Just before and just after the top of the hour, out of order.
+        for &microseconds in &[3_599_999_000u32, 1_000, 3_599_998_000, 2_000] {
+            let mut record = record.clone();
+            LittleEndian::write_u32(&mut record[timestamp..timestamp + 4], microseconds);
+            bytes.extend_from_slice(&record);
+        }
+        let timestamps = |window| {
+            PcapSlice::new(&bytes[..])
+                .unwrap()
+                .reorder(window)
+                .vlp_16_packets()
+                .map(|packet| packet.unwrap().timestamp().micros())
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(vec![3_599_999_000, 1_000, 3_599_998_000, 2_000], timestamps(0));
+        assert_eq!(vec![3_599_998_000, 3_599_999_000, 1_000, 2_000], timestamps(2));
+    }
+
+    #[test]
+    fn pcap_stream_skip_and_seek() {
+        use std::io::Cursor;
+        let mut bytes = Vec::new();
+        File::open("data/single.pcap").unwrap().read_to_end(&mut bytes).unwrap();
+        let record = bytes[PCAP_GLOBAL_HEADER_LEN..].to_vec();
+        bytes.extend_from_slice(&record);
+        let mut stream = PcapStream::new(Cursor::new(bytes)).unwrap();
+        assert_eq!(1, stream.skip_packets(1).unwrap());
+        let position = stream.position().unwrap();
+        assert_eq!((PCAP_GLOBAL_HEADER_LEN + record.len()) as u64, position);
+        assert_eq!(1, stream.skip_packets(2).unwrap());
+        stream.seek(position).unwrap();
+        assert!(stream.vlp_16_packets().next().unwrap().unwrap().is_data());
+    }
+
+    #[test]
+    #[cfg(feature = "pcap")]
+    fn progress() {
+        let mut calls = Vec::new();
+        {
+            let mut read = Pcap::open("data/single.pcap")
+                .unwrap()
+                .with_progress(|progress| calls.push(*progress));
+            read.interval(1);
+            assert_eq!(1, read.skip_packets(2).unwrap());
+        }
+        assert_eq!(2, calls.len());
+        assert_eq!(1, calls[0].packets);
+        assert_eq!(1248, calls[0].bytes);
+        assert!(!calls[0].done);
+        assert!(calls[0].fraction().is_none());
+        assert!(calls[1].done);
+    }
+
+    #[test]
+    fn pcap_stream_invalid_magic_number() {
+        match PcapStream::new(&[0u8; PCAP_GLOBAL_HEADER_LEN][..]) {
+            Err(Error::InvalidPcapMagicNumber(0)) => {}
+            result => panic!("unexpected result: {:?}", result.map(|_| ())),
+        }
+    }
+
+    #[test]
+    fn pcap_slice() {
+        let bytes = ::std::fs::read("data/single.pcap").unwrap();
+        let mut slice = PcapSlice::new(&bytes[..]).unwrap();
+        let mut stream = PcapStream::new(&bytes[..]).unwrap();
+        assert_eq!(stream.read().unwrap().unwrap(), slice.read().unwrap().unwrap());
+        assert_eq!(stream.timestamp(), slice.timestamp());
+        assert!(slice.read().is_none());
+        slice.seek(PCAP_GLOBAL_HEADER_LEN as u64).unwrap();
+        assert_eq!(1, slice.skip_packets(2).unwrap());
+        assert!(PcapSlice::new(&bytes[..bytes.len() - 1]).unwrap().read().unwrap().is_err());
+    }
+
+    #[test]
+    fn boxed_sources() {
+        let bytes = ::std::fs::read("data/single.pcap").unwrap();
+        let mut sources: Vec<Box<dyn Read>> = vec![Box::new(PcapSlice::new(bytes.clone()).unwrap()),
+                                                   Box::new(PcapStream::new(&bytes[..]).unwrap())];
+        for source in &mut sources {
+            assert!(source.read().unwrap().is_ok());
+            assert!(source.timestamp().is_some());
+            assert_eq!(0, source.skip_packets(1).unwrap());
+        }
+        let mut source = sources.remove(0);
+        assert_eq!(0, (&mut source).vlp_16_packets().count());
+    }
+
+    #[test]
+    #[cfg(feature = "mmap")]
+    fn pcap_slice_map() {
+        let slice = PcapSlice::map("data/single.pcap").unwrap();
+        assert!(slice.vlp_16_packets().next().unwrap().unwrap().is_data());
+    }
+
+    #[test]
+    #[cfg(feature = "socket")]
+    fn socket() {
+        use fixtures::VLP_16_DATA_PACKET;
+        let mut socket = Socket::bind("127.0.0.1:0").unwrap();
+        let sender = UdpSocket::bind("127.0.0.1:0").unwrap();
+        sender.send_to(&VLP_16_DATA_PACKET[42..], socket.get_ref().local_addr().unwrap()).unwrap();
+        let packet = (&mut socket).vlp_16_packets().next().unwrap().unwrap();
+        assert!(packet.is_data());
+        assert_eq!(1, socket.loss().received);
+    }
+
+    #[test]
+    fn tee() {
+        use fixtures::VLP_16_POSITION_PACKET;
+        let single = include_bytes!("../data/single.pcap");
+        let writer = PcapWriter::new(Vec::new()).unwrap();
+        let mut tee = PcapSlice::new(&single[..]).unwrap().tee(writer);
+        assert_eq!(1, (&mut tee).vlp_16_packets().count());
+        // Packets with headers are written as they were read.
+        assert_eq!(&single[..], &tee.writer.into_inner()[..]);
+        let mut writer = PcapWriter::new(Vec::new()).unwrap();
+        writer.write(&VLP_16_POSITION_PACKET[42..], UTC::now()).unwrap();
+        let bytes = writer.into_inner();
+        let mut slice = PcapSlice::new(&bytes[..]).unwrap();
+        let bytes = slice.read().unwrap().unwrap();
+        assert_eq!(&VLP_16_POSITION_PACKET[42..], &bytes[42..]);
+        assert_eq!(POSITION_PORT, BigEndian::read_u16(&bytes[36..]));
+        // The IPv4 header checksums to zero.
+        let sum = bytes[14..34]
+            .chunks(2)
+            .map(|word| u32::from(BigEndian::read_u16(word)))
+            .sum::<u32>();
+        assert_eq!(0xffff, (sum & 0xffff) + (sum >> 16));
+    }
+
+    #[test]
+    fn append() {
+        use std::io::Cursor;
+        let single = include_bytes!("../data/single.pcap");
+        let mut writer = PcapWriter::append_to(Cursor::new(single.to_vec())).unwrap();
+        let mut pcap = PcapSlice::new(&single[..]).unwrap();
+        assert_eq!(1, writer.copy_packets_from(&mut pcap, 0..2).unwrap());
+        let mut pcap = PcapSlice::new(&single[..]).unwrap();
+        assert_eq!(0, writer.copy_packets_from(&mut pcap, 1..2).unwrap());
+        // Records are copied byte for byte, capture times included.
+        let appended = writer.into_inner().into_inner();
+        assert_eq!(&single[..], &appended[..single.len()]);
+        assert_eq!(&single[PCAP_GLOBAL_HEADER_LEN..], &appended[single.len()..]);
+
+        let writer = PcapWriter::append_to(Cursor::new(Vec::new())).unwrap();
+        assert_eq!(PCAP_GLOBAL_HEADER_LEN, writer.into_inner().into_inner().len());
+        let mut big_endian = single.to_vec();
+        big_endian[..4].reverse();
+        assert!(PcapWriter::append_to(Cursor::new(big_endian)).is_err());
+    }
+
+    #[test]
+    fn split_writer() {
+        use fixtures::VLP_16_DATA_PACKET;
+        let mut streams = vec![Vec::new(); 3];
+        {
+            let mut outputs = streams.iter_mut();
+            let record_len = PCAP_RECORD_HEADER_LEN + VLP_16_DATA_PACKET.len();
+            let len = (PCAP_GLOBAL_HEADER_LEN + 2 * record_len) as u64;
+            let mut writer = SplitWriter::new(SplitLimit::Bytes(len),
+                                              |_| Ok(outputs.next().unwrap()));
+            // Bare payloads count with the headers that they're written with.
+            writer.write(&VLP_16_DATA_PACKET[42..], UTC::now()).unwrap();
+            for _ in 0..4 {
+                writer.write(&VLP_16_DATA_PACKET, UTC::now()).unwrap();
+            }
+            writer.flush().unwrap();
+            assert_eq!(3, writer.streams());
+        }
+        for (stream, &packets) in streams.iter().zip(&[2, 2, 1]) {
+            assert_eq!(packets, PcapSlice::new(stream).unwrap().vlp_16_packets().count());
+        }
+        let mut writer = SplitWriter::new(SplitLimit::Packets(3), |_| Ok(Vec::new()));
+        for _ in 0..7 {
+            writer.write(&VLP_16_DATA_PACKET, UTC::now()).unwrap();
+        }
+        assert_eq!(3, writer.streams());
+    }
+
+    #[test]
+    #[cfg(feature = "socket")]
+    fn socket_options() {
+        let mut options = SocketOptions::new();
+        options.recv_buffer_size(64 * 1024).reuse_address(true);
+        if cfg!(unix) {
+            options.reuse_port(true);
+        }
+        let socket = options.bind("127.0.0.1:0").unwrap();
+        // Linux doubles the requested size to leave room for bookkeeping.
+        assert!(socket.recv_buffer_size().unwrap() >= 64 * 1024);
+        let address = socket.get_ref().local_addr().unwrap();
+        options.bind(address).unwrap();
+        options.read_timeout(std::time::Duration::from_millis(1));
+        let mut socket = options.bind("127.0.0.1:0").unwrap();
+        assert!(socket.read().unwrap().is_err());
+    }
+
+    #[test]
+    #[cfg(feature = "socket")]
+    fn socket_data_and_position() {
+        use fixtures::{VLP_16_DATA_PACKET, VLP_16_POSITION_PACKET};
+        let socket = Socket::bind_data_and_position("127.0.0.1:0", "127.0.0.1:0").unwrap();
+        let sender = UdpSocket::bind("127.0.0.1:0").unwrap();
+        let position_addr = socket.position_ref().unwrap().local_addr().unwrap();
+        sender.send_to(&VLP_16_POSITION_PACKET[42..], position_addr).unwrap();
+        sender.send_to(&VLP_16_DATA_PACKET[42..], socket.get_ref().local_addr().unwrap()).unwrap();
+        let mut packets = socket.vlp_16_packets();
+        let first = packets.next().unwrap().unwrap();
+        let second = packets.next().unwrap().unwrap();
+        assert!(first.is_position() != second.is_position());
     }
 }
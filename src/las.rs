@@ -0,0 +1,609 @@
+//! Map points onto the attributes of LAS point records.
+//!
+//! LAS is the usual interchange format for lidar point clouds. `Attributes` holds the fields of a
+//! LAS point record that don't come straight from a point's coordinates, named after the fields
+//! of `las::Point` in the `las` crate:
+//!
+//! ```
+//! use velodyne::fixtures::VLP_16_DATA_PACKET;
+//! use velodyne::las::Attributes;
+//! use velodyne::time::GpsConverter;
+//! use velodyne::vlp_16::Packet;
+//! let points = Packet::new(&VLP_16_DATA_PACKET).unwrap().points().unwrap();
+//! let attributes = Attributes::from_points(&points, &GpsConverter::new());
+//! assert_eq!(points.len(), attributes.len());
+//! ```
+//!
+//! With the `las` feature, points and records convert to and from that crate's types with `From`,
+//! and `Attributes::to_las_point` builds a `las::Point` from attributes that were numbered across
+//! returns, or computed with a mounting pose.
+//!
+//! GPS times are adjusted standard GPS time, so set the global encoding's GPS time type bit in the
+//! LAS header. Scan angles are relative to the vehicle, so give the sensor's mounting pose to
+//! `Attributes::from_point_at` unless the sensor is mounted upright with its x axis forward.
+//!
+//! Georeferenced points need their coordinate reference system in the file too, which LAS keeps in
+//! a variable length record. `Vlr::from_crs` builds it with the fields of `las::Vlr`.
+
+use {Error, Result};
+use byteorder::{ByteOrder, LittleEndian};
+use chrono::Duration;
+use crs::Crs;
+#[cfg(feature = "las")]
+use las_rs;
+use point::{Azimuth, Point, ReturnType, Time};
+use time::GpsConverter;
+use transform::Transform;
+use units::Degrees;
+
+/// Reflectivity is scaled by this factor, so 255 maps to the largest LAS intensity.
+const INTENSITY_SCALE: u16 = 257;
+
+/// The user ID of the records that hold a coordinate reference system.
+const PROJECTION_USER_ID: &str = "LASF_Projection";
+
+/// The record ID of a GeoTIFF key directory.
+const GEO_KEY_DIRECTORY: u16 = 34735;
+
+/// The record ID of an OGC WKT coordinate system.
+const OGC_WKT: u16 = 2112;
+
+/// The GeoTIFF key for the model type, whose value is 1 for projected systems.
+const GT_MODEL_TYPE: u16 = 1024;
+
+/// The GeoTIFF key for a projected system's EPSG code.
+const PROJECTED_CS_TYPE: u16 = 3072;
+
+/// The attributes of a LAS point record, other than its coordinates.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Attributes {
+    /// The calibrated reflectivity, scaled to the full range of a `u16`.
+    pub intensity: u16,
+    /// The return number, counting from one.
+    ///
+    /// In dual-return mode, the last return is number two and the other return is number one.
+    pub return_number: u8,
+    /// The number of returns of the laser pulse, i.e. two in dual-return mode and one otherwise.
+    pub number_of_returns: u8,
+    /// The angle of the laser beam from nadir, in degrees, negative to the left of the vehicle.
+    ///
+    /// This is in (-180, 180], since beams can point above the horizon. See `scan_angle_rank`
+    /// for older LAS versions.
+    pub scan_angle: f32,
+    /// The laser channel.
+    pub user_data: u8,
+    /// The adjusted standard GPS time, or `None` if the point's time is relative to the top of the
+    /// hour.
+    pub gps_time: Option<f64>,
+}
+
+/// A LAS variable length record.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Vlr {
+    /// The user ID, at most 16 bytes.
+    pub user_id: String,
+    /// The record ID, which means something to the user ID.
+    pub record_id: u16,
+    /// A description, at most 32 bytes.
+    pub description: String,
+    /// The record's data.
+    pub data: Vec<u8>,
+}
+
+impl Attributes {
+    /// Returns the LAS attributes of a point from an upright sensor.
+    ///
+    /// A point doesn't know its packet's return mode, so a last return is return one of one. Use
+    /// `from_points` to number the returns of dual-return firings.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use velodyne::fixtures::VLP_16_DATA_PACKET;
+    /// use velodyne::las::Attributes;
+    /// use velodyne::time::GpsConverter;
+    /// use velodyne::vlp_16::Packet;
+    /// let point = Packet::new(&VLP_16_DATA_PACKET).unwrap().points().unwrap()[0];
+    /// let attributes = Attributes::from_point(&point, &GpsConverter::new());
+    /// assert_eq!(1, attributes.return_number);
+    /// assert!((attributes.scan_angle - 75.).abs() < 1e-3);
+    /// ```
+    pub fn from_point(point: &Point, converter: &GpsConverter) -> Attributes {
+        Attributes::from_point_at(point, converter, &Transform::identity())
+    }
+
+    /// Returns the LAS attributes of a point from a sensor with a mounting pose.
+    ///
+    /// See `scan_angle` for the frames involved.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use velodyne::fixtures::VLP_16_DATA_PACKET;
+    /// use velodyne::las::Attributes;
+    /// use velodyne::time::GpsConverter;
+    /// use velodyne::transform::Transform;
+    /// use velodyne::vlp_16::Packet;
+    /// let point = Packet::new(&VLP_16_DATA_PACKET).unwrap().points().unwrap()[0];
+    /// let upside_down = Transform::from_euler(180., 0., 0., [0., 0., 2.]);
+    /// let attributes = Attributes::from_point_at(&point, &GpsConverter::new(), &upside_down);
+    /// assert!((attributes.scan_angle + 105.).abs() < 1e-3);
+    /// ```
+    pub fn from_point_at(point: &Point,
+                         converter: &GpsConverter,
+                         mounting: &Transform)
+                         -> Attributes {
+        let (return_number, number_of_returns) = match point.return_type {
+            ReturnType::Strongest | ReturnType::Last => (1, 1),
+            ReturnType::Secondary => (1, 2),
+        };
+        Attributes {
+            intensity: u16::from(point.reflectivity) * INTENSITY_SCALE,
+            return_number,
+            number_of_returns,
+            scan_angle: scan_angle(point, mounting),
+            user_data: point.channel,
+            gps_time: match point.time {
+                Time::Absolute(time) => Some(converter.adjusted_standard(time)),
+                Time::Offset(_) => None,
+            },
+        }
+    }
+
+    /// Returns the LAS attributes of consecutive points, e.g. a packet's or a frame's.
+    ///
+    /// A last return that directly follows a secondary return of the same channel is part of a
+    /// dual-return firing, as `Firing::points` produces them, so it is return two of two.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use velodyne::fixtures::VLP_16_DATA_PACKET;
+    /// use velodyne::las::Attributes;
+    /// use velodyne::time::GpsConverter;
+    /// use velodyne::vlp_16::Packet;
+    /// let points = Packet::new(&VLP_16_DATA_PACKET).unwrap().points().unwrap();
+    /// let attributes = Attributes::from_points(&points, &GpsConverter::new());
+    /// assert!(attributes.iter().all(|attributes| attributes.number_of_returns == 1));
+    /// ```
+    pub fn from_points(points: &[Point], converter: &GpsConverter) -> Vec<Attributes> {
+        Attributes::from_points_at(points, converter, &Transform::identity())
+    }
+
+    /// Returns the LAS attributes of consecutive points from a sensor with a mounting pose.
+    ///
+    /// See `from_points` and `from_point_at`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use velodyne::fixtures::VLP_16_DATA_PACKET;
+    /// use velodyne::las::Attributes;
+    /// use velodyne::time::GpsConverter;
+    /// use velodyne::transform::Transform;
+    /// use velodyne::vlp_16::Packet;
+    /// let points = Packet::new(&VLP_16_DATA_PACKET).unwrap().points().unwrap();
+    /// let mounting = Transform::from_euler(0., 0., 90., [0., 0., 2.]);
+    /// let attributes = Attributes::from_points_at(&points, &GpsConverter::new(), &mounting);
+    /// assert_eq!(points.len(), attributes.len());
+    /// ```
+    pub fn from_points_at(points: &[Point],
+                          converter: &GpsConverter,
+                          mounting: &Transform)
+                          -> Vec<Attributes> {
+        let mut attributes = points.iter()
+            .map(|point| Attributes::from_point_at(point, converter, mounting))
+            .collect::<Vec<_>>();
+        for (i, pair) in points.windows(2).enumerate() {
+            if let (ReturnType::Last, ReturnType::Secondary) = (pair[0].return_type,
+                                                                pair[1].return_type) {
+                if pair[0].channel == pair[1].channel {
+                    attributes[i].return_number = 2;
+                    attributes[i].number_of_returns = 2;
+                }
+            }
+        }
+        attributes
+    }
+
+    /// Creates a point from LAS coordinates and these attributes.
+    ///
+    /// The azimuth is recovered from the coordinates. Points without a GPS time are at the top of
+    /// the hour, and a single return becomes a strongest return, since a LAS record doesn't say
+    /// which single-return mode the sensor was in.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use velodyne::fixtures::VLP_16_DATA_PACKET;
+    /// use velodyne::las::Attributes;
+    /// use velodyne::time::GpsConverter;
+    /// use velodyne::vlp_16::Packet;
+    /// let point = Packet::new(&VLP_16_DATA_PACKET).unwrap().points().unwrap()[0];
+    /// let converter = GpsConverter::new();
+    /// let attributes = Attributes::from_point(&point, &converter);
+    /// let xyz = [f64::from(point.x), f64::from(point.y), f64::from(point.z)];
+    /// let round_trip = attributes.to_point(xyz, &converter);
+    /// assert_eq!(point.reflectivity, round_trip.reflectivity);
+    /// assert_eq!(point.channel, round_trip.channel);
+    /// ```
+    pub fn to_point(&self, xyz: [f64; 3], converter: &GpsConverter) -> Point {
+        let mut point = Point {
+            x: xyz[0] as f32,
+            y: xyz[1] as f32,
+            z: xyz[2] as f32,
+            reflectivity: ((u32::from(self.intensity) + u32::from(INTENSITY_SCALE / 2)) /
+                           u32::from(INTENSITY_SCALE)) as u8,
+            channel: self.user_data,
+            sequence: 0,
+            return_type: if self.number_of_returns < 2 {
+                ReturnType::Strongest
+            } else if self.return_number == self.number_of_returns {
+                ReturnType::Last
+            } else {
+                ReturnType::Secondary
+            },
+            azimuth: Azimuth::Measured(Degrees(0.)),
+            time: match self.gps_time {
+                Some(seconds) => Time::Absolute(converter.utc_from_adjusted_standard(seconds)),
+                None => Time::Offset(Duration::zero()),
+            },
+            sensor_id: None,
+        };
+        point.azimuth = Azimuth::Measured(Degrees(point.spherical().1));
+        point
+    }
+
+    /// Returns the scan angle rounded to a LAS 1.0–1.3 scan angle rank, which is in [-90, 90].
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use velodyne::fixtures::VLP_16_DATA_PACKET;
+    /// use velodyne::las::Attributes;
+    /// use velodyne::time::GpsConverter;
+    /// use velodyne::vlp_16::Packet;
+    /// let point = Packet::new(&VLP_16_DATA_PACKET).unwrap().points().unwrap()[0];
+    /// assert_eq!(75, Attributes::from_point(&point, &GpsConverter::new()).scan_angle_rank());
+    /// ```
+    pub fn scan_angle_rank(&self) -> i8 {
+        self.scan_angle.round().clamp(-90., 90.) as i8
+    }
+
+    /// Returns a `las::Point` with these attributes at the given coordinates.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # extern crate las;
+    /// # extern crate velodyne;
+    /// # fn main() {
+    /// use velodyne::fixtures::VLP_16_DATA_PACKET;
+    /// use velodyne::las::Attributes;
+    /// use velodyne::time::GpsConverter;
+    /// use velodyne::vlp_16::Packet;
+    /// let points = Packet::new(&VLP_16_DATA_PACKET).unwrap().points().unwrap();
+    /// let attributes = Attributes::from_points(&points, &GpsConverter::new());
+    /// let xyz = [f64::from(points[0].x), f64::from(points[0].y), f64::from(points[0].z)];
+    /// let point: las::Point = attributes[0].to_las_point(xyz);
+    /// assert_eq!(points[0].channel, point.user_data);
+    /// # }
+    /// ```
+    #[cfg(feature = "las")]
+    pub fn to_las_point(&self, xyz: [f64; 3]) -> las_rs::Point {
+        las_rs::Point {
+            x: xyz[0],
+            y: xyz[1],
+            z: xyz[2],
+            intensity: self.intensity,
+            return_number: self.return_number,
+            number_of_returns: self.number_of_returns,
+            scan_angle: self.scan_angle,
+            user_data: self.user_data,
+            gps_time: self.gps_time,
+            ..Default::default()
+        }
+    }
+}
+
+/// Reads the attributes of a LAS point record.
+#[cfg(feature = "las")]
+impl<'a> From<&'a las_rs::Point> for Attributes {
+    fn from(point: &'a las_rs::Point) -> Attributes {
+        Attributes {
+            intensity: point.intensity,
+            return_number: point.return_number,
+            number_of_returns: point.number_of_returns,
+            scan_angle: point.scan_angle,
+            user_data: point.user_data,
+            gps_time: point.gps_time,
+        }
+    }
+}
+
+/// Converts a point from an upright sensor, with the built-in leap second table.
+///
+/// See `Attributes::from_point`, and use `Attributes::from_points_at` and
+/// `Attributes::to_las_point` to number dual returns or to give a mounting pose.
+///
+/// # Examples
+///
+/// ```
+/// # extern crate las;
+/// # extern crate velodyne;
+/// # fn main() {
+/// use velodyne::fixtures::VLP_16_DATA_PACKET;
+/// use velodyne::vlp_16::Packet;
+/// let point = Packet::new(&VLP_16_DATA_PACKET).unwrap().points().unwrap()[0];
+/// let las_point = las::Point::from(point);
+/// assert_eq!(f64::from(point.x), las_point.x);
+/// assert_eq!((1, 1), (las_point.return_number, las_point.number_of_returns));
+/// assert!((las_point.scan_angle - 75.).abs() < 1e-3);
+/// # }
+/// ```
+#[cfg(feature = "las")]
+impl From<Point> for las_rs::Point {
+    fn from(point: Point) -> las_rs::Point {
+        las_rs::Point::from(&point)
+    }
+}
+
+#[cfg(feature = "las")]
+impl<'a> From<&'a Point> for las_rs::Point {
+    fn from(point: &'a Point) -> las_rs::Point {
+        let xyz = [f64::from(point.x), f64::from(point.y), f64::from(point.z)];
+        Attributes::from_point(point, &GpsConverter::new()).to_las_point(xyz)
+    }
+}
+
+/// Converts a LAS point record, with the built-in leap second table.
+///
+/// See `Attributes::to_point`.
+///
+/// # Examples
+///
+/// ```
+/// # extern crate las;
+/// # extern crate velodyne;
+/// # fn main() {
+/// use velodyne::Point;
+/// let las_point = las::Point {
+///     x: 1.,
+///     intensity: u16::MAX,
+///     user_data: 3,
+///     ..Default::default()
+/// };
+/// let point = Point::from(las_point);
+/// assert_eq!((255, 3), (point.reflectivity, point.channel));
+/// # }
+/// ```
+#[cfg(feature = "las")]
+impl From<las_rs::Point> for Point {
+    fn from(point: las_rs::Point) -> Point {
+        Point::from(&point)
+    }
+}
+
+#[cfg(feature = "las")]
+impl<'a> From<&'a las_rs::Point> for Point {
+    fn from(point: &'a las_rs::Point) -> Point {
+        Attributes::from(point).to_point([point.x, point.y, point.z], &GpsConverter::new())
+    }
+}
+
+/// Converts a record, e.g. to add it to a `las::Builder`'s header.
+///
+/// # Examples
+///
+/// ```
+/// # extern crate las;
+/// # extern crate velodyne;
+/// # fn main() {
+/// use velodyne::crs::Crs;
+/// use velodyne::las::Vlr;
+/// let vlr = las::Vlr::from(Vlr::from_crs(&Crs::Epsg(32633)).unwrap());
+/// assert_eq!(34735, vlr.record_id);
+/// # }
+/// ```
+#[cfg(feature = "las")]
+impl From<Vlr> for las_rs::Vlr {
+    fn from(vlr: Vlr) -> las_rs::Vlr {
+        las_rs::Vlr {
+            user_id: vlr.user_id,
+            record_id: vlr.record_id,
+            description: vlr.description,
+            data: vlr.data,
+        }
+    }
+}
+
+impl Vlr {
+    /// Returns the record that holds a coordinate reference system.
+    ///
+    /// A WKT definition becomes an OGC WKT record, which LAS 1.4 requires, so set the global
+    /// encoding's WKT bit in the header too. An EPSG code becomes a GeoTIFF key directory, which
+    /// older readers expect, with the code as a projected coordinate system, since map frames
+    /// usually are. Give geographic or geocentric systems as WKT.
+    ///
+    /// # Errors
+    ///
+    /// GeoTIFF keys are 16 bits, so larger EPSG codes are an error.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use velodyne::crs::Crs;
+    /// use velodyne::las::Vlr;
+    /// let vlr = Vlr::from_crs(&Crs::Epsg(32633)).unwrap();
+    /// assert_eq!("LASF_Projection", vlr.user_id);
+    /// assert_eq!(34735, vlr.record_id);
+    /// ```
+    pub fn from_crs(crs: &Crs) -> Result<Vlr> {
+        match *crs {
+            Crs::Epsg(code) => {
+                if code > u32::from(u16::MAX) {
+                    return Err(Error::Crs(format!("EPSG:{} doesn't fit in a GeoTIFF key", code)));
+                }
+                let code = code as u16;
+                // A header of version 1.1.0 and the number of keys, then each key's ID, location
+                // (zero for a value in place), count, and value.
+                let keys = [1, 1, 0, 2, GT_MODEL_TYPE, 0, 1, 1, PROJECTED_CS_TYPE, 0, 1, code];
+                let mut data = vec![0; 2 * keys.len()];
+                LittleEndian::write_u16_into(&keys, &mut data);
+                Ok(Vlr {
+                       user_id: PROJECTION_USER_ID.to_string(),
+                       record_id: GEO_KEY_DIRECTORY,
+                       description: "GeoTiff GeoKeyDirectoryTag".to_string(),
+                       data,
+                   })
+            }
+            Crs::Wkt(_) => {
+                let mut data = crs.to_string().into_bytes();
+                data.push(0);
+                Ok(Vlr {
+                       user_id: PROJECTION_USER_ID.to_string(),
+                       record_id: OGC_WKT,
+                       description: "OGC Coordinate System WKT".to_string(),
+                       data,
+                   })
+            }
+        }
+    }
+}
+
+/// Returns the LAS scan angle of a point: the signed angle of its laser beam from nadir, in
+/// degrees, negative to the left of the vehicle.
+///
+/// The beam's direction comes from the point's elevation, which is its laser's vertical angle on
+/// any sensor, and its azimuth. Points without a return are at the origin, so their beam is taken
+/// as level. Azimuths must be clockwise, as the sensor sends them.
+/// The mounting pose is the sensor's pose in the vehicle's frame, which is forward-left-up, and
+/// rotates the beam out of the sensor's Velodyne axes. The result is in (-180, 180].
+///
+/// # Examples
+///
+/// ```
+/// use velodyne::fixtures::VLP_16_DATA_PACKET;
+/// use velodyne::las;
+/// use velodyne::transform::Transform;
+/// use velodyne::vlp_16::Packet;
+/// let point = Packet::new(&VLP_16_DATA_PACKET).unwrap().points().unwrap()[0];
+/// let scan_angle = las::scan_angle(&point, &Transform::identity());
+/// assert!((scan_angle - 75.).abs() < 1e-3);
+/// ```
+pub fn scan_angle(point: &Point, mounting: &Transform) -> f32 {
+    let (elevation, azimuth) =
+        (point.elevation().to_radians(), point.azimuth.degrees().to_radians());
+    let beam = [elevation.cos() * azimuth.sin(),
+                elevation.cos() * azimuth.cos(),
+                elevation.sin()];
+    let rotation = mounting.rotation();
+    let rotate = |row: [f32; 3]| row[0] * beam[0] + row[1] * beam[1] + row[2] * beam[2];
+    let (left, up) = (rotate(rotation[1]), rotate(rotation[2]));
+    let from_nadir = (-up).clamp(-1., 1.).acos().to_degrees();
+    if left > 0. { -from_nadir } else { from_nadir }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::{TimeZone, UTC};
+    use fixtures::{RELABELLED_HDL_32E_DATA_PACKET, VLP_16_DATA_PACKET};
+    use spec;
+    use units::Meters;
+    use vlp_16::{PACKET_HEADER_LEN, Packet};
+    use wire;
+
+    #[test]
+    fn dual_return() {
+        let mut bytes = VLP_16_DATA_PACKET;
+        bytes[PACKET_HEADER_LEN + wire::RETURN_MODE.offset] = 0x39;
+        let points = Packet::new(&bytes).unwrap().points().unwrap();
+        let converter = GpsConverter::new();
+        let attributes = Attributes::from_points(&points, &converter);
+        assert_eq!((2, 2), (attributes[0].return_number, attributes[0].number_of_returns));
+        assert_eq!((1, 2), (attributes[1].return_number, attributes[1].number_of_returns));
+        match attributes[0].to_point([0., 1., 0.], &converter).return_type {
+            ReturnType::Last => {}
+            return_type => panic!("unexpected return type: {:?}", return_type),
+        }
+    }
+
+    #[test]
+    fn scan_angle() {
+        let point = |azimuth| {
+            Point::from_spherical(Meters(10.),
+                                  Azimuth::Measured(Degrees(azimuth)),
+                                  Degrees(1.),
+                                  0,
+                                  1,
+                                  ReturnType::Strongest,
+                                  Time::Offset(Duration::zero()))
+        };
+        let identity = Transform::identity();
+        for &(azimuth, expected) in &[(0., -91.), (135., 91.), (180., 91.), (315., -91.)] {
+            assert!((super::scan_angle(&point(azimuth), &identity) - expected).abs() < 1e-3);
+        }
+        let pitched = Transform::from_euler(0., 90., 0., [0.; 3]);
+        let point = point(45.);
+        assert!(super::scan_angle(&point, &pitched).abs() < 90.);
+        let mut attributes = Attributes::from_point(&point, &GpsConverter::new());
+        attributes.scan_angle = -120.;
+        assert_eq!(-90, attributes.scan_angle_rank());
+        let points = Packet::new(&RELABELLED_HDL_32E_DATA_PACKET).unwrap().points().unwrap();
+        for point in points.iter().filter(|point| point.range() > 0.) {
+            let expected = 90. + spec::HDL_32E.elevation(point.channel as usize).0;
+            assert!((super::scan_angle(point, &identity).abs() - expected).abs() < 1e-2);
+        }
+    }
+
+    #[test]
+    fn crs() {
+        let vlr = Vlr::from_crs(&Crs::Epsg(32633)).unwrap();
+        assert_eq!(24, vlr.data.len());
+        assert_eq!(32633, LittleEndian::read_u16(&vlr.data[22..]));
+        assert!(Vlr::from_crs(&Crs::Epsg(100_000)).is_err());
+        let vlr = Vlr::from_crs(&Crs::Wkt("GEOCCS[\"WGS 84\",\n DATUM[\"WGS_1984\"]]".to_string()))
+            .unwrap();
+        assert_eq!(2112, vlr.record_id);
+        assert_eq!(&b"GEOCCS[\"WGS 84\", DATUM[\"WGS_1984\"]]\0"[..], &vlr.data[..]);
+    }
+
+    #[test]
+    fn round_trip() {
+        let mut point = Packet::new(&VLP_16_DATA_PACKET).unwrap().points().unwrap()[0];
+        let time = UTC.ymd(2015, 7, 23).and_hms_micro(21, 41, 6, 250_000);
+        point.time = Time::Absolute(time);
+        point.reflectivity = 255;
+        let converter = GpsConverter::new();
+        let attributes = Attributes::from_point(&point, &converter);
+        assert_eq!(u16::MAX, attributes.intensity);
+        let xyz = [f64::from(point.x), f64::from(point.y), f64::from(point.z)];
+        let round_trip = attributes.to_point(xyz, &converter);
+        assert_eq!(255, round_trip.reflectivity);
+        assert!((point.azimuth.degrees() - round_trip.azimuth.degrees()).abs() < 1e-3);
+        match round_trip.time {
+            Time::Absolute(round_trip) => assert_eq!(time, round_trip),
+            Time::Offset(_) => panic!("lost the absolute time"),
+        }
+    }
+
+    #[test]
+    #[cfg(feature = "las")]
+    fn las_round_trip() {
+        let mut point = Packet::new(&VLP_16_DATA_PACKET).unwrap().points().unwrap()[0];
+        let time = UTC.ymd(2015, 7, 23).and_hms_micro(21, 41, 6, 250_000);
+        point.time = Time::Absolute(time);
+        let las_point = las_rs::Point::from(&point);
+        let expected = GpsConverter::new().adjusted_standard(time);
+        assert_eq!(Some(expected), las_point.gps_time);
+        let round_trip = Point::from(las_point);
+        assert_eq!(point.reflectivity, round_trip.reflectivity);
+        assert_eq!(point.channel, round_trip.channel);
+        assert!((point.azimuth.degrees() - round_trip.azimuth.degrees()).abs() < 1e-3);
+        match round_trip.time {
+            Time::Absolute(round_trip) => assert_eq!(time, round_trip),
+            Time::Offset(_) => panic!("lost the absolute time"),
+        }
+    }
+}
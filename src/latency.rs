@@ -0,0 +1,246 @@
+//! Measure the delay between a sensor firing and the host receiving the packet.
+//!
+//! A packet's device timestamp is the time of its first firing, counted from the top of the hour,
+//! and its capture timestamp comes from the host's clock. If the sensor is locked to GPS and the
+//! host's clock is synchronized too, e.g. with PTP, the difference between them is the network,
+//! driver, and capture latency, plus the milliseconds that it takes to fill a packet:
+//!
+//! ```
+//! use velodyne::io::{Pcap, Read};
+//! use velodyne::latency::Analyzer;
+//! let analyzer = Analyzer::from_read(Pcap::open("data/single.pcap").unwrap()).unwrap();
+//! let summary = analyzer.summary().unwrap();
+//! println!("{} packets, median latency {}", summary.samples, summary.median);
+//! ```
+
+use Result;
+use chrono::{DateTime, Duration, UTC};
+use io::Read;
+use time::{HOUR_US, HourOffset};
+use vlp_16::{self, Packet};
+
+/// A packet's device timestamp and the time it was captured.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct TimestampPair {
+    /// The packet's timestamp.
+    pub device: HourOffset,
+    /// The time the packet was captured, by the host's clock.
+    pub capture: DateTime<UTC>,
+}
+
+/// An iterator over the timestamp pairs of a source's packets.
+///
+/// Records that aren't Velodyne packets are skipped, and so is everything if the source doesn't
+/// know its capture times. A packet that can't be parsed is an error, and the iterator moves on to
+/// the next one.
+#[derive(Debug)]
+pub struct TimestampPairs<R: Read> {
+    read: R,
+}
+
+/// Collects latencies and summarizes them.
+#[derive(Clone, Debug, Default)]
+pub struct Analyzer {
+    latencies: Vec<Duration>,
+}
+
+/// Statistics of the latencies of a run.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Summary {
+    /// The number of packets.
+    pub samples: usize,
+    /// The mean latency.
+    pub mean: Duration,
+    /// The smallest latency.
+    pub min: Duration,
+    /// The median latency.
+    pub median: Duration,
+    /// The 95th percentile latency.
+    pub p95: Duration,
+    /// The 99th percentile latency.
+    pub p99: Duration,
+    /// The largest latency.
+    pub max: Duration,
+}
+
+impl TimestampPair {
+    /// Returns the capture time minus the device time.
+    ///
+    /// Device timestamps roll over every hour, so the capture time is compared with the time past
+    /// its hour, and the latency is always within half an hour either way. It's negative if the
+    /// clocks aren't synchronized.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # extern crate chrono;
+    /// # extern crate velodyne;
+    /// # fn main() {
+    /// use chrono::{TimeZone, UTC};
+    /// use velodyne::latency::TimestampPair;
+    /// use velodyne::time::HourOffset;
+    /// let pair = TimestampPair {
+    ///     device: HourOffset::from_micros(3_599_999_000),
+    ///     capture: UTC.ymd(2024, 1, 1).and_hms_milli(13, 0, 0, 2),
+    /// };
+    /// assert_eq!(3_000, pair.latency().num_microseconds().unwrap());
+    /// # }
+    /// ```
+    pub fn latency(&self) -> Duration {
+        let capture = (self.capture.timestamp() % 3600) * 1_000_000 +
+                      i64::from(self.capture.timestamp_subsec_micros());
+        let mut latency = (capture - i64::from(self.device.micros())).rem_euclid(HOUR_US);
+        if latency >= HOUR_US / 2 {
+            latency -= HOUR_US;
+        }
+        Duration::microseconds(latency)
+    }
+}
+
+impl<R: Read> TimestampPairs<R> {
+    /// Creates an iterator over a source's timestamp pairs.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use velodyne::io::Pcap;
+    /// use velodyne::latency::TimestampPairs;
+    /// let pairs = TimestampPairs::new(Pcap::open("data/single.pcap").unwrap());
+    /// assert_eq!(1, pairs.count());
+    /// ```
+    pub fn new(read: R) -> TimestampPairs<R> {
+        TimestampPairs { read }
+    }
+}
+
+impl<R: Read> Iterator for TimestampPairs<R> {
+    type Item = Result<TimestampPair>;
+
+    fn next(&mut self) -> Option<Result<TimestampPair>> {
+        loop {
+            let bytes = match self.read.read()? {
+                Ok(bytes) => bytes,
+                Err(err) => return Some(Err(err)),
+            };
+            if vlp_16::classify(bytes).is_none() {
+                continue;
+            }
+            let device = match Packet::new(bytes) {
+                Ok(packet) => packet.timestamp(),
+                Err(err) => return Some(Err(err)),
+            };
+            if let Some(capture) = self.read.timestamp() {
+                return Some(Ok(TimestampPair { device, capture }));
+            }
+        }
+    }
+}
+
+impl Analyzer {
+    /// Creates a new, empty analyzer.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use velodyne::latency::Analyzer;
+    /// let analyzer = Analyzer::new();
+    /// assert!(analyzer.summary().is_none());
+    /// ```
+    pub fn new() -> Analyzer {
+        Analyzer::default()
+    }
+
+    /// Analyzes every packet from a source that knows its capture times.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use velodyne::io::Pcap;
+    /// use velodyne::latency::Analyzer;
+    /// let analyzer = Analyzer::from_read(Pcap::open("data/single.pcap").unwrap()).unwrap();
+    /// assert_eq!(1, analyzer.summary().unwrap().samples);
+    /// ```
+    pub fn from_read<R: Read>(read: R) -> Result<Analyzer> {
+        let mut analyzer = Analyzer::new();
+        for pair in TimestampPairs::new(read) {
+            analyzer.add(&pair?);
+        }
+        Ok(analyzer)
+    }
+
+    /// Adds a packet's timestamps.
+    pub fn add(&mut self, pair: &TimestampPair) {
+        self.latencies.push(pair.latency());
+    }
+
+    /// Returns the latency that `percent` of the packets are at or under, or `None` if there
+    /// aren't any packets.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `percent` isn't between zero and 100.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use velodyne::io::Pcap;
+    /// use velodyne::latency::Analyzer;
+    /// let analyzer = Analyzer::from_read(Pcap::open("data/single.pcap").unwrap()).unwrap();
+    /// assert_eq!(analyzer.percentile(0.), analyzer.percentile(100.));
+    /// ```
+    pub fn percentile(&self, percent: f64) -> Option<Duration> {
+        let mut latencies = self.latencies.clone();
+        latencies.sort();
+        percentile(&latencies, percent)
+    }
+
+    /// Summarizes the latencies, or returns `None` if there aren't any packets.
+    pub fn summary(&self) -> Option<Summary> {
+        let mut latencies = self.latencies.clone();
+        latencies.sort();
+        let total = latencies.iter().fold(Duration::zero(), |total, &latency| total + latency);
+        Some(Summary {
+                 samples: latencies.len(),
+                 mean: total / latencies.len().max(1) as i32,
+                 min: *latencies.first()?,
+                 median: percentile(&latencies, 50.)?,
+                 p95: percentile(&latencies, 95.)?,
+                 p99: percentile(&latencies, 99.)?,
+                 max: *latencies.last()?,
+             })
+    }
+}
+
+/// Returns the nearest-rank percentile of sorted latencies.
+fn percentile(latencies: &[Duration], percent: f64) -> Option<Duration> {
+    assert!((0. ..=100.).contains(&percent),
+            "a percentile must be between zero and 100");
+    let rank = (percent / 100. * latencies.len() as f64).ceil() as usize;
+    latencies.get(rank.max(1) - 1).cloned()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+
+    #[test]
+    fn summary() {
+        let mut analyzer = Analyzer::new();
+        let capture = UTC.ymd(2024, 1, 1).and_hms(13, 0, 1);
+        for micros in 1..101 {
+            analyzer.add(&TimestampPair {
+                              device: HourOffset::from_micros(1_000_000 - micros * 10),
+                              capture,
+                          });
+        }
+        let summary = analyzer.summary().unwrap();
+        assert_eq!(100, summary.samples);
+        assert_eq!(Duration::microseconds(505), summary.mean);
+        assert_eq!(Duration::microseconds(10), summary.min);
+        assert_eq!(Duration::microseconds(500), summary.median);
+        assert_eq!(Duration::microseconds(950), summary.p95);
+        assert_eq!(Duration::microseconds(990), summary.p99);
+        assert_eq!(Duration::microseconds(1_000), summary.max);
+    }
+}
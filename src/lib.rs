@@ -1,6 +1,31 @@
 //! Read data from Velodyne LiDAR sensors.
 //!
-//! As of now, only supports the VLP-16.
+//! Supports the VLP-16, and decodes HDL-32E packets with their own lasers and timing, though the
+//! per-ring tools, e.g. range images, only handle sixteen lasers.
+//!
+//! The quickest way to get at a capture's points is `read_pcap_points`. For long captures and
+//! live sensors, read packets with `io::Read` and decode them with a `vlp_16::Decoder`.
+//! `use velodyne::prelude::*` brings in the most common types.
+//!
+//! The `velodyne` command line tool is built with the `cli` feature, e.g. `cargo install velodyne
+//! --features cli`, so the library doesn't depend on its argument parser.
+//!
+//! Reading pcap files with libpcap requires the default `pcap` feature, and reading live sockets
+//! with `io::Socket` requires the default `socket` feature, which only builds on Unix and Windows.
+//! With `--no-default-features --features std`, the crate is pure Rust and builds for targets like
+//! `wasm32-unknown-unknown`, where captures can still be decoded from bytes with `io::PcapStream`.
+//!
+//! Without the default `std` feature, the crate is `no_std` and only parses packets' bytes, with
+//! the `raw` module, for embedded gateways that receive a sensor's packets directly.
+//!
+//! With the `las` feature, points convert to and from `las::Point`, so the `las` crate can write
+//! and read them.
+//!
+//! With the `nalgebra` feature, points convert into `nalgebra::Point3<f32>` and an
+//! `nalgebra::Isometry3<f32>` can be used anywhere a `transform::Transform` is expected.
+//!
+//! With the `testkit` feature, the `testkit` module generates multi-packet streams, so that
+//! downstream crates can test against realistic data without shipping captures.
 
 #![deny(missing_docs,
         missing_debug_implementations, missing_copy_implementations,
@@ -8,63 +33,378 @@
         unsafe_code,
         unstable_features,
         unused_import_braces, unused_qualifications)]
+#![cfg_attr(not(feature = "std"), no_std)]
 
+#[cfg(feature = "arrow")]
+extern crate arrow_array;
+#[cfg(feature = "arrow")]
+extern crate arrow_schema;
+#[cfg(all(test, feature = "arrow"))]
+extern crate bytes;
 extern crate byteorder;
+#[cfg(feature = "std")]
 extern crate chrono;
+// `no_std` brings `core` in on its own, but with `std` it has to be named for 2015 paths.
+#[cfg(feature = "std")]
+extern crate core;
+#[cfg(feature = "las")]
+extern crate las as las_rs;
+#[cfg(feature = "log")]
+#[macro_use]
+extern crate log;
+#[cfg(feature = "lz4")]
+extern crate lz4_flex;
+#[cfg(feature = "mmap")]
+extern crate memmap;
+#[cfg(feature = "nalgebra")]
+extern crate nalgebra;
+#[cfg(feature = "arrow")]
+extern crate parquet;
+#[cfg(feature = "pcap")]
 extern crate pcap;
+#[cfg(feature = "png")]
+extern crate png;
+#[cfg(feature = "rustc-serialize")]
+extern crate rustc_serialize;
+#[cfg(feature = "socket")]
+extern crate socket2;
+#[cfg(feature = "zstd")]
+extern crate zstd;
 
+// Without the `log` feature, diagnostics compile to nothing (but are still type-checked).
+#[cfg(all(feature = "std", not(feature = "log")))]
+macro_rules! warn {
+    ($($arg:tt)*) => { if false { let _ = format!($($arg)*); } }
+}
+#[cfg(all(feature = "std", not(feature = "log")))]
+macro_rules! debug {
+    ($($arg:tt)*) => { if false { let _ = format!($($arg)*); } }
+}
+
+#[cfg(feature = "std")]
+pub mod align;
+#[cfg(feature = "std")]
+pub mod anonymize;
+#[cfg(feature = "arrow")]
+pub mod arrow;
+#[cfg(feature = "std")]
+pub mod batch;
+#[cfg(feature = "std")]
+pub mod boresight;
+#[cfg(feature = "std")]
+pub mod callback;
+#[cfg(feature = "std")]
+pub mod cloud;
+#[cfg(feature = "std")]
+pub mod correction;
+#[cfg(feature = "std")]
+pub mod csv;
+#[cfg(feature = "std")]
+pub mod crs;
+#[cfg(feature = "std")]
+pub mod curvature;
+#[cfg(feature = "std")]
+pub mod describe;
+#[cfg(feature = "std")]
+pub mod discovery;
+#[cfg(feature = "std")]
+pub mod dump;
+#[cfg(feature = "std")]
+pub mod dynamic;
+#[cfg(feature = "std")]
+pub mod export;
+#[cfg(feature = "std")]
+pub mod filter;
+#[cfg(feature = "ffi")]
+#[allow(unsafe_code)]
+pub mod ffi;
 pub mod fixtures;
+#[cfg(feature = "std")]
+pub mod frame;
+#[cfg(feature = "std")]
+pub mod ground;
+#[cfg(feature = "std")]
+pub mod health;
+#[cfg(feature = "std")]
+pub mod heatmap;
+#[cfg(feature = "std")]
+pub mod info;
+#[cfg(feature = "std")]
+pub mod inspect;
+#[cfg(feature = "std")]
 pub mod io;
+#[cfg(feature = "std")]
+pub mod las;
+#[cfg(feature = "std")]
+pub mod latency;
+#[cfg(feature = "std")]
+pub mod merge;
+#[cfg(feature = "std")]
+pub mod monitor;
+#[cfg(feature = "std")]
 pub mod nmea;
+#[cfg(feature = "png")]
+pub mod panorama;
+#[cfg(feature = "std")]
+pub mod pcd;
+#[cfg(feature = "std")]
+pub mod pipeline;
+#[cfg(feature = "std")]
 pub mod point;
+#[cfg(feature = "std")]
+pub mod prelude;
+#[cfg(feature = "std")]
+pub mod range_image;
+pub mod raw;
+#[cfg(feature = "std")]
+pub mod reconstruct;
+#[cfg(feature = "std")]
+pub mod resample;
+#[cfg(feature = "std")]
+pub mod ros;
+#[cfg(feature = "sensor-http")]
+pub mod sensor_http;
+#[cfg(feature = "sidecar")]
+pub mod sidecar;
+#[cfg(feature = "std")]
+pub mod sink;
+#[cfg(feature = "std")]
+pub mod source;
+#[cfg(feature = "std")]
+pub mod spec;
+#[cfg(feature = "std")]
+pub mod spill;
+#[cfg(feature = "std")]
+pub mod stream;
+#[cfg(feature = "testkit")]
+pub mod testkit;
+#[cfg(feature = "std")]
+pub mod time;
+#[cfg(feature = "std")]
+pub mod timing;
+#[cfg(feature = "png")]
+pub mod topdown;
+#[cfg(feature = "std")]
+pub mod transform;
+pub mod units;
+#[cfg(feature = "std")]
+pub mod velocity;
+#[cfg(feature = "std")]
 pub mod vlp_16;
+#[cfg(feature = "std")]
+pub mod voxel;
+#[cfg(feature = "std")]
+pub mod warning;
+pub mod wire;
 
+#[cfg(feature = "std")]
+pub use batch::{read_pcap_frames, read_pcap_points};
+#[cfg(feature = "std")]
 pub use point::Point;
+#[cfg(feature = "std")]
+pub use vlp_16::Packet;
 
 /// Our crate-specific error enum.
+#[cfg(feature = "std")]
 #[derive(Debug)]
 pub enum Error {
+    /// Wrapper around `arrow_schema::ArrowError`.
+    #[cfg(feature = "arrow")]
+    Arrow(arrow_schema::ArrowError),
     /// Wrapper around `chrono::ParseError`.
     ChronoParse(chrono::ParseError),
+    /// A coordinate reference system is malformed or can't be stored.
+    Crs(String),
+    /// Something went wrong when talking to a sensor's web interface.
+    Http(String),
     /// Invalid sensor code.
     InvalidSensor(u8),
     /// Invalid start identifier for a data block.
     InvalidStartIdentifier(u16),
     /// Invalid return mode code.
     InvalidReturnMode(u8),
+    /// Invalid pulse per second status code.
+    InvalidPpsStatus(u8),
+    /// Invalid magic number at the start of a pcap stream.
+    InvalidPcapMagicNumber(u32),
+    /// A pcap record's timestamp is out of range, e.g. because its header is corrupt.
+    InvalidPcapTimestamp {
+        /// The record's seconds.
+        seconds: u32,
+        /// The record's microseconds, or nanoseconds in a nanosecond pcap.
+        fraction: u32,
+    },
+    /// A pcap record holds only part of a Velodyne packet, because the capture's snap length was
+    /// too small.
+    TruncatedRecord {
+        /// The number of bytes in the record.
+        captured: usize,
+        /// The packet's length on the wire.
+        original: usize,
+    },
+    /// A pcap record is longer than the capture's snap length allows, e.g. because its header is
+    /// corrupt.
+    OversizedRecord {
+        /// The length of the record's data, from its header.
+        len: usize,
+        /// The longest record that the capture can hold.
+        max: usize,
+    },
+    /// A data packet was needed, but the packet is a position packet.
+    NotADataPacket,
+    /// A position packet was needed, but the packet is a data packet.
+    NotAPositionPacket,
+    /// A frame dump is malformed or unsupported.
+    FrameDump(String),
+    /// A PCD file is malformed or unsupported.
+    Pcd(String),
+    /// A capture's sidecar is malformed or unsupported.
+    Sidecar(String),
+    /// A filename template is malformed.
+    Template(String),
     /// Wrapper around `std::io::Error`.
     Io(std::io::Error),
     /// Something went wrong when parsing a NMEA string.
-    Nmea(String),
+    Nmea(nmea::ParseError),
     /// Wrapper around `std::num::ParseFloatError`.
     ParseFloat(std::num::ParseFloatError),
+    /// Wrapper around `std::num::ParseIntError`.
+    ParseInt(std::num::ParseIntError),
+    /// Wrapper around `parquet::errors::ParquetError`.
+    #[cfg(feature = "arrow")]
+    Parquet(parquet::errors::ParquetError),
     /// Wrapper around `pcap::Error`.
+    #[cfg(feature = "pcap")]
     Pcap(pcap::Error),
+    /// Wrapper around `png::EncodingError`.
+    #[cfg(feature = "png")]
+    Png(png::EncodingError),
 }
 
+#[cfg(feature = "std")]
+impl std::fmt::Display for Error {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match *self {
+            #[cfg(feature = "arrow")]
+            Error::Arrow(ref err) => err.fmt(f),
+            Error::ChronoParse(ref err) => err.fmt(f),
+            Error::Crs(ref message) => {
+                write!(f, "invalid coordinate reference system: {}", message)
+            }
+            Error::Http(ref message) => write!(f, "sensor web interface error: {}", message),
+            Error::InvalidSensor(code) => write!(f, "invalid sensor code: 0x{:02x}", code),
+            Error::InvalidStartIdentifier(identifier) => {
+                write!(f, "invalid data block start identifier: 0x{:04x}", identifier)
+            }
+            Error::InvalidReturnMode(code) => write!(f, "invalid return mode code: 0x{:02x}", code),
+            Error::InvalidPpsStatus(code) => write!(f, "invalid PPS status code: {}", code),
+            Error::InvalidPcapMagicNumber(magic_number) => {
+                write!(f, "not a pcap file, magic number 0x{:08x}", magic_number)
+            }
+            Error::InvalidPcapTimestamp { seconds, fraction } => {
+                write!(f, "invalid pcap record timestamp: {}.{}", seconds, fraction)
+            }
+            Error::TruncatedRecord { captured, original } => {
+                write!(f,
+                       "pcap record holds {} of the packet's {} bytes, the snap length is too \
+                        small",
+                       captured,
+                       original)
+            }
+            Error::OversizedRecord { len, max } => {
+                write!(f, "pcap record of {} bytes is longer than the {} allowed", len, max)
+            }
+            Error::NotADataPacket => f.write_str("not a data packet"),
+            Error::NotAPositionPacket => f.write_str("not a position packet"),
+            Error::FrameDump(ref message) => write!(f, "invalid frame dump: {}", message),
+            Error::Pcd(ref message) => write!(f, "invalid PCD file: {}", message),
+            Error::Sidecar(ref message) => write!(f, "invalid sidecar: {}", message),
+            Error::Template(ref message) => write!(f, "invalid filename template: {}", message),
+            Error::Io(ref err) => err.fmt(f),
+            Error::Nmea(ref err) => err.fmt(f),
+            Error::ParseFloat(ref err) => err.fmt(f),
+            Error::ParseInt(ref err) => err.fmt(f),
+            #[cfg(feature = "arrow")]
+            Error::Parquet(ref err) => err.fmt(f),
+            #[cfg(feature = "pcap")]
+            Error::Pcap(ref err) => err.fmt(f),
+            #[cfg(feature = "png")]
+            Error::Png(ref err) => err.fmt(f),
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for Error {}
+
+#[cfg(feature = "std")]
 impl From<std::io::Error> for Error {
     fn from(err: std::io::Error) -> Error {
         Error::Io(err)
     }
 }
 
+#[cfg(feature = "std")]
 impl From<std::num::ParseFloatError> for Error {
     fn from(err: std::num::ParseFloatError) -> Error {
         Error::ParseFloat(err)
     }
 }
 
+#[cfg(feature = "std")]
+impl From<std::num::ParseIntError> for Error {
+    fn from(err: std::num::ParseIntError) -> Error {
+        Error::ParseInt(err)
+    }
+}
+
+#[cfg(feature = "std")]
 impl From<chrono::ParseError> for Error {
     fn from(err: chrono::ParseError) -> Error {
         Error::ChronoParse(err)
     }
 }
 
+#[cfg(feature = "std")]
+impl From<raw::ParseError> for Error {
+    fn from(err: raw::ParseError) -> Error {
+        match err {
+            raw::ParseError::TooShort(_) => {
+                std::io::Error::from(std::io::ErrorKind::UnexpectedEof).into()
+            }
+            raw::ParseError::InvalidStartIdentifier(n) => Error::InvalidStartIdentifier(n),
+        }
+    }
+}
+
+#[cfg(feature = "arrow")]
+impl From<arrow_schema::ArrowError> for Error {
+    fn from(err: arrow_schema::ArrowError) -> Error {
+        Error::Arrow(err)
+    }
+}
+
+#[cfg(feature = "arrow")]
+impl From<parquet::errors::ParquetError> for Error {
+    fn from(err: parquet::errors::ParquetError) -> Error {
+        Error::Parquet(err)
+    }
+}
+
+#[cfg(feature = "pcap")]
 impl From<pcap::Error> for Error {
     fn from(err: pcap::Error) -> Error {
         Error::Pcap(err)
     }
 }
 
+#[cfg(feature = "png")]
+impl From<png::EncodingError> for Error {
+    fn from(err: png::EncodingError) -> Error {
+        Error::Png(err)
+    }
+}
+
 /// Our crate-specific result type.
+#[cfg(feature = "std")]
 pub type Result<T> = std::result::Result<T, Error>;
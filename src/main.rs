@@ -1,28 +1,611 @@
+extern crate chrono;
 extern crate docopt;
-extern crate rustc_serialize;
 extern crate velodyne;
 
-use docopt::Docopt;
-use velodyne::io::{Read, Pcap};
+use chrono::{Duration, TimeZone, UTC};
+use docopt::{ArgvMap, Docopt};
+use std::fmt;
+use std::fs::{self, File};
+use std::io::{self, BufWriter};
+use std::num::{ParseFloatError, ParseIntError};
+use std::path::Path;
+use std::process;
+use velodyne::Error;
+use velodyne::anonymize::{Anonymizer, Gnss};
+use velodyne::cloud::Bounds;
+use velodyne::csv;
+use velodyne::describe;
+use velodyne::dump::Writer as FrameWriter;
+use velodyne::export::{FrameExporter, Template};
+use velodyne::info::{Estimate, Info, Sample, Scan};
+use velodyne::inspect::Report;
+use velodyne::io::{Pcap, PcapStream, PcapWriter, Read, SplitLimit, SplitWriter};
+use velodyne::nmea::Position;
+use velodyne::pcd::{self, Encoding};
+use velodyne::sidecar::Sidecar;
+use velodyne::sink::{self, PointSink};
+use velodyne::stream::PointStream;
+use velodyne::topdown::{TopDown, Value};
+use velodyne::vlp_16::{self, Packet};
 
-const USAGE: &'static str = "
-Usage: velodyne info <infile>
+/// The exit code for errors reading or writing files, from sysexits.h.
+const EXIT_IO: i32 = 74;
+/// The exit code for data that can't be decoded, from sysexits.h.
+const EXIT_DECODE: i32 = 65;
+/// The exit code for everything else, e.g. a malformed option.
+const EXIT_OTHER: i32 = 1;
+
+const USAGE: &str = "
+Read data from Velodyne LiDAR sensors.
+
+Usage:
+    velodyne info [--extent] [--continue-on-error] <infile>
+    velodyne info --quick <infile>
+    velodyne info --fast [--every=<n>] <infile>
+    velodyne describe [--packets=<n>] <infile>
+    velodyne dump --packet=<n> [--channels=<list>] <infile>
+    velodyne render [--by=<value>] [--resolution=<meters>] [--bounds=<list>] -o <outfile> <infile>
+    velodyne convert -o <outfile> <infile>
+    velodyne frames [--sensor=<id>] [--manifest=<file>] -o <template> <infile>
+    velodyne anonymize [--drop-positions | --offset=<list>] -o <outfile> <infile>
+    velodyne split (--packets=<n> | --megabytes=<n>) -o <prefix> <infile>
+    velodyne sidecar [--notes=<text>] [-o <outfile>] <infile>
+    velodyne (-h | --help)
+    velodyne --version
+
+Use `-` as the infile to read a pcap stream from standard input. `info --fast` only decodes every
+nth packet and prints its estimates with 95% confidence intervals, for captures too large to read
+in full. `describe` reads the first few packets, or at most n, and prints the sensor, return
+mode, rotation rate, the addresses and ports the packets were sent from and to, and whether a GNSS
+receiver is connected. `dump` prints the decoded structure and the bytes of one packet, counting
+from one like Wireshark does. `render` writes a top-down PNG of the points, where each pixel shows
+the highest point that falls into it.
+`convert` writes the points as CSV, PCD, or a frame dump, by the outfile's extension: `.csv`,
+`.pcd`, or `.vldf`. `frames` writes each frame to its own file in the same formats, named by a
+template with `{index}`, `{index:06}`, `{timestamp}`, and `{sensor}` placeholders, and can write a
+manifest of the files as CSV or JSON, by its extension.
+`anonymize` copies the Velodyne packets to a new pcap without the NMEA sentences of position
+packets, or with their positions shifted, so that the capture doesn't disclose where it was
+recorded. `split` writes standalone pcaps of at most n packets or megabytes each, named like
+`<prefix>-0001.pcap`. `sidecar` writes a JSON file with the capture's sensor, return mode, and
+rotation rate next to it, e.g. `capture.json` for `capture.pcap`, which can then be edited to add
+a calibration, mounting pose, and time reference. `anonymize` and `split` copy the infile's
+sidecar, if it has one, next to what they write.
+
+Exits with 74 if a file can't be read or written, 65 if a packet can't be decoded, and 1 for any
+other error.
+
+Options:
+    -h --help           Show this message.
+    --version           Show the version.
+    --extent            Report the bounding box and azimuth coverage of the points.
+    --quick             Only read packet metadata, without decoding points.
+    --fast              Estimate the summary from a sample of the packets.
+    --every=<n>         Sample every nth packet [default: 100].
+    --continue-on-error  Report packets that can't be decoded and keep going.
+    --packet=<n>        The packet to dump.
+    --channels=<list>   Comma-separated laser channels whose data records are dumped.
+    --by=<value>        Color by intensity or height [default: intensity].
+    --resolution=<meters>  The size of a pixel [default: 0.1].
+    --bounds=<list>     The rendered area as min x, min y, max x, max y, in meters.
+    --drop-positions    Drop position packets instead of blanking their NMEA sentences.
+    --offset=<list>     Shift positions by latitude, longitude, in degrees.
+    --packets=<n>       The most packets in each split file, or to describe.
+    --megabytes=<n>     The most megabytes, including pcap headers, in each split file.
+    --notes=<text>      Notes to record in the sidecar.
+    --sensor=<id>       The sensor's identifier, for the template and the manifest.
+    --manifest=<file>   Write a manifest of the frame files, as `.csv` or `.json`.
+    -o <outfile>        The output file.
 ";
 
-#[derive(Debug, RustcDecodable)]
-struct Args {
-    cmd_info: bool,
-    arg_infile: String,
+fn main() {
+    let args = Docopt::new(USAGE)
+        .and_then(|d| d.version(Some(env!("CARGO_PKG_VERSION").to_string())).parse())
+        .unwrap_or_else(|e| e.exit());
+    let result = if args.get_bool("info") {
+        info(&args)
+    } else if args.get_bool("describe") {
+        describe_stream(&args)
+    } else if args.get_bool("dump") {
+        dump(&args)
+    } else if args.get_bool("render") {
+        render(&args)
+    } else if args.get_bool("convert") {
+        convert(&args)
+    } else if args.get_bool("frames") {
+        frames(&args)
+    } else if args.get_bool("anonymize") {
+        anonymize(&args)
+    } else if args.get_bool("split") {
+        split(&args)
+    } else if args.get_bool("sidecar") {
+        sidecar(&args)
+    } else {
+        Ok(())
+    };
+    if let Err(failure) = result {
+        eprintln!("velodyne: {}", failure);
+        process::exit(failure.exit_code());
+    }
+}
+
+/// An error, with where in the input it happened.
+struct Failure {
+    error: Error,
+    context: Option<String>,
 }
 
-fn main() {
-    let args: Args = Docopt::new(USAGE).and_then(|d| d.decode()).unwrap_or_else(|e| e.exit());
-    if args.cmd_info {
-        let pcap = Pcap::open(args.arg_infile).unwrap();
-        let mut npoints = 0;
-        for packet in pcap.vlp_16_packets().map(|result| result.unwrap()) {
-            npoints += packet.points().unwrap().len();
+type Result<T> = std::result::Result<T, Failure>;
+
+impl Failure {
+    /// Returns this failure with the file, and maybe the packet, that caused it.
+    fn at(mut self, infile: &str, packet: Option<usize>) -> Failure {
+        self.context = Some(match packet {
+                                Some(n) => format!("{}, packet {}", infile, n),
+                                None => infile.to_string(),
+                            });
+        self
+    }
+
+    fn exit_code(&self) -> i32 {
+        match self.error {
+            Error::Io(_) | Error::Pcap(_) | Error::Png(_) => EXIT_IO,
+            Error::InvalidSensor(_) |
+            Error::InvalidStartIdentifier(_) |
+            Error::InvalidReturnMode(_) |
+            Error::InvalidPpsStatus(_) |
+            Error::InvalidPcapMagicNumber(_) |
+            Error::InvalidPcapTimestamp { .. } |
+            Error::TruncatedRecord { .. } |
+            Error::OversizedRecord { .. } |
+            Error::NotADataPacket |
+            Error::NotAPositionPacket |
+            Error::Nmea(_) => EXIT_DECODE,
+            _ => EXIT_OTHER,
+        }
+    }
+}
+
+impl fmt::Display for Failure {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        if let Some(ref context) = self.context {
+            write!(f, "{}: ", context)?;
+        }
+        write!(f, "{}", self.error)
+    }
+}
+
+impl From<Error> for Failure {
+    fn from(error: Error) -> Failure {
+        Failure {
+            error,
+            context: None,
         }
-        println!("Points: {}", npoints);
     }
 }
+
+impl From<io::Error> for Failure {
+    fn from(err: io::Error) -> Failure {
+        Error::from(err).into()
+    }
+}
+
+impl From<ParseIntError> for Failure {
+    fn from(err: ParseIntError) -> Failure {
+        Error::from(err).into()
+    }
+}
+
+impl From<ParseFloatError> for Failure {
+    fn from(err: ParseFloatError) -> Failure {
+        Error::from(err).into()
+    }
+}
+
+fn open<'a>(infile: &str, stdin: &'a io::Stdin) -> Result<Box<dyn Read + 'a>> {
+    let read: velodyne::Result<Box<dyn Read + 'a>> = if infile == "-" {
+        PcapStream::new(stdin.lock()).map(|stream| Box::new(stream) as Box<dyn Read>)
+    } else {
+        Pcap::open(infile).map(|pcap| Box::new(pcap) as Box<dyn Read>)
+    };
+    read.map_err(|err| Failure::from(err).at(infile, None))
+}
+
+fn info(args: &ArgvMap) -> Result<()> {
+    let infile = args.get_str("<infile>");
+    let continue_on_error = args.get_bool("--continue-on-error");
+    let stdin = io::stdin();
+    let mut read = open(infile, &stdin)?;
+    if args.get_bool("--fast") {
+        let every = args.get_str("--every").parse::<usize>()?.max(1);
+        let sample = Sample::from_read(read, every)
+            .map_err(|err| Failure::from(err).at(infile, None))?;
+        println!("Packets: {} ({} sampled)", sample.packets, sample.sampled);
+        println!("Data packets: {}", estimate(&sample.data_packets));
+        println!("Position packets: {}", estimate(&sample.position_packets));
+        println!("Points: {}", estimate(&sample.points));
+        if let Some(loss) = sample.loss {
+            println!("Loss: {:.2}% ({:.2}% to {:.2}%)",
+                     loss.value * 100.,
+                     loss.low * 100.,
+                     loss.high * 100.);
+        }
+        return Ok(());
+    }
+    let mut scan = Scan::default();
+    let mut info = if args.get_bool("--quick") {
+        None
+    } else if args.get_bool("--extent") {
+        Some(Info::with_extent())
+    } else {
+        Some(Info::new())
+    };
+    let mut errors = 0;
+    let mut n = 0;
+    while let Some(result) = read.read() {
+        n += 1;
+        let bytes = result.map_err(|err| Failure::from(err).at(infile, Some(n)))?;
+        scan.add(bytes);
+        if let Some(ref mut info) = info {
+            if vlp_16::classify(bytes).is_some() {
+                match Packet::new(bytes) {
+                    Ok(packet) => info.add(&packet),
+                    Err(err) => {
+                        let failure = Failure::from(err).at(infile, Some(n));
+                        if !continue_on_error {
+                            return Err(failure);
+                        }
+                        eprintln!("velodyne: skipping {}", failure);
+                        errors += 1;
+                    }
+                }
+            }
+        }
+        if let Some(time) = read.timestamp() {
+            scan.add_capture_time(time);
+        }
+    }
+    print_scan(&scan);
+    if continue_on_error {
+        println!("Undecodable packets: {}", errors);
+    }
+    if let Some(info) = info {
+        println!("Points: {}", info.points);
+        if let Some(extent) = info.extent {
+            if let Some(bounds) = extent.bounds {
+                println!("X: {} to {}", bounds.min_x, bounds.max_x);
+                println!("Y: {} to {}", bounds.min_y, bounds.max_y);
+                println!("Z: {} to {}", bounds.min_z, bounds.max_z);
+            }
+            println!("Azimuth coverage: {}/360 degrees", extent.azimuth_coverage());
+        }
+    }
+    Ok(())
+}
+
+fn describe_stream(args: &ArgvMap) -> Result<()> {
+    let infile = args.get_str("<infile>");
+    let limit = match args.get_str("--packets") {
+        "" => describe::DEFAULT_PACKET_LIMIT,
+        packets => packets.parse::<usize>()?,
+    };
+    let stdin = io::stdin();
+    let read = open(infile, &stdin)?;
+    let description = describe::describe_packets(read, limit)
+        .map_err(|err| Failure::from(err).at(infile, None))?;
+    println!("Packets: {}", description.packets);
+    println!("Data packets: {}", description.data_packets);
+    println!("Position packets: {}", description.position_packets);
+    if let Some(sensor) = description.sensor {
+        println!("Sensor: {:?}", sensor);
+    }
+    if let Some(return_mode) = description.return_mode {
+        println!("Return mode: {:?}", return_mode);
+    }
+    if let Some(rpm) = description.rpm {
+        println!("RPM: {:.1}", rpm);
+    }
+    for endpoint in &description.endpoints {
+        println!("Endpoint: {} to port {} ({} packets)",
+                 endpoint.source,
+                 endpoint.port,
+                 endpoint.packets);
+    }
+    println!("GNSS: {}", if description.has_gnss { "yes" } else { "no" });
+    Ok(())
+}
+
+fn dump(args: &ArgvMap) -> Result<()> {
+    let n = args.get_str("--packet").parse::<usize>()?;
+    if n == 0 {
+        docopt::Error::Argv("packets are counted from one".to_string()).exit();
+    }
+    let mut channels = Vec::new();
+    for channel in args.get_str("--channels").split(',').filter(|s| !s.is_empty()) {
+        channels.push(channel.trim().parse::<u8>()?);
+    }
+    let infile = args.get_str("<infile>");
+    let stdin = io::stdin();
+    let mut read = open(infile, &stdin)?;
+    let skipped = read.skip_packets(n - 1).map_err(|err| Failure::from(err).at(infile, None))?;
+    let bytes = match read.read() {
+        Some(result) => result.map_err(|err| Failure::from(err).at(infile, Some(n)))?,
+        None => {
+            let message = format!("the capture only has {} packets", skipped);
+            let err = io::Error::new(io::ErrorKind::UnexpectedEof, message);
+            return Err(Failure::from(err).at(infile, None));
+        }
+    };
+    print!("{}", Report::new(bytes).channels(&channels));
+    Ok(())
+}
+
+fn render(args: &ArgvMap) -> Result<()> {
+    let value = match args.get_str("--by") {
+        "intensity" => Value::Intensity,
+        "height" => Value::Height,
+        by => docopt::Error::Argv(format!("cannot color by {}", by)).exit(),
+    };
+    let mut bounds = Vec::new();
+    for bound in args.get_str("--bounds").split(',').filter(|s| !s.is_empty()) {
+        bounds.push(bound.trim().parse::<f32>()?);
+    }
+    if !bounds.is_empty() && bounds.len() != 4 {
+        docopt::Error::Argv("bounds are min x, min y, max x, max y".to_string()).exit();
+    }
+    let infile = args.get_str("<infile>");
+    let stdin = io::stdin();
+    let mut stream = open(infile, &stdin)?.points();
+    let points = stream.by_ref().collect::<Vec<_>>();
+    if let Some(err) = stream.error() {
+        eprintln!("velodyne: {}: stopped at an unreadable packet: {}", infile, err);
+    }
+    report_skipped(infile, stream.skipped_packets());
+    let mut top_down = TopDown::new(value);
+    top_down.resolution(args.get_str("--resolution").parse()?);
+    if let [min_x, min_y, max_x, max_y] = bounds[..] {
+        // Heights are colored over the points that are inside the rendered area.
+        let (mut min_z, mut max_z) = (0., 0.);
+        let inside = points.iter().filter(|point| {
+            point.range() > 0. && point.x >= min_x && point.x <= max_x && point.y >= min_y &&
+            point.y <= max_y
+        });
+        for (i, point) in inside.enumerate() {
+            if i == 0 || point.z < min_z {
+                min_z = point.z;
+            }
+            if i == 0 || point.z > max_z {
+                max_z = point.z;
+            }
+        }
+        top_down.extent(Bounds::new([min_x, min_y, min_z], [max_x, max_y, max_z]));
+    }
+    let outfile = args.get_str("-o");
+    top_down.write_to_path(&points, outfile).map_err(|err| Failure::from(err).at(outfile, None))
+}
+
+/// Tells the user how many packets weren't Velodyne packets, if any.
+fn report_skipped(infile: &str, skipped: usize) {
+    if skipped > 0 {
+        eprintln!("velodyne: {}: skipped {} non-Velodyne packets", infile, skipped);
+    }
+}
+
+fn convert(args: &ArgvMap) -> Result<()> {
+    let outfile = args.get_str("-o");
+    let extension = Path::new(outfile).extension().and_then(|extension| extension.to_str());
+    if !["csv", "pcd", "vldf"].contains(&extension.unwrap_or("")) {
+        docopt::Error::Argv(format!("cannot tell the format of {}", outfile)).exit();
+    }
+    let infile = args.get_str("<infile>");
+    let stdin = io::stdin();
+    let mut points = open(infile, &stdin)?.points();
+    let write = BufWriter::new(File::create(outfile)
+                                   .map_err(|err| Failure::from(err).at(outfile, None))?);
+    let mut sink: Box<dyn PointSink> = match extension {
+        Some("csv") => Box::new(csv::Writer::new(write)),
+        Some("pcd") => Box::new(pcd::Writer::new(write, Encoding::Binary)),
+        _ => Box::new(FrameWriter::new(write)),
+    };
+    sink::write_frames(points.by_ref().frames(), &mut sink)
+        .map_err(|err| Failure::from(err).at(outfile, None))?;
+    if let Some(err) = points.error() {
+        eprintln!("velodyne: {}: stopped at an unreadable packet: {}", infile, err);
+    }
+    report_skipped(infile, points.skipped_packets());
+    Ok(())
+}
+
+fn frames(args: &ArgvMap) -> Result<()> {
+    let template = args.get_str("-o");
+    let extension = Path::new(template).extension().and_then(|extension| extension.to_str());
+    if !["csv", "pcd", "vldf"].contains(&extension.unwrap_or("")) {
+        docopt::Error::Argv(format!("cannot tell the format of {}", template)).exit();
+    }
+    let manifest = args.get_str("--manifest");
+    let manifest_extension =
+        Path::new(manifest).extension().and_then(|extension| extension.to_str());
+    if !manifest.is_empty() && !["csv", "json"].contains(&manifest_extension.unwrap_or("")) {
+        docopt::Error::Argv(format!("cannot tell the format of {}", manifest)).exit();
+    }
+    let infile = args.get_str("<infile>");
+    let stdin = io::stdin();
+    let mut points = open(infile, &stdin)?.points();
+    let create = |path: &str| -> velodyne::Result<Box<dyn PointSink>> {
+        if let Some(parent) = Path::new(path).parent() {
+            fs::create_dir_all(parent)?;
+        }
+        let write = BufWriter::new(File::create(path)?);
+        Ok(match extension {
+               Some("csv") => Box::new(csv::Writer::new(write)),
+               Some("pcd") => Box::new(pcd::Writer::new(write, Encoding::Binary)),
+               _ => Box::new(FrameWriter::new(write)),
+           })
+    };
+    let mut exporter = FrameExporter::new(Template::new(template)?, create);
+    exporter.sensor(args.get_str("--sensor"));
+    sink::write_frames(points.by_ref().frames(), &mut exporter)
+        .map_err(|err| Failure::from(err).at(template, None))?;
+    if let Some(err) = points.error() {
+        eprintln!("velodyne: {}: stopped at an unreadable packet: {}", infile, err);
+    }
+    report_skipped(infile, points.skipped_packets());
+    if !manifest.is_empty() {
+        let write = BufWriter::new(File::create(manifest)
+                                       .map_err(|err| Failure::from(err).at(manifest, None))?);
+        let written = if manifest_extension == Some("csv") {
+            exporter.manifest().write_csv(write)
+        } else {
+            exporter.manifest().write_json(write)
+        };
+        written.map_err(|err| Failure::from(err).at(manifest, None))?;
+    }
+    Ok(())
+}
+
+fn anonymize(args: &ArgvMap) -> Result<()> {
+    let mut anonymizer = Anonymizer::new();
+    if args.get_bool("--drop-positions") {
+        anonymizer.gnss(Gnss::Drop);
+    }
+    let mut offset = Vec::new();
+    for degrees in args.get_str("--offset").split(',').filter(|s| !s.is_empty()) {
+        offset.push(degrees.trim().parse::<f64>()?);
+    }
+    match offset[..] {
+        [] => {}
+        [latitude, longitude] => {
+            anonymizer.gnss(Gnss::Offset {
+                                latitude,
+                                longitude,
+                            });
+        }
+        _ => docopt::Error::Argv("the offset is latitude, longitude".to_string()).exit(),
+    }
+    let infile = args.get_str("<infile>");
+    let stdin = io::stdin();
+    let read = open(infile, &stdin)?;
+    let outfile = args.get_str("-o");
+    let mut writer = PcapWriter::create(outfile)
+        .map_err(|err| Failure::from(err).at(outfile, None))?;
+    anonymizer.copy(read, &mut writer).map_err(|err| Failure::from(err).at(infile, None))?;
+    copy_sidecar(infile, outfile)
+}
+
+fn split(args: &ArgvMap) -> Result<()> {
+    let limit = if args.get_bool("--packets") {
+        SplitLimit::Packets(args.get_str("--packets").parse()?)
+    } else {
+        let megabytes = args.get_str("--megabytes").parse::<f64>()?;
+        SplitLimit::Bytes((megabytes * 1e6) as u64)
+    };
+    if limit == SplitLimit::Packets(0) {
+        docopt::Error::Argv("split files need at least one packet".to_string()).exit();
+    }
+    let infile = args.get_str("<infile>");
+    let stdin = io::stdin();
+    let mut read = open(infile, &stdin)?;
+    let prefix = args.get_str("-o");
+    let path = |n: usize| format!("{}-{:04}.pcap", prefix, n + 1);
+    let mut writer = SplitWriter::new(limit, |n| Ok(BufWriter::new(File::create(path(n))?)));
+    let mut bytes = Vec::new();
+    let mut n = 0;
+    while let Some(result) = read.read() {
+        n += 1;
+        bytes.clear();
+        bytes.extend_from_slice(result.map_err(|err| Failure::from(err).at(infile, Some(n)))?);
+        let time = read.timestamp().unwrap_or_else(|| UTC.timestamp(0, 0));
+        writer.write(&bytes, time)?;
+    }
+    writer.flush()?;
+    for n in 0..writer.streams() {
+        copy_sidecar(infile, &path(n))?;
+    }
+    println!("Files written: {}", writer.streams());
+    Ok(())
+}
+
+fn sidecar(args: &ArgvMap) -> Result<()> {
+    let infile = args.get_str("<infile>");
+    let stdin = io::stdin();
+    let mut read = open(infile, &stdin)?;
+    let mut scan = Scan::default();
+    let mut n = 0;
+    while let Some(result) = read.read() {
+        n += 1;
+        scan.add(result.map_err(|err| Failure::from(err).at(infile, Some(n)))?);
+    }
+    let mut sidecar = Sidecar::from_scan(&scan);
+    sidecar.notes = args.get_str("--notes").to_string();
+    let outfile = match args.get_str("-o") {
+        "" if infile == "-" => {
+            println!("{}", sidecar.to_json());
+            return Ok(());
+        }
+        "" => Sidecar::path_for(infile),
+        outfile => outfile.into(),
+    };
+    sidecar.write_to_path(&outfile)
+        .map_err(|err| Failure::from(err).at(&outfile.to_string_lossy(), None))
+}
+
+/// Copies a capture's sidecar, if it has one, to go with a capture that was written from it.
+fn copy_sidecar(infile: &str, outfile: &str) -> Result<()> {
+    let path = Sidecar::path_for(infile);
+    if infile == "-" || !path.is_file() {
+        return Ok(());
+    }
+    // Copied verbatim, so fields from newer versions of the format aren't lost.
+    fs::copy(&path, Sidecar::path_for(outfile))?;
+    Ok(())
+}
+
+fn print_scan(scan: &Scan) {
+    println!("Data packets: {}", scan.data_packets);
+    println!("Position packets: {}", scan.position_packets);
+    println!("Other packets: {}", scan.other_packets);
+    if let Some(sensor) = scan.sensor {
+        println!("Sensor: {:?}", sensor);
+    }
+    if let Some(return_mode) = scan.return_mode {
+        println!("Return mode: {:?}", return_mode);
+    }
+    if let (Some(first), Some(last)) = (scan.first_capture_time, scan.last_capture_time) {
+        println!("Capture time: {} to {}", first, last);
+    }
+    if let Some(duration) = scan.capture_duration() {
+        println!("Capture duration: {} seconds", seconds(duration));
+    }
+    if let Some(duration) = scan.device_duration() {
+        println!("Device duration: {} seconds", seconds(duration));
+    }
+    if let Some(rpm) = scan.rpm() {
+        println!("RPM: {:.1}", rpm);
+    }
+    match scan.largest_gap {
+        Some(largest) => println!("Gaps: {} (largest {} seconds)", scan.gaps, seconds(largest)),
+        None => println!("Gaps: {}", scan.gaps),
+    }
+    if let Some(ref position) = scan.first_position {
+        println!("First position: {}", describe(position));
+    }
+    if let Some(ref position) = scan.last_position {
+        println!("Last position: {}", describe(position));
+    }
+}
+
+fn estimate(estimate: &Estimate) -> String {
+    format!("~{:.0} ({:.0} to {:.0})", estimate.value, estimate.low, estimate.high)
+}
+
+fn seconds(duration: Duration) -> f64 {
+    duration.num_microseconds().map_or(0., |us| us as f64 / 1e6)
+}
+
+fn describe(position: &Position) -> String {
+    format!("{}, {} at {}{}",
+            position.latitude,
+            position.longitude,
+            position.datetime,
+            if position.valid { "" } else { " (invalid)" })
+}
@@ -0,0 +1,213 @@
+//! Merge the frames of several sensors into one stream, e.g. for a rig with two pucks.
+//!
+//! Each sensor's frames are transformed into a common frame, usually the vehicle's, and frames
+//! that start at about the same time are combined. Sensors should share a time reference, e.g.
+//! a GPS receiver, for their frames to line up.
+
+use chrono::Duration;
+use frame::Frame;
+use point::Time;
+use transform::Transform;
+
+/// By default, frames that start within this many milliseconds of each other are merged, which is
+/// half of a rotation at 600 RPM.
+const DEFAULT_TOLERANCE_MS: i64 = 50;
+
+/// Merges the frames of several sensors.
+///
+/// Points without a return are dropped, since they'd otherwise end up at the sensor's position.
+/// Channel numbers are kept, so they aren't unique in a merged frame, but points are stamped with
+/// the index of their sensor, in the order the sensors were added, unless they already have a
+/// sensor id. A merged frame is complete if it includes a complete frame from every sensor.
+#[derive(Debug)]
+pub struct Merge<I> {
+    sensors: Vec<Sensor<I>>,
+    tolerance: Duration,
+}
+
+#[derive(Debug)]
+struct Sensor<I> {
+    frames: I,
+    transform: Transform,
+    next: Option<Frame>,
+}
+
+impl<I: Iterator<Item = Frame>> Merge<I> {
+    /// Creates a new merge without any sensors.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use velodyne::frame::Frame;
+    /// use velodyne::merge::Merge;
+    /// let merge: Merge<std::vec::IntoIter<Frame>> = Merge::new();
+    /// ```
+    pub fn new() -> Merge<I> {
+        Merge {
+            sensors: Vec::new(),
+            tolerance: Duration::milliseconds(DEFAULT_TOLERANCE_MS),
+        }
+    }
+
+    /// Adds a sensor's frames and the transform from the sensor's frame into the common frame.
+    ///
+    /// With the `nalgebra` feature, the transform can also be an `Isometry3<f32>`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use velodyne::fixtures::VLP_16_DATA_PACKET;
+    /// use velodyne::frame::Frame;
+    /// use velodyne::merge::Merge;
+    /// use velodyne::transform::Transform;
+    /// use velodyne::vlp_16::Packet;
+    /// let frame = Frame::new(Packet::new(&VLP_16_DATA_PACKET).unwrap().points().unwrap());
+    /// let mut merge = Merge::new();
+    /// merge.sensor(vec![frame.clone()].into_iter(),
+    ///              Transform::from_euler(0., 0., 0., [0., 0.5, 0.]))
+    ///     .sensor(vec![frame].into_iter(),
+    ///             Transform::from_euler(0., 0., 180., [0., -0.5, 0.]));
+    /// let frames = merge.collect::<Vec<_>>();
+    /// assert_eq!(1, frames.len());
+    /// ```
+    pub fn sensor<T: Into<Transform>>(&mut self, frames: I, transform: T) -> &mut Merge<I> {
+        self.sensors.push(Sensor {
+                              frames,
+                              transform: transform.into(),
+                              next: None,
+                          });
+        self
+    }
+
+    /// Sets how close together the start times of frames must be for them to be merged.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # extern crate chrono;
+    /// # extern crate velodyne;
+    /// # fn main() {
+    /// use chrono::Duration;
+    /// use velodyne::frame::Frame;
+    /// use velodyne::merge::Merge;
+    /// let mut merge: Merge<std::vec::IntoIter<Frame>> = Merge::new();
+    /// merge.tolerance(Duration::milliseconds(20));
+    /// # }
+    /// ```
+    pub fn tolerance(&mut self, tolerance: Duration) -> &mut Merge<I> {
+        self.tolerance = tolerance;
+        self
+    }
+}
+
+impl<I: Iterator<Item = Frame>> Default for Merge<I> {
+    fn default() -> Merge<I> {
+        Merge::new()
+    }
+}
+
+impl<I: Iterator<Item = Frame>> Iterator for Merge<I> {
+    type Item = Frame;
+
+    fn next(&mut self) -> Option<Frame> {
+        for sensor in &mut self.sensors {
+            if sensor.next.is_none() {
+                sensor.next = sensor.frames.by_ref().find(|frame| !frame.points.is_empty());
+            }
+        }
+        let start = self.sensors
+            .iter()
+            .filter_map(|sensor| sensor.next.as_ref().map(start_time))
+            .fold(None, |earliest: Option<Time>, time| match earliest {
+                Some(earliest) if time.duration_since(earliest)
+                                      .map(|d| d >= Duration::zero())
+                                      .unwrap_or(true) => Some(earliest),
+                _ => Some(time),
+            })?;
+        let mut points = Vec::new();
+        let mut complete = true;
+        let tolerance = self.tolerance;
+        for (id, sensor) in self.sensors.iter_mut().enumerate() {
+            let within_tolerance = sensor.next
+                .as_ref()
+                .and_then(|frame| start_time(frame).duration_since(start))
+                .map(|duration| duration <= tolerance)
+                .unwrap_or(false);
+            if !within_tolerance {
+                complete = false;
+                continue;
+            }
+            if let Some(frame) = sensor.next.take() {
+                complete &= frame.complete;
+                let transform = sensor.transform;
+                points.extend(frame.points
+                                  .into_iter()
+                                  .filter(|point| point.range() > 0.)
+                                  .map(|mut point| {
+                                           transform.apply(&mut point);
+                                           if point.sensor_id.is_none() {
+                                               point.sensor_id = Some(id as u16);
+                                           }
+                                           point
+                                       }));
+            }
+        }
+        points.sort_by_key(|point| {
+                               point.time.duration_since(start).unwrap_or_else(Duration::zero)
+                           });
+        Some(Frame { points, complete })
+    }
+}
+
+fn start_time(frame: &Frame) -> Time {
+    frame.points[0].time
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use Point;
+    use point::{Azimuth, ReturnType};
+    use units::Degrees;
+
+    fn frame(start_ms: i64, x: f32, complete: bool) -> Frame {
+        let points = (0..3)
+            .map(|i| {
+                Point {
+                    x,
+                    y: 1.,
+                    z: 0.,
+                    reflectivity: 0,
+                    channel: 0,
+                    sequence: 0,
+                    return_type: ReturnType::Strongest,
+                    azimuth: Azimuth::Measured(Degrees(0.)),
+                    time: Time::Offset(Duration::milliseconds(start_ms + 40 * i)),
+                    sensor_id: None,
+                }
+            })
+            .collect();
+        Frame { points, complete }
+    }
+
+    #[test]
+    fn time_aligned() {
+        let a = vec![frame(0, 1., true), frame(100, 1., true), frame(200, 1., true)];
+        let b = vec![frame(10, 2., true), frame(210, 2., false)];
+        let mut merge = Merge::new();
+        merge.sensor(a.into_iter(), Transform::identity())
+            .sensor(b.into_iter(), Transform::from_euler(0., 0., 0., [10., 0., 0.]));
+        let frames = merge.collect::<Vec<_>>();
+        assert_eq!(3, frames.len());
+        assert_eq!(6, frames[0].points.len());
+        assert!(frames[0].complete);
+        assert_eq!(1., frames[0].points[0].x);
+        assert_eq!(12., frames[0].points[1].x);
+        assert_eq!(Some(0), frames[0].points[0].sensor_id);
+        assert_eq!(Some(1), frames[0].points[1].sensor_id);
+        assert_eq!(3, frames[1].points.len());
+        assert!(!frames[1].complete);
+        assert_eq!(6, frames[2].points.len());
+        assert!(!frames[2].complete);
+    }
+}
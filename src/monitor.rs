@@ -0,0 +1,214 @@
+//! Monitor throughput while data is flowing.
+//!
+//! A `Monitor` is a cheap, cloneable handle to a set of counters. Sources and pipelines count
+//! into it from whichever thread they run on, and the application polls it, e.g. to show the
+//! sensor's throughput in a user interface:
+//!
+//! ```
+//! use velodyne::io::{Pcap, Read};
+//! use velodyne::monitor::Monitor;
+//! let monitor = Monitor::new();
+//! let mut pcap = Pcap::open("data/single.pcap").unwrap().monitor(monitor.clone());
+//! while let Some(result) = pcap.read() {
+//!     result.unwrap();
+//! }
+//! assert_eq!(1, monitor.totals().packets);
+//! ```
+
+use std::sync::{Arc, Mutex};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::time::Instant;
+use warning::DecodeWarning;
+
+/// A handle to shared throughput counters.
+///
+/// Clones share the same counters.
+#[derive(Clone, Debug)]
+pub struct Monitor {
+    shared: Arc<Shared>,
+}
+
+/// The counts since a monitor was created.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct Totals {
+    /// The number of packets read.
+    pub packets: usize,
+    /// The number of points decoded.
+    pub points: usize,
+    /// The number of frames assembled.
+    pub frames: usize,
+    /// The number of packets read that weren't Velodyne packets, and so were skipped.
+    pub skipped: usize,
+}
+
+/// Throughput since the previous poll, and the current queue depths.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct Rates {
+    /// Packets read per second.
+    pub packets_per_second: f64,
+    /// Points decoded per second.
+    pub points_per_second: f64,
+    /// Frames assembled per second.
+    pub frames_per_second: f64,
+    /// The number of raw packets waiting to be decoded, for pipelines.
+    pub read_queue: usize,
+    /// The number of decoded packets waiting to be consumed, for pipelines.
+    pub output_queue: usize,
+}
+
+#[derive(Debug)]
+struct Shared {
+    packets: AtomicUsize,
+    points: AtomicUsize,
+    frames: AtomicUsize,
+    skipped: AtomicUsize,
+    read_queue: AtomicUsize,
+    output_queue: AtomicUsize,
+    warnings: Mutex<Vec<DecodeWarning>>,
+    last_poll: Mutex<(Instant, Totals)>,
+}
+
+impl Monitor {
+    /// Creates a new monitor with all counters at zero.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use velodyne::monitor::Monitor;
+    /// let monitor = Monitor::new();
+    /// assert_eq!(0, monitor.totals().packets);
+    /// ```
+    pub fn new() -> Monitor {
+        Monitor {
+            shared: Arc::new(Shared {
+                                 packets: AtomicUsize::new(0),
+                                 points: AtomicUsize::new(0),
+                                 frames: AtomicUsize::new(0),
+                                 skipped: AtomicUsize::new(0),
+                                 read_queue: AtomicUsize::new(0),
+                                 output_queue: AtomicUsize::new(0),
+                                 warnings: Mutex::new(Vec::new()),
+                                 last_poll: Mutex::new((Instant::now(), Totals::default())),
+                             }),
+        }
+    }
+
+    /// Counts some packets.
+    pub fn add_packets(&self, n: usize) {
+        self.shared.packets.fetch_add(n, Ordering::Relaxed);
+    }
+
+    /// Counts some points, e.g. when decoding outside of a pipeline.
+    pub fn add_points(&self, n: usize) {
+        self.shared.points.fetch_add(n, Ordering::Relaxed);
+    }
+
+    /// Counts some frames, e.g. when assembling frames outside of a pipeline.
+    pub fn add_frames(&self, n: usize) {
+        self.shared.frames.fetch_add(n, Ordering::Relaxed);
+    }
+
+    /// Counts some packets that were skipped because they weren't Velodyne packets.
+    pub fn add_skipped(&self, n: usize) {
+        self.shared.skipped.fetch_add(n, Ordering::Relaxed);
+    }
+
+    /// Records a warning about the data.
+    pub fn add_warning(&self, warning: DecodeWarning) {
+        self.shared.warnings.lock().expect("monitor lock poisoned").push(warning);
+    }
+
+    /// Returns the warnings recorded since this monitor was created.
+    ///
+    /// Pipelines decode packets one at a time, so their warnings are about single packets, i.e.
+    /// skipped packets and NMEA checksum failures. Use a `vlp_16::Decoder` to also be warned
+    /// about gaps between packets.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use velodyne::monitor::Monitor;
+    /// use velodyne::warning::DecodeWarning;
+    /// let monitor = Monitor::new();
+    /// monitor.add_warning(DecodeWarning::SkippedPacket);
+    /// assert_eq!(vec![DecodeWarning::SkippedPacket], monitor.warnings());
+    /// ```
+    pub fn warnings(&self) -> Vec<DecodeWarning> {
+        self.shared.warnings.lock().expect("monitor lock poisoned").clone()
+    }
+
+    /// Returns the counts since this monitor was created.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use velodyne::monitor::Monitor;
+    /// let monitor = Monitor::new();
+    /// monitor.add_points(384);
+    /// assert_eq!(384, monitor.totals().points);
+    /// ```
+    pub fn totals(&self) -> Totals {
+        Totals {
+            packets: self.shared.packets.load(Ordering::Relaxed),
+            points: self.shared.points.load(Ordering::Relaxed),
+            frames: self.shared.frames.load(Ordering::Relaxed),
+            skipped: self.shared.skipped.load(Ordering::Relaxed),
+        }
+    }
+
+    /// Returns the throughput since the previous call, or since this monitor was created.
+    ///
+    /// Rates are shared between clones, so an application should poll from one place.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use velodyne::monitor::Monitor;
+    /// let monitor = Monitor::new();
+    /// monitor.add_packets(10);
+    /// assert!(monitor.rates().packets_per_second > 0.);
+    /// assert_eq!(0., monitor.rates().packets_per_second);
+    /// ```
+    pub fn rates(&self) -> Rates {
+        let now = Instant::now();
+        let totals = self.totals();
+        let mut last_poll = self.shared.last_poll.lock().expect("monitor lock poisoned");
+        let (then, previous) = *last_poll;
+        *last_poll = (now, totals);
+        let seconds = now.duration_since(then).as_secs_f64();
+        let per_second = |count: usize, previous: usize| if seconds > 0. {
+            (count - previous) as f64 / seconds
+        } else {
+            0.
+        };
+        Rates {
+            packets_per_second: per_second(totals.packets, previous.packets),
+            points_per_second: per_second(totals.points, previous.points),
+            frames_per_second: per_second(totals.frames, previous.frames),
+            read_queue: self.shared.read_queue.load(Ordering::Relaxed),
+            output_queue: self.shared.output_queue.load(Ordering::Relaxed),
+        }
+    }
+
+    pub(crate) fn enqueue_read(&self) {
+        self.shared.read_queue.fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub(crate) fn dequeue_read(&self) {
+        self.shared.read_queue.fetch_sub(1, Ordering::Relaxed);
+    }
+
+    pub(crate) fn enqueue_output(&self) {
+        self.shared.output_queue.fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub(crate) fn dequeue_output(&self) {
+        self.shared.output_queue.fetch_sub(1, Ordering::Relaxed);
+    }
+}
+
+impl Default for Monitor {
+    fn default() -> Monitor {
+        Monitor::new()
+    }
+}
@@ -1,12 +1,101 @@
 //! NMEA parsing.
 //!
-//! So far, all we need to do is parse $GPRMC messages.
+//! Position packets echo the sentences from a GNSS receiver. $GPRMC and $GPGGA sentences are
+//! parsed, and any others are passed through as text.
 
 use {Error, Result};
-use chrono::{DateTime, TimeZone, UTC};
+use chrono::{DateTime, Duration, NaiveDate, NaiveTime, TimeZone, Timelike, UTC};
+use raw;
+use std::fmt;
+use std::ops::Deref;
+use std::str::{self, FromStr};
+use time::{HOUR_US, HourOffset};
+use vlp_16::POSITION_NMEA_LEN;
+
+pub use units::Degrees;
+
+/// Joining a position packet's sentences with CRLF adds at most one byte per sentence, and every
+/// sentence takes at least two bytes of the packet.
+const BUFFER_CAPACITY: usize = POSITION_NMEA_LEN + POSITION_NMEA_LEN / 2;
+
+/// A position packet's NMEA sentences, stored inline so that decoding doesn't allocate.
+///
+/// Sentences are separated by CRLF. Bytes that aren't ASCII, which NMEA doesn't allow, are
+/// replaced with `?`. Dereferences to `str`.
+#[derive(Clone, Copy)]
+pub struct Buffer {
+    bytes: [u8; BUFFER_CAPACITY],
+    len: usize,
+}
+
+/// A NMEA sentence.
+#[derive(Clone, Debug)]
+pub enum Sentence {
+    /// A recommended minimum ($GPRMC) sentence.
+    Rmc(Position),
+    /// A fix data ($GPGGA) sentence.
+    Gga(Fix),
+    /// Any other sentence, with a valid checksum but otherwise unparsed.
+    Other(String),
+}
+
+/// How closely sentences have to follow the NMEA standard.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Validation {
+    /// Every field has to be present and parse, and every sentence needs a checksum.
+    #[default]
+    Strict,
+    /// Accept what real receivers often send: sentences without a checksum, NMEA 2.0 $GPRMC
+    /// sentences without a mode field, and empty speed, course, variation, HDOP, altitude, and
+    /// geoid separation fields, which are parsed as NaN.
+    ///
+    /// Checksums that are present still have to match.
+    Lenient,
+}
+
+/// Why a NMEA sentence couldn't be parsed.
+///
+/// Field indexes count the sentence type, e.g. `$GPRMC`, as field zero.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum ParseError {
+    /// The sentence has no `*` before its checksum.
+    MissingChecksum,
+    /// The sentence is too short to have a body.
+    TooShort,
+    /// The checksum doesn't match the sentence.
+    BadChecksum {
+        /// The checksum at the end of the sentence.
+        expected: String,
+        /// The checksum of the sentence's body.
+        calculated: u8,
+    },
+    /// The sentence isn't of the type that was asked for.
+    WrongType {
+        /// The sentence type that was asked for, without the talker, e.g. `RMC`.
+        expected: &'static str,
+        /// The sentence's first field.
+        found: String,
+    },
+    /// The sentence has the wrong number of fields.
+    FieldCount {
+        /// The number of fields that the sentence type has.
+        expected: usize,
+        /// The number of fields that the sentence has.
+        found: usize,
+    },
+    /// A field couldn't be parsed.
+    Field {
+        /// The index of the field.
+        index: usize,
+        /// The field's text.
+        value: String,
+    },
+    /// A position packet doesn't have a sentence of the type that was asked for.
+    MissingSentence(&'static str),
+}
 
 /// A position measurement from a $GPRMC message.
-#[derive(Clone, Copy, Debug)]
+#[derive(Clone, Copy, Debug, PartialEq)]
 pub struct Position {
     /// The date and time of the position information.
     pub datetime: DateTime<UTC>,
@@ -22,10 +111,74 @@ pub struct Position {
     pub true_course: Degrees,
     /// Magnetic variation, negative numbers are west.
     pub variation: f32,
+    /// The sensor's timestamp when the sentence was received, or `None` if the sentence didn't
+    /// come from a position packet.
+    pub received: Option<HourOffset>,
+}
+
+/// A fix from a $GPGGA message.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Fix {
+    /// The UTC time of the fix.
+    pub time: NaiveTime,
+    /// The latitude, negative numbers are south.
+    pub latitude: f64,
+    /// The longitude, negative numbers are west.
+    pub longitude: f64,
+    /// The fix quality, e.g. zero for no fix, one for GPS, and two for differential GPS.
+    pub quality: u8,
+    /// The number of satellites in use.
+    pub satellites: u8,
+    /// The horizontal dilution of precision.
+    pub hdop: f32,
+    /// The altitude above mean sea level, in meters.
+    pub altitude: f64,
+    /// The height of the geoid above the WGS84 ellipsoid, in meters.
+    pub geoid_separation: f64,
+}
+
+impl Sentence {
+    /// Parses a sentence, strictly.
+    ///
+    /// The talker is ignored, so e.g. $GNRMC sentences are parsed too. Malformed text of any kind
+    /// is an error, never a panic.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use velodyne::nmea::Sentence;
+    /// let nmea = "$GPGSA,A,3,04,05,,09,12,,,24,,,,,2.5,1.3,2.1*39";
+    /// match Sentence::new(nmea).unwrap() {
+    ///     Sentence::Other(sentence) => assert_eq!(nmea, sentence),
+    ///     _ => panic!("should not be parsed"),
+    /// }
+    /// ```
+    pub fn new(nmea: &str) -> Result<Sentence> {
+        Sentence::parse(nmea, Validation::Strict)
+    }
+
+    /// Parses a sentence with the given validation.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use velodyne::nmea::{Sentence, Validation};
+    /// let sentence = Sentence::parse("$GPGSA,A,3", Validation::Lenient).unwrap();
+    /// ```
+    pub fn parse(nmea: &str, validation: Validation) -> Result<Sentence> {
+        match nmea.get(3..6) {
+            Some("RMC") => Position::parse(nmea, validation).map(Sentence::Rmc),
+            Some("GGA") => Fix::parse(nmea, validation).map(Sentence::Gga),
+            _ => {
+                check(nmea, validation)?;
+                Ok(Sentence::Other(nmea.to_string()))
+            }
+        }
+    }
 }
 
 impl Position {
-    /// Parses a position from a NMEA $GPRMC string.
+    /// Parses a position from a NMEA $GPRMC string, strictly.
     ///
     /// # Examples
     ///
@@ -35,58 +188,389 @@ impl Position {
     /// let position = Position::new(nmea).unwrap();
     /// ```
     pub fn new(nmea: &str) -> Result<Position> {
-        let words = nmea.split(',').collect::<Vec<_>>();
-        if words.len() != 13 {
-            return Err(Error::Nmea(format!("$GPRMC should have 13 words, only has {}",
-                                           words.len())));
-        }
-        if words[0] != "$GPRMC" {
-            return Err(Error::Nmea(format!("Positions can only be created from $GPRMC messages, not {}",
-                                           words[0])));
-        }
-        let last_star_position = if let Some(index) = nmea.rfind('*') {
-            index
+        Position::parse(nmea, Validation::Strict)
+    }
+
+    /// Parses a position from a NMEA $GPRMC string with the given validation.
+    ///
+    /// Lenient validation accepts NMEA 2.0 sentences, which don't have a mode field.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use velodyne::nmea::{Position, Validation};
+    /// let nmea = "$GPRMC,214106,A,3707.8178,N,12139.2690,W,,,230715,,";
+    /// assert!(Position::new(nmea).is_err());
+    /// let position = Position::parse(nmea, Validation::Lenient).unwrap();
+    /// assert!(position.speed.0.is_nan());
+    /// ```
+    pub fn parse(nmea: &str, validation: Validation) -> Result<Position> {
+        let words = split_fields(nmea);
+        let expected = if validation == Validation::Lenient && words.len() == 12 {
+            12
         } else {
-            return Err(Error::Nmea("No ending star to delineate checksum".to_string()));
+            13
         };
-        let expected_checksum = &nmea[nmea.len() - 2..];
-        let calculated_checksum =
-            format!("{:02x}",
-                    nmea[1..last_star_position].bytes().fold(0, |acc, n| acc ^ n));
-        if expected_checksum != calculated_checksum {
-            return Err(Error::Nmea(format!("Invalid checksum, expected {}, got {}",
-                                           expected_checksum,
-                                           calculated_checksum)));
-        }
-        let latitude = to_dd(words[3].parse()?) * if words[4] == "S" { -1. } else { 1. };
-        let longitude = to_dd(words[5].parse()?) * if words[6] == "W" { -1. } else { 1. };
-        let variation = words[10].parse::<f32>()? * if words[11] == "W" { -1. } else { 1. };
+        check_fields(&words, "RMC", expected)?;
+        check(nmea, validation)?;
+        let date = NaiveDate::parse_from_str(words[9], "%d%m%y")
+            .map_err(|_| field_error(&words, 9))?;
+        let time = NaiveTime::parse_from_str(words[1], "%H%M%S%.f")
+            .map_err(|_| field_error(&words, 1))?;
+        let latitude = coordinate(&words, 3)?;
+        let longitude = coordinate(&words, 5)?;
+        let variation = optional_field::<f32>(&words, 10, validation)? *
+                        if words[11] == "W" { -1. } else { 1. };
         Ok(Position {
-               datetime: UTC.datetime_from_str(&format!("{}{}", words[9], words[1]),
-                                               "%d%m%y%H%M%S")?,
+               datetime: UTC.from_utc_datetime(&date.and_time(time)),
                valid: words[2] == "A",
-               latitude: latitude,
-               longitude: longitude,
-               speed: Knots(words[7].parse()?),
-               true_course: Degrees(words[8].parse()?),
-               variation: variation,
+               latitude,
+               longitude,
+               speed: Knots(optional_field(&words, 7, validation)?),
+               true_course: Degrees(optional_field(&words, 8, validation)?),
+               variation,
+               received: None,
            })
     }
+
+    /// Returns how long after the fix the sensor received the sentence, or `None` if the sentence
+    /// didn't come from a position packet.
+    ///
+    /// GNSS receivers send a fix a few hundred milliseconds after its time of validity, so points
+    /// should be georeferenced with the fix's `datetime`, not the time it was received. The
+    /// sensor's timestamp counts from the top of the UTC hour, so this wraps around the hour and
+    /// is always within half an hour either way.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use velodyne::nmea::Position;
+    /// use velodyne::time::HourOffset;
+    /// let nmea = "$GPRMC,214106,A,3707.8178,N,12139.2690,W,010.3,188.2,230715,013.8,E,D*05";
+    /// let mut position = Position::new(nmea).unwrap();
+    /// assert_eq!(None, position.latency());
+    /// position.received = Some(HourOffset::from_micros((41 * 60 + 6) * 1_000_000 + 250_000));
+    /// assert_eq!(250, position.latency().unwrap().num_milliseconds());
+    /// ```
+    pub fn latency(&self) -> Option<Duration> {
+        let received = self.received?;
+        let time = self.datetime.time();
+        let fix = i64::from(time.minute() * 60 + time.second()) * 1_000_000 +
+                  i64::from(time.nanosecond()) / 1_000;
+        let mut latency = (i64::from(received.micros()) - fix).rem_euclid(HOUR_US);
+        if latency >= HOUR_US / 2 {
+            latency -= HOUR_US;
+        }
+        Some(Duration::microseconds(latency))
+    }
+}
+
+impl Fix {
+    /// Parses a fix from a NMEA $GPGGA string, strictly.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use velodyne::nmea::Fix;
+    /// let nmea = "$GPGGA,214106.00,3707.8178,N,12139.2690,W,2,09,0.9,12.3,M,-25.6,M,1.0,0000*4E";
+    /// let fix = Fix::new(nmea).unwrap();
+    /// assert_eq!(9, fix.satellites);
+    /// ```
+    pub fn new(nmea: &str) -> Result<Fix> {
+        Fix::parse(nmea, Validation::Strict)
+    }
+
+    /// Parses a fix from a NMEA $GPGGA string with the given validation.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use velodyne::nmea::{Fix, Validation};
+    /// let nmea = "$GPGGA,214106.00,3707.8178,N,12139.2690,W,0,00,,,M,,M,,";
+    /// let fix = Fix::parse(nmea, Validation::Lenient).unwrap();
+    /// assert!(fix.altitude.is_nan());
+    /// ```
+    pub fn parse(nmea: &str, validation: Validation) -> Result<Fix> {
+        let words = split_fields(nmea);
+        check_fields(&words, "GGA", 15)?;
+        check(nmea, validation)?;
+        Ok(Fix {
+               time: NaiveTime::parse_from_str(words[1], "%H%M%S%.f")
+                   .map_err(|_| field_error(&words, 1))?,
+               latitude: coordinate(&words, 2)?,
+               longitude: coordinate(&words, 4)?,
+               quality: field(&words, 6)?,
+               satellites: field(&words, 7)?,
+               hdop: optional_field(&words, 8, validation)?,
+               altitude: optional_field(&words, 9, validation)?,
+               geoid_separation: optional_field(&words, 11, validation)?,
+           })
+    }
+}
+
+impl Buffer {
+    /// Collects the sentences in raw bytes, skipping anything that doesn't start with a `$`.
+    ///
+    /// Sentences that don't fit are dropped, which can't happen with a position packet's bytes.
+    pub(crate) fn from_bytes(bytes: &[u8]) -> Buffer {
+        let mut buffer = Buffer {
+            bytes: [0; BUFFER_CAPACITY],
+            len: 0,
+        };
+        for sentence in raw::sentences(bytes) {
+            let separator: &[u8] = if buffer.len == 0 { b"" } else { b"\r\n" };
+            if buffer.len + separator.len() + sentence.len() > BUFFER_CAPACITY {
+                break;
+            }
+            for &n in separator.iter().chain(sentence) {
+                buffer.bytes[buffer.len] = if n.is_ascii() { n } else { b'?' };
+                buffer.len += 1;
+            }
+        }
+        buffer
+    }
+
+    /// Returns the sentences as a string.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use velodyne::fixtures::VLP_16_POSITION_PACKET;
+    /// use velodyne::vlp_16::Packet;
+    /// let packet = Packet::new(&VLP_16_POSITION_PACKET).unwrap();
+    /// let nmea = packet.into_position().unwrap().nmea;
+    /// assert!(nmea.as_str().starts_with("$GPRMC"));
+    /// ```
+    pub fn as_str(&self) -> &str {
+        // Only ASCII is ever stored, so this can't fail.
+        str::from_utf8(&self.bytes[..self.len]).unwrap_or("")
+    }
+}
+
+impl Default for Buffer {
+    fn default() -> Buffer {
+        Buffer::from_bytes(&[])
+    }
+}
+
+impl Deref for Buffer {
+    type Target = str;
+
+    fn deref(&self) -> &str {
+        self.as_str()
+    }
+}
+
+impl AsRef<str> for Buffer {
+    fn as_ref(&self) -> &str {
+        self.as_str()
+    }
+}
+
+impl PartialEq for Buffer {
+    fn eq(&self, other: &Buffer) -> bool {
+        self.as_str() == other.as_str()
+    }
+}
+
+impl Eq for Buffer {}
+
+impl fmt::Debug for Buffer {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt::Debug::fmt(self.as_str(), f)
+    }
+}
+
+impl fmt::Display for Buffer {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt::Display::fmt(self.as_str(), f)
+    }
+}
+
+/// Splits text into sentences, which can be terminated by CRLF or NUL.
+///
+/// Anything that doesn't start with a `$` is skipped, e.g. padding.
+pub(crate) fn split<'a>(text: &'a str) -> impl Iterator<Item = &'a str> + 'a {
+    text.split(&['\r', '\n', '\0'][..])
+        .filter(|sentence| sentence.starts_with('$'))
+}
+
+/// Returns true if the first word of a sentence is of the given type, from any talker.
+pub(crate) fn is_type(word: &str, kind: &str) -> bool {
+    word.len() == 6 && word.starts_with('$') && word.ends_with(kind)
+}
+
+/// Splits a sentence into its fields, leaving the checksum out of the last one.
+fn split_fields(nmea: &str) -> Vec<&str> {
+    raw::fields(nmea).collect()
+}
+
+/// Checks a sentence's type and number of fields.
+fn check_fields(words: &[&str], kind: &'static str, expected: usize) -> Result<()> {
+    if !is_type(words[0], kind) {
+        return Err(ParseError::WrongType {
+                           expected: kind,
+                           found: words[0].to_string(),
+                       }
+                       .into());
+    }
+    if words.len() != expected {
+        return Err(ParseError::FieldCount {
+                           expected,
+                           found: words.len(),
+                       }
+                       .into());
+    }
+    Ok(())
+}
+
+/// Parses a required field.
+fn field<T: FromStr>(words: &[&str], index: usize) -> Result<T> {
+    words[index].parse().map_err(|_| field_error(words, index))
+}
+
+/// Parses a latitude or longitude and the hemisphere in the field after it.
+fn coordinate(words: &[&str], index: usize) -> Result<f64> {
+    let degrees = to_decimal_degrees(field(words, index)?);
+    match words[index + 1] {
+        "S" | "W" => Ok(-degrees),
+        _ => Ok(degrees),
+    }
+}
+
+/// Parses a field that receivers leave empty when they don't know it, which lenient validation
+/// allows.
+fn optional_field<T: FromStr + Nan>(words: &[&str],
+                                      index: usize,
+                                      validation: Validation)
+                                      -> Result<T> {
+    if validation == Validation::Lenient && words[index].is_empty() {
+        Ok(T::NAN)
+    } else {
+        field(words, index)
+    }
+}
+
+/// Floats that can stand in for an unknown value.
+trait Nan {
+    const NAN: Self;
+}
+
+impl Nan for f32 {
+    const NAN: f32 = f32::NAN;
+}
+
+impl Nan for f64 {
+    const NAN: f64 = f64::NAN;
+}
+
+fn field_error(words: &[&str], index: usize) -> Error {
+    ParseError::Field {
+            index,
+            value: words[index].to_string(),
+        }
+        .into()
+}
+
+/// Validates a sentence's checksum.
+///
+/// Lenient validation accepts sentences without one, but never a wrong one.
+pub(crate) fn check(nmea: &str, validation: Validation) -> Result<()> {
+    let last_star_position = if let Some(index) = nmea.rfind('*') {
+        index
+    } else if validation == Validation::Lenient && nmea.len() > 1 {
+        return Ok(());
+    } else {
+        return Err(ParseError::MissingChecksum.into());
+    };
+    // The star is one byte, so slicing after it is safe, but the sentence could be just a star.
+    let expected_checksum = &nmea[last_star_position + 1..];
+    let body = match nmea.get(1..last_star_position) {
+        Some(body) => body,
+        None => return Err(ParseError::TooShort.into()),
+    };
+    let calculated_checksum = checksum(body);
+    if !expected_checksum.eq_ignore_ascii_case(&format!("{:02x}", calculated_checksum)) {
+        warn!("NMEA checksum failure, expected {}, got {:02X}: {}",
+              expected_checksum,
+              calculated_checksum,
+              nmea);
+        return Err(ParseError::BadChecksum {
+                           expected: expected_checksum.to_string(),
+                           calculated: calculated_checksum,
+                       }
+                       .into());
+    }
+    Ok(())
 }
 
-fn to_dd(n: f64) -> f64 {
-    let degrees = (n / 100.).round();
-    let decimal = (n / 100.).fract() * 100. / 60.;
-    degrees + decimal
+/// Returns the checksum of the text between a sentence's `$` and `*`.
+pub(crate) fn checksum(body: &str) -> u8 {
+    raw::checksum(body.as_bytes())
+}
+
+/// Converts an angle in NMEA's degrees and minutes format, DDDMM.MMMM, to decimal degrees.
+///
+/// # Examples
+///
+/// ```
+/// use velodyne::nmea::to_decimal_degrees;
+/// assert_eq!(37.75, to_decimal_degrees(3745.0));
+/// ```
+pub fn to_decimal_degrees(ddmm: f64) -> f64 {
+    let degrees = (ddmm / 100.).trunc();
+    let minutes = ddmm - degrees * 100.;
+    degrees + minutes / 60.
 }
 
 /// Knots.
 #[derive(Clone, Copy, Debug, PartialEq)]
 pub struct Knots(pub f32);
 
-/// Degrees.
-#[derive(Clone, Copy, Debug, PartialEq)]
-pub struct Degrees(pub f32);
+impl Knots {
+    /// Converts these knots to meters per second.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use velodyne::nmea::Knots;
+    /// assert!((Knots(1.).meters_per_second() - 0.5144).abs() < 1e-4);
+    /// ```
+    pub fn meters_per_second(self) -> f32 {
+        self.0 * 1852. / 3600.
+    }
+}
+
+impl fmt::Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            ParseError::MissingChecksum => f.write_str("NMEA sentence has no checksum"),
+            ParseError::TooShort => f.write_str("NMEA sentence is too short"),
+            ParseError::BadChecksum { ref expected, calculated } => {
+                write!(f, "NMEA checksum mismatch, expected {}, got {:02X}", expected, calculated)
+            }
+            ParseError::WrongType { expected, ref found } => {
+                write!(f, "expected a {} sentence, found {}", expected, found)
+            }
+            ParseError::FieldCount { expected, found } => {
+                write!(f, "expected {} NMEA fields, found {}", expected, found)
+            }
+            ParseError::Field { index, ref value } => {
+                write!(f, "invalid NMEA field {}: {:?}", index, value)
+            }
+            ParseError::MissingSentence(sentence) => {
+                write!(f, "position packet has no {} sentence", sentence)
+            }
+        }
+    }
+}
+
+impl From<ParseError> for Error {
+    fn from(err: ParseError) -> Error {
+        Error::Nmea(err)
+    }
+}
+
 
 #[cfg(test)]
 mod tests {
@@ -105,23 +589,140 @@ mod tests {
         assert_eq!(Knots(10.3), position.speed);
         assert_eq!(Degrees(188.2), position.true_course);
         assert_eq!(13.8, position.variation);
+        assert_eq!(None, position.received);
+    }
+
+    #[test]
+    fn latency() {
+        let nmea = "$GPRMC,000000.90,A,3707.8178,N,12139.2690,W,,,230715,,";
+        let mut position = Position::parse(nmea, Validation::Lenient).unwrap();
+        position.received = Some(HourOffset::from_micros(100_000));
+        assert_eq!(Duration::milliseconds(-800), position.latency().unwrap());
+        position.received = Some(HourOffset::from_micros(HOUR_US as u32 - 100_000));
+        assert_eq!(Duration::milliseconds(-1_000), position.latency().unwrap());
+        position.received = Some(HourOffset::from_micros(1_200_000));
+        assert_eq!(Duration::milliseconds(300), position.latency().unwrap());
     }
 
     #[test]
     fn bad_checksum() {
         let nmea = "$GPRMC,214106,A,3707.8178,N,12139.2690,W,010.3,188.2,230715,013.9,E,D*05";
-        assert!(Position::new(nmea).is_err());
+        assert_eq!(ParseError::BadChecksum {
+                       expected: "05".to_string(),
+                       calculated: 0x04,
+                   },
+                   parse_error(Position::parse(nmea, Validation::Lenient)));
     }
 
     #[test]
     fn too_few_words() {
         let nmea = "$GPRMC,214106,A,3707.8178,N,12139.2690,W,010.3,188.2,230715,013.9,E";
-        assert!(Position::new(nmea).is_err());
+        assert_eq!(ParseError::FieldCount {
+                       expected: 13,
+                       found: 12,
+                   },
+                   parse_error(Position::new(nmea)));
+    }
+
+    #[test]
+    fn validation() {
+        let nmea = "$GPRMC,214106.50,A,3707.8178,N,12139.2690,W,010.3,,230715,,";
+        assert_eq!(ParseError::FieldCount {
+                       expected: 13,
+                       found: 12,
+                   },
+                   parse_error(Position::new(nmea)));
+        assert_eq!(ParseError::MissingChecksum, parse_error(Sentence::new("$GPGSA,A,3")));
+        let position = Position::parse(nmea, Validation::Lenient).unwrap();
+        assert_eq!(UTC.ymd(2015, 7, 23).and_hms_milli(21, 41, 6, 500), position.datetime);
+        assert_eq!(Knots(10.3), position.speed);
+        assert!(position.true_course.0.is_nan());
+        let nmea = "$GPRMC,214106,A,3707.8178,N,,W,010.3,188.2,230715,013.8,E,D";
+        assert_eq!(ParseError::Field {
+                       index: 5,
+                       value: String::new(),
+                   },
+                   parse_error(Position::parse(nmea, Validation::Lenient)));
+    }
+
+    #[test]
+    fn fix() {
+        let nmea = "$GPGGA,214106.00,3707.8178,N,12139.2690,W,2,09,0.9,12.3,M,-25.6,M,1.0,0000*4E";
+        let fix = Fix::new(nmea).unwrap();
+        assert_eq!(NaiveTime::from_hms(21, 41, 6), fix.time);
+        assert!((37.1303 - fix.latitude).abs() < 1e-4);
+        assert!((-121.6545 - fix.longitude).abs() < 1e-4);
+        assert_eq!(2, fix.quality);
+        assert_eq!(0.9, fix.hdop);
+        assert_eq!(12.3, fix.altitude);
+        assert_eq!(-25.6, fix.geoid_separation);
+        assert!(Fix::new(&nmea.replace("*4E", "*4F")).is_err());
+    }
+
+    #[test]
+    fn decimal_degrees() {
+        for &(ddmm, dd) in &[(3747.0, 37.783333),
+                             (3729.9999, 37.499998),
+                             (3730.0, 37.5),
+                             (3759.9999, 37.999998),
+                             (3800.0, 38.),
+                             (0.0, 0.),
+                             (0.6, 0.01),
+                             (5959.9999, 59.999998),
+                             (17959.9999, 179.999998),
+                             (18000.0, 180.)] {
+            assert!((dd - to_decimal_degrees(ddmm)).abs() < 1e-6, "{}", ddmm);
+        }
+        let words = ["$GPGGA", "0000.0000", "S", "18000.0000", "W"];
+        assert_eq!(0., coordinate(&words, 1).unwrap());
+        assert_eq!(-180., coordinate(&words, 3).unwrap());
+    }
+
+    #[test]
+    fn split_sentences() {
+        let text = "$GPGGA,1*00\r\n$GPRMC,2*00\0\0$GPGSA,3*00\r\n\0\0garbage";
+        assert_eq!(vec!["$GPGGA,1*00", "$GPRMC,2*00", "$GPGSA,3*00"],
+                   split(text).collect::<Vec<_>>());
+    }
+
+    #[test]
+    fn buffer() {
+        let buffer = Buffer::from_bytes(b"$GPGGA,1*00\r\n$GPRMC,\xe9*00\0\0junk\n$GPGSA,3*00\0\0");
+        assert_eq!("$GPGGA,1*00\r\n$GPRMC,?*00\r\n$GPGSA,3*00", buffer.as_str());
+        // Single-byte separators are the worst case for growth.
+        let crowded = [b'$', 0].repeat(POSITION_NMEA_LEN / 2);
+        assert_eq!(153 + 2 * 152, Buffer::from_bytes(&crowded).len());
+        assert!(Buffer::default().is_empty());
+    }
+
+    #[test]
+    fn malformed() {
+        for nmea in &["",
+                      "*",
+                      "$*",
+                      "\u{e9}*",
+                      "$GPRMC*",
+                      "$GPRMC,,,,,,,,,,,,*4B",
+                      "$GPGGA,,,,,,,,,,,,,,*56",
+                      "$GPRMC,999999,A,0,N,0,W,0,0,999999,0,E,D*22"] {
+            assert!(Sentence::new(nmea).is_err(), "{}", nmea);
+        }
     }
 
     #[test]
     fn not_gprmc() {
         let nmea = "$GPRMZ,214106,A,3707.8178,N,12139.2690,W,010.3,188.2,230715,013.9,E,D*05";
-        assert!(Position::new(nmea).is_err());
+        assert_eq!(ParseError::WrongType {
+                       expected: "RMC",
+                       found: "$GPRMZ".to_string(),
+                   },
+                   parse_error(Position::new(nmea)));
+    }
+
+    fn parse_error<T>(result: Result<T>) -> ParseError {
+        match result {
+            Err(Error::Nmea(err)) => err,
+            _ => panic!("should be a NMEA error"),
+        }
     }
 }
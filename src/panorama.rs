@@ -0,0 +1,226 @@
+//! Render range images as PNG panoramas.
+//!
+//! A panorama is the quickest way to eyeball whether a capture is sane without loading a 3D
+//! viewer.
+
+use Result;
+use frame::Frame;
+use png::{BitDepth, ColorType, Encoder};
+use point::normalize_reflectivity;
+use range_image::{Cell, RangeImage};
+use std::fs::File;
+use std::io::{BufWriter, Write};
+use std::path::Path;
+use units::Meters;
+
+const DEFAULT_MAX_RANGE: f32 = 100.;
+const DEFAULT_ROW_HEIGHT: usize = 4;
+const DEFAULT_COLUMNS: usize = 1800;
+
+/// Renders range images into PNG panoramas.
+#[derive(Clone, Copy, Debug)]
+pub struct Panorama {
+    value: Value,
+    colormap: Colormap,
+    max_range: f32,
+    row_height: usize,
+}
+
+/// The value that is rendered into each pixel.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Value {
+    /// The range, with near points brighter.
+    Range,
+    /// The calibrated reflectivity, normalized as with `Point::intensity`.
+    Intensity,
+}
+
+/// How values are mapped to colors.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Colormap {
+    /// Black to white.
+    Grayscale,
+    /// Blue to red, through cyan, yellow, and orange.
+    Jet,
+}
+
+impl Panorama {
+    /// Creates a new grayscale panorama renderer for the given value.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use velodyne::panorama::{Panorama, Value};
+    /// let panorama = Panorama::new(Value::Range);
+    /// ```
+    pub fn new(value: Value) -> Panorama {
+        Panorama {
+            value,
+            colormap: Colormap::Grayscale,
+            max_range: DEFAULT_MAX_RANGE,
+            row_height: DEFAULT_ROW_HEIGHT,
+        }
+    }
+
+    /// Sets the colormap.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use velodyne::panorama::{Panorama, Value};
+    /// use velodyne::panorama::Colormap;
+    /// let mut panorama = Panorama::new(Value::Range);
+    /// panorama.colormap(Colormap::Jet);
+    /// ```
+    pub fn colormap(&mut self, colormap: Colormap) -> &mut Panorama {
+        self.colormap = colormap;
+        self
+    }
+
+    /// Sets the range that maps to the darkest value.
+    ///
+    /// Points further away than this are clamped.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use velodyne::panorama::{Panorama, Value};
+    /// use velodyne::units::Meters;
+    /// let mut panorama = Panorama::new(Value::Range);
+    /// panorama.max_range(Meters(50.));
+    /// ```
+    pub fn max_range(&mut self, max_range: Meters) -> &mut Panorama {
+        self.max_range = max_range.0;
+        self
+    }
+
+    /// Sets the number of pixel rows used for each laser.
+    ///
+    /// There are only sixteen lasers, so each one is drawn a few pixels tall to make the image
+    /// easier to look at.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use velodyne::panorama::{Panorama, Value};
+    /// let mut panorama = Panorama::new(Value::Range);
+    /// panorama.row_height(8);
+    /// ```
+    pub fn row_height(&mut self, row_height: usize) -> &mut Panorama {
+        self.row_height = row_height;
+        self
+    }
+
+    /// Renders a range image into a PNG.
+    ///
+    /// Empty cells are black.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use velodyne::panorama::{Panorama, Value};
+    /// use velodyne::frame::Frame;
+    /// use velodyne::range_image::RangeImage;
+    /// let range_image = RangeImage::from_frame(&Frame::default(), 360);
+    /// let mut png = Vec::new();
+    /// Panorama::new(Value::Intensity).write(&range_image, &mut png).unwrap();
+    /// ```
+    pub fn write<W: Write>(&self, range_image: &RangeImage, write: W) -> Result<()> {
+        let width = range_image.columns();
+        let height = range_image.rows() * self.row_height;
+        let mut data = Vec::with_capacity(width * height * 3);
+        for row in 0..range_image.rows() {
+            let mut line = Vec::with_capacity(width * 3);
+            for column in 0..width {
+                let rgb = range_image.get(row, column)
+                    .map(|cell| self.colormap.rgb(self.normalize(cell)))
+                    .unwrap_or([0, 0, 0]);
+                line.extend_from_slice(&rgb);
+            }
+            for _ in 0..self.row_height {
+                data.extend_from_slice(&line);
+            }
+        }
+        let mut encoder = Encoder::new(write, width as u32, height as u32);
+        encoder.set_color(ColorType::Rgb);
+        encoder.set_depth(BitDepth::Eight);
+        let mut writer = encoder.write_header()?;
+        writer.write_image_data(&data)?;
+        writer.finish()?;
+        Ok(())
+    }
+
+    /// Renders a frame into a PNG file, using 1800 azimuth bins (0.2° each).
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// # use velodyne::panorama::{Panorama, Value};
+    /// use velodyne::frame::Frame;
+    /// Panorama::new(Value::Range).write_frame_to_path(&Frame::default(), "frame.png").unwrap();
+    /// ```
+    pub fn write_frame_to_path<P: AsRef<Path>>(&self, frame: &Frame, path: P) -> Result<()> {
+        let range_image = RangeImage::from_frame(frame, DEFAULT_COLUMNS);
+        self.write(&range_image, BufWriter::new(File::create(path)?))
+    }
+
+    fn normalize(&self, cell: &Cell) -> f32 {
+        match self.value {
+            Value::Range => 1. - (cell.range / self.max_range).min(1.),
+            Value::Intensity => normalize_reflectivity(cell.intensity),
+        }
+    }
+}
+
+impl Colormap {
+    /// Maps a value between zero and one to a color.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use velodyne::panorama::Colormap;
+    /// assert_eq!([255, 255, 255], Colormap::Grayscale.rgb(1.));
+    /// assert_eq!([0, 0, 128], Colormap::Jet.rgb(0.));
+    /// ```
+    pub fn rgb(&self, value: f32) -> [u8; 3] {
+        let value = value.clamp(0., 1.);
+        match *self {
+            Colormap::Grayscale => {
+                let gray = to_u8(value);
+                [gray, gray, gray]
+            }
+            Colormap::Jet => {
+                let channel = |offset: f32| to_u8((1.5 - (4. * value - offset).abs()).min(1.));
+                [channel(3.), channel(2.), channel(1.)]
+            }
+        }
+    }
+}
+
+fn to_u8(value: f32) -> u8 {
+    (value.max(0.) * 255.).round() as u8
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use fixtures::VLP_16_DATA_PACKET;
+    use vlp_16::Packet;
+
+    #[test]
+    fn png_signature_and_size() {
+        let frame = Frame::new(Packet::new(&VLP_16_DATA_PACKET).unwrap().points().unwrap());
+        let range_image = RangeImage::from_frame(&frame, 360);
+        let mut png = Vec::new();
+        Panorama::new(Value::Range).row_height(2).write(&range_image, &mut png).unwrap();
+        assert_eq!(b"\x89PNG", &png[0..4]);
+        assert_eq!(&[0, 0, 1, 104], &png[16..20]);
+        assert_eq!(&[0, 0, 0, 32], &png[20..24]);
+    }
+
+    #[test]
+    fn jet() {
+        assert_eq!([128, 0, 0], Colormap::Jet.rgb(1.));
+        assert_eq!([128, 255, 128], Colormap::Jet.rgb(0.5));
+    }
+}
@@ -0,0 +1,470 @@
+//! Write and read points as PCD files, the Point Cloud Library's format.
+//!
+//! Every point becomes one record with the fields `x`, `y`, `z`, `intensity`, `ring`, `azimuth`,
+//! `time`, and `return`, so PCL and its viewers can open the files directly. `time` is in seconds:
+//! past the hour for offset times, or since the Unix epoch for absolute times. Files written by
+//! this module read back into the same points:
+//!
+//! ```
+//! use velodyne::fixtures::VLP_16_DATA_PACKET;
+//! use velodyne::pcd::{self, Encoding};
+//! use velodyne::vlp_16::Packet;
+//! let points = Packet::new(&VLP_16_DATA_PACKET).unwrap().points().unwrap();
+//! let mut bytes = Vec::new();
+//! pcd::write(&mut bytes, &points, Encoding::Binary).unwrap();
+//! assert_eq!(points.len(), pcd::read(&bytes[..]).unwrap().len());
+//! ```
+//!
+//! PCD has no place for a coordinate reference system, so a georeferenced cloud's is written as a
+//! `# CRS` comment line in the header, see `Writer::crs`, which PCL skips.
+
+use {Error, Point, Result};
+use byteorder::{ByteOrder, LittleEndian};
+use chrono::{Duration, TimeZone, UTC};
+use crs::Crs;
+use point::{Azimuth, ReturnType, Time};
+use sink::PointSink;
+use std::io::{self, BufRead, Write};
+use units::Degrees;
+
+const FIELDS: &str = "x y z intensity ring azimuth time return";
+const SIZES: &str = "4 4 4 1 2 4 8 1";
+const TYPES: &str = "F F F U U F F U";
+const COUNTS: &str = "1 1 1 1 1 1 1 1";
+const RECORD_LEN: usize = 28;
+/// Times below this many seconds are offsets past the hour.
+const SECONDS_PER_HOUR: f64 = 3600.;
+
+/// How the records of a PCD file are stored.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Encoding {
+    /// One line of text per point.
+    Ascii,
+    /// Packed little-endian records, which are smaller and faster to read.
+    #[default]
+    Binary,
+}
+
+/// Collects points and writes them as a PCD file when finished.
+///
+/// A PCD header has the number of points, so nothing is written until `finish`.
+#[derive(Debug)]
+pub struct Writer<W: Write> {
+    write: W,
+    encoding: Encoding,
+    crs: Option<Crs>,
+    points: Vec<Point>,
+    finished: bool,
+}
+
+/// A field of a PCD file, as declared by its header.
+#[derive(Clone, Debug)]
+struct Field {
+    name: String,
+    size: usize,
+    kind: char,
+    count: usize,
+}
+
+/// Writes points as a PCD file, as a single unorganized cloud.
+///
+/// # Examples
+///
+/// ```
+/// use velodyne::fixtures::VLP_16_DATA_PACKET;
+/// use velodyne::pcd::{self, Encoding};
+/// use velodyne::vlp_16::Packet;
+/// let points = Packet::new(&VLP_16_DATA_PACKET).unwrap().points().unwrap();
+/// let mut bytes = Vec::new();
+/// pcd::write(&mut bytes, &points, Encoding::Ascii).unwrap();
+/// assert!(bytes.starts_with(b"# .PCD v0.7"));
+/// ```
+pub fn write<W: Write>(write: W, points: &[Point], encoding: Encoding) -> Result<()> {
+    write_with_crs(write, points, encoding, None)
+}
+
+fn write_with_crs<W: Write>(mut write: W,
+                            points: &[Point],
+                            encoding: Encoding,
+                            crs: Option<&Crs>)
+                            -> Result<()> {
+    writeln!(write, "# .PCD v0.7 - Point Cloud Data file format")?;
+    if let Some(crs) = crs {
+        writeln!(write, "# CRS {}", crs)?;
+    }
+    write!(write,
+           "VERSION 0.7\nFIELDS {}\nSIZE {}\nTYPE {}\nCOUNT {}\nWIDTH {}\nHEIGHT 1\n\
+            VIEWPOINT 0 0 0 1 0 0 0\nPOINTS {}\nDATA {}\n",
+           FIELDS,
+           SIZES,
+           TYPES,
+           COUNTS,
+           points.len(),
+           points.len(),
+           match encoding {
+               Encoding::Ascii => "ascii",
+               Encoding::Binary => "binary",
+           })?;
+    let mut record = [0; RECORD_LEN];
+    for point in points {
+        let azimuth = point.azimuth.degrees();
+        let time = seconds(point.time);
+        let return_type = return_code(point.return_type);
+        match encoding {
+            Encoding::Ascii => {
+                writeln!(write,
+                         "{} {} {} {} {} {} {} {}",
+                         point.x,
+                         point.y,
+                         point.z,
+                         point.reflectivity,
+                         point.channel,
+                         azimuth,
+                         time,
+                         return_type)?
+            }
+            Encoding::Binary => {
+                LittleEndian::write_f32(&mut record[0..], point.x);
+                LittleEndian::write_f32(&mut record[4..], point.y);
+                LittleEndian::write_f32(&mut record[8..], point.z);
+                record[12] = point.reflectivity;
+                LittleEndian::write_u16(&mut record[13..], u16::from(point.channel));
+                LittleEndian::write_f32(&mut record[15..], azimuth);
+                LittleEndian::write_f64(&mut record[19..], time);
+                record[27] = return_type;
+                write.write_all(&record)?;
+            }
+        }
+    }
+    write.flush().map_err(Into::into)
+}
+
+impl<W: Write> Writer<W> {
+    /// Creates a new writer.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use velodyne::pcd::{Encoding, Writer};
+    /// let writer = Writer::new(Vec::new(), Encoding::Binary);
+    /// ```
+    pub fn new(write: W, encoding: Encoding) -> Writer<W> {
+        Writer {
+            write,
+            encoding,
+            crs: None,
+            points: Vec::new(),
+            finished: false,
+        }
+    }
+
+    /// Records the points' coordinate reference system in the header, for georeferenced points.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use velodyne::crs::Crs;
+    /// use velodyne::pcd::{self, Encoding, Writer};
+    /// use velodyne::sink::PointSink;
+    /// let mut writer = Writer::new(Vec::new(), Encoding::Ascii);
+    /// writer.crs(Crs::Epsg(32633));
+    /// writer.finish().unwrap();
+    /// let bytes = writer.into_inner();
+    /// assert_eq!(Some(Crs::Epsg(32633)), pcd::read_crs(&bytes[..]).unwrap());
+    /// ```
+    pub fn crs(&mut self, crs: Crs) -> &mut Writer<W> {
+        self.crs = Some(crs);
+        self
+    }
+
+    /// Returns the underlying writer, which is empty unless the writer was finished.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use velodyne::fixtures::VLP_16_DATA_PACKET;
+    /// use velodyne::pcd::{self, Encoding, Writer};
+    /// use velodyne::sink::PointSink;
+    /// use velodyne::vlp_16::Packet;
+    /// let points = Packet::new(&VLP_16_DATA_PACKET).unwrap().points().unwrap();
+    /// let mut writer = Writer::new(Vec::new(), Encoding::Ascii);
+    /// writer.write_points(&points).unwrap();
+    /// writer.finish().unwrap();
+    /// assert_eq!(points.len(), pcd::read(&writer.into_inner()[..]).unwrap().len());
+    /// ```
+    pub fn into_inner(self) -> W {
+        self.write
+    }
+}
+
+impl<W: Write> PointSink for Writer<W> {
+    fn write_points(&mut self, points: &[Point]) -> Result<()> {
+        self.points.extend_from_slice(points);
+        Ok(())
+    }
+
+    /// Writes the file, once.
+    fn finish(&mut self) -> Result<()> {
+        if !self.finished {
+            write_with_crs(&mut self.write, &self.points, self.encoding, self.crs.as_ref())?;
+            self.points = Vec::new();
+            self.finished = true;
+        }
+        Ok(())
+    }
+}
+
+/// Reads the points of a PCD file with ASCII or binary data.
+///
+/// Any PCD file with `x`, `y`, and `z` fields can be read, e.g. one written by PCL. Fields that
+/// aren't in the file get defaults: no reflectivity, channel zero, strongest returns, zero offset
+/// times, and azimuths computed from the coordinates. Azimuths are read back as measured, and
+/// compressed data isn't supported.
+///
+/// # Examples
+///
+/// ```
+/// use velodyne::pcd;
+/// let file = "VERSION 0.7\nFIELDS x y z\nSIZE 4 4 4\nTYPE F F F\nCOUNT 1 1 1\nWIDTH 1\n\
+///             HEIGHT 1\nPOINTS 1\nDATA ascii\n1 2 3\n";
+/// let points = pcd::read(file.as_bytes()).unwrap();
+/// assert_eq!((1., 2., 3.), (points[0].x, points[0].y, points[0].z));
+/// ```
+pub fn read<R: BufRead>(mut read: R) -> Result<Vec<Point>> {
+    let mut names = Vec::new();
+    let mut sizes = Vec::new();
+    let mut kinds = Vec::new();
+    let mut counts = Vec::new();
+    let mut npoints = None;
+    let mut line = String::new();
+    let encoding = loop {
+        line.clear();
+        if read.read_line(&mut line)? == 0 {
+            return Err(invalid("missing DATA line"));
+        }
+        let mut words = line.split_whitespace();
+        let values = || line.split_whitespace().skip(1);
+        match words.next() {
+            Some("FIELDS") => names = values().map(String::from).collect(),
+            Some("SIZE") => sizes = numbers(values())?,
+            Some("TYPE") => kinds = values().filter_map(|kind| kind.chars().next()).collect(),
+            Some("COUNT") => counts = numbers(values())?,
+            Some("POINTS") => npoints = Some(words.next().unwrap_or_default().parse::<usize>()?),
+            Some("DATA") => {
+                match words.next() {
+                    Some("ascii") => break Encoding::Ascii,
+                    Some("binary") => break Encoding::Binary,
+                    data => return Err(invalid(&format!("unsupported data: {:?}", data))),
+                }
+            }
+            _ => {}
+        }
+    };
+    if counts.is_empty() {
+        counts = vec![1; names.len()];
+    }
+    if sizes.len() != names.len() || kinds.len() != names.len() || counts.len() != names.len() {
+        return Err(invalid("FIELDS, SIZE, TYPE, and COUNT have different lengths"));
+    }
+    let fields = names.into_iter()
+        .zip(sizes)
+        .zip(kinds)
+        .zip(counts)
+        .map(|(((name, size), kind), count)| Field { name, size, kind, count })
+        .collect::<Vec<_>>();
+    for field in &fields {
+        if field.count == 0 || decode(field, &[0; 8]).is_none() {
+            return Err(invalid(&format!("unsupported {} field: {}{}",
+                                        field.name,
+                                        field.kind,
+                                        field.size)));
+        }
+    }
+    let npoints = npoints.ok_or_else(|| invalid("missing POINTS line"))?;
+    let mut values = vec![0.; fields.len()];
+    // The header can't be trusted with an allocation.
+    let mut points = Vec::with_capacity(npoints.min(1 << 16));
+    match encoding {
+        Encoding::Ascii => {
+            while points.len() < npoints {
+                line.clear();
+                if read.read_line(&mut line)? == 0 {
+                    return Err(io::Error::from(io::ErrorKind::UnexpectedEof).into());
+                }
+                if line.trim().is_empty() {
+                    continue;
+                }
+                let mut words = line.split_whitespace();
+                for (value, field) in values.iter_mut().zip(&fields) {
+                    let mut words = words.by_ref().take(field.count);
+                    *value = words.next().ok_or_else(|| invalid("short record"))?.parse()?;
+                    words.for_each(drop);
+                }
+                points.push(point(&fields, &values)?);
+            }
+        }
+        Encoding::Binary => {
+            let len = fields.iter().map(|field| field.size * field.count).sum();
+            let mut record = vec![0; len];
+            for _ in 0..npoints {
+                read.read_exact(&mut record)?;
+                let mut offset = 0;
+                for (value, field) in values.iter_mut().zip(&fields) {
+                    *value = decode(field, &record[offset..]).unwrap_or_default();
+                    offset += field.size * field.count;
+                }
+                points.push(point(&fields, &values)?);
+            }
+        }
+    }
+    Ok(points)
+}
+
+/// Reads the coordinate reference system from a PCD file's header, or `None` if it doesn't have
+/// one.
+pub fn read_crs<R: BufRead>(read: R) -> Result<Option<Crs>> {
+    for line in read.lines() {
+        let line = line?;
+        if let Some(crs) = line.strip_prefix("# CRS ") {
+            return Crs::parse(crs).map(Some);
+        }
+        if line.starts_with("DATA") {
+            break;
+        }
+    }
+    Ok(None)
+}
+
+fn numbers<'a, I: Iterator<Item = &'a str>>(words: I) -> Result<Vec<usize>> {
+    words.map(|word| word.parse().map_err(Into::into)).collect()
+}
+
+/// Decodes the first value of a binary field, or returns `None` if its type isn't supported.
+fn decode(field: &Field, bytes: &[u8]) -> Option<f64> {
+    Some(match (field.kind, field.size) {
+             ('F', 4) => f64::from(LittleEndian::read_f32(bytes)),
+             ('F', 8) => LittleEndian::read_f64(bytes),
+             ('U', 1) => f64::from(bytes[0]),
+             ('U', 2) => f64::from(LittleEndian::read_u16(bytes)),
+             ('U', 4) => f64::from(LittleEndian::read_u32(bytes)),
+             ('I', 1) => f64::from(bytes[0] as i8),
+             ('I', 2) => f64::from(LittleEndian::read_i16(bytes)),
+             ('I', 4) => f64::from(LittleEndian::read_i32(bytes)),
+             _ => return None,
+         })
+}
+
+fn point(fields: &[Field], values: &[f64]) -> Result<Point> {
+    let value = |name: &str| {
+        fields.iter().position(|field| field.name == name).map(|index| values[index])
+    };
+    let (x, y, z) = match (value("x"), value("y"), value("z")) {
+        (Some(x), Some(y), Some(z)) => (x as f32, y as f32, z as f32),
+        _ => return Err(invalid("missing x, y, or z field")),
+    };
+    let azimuth = value("azimuth")
+        .map_or_else(|| x.atan2(y).to_degrees().rem_euclid(360.), |azimuth| azimuth as f32);
+    Ok(Point {
+           x,
+           y,
+           z,
+           reflectivity: value("intensity").map_or(0, |intensity| intensity.round() as u8),
+           channel: value("ring").map_or(0, |ring| ring as u8),
+           sequence: 0,
+           return_type: match value("return").map_or(0, |code| code as u8) {
+               0 => ReturnType::Strongest,
+               1 => ReturnType::Last,
+               2 => ReturnType::Secondary,
+               code => return Err(invalid(&format!("invalid return: {}", code))),
+           },
+           azimuth: Azimuth::Measured(Degrees(azimuth)),
+           time: time(value("time").unwrap_or(0.)),
+           sensor_id: None,
+       })
+}
+
+/// Returns a time in seconds, past the hour for offset times or since the Unix epoch for absolute
+/// times.
+pub(crate) fn seconds(time: Time) -> f64 {
+    match time {
+        Time::Offset(offset) => offset.num_nanoseconds().unwrap_or(0) as f64 / 1e9,
+        Time::Absolute(time) => {
+            time.timestamp() as f64 + f64::from(time.timestamp_subsec_nanos()) / 1e9
+        }
+    }
+}
+
+fn time(seconds: f64) -> Time {
+    if seconds < SECONDS_PER_HOUR {
+        Time::Offset(Duration::nanoseconds((seconds * 1e9).round() as i64))
+    } else {
+        let whole = seconds.floor();
+        let nanoseconds = (((seconds - whole) * 1e9).round() as u32).min(999_999_999);
+        Time::Absolute(UTC.timestamp(whole as i64, nanoseconds))
+    }
+}
+
+fn return_code(return_type: ReturnType) -> u8 {
+    match return_type {
+        ReturnType::Strongest => 0,
+        ReturnType::Last => 1,
+        ReturnType::Secondary => 2,
+    }
+}
+
+fn invalid(message: &str) -> Error {
+    Error::Pcd(message.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use fixtures::VLP_16_DATA_PACKET;
+    use vlp_16::Packet;
+
+    #[test]
+    fn roundtrip() {
+        let mut points = Packet::new(&VLP_16_DATA_PACKET).unwrap().points().unwrap();
+        points[1].time = Time::Absolute(UTC.timestamp(1_437_687_666, 108_343_000));
+        points[2].return_type = ReturnType::Secondary;
+        for &encoding in &[Encoding::Ascii, Encoding::Binary] {
+            let mut bytes = Vec::new();
+            write(&mut bytes, &points, encoding).unwrap();
+            let read = read(&bytes[..]).unwrap();
+            assert_eq!(points.len(), read.len());
+            for (expected, point) in points.iter().zip(&read) {
+                assert_eq!((expected.x, expected.y, expected.z), (point.x, point.y, point.z));
+                assert_eq!(expected.reflectivity, point.reflectivity);
+                assert_eq!(expected.channel, point.channel);
+                assert_eq!(expected.azimuth.degrees(), point.azimuth.degrees());
+                assert_eq!(return_code(expected.return_type), return_code(point.return_type));
+                let error = point.time.duration_since(expected.time).unwrap();
+                assert!(error.num_microseconds().unwrap().abs() <= 1, "{:?}", error);
+            }
+        }
+    }
+
+    #[test]
+    fn crs() {
+        let points = Packet::new(&VLP_16_DATA_PACKET).unwrap().points().unwrap();
+        let mut writer = Writer::new(Vec::new(), Encoding::Binary);
+        writer.crs(Crs::Wkt("PROJCS[\"WGS 84 / UTM zone 33N\"]".to_string()));
+        writer.write_points(&points).unwrap();
+        writer.finish().unwrap();
+        let bytes = writer.into_inner();
+        assert_eq!(Some(Crs::Wkt("PROJCS[\"WGS 84 / UTM zone 33N\"]".to_string())),
+                   read_crs(&bytes[..]).unwrap());
+        assert_eq!(points.len(), read(&bytes[..]).unwrap().len());
+        let mut bytes = Vec::new();
+        write(&mut bytes, &points, Encoding::Binary).unwrap();
+        assert_eq!(None, read_crs(&bytes[..]).unwrap());
+    }
+
+    #[test]
+    fn unsupported() {
+        let file = "FIELDS x y z\nSIZE 4 4 4\nTYPE F F F\nPOINTS 0\nDATA binary_compressed\n";
+        assert!(read(file.as_bytes()).is_err());
+        let file = "FIELDS x y\nSIZE 4 4\nTYPE F F\nPOINTS 1\nDATA ascii\n1 2\n";
+        assert!(read(file.as_bytes()).is_err());
+    }
+}
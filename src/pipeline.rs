@@ -0,0 +1,365 @@
+//! Multi-threaded capture and decode.
+//!
+//! A pipeline has two stages: a single reader thread pulls raw packets off of a source, and a
+//! pool of worker threads decodes those packets into points. Results are delivered in the order
+//! the packets were read.
+
+use {Point, Result};
+use frame::{Assembler, Frame};
+use io::Read;
+use monitor::Monitor;
+use std::collections::BTreeMap;
+use std::sync::{Arc, Mutex};
+use std::sync::mpsc::{self, Receiver, SyncSender};
+use std::thread::{self, JoinHandle};
+use vlp_16::{self, Packet};
+use warning::DecodeWarning;
+
+const DEFAULT_QUEUE_DEPTH: usize = 256;
+
+/// Configures and starts multi-threaded decoding.
+#[derive(Clone, Copy, Debug)]
+pub struct Pipeline {
+    workers: usize,
+    read_queue_depth: usize,
+    output_queue_depth: usize,
+}
+
+/// A single decoded packet.
+#[derive(Clone, Debug)]
+pub struct Decoded {
+    /// The decoded packet.
+    pub packet: Packet,
+    /// The points in the packet, empty if this is a position packet.
+    pub points: Vec<Point>,
+}
+
+/// An iterator over the decoded output of a running pipeline.
+#[derive(Debug)]
+pub struct Output {
+    receiver: Receiver<(usize, Result<Decoded>)>,
+    pending: BTreeMap<usize, Result<Decoded>>,
+    next: usize,
+    threads: Vec<JoinHandle<()>>,
+    monitor: Monitor,
+}
+
+/// An iterator over the frames produced by a running pipeline.
+#[derive(Debug)]
+pub struct Frames {
+    output: Output,
+    assembler: Assembler,
+    points: ::std::vec::IntoIter<Point>,
+}
+
+impl Pipeline {
+    /// Creates a new pipeline with one worker per available CPU.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use velodyne::pipeline::Pipeline;
+    /// let pipeline = Pipeline::new();
+    /// ```
+    pub fn new() -> Pipeline {
+        Pipeline {
+            workers: thread::available_parallelism().map(|n| n.get()).unwrap_or(1),
+            read_queue_depth: DEFAULT_QUEUE_DEPTH,
+            output_queue_depth: DEFAULT_QUEUE_DEPTH,
+        }
+    }
+
+    /// Sets the number of decoding threads.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `workers` is zero.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use velodyne::pipeline::Pipeline;
+    /// let mut pipeline = Pipeline::new();
+    /// pipeline.workers(4);
+    /// ```
+    pub fn workers(&mut self, workers: usize) -> &mut Pipeline {
+        assert!(workers > 0, "a pipeline needs at least one worker");
+        self.workers = workers;
+        self
+    }
+
+    /// Sets the maximum number of raw packets waiting to be decoded.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use velodyne::pipeline::Pipeline;
+    /// let mut pipeline = Pipeline::new();
+    /// pipeline.read_queue_depth(1024);
+    /// ```
+    pub fn read_queue_depth(&mut self, depth: usize) -> &mut Pipeline {
+        self.read_queue_depth = depth;
+        self
+    }
+
+    /// Sets the maximum number of decoded packets waiting to be consumed.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use velodyne::pipeline::Pipeline;
+    /// let mut pipeline = Pipeline::new();
+    /// pipeline.output_queue_depth(1024);
+    /// ```
+    pub fn output_queue_depth(&mut self, depth: usize) -> &mut Pipeline {
+        self.output_queue_depth = depth;
+        self
+    }
+
+    /// Starts the pipeline.
+    ///
+    /// Sources like `Pcap` can't be sent between threads, so this takes a function that opens the
+    /// source on the reader thread.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use velodyne::pipeline::Pipeline;
+    /// use velodyne::io::Pcap;
+    /// let output = Pipeline::new().start(|| Pcap::open("data/single.pcap"));
+    /// let npoints = output.map(|decoded| decoded.unwrap().points.len()).sum::<usize>();
+    /// assert_eq!(384, npoints);
+    /// ```
+    pub fn start<F, R>(&self, open: F) -> Output
+        where F: FnOnce() -> Result<R> + Send + 'static,
+              R: Read
+    {
+        let (raw_sender, raw_receiver) = mpsc::sync_channel(self.read_queue_depth);
+        let (output_sender, output_receiver) = mpsc::sync_channel(self.output_queue_depth);
+        let raw_receiver = Arc::new(Mutex::new(raw_receiver));
+        let monitor = Monitor::new();
+        let mut threads = Vec::with_capacity(self.workers + 1);
+        for _ in 0..self.workers {
+            let raw_receiver = raw_receiver.clone();
+            let output_sender = output_sender.clone();
+            let monitor = monitor.clone();
+            threads.push(thread::spawn(move || decode(&raw_receiver, &output_sender, &monitor)));
+        }
+        let reader_monitor = monitor.clone();
+        threads.push(thread::spawn(move || {
+                                       read(open, &raw_sender, &output_sender, &reader_monitor)
+                                   }));
+        Output {
+            receiver: output_receiver,
+            pending: BTreeMap::new(),
+            next: 0,
+            threads,
+            monitor,
+        }
+    }
+}
+
+impl Default for Pipeline {
+    fn default() -> Pipeline {
+        Pipeline::new()
+    }
+}
+
+impl Output {
+    /// Returns a monitor of this pipeline's throughput and queue depths.
+    ///
+    /// The monitor can be polled from any thread, while the pipeline runs and afterwards.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use velodyne::pipeline::Pipeline;
+    /// # use velodyne::io::Pcap;
+    /// let frames = Pipeline::new().start(|| Pcap::open("data/single.pcap")).frames();
+    /// let monitor = frames.monitor();
+    /// assert_eq!(1, frames.count());
+    /// assert_eq!(384, monitor.totals().points);
+    /// assert_eq!(1, monitor.totals().frames);
+    /// ```
+    pub fn monitor(&self) -> Monitor {
+        self.monitor.clone()
+    }
+
+    /// Assembles this pipeline's points into frames.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use velodyne::pipeline::Pipeline;
+    /// # use velodyne::io::Pcap;
+    /// let frames = Pipeline::new().start(|| Pcap::open("data/single.pcap")).frames();
+    /// assert_eq!(1, frames.count());
+    /// ```
+    pub fn frames(self) -> Frames {
+        Frames {
+            output: self,
+            assembler: Assembler::new(),
+            points: Vec::new().into_iter(),
+        }
+    }
+}
+
+impl Iterator for Output {
+    type Item = Result<Decoded>;
+
+    fn next(&mut self) -> Option<Result<Decoded>> {
+        loop {
+            if let Some(result) = self.pending.remove(&self.next) {
+                self.next += 1;
+                return Some(result);
+            }
+            match self.receiver.recv() {
+                Ok((index, result)) => {
+                    self.monitor.dequeue_output();
+                    self.pending.insert(index, result);
+                }
+                Err(_) => {
+                    for thread in self.threads.drain(..) {
+                        thread.join().expect("pipeline thread panicked");
+                    }
+                    return None;
+                }
+            }
+        }
+    }
+}
+
+impl Frames {
+    /// Returns a monitor of this pipeline's throughput and queue depths, including frames.
+    pub fn monitor(&self) -> Monitor {
+        self.output.monitor()
+    }
+}
+
+impl Iterator for Frames {
+    type Item = Result<Frame>;
+
+    fn next(&mut self) -> Option<Result<Frame>> {
+        loop {
+            for point in &mut self.points {
+                if let Some(frame) = self.assembler.push(point) {
+                    self.output.monitor.add_frames(1);
+                    return Some(Ok(frame));
+                }
+            }
+            match self.output.next() {
+                Some(Ok(decoded)) => self.points = decoded.points.into_iter(),
+                Some(Err(err)) => return Some(Err(err)),
+                None => {
+                    let frame = self.assembler.finish();
+                    if frame.is_some() {
+                        self.output.monitor.add_frames(1);
+                    }
+                    return frame.map(Ok);
+                }
+            }
+        }
+    }
+}
+
+fn read<F, R>(open: F,
+              raw_sender: &SyncSender<(usize, Vec<u8>)>,
+              output_sender: &SyncSender<(usize, Result<Decoded>)>,
+              monitor: &Monitor)
+    where F: FnOnce() -> Result<R>,
+          R: Read
+{
+    let mut read = match open() {
+        Ok(read) => read,
+        Err(err) => {
+            monitor.enqueue_output();
+            let _ = output_sender.send((0, Err(err)));
+            return;
+        }
+    };
+    let mut index = 0;
+    while let Some(result) = read.read() {
+        let sent = match result {
+            Ok(bytes) => {
+                monitor.add_packets(1);
+                if vlp_16::classify(bytes).is_none() {
+                    monitor.add_skipped(1);
+                    monitor.add_warning(DecodeWarning::SkippedPacket);
+                    continue;
+                }
+                monitor.enqueue_read();
+                raw_sender.send((index, bytes.to_vec())).is_ok()
+            }
+            Err(err) => {
+                monitor.enqueue_output();
+                output_sender.send((index, Err(err))).is_ok()
+            }
+        };
+        if !sent {
+            return;
+        }
+        index += 1;
+    }
+}
+
+fn decode(raw_receiver: &Mutex<Receiver<(usize, Vec<u8>)>>,
+          output_sender: &SyncSender<(usize, Result<Decoded>)>,
+          monitor: &Monitor) {
+    loop {
+        let message = raw_receiver.lock().expect("pipeline lock poisoned").recv();
+        let (index, bytes) = match message {
+            Ok(message) => message,
+            Err(_) => return,
+        };
+        monitor.dequeue_read();
+        let result = Packet::new(&bytes).map(|packet| {
+            Decoded {
+                points: packet.points().unwrap_or_default(),
+                packet,
+            }
+        });
+        if let Ok(ref decoded) = result {
+            monitor.add_points(decoded.points.len());
+            for warning in decoded.packet.checksum_warnings() {
+                monitor.add_warning(warning);
+            }
+        }
+        monitor.enqueue_output();
+        if output_sender.send((index, result)).is_err() {
+            return;
+        }
+    }
+}
+
+#[cfg(all(test, feature = "pcap"))]
+mod tests {
+    use super::*;
+    use io::Pcap;
+
+    #[test]
+    fn position() {
+        let mut output = Pipeline::new().workers(3).start(|| Pcap::open("data/position.pcap"));
+        let decoded = output.next().unwrap().unwrap();
+        assert!(decoded.packet.is_position());
+        assert!(decoded.points.is_empty());
+        assert!(output.next().is_none());
+    }
+
+    #[test]
+    fn monitor() {
+        let output = Pipeline::new().workers(2).start(|| Pcap::open("data/single.pcap"));
+        let monitor = output.monitor();
+        assert_eq!(1, output.count());
+        let rates = monitor.rates();
+        assert_eq!((0, 0), (rates.read_queue, rates.output_queue));
+        assert_eq!(1, monitor.totals().packets);
+        assert_eq!(384, monitor.totals().points);
+    }
+
+    #[test]
+    fn open_error() {
+        let mut output = Pipeline::new().start(|| Pcap::open("notafile"));
+        assert!(output.next().unwrap().is_err());
+        assert!(output.next().is_none());
+    }
+}
@@ -1,6 +1,12 @@
 //! Measured data points.
 
-use chrono::{Duration, UTC};
+use chrono::{DateTime, Duration, UTC};
+use units::{Degrees, Meters};
+
+/// The largest calibrated reflectivity of a diffuse reflector.
+///
+/// Larger values are retro-reflectors.
+pub const MAX_DIFFUSE_REFLECTIVITY: u8 = 100;
 
 /// A three-dimensional Velodyne point.
 #[derive(Clone, Copy, Debug)]
@@ -15,12 +21,23 @@ pub struct Point {
     pub reflectivity: u8,
     /// The laser channel.
     pub channel: u8,
+    /// The firing sequence within the point's data block, zero or one.
+    ///
+    /// The VLP-16 fires every laser twice per data block, 55.296 µs apart. Averaged points, see
+    /// `vlp_16::FiringSequences`, and points that weren't decoded from a packet are in sequence
+    /// zero.
+    pub sequence: u8,
     /// The type of return.
     pub return_type: ReturnType,
     /// The azimuth measurement.
     pub azimuth: Azimuth,
     /// The time of the point.
     pub time: Time,
+    /// The sensor that measured the point, when points from several sensors are combined.
+    ///
+    /// Points are stamped by a decoder with `vlp_16::Decoder::sensor_id`, or by a
+    /// `merge::Merge`. File formats don't store it.
+    pub sensor_id: Option<u16>,
 }
 
 /// The type of laser return.
@@ -41,19 +58,298 @@ pub enum ReturnType {
 #[derive(Clone, Copy, Debug)]
 pub enum Azimuth {
     /// The azimuth was provided as part of the data packet.
-    Measured(f32),
+    Measured(Degrees),
     /// The azimuth was interpolated.
-    Interpolated(f32),
+    Interpolated(Degrees),
     /// The azimuth was extrapolated.
-    Extrapolated(f32),
+    Extrapolated(Degrees),
 }
 
+/// An azimuth in hundredths of a degree, as sensors send them.
+///
+/// Unlike degrees in an `f32`, centidegrees can be compared exactly and are the same on every
+/// platform.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Centidegrees(pub u16);
+
 /// The type of time measurement.
 #[derive(Clone, Copy, Debug)]
 pub enum Time {
     /// The timestamp provided in the data packet, which is an offset from the last hour.
     Offset(Duration),
-    /// The absolute time of the point, as calcualted from the offset and a GPS-provided time
-    /// value.
-    Absolute(UTC),
+    /// The absolute time of the point, as calculated from the offset and a time source.
+    Absolute(DateTime<UTC>),
+}
+
+#[cfg(feature = "nalgebra")]
+impl From<Point> for nalgebra::Point3<f32> {
+    fn from(point: Point) -> nalgebra::Point3<f32> {
+        nalgebra::Point3::new(point.x, point.y, point.z)
+    }
+}
+
+#[cfg(feature = "nalgebra")]
+impl<'a> From<&'a Point> for nalgebra::Point3<f32> {
+    fn from(point: &'a Point) -> nalgebra::Point3<f32> {
+        nalgebra::Point3::new(point.x, point.y, point.z)
+    }
+}
+
+impl Azimuth {
+    /// Returns the azimuth in degrees, regardless of how it was determined.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use velodyne::point::Azimuth;
+    /// use velodyne::units::Degrees;
+    /// assert_eq!(42., Azimuth::Interpolated(Degrees(42.)).degrees());
+    /// ```
+    pub fn degrees(&self) -> f32 {
+        match *self {
+            Azimuth::Measured(degrees) |
+            Azimuth::Interpolated(degrees) |
+            Azimuth::Extrapolated(degrees) => degrees.0,
+        }
+    }
+
+    /// Returns the azimuth in centidegrees, rounded to the nearest hundredth of a degree.
+    ///
+    /// Decoded azimuths are always whole centidegrees, so this is exact for them.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use velodyne::point::{Azimuth, Centidegrees};
+    /// use velodyne::units::Degrees;
+    /// assert_eq!(Centidegrees(4_201), Azimuth::Measured(Degrees(42.01)).centidegrees());
+    /// ```
+    pub fn centidegrees(&self) -> Centidegrees {
+        Centidegrees::from_degrees(self.degrees())
+    }
+}
+
+impl Centidegrees {
+    /// Converts degrees to centidegrees, rounding to the nearest hundredth of a degree and
+    /// wrapping into [0, 360).
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use velodyne::point::Centidegrees;
+    /// assert_eq!(Centidegrees(22_970), Centidegrees::from_degrees(229.7));
+    /// assert_eq!(Centidegrees(0), Centidegrees::from_degrees(360.));
+    /// ```
+    pub fn from_degrees(degrees: f32) -> Centidegrees {
+        Centidegrees((f64::from(degrees) * 100.).round().rem_euclid(36_000.) as u16)
+    }
+
+    /// Returns this azimuth in degrees.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use velodyne::point::Centidegrees;
+    /// assert_eq!(229.7, Centidegrees(22_970).degrees());
+    /// ```
+    pub fn degrees(self) -> f32 {
+        f32::from(self.0) / 100.
+    }
+}
+
+impl Time {
+    /// Returns the time elapsed since an earlier time, or `None` if one time is an offset and the
+    /// other is absolute.
+    ///
+    /// Offsets wrap at the top of the hour, so an offset just after the hour is later than one
+    /// just before it.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # extern crate chrono;
+    /// # extern crate velodyne;
+    /// # fn main() {
+    /// use chrono::Duration;
+    /// use velodyne::point::Time;
+    /// let earlier = Time::Offset(Duration::minutes(59));
+    /// let later = Time::Offset(Duration::minutes(1));
+    /// assert_eq!(Some(Duration::minutes(2)), later.duration_since(earlier));
+    /// # }
+    /// ```
+    pub fn duration_since(&self, earlier: Time) -> Option<Duration> {
+        match (*self, earlier) {
+            (Time::Offset(time), Time::Offset(earlier)) => {
+                let duration = time - earlier;
+                if duration < -Duration::minutes(30) {
+                    Some(duration + Duration::hours(1))
+                } else {
+                    Some(duration)
+                }
+            }
+            (Time::Absolute(time), Time::Absolute(earlier)) => {
+                Some(time.signed_duration_since(earlier))
+            }
+            _ => None,
+        }
+    }
+}
+
+impl Point {
+    /// Creates a point from spherical coordinates.
+    ///
+    /// The azimuth is measured clockwise from the y axis and the elevation is measured up from
+    /// the xy plane.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # extern crate chrono;
+    /// # extern crate velodyne;
+    /// # fn main() {
+    /// use chrono::Duration;
+    /// use velodyne::Point;
+    /// use velodyne::point::{Azimuth, ReturnType, Time};
+    /// use velodyne::units::{Degrees, Meters};
+    /// let point = Point::from_spherical(Meters(2.),
+    ///                                   Azimuth::Measured(Degrees(90.)),
+    ///                                   Degrees(0.),
+    ///                                   100,
+    ///                                   0,
+    ///                                   ReturnType::Strongest,
+    ///                                   Time::Offset(Duration::zero()));
+    /// assert_eq!(2., point.x);
+    /// # }
+    /// ```
+    pub fn from_spherical(range: Meters,
+                          azimuth: Azimuth,
+                          elevation: Degrees,
+                          reflectivity: u8,
+                          channel: u8,
+                          return_type: ReturnType,
+                          time: Time)
+                          -> Point {
+        let azimuth_rad = azimuth.degrees().to_radians();
+        let elevation_rad = elevation.radians().0;
+        let range = range.0;
+        Point {
+            x: range * elevation_rad.cos() * azimuth_rad.sin(),
+            y: range * elevation_rad.cos() * azimuth_rad.cos(),
+            z: range * elevation_rad.sin(),
+            reflectivity,
+            channel,
+            sequence: 0,
+            return_type,
+            azimuth,
+            time,
+            sensor_id: None,
+        }
+    }
+
+    /// Returns the calibrated reflectivity as a number between zero and one.
+    ///
+    /// See `normalize_reflectivity`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use velodyne::fixtures::VLP_16_DATA_PACKET;
+    /// use velodyne::vlp_16::Packet;
+    /// let point = Packet::new(&VLP_16_DATA_PACKET).unwrap().points().unwrap()[0];
+    /// assert_eq!(4, point.reflectivity);
+    /// assert_eq!(0.02, point.intensity());
+    /// ```
+    pub fn intensity(&self) -> f32 {
+        normalize_reflectivity(self.reflectivity)
+    }
+
+    /// Returns the distance from the sensor to this point.
+    pub fn range(&self) -> f32 {
+        (self.x * self.x + self.y * self.y + self.z * self.z).sqrt()
+    }
+
+    /// Returns the elevation of this point above the xy plane, in degrees.
+    ///
+    /// Points without a return are at the origin, and have an elevation of zero.
+    pub fn elevation(&self) -> f32 {
+        self.z.atan2((self.x * self.x + self.y * self.y).sqrt()).to_degrees()
+    }
+
+    /// Returns the range, azimuth, and elevation of this point, recovered from its coordinates.
+    ///
+    /// The azimuth is in degrees in `[0, 360)`, measured clockwise from the y axis. Unlike the
+    /// `azimuth` field, this is computed from the coordinates, so it is only meaningful for
+    /// points with a return.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use velodyne::fixtures::VLP_16_DATA_PACKET;
+    /// use velodyne::vlp_16::Packet;
+    /// let point = Packet::new(&VLP_16_DATA_PACKET).unwrap().points().unwrap()[0];
+    /// let (range, azimuth, elevation) = point.spherical();
+    /// assert!((azimuth - point.azimuth.degrees()).abs() < 1e-3);
+    /// assert!((elevation + 15.).abs() < 1e-3);
+    /// ```
+    pub fn spherical(&self) -> (f32, f32, f32) {
+        let azimuth = self.x.atan2(self.y).to_degrees();
+        let azimuth = if azimuth < 0. { azimuth + 360. } else { azimuth };
+        (self.range(), azimuth, self.elevation())
+    }
+}
+
+/// Maps a calibrated reflectivity onto a number between zero and one.
+///
+/// Diffuse reflectors, from zero to 100, take the lower half of the range, and retro-reflectors,
+/// from 101 to 255, take the upper half, so that a white wall and a road sign are as far apart as
+/// the sensor says they are.
+///
+/// # Examples
+///
+/// ```
+/// use velodyne::point::normalize_reflectivity;
+/// assert_eq!(0., normalize_reflectivity(0));
+/// assert_eq!(0.5, normalize_reflectivity(100));
+/// assert_eq!(1., normalize_reflectivity(255));
+/// ```
+pub fn normalize_reflectivity(reflectivity: u8) -> f32 {
+    let max_diffuse = f32::from(MAX_DIFFUSE_REFLECTIVITY);
+    if reflectivity <= MAX_DIFFUSE_REFLECTIVITY {
+        f32::from(reflectivity) / max_diffuse / 2.
+    } else {
+        0.5 + (f32::from(reflectivity) - max_diffuse) / (255. - max_diffuse) / 2.
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn normalized_reflectivity() {
+        assert!(normalize_reflectivity(101) > normalize_reflectivity(100));
+        let mut last = -1.;
+        for reflectivity in 0..=255 {
+            let intensity = normalize_reflectivity(reflectivity);
+            assert!(intensity > last && intensity <= 1.);
+            last = intensity;
+        }
+    }
+
+    #[test]
+    fn spherical_round_trip() {
+        for &(azimuth, elevation) in &[(0., 0.), (45., -15.), (181.5, 3.), (359., 15.)] {
+            let point = Point::from_spherical(Meters(12.5),
+                                              Azimuth::Measured(Degrees(azimuth)),
+                                              Degrees(elevation),
+                                              0,
+                                              0,
+                                              ReturnType::Strongest,
+                                              Time::Offset(Duration::zero()));
+            let (range, actual_azimuth, actual_elevation) = point.spherical();
+            assert!((range - 12.5).abs() < 1e-4);
+            assert!((actual_azimuth - azimuth).abs() < 1e-3);
+            assert!((actual_elevation - elevation).abs() < 1e-3);
+        }
+    }
 }
@@ -0,0 +1,25 @@
+//! The types that most programs need, in one `use`.
+//!
+//! # Examples
+//!
+//! ```
+//! use velodyne::prelude::*;
+//! let pcap = Pcap::open("data/single.pcap").unwrap();
+//! for packet in pcap.vlp_16_packets() {
+//!     if let Some(points) = packet.unwrap().points() {
+//!         let frame = Frame::new(points);
+//!     }
+//! }
+//! ```
+
+pub use Point;
+pub use batch::Config;
+pub use frame::Frame;
+#[cfg(feature = "pcap")]
+pub use io::Pcap;
+pub use io::Read;
+pub use nmea::Position;
+pub use source::Source;
+pub use stream::PointStream;
+pub use units::{Degrees, Meters, Radians};
+pub use vlp_16::Packet;
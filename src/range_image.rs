@@ -0,0 +1,293 @@
+//! Organized range images.
+//!
+//! A range image is a grid with one row per laser, ordered by elevation, and one column per
+//! azimuth bin. Many perception algorithms work on this organized representation instead of an
+//! unordered list of points.
+
+use Point;
+use frame::Frame;
+use vlp_16::{NUM_LASERS, vertical_angle};
+
+/// A grid of range and intensity measurements.
+#[derive(Clone, Debug)]
+pub struct RangeImage {
+    columns: usize,
+    cells: Vec<Option<Cell>>,
+}
+
+/// A single cell in a range image.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Cell {
+    /// The distance from the sensor to the point.
+    pub range: f32,
+    /// The calibrated reflectivity of the point.
+    pub intensity: u8,
+    /// The index of the point in the frame.
+    ///
+    /// For a filled cell, this is one of the points it was interpolated from.
+    pub point: usize,
+    /// Whether this cell was interpolated by `fill_gaps`, rather than measured.
+    pub filled: bool,
+}
+
+impl RangeImage {
+    /// Creates a range image from a frame with the given number of azimuth bins.
+    ///
+    /// If more than one point falls in a cell, the closest one is kept. Points without a return
+    /// are skipped, so their cells are empty, as are points from lasers past the sixteenth, e.g.
+    /// the HDL-32E's upper channels.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `columns` is zero.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use velodyne::range_image::RangeImage;
+    /// use velodyne::frame::Frame;
+    /// let range_image = RangeImage::from_frame(&Frame::default(), 1800);
+    /// assert_eq!(16, range_image.rows());
+    /// assert_eq!(1800, range_image.columns());
+    /// ```
+    pub fn from_frame(frame: &Frame, columns: usize) -> RangeImage {
+        RangeImage::from_points(&frame.points, columns)
+    }
+
+    /// Creates a range image from points with the given number of azimuth bins.
+    ///
+    /// Cells refer to points by their index in the slice.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `columns` is zero.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use velodyne::range_image::RangeImage;
+    /// let range_image = RangeImage::from_points(&[], 1800);
+    /// assert_eq!(1800, range_image.columns());
+    /// ```
+    pub fn from_points(points: &[Point], columns: usize) -> RangeImage {
+        assert!(columns > 0, "a range image needs at least one column");
+        let mut range_image = RangeImage {
+            columns,
+            cells: vec![None; NUM_LASERS * columns],
+        };
+        for (index, point) in points.iter().enumerate() {
+            let range = point.range();
+            let row = match RangeImage::point_row(point) {
+                Some(row) if range > 0. => row,
+                _ => continue,
+            };
+            let column = range_image.column(point.azimuth.degrees());
+            let cell = &mut range_image.cells[row * columns + column];
+            if cell.map(|cell| range < cell.range).unwrap_or(true) {
+                *cell = Some(Cell {
+                                 range,
+                                 intensity: point.reflectivity,
+                                 point: index,
+                                 filled: false,
+                             });
+            }
+        }
+        range_image
+    }
+
+    /// Returns the row for a laser channel.
+    ///
+    /// Row zero is the highest laser.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use velodyne::range_image::RangeImage;
+    /// assert_eq!(15, RangeImage::row(0));
+    /// assert_eq!(0, RangeImage::row(15));
+    /// ```
+    pub fn row(channel: usize) -> usize {
+        let elevation = vertical_angle(channel);
+        (0..NUM_LASERS).filter(|&other| vertical_angle(other) > elevation).count()
+    }
+
+    /// Returns the row for a point's laser, or `None` if the range image has no row for it.
+    pub(crate) fn point_row(point: &Point) -> Option<usize> {
+        let channel = point.channel as usize;
+        if channel < NUM_LASERS {
+            Some(RangeImage::row(channel))
+        } else {
+            None
+        }
+    }
+
+    /// Returns the number of rows, which is the number of lasers.
+    pub fn rows(&self) -> usize {
+        NUM_LASERS
+    }
+
+    /// Returns the number of azimuth bins.
+    pub fn columns(&self) -> usize {
+        self.columns
+    }
+
+    /// Returns the cell at the given row and column, or `None` if the cell is empty.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the row or column is out of bounds.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use velodyne::range_image::RangeImage;
+    /// # use velodyne::frame::Frame;
+    /// let range_image = RangeImage::from_frame(&Frame::default(), 1800);
+    /// assert!(range_image.get(0, 0).is_none());
+    /// ```
+    pub fn get(&self, row: usize, column: usize) -> Option<&Cell> {
+        assert!(row < NUM_LASERS && column < self.columns);
+        self.cells[row * self.columns + column].as_ref()
+    }
+
+    /// Fills isolated empty cells by interpolating their neighbors, returning the number of
+    /// filled cells.
+    ///
+    /// A cell is filled if both of its neighbors in the same row, or failing that both of its
+    /// neighbors in the same column, were measured and their ranges differ by at most
+    /// `max_difference` meters, so gaps aren't bridged across the edges of objects. Only measured
+    /// cells are interpolated, so filling twice doesn't grow the filled areas. Columns wrap
+    /// around.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use velodyne::range_image::RangeImage;
+    /// use velodyne::fixtures::VLP_16_DATA_PACKET;
+    /// use velodyne::vlp_16::Packet;
+    /// let points = Packet::new(&VLP_16_DATA_PACKET).unwrap().points().unwrap();
+    /// let mut range_image = RangeImage::from_points(&points, 3600);
+    /// range_image.fill_gaps(0.5);
+    /// ```
+    pub fn fill_gaps(&mut self, max_difference: f32) -> usize {
+        let measured = |cells: &[Option<Cell>], index: usize| {
+            cells[index].filter(|cell| !cell.filled)
+        };
+        let mut filled = Vec::new();
+        for row in 0..NUM_LASERS {
+            for column in 0..self.columns {
+                let index = row * self.columns + column;
+                if self.cells[index].is_some() {
+                    continue;
+                }
+                let left = row * self.columns + (column + self.columns - 1) % self.columns;
+                let right = row * self.columns + (column + 1) % self.columns;
+                let mut pairs = vec![(left, right)];
+                if row > 0 && row + 1 < NUM_LASERS {
+                    pairs.push((index - self.columns, index + self.columns));
+                }
+                let cell = pairs.into_iter()
+                    .filter_map(|(a, b)| {
+                        let (a, b) = (measured(&self.cells, a)?, measured(&self.cells, b)?);
+                        if (a.range - b.range).abs() > max_difference {
+                            return None;
+                        }
+                        Some(Cell {
+                                 range: (a.range + b.range) / 2.,
+                                 intensity: (u16::from(a.intensity) + u16::from(b.intensity))
+                                     .div_ceil(2) as u8,
+                                 point: a.point,
+                                 filled: true,
+                             })
+                    })
+                    .next();
+                if let Some(cell) = cell {
+                    filled.push((index, cell));
+                }
+            }
+        }
+        for &(index, cell) in &filled {
+            self.cells[index] = Some(cell);
+        }
+        filled.len()
+    }
+
+    pub(crate) fn column(&self, azimuth: f32) -> usize {
+        (azimuth / 360. * self.columns as f32) as usize % self.columns
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use fixtures::VLP_16_DATA_PACKET;
+    use point::Azimuth;
+    use units::Degrees;
+    use vlp_16::Packet;
+
+    #[test]
+    fn rows_are_ordered_by_elevation() {
+        let mut rows = (0..NUM_LASERS).map(RangeImage::row).collect::<Vec<_>>();
+        rows.sort();
+        assert_eq!((0..NUM_LASERS).collect::<Vec<_>>(), rows);
+        assert_eq!(7, RangeImage::row(1));
+        assert_eq!(8, RangeImage::row(14));
+    }
+
+    #[test]
+    fn from_packet() {
+        let frame = Frame::new(Packet::new(&VLP_16_DATA_PACKET).unwrap().points().unwrap());
+        let range_image = RangeImage::from_frame(&frame, 3600);
+        let cell = range_image.get(RangeImage::row(0), 2297).unwrap();
+        assert!((cell.range - 6.524).abs() < 1e-3);
+        assert_eq!(4, cell.intensity);
+        assert_eq!(0, cell.point);
+        assert!(!cell.filled);
+    }
+
+    #[test]
+    fn fill_gaps() {
+        let template = Packet::new(&VLP_16_DATA_PACKET).unwrap().points().unwrap()[0];
+        // A dropout between two returns, a wider gap, and a dropout at the edge of an object.
+        let points = [10., 0., 10., 0., 0., 10., 0., 2.]
+            .iter()
+            .enumerate()
+            .map(|(column, &range)| {
+                     Point {
+                         x: range,
+                         y: 0.,
+                         z: 0.,
+                         azimuth: Azimuth::Measured(Degrees(column as f32 * 45. + 1.)),
+                         ..template
+                     }
+                 })
+            .collect::<Vec<_>>();
+        let mut range_image = RangeImage::from_points(&points, 8);
+        let row = RangeImage::row(template.channel as usize);
+        assert_eq!(1, range_image.fill_gaps(0.5));
+        let cell = range_image.get(row, 1).unwrap();
+        assert!(cell.filled);
+        assert_eq!(10., cell.range);
+        assert!(range_image.get(row, 3).is_none());
+        assert!(range_image.get(row, 6).is_none());
+        assert_eq!(0, range_image.fill_gaps(0.5));
+    }
+
+    #[test]
+    fn hdl_32e() {
+        use fixtures::RELABELLED_HDL_32E_DATA_PACKET;
+        let points = Packet::new(&RELABELLED_HDL_32E_DATA_PACKET).unwrap().points().unwrap();
+        assert!(points.iter().any(|point| point.channel as usize >= NUM_LASERS));
+        let range_image = RangeImage::from_frame(&Frame::new(points.clone()), 3600);
+        let mut cells = 0;
+        for row in 0..range_image.rows() {
+            for column in 0..range_image.columns() {
+                if let Some(cell) = range_image.get(row, column) {
+                    assert!((points[cell.point].channel as usize) < NUM_LASERS);
+                    cells += 1;
+                }
+            }
+        }
+        assert!(cells > 0);
+    }
+}
@@ -0,0 +1,262 @@
+//! Byte-level packet and NMEA parsing that doesn't need the standard library.
+//!
+//! Embedded gateways that receive a sensor's UDP packets directly often can't afford `std` or
+//! chrono. Without the default `std` feature the crate is `no_std`, and only this module, `wire`,
+//! `units`, and the fixtures' bytes are built. Payloads are parsed into the integers that are on
+//! the wire, without allocating, so they're cheap to summarize and forward; `vlp_16::Packet` is
+//! built from them when `std` is available.
+//!
+//! ```
+//! use velodyne::fixtures::VLP_16_DATA_PACKET;
+//! use velodyne::raw::DataPayload;
+//! use velodyne::wire;
+//! let payload = DataPayload::parse(&VLP_16_DATA_PACKET[wire::PACKET_HEADER_LEN..]).unwrap();
+//! assert_eq!(0x22, payload.sensor); // A VLP-16.
+//! ```
+
+use byteorder::{ByteOrder, LittleEndian};
+use wire::{self, DATA_RECORDS_PER_BLOCK, NUM_DATA_BLOCKS, START_IDENTIFIER};
+
+/// The reasons that a payload can't be parsed.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ParseError {
+    /// The payload is shorter than its fields, and has this many bytes.
+    TooShort(usize),
+    /// A data block doesn't start with the start identifier.
+    InvalidStartIdentifier(u16),
+}
+
+/// A data packet's payload, as it is on the wire.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct DataPayload {
+    /// The data blocks.
+    pub data_blocks: [DataBlock; NUM_DATA_BLOCKS],
+    /// The timestamp, in microseconds past the hour.
+    pub timestamp: u32,
+    /// The return mode factory byte.
+    pub return_mode: u8,
+    /// The sensor factory byte.
+    pub sensor: u8,
+}
+
+/// A data block, as it is on the wire.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct DataBlock {
+    /// The azimuth of the first firing, in centidegrees.
+    pub azimuth: u16,
+    /// The data records, in firing order.
+    pub data_records: [DataRecord; DATA_RECORDS_PER_BLOCK],
+}
+
+/// A data record, as it is on the wire.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct DataRecord {
+    /// The distance, in units of the sensor's distance resolution, or zero if there's no return.
+    pub distance: u16,
+    /// The calibrated reflectivity.
+    pub reflectivity: u8,
+}
+
+/// A position packet's payload, as it is on the wire.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct PositionPayload<'a> {
+    /// The temperature of the top board.
+    pub top_board_temperature: u8,
+    /// The temperature of the bottom board.
+    pub bottom_board_temperature: u8,
+    /// The temperature when the ADC calibration last ran.
+    pub adc_calibration_temperature: u8,
+    /// The change in temperature since the last ADC calibration.
+    pub adc_calibration_temperature_change: u16,
+    /// The number of seconds since the last ADC calibration.
+    pub seconds_since_adc_calibration: u32,
+    /// The reason for the last ADC calibration.
+    pub adc_calibration_reason: u8,
+    /// A bitmask of the ADC calibration's current status.
+    pub adc_calibration_status: u8,
+    /// The timestamp, in microseconds past the hour.
+    pub timestamp: u32,
+    /// The PPS status byte.
+    pub pps_status: u8,
+    /// Nonzero if the sensor is shut down because it is too hot.
+    pub thermal_shutdown: u8,
+    /// The temperature at the last thermal shutdown.
+    pub last_shutdown_temperature: u8,
+    /// The temperature of the unit when it powered up.
+    pub power_up_temperature: u8,
+    /// The NMEA buffer, padded with NULs, or as much of it as the payload has.
+    pub nmea: &'a [u8],
+}
+
+impl DataPayload {
+    /// Parses a data packet's payload.
+    ///
+    /// Only the start identifiers are checked, so the factory bytes can be anything.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use velodyne::fixtures::VLP_16_DATA_PACKET;
+    /// use velodyne::raw::{DataPayload, ParseError};
+    /// use velodyne::wire;
+    /// let payload = &VLP_16_DATA_PACKET[wire::PACKET_HEADER_LEN..];
+    /// assert!(DataPayload::parse(payload).is_ok());
+    /// assert_eq!(Err(ParseError::TooShort(1205)), DataPayload::parse(&payload[..1205]));
+    /// ```
+    pub fn parse(payload: &[u8]) -> Result<DataPayload, ParseError> {
+        if payload.len() < wire::DATA_PAYLOAD_LEN {
+            return Err(ParseError::TooShort(payload.len()));
+        }
+        let mut data_payload = DataPayload::default();
+        for (i, data_block) in data_payload.data_blocks.iter_mut().enumerate() {
+            let start_identifier = LittleEndian::read_u16(&payload[wire::data_block(i).range()]);
+            if start_identifier != START_IDENTIFIER {
+                return Err(ParseError::InvalidStartIdentifier(start_identifier));
+            }
+            data_block.azimuth = LittleEndian::read_u16(&payload[wire::azimuth(i).range()]);
+            for (j, data_record) in data_block.data_records.iter_mut().enumerate() {
+                let field = &payload[wire::data_record(i, j).range()];
+                data_record.distance = LittleEndian::read_u16(field);
+                data_record.reflectivity = field[2];
+            }
+        }
+        data_payload.timestamp = LittleEndian::read_u32(&payload[wire::DATA_TIMESTAMP.range()]);
+        data_payload.return_mode = payload[wire::RETURN_MODE.offset];
+        data_payload.sensor = payload[wire::SENSOR.offset];
+        Ok(data_payload)
+    }
+}
+
+impl<'a> PositionPayload<'a> {
+    /// Parses a position packet's payload.
+    ///
+    /// Newer firmware uses more of the NMEA buffer, so a payload only needs the status fields, and
+    /// the buffer is cut off at the end of a standard payload.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use velodyne::fixtures::VLP_16_POSITION_PACKET;
+    /// use velodyne::raw::PositionPayload;
+    /// use velodyne::wire;
+    /// let payload = &VLP_16_POSITION_PACKET[wire::PACKET_HEADER_LEN..];
+    /// let position_payload = PositionPayload::parse(payload).unwrap();
+    /// assert!(position_payload.nmea.starts_with(b"$GPRMC"));
+    /// ```
+    pub fn parse(payload: &'a [u8]) -> Result<PositionPayload<'a>, ParseError> {
+        if payload.len() < wire::POSITION_STATUS.end() {
+            return Err(ParseError::TooShort(payload.len()));
+        }
+        let status = &payload[wire::POSITION_STATUS.range()];
+        let nmea_end = payload.len().min(wire::NMEA.end());
+        Ok(PositionPayload {
+               top_board_temperature: status[0],
+               bottom_board_temperature: status[1],
+               adc_calibration_temperature: status[2],
+               adc_calibration_temperature_change: LittleEndian::read_u16(&status[3..]),
+               seconds_since_adc_calibration: LittleEndian::read_u32(&status[5..]),
+               adc_calibration_reason: status[9],
+               adc_calibration_status: status[10],
+               timestamp: LittleEndian::read_u32(&payload[wire::POSITION_TIMESTAMP.range()]),
+               pps_status: payload[wire::PPS_STATUS.offset],
+               thermal_shutdown: status[16],
+               last_shutdown_temperature: status[17],
+               power_up_temperature: status[18],
+               nmea: &payload[wire::NMEA.offset..nmea_end],
+           })
+    }
+
+    /// Returns the NMEA sentences in the buffer.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use velodyne::fixtures::VLP_16_POSITION_PACKET;
+    /// use velodyne::raw::PositionPayload;
+    /// use velodyne::wire;
+    /// let payload = &VLP_16_POSITION_PACKET[wire::PACKET_HEADER_LEN..];
+    /// assert_eq!(1, PositionPayload::parse(payload).unwrap().sentences().count());
+    /// ```
+    pub fn sentences(&self) -> impl Iterator<Item = &'a [u8]> {
+        sentences(self.nmea)
+    }
+}
+
+/// Splits bytes into NMEA sentences, which can be terminated by CR, LF, or NUL.
+///
+/// Anything that doesn't start with a `$` is skipped, e.g. padding.
+///
+/// # Examples
+///
+/// ```
+/// use velodyne::raw;
+/// let mut sentences = raw::sentences(b"$GPRMC,1*00\r\n\0\0$GPGGA,2*00\0");
+/// assert_eq!(Some(&b"$GPRMC,1*00"[..]), sentences.next());
+/// assert_eq!(Some(&b"$GPGGA,2*00"[..]), sentences.next());
+/// assert_eq!(None, sentences.next());
+/// ```
+pub fn sentences(bytes: &[u8]) -> impl Iterator<Item = &[u8]> {
+    bytes.split(|&n| n == b'\r' || n == b'\n' || n == 0)
+        .filter(|sentence| sentence.first() == Some(&b'$'))
+}
+
+/// Splits a sentence into its comma-separated fields, leaving the checksum out of the last one.
+///
+/// The first field is the sentence's type, e.g. `$GPRMC`.
+///
+/// # Examples
+///
+/// ```
+/// use velodyne::raw;
+/// let mut fields = raw::fields("$GPGGA,214106.00,3707.8178*4E");
+/// assert_eq!(Some("$GPGGA"), fields.next());
+/// assert_eq!(Some("3707.8178"), fields.nth(1));
+/// assert_eq!(None, fields.next());
+/// ```
+pub fn fields(sentence: &str) -> impl Iterator<Item = &str> {
+    let data = sentence.rfind('*').map_or(sentence, |index| &sentence[..index]);
+    data.split(',')
+}
+
+/// Returns the checksum of the bytes between a sentence's `$` and `*`.
+///
+/// # Examples
+///
+/// ```
+/// use velodyne::raw;
+/// assert_eq!(0x4e, raw::checksum(b"GPGGA,214106.00,3707.8178,N,12139.2690,W,2,09,0.9,12.3,M,\
+///                                  -25.6,M,1.0,0000"));
+/// ```
+pub fn checksum(body: &[u8]) -> u8 {
+    body.iter().fold(0, |acc, &n| acc ^ n)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use fixtures::{VLP_16_DATA_PACKET, VLP_16_POSITION_PACKET};
+    use wire::PACKET_HEADER_LEN;
+
+    #[test]
+    fn data_payload() {
+        let mut payload = VLP_16_DATA_PACKET;
+        let data_payload = DataPayload::parse(&payload[PACKET_HEADER_LEN..]).unwrap();
+        assert_eq!(2_467_108_343, data_payload.timestamp);
+        assert_eq!(0x37, data_payload.return_mode);
+        assert_eq!(0x59ba, data_payload.data_blocks[0].azimuth);
+        payload[PACKET_HEADER_LEN + wire::data_block(11).offset] = 0;
+        assert_eq!(Err(ParseError::InvalidStartIdentifier(0xee00)),
+                   DataPayload::parse(&payload[PACKET_HEADER_LEN..]));
+    }
+
+    #[test]
+    fn position_payload() {
+        let payload = &VLP_16_POSITION_PACKET[PACKET_HEADER_LEN..];
+        let position_payload = PositionPayload::parse(payload).unwrap();
+        assert_eq!(wire::NMEA.len, position_payload.nmea.len());
+        let short = PositionPayload::parse(&payload[..wire::NMEA.offset]).unwrap();
+        assert!(short.nmea.is_empty());
+        assert_eq!(position_payload.timestamp, short.timestamp);
+        assert_eq!(Err(ParseError::TooShort(205)), PositionPayload::parse(&payload[..205]));
+    }
+}
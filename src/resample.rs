@@ -0,0 +1,250 @@
+//! Resample frames onto a fixed angular grid.
+//!
+//! Some consumers, e.g. neural networks with a fixed input topology and grid-based compression
+//! schemes, need every frame to be the same size. A `Resampler` bins a frame's points into one row
+//! per ring and one column per azimuth bin, keeping one point per cell:
+//!
+//! ```
+//! use velodyne::fixtures::VLP_16_DATA_PACKET;
+//! use velodyne::frame::Frame;
+//! use velodyne::resample::Resampler;
+//! use velodyne::units::Degrees;
+//! use velodyne::vlp_16::Packet;
+//! let frame = Frame::new(Packet::new(&VLP_16_DATA_PACKET).unwrap().points().unwrap());
+//! let grid = Resampler::new(Degrees(0.2)).resample(&frame);
+//! assert_eq!(16 * 1800, grid.cells().len());
+//! ```
+
+use Point;
+use frame::Frame;
+use units::Degrees;
+use vlp_16::NUM_LASERS;
+
+/// Which point a cell keeps when more than one point falls in it.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Selection {
+    /// Keep the point closest to the center of the cell's azimuth bin.
+    #[default]
+    Nearest,
+    /// Keep the point with the highest reflectivity, or the first of them if there's a tie.
+    Strongest,
+}
+
+/// Bins points into a fixed grid of rings and azimuths.
+#[derive(Clone, Copy, Debug)]
+pub struct Resampler {
+    columns: usize,
+    selection: Selection,
+}
+
+/// A resampled frame, with one cell per ring and azimuth bin.
+#[derive(Clone, Debug)]
+pub struct Grid {
+    columns: usize,
+    cells: Vec<Option<Point>>,
+}
+
+impl Resampler {
+    /// Creates a resampler with azimuth bins of about the given width.
+    ///
+    /// The width is rounded so that a whole number of bins covers a full turn.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the resolution isn't positive, or is more than a full turn.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use velodyne::resample::Resampler;
+    /// use velodyne::units::Degrees;
+    /// assert_eq!(1800, Resampler::new(Degrees(0.2)).columns());
+    /// ```
+    pub fn new(resolution: Degrees) -> Resampler {
+        assert!(resolution.0 > 0. && resolution.0 <= 360.,
+                "invalid angular resolution: {}",
+                resolution.0);
+        Resampler {
+            columns: (360. / resolution.0).round() as usize,
+            selection: Selection::default(),
+        }
+    }
+
+    /// Sets which point a cell keeps.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use velodyne::resample::{Resampler, Selection};
+    /// use velodyne::units::Degrees;
+    /// let mut resampler = Resampler::new(Degrees(0.2));
+    /// resampler.selection(Selection::Strongest);
+    /// ```
+    pub fn selection(&mut self, selection: Selection) -> &mut Resampler {
+        self.selection = selection;
+        self
+    }
+
+    /// Returns the number of azimuth bins.
+    pub fn columns(&self) -> usize {
+        self.columns
+    }
+
+    /// Resamples a frame.
+    ///
+    /// Points without a return, and points from rings that the VLP-16 doesn't have, are skipped,
+    /// so their cells can be empty.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use velodyne::frame::Frame;
+    /// use velodyne::resample::Resampler;
+    /// use velodyne::units::Degrees;
+    /// let grid = Resampler::new(Degrees(1.)).resample(&Frame::default());
+    /// assert!(grid.cells().iter().all(Option::is_none));
+    /// ```
+    pub fn resample(&self, frame: &Frame) -> Grid {
+        self.resample_points(&frame.points)
+    }
+
+    /// Resamples points, e.g. the points of a packet.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use velodyne::fixtures::VLP_16_DATA_PACKET;
+    /// use velodyne::resample::Resampler;
+    /// use velodyne::units::Degrees;
+    /// use velodyne::vlp_16::Packet;
+    /// let points = Packet::new(&VLP_16_DATA_PACKET).unwrap().points().unwrap();
+    /// let grid = Resampler::new(Degrees(0.2)).resample_points(&points);
+    /// assert!(grid.len() < points.len());
+    /// ```
+    pub fn resample_points(&self, points: &[Point]) -> Grid {
+        let width = 360. / self.columns as f32;
+        let mut grid = Grid {
+            columns: self.columns,
+            cells: vec![None; NUM_LASERS * self.columns],
+        };
+        for point in points {
+            let channel = point.channel as usize;
+            if channel >= NUM_LASERS || point.range() == 0. {
+                continue;
+            }
+            let azimuth = point.azimuth.degrees();
+            let column = (azimuth / width) as usize % self.columns;
+            let cell = &mut grid.cells[channel * self.columns + column];
+            let replace = match *cell {
+                None => true,
+                Some(ref kept) => {
+                    match self.selection {
+                        Selection::Nearest => {
+                            let center = (column as f32 + 0.5) * width;
+                            (azimuth - center).abs() < (kept.azimuth.degrees() - center).abs()
+                        }
+                        Selection::Strongest => point.reflectivity > kept.reflectivity,
+                    }
+                }
+            };
+            if replace {
+                *cell = Some(*point);
+            }
+        }
+        grid
+    }
+}
+
+impl Grid {
+    /// Returns the number of rows, one per ring.
+    pub fn rows(&self) -> usize {
+        NUM_LASERS
+    }
+
+    /// Returns the number of azimuth bins.
+    pub fn columns(&self) -> usize {
+        self.columns
+    }
+
+    /// Returns the point in a ring's azimuth bin, or `None` if the cell is empty.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the ring or column is out of bounds.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use velodyne::frame::Frame;
+    /// use velodyne::resample::Resampler;
+    /// use velodyne::units::Degrees;
+    /// let grid = Resampler::new(Degrees(1.)).resample(&Frame::default());
+    /// assert!(grid.get(15, 359).is_none());
+    /// ```
+    pub fn get(&self, ring: usize, column: usize) -> Option<&Point> {
+        assert!(ring < NUM_LASERS && column < self.columns);
+        self.cells[ring * self.columns + column].as_ref()
+    }
+
+    /// Returns every cell, ring by ring, which is always `rows() * columns()` cells.
+    pub fn cells(&self) -> &[Option<Point>] {
+        &self.cells
+    }
+
+    /// Returns the number of cells with a point.
+    pub fn len(&self) -> usize {
+        self.cells.iter().filter(|cell| cell.is_some()).count()
+    }
+
+    /// Returns true if no cell has a point.
+    pub fn is_empty(&self) -> bool {
+        self.cells.iter().all(Option::is_none)
+    }
+
+    /// Returns the points of the occupied cells, ring by ring.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use velodyne::fixtures::VLP_16_DATA_PACKET;
+    /// use velodyne::resample::Resampler;
+    /// use velodyne::units::Degrees;
+    /// use velodyne::vlp_16::Packet;
+    /// let points = Packet::new(&VLP_16_DATA_PACKET).unwrap().points().unwrap();
+    /// let grid = Resampler::new(Degrees(0.2)).resample_points(&points);
+    /// assert_eq!(grid.len(), grid.points().len());
+    /// ```
+    pub fn points(&self) -> Vec<Point> {
+        self.cells.iter().filter_map(|cell| *cell).collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::Duration;
+    use point::{Azimuth, ReturnType, Time};
+    use units::Meters;
+
+    fn point(azimuth: f32, reflectivity: u8) -> Point {
+        Point::from_spherical(Meters(10.),
+                              Azimuth::Interpolated(Degrees(azimuth)),
+                              Degrees(0.),
+                              reflectivity,
+                              3,
+                              ReturnType::Strongest,
+                              Time::Offset(Duration::zero()))
+    }
+
+    #[test]
+    fn selection() {
+        let points = [point(10.05, 50), point(10.11, 10), point(10.19, 90), point(359.9, 1)];
+        let mut resampler = Resampler::new(Degrees(0.2));
+        let grid = resampler.resample_points(&points);
+        assert_eq!(2, grid.len());
+        assert_eq!(10, grid.get(3, 50).unwrap().reflectivity);
+        assert_eq!(1, grid.get(3, 1799).unwrap().reflectivity);
+        let grid = resampler.selection(Selection::Strongest).resample_points(&points);
+        assert_eq!(90, grid.get(3, 50).unwrap().reflectivity);
+    }
+}
@@ -0,0 +1,304 @@
+//! Serialize points in the layout of a ROS `sensor_msgs/PointCloud2` message.
+//!
+//! This doesn't depend on any ROS libraries. The resulting `PointCloud2` holds the message's
+//! fields (other than the header), so a bridge can copy them straight into a ROS1 or ROS2 message.
+
+use byteorder::{ByteOrder, LittleEndian};
+use point::{Point, Time};
+
+/// `sensor_msgs/PointField` datatype for `uint16`.
+pub const UINT16: u8 = 4;
+/// `sensor_msgs/PointField` datatype for `float32`.
+pub const FLOAT32: u8 = 7;
+
+/// A point attribute that can be serialized.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Field {
+    /// The x coordinate, as a `float32` in meters.
+    X,
+    /// The y coordinate, as a `float32` in meters.
+    Y,
+    /// The z coordinate, as a `float32` in meters.
+    Z,
+    /// The calibrated reflectivity, as a `float32`.
+    Intensity,
+    /// The laser channel, as a `uint16`.
+    Ring,
+    /// The time since the first point, as a `float32` in seconds.
+    Time,
+}
+
+/// The description of one field, as in `sensor_msgs/PointField`.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct PointField {
+    /// The name of the field.
+    pub name: &'static str,
+    /// The offset of the field from the start of a point, in bytes.
+    pub offset: u32,
+    /// The datatype of the field, e.g. `FLOAT32`.
+    pub datatype: u8,
+    /// The number of elements in the field.
+    pub count: u32,
+}
+
+/// The body of a `sensor_msgs/PointCloud2` message.
+#[derive(Clone, Debug, PartialEq)]
+pub struct PointCloud2 {
+    /// The height of the cloud, always one since Velodyne clouds are unordered.
+    pub height: u32,
+    /// The number of points.
+    pub width: u32,
+    /// The layout of each point.
+    pub fields: Vec<PointField>,
+    /// Whether the data is big-endian, always false.
+    pub is_bigendian: bool,
+    /// The length of one point, in bytes.
+    pub point_step: u32,
+    /// The length of one row, in bytes.
+    pub row_step: u32,
+    /// The point data.
+    pub data: Vec<u8>,
+    /// Whether all points are finite.
+    pub is_dense: bool,
+}
+
+/// Serializes points into `PointCloud2` data.
+#[derive(Clone, Debug)]
+pub struct Serializer {
+    fields: Vec<Field>,
+}
+
+impl Field {
+    /// Returns the name of this field, as used by the ROS Velodyne driver.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use velodyne::ros::Field;
+    /// assert_eq!("ring", Field::Ring.name());
+    /// ```
+    pub fn name(&self) -> &'static str {
+        match *self {
+            Field::X => "x",
+            Field::Y => "y",
+            Field::Z => "z",
+            Field::Intensity => "intensity",
+            Field::Ring => "ring",
+            Field::Time => "time",
+        }
+    }
+
+    /// Returns the `sensor_msgs/PointField` datatype of this field.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use velodyne::ros::{FLOAT32, Field};
+    /// assert_eq!(FLOAT32, Field::X.datatype());
+    /// ```
+    pub fn datatype(&self) -> u8 {
+        match *self {
+            Field::Ring => UINT16,
+            _ => FLOAT32,
+        }
+    }
+
+    /// Returns the size of this field, in bytes.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use velodyne::ros::Field;
+    /// assert_eq!(2, Field::Ring.size());
+    /// ```
+    pub fn size(&self) -> usize {
+        match *self {
+            Field::Ring => 2,
+            _ => 4,
+        }
+    }
+}
+
+impl Serializer {
+    /// Creates a new serializer with the fields used by the ROS Velodyne driver.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use velodyne::ros::Serializer;
+    /// let serializer = Serializer::new();
+    /// assert_eq!(22, serializer.point_step());
+    /// ```
+    pub fn new() -> Serializer {
+        Serializer::default()
+    }
+
+    /// Sets the fields, in the order they are laid out.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use velodyne::ros::{Field, Serializer};
+    /// let mut serializer = Serializer::new();
+    /// serializer.fields(&[Field::X, Field::Y, Field::Z]);
+    /// assert_eq!(12, serializer.point_step());
+    /// ```
+    pub fn fields(&mut self, fields: &[Field]) -> &mut Serializer {
+        self.fields = fields.to_vec();
+        self
+    }
+
+    /// Returns the length of one point, in bytes.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use velodyne::ros::Serializer;
+    /// assert_eq!(22, Serializer::new().point_step());
+    /// ```
+    pub fn point_step(&self) -> usize {
+        self.fields.iter().map(Field::size).sum()
+    }
+
+    /// Returns the layout of a point.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use velodyne::ros::Serializer;
+    /// let layout = Serializer::new().layout();
+    /// assert_eq!("intensity", layout[3].name);
+    /// assert_eq!(12, layout[3].offset);
+    /// ```
+    pub fn layout(&self) -> Vec<PointField> {
+        let mut offset = 0;
+        self.fields
+            .iter()
+            .map(|field| {
+                     let point_field = PointField {
+                         name: field.name(),
+                         offset: offset as u32,
+                         datatype: field.datatype(),
+                         count: 1,
+                     };
+                     offset += field.size();
+                     point_field
+                 })
+            .collect()
+    }
+
+    /// Serializes points into a new cloud.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use velodyne::ros::Serializer;
+    /// use velodyne::fixtures::VLP_16_DATA_PACKET;
+    /// use velodyne::vlp_16::Packet;
+    /// let points = Packet::new(&VLP_16_DATA_PACKET).unwrap().points().unwrap();
+    /// let cloud = Serializer::new().serialize(&points);
+    /// assert_eq!(384, cloud.width);
+    /// assert_eq!(384 * 22, cloud.data.len());
+    /// ```
+    pub fn serialize(&self, points: &[Point]) -> PointCloud2 {
+        let mut data = Vec::new();
+        self.serialize_into(points, &mut data);
+        let point_step = self.point_step() as u32;
+        PointCloud2 {
+            height: 1,
+            width: points.len() as u32,
+            fields: self.layout(),
+            is_bigendian: false,
+            point_step,
+            row_step: point_step * points.len() as u32,
+            data,
+            is_dense: points.iter().all(|p| p.x.is_finite() && p.y.is_finite() && p.z.is_finite()),
+        }
+    }
+
+    /// Serializes points into an existing buffer, replacing its contents.
+    ///
+    /// Use this to reuse one allocation across frames.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use velodyne::ros::Serializer;
+    /// # use velodyne::fixtures::VLP_16_DATA_PACKET;
+    /// # use velodyne::vlp_16::Packet;
+    /// let points = Packet::new(&VLP_16_DATA_PACKET).unwrap().points().unwrap();
+    /// let mut data = Vec::new();
+    /// Serializer::new().serialize_into(&points, &mut data);
+    /// assert_eq!(384 * 22, data.len());
+    /// ```
+    pub fn serialize_into(&self, points: &[Point], data: &mut Vec<u8>) {
+        let point_step = self.point_step();
+        data.clear();
+        data.resize(point_step * points.len(), 0);
+        let start = points.first().map(|point| point.time);
+        for (point, buf) in points.iter().zip(data.chunks_mut(point_step)) {
+            let mut offset = 0;
+            for field in &self.fields {
+                let buf = &mut buf[offset..];
+                match *field {
+                    Field::X => LittleEndian::write_f32(buf, point.x),
+                    Field::Y => LittleEndian::write_f32(buf, point.y),
+                    Field::Z => LittleEndian::write_f32(buf, point.z),
+                    Field::Intensity => LittleEndian::write_f32(buf, f32::from(point.reflectivity)),
+                    Field::Ring => LittleEndian::write_u16(buf, u16::from(point.channel)),
+                    Field::Time => {
+                        LittleEndian::write_f32(buf, seconds_since(start, point.time))
+                    }
+                }
+                offset += field.size();
+            }
+        }
+    }
+}
+
+impl Default for Serializer {
+    fn default() -> Serializer {
+        Serializer {
+            fields: vec![Field::X, Field::Y, Field::Z, Field::Intensity, Field::Ring, Field::Time],
+        }
+    }
+}
+
+fn seconds_since(start: Option<Time>, time: Time) -> f32 {
+    let elapsed = match (start, time) {
+        (Some(Time::Offset(start)), Time::Offset(time)) => time - start,
+        (Some(Time::Absolute(start)), Time::Absolute(time)) => time.signed_duration_since(start),
+        _ => return 0.,
+    };
+    elapsed.num_microseconds().unwrap_or(0) as f32 / 1e6
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use fixtures::VLP_16_DATA_PACKET;
+    use vlp_16::Packet;
+
+    #[test]
+    fn layout() {
+        let points = Packet::new(&VLP_16_DATA_PACKET).unwrap().points().unwrap();
+        let cloud = Serializer::new().serialize(&points);
+        assert!(cloud.is_dense);
+        assert_eq!(cloud.point_step * cloud.width, cloud.row_step);
+        let point = &cloud.data[22..44];
+        assert_eq!(points[1].x, LittleEndian::read_f32(&point[0..]));
+        assert_eq!(points[1].z, LittleEndian::read_f32(&point[8..]));
+        assert_eq!(f32::from(points[1].reflectivity), LittleEndian::read_f32(&point[12..]));
+        assert_eq!(u16::from(points[1].channel), LittleEndian::read_u16(&point[16..]));
+        assert!(LittleEndian::read_f32(&point[18..]) > 0.);
+    }
+
+    #[test]
+    fn custom_fields() {
+        let points = Packet::new(&VLP_16_DATA_PACKET).unwrap().points().unwrap();
+        let cloud = Serializer::new().fields(&[Field::Ring, Field::X]).serialize(&points);
+        assert_eq!(6, cloud.point_step);
+        assert_eq!(2, cloud.fields[1].offset);
+        assert_eq!(points[3].x, LittleEndian::read_f32(&cloud.data[20..]));
+    }
+}
@@ -0,0 +1,500 @@
+//! Configure a sensor through its web interface.
+//!
+//! VLP-16s serve a small HTTP interface for reading status and changing settings such as the
+//! motor speed, field of view, return mode, and destination host. This is a minimal client for
+//! that interface, so capture tools can set up a sensor before recording.
+
+use {Error, Result};
+use rustc_serialize::json::Json;
+use std::io::{Read, Write};
+use std::net::{Ipv4Addr, SocketAddr, TcpStream, ToSocketAddrs};
+use std::time::Duration;
+use vlp_16::ReturnMode;
+
+const DEFAULT_TIMEOUT_SECS: u64 = 5;
+const ADC_VOLTS_PER_COUNT: f32 = 5. / 4096.;
+
+/// A client for a sensor's web interface.
+#[derive(Clone, Copy, Debug)]
+pub struct Client {
+    address: SocketAddr,
+    timeout: Duration,
+}
+
+/// The sensor's current settings, as reported by `settings.json`.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Settings {
+    /// Is the laser on?
+    pub laser: bool,
+    /// The return mode.
+    pub return_mode: ReturnMode,
+    /// The configured motor speed, in rotations per minute.
+    pub rpm: u16,
+    /// The start of the field of view, in degrees.
+    pub fov_start: u16,
+    /// The end of the field of view, in degrees.
+    pub fov_end: u16,
+    /// The destination address for data and position packets.
+    pub host: Host,
+}
+
+/// Where the sensor sends its packets.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Host {
+    /// The destination address.
+    pub address: Ipv4Addr,
+    /// The destination port for data packets.
+    pub data_port: u16,
+    /// The destination port for position (telemetry) packets.
+    pub telemetry_port: u16,
+}
+
+/// The sensor's current status, as reported by `status.json`.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Status {
+    /// Is the laser on?
+    pub laser: bool,
+    /// Is the motor on?
+    pub motor: bool,
+    /// The measured motor speed, in rotations per minute.
+    pub rpm: u16,
+    /// The state of the GPS pulse-per-second signal, e.g. "Locked" or "Absent".
+    pub pps_state: String,
+}
+
+/// Voltage, current, and temperature diagnostics, as reported by `diag.json`.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Diagnostics {
+    /// Measurements from the top board, which holds the lasers.
+    pub top: TopBoard,
+    /// Measurements from the bottom board, which holds the motor controller and power supply.
+    pub bottom: BottomBoard,
+}
+
+/// Diagnostics from the sensor's top board.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct TopBoard {
+    /// The laser high voltage, in volts.
+    pub high_voltage: f32,
+    /// The board temperature, in degrees Celsius.
+    pub temperature: f32,
+    /// The 5V supply, in volts.
+    pub supply_5v: f32,
+    /// The 3.3V supply, in volts.
+    pub supply_3_3v: f32,
+    /// The 2.5V supply, in volts.
+    pub supply_2_5v: f32,
+    /// The unregulated supply, in volts.
+    pub supply_raw: f32,
+    /// The FPGA core voltage, in volts.
+    pub vccint: f32,
+}
+
+/// Diagnostics from the sensor's bottom board.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct BottomBoard {
+    /// The current draw, in amps.
+    pub output_current: f32,
+    /// The board temperature, in degrees Celsius.
+    pub temperature: f32,
+    /// The 5V supply, in volts.
+    pub supply_5v: f32,
+    /// The 3.3V supply, in volts.
+    pub supply_3_3v: f32,
+    /// The 2.5V supply, in volts.
+    pub supply_2_5v: f32,
+    /// The 1.25V supply, in volts.
+    pub supply_1_25v: f32,
+    /// The 1.2V supply, in volts.
+    pub supply_1_2v: f32,
+    /// The input voltage to the sensor, in volts.
+    pub input_voltage: f32,
+}
+
+impl Client {
+    /// Creates a new client for the sensor at the given address.
+    ///
+    /// The address should include the port, which is usually 80.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use velodyne::sensor_http::Client;
+    /// let client = Client::new("192.168.1.201:80").unwrap();
+    /// ```
+    pub fn new<A: ToSocketAddrs>(address: A) -> Result<Client> {
+        let address = address.to_socket_addrs()?
+            .next()
+            .ok_or_else(|| Error::Http("no address for sensor".to_string()))?;
+        Ok(Client {
+               address,
+               timeout: Duration::from_secs(DEFAULT_TIMEOUT_SECS),
+           })
+    }
+
+    /// Sets the connect, read, and write timeout for requests.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use velodyne::sensor_http::Client;
+    /// use std::time::Duration;
+    /// let mut client = Client::new("192.168.1.201:80").unwrap();
+    /// client.timeout(Duration::from_secs(1));
+    /// ```
+    pub fn timeout(&mut self, timeout: Duration) -> &mut Client {
+        self.timeout = timeout;
+        self
+    }
+
+    /// Reads the sensor's current settings.
+    pub fn settings(&self) -> Result<Settings> {
+        self.get("/cgi/settings.json").and_then(|body| Settings::from_json(&body))
+    }
+
+    /// Reads the sensor's current status.
+    pub fn status(&self) -> Result<Status> {
+        self.get("/cgi/status.json").and_then(|body| Status::from_json(&body))
+    }
+
+    /// Reads the sensor's voltage, current, and temperature diagnostics.
+    pub fn diagnostics(&self) -> Result<Diagnostics> {
+        self.get("/cgi/diag.json").and_then(|body| Diagnostics::from_json(&body))
+    }
+
+    /// Sets the motor speed, in rotations per minute.
+    ///
+    /// The VLP-16 supports 300 to 1200 RPM in increments of 60.
+    pub fn set_rpm(&self, rpm: u16) -> Result<()> {
+        self.post("/cgi/setting", &format!("rpm={}", rpm))
+    }
+
+    /// Turns the laser on or off.
+    pub fn set_laser(&self, on: bool) -> Result<()> {
+        self.post("/cgi/setting",
+                  &format!("laser={}", if on { "on" } else { "off" }))
+    }
+
+    /// Sets the return mode.
+    pub fn set_return_mode(&self, return_mode: ReturnMode) -> Result<()> {
+        let returns = match return_mode {
+            ReturnMode::StrongestReturn => "Strongest",
+            ReturnMode::LastReturn => "Last",
+            ReturnMode::DualReturn => "Dual",
+            ReturnMode::Unknown(n) => return Err(Error::InvalidReturnMode(n)),
+        };
+        self.post("/cgi/setting", &format!("returns={}", returns))
+    }
+
+    /// Sets the field of view, in degrees.
+    pub fn set_fov(&self, start: u16, end: u16) -> Result<()> {
+        self.post("/cgi/setting/fov", &format!("start={}&end={}", start, end))
+    }
+
+    /// Sets where the sensor sends its packets.
+    pub fn set_host(&self, host: Host) -> Result<()> {
+        self.post("/cgi/setting/host",
+                  &format!("addr={}&dport={}&tport={}",
+                           host.address,
+                           host.data_port,
+                           host.telemetry_port))
+    }
+
+    /// Saves the current settings so they persist across power cycles.
+    pub fn save(&self) -> Result<()> {
+        self.post("/cgi/save", "submit")
+    }
+
+    fn get(&self, path: &str) -> Result<String> {
+        self.request("GET", path, None)
+    }
+
+    fn post(&self, path: &str, body: &str) -> Result<()> {
+        self.request("POST", path, Some(body)).map(|_| ())
+    }
+
+    fn request(&self, method: &str, path: &str, body: Option<&str>) -> Result<String> {
+        let mut stream = TcpStream::connect_timeout(&self.address, self.timeout)?;
+        stream.set_read_timeout(Some(self.timeout))?;
+        stream.set_write_timeout(Some(self.timeout))?;
+        let mut request = format!("{} {} HTTP/1.0\r\nHost: {}\r\n", method, path, self.address);
+        if let Some(body) = body {
+            request.push_str(&format!("Content-Type: application/x-www-form-urlencoded\r\n\
+                                       Content-Length: {}\r\n\r\n{}",
+                                      body.len(),
+                                      body));
+        } else {
+            request.push_str("\r\n");
+        }
+        stream.write_all(request.as_bytes())?;
+        let mut response = String::new();
+        stream.read_to_string(&mut response)?;
+        let (head, body) = match response.find("\r\n\r\n") {
+            Some(index) => (&response[..index], &response[index + 4..]),
+            None => return Err(Error::Http("malformed HTTP response".to_string())),
+        };
+        let status_line = head.lines().next().unwrap_or("");
+        match status_line.split_whitespace().nth(1).and_then(|code| code.parse::<u16>().ok()) {
+            Some(code) if code < 400 => Ok(body.to_string()),
+            _ => Err(Error::Http(format!("{} {} failed: {}", method, path, status_line))),
+        }
+    }
+}
+
+impl Settings {
+    /// Parses settings from the contents of `settings.json`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use velodyne::sensor_http::Settings;
+    /// let json = r#"{"laser":"On","returns":"Strongest","rpm":600,"fov":{"start":0,"end":359},
+    ///     "host":{"addr":"255.255.255.255","dport":"2368","tport":"8308"}}"#;
+    /// let settings = Settings::from_json(json).unwrap();
+    /// assert_eq!(600, settings.rpm);
+    /// ```
+    pub fn from_json(json: &str) -> Result<Settings> {
+        let json = parse(json)?;
+        Ok(Settings {
+               laser: on_off(&json, &["laser"])?,
+               return_mode: return_mode(string(&json, &["returns"])?)?,
+               rpm: number(&json, &["rpm"])?,
+               fov_start: number(&json, &["fov", "start"])?,
+               fov_end: number(&json, &["fov", "end"])?,
+               host: Host {
+                   address: string(&json, &["host", "addr"])?
+                       .parse()
+                       .map_err(|_| field_error(&["host", "addr"]))?,
+                   data_port: number(&json, &["host", "dport"])?,
+                   telemetry_port: number(&json, &["host", "tport"])?,
+               },
+           })
+    }
+}
+
+impl Status {
+    /// Parses status from the contents of `status.json`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use velodyne::sensor_http::Status;
+    /// let json = r#"{"gps":{"pps_state":"Locked"},"motor":{"state":"On","rpm":602},
+    ///     "laser":{"state":"On"}}"#;
+    /// let status = Status::from_json(json).unwrap();
+    /// assert_eq!(602, status.rpm);
+    /// ```
+    pub fn from_json(json: &str) -> Result<Status> {
+        let json = parse(json)?;
+        Ok(Status {
+               laser: on_off(&json, &["laser", "state"])?,
+               motor: on_off(&json, &["motor", "state"])?,
+               rpm: number(&json, &["motor", "rpm"])?,
+               pps_state: string(&json, &["gps", "pps_state"])?.to_string(),
+           })
+    }
+}
+
+impl Diagnostics {
+    /// Parses diagnostics from the contents of `diag.json`.
+    ///
+    /// The sensor reports raw 12-bit ADC counts, which are converted to engineering units using
+    /// the scale factors in the VLP-16 manual.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use velodyne::sensor_http::Diagnostics;
+    /// let json = r#"{"volt_temp":{
+    ///     "top":{"hv":2998,"lm20_temp":1273,"pwr_5v":2087,"pwr_2_5v":2042,"pwr_3_3v":2694,
+    ///            "pwr_raw":1594,"pwr_vccint":983},
+    ///     "bot":{"i_out":2378,"pwr_1_2v":983,"lm20_temp":1245,"pwr_5v":2080,"pwr_2_5v":2042,
+    ///            "pwr_3_3v":2696,"pwr_v_in":1052,"pwr_1_25v":1020}}}"#;
+    /// let diagnostics = Diagnostics::from_json(json).unwrap();
+    /// assert!((diagnostics.top.temperature - 26.7).abs() < 0.1);
+    /// ```
+    pub fn from_json(json: &str) -> Result<Diagnostics> {
+        let json = parse(json)?;
+        let top = |field| adc_volts(&json, &["volt_temp", "top", field]);
+        let bottom = |field| adc_volts(&json, &["volt_temp", "bot", field]);
+        Ok(Diagnostics {
+               top: TopBoard {
+                   high_voltage: 101. * (top("hv")? - 5.),
+                   temperature: lm20_temperature(top("lm20_temp")?),
+                   supply_5v: 2. * top("pwr_5v")?,
+                   supply_3_3v: top("pwr_3_3v")?,
+                   supply_2_5v: top("pwr_2_5v")?,
+                   supply_raw: top("pwr_raw")?,
+                   vccint: top("pwr_vccint")?,
+               },
+               bottom: BottomBoard {
+                   output_current: 10. * (bottom("i_out")? - 2.5),
+                   temperature: lm20_temperature(bottom("lm20_temp")?),
+                   supply_5v: 2. * bottom("pwr_5v")?,
+                   supply_3_3v: bottom("pwr_3_3v")?,
+                   supply_2_5v: bottom("pwr_2_5v")?,
+                   supply_1_25v: bottom("pwr_1_25v")?,
+                   supply_1_2v: bottom("pwr_1_2v")?,
+                   input_voltage: 11. * bottom("pwr_v_in")?,
+               },
+           })
+    }
+}
+
+fn parse(json: &str) -> Result<Json> {
+    Json::from_str(json).map_err(|err| Error::Http(format!("invalid JSON: {}", err)))
+}
+
+fn adc_volts(json: &Json, path: &[&str]) -> Result<f32> {
+    json.find_path(path)
+        .and_then(|json| json.as_f64())
+        .map(|counts| counts as f32 * ADC_VOLTS_PER_COUNT)
+        .ok_or_else(|| field_error(path))
+}
+
+fn lm20_temperature(volts: f32) -> f32 {
+    -1481.96 + (2.1962e6 + (1.8639 - volts) / 3.88e-6).sqrt()
+}
+
+fn field_error(path: &[&str]) -> Error {
+    Error::Http(format!("missing or invalid field: {}", path.join(".")))
+}
+
+fn string<'a>(json: &'a Json, path: &[&str]) -> Result<&'a str> {
+    json.find_path(path).and_then(|json| json.as_string()).ok_or_else(|| field_error(path))
+}
+
+fn number(json: &Json, path: &[&str]) -> Result<u16> {
+    // Some firmware reports numbers as strings, e.g. `"dport": "2368"`.
+    json.find_path(path)
+        .and_then(|json| {
+                      json.as_u64()
+                          .or_else(|| json.as_string().and_then(|s| s.parse().ok()))
+                  })
+        .and_then(|n| if n <= u64::from(u16::MAX) {
+                      Some(n as u16)
+                  } else {
+                      None
+                  })
+        .ok_or_else(|| field_error(path))
+}
+
+fn on_off(json: &Json, path: &[&str]) -> Result<bool> {
+    match string(json, path)?.to_lowercase().as_str() {
+        "on" => Ok(true),
+        "off" => Ok(false),
+        _ => Err(field_error(path)),
+    }
+}
+
+fn return_mode(returns: &str) -> Result<ReturnMode> {
+    match returns {
+        "Strongest" => Ok(ReturnMode::StrongestReturn),
+        "Last" => Ok(ReturnMode::LastReturn),
+        "Dual" => Ok(ReturnMode::DualReturn),
+        _ => Err(field_error(&["returns"])),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::{BufRead, BufReader};
+    use std::net::TcpListener;
+    use std::thread::{self, JoinHandle};
+
+    const SETTINGS: &str = r#"{"laser":"On","returns":"Strongest","rpm":600,
+        "fov":{"start":0,"end":359},
+        "host":{"addr":"255.255.255.255","dport":"2368","tport":"8308"}}"#;
+    const STATUS: &str = r#"{"gps":{"pps_state":"Locked"},
+        "motor":{"state":"On","rpm":602},"laser":{"state":"On"}}"#;
+
+    const DIAG: &str = r#"{"volt_temp":{
+        "top":{"hv":2998,"lm20_temp":1273,"pwr_5v":2087,"pwr_2_5v":2042,"pwr_3_3v":2694,
+               "pwr_raw":1594,"pwr_vccint":983},
+        "bot":{"i_out":2378,"pwr_1_2v":983,"lm20_temp":1245,"pwr_5v":2080,"pwr_2_5v":2042,
+               "pwr_3_3v":2696,"pwr_v_in":1052,"pwr_1_25v":1020}}}"#;
+
+    fn serve(status: &'static str, body: &'static str) -> (Client, JoinHandle<String>) {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let client = Client::new(listener.local_addr().unwrap()).unwrap();
+        let handle = thread::spawn(move || {
+            let (stream, _) = listener.accept().unwrap();
+            let mut reader = BufReader::new(stream);
+            let mut request_line = String::new();
+            reader.read_line(&mut request_line).unwrap();
+            let mut content_length = 0;
+            loop {
+                let mut line = String::new();
+                reader.read_line(&mut line).unwrap();
+                if line == "\r\n" {
+                    break;
+                }
+                if let Some(value) = line.strip_prefix("Content-Length: ") {
+                    content_length = value.trim().parse().unwrap();
+                }
+            }
+            let mut request_body = vec![0; content_length];
+            reader.read_exact(&mut request_body).unwrap();
+            write!(reader.get_mut(), "HTTP/1.0 {}\r\n\r\n{}", status, body).unwrap();
+            format!("{}{}",
+                    request_line.trim_end(),
+                    String::from_utf8(request_body).unwrap())
+        });
+        (client, handle)
+    }
+
+    #[test]
+    fn settings() {
+        let (client, handle) = serve("200 OK", SETTINGS);
+        let settings = client.settings().unwrap();
+        assert_eq!("GET /cgi/settings.json HTTP/1.0", handle.join().unwrap());
+        assert!(settings.laser);
+        assert_eq!(ReturnMode::StrongestReturn, settings.return_mode);
+        assert_eq!(600, settings.rpm);
+        assert_eq!(359, settings.fov_end);
+        assert_eq!(Ipv4Addr::new(255, 255, 255, 255), settings.host.address);
+        assert_eq!(2368, settings.host.data_port);
+        assert_eq!(8308, settings.host.telemetry_port);
+    }
+
+    #[test]
+    fn status() {
+        let (client, _) = serve("200 OK", STATUS);
+        let status = client.status().unwrap();
+        assert!(status.motor);
+        assert_eq!(602, status.rpm);
+        assert_eq!("Locked", status.pps_state);
+    }
+
+    #[test]
+    fn diagnostics() {
+        let (client, handle) = serve("200 OK", DIAG);
+        let diagnostics = client.diagnostics().unwrap();
+        assert_eq!("GET /cgi/diag.json HTTP/1.0", handle.join().unwrap());
+        assert!((diagnostics.top.high_voltage - -135.).abs() < 1.);
+        assert!((diagnostics.top.temperature - 26.7).abs() < 0.1);
+        assert!((diagnostics.bottom.temperature - 29.6).abs() < 0.1);
+        assert!((diagnostics.top.supply_5v - 5.10).abs() < 0.01);
+        assert!((diagnostics.bottom.output_current - 4.03).abs() < 0.01);
+        assert!((diagnostics.bottom.input_voltage - 14.13).abs() < 0.01);
+    }
+
+    #[test]
+    fn diagnostics_missing_field() {
+        assert!(Diagnostics::from_json(r#"{"volt_temp":{"top":{}}}"#).is_err());
+    }
+
+    #[test]
+    fn set_rpm() {
+        let (client, handle) = serve("200 OK", "");
+        client.set_rpm(900).unwrap();
+        assert_eq!("POST /cgi/setting HTTP/1.0rpm=900", handle.join().unwrap());
+    }
+
+    #[test]
+    fn error_status() {
+        let (client, _) = serve("404 Not Found", "");
+        assert!(client.set_fov(0, 180).is_err());
+    }
+}
@@ -0,0 +1,458 @@
+//! Metadata that travels next to a capture.
+//!
+//! A pcap holds the packets, but not how they should be decoded: the calibration, how the sensor
+//! was mounted, where its time came from, or what the operator noted about the recording. A
+//! sidecar is a small JSON file with that metadata, stored next to the capture as
+//! `<capture>.json`, so captures stay self-describing as they move between teams:
+//!
+//! ```json
+//! {
+//!   "calibration": {
+//!     "azimuth_offsets": [0.0, 0.1],
+//!     "distance_biases": [0.012, -0.004],
+//!     "elevations": []
+//!   },
+//!   "mounting": {
+//!     "rotation": [[1.0, 0.0, 0.0], [0.0, 1.0, 0.0], [0.0, 0.0, 1.0]],
+//!     "translation": [0.0, 0.0, 1.8]
+//!   },
+//!   "notes": "Parking lot, clear skies",
+//!   "return_mode": "strongest",
+//!   "rpm": 600.0,
+//!   "sensor": "VLP-16",
+//!   "time_reference": "gps",
+//!   "version": 1
+//! }
+//! ```
+//!
+//! Every field is optional. Angles are in degrees and distances in meters. Sensors and return
+//! modes that this crate doesn't know are written as their factory bytes. This module requires
+//! the `sidecar` feature. The CLI's `sidecar` command writes a capture's sidecar, and `anonymize`
+//! and `split` copy it next to the captures they write.
+
+use {Error, Result};
+use batch;
+use correction::RangeBias;
+use info::Scan;
+use rustc_serialize::json::Json;
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use time::{CaptureTime, GpsTime};
+use transform::Transform;
+use units::{Degrees, Meters};
+use vlp_16::{Decoder, ReturnMode, Sensor};
+
+const VERSION: u64 = 1;
+
+/// A capture's metadata.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Sidecar {
+    /// The sensor that recorded the capture.
+    pub sensor: Option<Sensor>,
+    /// The sensor's return mode.
+    pub return_mode: Option<ReturnMode>,
+    /// The sensor's rotation rate, in revolutions per minute.
+    pub rpm: Option<f64>,
+    /// The per-laser azimuth offsets, see `Decoder::azimuth_offsets`.
+    pub azimuth_offsets: Vec<Degrees>,
+    /// The per-laser elevations, see `Decoder::elevations`.
+    pub elevations: Vec<Degrees>,
+    /// The per-laser distance biases, see `correction::RangeBias`.
+    pub distance_biases: Vec<Meters>,
+    /// The sensor's pose in the vehicle's frame.
+    pub mounting: Option<Transform>,
+    /// Where the points' absolute times should come from.
+    pub time_reference: Option<TimeReference>,
+    /// Free-form notes from whoever recorded the capture.
+    pub notes: String,
+}
+
+/// Where a capture's absolute times come from.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum TimeReference {
+    /// Only the sensor's timestamps, which are offsets from the top of the hour.
+    Device,
+    /// The GNSS receiver's time, from the position packets. See `time::GpsTime`.
+    Gps,
+    /// The capture times of the packets. See `time::CaptureTime`.
+    Capture,
+}
+
+impl Sidecar {
+    /// Creates an empty sidecar.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use velodyne::sidecar::Sidecar;
+    /// let sidecar = Sidecar::new();
+    /// ```
+    pub fn new() -> Sidecar {
+        Sidecar::default()
+    }
+
+    /// Creates a sidecar with the sensor, return mode, and rotation rate of a scanned capture.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use velodyne::info::Scan;
+    /// use velodyne::io::{Pcap, Read};
+    /// use velodyne::sidecar::Sidecar;
+    /// use velodyne::vlp_16::Sensor;
+    /// let mut pcap = Pcap::open("data/single.pcap").unwrap();
+    /// let mut scan = Scan::default();
+    /// while let Some(bytes) = pcap.read() {
+    ///     scan.add(bytes.unwrap());
+    /// }
+    /// assert_eq!(Some(Sensor::VLP_16), Sidecar::from_scan(&scan).sensor);
+    /// ```
+    pub fn from_scan(scan: &Scan) -> Sidecar {
+        Sidecar {
+            sensor: scan.sensor,
+            return_mode: scan.return_mode,
+            rpm: scan.rpm(),
+            ..Sidecar::default()
+        }
+    }
+
+    /// Returns the path of a capture's sidecar, i.e. the capture's path with a `.json` extension.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use std::path::Path;
+    /// use velodyne::sidecar::Sidecar;
+    /// assert_eq!(Path::new("data/single.json"), Sidecar::path_for("data/single.pcap"));
+    /// ```
+    pub fn path_for<P: AsRef<Path>>(capture: P) -> PathBuf {
+        capture.as_ref().with_extension("json")
+    }
+
+    /// Reads a sidecar from a path.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use velodyne::sidecar::Sidecar;
+    /// assert!(Sidecar::from_path(Sidecar::path_for("data/single.pcap")).is_err());
+    /// ```
+    pub fn from_path<P: AsRef<Path>>(path: P) -> Result<Sidecar> {
+        Sidecar::from_json(&fs::read_to_string(path)?)
+    }
+
+    /// Writes this sidecar to a path.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// use velodyne::sidecar::Sidecar;
+    /// Sidecar::new().write_to_path(Sidecar::path_for("capture.pcap")).unwrap();
+    /// ```
+    pub fn write_to_path<P: AsRef<Path>>(&self, path: P) -> Result<()> {
+        fs::write(path, self.to_json() + "\n").map_err(Error::from)
+    }
+
+    /// Parses a sidecar.
+    ///
+    /// Fields that aren't part of the format are ignored, so newer sidecars can still be read.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use velodyne::sidecar::{Sidecar, TimeReference};
+    /// use velodyne::vlp_16::ReturnMode;
+    /// let json = r#"{"version": 1, "return_mode": "dual", "time_reference": "gps"}"#;
+    /// let sidecar = Sidecar::from_json(json).unwrap();
+    /// assert_eq!(Some(ReturnMode::DualReturn), sidecar.return_mode);
+    /// assert_eq!(Some(TimeReference::Gps), sidecar.time_reference);
+    /// ```
+    pub fn from_json(json: &str) -> Result<Sidecar> {
+        let json = Json::from_str(json).map_err(|err| invalid(&format!("invalid JSON: {}", err)))?;
+        if !json.is_object() {
+            return Err(invalid("not an object"));
+        }
+        match json.find("version").map(|version| version.as_u64()) {
+            None | Some(Some(VERSION)) => {}
+            Some(Some(version)) => return Err(invalid(&format!("unsupported version {}", version))),
+            Some(None) => return Err(field_error("version")),
+        }
+        let mut sidecar = Sidecar::new();
+        if let Some(sensor) = json.find("sensor") {
+            sidecar.sensor = Some(match (sensor.as_string(), sensor.as_u64()) {
+                (Some("HDL-32E"), _) => Sensor::HDL_32E,
+                (Some("VLP-16"), _) => Sensor::VLP_16,
+                (None, Some(n)) if n <= u64::from(u8::MAX) => {
+                    Sensor::from_u8(n as u8).unwrap_or(Sensor::Unknown(n as u8))
+                }
+                _ => return Err(field_error("sensor")),
+            });
+        }
+        if let Some(return_mode) = json.find("return_mode") {
+            sidecar.return_mode = Some(match (return_mode.as_string(), return_mode.as_u64()) {
+                (Some("strongest"), _) => ReturnMode::StrongestReturn,
+                (Some("last"), _) => ReturnMode::LastReturn,
+                (Some("dual"), _) => ReturnMode::DualReturn,
+                (None, Some(n)) if n <= u64::from(u8::MAX) => {
+                    ReturnMode::from_u8(n as u8).unwrap_or(ReturnMode::Unknown(n as u8))
+                }
+                _ => return Err(field_error("return_mode")),
+            });
+        }
+        if let Some(rpm) = json.find("rpm") {
+            sidecar.rpm = Some(rpm.as_f64().ok_or_else(|| field_error("rpm"))?);
+        }
+        if let Some(calibration) = json.find("calibration") {
+            let list = |field| -> Result<Vec<f32>> {
+                match calibration.find(field) {
+                    Some(json) => floats(json).ok_or_else(|| field_error(field)),
+                    None => Ok(Vec::new()),
+                }
+            };
+            sidecar.azimuth_offsets = list("azimuth_offsets")?.into_iter().map(Degrees).collect();
+            sidecar.elevations = list("elevations")?.into_iter().map(Degrees).collect();
+            sidecar.distance_biases = list("distance_biases")?.into_iter().map(Meters).collect();
+        }
+        if let Some(mounting) = json.find("mounting") {
+            let rows = mounting.find("rotation")
+                .and_then(|rotation| rotation.as_array())
+                .map(|rows| rows.iter().filter_map(floats).collect::<Vec<_>>());
+            let translation = mounting.find("translation").and_then(floats);
+            let mut transform = Transform::identity();
+            if let Some(rows) = rows {
+                let mut rotation = [[0.; 3]; 3];
+                if rows.len() != 3 || rows.iter().any(|row| row.len() != 3) {
+                    return Err(field_error("mounting.rotation"));
+                }
+                for (row, values) in rotation.iter_mut().zip(&rows) {
+                    row.copy_from_slice(values);
+                }
+                transform = Transform::new(rotation, transform.translation());
+            }
+            if let Some(translation) = translation {
+                if translation.len() != 3 {
+                    return Err(field_error("mounting.translation"));
+                }
+                transform = Transform::new(transform.rotation(),
+                                           [translation[0], translation[1], translation[2]]);
+            }
+            sidecar.mounting = Some(transform);
+        }
+        if let Some(time_reference) = json.find("time_reference") {
+            sidecar.time_reference = Some(match time_reference.as_string() {
+                Some("device") => TimeReference::Device,
+                Some("gps") => TimeReference::Gps,
+                Some("capture") => TimeReference::Capture,
+                _ => return Err(field_error("time_reference")),
+            });
+        }
+        if let Some(notes) = json.find("notes") {
+            sidecar.notes = notes.as_string().ok_or_else(|| field_error("notes"))?.to_string();
+        }
+        Ok(sidecar)
+    }
+
+    /// Returns this sidecar as pretty-printed JSON, leaving out the fields that aren't set.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use velodyne::sidecar::Sidecar;
+    /// use velodyne::vlp_16::Sensor;
+    /// let mut sidecar = Sidecar::new();
+    /// sidecar.sensor = Some(Sensor::VLP_16);
+    /// assert_eq!(sidecar, Sidecar::from_json(&sidecar.to_json()).unwrap());
+    /// ```
+    pub fn to_json(&self) -> String {
+        let mut object = BTreeMap::new();
+        object.insert("version".to_string(), Json::U64(VERSION));
+        if let Some(sensor) = self.sensor {
+            object.insert("sensor".to_string(),
+                          match sensor {
+                              Sensor::HDL_32E => Json::String("HDL-32E".to_string()),
+                              Sensor::VLP_16 => Json::String("VLP-16".to_string()),
+                              Sensor::Unknown(n) => Json::U64(u64::from(n)),
+                          });
+        }
+        if let Some(return_mode) = self.return_mode {
+            object.insert("return_mode".to_string(),
+                          match return_mode {
+                              ReturnMode::StrongestReturn => Json::String("strongest".to_string()),
+                              ReturnMode::LastReturn => Json::String("last".to_string()),
+                              ReturnMode::DualReturn => Json::String("dual".to_string()),
+                              ReturnMode::Unknown(n) => Json::U64(u64::from(n)),
+                          });
+        }
+        if let Some(rpm) = self.rpm {
+            object.insert("rpm".to_string(), Json::F64(rpm));
+        }
+        if !(self.azimuth_offsets.is_empty() && self.elevations.is_empty() &&
+             self.distance_biases.is_empty()) {
+            let mut calibration = BTreeMap::new();
+            let list = |values: Vec<f32>| Json::Array(values.into_iter().map(float).collect());
+            calibration.insert("azimuth_offsets".to_string(),
+                               list(self.azimuth_offsets.iter().map(|d| d.0).collect()));
+            calibration.insert("elevations".to_string(),
+                               list(self.elevations.iter().map(|d| d.0).collect()));
+            calibration.insert("distance_biases".to_string(),
+                               list(self.distance_biases.iter().map(|m| m.0).collect()));
+            object.insert("calibration".to_string(), Json::Object(calibration));
+        }
+        if let Some(mounting) = self.mounting {
+            let list = |values: &[f32]| Json::Array(values.iter().cloned().map(float).collect());
+            let mut object_mounting = BTreeMap::new();
+            object_mounting.insert("rotation".to_string(),
+                                   Json::Array(mounting.rotation()
+                                                   .iter()
+                                                   .map(|row| list(row))
+                                                   .collect()));
+            object_mounting.insert("translation".to_string(), list(&mounting.translation()));
+            object.insert("mounting".to_string(), Json::Object(object_mounting));
+        }
+        if let Some(time_reference) = self.time_reference {
+            let name = match time_reference {
+                TimeReference::Device => "device",
+                TimeReference::Gps => "gps",
+                TimeReference::Capture => "capture",
+            };
+            object.insert("time_reference".to_string(), Json::String(name.to_string()));
+        }
+        if !self.notes.is_empty() {
+            object.insert("notes".to_string(), Json::String(self.notes.clone()));
+        }
+        Json::Object(object).pretty().to_string()
+    }
+
+    /// Returns a decoder configured by this sidecar.
+    ///
+    /// The calibration, mounting pose, return mode, and time reference are all applied. The
+    /// sensor and rotation rate are informational.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use velodyne::fixtures::VLP_16_DATA_PACKET;
+    /// use velodyne::sidecar::Sidecar;
+    /// use velodyne::transform::Transform;
+    /// use velodyne::vlp_16::Packet;
+    /// let mut sidecar = Sidecar::new();
+    /// sidecar.mounting = Some(Transform::from_euler(0., 0., 0., [0., 0., 1.8]));
+    /// let mut decoder = sidecar.decoder();
+    /// decoder.push(Packet::new(&VLP_16_DATA_PACKET).unwrap());
+    /// let points = decoder.finish().unwrap();
+    /// ```
+    pub fn decoder(&self) -> Decoder {
+        let mut decoder = Decoder::new();
+        if let Some(return_mode) = self.return_mode {
+            decoder.return_mode(return_mode);
+        }
+        if !self.azimuth_offsets.is_empty() {
+            decoder.azimuth_offsets(&self.azimuth_offsets);
+        }
+        if !self.elevations.is_empty() {
+            decoder.elevations(&self.elevations);
+        }
+        if !self.distance_biases.is_empty() {
+            decoder.distance_correction(RangeBias::new(&self.distance_biases));
+        }
+        if let Some(mounting) = self.mounting {
+            decoder.transform(mounting);
+        }
+        match self.time_reference {
+            Some(TimeReference::Gps) => {
+                decoder.time_source(GpsTime::new());
+            }
+            Some(TimeReference::Capture) => {
+                decoder.time_source(CaptureTime::new());
+            }
+            Some(TimeReference::Device) | None => {}
+        }
+        decoder
+    }
+
+    /// Returns a batch configuration configured by this sidecar.
+    ///
+    /// Batch decoding doesn't support distance corrections or time sources, so the distance
+    /// biases and time reference are ignored. The sensor overrides the packets' sensor factory
+    /// bytes, see `batch::Config::sensor`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use velodyne::sidecar::Sidecar;
+    /// let config = Sidecar::new().config();
+    /// let frames = velodyne::read_pcap_frames("data/single.pcap", &config).unwrap();
+    /// ```
+    pub fn config(&self) -> batch::Config {
+        let mut config = batch::Config::new();
+        if let Some(sensor) = self.sensor {
+            config.sensor(sensor);
+        }
+        if let Some(return_mode) = self.return_mode {
+            config.return_mode(return_mode);
+        }
+        if !self.azimuth_offsets.is_empty() {
+            config.azimuth_offsets(&self.azimuth_offsets);
+        }
+        if !self.elevations.is_empty() {
+            config.elevations(&self.elevations);
+        }
+        if let Some(mounting) = self.mounting {
+            config.transform(mounting);
+        }
+        config
+    }
+}
+
+fn invalid(message: &str) -> Error {
+    Error::Sidecar(message.to_string())
+}
+
+fn field_error(field: &str) -> Error {
+    invalid(&format!("missing or invalid field: {}", field))
+}
+
+fn floats(json: &Json) -> Option<Vec<f32>> {
+    json.as_array()?.iter().map(|value| value.as_f64().map(|n| n as f32)).collect()
+}
+
+/// Converts to JSON through the shortest decimal that round-trips, so `0.1` isn't written as
+/// `0.10000000149011612`.
+fn float(n: f32) -> Json {
+    Json::F64(n.to_string().parse().unwrap_or_else(|_| f64::from(n)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn round_trip() {
+        let sidecar = Sidecar {
+            sensor: Some(Sensor::Unknown(0x28)),
+            return_mode: Some(ReturnMode::LastReturn),
+            rpm: Some(600.5),
+            azimuth_offsets: vec![Degrees(0.1), Degrees(-0.2)],
+            elevations: vec![Degrees(-15.)],
+            distance_biases: vec![Meters(0.012)],
+            mounting: Some(Transform::from_euler(0., 0., 90., [1., 2., 1.8])),
+            time_reference: Some(TimeReference::Capture),
+            notes: "Parking lot".to_string(),
+        };
+        let json = sidecar.to_json();
+        assert!(json.contains("0.1,"), "{}", json);
+        assert_eq!(sidecar, Sidecar::from_json(&json).unwrap());
+        assert_eq!(Sidecar::new(), Sidecar::from_json(&Sidecar::new().to_json()).unwrap());
+    }
+
+    #[test]
+    fn invalid() {
+        assert!(Sidecar::from_json("[]").is_err());
+        assert!(Sidecar::from_json(r#"{"version": 2}"#).is_err());
+        assert!(Sidecar::from_json(r#"{"sensor": "VLP-32"}"#).is_err());
+        assert!(Sidecar::from_json(r#"{"mounting": {"translation": [1, 2]}}"#).is_err());
+        let sidecar = Sidecar::from_json(r#"{"sensor": 34, "future": true}"#).unwrap();
+        assert_eq!(Some(Sensor::VLP_16), sidecar.sensor);
+    }
+}
@@ -0,0 +1,147 @@
+//! Write points to any output.
+//!
+//! A `PointSink` accepts frames or batches of points and is finished when there are no more. The
+//! crate's writers are sinks, e.g. `csv::Writer`, `pcd::Writer`, and `dump::Writer`, so a
+//! conversion can be written once and pointed at any of them, or at a sink of your own that
+//! inserts into a database or publishes to a message queue:
+//!
+//! ```
+//! use velodyne::csv;
+//! use velodyne::io::{Pcap, Read};
+//! use velodyne::sink;
+//! use velodyne::stream::PointStream;
+//! let frames = Pcap::open("data/single.pcap").unwrap().points().frames();
+//! let mut writer = csv::Writer::new(Vec::new());
+//! sink::write_frames(frames, &mut writer).unwrap();
+//! ```
+
+use {Point, Result};
+use frame::Frame;
+
+/// A destination for points.
+pub trait PointSink {
+    /// Writes a batch of points.
+    fn write_points(&mut self, points: &[Point]) -> Result<()>;
+
+    /// Writes a frame.
+    ///
+    /// The default implementation writes the frame's points. Sinks that keep frames apart, e.g.
+    /// frame dumps, override it.
+    fn write_frame(&mut self, frame: &Frame) -> Result<()> {
+        self.write_points(&frame.points)
+    }
+
+    /// Finishes the output, e.g. by writing a header that needs the number of points or a footer.
+    ///
+    /// Nothing should be written after a sink is finished. The default implementation does
+    /// nothing.
+    fn finish(&mut self) -> Result<()> {
+        Ok(())
+    }
+}
+
+/// Writes frames to a sink and finishes it, returning the number of frames written.
+///
+/// # Examples
+///
+/// ```
+/// use velodyne::Point;
+/// use velodyne::frame::Frame;
+/// use velodyne::sink;
+/// let frames = vec![Frame::default(), Frame::default()];
+/// let mut points: Vec<Point> = Vec::new();
+/// assert_eq!(2, sink::write_frames(frames, &mut points).unwrap());
+/// ```
+pub fn write_frames<I, S>(frames: I, sink: &mut S) -> Result<usize>
+    where I: IntoIterator<Item = Frame>,
+          S: PointSink + ?Sized
+{
+    let mut count = 0;
+    for frame in frames {
+        sink.write_frame(&frame)?;
+        count += 1;
+    }
+    sink.finish()?;
+    Ok(count)
+}
+
+/// Collects points in memory.
+impl PointSink for Vec<Point> {
+    fn write_points(&mut self, points: &[Point]) -> Result<()> {
+        self.extend_from_slice(points);
+        Ok(())
+    }
+}
+
+impl<S: PointSink + ?Sized> PointSink for Box<S> {
+    fn write_points(&mut self, points: &[Point]) -> Result<()> {
+        (**self).write_points(points)
+    }
+
+    fn write_frame(&mut self, frame: &Frame) -> Result<()> {
+        (**self).write_frame(frame)
+    }
+
+    fn finish(&mut self) -> Result<()> {
+        (**self).finish()
+    }
+}
+
+impl<S: PointSink + ?Sized> PointSink for &mut S {
+    fn write_points(&mut self, points: &[Point]) -> Result<()> {
+        (**self).write_points(points)
+    }
+
+    fn write_frame(&mut self, frame: &Frame) -> Result<()> {
+        (**self).write_frame(frame)
+    }
+
+    fn finish(&mut self) -> Result<()> {
+        (**self).finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use fixtures::VLP_16_DATA_PACKET;
+    use vlp_16::Packet;
+
+    #[derive(Debug, Default)]
+    struct Counter {
+        frames: usize,
+        points: usize,
+        finished: bool,
+    }
+
+    impl PointSink for Counter {
+        fn write_points(&mut self, points: &[Point]) -> Result<()> {
+            self.points += points.len();
+            Ok(())
+        }
+
+        fn write_frame(&mut self, frame: &Frame) -> Result<()> {
+            self.frames += 1;
+            self.write_points(&frame.points)
+        }
+
+        fn finish(&mut self) -> Result<()> {
+            self.finished = true;
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn custom_sink() {
+        let points = Packet::new(&VLP_16_DATA_PACKET).unwrap().points().unwrap();
+        let frames = vec![Frame::new(points.clone()), Frame::new(points.clone())];
+        let mut counter = Counter::default();
+        {
+            let mut sink: Box<dyn PointSink + '_> = Box::new(&mut counter);
+            assert_eq!(2, write_frames(frames, &mut sink).unwrap());
+        }
+        assert_eq!(2, counter.frames);
+        assert_eq!(2 * points.len(), counter.points);
+        assert!(counter.finished);
+    }
+}
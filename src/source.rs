@@ -1,26 +1,304 @@
 //! Sources of Velodyne data.
+//!
+//! A `Source` wraps any reader — a pcap file, a live socket, or bytes in memory — together with
+//! the configuration that turns its packets into points, so every kind of input is decoded the
+//! same way:
+//!
+//! ```
+//! use velodyne::source::Source;
+//! use velodyne::time::GpsTime;
+//! let mut source = Source::open("data/single.pcap").unwrap();
+//! source.time_source(GpsTime::new());
+//! assert_eq!(1, source.frames().count());
+//! ```
 
-use Point;
+use {Point, Result};
+use filter::Filter;
+use frame::Assembler;
+use io::{PcapStream, Read};
+#[cfg(feature = "socket")]
+use io::Socket;
+use std::fmt;
+use std::fs::File;
+use std::io::BufReader;
+#[cfg(feature = "socket")]
+use std::net::ToSocketAddrs;
+use std::path::Path;
+use stream::{Frames, PointStream, Points};
+use time::TimeSource;
+use transform::Transform;
+use vlp_16::{AzimuthDirection, Decoder, Packets};
 
 /// A source of Velodyne data.
-#[derive(Clone, Copy, Debug)]
-pub struct Source;
+///
+/// Sources are configured with builder-style setters, and then consumed as packets, points, or
+/// frames.
+pub struct Source {
+    read: Box<dyn Read>,
+    decoder: Decoder,
+    assembler: Assembler,
+}
 
 impl Source {
-    /// Returns an interator over this source's points.
-    pub fn points(&mut self) -> Points {
-        unimplemented!()
+    /// Creates a source from any reader.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use velodyne::io::PcapSlice;
+    /// use velodyne::source::Source;
+    /// let bytes = std::fs::read("data/single.pcap").unwrap();
+    /// let source = Source::new(PcapSlice::new(bytes).unwrap());
+    /// assert_eq!(384, source.points().count());
+    /// ```
+    pub fn new<R: Read + 'static>(read: R) -> Source {
+        Source {
+            read: Box::new(read),
+            decoder: Decoder::new(),
+            assembler: Assembler::new(),
+        }
+    }
+
+    /// Opens a pcap file.
+    ///
+    /// The file is read with this crate's own pcap parser, so this doesn't need libpcap.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use velodyne::source::Source;
+    /// let source = Source::open("data/single.pcap").unwrap();
+    /// ```
+    pub fn open<P: AsRef<Path>>(path: P) -> Result<Source> {
+        let stream = PcapStream::new(BufReader::new(File::open(path)?))?;
+        Ok(Source::new(stream))
+    }
+
+    /// Binds a socket to the given address and reads packets from it.
+    ///
+    /// Use `Source::new` with a `Socket` for more control, e.g. to receive position packets.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use velodyne::source::Source;
+    /// let source = Source::bind("127.0.0.1:0").unwrap();
+    /// ```
+    #[cfg(feature = "socket")]
+    pub fn bind<A: ToSocketAddrs>(address: A) -> Result<Source> {
+        Ok(Source::new(Socket::bind(address)?))
+    }
+
+    /// Sets the time source used to stamp points with absolute times.
+    ///
+    /// See `Decoder::time_source`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use velodyne::source::Source;
+    /// use velodyne::time::GpsTime;
+    /// let mut source = Source::open("data/single.pcap").unwrap();
+    /// source.time_source(GpsTime::new());
+    /// ```
+    pub fn time_source<T: TimeSource + 'static>(&mut self, time_source: T) -> &mut Source {
+        self.decoder.time_source(time_source);
+        self
+    }
+
+    /// Adds a filter that is applied to every decoded point.
+    ///
+    /// See `Decoder::filter`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use velodyne::source::Source;
+    /// use velodyne::filter::NoiseFilter;
+    /// let mut source = Source::open("data/single.pcap").unwrap();
+    /// source.filter(NoiseFilter::new());
+    /// ```
+    pub fn filter<F: Filter + 'static>(&mut self, filter: F) -> &mut Source {
+        self.decoder.filter(filter);
+        self
+    }
+
+    /// Sets the sensor's pose, which is applied to every decoded point.
+    ///
+    /// See `Decoder::transform`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use velodyne::source::Source;
+    /// use velodyne::transform::Transform;
+    /// let mut source = Source::open("data/single.pcap").unwrap();
+    /// source.transform(Transform::from_euler(0., 0., 90., [0., 0., 1.8]));
+    /// ```
+    pub fn transform<T: Into<Transform>>(&mut self, transform: T) -> &mut Source {
+        self.decoder.transform(transform);
+        self
+    }
+
+    /// Sets which way the points' azimuths increase, for both the decoder and the assembler.
+    ///
+    /// See `Decoder::azimuth_direction`. Replacing the decoder or assembler afterwards replaces
+    /// this setting too.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use velodyne::source::Source;
+    /// use velodyne::vlp_16::AzimuthDirection;
+    /// let mut source = Source::open("data/single.pcap").unwrap();
+    /// source.azimuth_direction(AzimuthDirection::Counterclockwise);
+    /// assert_eq!(1, source.frames().count());
+    /// ```
+    pub fn azimuth_direction(&mut self, azimuth_direction: AzimuthDirection) -> &mut Source {
+        self.decoder.azimuth_direction(azimuth_direction);
+        self.assembler.azimuth_direction(azimuth_direction);
+        self
+    }
+
+    /// Stamps every point with a sensor id, see `Decoder::sensor_id`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use velodyne::source::Source;
+    /// let mut source = Source::open("data/single.pcap").unwrap();
+    /// source.sensor_id(1);
+    /// assert!(source.points().all(|point| point.sensor_id == Some(1)));
+    /// ```
+    pub fn sensor_id(&mut self, sensor_id: u16) -> &mut Source {
+        self.decoder.sensor_id(sensor_id);
+        self
+    }
+
+    /// Replaces the decoder, for settings that don't have a setter here.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use velodyne::source::Source;
+    /// use velodyne::vlp_16::{Decoder, Interpolation};
+    /// let mut decoder = Decoder::new();
+    /// decoder.interpolation(Interpolation::Timestamp);
+    /// let mut source = Source::open("data/single.pcap").unwrap();
+    /// source.decoder(decoder);
+    /// ```
+    pub fn decoder(&mut self, decoder: Decoder) -> &mut Source {
+        self.decoder = decoder;
+        self
+    }
+
+    /// Sets the assembler that groups points into frames.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use velodyne::source::Source;
+    /// use velodyne::frame::Assembler;
+    /// use velodyne::units::Degrees;
+    /// let mut assembler = Assembler::new();
+    /// assembler.cut_angle(Degrees(180.));
+    /// let mut source = Source::open("data/single.pcap").unwrap();
+    /// source.assembler(assembler);
+    /// ```
+    pub fn assembler(&mut self, assembler: Assembler) -> &mut Source {
+        self.assembler = assembler;
+        self
+    }
+
+    /// Returns an iterator over this source's packets.
+    ///
+    /// Packets are returned as they are parsed, so the decoder and assembler aren't used.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use velodyne::source::Source;
+    /// let source = Source::open("data/single.pcap").unwrap();
+    /// assert_eq!(1, source.packets().count());
+    /// ```
+    pub fn packets(self) -> Packets<Box<dyn Read>> {
+        self.read.vlp_16_packets()
+    }
+
+    /// Returns an iterator over this source's decoded points.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use velodyne::source::Source;
+    /// let source = Source::open("data/single.pcap").unwrap();
+    /// assert_eq!(384, source.points().count());
+    /// ```
+    pub fn points(self) -> Points<Box<dyn Read>> {
+        Points::with_decoder(self.read, self.decoder)
+    }
+
+    /// Returns an iterator over this source's frames.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use velodyne::source::Source;
+    /// let source = Source::open("data/single.pcap").unwrap();
+    /// assert_eq!(1, source.frames().count());
+    /// ```
+    pub fn frames(self) -> Frames<Points<Box<dyn Read>>> {
+        let assembler = self.assembler;
+        Points::with_decoder(self.read, self.decoder).frames_with(assembler)
+    }
+}
+
+impl fmt::Debug for Source {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Source")
+            .field("decoder", &self.decoder)
+            .field("assembler", &self.assembler)
+            .finish()
     }
 }
 
-/// An iterator over a source's points.
-#[derive(Clone, Copy, Debug)]
-pub struct Points;
+impl<R: Read + 'static> From<R> for Source {
+    fn from(read: R) -> Source {
+        Source::new(read)
+    }
+}
 
-impl Iterator for Points {
+impl IntoIterator for Source {
     type Item = Point;
+    type IntoIter = Points<Box<dyn Read>>;
+
+    fn into_iter(self) -> Points<Box<dyn Read>> {
+        self.points()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use cloud::Bounds;
+    use filter::CropBox;
+    use io::PcapSlice;
+    use std::fs;
+
+    #[test]
+    fn sources_agree() {
+        let bytes = fs::read("data/single.pcap").unwrap();
+        let from_slice = Source::from(PcapSlice::new(bytes).unwrap()).points().collect::<Vec<_>>();
+        let from_file = Source::open("data/single.pcap").unwrap().into_iter().collect::<Vec<_>>();
+        assert_eq!(384, from_slice.len());
+        assert_eq!(from_slice.len(), from_file.len());
+    }
 
-    fn next(&mut self) -> Option<Point> {
-        unimplemented!()
+    #[test]
+    fn configuration() {
+        let mut source = Source::open("data/single.pcap").unwrap();
+        source.filter(CropBox::new(Bounds::new([0.; 3], [0.; 3])));
+        assert!(source.points().all(|point| point.range() == 0.));
+        assert!(Source::open("data/missing.pcap").is_err());
     }
 }
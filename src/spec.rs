@@ -0,0 +1,155 @@
+//! Published sensor specifications.
+//!
+//! These numbers come from the sensors' user manuals. They are what the decoders use, and are
+//! public so that downstream code (e.g. georeferencing or simulation) doesn't have to copy them.
+
+use units::{Degrees, Meters};
+
+/// The specification of a sensor model.
+#[derive(Clone, Copy, Debug)]
+pub struct SensorSpec {
+    /// The name of the sensor model.
+    pub name: &'static str,
+    /// The lasers, indexed by channel.
+    pub lasers: &'static [LaserSpec],
+    /// The time between the starts of two firing sequences, in microseconds.
+    pub firing_cycle_us: f32,
+    /// The time between two laser firings within a firing sequence, in microseconds.
+    pub firing_us: f32,
+    /// The distance represented by one unit of a data record's distance.
+    pub distance_resolution: Meters,
+    /// The maximum measurable range.
+    pub max_range: Meters,
+}
+
+/// The specification of a single laser.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct LaserSpec {
+    /// The angle of the laser above the horizontal plane.
+    pub elevation: Degrees,
+    /// The vertical offset of the laser from the sensor's origin.
+    pub vertical_offset: Meters,
+}
+
+/// The VLP-16.
+pub const VLP_16: SensorSpec = SensorSpec {
+    name: "VLP-16",
+    lasers: &VLP_16_LASERS,
+    firing_cycle_us: 55.296,
+    firing_us: 2.304,
+    distance_resolution: Meters(0.002),
+    max_range: Meters(100.),
+};
+
+/// The HDL-32E.
+pub const HDL_32E: SensorSpec = SensorSpec {
+    name: "HDL-32E",
+    lasers: &HDL_32E_LASERS,
+    firing_cycle_us: 46.080,
+    firing_us: 1.152,
+    distance_resolution: Meters(0.002),
+    max_range: Meters(100.),
+};
+
+const VLP_16_LASERS: [LaserSpec; 16] = [laser(-15., 0.0112),
+                                        laser(1., -0.0007),
+                                        laser(-13., 0.0097),
+                                        laser(3., -0.0022),
+                                        laser(-11., 0.0081),
+                                        laser(5., -0.0037),
+                                        laser(-9., 0.0066),
+                                        laser(7., -0.0051),
+                                        laser(-7., 0.0051),
+                                        laser(9., -0.0066),
+                                        laser(-5., 0.0037),
+                                        laser(11., -0.0081),
+                                        laser(-3., 0.0022),
+                                        laser(13., -0.0097),
+                                        laser(-1., 0.0007),
+                                        laser(15., -0.0112)];
+
+const HDL_32E_LASERS: [LaserSpec; 32] = [laser(-30.67, 0.),
+                                         laser(-9.33, 0.),
+                                         laser(-29.33, 0.),
+                                         laser(-8., 0.),
+                                         laser(-28., 0.),
+                                         laser(-6.67, 0.),
+                                         laser(-26.67, 0.),
+                                         laser(-5.33, 0.),
+                                         laser(-25.33, 0.),
+                                         laser(-4., 0.),
+                                         laser(-24., 0.),
+                                         laser(-2.67, 0.),
+                                         laser(-22.67, 0.),
+                                         laser(-1.33, 0.),
+                                         laser(-21.33, 0.),
+                                         laser(0., 0.),
+                                         laser(-20., 0.),
+                                         laser(1.33, 0.),
+                                         laser(-18.67, 0.),
+                                         laser(2.67, 0.),
+                                         laser(-17.33, 0.),
+                                         laser(4., 0.),
+                                         laser(-16., 0.),
+                                         laser(5.33, 0.),
+                                         laser(-14.67, 0.),
+                                         laser(6.67, 0.),
+                                         laser(-13.33, 0.),
+                                         laser(8., 0.),
+                                         laser(-12., 0.),
+                                         laser(9.33, 0.),
+                                         laser(-10.67, 0.),
+                                         laser(10.67, 0.)];
+
+const fn laser(elevation: f32, vertical_offset: f32) -> LaserSpec {
+    LaserSpec {
+        elevation: Degrees(elevation),
+        vertical_offset: Meters(vertical_offset),
+    }
+}
+
+impl SensorSpec {
+    /// Returns the number of lasers.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use velodyne::spec::VLP_16;
+    /// assert_eq!(16, VLP_16.num_lasers());
+    /// ```
+    pub fn num_lasers(&self) -> usize {
+        self.lasers.len()
+    }
+
+    /// Returns the elevation of a laser channel.
+    ///
+    /// # Panics
+    ///
+    /// Panics if there is no such channel.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use velodyne::spec::VLP_16;
+    /// use velodyne::units::Degrees;
+    /// assert_eq!(Degrees(-15.), VLP_16.elevation(0));
+    /// assert_eq!(Degrees(15.), VLP_16.elevation(15));
+    /// ```
+    pub fn elevation(&self, channel: usize) -> Degrees {
+        self.lasers[channel].elevation
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn vlp_16_lasers_are_symmetric() {
+        for channel in 0..VLP_16.num_lasers() {
+            let other = VLP_16.lasers[15 - channel];
+            assert_eq!(-VLP_16.lasers[channel].elevation, other.elevation);
+            assert_eq!(-VLP_16.lasers[channel].vertical_offset, other.vertical_offset);
+        }
+    }
+}
@@ -0,0 +1,335 @@
+//! Accumulate points in bounded memory by spilling them to disk.
+//!
+//! `read_pcap_points` holds a whole capture's points in memory, which is a problem for captures
+//! of many gigabytes. A `Spill` collects points like a `Vec`, but whenever it holds more than its
+//! memory limit, it writes them to a temporary frame dump and starts over. Exporting reads the
+//! chunks back one at a time, in the order the points arrived, so the points only come together
+//! in the sink:
+//!
+//! ```
+//! use velodyne::batch::{self, Config};
+//! use velodyne::csv;
+//! let spill = batch::spill_pcap_points("data/single.pcap", &Config::new(), 1 << 20).unwrap();
+//! let mut writer = csv::Writer::new(Vec::new());
+//! assert_eq!(384, spill.write_to(&mut writer).unwrap());
+//! ```
+//!
+//! Sinks that need every point before they can write anything, e.g. `pcd::Writer`, still hold
+//! them all, so export to a streaming format, like CSV or a frame dump, to keep memory bounded
+//! from end to end. The temporary file is removed when the spill is dropped.
+
+use {Point, Result};
+use dump::{self, Compression};
+use frame::Frame;
+use sink::PointSink;
+use std::env;
+use std::fs::{self, File, OpenOptions};
+use std::io::{BufReader, BufWriter};
+use std::mem;
+use std::path::{Path, PathBuf};
+use std::process;
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+/// Numbers the temporary files of the spills in this process, so that they don't collide.
+static NEXT_FILE: AtomicUsize = AtomicUsize::new(0);
+
+/// Collects points, spilling them to a temporary file once they exceed a memory limit.
+#[derive(Debug)]
+pub struct Spill {
+    limit: usize,
+    dir: PathBuf,
+    compression: Compression,
+    buffer: Vec<Point>,
+    writer: Option<dump::Writer<BufWriter<File>>>,
+    path: Option<PathBuf>,
+    chunks: usize,
+    spilled: usize,
+}
+
+/// An iterator over the chunks of a spill, in the order their points arrived.
+#[derive(Debug)]
+pub struct Chunks<'a> {
+    reader: Option<dump::Reader<BufReader<File>>>,
+    buffer: Option<&'a [Point]>,
+}
+
+impl Spill {
+    /// Creates a spill that holds at most `limit` bytes of points in memory, and spills to the
+    /// system's temporary directory.
+    ///
+    /// A chunk is only spilled once a batch of points takes it over the limit, so the memory used
+    /// can exceed the limit by one batch, e.g. one packet's points.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use velodyne::spill::Spill;
+    /// let spill = Spill::new(256 << 20);
+    /// assert!(spill.is_empty());
+    /// ```
+    pub fn new(limit: usize) -> Spill {
+        Spill {
+            limit,
+            dir: env::temp_dir(),
+            compression: Compression::None,
+            buffer: Vec::new(),
+            writer: None,
+            path: None,
+            chunks: 0,
+            spilled: 0,
+        }
+    }
+
+    /// Sets the directory that the temporary file is created in.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use velodyne::spill::Spill;
+    /// let mut spill = Spill::new(256 << 20);
+    /// spill.dir("/var/tmp");
+    /// ```
+    pub fn dir<P: Into<PathBuf>>(&mut self, dir: P) -> &mut Spill {
+        self.dir = dir.into();
+        self
+    }
+
+    /// Sets how spilled chunks are compressed.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use velodyne::dump::Compression;
+    /// use velodyne::spill::Spill;
+    /// let mut spill = Spill::new(256 << 20);
+    /// spill.compression(Compression::None);
+    /// ```
+    pub fn compression(&mut self, compression: Compression) -> &mut Spill {
+        self.compression = compression;
+        self
+    }
+
+    /// Returns the number of points, in memory and on disk.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use velodyne::Packet;
+    /// use velodyne::fixtures::VLP_16_DATA_PACKET;
+    /// use velodyne::sink::PointSink;
+    /// use velodyne::spill::Spill;
+    /// let points = Packet::new(&VLP_16_DATA_PACKET).unwrap().points().unwrap();
+    /// let mut spill = Spill::new(0);
+    /// spill.write_points(&points).unwrap();
+    /// assert_eq!(384, spill.len());
+    /// ```
+    pub fn len(&self) -> usize {
+        self.spilled + self.buffer.len()
+    }
+
+    /// Returns true if there are no points.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use velodyne::spill::Spill;
+    /// assert!(Spill::new(0).is_empty());
+    /// ```
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Returns the number of points that have been spilled to disk.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use velodyne::Packet;
+    /// use velodyne::fixtures::VLP_16_DATA_PACKET;
+    /// use velodyne::sink::PointSink;
+    /// use velodyne::spill::Spill;
+    /// let points = Packet::new(&VLP_16_DATA_PACKET).unwrap().points().unwrap();
+    /// let mut spill = Spill::new(1 << 20);
+    /// spill.write_points(&points).unwrap();
+    /// assert_eq!(0, spill.spilled());
+    /// ```
+    pub fn spilled(&self) -> usize {
+        self.spilled
+    }
+
+    /// Returns the path of the temporary file, if anything has been spilled.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use velodyne::spill::Spill;
+    /// assert!(Spill::new(0).path().is_none());
+    /// ```
+    pub fn path(&self) -> Option<&Path> {
+        self.path.as_deref()
+    }
+
+    /// Returns an iterator over the points in chunks, the spilled ones first.
+    ///
+    /// Only one chunk is read into memory at a time.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use velodyne::Packet;
+    /// use velodyne::fixtures::VLP_16_DATA_PACKET;
+    /// use velodyne::sink::PointSink;
+    /// use velodyne::spill::Spill;
+    /// let points = Packet::new(&VLP_16_DATA_PACKET).unwrap().points().unwrap();
+    /// let mut spill = Spill::new(0);
+    /// spill.write_points(&points).unwrap();
+    /// spill.write_points(&points).unwrap();
+    /// assert_eq!(2, spill.chunks().unwrap().count());
+    /// ```
+    pub fn chunks(&self) -> Result<Chunks<'_>> {
+        let reader = match self.path {
+            Some(ref path) => Some(dump::Reader::new(BufReader::new(File::open(path)?))),
+            None => None,
+        };
+        Ok(Chunks {
+               reader,
+               buffer: Some(&self.buffer),
+           })
+    }
+
+    /// Writes every point to a sink, in the order they arrived, and finishes it, returning the
+    /// number of points.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use velodyne::Packet;
+    /// use velodyne::fixtures::VLP_16_DATA_PACKET;
+    /// use velodyne::sink::PointSink;
+    /// use velodyne::spill::Spill;
+    /// let points = Packet::new(&VLP_16_DATA_PACKET).unwrap().points().unwrap();
+    /// let mut spill = Spill::new(0);
+    /// spill.write_points(&points).unwrap();
+    /// let mut merged = Vec::new();
+    /// assert_eq!(384, spill.write_to(&mut merged).unwrap());
+    /// ```
+    pub fn write_to<S: PointSink + ?Sized>(self, sink: &mut S) -> Result<usize> {
+        let mut count = 0;
+        for chunk in self.chunks()? {
+            let chunk = chunk?;
+            sink.write_points(&chunk)?;
+            count += chunk.len();
+        }
+        sink.finish()?;
+        Ok(count)
+    }
+
+    /// Writes the points in memory to the temporary file, creating it if need be.
+    fn spill(&mut self) -> Result<()> {
+        if self.writer.is_none() {
+            let (file, path) = self.create()?;
+            let mut writer = dump::Writer::new(BufWriter::new(file));
+            writer.compression(self.compression);
+            self.writer = Some(writer);
+            self.path = Some(path);
+        }
+        if let Some(ref mut writer) = self.writer {
+            let points = mem::take(&mut self.buffer);
+            self.spilled += points.len();
+            writer.write(&Frame::new(points))?;
+            // Flushed, so that chunks can be read back at any time.
+            writer.finish()?;
+        }
+        self.chunks += 1;
+        debug!("spilled chunk {} ({} points on disk)", self.chunks, self.spilled);
+        Ok(())
+    }
+
+    fn create(&self) -> Result<(File, PathBuf)> {
+        loop {
+            let n = NEXT_FILE.fetch_add(1, Ordering::Relaxed);
+            let path = self.dir.join(format!("velodyne-spill-{}-{}.vldf", process::id(), n));
+            match OpenOptions::new().write(true).create_new(true).open(&path) {
+                Ok(file) => return Ok((file, path)),
+                Err(ref err) if err.kind() == ::std::io::ErrorKind::AlreadyExists => continue,
+                Err(err) => return Err(err.into()),
+            }
+        }
+    }
+}
+
+/// Points are collected, and spilled whenever they exceed the memory limit. Frames are flattened
+/// into points.
+impl PointSink for Spill {
+    fn write_points(&mut self, points: &[Point]) -> Result<()> {
+        self.buffer.extend_from_slice(points);
+        if self.buffer.len() * size_of::<Point>() > self.limit {
+            self.spill()?;
+        }
+        Ok(())
+    }
+}
+
+impl Drop for Spill {
+    fn drop(&mut self) {
+        self.writer = None;
+        if let Some(ref path) = self.path {
+            if let Err(err) = fs::remove_file(path) {
+                warn!("could not remove {}: {}", path.display(), err);
+            }
+        }
+    }
+}
+
+impl<'a> Iterator for Chunks<'a> {
+    type Item = Result<Vec<Point>>;
+
+    fn next(&mut self) -> Option<Result<Vec<Point>>> {
+        if let Some(mut reader) = self.reader.take() {
+            match reader.read() {
+                Ok(Some(frame)) => {
+                    self.reader = Some(reader);
+                    return Some(Ok(frame.points));
+                }
+                Ok(None) => {}
+                Err(err) => return Some(Err(err)),
+            }
+        }
+        self.buffer.take().filter(|buffer| !buffer.is_empty()).map(|buffer| Ok(buffer.to_vec()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use fixtures::VLP_16_DATA_PACKET;
+    use vlp_16::Packet;
+
+    #[test]
+    fn spills_and_merges_in_order() {
+        let points = Packet::new(&VLP_16_DATA_PACKET).unwrap().points().unwrap();
+        let limit = 500 * size_of::<Point>();
+        let mut spill = Spill::new(limit);
+        let mut expected = Vec::new();
+        for i in 0..5 {
+            let mut batch = points.clone();
+            for point in &mut batch {
+                point.x += i as f32;
+            }
+            spill.write_points(&batch).unwrap();
+            expected.extend(batch);
+        }
+        assert_eq!(1920, spill.len());
+        assert_eq!(1536, spill.spilled());
+        let path = spill.path().unwrap().to_path_buf();
+        assert!(path.exists());
+        assert_eq!(3, spill.chunks().unwrap().count());
+        let mut merged = Vec::new();
+        assert_eq!(1920, spill.write_to(&mut merged).unwrap());
+        assert!(!path.exists());
+        for (expected, actual) in expected.iter().zip(&merged) {
+            assert_eq!(expected.x, actual.x);
+            assert_eq!(expected.channel, actual.channel);
+        }
+    }
+}
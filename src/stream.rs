@@ -0,0 +1,663 @@
+//! Compose point streams.
+//!
+//! `PointStream` adds adapters to every iterator over points, so pipelines read left to right:
+//!
+//! ```
+//! use velodyne::io::{Pcap, Read};
+//! use velodyne::stream::PointStream;
+//! use velodyne::units::{Degrees, Meters};
+//! let frames = Pcap::open("data/single.pcap")
+//!     .unwrap()
+//!     .points()
+//!     .min_range(Meters(0.9))
+//!     .fov(Degrees(270.), Degrees(90.))
+//!     .frames()
+//!     .collect::<Vec<_>>();
+//! ```
+
+use {Error, Point};
+use frame::{Assembler, Frame};
+use io::Read;
+use point::Time;
+use std::cmp::{Ordering, Reverse};
+use std::collections::BinaryHeap;
+use std::vec;
+use time::TimeQuality;
+use transform::Transform;
+use units::{Degrees, Meters};
+use vlp_16::{DecodeMetrics, Decoder, Packets};
+use warning::DecodeWarning;
+
+/// Adapters for iterators over points.
+pub trait PointStream: Iterator<Item = Point> + Sized {
+    /// Keeps the points that are at least `min` away, which also drops points without a return.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use velodyne::fixtures::VLP_16_DATA_PACKET;
+    /// use velodyne::stream::PointStream;
+    /// use velodyne::units::Meters;
+    /// use velodyne::vlp_16::Packet;
+    /// let points = Packet::new(&VLP_16_DATA_PACKET).unwrap().points().unwrap();
+    /// assert!(points.into_iter().min_range(Meters(0.9)).all(|point| point.range() >= 0.9));
+    /// ```
+    fn min_range(self, min: Meters) -> RangeFilter<Self> {
+        RangeFilter {
+            points: self,
+            min: min.0,
+            max: f32::INFINITY,
+        }
+    }
+
+    /// Keeps the points that are at most `max` away, including points without a return.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use velodyne::fixtures::VLP_16_DATA_PACKET;
+    /// use velodyne::stream::PointStream;
+    /// use velodyne::units::Meters;
+    /// use velodyne::vlp_16::Packet;
+    /// let points = Packet::new(&VLP_16_DATA_PACKET).unwrap().points().unwrap();
+    /// assert!(points.into_iter().max_range(Meters(10.)).all(|point| point.range() <= 10.));
+    /// ```
+    fn max_range(self, max: Meters) -> RangeFilter<Self> {
+        RangeFilter {
+            points: self,
+            min: f32::NEG_INFINITY,
+            max: max.0,
+        }
+    }
+
+    /// Keeps the points whose azimuths run clockwise from `start` to `end`.
+    ///
+    /// The field of view can wrap through zero, e.g. `fov(Degrees(270.), Degrees(90.))` keeps the
+    /// front half of a scan. A field of view of 360 degrees or more keeps everything.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use velodyne::fixtures::VLP_16_DATA_PACKET;
+    /// use velodyne::stream::PointStream;
+    /// use velodyne::units::Degrees;
+    /// use velodyne::vlp_16::Packet;
+    /// let points = Packet::new(&VLP_16_DATA_PACKET).unwrap().points().unwrap();
+    /// assert_eq!(0, points.into_iter().fov(Degrees(270.), Degrees(90.)).count());
+    /// ```
+    fn fov(self, start: Degrees, end: Degrees) -> Fov<Self> {
+        let (start, end) = (start.0, end.0);
+        let width = if end - start >= 360. {
+            360.
+        } else {
+            (end - start).rem_euclid(360.)
+        };
+        Fov {
+            points: self,
+            start,
+            width,
+        }
+    }
+
+    /// Keeps the points from some laser channels.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use velodyne::fixtures::VLP_16_DATA_PACKET;
+    /// use velodyne::stream::PointStream;
+    /// use velodyne::vlp_16::Packet;
+    /// let points = Packet::new(&VLP_16_DATA_PACKET).unwrap().points().unwrap();
+    /// assert_eq!(48, points.into_iter().rings(&[0, 1]).count());
+    /// ```
+    fn rings(self, rings: &[u8]) -> Rings<Self> {
+        let mut keep = [false; 256];
+        for &ring in rings {
+            keep[ring as usize] = true;
+        }
+        Rings { points: self, keep }
+    }
+
+    /// Transforms every point with a return, e.g. into a vehicle's frame.
+    ///
+    /// Points without a return are left at the origin, as with `Decoder::transform`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use velodyne::fixtures::VLP_16_DATA_PACKET;
+    /// use velodyne::stream::PointStream;
+    /// use velodyne::transform::Transform;
+    /// use velodyne::vlp_16::Packet;
+    /// let points = Packet::new(&VLP_16_DATA_PACKET).unwrap().points().unwrap();
+    /// let z = points[0].z;
+    /// let point = points.into_iter()
+    ///     .transform(Transform::from_euler(0., 0., 0., [0., 0., 1.]))
+    ///     .next()
+    ///     .unwrap();
+    /// assert_eq!(z + 1., point.z);
+    /// ```
+    fn transform<T: Into<Transform>>(self, transform: T) -> Transformed<Self> {
+        Transformed {
+            points: self,
+            transform: transform.into(),
+        }
+    }
+
+    /// Groups the points into frames, one per rotation.
+    ///
+    /// The last frame is returned even if it is incomplete.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use velodyne::fixtures::VLP_16_DATA_PACKET;
+    /// use velodyne::stream::PointStream;
+    /// use velodyne::vlp_16::Packet;
+    /// let points = Packet::new(&VLP_16_DATA_PACKET).unwrap().points().unwrap();
+    /// assert_eq!(1, points.into_iter().frames().count());
+    /// ```
+    fn frames(self) -> Frames<Self> {
+        self.frames_with(Assembler::new())
+    }
+
+    /// Groups the points into frames with a configured assembler, e.g. with a cut angle.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use velodyne::fixtures::VLP_16_DATA_PACKET;
+    /// use velodyne::frame::Assembler;
+    /// use velodyne::stream::PointStream;
+    /// use velodyne::units::Degrees;
+    /// use velodyne::vlp_16::Packet;
+    /// let points = Packet::new(&VLP_16_DATA_PACKET).unwrap().points().unwrap();
+    /// let mut assembler = Assembler::new();
+    /// assembler.cut_angle(Degrees(180.));
+    /// assert_eq!(1, points.into_iter().frames_with(assembler).count());
+    /// ```
+    fn frames_with(self, assembler: Assembler) -> Frames<Self> {
+        Frames {
+            points: self,
+            assembler,
+            done: false,
+        }
+    }
+
+    /// Sorts the points by time, holding at most `window` points back.
+    ///
+    /// Some consumers, e.g. LAS files with GPS times and many SLAM tools, need their input in
+    /// time order, but decoding and merging can reorder points slightly. The window bounds the
+    /// memory, so a point that's more than `window` points late is still emitted late. Offset
+    /// times are ordered across the top of the hour, and points whose time can't be compared
+    /// with the previous point's, i.e. an offset and an absolute time, are treated as
+    /// simultaneous with it.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `window` is zero.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use velodyne::fixtures::VLP_16_DATA_PACKET;
+    /// use velodyne::stream::PointStream;
+    /// use velodyne::vlp_16::Packet;
+    /// let points = Packet::new(&VLP_16_DATA_PACKET).unwrap().points().unwrap();
+    /// let sorted = points.into_iter().rev().sorted_by_time(1000).collect::<Vec<_>>();
+    /// let elapsed = |pair: &[velodyne::Point]| pair[1].time.duration_since(pair[0].time).unwrap();
+    /// assert!(sorted.windows(2).all(|pair| elapsed(pair).num_nanoseconds().unwrap() >= 0));
+    /// ```
+    fn sorted_by_time(self, window: usize) -> TimeSorted<Self> {
+        assert!(window > 0, "a sort needs a window of at least one point");
+        TimeSorted {
+            points: self,
+            window,
+            pending: BinaryHeap::new(),
+            last: None,
+            sequence: 0,
+        }
+    }
+
+    /// Groups the points into frames and sorts each frame by azimuth.
+    ///
+    /// One frame is held in memory at a time. Points with the same azimuth keep their order, so
+    /// the channels of a firing sequence stay together.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use velodyne::fixtures::VLP_16_DATA_PACKET;
+    /// use velodyne::stream::PointStream;
+    /// use velodyne::vlp_16::Packet;
+    /// let points = Packet::new(&VLP_16_DATA_PACKET).unwrap().points().unwrap();
+    /// let sorted = points.into_iter().sorted_by_azimuth().collect::<Vec<_>>();
+    /// let degrees = sorted.iter().map(|point| point.azimuth.degrees()).collect::<Vec<_>>();
+    /// assert!(degrees.windows(2).all(|pair| pair[0] <= pair[1]));
+    /// ```
+    fn sorted_by_azimuth(self) -> AzimuthSorted<Self> {
+        AzimuthSorted {
+            frames: self.frames(),
+            points: Vec::new().into_iter(),
+        }
+    }
+}
+
+impl<I: Iterator<Item = Point>> PointStream for I {}
+
+/// Keeps the points within a range of distances.
+#[derive(Clone, Debug)]
+pub struct RangeFilter<I> {
+    points: I,
+    min: f32,
+    max: f32,
+}
+
+/// Keeps the points within a field of view.
+#[derive(Clone, Debug)]
+pub struct Fov<I> {
+    points: I,
+    start: f32,
+    width: f32,
+}
+
+/// Keeps the points from some laser channels.
+#[derive(Clone, Debug)]
+pub struct Rings<I> {
+    points: I,
+    keep: [bool; 256],
+}
+
+/// Transforms points.
+#[derive(Clone, Debug)]
+pub struct Transformed<I> {
+    points: I,
+    transform: Transform,
+}
+
+/// Groups points into frames.
+#[derive(Debug)]
+pub struct Frames<I> {
+    points: I,
+    assembler: Assembler,
+    done: bool,
+}
+
+/// Sorts points by time within a bounded window.
+#[derive(Debug)]
+pub struct TimeSorted<I> {
+    points: I,
+    window: usize,
+    pending: BinaryHeap<Reverse<Pending>>,
+    last: Option<(Time, i64)>,
+    sequence: u64,
+}
+
+/// Sorts the points of each frame by azimuth.
+#[derive(Debug)]
+pub struct AzimuthSorted<I> {
+    frames: Frames<I>,
+    points: vec::IntoIter<Point>,
+}
+
+/// A point waiting in a `TimeSorted`, ordered by its time and then by its arrival.
+#[derive(Debug)]
+struct Pending {
+    nanoseconds: i64,
+    sequence: u64,
+    point: Point,
+}
+
+/// Decodes the points from a reader, created by `io::Read::points`.
+///
+/// The stream ends at the first packet that can't be read or parsed. Check `error` afterwards to
+/// tell an error from the end of the source.
+#[derive(Debug)]
+pub struct Points<R: Read> {
+    packets: Packets<R>,
+    decoder: Decoder,
+    points: vec::IntoIter<Point>,
+    error: Option<Error>,
+    done: bool,
+}
+
+impl<I: Iterator<Item = Point>> Iterator for RangeFilter<I> {
+    type Item = Point;
+
+    fn next(&mut self) -> Option<Point> {
+        let (min, max) = (self.min, self.max);
+        self.points.find(|point| {
+                             let range = point.range();
+                             range >= min && range <= max
+                         })
+    }
+}
+
+impl<I: Iterator<Item = Point>> Iterator for Fov<I> {
+    type Item = Point;
+
+    fn next(&mut self) -> Option<Point> {
+        let (start, width) = (self.start, self.width);
+        self.points.find(|point| {
+                             width >= 360. ||
+                             (point.azimuth.degrees() - start).rem_euclid(360.) <= width
+                         })
+    }
+}
+
+impl<I: Iterator<Item = Point>> Iterator for Rings<I> {
+    type Item = Point;
+
+    fn next(&mut self) -> Option<Point> {
+        let keep = &self.keep;
+        self.points.find(|point| keep[point.channel as usize])
+    }
+}
+
+impl<I: Iterator<Item = Point>> Iterator for Transformed<I> {
+    type Item = Point;
+
+    fn next(&mut self) -> Option<Point> {
+        self.points.next().map(|mut point| {
+                                   if point.range() > 0. {
+                                       self.transform.apply(&mut point);
+                                   }
+                                   point
+                               })
+    }
+}
+
+impl<I: Iterator<Item = Point>> Iterator for Frames<I> {
+    type Item = Frame;
+
+    fn next(&mut self) -> Option<Frame> {
+        if self.done {
+            return None;
+        }
+        for point in &mut self.points {
+            if let Some(frame) = self.assembler.push(point) {
+                return Some(frame);
+            }
+        }
+        self.done = true;
+        self.assembler.finish()
+    }
+}
+
+impl<I: Iterator<Item = Point>> TimeSorted<I> {
+    /// Returns the nanoseconds since the first point, following the times from point to point so
+    /// that offsets that roll over keep counting up.
+    fn nanoseconds(&mut self, time: Time) -> i64 {
+        let nanoseconds = match self.last {
+            Some((last, nanoseconds)) => {
+                let elapsed = time.duration_since(last)
+                    .and_then(|elapsed| elapsed.num_nanoseconds())
+                    .unwrap_or(0);
+                nanoseconds + elapsed
+            }
+            None => 0,
+        };
+        self.last = Some((time, nanoseconds));
+        nanoseconds
+    }
+}
+
+impl<I: Iterator<Item = Point>> Iterator for TimeSorted<I> {
+    type Item = Point;
+
+    fn next(&mut self) -> Option<Point> {
+        while self.pending.len() < self.window {
+            let point = match self.points.next() {
+                Some(point) => point,
+                None => break,
+            };
+            let nanoseconds = self.nanoseconds(point.time);
+            self.pending.push(Reverse(Pending {
+                                          nanoseconds,
+                                          sequence: self.sequence,
+                                          point,
+                                      }));
+            self.sequence += 1;
+        }
+        self.pending.pop().map(|Reverse(pending)| pending.point)
+    }
+}
+
+impl<I: Iterator<Item = Point>> Iterator for AzimuthSorted<I> {
+    type Item = Point;
+
+    fn next(&mut self) -> Option<Point> {
+        loop {
+            if let Some(point) = self.points.next() {
+                return Some(point);
+            }
+            let mut points = self.frames.next()?.points;
+            points.sort_by(|a, b| a.azimuth.degrees().total_cmp(&b.azimuth.degrees()));
+            self.points = points.into_iter();
+        }
+    }
+}
+
+impl Ord for Pending {
+    fn cmp(&self, other: &Pending) -> Ordering {
+        (self.nanoseconds, self.sequence).cmp(&(other.nanoseconds, other.sequence))
+    }
+}
+
+impl PartialOrd for Pending {
+    fn partial_cmp(&self, other: &Pending) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl PartialEq for Pending {
+    fn eq(&self, other: &Pending) -> bool {
+        self.cmp(other) == Ordering::Equal
+    }
+}
+
+impl Eq for Pending {}
+
+impl<R: Read> Points<R> {
+    pub(crate) fn new(read: R) -> Points<R> {
+        Points::with_decoder(read, Decoder::new())
+    }
+
+    pub(crate) fn with_decoder(read: R, decoder: Decoder) -> Points<R> {
+        Points {
+            packets: read.vlp_16_packets(),
+            decoder,
+            points: Vec::new().into_iter(),
+            error: None,
+            done: false,
+        }
+    }
+
+    /// Returns the error that ended the stream, if there was one.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use velodyne::io::{Pcap, Read};
+    /// use velodyne::stream::PointStream;
+    /// use velodyne::units::Meters;
+    /// let mut points = Pcap::open("data/single.pcap").unwrap().points();
+    /// let frames = points.by_ref().min_range(Meters(0.9)).frames().count();
+    /// assert!(points.error().is_none());
+    /// ```
+    pub fn error(&self) -> Option<&Error> {
+        self.error.as_ref()
+    }
+
+    /// Returns the decoder's time quality, as of the last packet read.
+    ///
+    /// See `vlp_16::Decoder::time_quality`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use velodyne::io::{Pcap, Read};
+    /// let mut points = Pcap::open("data/single.pcap").unwrap().points();
+    /// let count = points.by_ref().count();
+    /// assert_eq!(None, points.time_quality());
+    /// ```
+    pub fn time_quality(&self) -> Option<TimeQuality> {
+        self.decoder.time_quality()
+    }
+
+    /// Returns what the decoder has decoded so far, and how long it took.
+    ///
+    /// See `vlp_16::Decoder::metrics`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use velodyne::io::{Pcap, Read};
+    /// let mut points = Pcap::open("data/single.pcap").unwrap().points();
+    /// let count = points.by_ref().count();
+    /// assert_eq!(count, points.metrics().points);
+    /// ```
+    pub fn metrics(&self) -> DecodeMetrics {
+        self.decoder.metrics()
+    }
+
+    /// Returns the number of non-Velodyne packets, e.g. ARP or mDNS, that have been skipped.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use velodyne::io::{Pcap, Read};
+    /// let mut points = Pcap::open("data/single.pcap").unwrap().points();
+    /// let count = points.by_ref().count();
+    /// assert_eq!(0, points.skipped_packets());
+    /// ```
+    pub fn skipped_packets(&self) -> usize {
+        self.packets.skipped()
+    }
+
+    /// Returns the warnings about the data so far, including skipped packets.
+    ///
+    /// See `vlp_16::Decoder::warnings`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use velodyne::io::{Pcap, Read};
+    /// let mut points = Pcap::open("data/single.pcap").unwrap().points();
+    /// let count = points.by_ref().count();
+    /// assert!(points.warnings().is_empty());
+    /// ```
+    pub fn warnings(&self) -> &[DecodeWarning] {
+        self.decoder.warnings()
+    }
+}
+
+impl<R: Read> Iterator for Points<R> {
+    type Item = Point;
+
+    fn next(&mut self) -> Option<Point> {
+        loop {
+            if let Some(point) = self.points.next() {
+                return Some(point);
+            }
+            if self.done {
+                return None;
+            }
+            let skipped = self.packets.skipped();
+            let next = self.packets.next();
+            for _ in skipped..self.packets.skipped() {
+                self.decoder.warn(DecodeWarning::SkippedPacket);
+            }
+            let points = match next {
+                Some(Ok(packet)) => self.decoder.push(packet),
+                Some(Err(err)) => {
+                    warn!("stopping point stream: {:?}", err);
+                    self.error = Some(err);
+                    self.done = true;
+                    self.decoder.finish()
+                }
+                None => {
+                    self.done = true;
+                    self.decoder.finish()
+                }
+            };
+            if let Some(points) = points {
+                self.points = points.into_iter();
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use fixtures::VLP_16_DATA_PACKET;
+    use io::PcapSlice;
+    use vlp_16::Packet;
+
+    #[test]
+    fn fov() {
+        let points = Packet::new(&VLP_16_DATA_PACKET).unwrap().points().unwrap();
+        let all = points.len();
+        assert_eq!(all, points.clone().into_iter().fov(Degrees(0.), Degrees(360.)).count());
+        assert_eq!(all, points.clone().into_iter().fov(Degrees(200.), Degrees(250.)).count());
+        let some = points.clone().into_iter().fov(Degrees(230.), Degrees(250.)).count();
+        assert!(some > 0 && some < all);
+        // The complement of a field of view keeps everything else, and shares its edges.
+        assert!(points.into_iter().fov(Degrees(250.), Degrees(230.)).count() >= all - some);
+    }
+
+    #[test]
+    fn sorted_by_time() {
+        use chrono::Duration;
+        let mut points = Packet::new(&VLP_16_DATA_PACKET).unwrap().points().unwrap();
+        // Straddle the top of the hour.
+        for (i, point) in points.iter_mut().enumerate() {
+            let microseconds = (3_600_000_000 - 100 + i as i64) % 3_600_000_000;
+            point.time = Time::Offset(Duration::microseconds(microseconds));
+            point.channel = (i % 16) as u8;
+        }
+        let mut shuffled = points.clone();
+        for pair in shuffled.chunks_mut(2) {
+            pair.swap(0, 1);
+        }
+        let sorted = shuffled.into_iter().sorted_by_time(2).collect::<Vec<_>>();
+        assert_eq!(points.len(), sorted.len());
+        for (expected, point) in points.iter().zip(&sorted) {
+            assert_eq!(expected.channel, point.channel);
+        }
+    }
+
+    #[test]
+    fn points_stop_at_error() {
+        let mut bytes = include_bytes!("../data/single.pcap").to_vec();
+        let len = bytes.len();
+        // Corrupt the data packet's second start identifier, so it still looks like a data packet.
+        bytes[len - 1206 + 100] = 0;
+        let mut points = PcapSlice::new(bytes).unwrap().points();
+        assert_eq!(0, points.by_ref().count());
+        assert!(points.error().is_some());
+    }
+
+    #[test]
+    fn points_skip_foreign_packets() {
+        use chrono::UTC;
+        use io::PcapWriter;
+        use vlp_16::PACKET_HEADER_LEN;
+        let mut writer = PcapWriter::new(Vec::new()).unwrap();
+        // About the size of an ARP request, and of an mDNS query.
+        writer.write(&[0xff; 42], UTC::now()).unwrap();
+        writer.write(&VLP_16_DATA_PACKET[PACKET_HEADER_LEN..], UTC::now()).unwrap();
+        writer.write(&[0; 87], UTC::now()).unwrap();
+        let pcap = writer.into_inner();
+        let mut points = PcapSlice::new(&pcap[..]).unwrap().points();
+        assert_eq!(384, points.by_ref().count());
+        assert!(points.error().is_none());
+        assert_eq!(2, points.skipped_packets());
+        assert_eq!(vec![DecodeWarning::SkippedPacket; 2], points.warnings());
+
+        let mut packets = PcapSlice::new(&pcap[..]).unwrap().vlp_16_packets();
+        packets.strict(true);
+        assert!(packets.next().unwrap().is_err());
+    }
+}
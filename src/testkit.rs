@@ -0,0 +1,483 @@
+//! Generated streams of packets for integration tests.
+//!
+//! The fixtures are single packets, and most behavior worth testing, e.g. frame assembly, loss
+//! accounting, or dual-return decoding, needs many of them. A `Simulator` turns the VLP-16
+//! fixtures into a stream from a sensor spinning at a steady rate: every data block gets the
+//! azimuth and every packet the timestamp that the sensor would have sent, and position packets
+//! are interleaved at a fixed interval. The laser returns and the NMEA sentences are the
+//! fixtures', so the points are realistic but repeat from one packet to the next.
+//!
+//! Streams are read with `io::Read`, like any other source, or written out as pcap bytes for code
+//! that only takes captures. The canned streams cover the common cases:
+//!
+//! ```
+//! use velodyne::io::Read;
+//! use velodyne::testkit;
+//! let stream = testkit::full_rotation();
+//! assert_eq!(80 * 384, stream.points().count());
+//! ```
+//!
+//! This module is only built with the `testkit` feature, so add it to the dev-dependency:
+//!
+//! ```toml
+//! [dev-dependencies]
+//! velodyne = { version = "0.1", features = ["testkit"] }
+//! ```
+
+use Result;
+use byteorder::{ByteOrder, LittleEndian};
+use chrono::{DateTime, Duration, TimeZone, UTC};
+use fixtures::{VLP_16_DATA_PACKET, VLP_16_POSITION_PACKET};
+use io::{PcapWriter, Read};
+use time::HOUR_US;
+use timing;
+use vlp_16::ReturnMode;
+use wire::{self, NUM_DATA_BLOCKS, PACKET_HEADER_LEN};
+
+/// Generates a stream of VLP-16 packets.
+#[derive(Clone, Copy, Debug)]
+pub struct Simulator {
+    rpm: f64,
+    return_mode: ReturnMode,
+    position_interval: Option<Duration>,
+    drop_every: usize,
+    start: DateTime<UTC>,
+    azimuth: f64,
+}
+
+/// A generated stream of packets, with their capture times.
+#[derive(Clone, Debug)]
+pub struct Stream {
+    packets: Vec<(Vec<u8>, DateTime<UTC>)>,
+    next: usize,
+    data_packets: usize,
+    position_packets: usize,
+    lost: usize,
+}
+
+/// Returns a full rotation at 600 RPM in strongest-return mode, with a position packet every
+/// 25 milliseconds.
+///
+/// The stream starts ten degrees before the cut at zero degrees and ends a few degrees after it
+/// comes around again, so that a frame assembler gets one complete frame out of it. Real sensors
+/// only send a position packet every second, but that would leave a rotation with one at most.
+///
+/// # Examples
+///
+/// ```
+/// use velodyne::testkit;
+/// let stream = testkit::full_rotation();
+/// assert_eq!(80, stream.data_packets());
+/// assert_eq!(5, stream.position_packets());
+/// ```
+pub fn full_rotation() -> Stream {
+    Simulator::new()
+        .azimuth(350.)
+        .position_interval(Some(Duration::milliseconds(25)))
+        .rotations(1.05)
+}
+
+/// Returns one full rotation at 600 RPM in dual-return mode.
+///
+/// # Examples
+///
+/// ```
+/// use velodyne::testkit;
+/// let stream = testkit::dual_return_rotation();
+/// assert_eq!(151, stream.data_packets());
+/// ```
+pub fn dual_return_rotation() -> Stream {
+    Simulator::new().return_mode(ReturnMode::DualReturn).rotations(1.)
+}
+
+/// Returns three rotations at 600 RPM in strongest-return mode, missing every tenth data packet.
+///
+/// # Examples
+///
+/// ```
+/// use velodyne::testkit;
+/// let stream = testkit::lossy_stream();
+/// assert_eq!(22, stream.lost());
+/// ```
+pub fn lossy_stream() -> Stream {
+    Simulator::new().drop_every(10).rotations(3.)
+}
+
+impl Simulator {
+    /// Creates a simulator for a sensor spinning at 600 RPM in strongest-return mode, which sends
+    /// a position packet every second.
+    ///
+    /// Streams start at a fixed time, so that they're the same on every run.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use velodyne::testkit::Simulator;
+    /// let stream = Simulator::new().packets(10);
+    /// assert_eq!(10, stream.data_packets());
+    /// ```
+    pub fn new() -> Simulator {
+        Simulator::default()
+    }
+
+    /// Sets the rotation rate, in revolutions per minute.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use velodyne::testkit::Simulator;
+    /// let stream = Simulator::new().rpm(1200.).rotations(1.);
+    /// assert_eq!(38, stream.data_packets());
+    /// ```
+    pub fn rpm(&mut self, rpm: f64) -> &mut Simulator {
+        self.rpm = rpm;
+        self
+    }
+
+    /// Sets the return mode.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use velodyne::testkit::Simulator;
+    /// use velodyne::vlp_16::ReturnMode;
+    /// let stream = Simulator::new().return_mode(ReturnMode::LastReturn).packets(1);
+    /// ```
+    pub fn return_mode(&mut self, return_mode: ReturnMode) -> &mut Simulator {
+        self.return_mode = return_mode;
+        self
+    }
+
+    /// Sets the time between position packets, or none to leave them out.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use velodyne::testkit::Simulator;
+    /// let stream = Simulator::new().position_interval(None).packets(10);
+    /// assert_eq!(0, stream.position_packets());
+    /// ```
+    pub fn position_interval(&mut self, interval: Option<Duration>) -> &mut Simulator {
+        self.position_interval = interval;
+        self
+    }
+
+    /// Leaves out every nth data packet, as if it was lost on the network. Zero keeps them all.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use velodyne::testkit::Simulator;
+    /// let stream = Simulator::new().drop_every(2).packets(10);
+    /// assert_eq!(5, stream.data_packets());
+    /// assert_eq!(5, stream.lost());
+    /// ```
+    pub fn drop_every(&mut self, n: usize) -> &mut Simulator {
+        self.drop_every = n;
+        self
+    }
+
+    /// Sets the capture time of the start of the stream, from which the sensor's timestamps are
+    /// derived too.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # extern crate chrono;
+    /// # extern crate velodyne;
+    /// # fn main() {
+    /// use chrono::{TimeZone, UTC};
+    /// use velodyne::testkit::Simulator;
+    /// let stream = Simulator::new().start(UTC.timestamp(1_600_000_000, 0)).packets(1);
+    /// # }
+    /// ```
+    pub fn start(&mut self, start: DateTime<UTC>) -> &mut Simulator {
+        self.start = start;
+        self
+    }
+
+    /// Sets the azimuth of the first data block, in degrees.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use velodyne::testkit::Simulator;
+    /// let stream = Simulator::new().azimuth(180.).packets(1);
+    /// ```
+    pub fn azimuth(&mut self, azimuth: f64) -> &mut Simulator {
+        self.azimuth = azimuth;
+        self
+    }
+
+    /// Generates enough data packets to cover some rotations.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use velodyne::testkit::Simulator;
+    /// let stream = Simulator::new().rotations(2.);
+    /// assert_eq!(151, stream.data_packets());
+    /// ```
+    pub fn rotations(&self, rotations: f64) -> Stream {
+        let per_rotation = timing::VLP_16.packets_per_rotation(self.rpm, self.return_mode);
+        self.packets((per_rotation * rotations).ceil() as usize)
+    }
+
+    /// Generates a number of data packets, including the ones that are dropped.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use velodyne::testkit::Simulator;
+    /// let stream = Simulator::new().packets(1000);
+    /// assert_eq!(2, stream.position_packets());
+    /// ```
+    pub fn packets(&self, n: usize) -> Stream {
+        let packet_ns = timing::VLP_16.packet_duration(self.return_mode)
+            .num_nanoseconds()
+            .unwrap_or(0);
+        let position_ns = self.position_interval
+            .and_then(|interval| interval.num_nanoseconds())
+            .filter(|&ns| ns > 0);
+        let mut stream = Stream {
+            packets: Vec::new(),
+            next: 0,
+            data_packets: 0,
+            position_packets: 0,
+            lost: 0,
+        };
+        let mut next_position = 0;
+        for i in 0..n {
+            let ns = i as i64 * packet_ns;
+            if let Some(interval) = position_ns {
+                while next_position <= ns {
+                    let bytes = self.position_packet(next_position);
+                    stream.packets.push((bytes, self.capture_time(next_position)));
+                    stream.position_packets += 1;
+                    next_position += interval;
+                }
+            }
+            if self.drop_every > 0 && (i + 1) % self.drop_every == 0 {
+                stream.lost += 1;
+                continue;
+            }
+            let bytes = self.data_packet(ns);
+            stream.packets.push((bytes, self.capture_time(ns + packet_ns)));
+            stream.data_packets += 1;
+        }
+        stream
+    }
+
+    /// Returns a data packet whose first firing is `ns` after the start.
+    fn data_packet(&self, ns: i64) -> Vec<u8> {
+        let mut bytes = VLP_16_DATA_PACKET.to_vec();
+        let blocks_per_firing = if self.return_mode == ReturnMode::DualReturn {
+            2
+        } else {
+            1
+        };
+        let block_ns = timing::VLP_16.sequences_per_data_block as i64 *
+                       timing::VLP_16.firing_cycle_ns;
+        let centidegrees_per_ns = self.rpm * 36_000. / 60e9;
+        for block in 0..NUM_DATA_BLOCKS {
+            let firing_ns = ns + (block / blocks_per_firing) as i64 * block_ns;
+            let azimuth = (self.azimuth * 100. + firing_ns as f64 * centidegrees_per_ns)
+                .round()
+                .rem_euclid(36_000.);
+            let offset = PACKET_HEADER_LEN + wire::azimuth(block).offset;
+            LittleEndian::write_u16(&mut bytes[offset..], azimuth as u16);
+        }
+        let offset = PACKET_HEADER_LEN + wire::DATA_TIMESTAMP.offset;
+        LittleEndian::write_u32(&mut bytes[offset..], self.device_micros(ns));
+        bytes[PACKET_HEADER_LEN + wire::RETURN_MODE.offset] = return_mode_code(self.return_mode);
+        bytes
+    }
+
+    /// Returns a position packet sent `ns` after the start.
+    fn position_packet(&self, ns: i64) -> Vec<u8> {
+        let mut bytes = VLP_16_POSITION_PACKET.to_vec();
+        let offset = PACKET_HEADER_LEN + wire::POSITION_TIMESTAMP.offset;
+        LittleEndian::write_u32(&mut bytes[offset..], self.device_micros(ns));
+        bytes
+    }
+
+    /// Returns the sensor's timestamp, in microseconds past the hour, `ns` after the start.
+    fn device_micros(&self, ns: i64) -> u32 {
+        let start_us = self.start.timestamp() * 1_000_000 +
+                       i64::from(self.start.timestamp_subsec_micros());
+        ((start_us + ns / 1000).rem_euclid(HOUR_US)) as u32
+    }
+
+    fn capture_time(&self, ns: i64) -> DateTime<UTC> {
+        self.start + Duration::nanoseconds(ns)
+    }
+}
+
+/// Returns the factory byte of a return mode.
+fn return_mode_code(return_mode: ReturnMode) -> u8 {
+    match return_mode {
+        ReturnMode::StrongestReturn => 0x37,
+        ReturnMode::LastReturn => 0x38,
+        ReturnMode::DualReturn => 0x39,
+        ReturnMode::Unknown(n) => n,
+    }
+}
+
+impl Default for Simulator {
+    fn default() -> Simulator {
+        Simulator {
+            rpm: 600.,
+            return_mode: ReturnMode::StrongestReturn,
+            position_interval: Some(Duration::seconds(1)),
+            drop_every: 0,
+            start: UTC.timestamp(1_500_000_000, 0),
+            azimuth: 0.,
+        }
+    }
+}
+
+impl Stream {
+    /// Returns the number of packets in this stream.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use velodyne::testkit;
+    /// assert_eq!(85, testkit::full_rotation().len());
+    /// ```
+    pub fn len(&self) -> usize {
+        self.packets.len()
+    }
+
+    /// Returns true if this stream has no packets.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use velodyne::testkit::Simulator;
+    /// assert!(Simulator::new().packets(0).is_empty());
+    /// ```
+    pub fn is_empty(&self) -> bool {
+        self.packets.is_empty()
+    }
+
+    /// Returns the number of data packets in this stream.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use velodyne::testkit;
+    /// assert_eq!(80, testkit::full_rotation().data_packets());
+    /// ```
+    pub fn data_packets(&self) -> usize {
+        self.data_packets
+    }
+
+    /// Returns the number of position packets in this stream.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use velodyne::testkit;
+    /// assert_eq!(1, testkit::dual_return_rotation().position_packets());
+    /// ```
+    pub fn position_packets(&self) -> usize {
+        self.position_packets
+    }
+
+    /// Returns the number of data packets that were dropped from this stream.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use velodyne::testkit;
+    /// assert_eq!(0, testkit::full_rotation().lost());
+    /// ```
+    pub fn lost(&self) -> usize {
+        self.lost
+    }
+
+    /// Writes this stream's packets, with their capture times, as a pcap.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use velodyne::io::{PcapSlice, Read};
+    /// use velodyne::testkit;
+    /// let pcap = testkit::full_rotation().to_pcap().unwrap();
+    /// let slice = PcapSlice::new(&pcap[..]).unwrap();
+    /// assert_eq!(85, slice.vlp_16_packets().count());
+    /// ```
+    pub fn to_pcap(&self) -> Result<Vec<u8>> {
+        let mut writer = PcapWriter::new(Vec::new())?;
+        for &(ref bytes, time) in &self.packets {
+            writer.write(bytes, time)?;
+        }
+        Ok(writer.into_inner())
+    }
+}
+
+impl Read for Stream {
+    fn read(&mut self) -> Option<Result<&[u8]>> {
+        let i = self.next;
+        if i >= self.packets.len() {
+            return None;
+        }
+        self.next += 1;
+        Some(Ok(&self.packets[i].0))
+    }
+
+    fn timestamp(&self) -> Option<DateTime<UTC>> {
+        self.next.checked_sub(1).map(|i| self.packets[i].1)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use info::Scan;
+    use vlp_16::{Packet, Sensor};
+
+    #[test]
+    fn full_rotation_covers_every_azimuth() {
+        let scan = full_rotation().quick_scan().unwrap();
+        assert_eq!(80, scan.data_packets);
+        assert_eq!(Some(Sensor::VLP_16), scan.sensor);
+        assert_eq!(0, scan.gaps);
+        let rpm = scan.rpm().unwrap();
+        assert!((rpm - 600.).abs() < 1., "{}", rpm);
+        let mut stream = full_rotation();
+        let mut azimuths = Vec::new();
+        while let Some(bytes) = stream.read() {
+            let packet = Packet::new(bytes.unwrap()).unwrap();
+            if let Some(points) = packet.points() {
+                azimuths.extend(points.iter().map(|p| p.azimuth.degrees()));
+            }
+        }
+        let turned = azimuths.windows(2)
+            .map(|w| (w[1] - w[0]).rem_euclid(360.))
+            .sum::<f32>();
+        assert!(turned >= 360., "{}", turned);
+    }
+
+    #[test]
+    fn dual_return_blocks_share_azimuths() {
+        let mut stream = dual_return_rotation();
+        // The first packet is a position packet.
+        stream.skip_packets(1).unwrap();
+        let bytes = stream.read().unwrap().unwrap();
+        let packet = Packet::new(bytes).unwrap();
+        assert_eq!(Some(ReturnMode::DualReturn), packet.return_mode());
+        let payload = &bytes[PACKET_HEADER_LEN..];
+        for pair in 0..NUM_DATA_BLOCKS / 2 {
+            let first = LittleEndian::read_u16(&payload[wire::azimuth(2 * pair).offset..]);
+            let second = LittleEndian::read_u16(&payload[wire::azimuth(2 * pair + 1).offset..]);
+            assert_eq!(first, second);
+        }
+        let mut scan = Scan::default();
+        while let Some(bytes) = stream.read() {
+            scan.add(bytes.unwrap());
+        }
+        assert_eq!(0, scan.gaps);
+    }
+}
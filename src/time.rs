@@ -0,0 +1,1015 @@
+//! Assign absolute times to points.
+//!
+//! Data packets are only stamped with the time since the top of the hour, so a point's absolute
+//! time has to come from some other reference. Different deployments have different references
+//! (a GPS receiver, the capture machine's clock, or just a known start time), so the reference is
+//! pluggable.
+
+use {Point, Result};
+use chrono::{DateTime, Duration, TimeZone, UTC};
+use io::Read;
+use point::Time;
+use std::fmt::Debug;
+use vlp_16::{self, Packet, PpsStatus};
+
+/// The number of microseconds in an hour, when packet timestamps roll over.
+pub(crate) const HOUR_US: i64 = 3_600_000_000;
+/// The number of seconds in a GPS week.
+const SECONDS_PER_WEEK: i64 = 7 * 24 * 60 * 60;
+/// The offset between GPS time and LAS adjusted standard GPS time.
+const ADJUSTED_STANDARD_OFFSET: f64 = 1e9;
+/// The dates on which leap seconds took effect since the GPS epoch, and GPS time minus UTC after
+/// each one.
+const LEAP_SECONDS: [((i32, u32, u32), i64); 18] = [((1981, 7, 1), 1),
+                                                   ((1982, 7, 1), 2),
+                                                   ((1983, 7, 1), 3),
+                                                   ((1985, 7, 1), 4),
+                                                   ((1988, 1, 1), 5),
+                                                   ((1990, 1, 1), 6),
+                                                   ((1991, 1, 1), 7),
+                                                   ((1992, 7, 1), 8),
+                                                   ((1993, 7, 1), 9),
+                                                   ((1994, 7, 1), 10),
+                                                   ((1996, 1, 1), 11),
+                                                   ((1997, 7, 1), 12),
+                                                   ((1999, 1, 1), 13),
+                                                   ((2006, 1, 1), 14),
+                                                   ((2009, 1, 1), 15),
+                                                   ((2012, 7, 1), 16),
+                                                   ((2015, 7, 1), 17),
+                                                   ((2017, 1, 1), 18)];
+
+/// A source of absolute time.
+pub trait TimeSource: Debug {
+    /// Observes a packet, along with the time at which it was captured, if known.
+    ///
+    /// Time sources use this to learn or update their time reference. The default implementation
+    /// does nothing.
+    fn observe(&mut self, packet: &Packet, capture_time: Option<DateTime<UTC>>) {
+        let _ = (packet, capture_time);
+    }
+
+    /// Converts a packet timestamp, i.e. the duration since the top of the hour, into an
+    /// absolute time.
+    ///
+    /// Returns `None` if the time source doesn't have a reference yet.
+    fn absolute(&self, offset: Duration) -> Option<DateTime<UTC>>;
+
+    /// Returns how well the sensor's clock is synchronized, as of the last observed packet.
+    ///
+    /// Returns `None` if this time source can't tell, e.g. because it doesn't use the sensor's
+    /// GPS input. The default implementation returns `None`.
+    fn quality(&self) -> Option<TimeQuality> {
+        None
+    }
+
+    /// Converts the offset times of points into absolute times, where possible.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # extern crate chrono;
+    /// # extern crate velodyne;
+    /// # fn main() {
+    /// use chrono::{TimeZone, UTC};
+    /// use velodyne::fixtures::VLP_16_DATA_PACKET;
+    /// use velodyne::point::Time;
+    /// use velodyne::time::{Epoch, TimeSource};
+    /// use velodyne::vlp_16::Packet;
+    /// let mut points = Packet::new(&VLP_16_DATA_PACKET).unwrap().points().unwrap();
+    /// Epoch::new(UTC.ymd(2017, 1, 1).and_hms(12, 0, 0)).stamp(&mut points);
+    /// match points[0].time {
+    ///     Time::Absolute(time) => assert_eq!(UTC.ymd(2017, 1, 1).and_hms_micro(12, 41, 7, 108343),
+    ///                                        time),
+    ///     Time::Offset(_) => panic!("time wasn't stamped"),
+    /// }
+    /// # }
+    /// ```
+    fn stamp(&self, points: &mut [Point]) {
+        for point in points {
+            if let Time::Offset(offset) = point.time {
+                if let Some(time) = self.absolute(offset) {
+                    point.time = Time::Absolute(time);
+                }
+            }
+        }
+    }
+}
+
+/// A packet's timestamp, the microseconds since the top of the hour by the sensor's clock.
+///
+/// Which hour isn't in the packet, so the timestamp can't be added to an absolute time directly;
+/// that goes wrong around the top of the hour. Convert it with `absolute` and a `TimeSource`,
+/// which knows the hour, instead.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct HourOffset(u32);
+
+/// Uses the GPS time from the NMEA messages in position packets.
+///
+/// Invalid positions are ignored. By default, the GPS time is used even if the sensor isn't
+/// locked to the receiver's PPS signal, see `require_sync`.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct GpsTime {
+    reference: Option<Reference>,
+    quality: TimeQuality,
+    require_sync: bool,
+}
+
+/// How well the sensor's clock is synchronized to a GPS receiver.
+///
+/// The sensor's timestamps are only trustworthy when it is locked to the receiver's pulse per
+/// second (PPS) signal. Without a lock, the sensor's clock runs free and drifts.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum TimeQuality {
+    /// The PPS signal is locked and the receiver has a valid fix.
+    Locked,
+    /// The sensor was locked, but has since lost the PPS signal or the fix, so its clock is
+    /// running on from the last lock.
+    Holdover,
+    /// The sensor has never been locked, so its clock is free-running.
+    #[default]
+    Unsynchronized,
+}
+
+/// Uses the capture time of the packets, e.g. from a pcap file's packet headers.
+///
+/// Each packet's timestamp is anchored to its capture time, so the relative timing between points
+/// still comes from the sensor.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct CaptureTime {
+    reference: Option<Reference>,
+}
+
+/// Anchors packet timestamps to the capture clock with a single, fixed offset.
+///
+/// Without a GPS receiver, the sensor's clock counts from an arbitrary top of the hour. This
+/// estimates that hour and the offset from the capture times, keeping the packet that arrived with
+/// the least delay as its reference, so, unlike `CaptureTime`, network and capture jitter don't
+/// leak into the point times. The offset is fixed, so the sensor's clock drift does.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct CaptureAnchor {
+    reference: Option<Reference>,
+}
+
+/// Uses one time source, and another until the first has a reference.
+///
+/// Times jump when the primary source gets its first reference, e.g. when a GPS receiver gets a
+/// fix partway through a run.
+#[derive(Clone, Copy, Debug)]
+pub struct Fallback<P, S> {
+    primary: P,
+    secondary: S,
+}
+
+/// Uses a user-provided top of the hour.
+///
+/// Packet timestamps roll over every hour, which the epoch follows as long as it observes
+/// packets.
+#[derive(Clone, Copy, Debug)]
+pub struct Epoch {
+    top_of_hour: DateTime<UTC>,
+    reference: Option<Reference>,
+}
+
+/// Compares device timestamps with capture timestamps over a run.
+///
+/// A sensor that is locked to a PPS signal keeps a constant offset from a well-behaved capture
+/// clock. If the offset starts to drift, or jumps partway through a run, the sensor probably lost
+/// its lock.
+#[derive(Clone, Debug, Default)]
+pub struct DriftAnalyzer {
+    samples: Vec<(f64, f64)>,
+    start: Option<DateTime<UTC>>,
+}
+
+/// The result of a drift analysis.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Drift {
+    /// The number of packets that were analyzed.
+    pub samples: usize,
+    /// The capture time minus the device time at the start of the run, in seconds.
+    pub offset: f64,
+    /// The change in offset per second of capture time, e.g. `1e-6` is one part per million.
+    pub rate: f64,
+    /// The largest difference between an observed offset and the linear fit, in seconds.
+    pub max_residual: f64,
+}
+
+/// Converts between UTC and GPS time.
+///
+/// GPS time counts seconds from 1980-01-06 without leap seconds, so it runs ahead of UTC by the
+/// number of leap seconds since then. By default, that number comes from a leap second table.
+#[derive(Clone, Debug, Default)]
+pub struct GpsConverter {
+    leap_seconds: Option<i64>,
+    table: LeapSeconds,
+}
+
+/// A table of leap seconds.
+///
+/// The table includes every leap second up to its release. Leap seconds are announced months
+/// ahead, so new ones can be added without waiting for a new release.
+#[derive(Clone, Debug)]
+pub struct LeapSeconds {
+    entries: Vec<(DateTime<UTC>, i64)>,
+}
+
+/// A time as a GPS week and the seconds into that week.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct GpsWeekTime {
+    /// The number of weeks since the GPS epoch, without rollover.
+    pub week: i64,
+    /// The seconds since the start of the week.
+    pub seconds: f64,
+}
+
+/// An absolute time and the packet timestamp it corresponds to.
+#[derive(Clone, Copy, Debug)]
+struct Reference {
+    time: DateTime<UTC>,
+    offset: Duration,
+}
+
+impl GpsTime {
+    /// Creates a new GPS time source, which won't have a reference until it observes a valid
+    /// position packet.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use velodyne::time::GpsTime;
+    /// let gps_time = GpsTime::new();
+    /// ```
+    pub fn new() -> GpsTime {
+        GpsTime::default()
+    }
+
+    /// Refuses to assign absolute times while the sensor is unsynchronized.
+    ///
+    /// Times are still assigned in holdover, since the sensor's clock was set by a lock.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use velodyne::time::GpsTime;
+    /// let mut gps_time = GpsTime::new();
+    /// gps_time.require_sync(true);
+    /// ```
+    pub fn require_sync(&mut self, require_sync: bool) -> &mut GpsTime {
+        self.require_sync = require_sync;
+        self
+    }
+}
+
+impl TimeQuality {
+    /// Returns the time quality after a position packet, given the quality before it.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use velodyne::time::TimeQuality;
+    /// use velodyne::vlp_16::PpsStatus;
+    /// let quality = TimeQuality::Unsynchronized.update(PpsStatus::Locked, true);
+    /// assert_eq!(TimeQuality::Locked, quality);
+    /// assert_eq!(TimeQuality::Holdover, quality.update(PpsStatus::Absent, true));
+    /// ```
+    pub fn update(self, pps: PpsStatus, valid: bool) -> TimeQuality {
+        match (pps, valid, self) {
+            (PpsStatus::Locked, true, _) => TimeQuality::Locked,
+            (_, _, TimeQuality::Unsynchronized) => TimeQuality::Unsynchronized,
+            _ => TimeQuality::Holdover,
+        }
+    }
+}
+
+impl TimeSource for GpsTime {
+    fn observe(&mut self, packet: &Packet, _: Option<DateTime<UTC>>) {
+        let position = match packet.position() {
+            Some(Ok(position)) => position,
+            _ => return,
+        };
+        if let Some(status) = packet.position_status() {
+            self.quality = self.quality.update(status.pps, position.valid);
+        }
+        if !position.valid {
+            return;
+        }
+        let since_top_of_hour = Duration::seconds(position.datetime.timestamp() % 3600);
+        let reference = Reference {
+            time: position.datetime,
+            offset: since_top_of_hour,
+        };
+        let offset = packet.timestamp().duration();
+        self.reference = Some(Reference {
+                                  time: reference.absolute(offset),
+                                  offset,
+                              });
+    }
+
+    fn absolute(&self, offset: Duration) -> Option<DateTime<UTC>> {
+        if self.require_sync && self.quality == TimeQuality::Unsynchronized {
+            return None;
+        }
+        self.reference.map(|reference| reference.absolute(offset))
+    }
+
+    fn quality(&self) -> Option<TimeQuality> {
+        Some(self.quality)
+    }
+}
+
+impl CaptureTime {
+    /// Creates a new capture time source, which won't have a reference until it observes a
+    /// packet with a capture time.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use velodyne::time::CaptureTime;
+    /// let capture_time = CaptureTime::new();
+    /// ```
+    pub fn new() -> CaptureTime {
+        CaptureTime::default()
+    }
+}
+
+impl TimeSource for CaptureTime {
+    fn observe(&mut self, packet: &Packet, capture_time: Option<DateTime<UTC>>) {
+        if let Some(time) = capture_time {
+            self.reference = Some(Reference {
+                                      time,
+                                      offset: packet.timestamp().duration(),
+                                  });
+        }
+    }
+
+    fn absolute(&self, offset: Duration) -> Option<DateTime<UTC>> {
+        self.reference.map(|reference| reference.absolute(offset))
+    }
+}
+
+impl CaptureAnchor {
+    /// Creates a new capture anchor, which won't have a reference until it observes a packet with
+    /// a capture time.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use velodyne::time::CaptureAnchor;
+    /// let capture_anchor = CaptureAnchor::new();
+    /// ```
+    pub fn new() -> CaptureAnchor {
+        CaptureAnchor::default()
+    }
+}
+
+impl TimeSource for CaptureAnchor {
+    fn observe(&mut self, packet: &Packet, capture_time: Option<DateTime<UTC>>) {
+        let time = match capture_time {
+            Some(time) => time,
+            None => return,
+        };
+        let offset = packet.timestamp().duration();
+        // Packets can only arrive after they are sent, so an earlier arrival is a better anchor.
+        if self.reference.is_none_or(|reference| time < reference.absolute(offset)) {
+            self.reference = Some(Reference { time, offset });
+        }
+    }
+
+    fn absolute(&self, offset: Duration) -> Option<DateTime<UTC>> {
+        self.reference.map(|reference| reference.absolute(offset))
+    }
+}
+
+impl<P: TimeSource, S: TimeSource> Fallback<P, S> {
+    /// Creates a new time source that falls back from one source to another.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use velodyne::time::{CaptureAnchor, Fallback, GpsTime};
+    /// let time_source = Fallback::new(GpsTime::new(), CaptureAnchor::new());
+    /// ```
+    pub fn new(primary: P, secondary: S) -> Fallback<P, S> {
+        Fallback { primary, secondary }
+    }
+}
+
+impl<P: TimeSource, S: TimeSource> TimeSource for Fallback<P, S> {
+    fn observe(&mut self, packet: &Packet, capture_time: Option<DateTime<UTC>>) {
+        self.primary.observe(packet, capture_time);
+        self.secondary.observe(packet, capture_time);
+    }
+
+    fn absolute(&self, offset: Duration) -> Option<DateTime<UTC>> {
+        self.primary.absolute(offset).or_else(|| self.secondary.absolute(offset))
+    }
+
+    fn quality(&self) -> Option<TimeQuality> {
+        self.primary.quality().or_else(|| self.secondary.quality())
+    }
+}
+
+impl Epoch {
+    /// Creates a new epoch time source from the top of the hour in which the packets start.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # extern crate chrono;
+    /// # extern crate velodyne;
+    /// # fn main() {
+    /// use chrono::{TimeZone, UTC};
+    /// use velodyne::time::Epoch;
+    /// let epoch = Epoch::new(UTC.ymd(2017, 1, 1).and_hms(12, 0, 0));
+    /// # }
+    /// ```
+    pub fn new(top_of_hour: DateTime<UTC>) -> Epoch {
+        Epoch {
+            top_of_hour,
+            reference: None,
+        }
+    }
+}
+
+impl TimeSource for Epoch {
+    fn observe(&mut self, packet: &Packet, _: Option<DateTime<UTC>>) {
+        let offset = packet.timestamp().duration();
+        self.reference = self.absolute(offset).map(|time| Reference { time, offset });
+    }
+
+    fn absolute(&self, offset: Duration) -> Option<DateTime<UTC>> {
+        match self.reference {
+            Some(reference) => Some(reference.absolute(offset)),
+            None => Some(self.top_of_hour + offset),
+        }
+    }
+}
+
+impl GpsConverter {
+    /// Creates a new converter that uses the built-in leap second table.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use velodyne::time::GpsConverter;
+    /// let converter = GpsConverter::new();
+    /// ```
+    pub fn new() -> GpsConverter {
+        GpsConverter::default()
+    }
+
+    /// Uses a fixed number of leap seconds between GPS time and UTC instead of the table.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use velodyne::time::GpsConverter;
+    /// let mut converter = GpsConverter::new();
+    /// converter.leap_seconds(17);
+    /// ```
+    pub fn leap_seconds(&mut self, leap_seconds: i64) -> &mut GpsConverter {
+        self.leap_seconds = Some(leap_seconds);
+        self
+    }
+
+    /// Sets the leap second table.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use velodyne::time::{GpsConverter, LeapSeconds};
+    /// let mut converter = GpsConverter::new();
+    /// converter.table(LeapSeconds::new());
+    /// ```
+    pub fn table(&mut self, table: LeapSeconds) -> &mut GpsConverter {
+        self.table = table;
+        self
+    }
+
+    /// Returns the number of seconds since the GPS epoch.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # extern crate chrono;
+    /// # extern crate velodyne;
+    /// # fn main() {
+    /// use chrono::{TimeZone, UTC};
+    /// # use velodyne::time::GpsConverter;
+    /// let seconds = GpsConverter::new().seconds(UTC.ymd(1980, 1, 6).and_hms(0, 0, 0));
+    /// assert_eq!(0., seconds);
+    /// # }
+    /// ```
+    pub fn seconds(&self, time: DateTime<UTC>) -> f64 {
+        let elapsed = time.signed_duration_since(gps_epoch());
+        let seconds = elapsed.num_seconds();
+        let nanoseconds = (elapsed - Duration::seconds(seconds)).num_nanoseconds().unwrap_or(0);
+        let leap_seconds = self.leap_seconds.unwrap_or_else(|| self.table.at(time));
+        (seconds + leap_seconds) as f64 + nanoseconds as f64 / 1e9
+    }
+
+    /// Converts seconds since the GPS epoch into UTC.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # extern crate chrono;
+    /// # extern crate velodyne;
+    /// # fn main() {
+    /// use chrono::{TimeZone, UTC};
+    /// # use velodyne::time::GpsConverter;
+    /// let converter = GpsConverter::new();
+    /// let time = UTC.ymd(2017, 1, 1).and_hms(0, 0, 0);
+    /// assert_eq!(time, converter.utc(converter.seconds(time)));
+    /// # }
+    /// ```
+    pub fn utc(&self, seconds: f64) -> DateTime<UTC> {
+        let leap_seconds = self.leap_seconds.unwrap_or_else(|| self.table.at_gps(seconds));
+        let whole = seconds.floor();
+        let nanoseconds = ((seconds - whole) * 1e9).round() as i64;
+        gps_epoch() + Duration::seconds(whole as i64 - leap_seconds) +
+        Duration::nanoseconds(nanoseconds)
+    }
+
+    /// Returns the GPS week and seconds of the week.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # extern crate chrono;
+    /// # extern crate velodyne;
+    /// # fn main() {
+    /// use chrono::{TimeZone, UTC};
+    /// # use velodyne::time::GpsConverter;
+    /// let week_time = GpsConverter::new().week_time(UTC.ymd(2017, 1, 1).and_hms(0, 0, 0));
+    /// assert_eq!(1930, week_time.week);
+    /// assert_eq!(18., week_time.seconds);
+    /// # }
+    /// ```
+    pub fn week_time(&self, time: DateTime<UTC>) -> GpsWeekTime {
+        let seconds = self.seconds(time);
+        let week = (seconds / SECONDS_PER_WEEK as f64).floor();
+        GpsWeekTime {
+            week: week as i64,
+            seconds: seconds - week * SECONDS_PER_WEEK as f64,
+        }
+    }
+
+    /// Returns the adjusted standard GPS time, which is GPS time minus one billion seconds.
+    ///
+    /// This is the time used by LAS files when their global encoding says so.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # extern crate chrono;
+    /// # extern crate velodyne;
+    /// # fn main() {
+    /// use chrono::{TimeZone, UTC};
+    /// # use velodyne::time::GpsConverter;
+    /// let time = UTC.ymd(2015, 7, 23).and_hms(21, 41, 6);
+    /// assert_eq!(121722883., GpsConverter::new().adjusted_standard(time));
+    /// # }
+    /// ```
+    pub fn adjusted_standard(&self, time: DateTime<UTC>) -> f64 {
+        self.seconds(time) - ADJUSTED_STANDARD_OFFSET
+    }
+
+    /// Converts adjusted standard GPS time into UTC.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # extern crate chrono;
+    /// # extern crate velodyne;
+    /// # fn main() {
+    /// use chrono::{TimeZone, UTC};
+    /// # use velodyne::time::GpsConverter;
+    /// let time = UTC.ymd(2015, 7, 23).and_hms(21, 41, 6);
+    /// assert_eq!(time, GpsConverter::new().utc_from_adjusted_standard(121722883.));
+    /// # }
+    /// ```
+    pub fn utc_from_adjusted_standard(&self, seconds: f64) -> DateTime<UTC> {
+        self.utc(seconds + ADJUSTED_STANDARD_OFFSET)
+    }
+}
+
+impl LeapSeconds {
+    /// Creates the built-in leap second table.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use velodyne::time::LeapSeconds;
+    /// let leap_seconds = LeapSeconds::new();
+    /// ```
+    pub fn new() -> LeapSeconds {
+        LeapSeconds::default()
+    }
+
+    /// Adds a leap second, given the UTC time it takes effect and GPS time minus UTC after it.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # extern crate chrono;
+    /// # extern crate velodyne;
+    /// # fn main() {
+    /// use chrono::{TimeZone, UTC};
+    /// # use velodyne::time::LeapSeconds;
+    /// let mut leap_seconds = LeapSeconds::new();
+    /// leap_seconds.insert(UTC.ymd(2030, 1, 1).and_hms(0, 0, 0), 19);
+    /// assert_eq!(19, leap_seconds.at(UTC.ymd(2030, 6, 1).and_hms(0, 0, 0)));
+    /// # }
+    /// ```
+    pub fn insert(&mut self, time: DateTime<UTC>, leap_seconds: i64) -> &mut LeapSeconds {
+        let index = self.entries.iter().take_while(|&&(other, _)| other < time).count();
+        if self.entries.get(index).map(|&(other, _)| other == time).unwrap_or(false) {
+            self.entries[index].1 = leap_seconds;
+        } else {
+            self.entries.insert(index, (time, leap_seconds));
+        }
+        self
+    }
+
+    /// Returns GPS time minus UTC at a UTC time.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # extern crate chrono;
+    /// # extern crate velodyne;
+    /// # fn main() {
+    /// use chrono::{TimeZone, UTC};
+    /// # use velodyne::time::LeapSeconds;
+    /// let leap_seconds = LeapSeconds::new();
+    /// assert_eq!(16, leap_seconds.at(UTC.ymd(2015, 6, 30).and_hms(23, 59, 59)));
+    /// assert_eq!(17, leap_seconds.at(UTC.ymd(2015, 7, 1).and_hms(0, 0, 0)));
+    /// # }
+    /// ```
+    pub fn at(&self, time: DateTime<UTC>) -> i64 {
+        self.entries
+            .iter()
+            .take_while(|&&(other, _)| other <= time)
+            .last()
+            .map(|&(_, leap_seconds)| leap_seconds)
+            .unwrap_or(0)
+    }
+
+    /// Returns GPS time minus UTC at a time in seconds since the GPS epoch.
+    fn at_gps(&self, seconds: f64) -> i64 {
+        self.entries
+            .iter()
+            .take_while(|&&(time, leap_seconds)| {
+                             let elapsed = time.signed_duration_since(gps_epoch()).num_seconds();
+                             (elapsed + leap_seconds) as f64 <= seconds
+                         })
+            .last()
+            .map(|&(_, leap_seconds)| leap_seconds)
+            .unwrap_or(0)
+    }
+}
+
+impl Default for LeapSeconds {
+    fn default() -> LeapSeconds {
+        LeapSeconds {
+            entries: LEAP_SECONDS
+                .iter()
+                .map(|&((year, month, day), leap_seconds)| {
+                         (UTC.ymd(year, month, day).and_hms(0, 0, 0), leap_seconds)
+                     })
+                .collect(),
+        }
+    }
+}
+
+impl DriftAnalyzer {
+    /// Creates a new, empty analyzer.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use velodyne::time::DriftAnalyzer;
+    /// let analyzer = DriftAnalyzer::new();
+    /// assert!(analyzer.drift().is_none());
+    /// ```
+    pub fn new() -> DriftAnalyzer {
+        DriftAnalyzer::default()
+    }
+
+    /// Analyzes every packet from a reader that knows its capture times.
+    ///
+    /// Anything that isn't a Velodyne packet is skipped.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use velodyne::io::Pcap;
+    /// use velodyne::time::DriftAnalyzer;
+    /// let analyzer = DriftAnalyzer::from_read(Pcap::open("data/single.pcap").unwrap()).unwrap();
+    /// assert_eq!(1, analyzer.drift().unwrap().samples);
+    /// ```
+    pub fn from_read<R: Read>(mut read: R) -> Result<DriftAnalyzer> {
+        let mut analyzer = DriftAnalyzer::new();
+        while let Some(result) = read.read() {
+            let bytes = result?;
+            if vlp_16::classify(bytes).is_none() {
+                continue;
+            }
+            let packet = Packet::new(bytes)?;
+            if let Some(capture_time) = read.timestamp() {
+                analyzer.add(packet.timestamp().duration(), capture_time);
+            }
+        }
+        Ok(analyzer)
+    }
+
+    /// Adds a device timestamp and the time its packet was captured.
+    pub fn add(&mut self, device_time: Duration, capture_time: DateTime<UTC>) {
+        let start = *self.start.get_or_insert(capture_time);
+        let elapsed = to_seconds(capture_time.signed_duration_since(start));
+        let nanoseconds = i64::from(capture_time.timestamp_subsec_nanos());
+        let since_top_of_hour = Duration::seconds(capture_time.timestamp() % 3600) +
+                                Duration::nanoseconds(nanoseconds);
+        let mut offset = to_seconds(since_top_of_hour - device_time);
+        if offset >= 1800. {
+            offset -= 3600.;
+        } else if offset < -1800. {
+            offset += 3600.;
+        }
+        self.samples.push((elapsed, offset));
+    }
+
+    /// Fits a line to the offsets, or returns `None` if there aren't any samples.
+    ///
+    /// With a single sample, the rate is zero.
+    pub fn drift(&self) -> Option<Drift> {
+        if self.samples.is_empty() {
+            return None;
+        }
+        let n = self.samples.len() as f64;
+        let mean_x = self.samples.iter().map(|&(x, _)| x).sum::<f64>() / n;
+        let mean_y = self.samples.iter().map(|&(_, y)| y).sum::<f64>() / n;
+        let (covariance, variance) = self.samples
+            .iter()
+            .fold((0., 0.), |(covariance, variance), &(x, y)| {
+                (covariance + (x - mean_x) * (y - mean_y), variance + (x - mean_x).powi(2))
+            });
+        let rate = if variance > 0. { covariance / variance } else { 0. };
+        let offset = mean_y - rate * mean_x;
+        let max_residual = self.samples
+            .iter()
+            .map(|&(x, y)| (y - offset - rate * x).abs())
+            .fold(0., f64::max);
+        Some(Drift {
+                 samples: self.samples.len(),
+                 offset,
+                 rate,
+                 max_residual,
+             })
+    }
+}
+
+impl HourOffset {
+    /// Creates an offset from the microseconds since the top of the hour, as in a packet.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use velodyne::time::HourOffset;
+    /// let offset = HourOffset::from_micros(2_467_108_343);
+    /// ```
+    pub fn from_micros(micros: u32) -> HourOffset {
+        HourOffset(micros)
+    }
+
+    /// Returns the microseconds since the top of the hour, exactly as in the packet.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use velodyne::fixtures::VLP_16_DATA_PACKET;
+    /// use velodyne::vlp_16::Packet;
+    /// let packet = Packet::new(&VLP_16_DATA_PACKET).unwrap();
+    /// assert_eq!(2_467_108_343, packet.timestamp().micros());
+    /// ```
+    pub fn micros(self) -> u32 {
+        self.0
+    }
+
+    /// Returns true if the offset is less than an hour.
+    ///
+    /// The wire format can hold more than an hour, which a working sensor never sends.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use velodyne::time::HourOffset;
+    /// assert!(HourOffset::from_micros(3_599_999_999).is_valid());
+    /// assert!(!HourOffset::from_micros(3_600_000_000).is_valid());
+    /// ```
+    pub fn is_valid(self) -> bool {
+        i64::from(self.0) < HOUR_US
+    }
+
+    /// Returns the offset as a duration since the top of the hour, for arithmetic on offsets.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # extern crate chrono;
+    /// # extern crate velodyne;
+    /// # fn main() {
+    /// use chrono::Duration;
+    /// use velodyne::time::HourOffset;
+    /// assert_eq!(Duration::seconds(1), HourOffset::from_micros(1_000_000).duration());
+    /// # }
+    /// ```
+    pub fn duration(self) -> Duration {
+        Duration::microseconds(i64::from(self.0))
+    }
+
+    /// Converts the offset into an absolute time with a time source.
+    ///
+    /// Returns `None` if the offset isn't valid or the time source doesn't have a reference yet.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # extern crate chrono;
+    /// # extern crate velodyne;
+    /// # fn main() {
+    /// use chrono::{TimeZone, UTC};
+    /// use velodyne::time::{Epoch, HourOffset};
+    /// let epoch = Epoch::new(UTC.ymd(2017, 1, 1).and_hms(12, 0, 0));
+    /// assert_eq!(UTC.ymd(2017, 1, 1).and_hms(12, 0, 1),
+    ///            HourOffset::from_micros(1_000_000).absolute(&epoch).unwrap());
+    /// assert!(HourOffset::from_micros(u32::MAX).absolute(&epoch).is_none());
+    /// # }
+    /// ```
+    pub fn absolute<T: TimeSource + ?Sized>(self, time_source: &T) -> Option<DateTime<UTC>> {
+        if self.is_valid() {
+            time_source.absolute(self.duration())
+        } else {
+            None
+        }
+    }
+}
+
+impl Reference {
+    /// Returns the absolute time of a packet timestamp.
+    ///
+    /// Packet timestamps roll over at the top of the hour, so the timestamp is assumed to be
+    /// within half an hour of the reference.
+    fn absolute(&self, offset: Duration) -> DateTime<UTC> {
+        let mut delta = offset - self.offset;
+        if delta >= Duration::minutes(30) {
+            delta = delta - Duration::hours(1);
+        } else if delta < Duration::minutes(-30) {
+            delta = delta + Duration::hours(1);
+        }
+        self.time + delta
+    }
+}
+
+fn gps_epoch() -> DateTime<UTC> {
+    UTC.ymd(1980, 1, 6).and_hms(0, 0, 0)
+}
+
+fn to_seconds(duration: Duration) -> f64 {
+    duration.num_microseconds().map(|n| n as f64 / 1e6).unwrap_or(duration.num_seconds() as f64)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+    use fixtures::{VLP_16_DATA_PACKET, VLP_16_POSITION_PACKET};
+
+    #[test]
+    fn gps_time() {
+        let mut gps_time = GpsTime::new();
+        let data_packet = Packet::new(&VLP_16_DATA_PACKET).unwrap();
+        gps_time.observe(&data_packet, None);
+        assert!(data_packet.timestamp().absolute(&gps_time).is_none());
+        gps_time.observe(&Packet::new(&VLP_16_POSITION_PACKET).unwrap(), None);
+        assert_eq!(UTC.ymd(2015, 7, 23).and_hms_micro(21, 41, 7, 108343),
+                   data_packet.timestamp().absolute(&gps_time).unwrap());
+    }
+
+    #[test]
+    fn time_quality() {
+        let mut unlocked = VLP_16_POSITION_PACKET;
+        unlocked[42 + 187 + 15] = 0;
+        let unlocked = Packet::new(&unlocked).unwrap();
+        let locked = Packet::new(&VLP_16_POSITION_PACKET).unwrap();
+        let data_packet = Packet::new(&VLP_16_DATA_PACKET).unwrap();
+        let mut gps_time = GpsTime::new();
+        gps_time.require_sync(true);
+        assert_eq!(Some(TimeQuality::Unsynchronized), gps_time.quality());
+        gps_time.observe(&unlocked, None);
+        assert!(data_packet.timestamp().absolute(&gps_time).is_none());
+        gps_time.observe(&locked, None);
+        assert_eq!(Some(TimeQuality::Locked), gps_time.quality());
+        gps_time.observe(&unlocked, None);
+        assert_eq!(Some(TimeQuality::Holdover), gps_time.quality());
+        assert!(data_packet.timestamp().absolute(&gps_time).is_some());
+        assert_eq!(None, CaptureTime::new().quality());
+    }
+
+    #[test]
+    fn capture_time() {
+        let mut capture_time = CaptureTime::new();
+        let packet = Packet::new(&VLP_16_DATA_PACKET).unwrap();
+        let time = UTC.ymd(2017, 1, 1).and_hms(0, 0, 0);
+        capture_time.observe(&packet, Some(time));
+        assert_eq!(time + Duration::milliseconds(1),
+                   capture_time.absolute(packet.timestamp().duration() + Duration::milliseconds(1))
+                       .unwrap());
+    }
+
+    #[test]
+    fn capture_anchor() {
+        let mut capture_anchor = CaptureAnchor::new();
+        let packet = Packet::new(&VLP_16_DATA_PACKET).unwrap();
+        let time = UTC.ymd(2017, 1, 1).and_hms(0, 0, 0);
+        capture_anchor.observe(&packet, Some(time + Duration::milliseconds(3)));
+        capture_anchor.observe(&packet, Some(time + Duration::milliseconds(1)));
+        capture_anchor.observe(&packet, Some(time + Duration::milliseconds(2)));
+        assert_eq!(time + Duration::milliseconds(1),
+                   packet.timestamp().absolute(&capture_anchor).unwrap());
+    }
+
+    #[test]
+    fn fallback() {
+        let mut fallback = Fallback::new(GpsTime::new(), CaptureAnchor::new());
+        let data_packet = Packet::new(&VLP_16_DATA_PACKET).unwrap();
+        assert!(data_packet.timestamp().absolute(&fallback).is_none());
+        let time = UTC.ymd(2017, 1, 1).and_hms(0, 0, 0);
+        fallback.observe(&data_packet, Some(time));
+        assert_eq!(time, data_packet.timestamp().absolute(&fallback).unwrap());
+        fallback.observe(&Packet::new(&VLP_16_POSITION_PACKET).unwrap(), Some(time));
+        assert_eq!(UTC.ymd(2015, 7, 23).and_hms_micro(21, 41, 7, 108343),
+                   data_packet.timestamp().absolute(&fallback).unwrap());
+    }
+
+    #[test]
+    fn drift() {
+        let mut analyzer = DriftAnalyzer::new();
+        let start = UTC.ymd(2017, 1, 1).and_hms(12, 59, 0);
+        for i in 0..120 {
+            let elapsed = Duration::seconds(i);
+            let device_time = Duration::minutes(59) + elapsed - Duration::milliseconds(2) +
+                              Duration::microseconds(10 * i);
+            analyzer.add(device_time, start + elapsed);
+        }
+        let drift = analyzer.drift().unwrap();
+        assert_eq!(120, drift.samples);
+        assert!((drift.offset - 0.002).abs() < 1e-9);
+        assert!((drift.rate + 1e-5).abs() < 1e-9);
+        assert!(drift.max_residual < 1e-9);
+    }
+
+    #[test]
+    fn gps_week_time() {
+        let mut converter = GpsConverter::new();
+        converter.leap_seconds(17);
+        let time = UTC.ymd(2015, 7, 23).and_hms_micro(21, 41, 6, 500_000);
+        let week_time = converter.week_time(time);
+        assert_eq!(1854, week_time.week);
+        assert_eq!(4. * 86_400. + 21. * 3600. + 41. * 60. + 6.5 + 17., week_time.seconds);
+        assert_eq!(121722883.5, converter.adjusted_standard(time));
+    }
+
+    #[test]
+    fn leap_seconds() {
+        let converter = GpsConverter::new();
+        let before = UTC.ymd(2016, 12, 31).and_hms(23, 59, 59);
+        let after = UTC.ymd(2017, 1, 1).and_hms(0, 0, 0);
+        assert_eq!(2., converter.seconds(after) - converter.seconds(before));
+        assert_eq!(before, converter.utc(converter.seconds(before)));
+        assert_eq!(after, converter.utc(converter.seconds(after)));
+        let time = UTC.ymd(2015, 7, 23).and_hms_micro(21, 41, 6, 500_000);
+        assert_eq!(time, converter.utc(converter.seconds(time)));
+        let mut converter = GpsConverter::new();
+        converter.leap_seconds(0);
+        assert_eq!(0., converter.seconds(gps_epoch()));
+    }
+
+    #[test]
+    fn rollover() {
+        let reference = Reference {
+            time: UTC.ymd(2017, 1, 1).and_hms(0, 59, 59),
+            offset: Duration::seconds(3599),
+        };
+        assert_eq!(UTC.ymd(2017, 1, 1).and_hms(1, 0, 1),
+                   reference.absolute(Duration::seconds(1)));
+        let reference = Reference {
+            time: UTC.ymd(2017, 1, 1).and_hms(1, 0, 1),
+            offset: Duration::seconds(1),
+        };
+        assert_eq!(UTC.ymd(2017, 1, 1).and_hms(0, 59, 59),
+                   reference.absolute(Duration::seconds(3599)));
+    }
+}
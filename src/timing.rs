@@ -0,0 +1,259 @@
+//! When each laser fires within a packet.
+//!
+//! Every point in a data packet is fired at a fixed offset from the packet's timestamp. The
+//! offsets are kept in integer nanoseconds, so point times and interpolated azimuths are exact and
+//! the same on every platform.
+//!
+//! ```
+//! # extern crate chrono;
+//! # extern crate velodyne;
+//! # fn main() {
+//! use chrono::Duration;
+//! use velodyne::timing;
+//! // The second firing of the fourth data block's second firing sequence.
+//! assert_eq!(Duration::nanoseconds(389_376), timing::VLP_16.firing_offset(3, 1, 1));
+//! # }
+//! ```
+
+use chrono::Duration;
+use vlp_16::{ReturnMode, Sensor};
+use wire::{DATA_RECORDS_PER_BLOCK, NUM_DATA_BLOCKS};
+
+/// The number of points in a data packet, with or without a return, for every sensor and return
+/// mode.
+pub const POINTS_PER_PACKET: usize = 384;
+
+/// The firing timing of a sensor model.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Timing {
+    /// The time between the starts of two firing sequences, in nanoseconds.
+    pub firing_cycle_ns: i64,
+    /// The time between two laser firings within a firing sequence, in nanoseconds.
+    pub firing_ns: i64,
+    /// The number of firing sequences in each data block of a single-return packet.
+    pub sequences_per_data_block: usize,
+}
+
+/// The VLP-16, which fires two sequences of sixteen lasers per data block.
+pub const VLP_16: Timing = Timing {
+    firing_cycle_ns: 55_296,
+    firing_ns: 2_304,
+    sequences_per_data_block: 2,
+};
+
+/// The HDL-32E, which fires one sequence of thirty-two lasers per data block.
+pub const HDL_32E: Timing = Timing {
+    firing_cycle_ns: 46_080,
+    firing_ns: 1_152,
+    sequences_per_data_block: 1,
+};
+
+impl Timing {
+    /// Returns the timing of a sensor model.
+    ///
+    /// Unknown sensors get the VLP-16's timing.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use velodyne::timing::{self, Timing};
+    /// use velodyne::vlp_16::Sensor;
+    /// assert_eq!(timing::VLP_16, *Timing::for_sensor(Sensor::VLP_16));
+    /// ```
+    pub fn for_sensor(sensor: Sensor) -> &'static Timing {
+        match sensor {
+            Sensor::HDL_32E => &HDL_32E,
+            Sensor::VLP_16 | Sensor::Unknown(_) => &VLP_16,
+        }
+    }
+
+    /// Returns the time between the starts of two firing sequences.
+    pub fn firing_cycle(&self) -> Duration {
+        Duration::nanoseconds(self.firing_cycle_ns)
+    }
+
+    /// Returns the time between two laser firings within a firing sequence.
+    pub fn firing(&self) -> Duration {
+        Duration::nanoseconds(self.firing_ns)
+    }
+
+    /// Returns the number of lasers that fire in each firing sequence.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use velodyne::timing;
+    /// assert_eq!(16, timing::VLP_16.lasers_per_sequence());
+    /// assert_eq!(32, timing::HDL_32E.lasers_per_sequence());
+    /// ```
+    pub fn lasers_per_sequence(&self) -> usize {
+        DATA_RECORDS_PER_BLOCK / self.sequences_per_data_block
+    }
+
+    /// Returns where a firing's data record is in its data block, as the half of the block and
+    /// the index within that half, i.e. the indices into `DataBlock::data_records`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use velodyne::timing;
+    /// assert_eq!((1, 3), timing::VLP_16.data_record(1, 3));
+    /// assert_eq!((1, 3), timing::HDL_32E.data_record(0, 19));
+    /// ```
+    pub fn data_record(&self, sequence: usize, channel: usize) -> (usize, usize) {
+        let index = sequence * self.lasers_per_sequence() + channel;
+        let half = DATA_RECORDS_PER_BLOCK / 2;
+        (index / half, index % half)
+    }
+
+    /// Returns the number of firing sequences in a packet.
+    ///
+    /// Dual-return packets report each firing sequence twice, so they hold half as many. Unknown
+    /// return modes are counted as single returns.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use velodyne::timing;
+    /// use velodyne::vlp_16::ReturnMode;
+    /// assert_eq!(24, timing::VLP_16.sequences_per_packet(ReturnMode::StrongestReturn));
+    /// assert_eq!(12, timing::VLP_16.sequences_per_packet(ReturnMode::DualReturn));
+    /// ```
+    pub fn sequences_per_packet(&self, return_mode: ReturnMode) -> usize {
+        match return_mode {
+            ReturnMode::DualReturn => NUM_DATA_BLOCKS * self.sequences_per_data_block / 2,
+            ReturnMode::StrongestReturn |
+            ReturnMode::LastReturn |
+            ReturnMode::Unknown(_) => NUM_DATA_BLOCKS * self.sequences_per_data_block,
+        }
+    }
+
+    /// Returns the time that a packet covers, i.e. the time between consecutive packets.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # extern crate chrono;
+    /// # extern crate velodyne;
+    /// # fn main() {
+    /// use chrono::Duration;
+    /// use velodyne::timing;
+    /// use velodyne::vlp_16::ReturnMode;
+    /// let duration = timing::VLP_16.packet_duration(ReturnMode::StrongestReturn);
+    /// assert_eq!(Duration::nanoseconds(1_327_104), duration);
+    /// # }
+    /// ```
+    pub fn packet_duration(&self, return_mode: ReturnMode) -> Duration {
+        Duration::nanoseconds(self.packet_duration_ns(return_mode))
+    }
+
+    /// Returns the number of data packets that the sensor sends per second.
+    ///
+    /// Lasers fire at a fixed rate, so this doesn't depend on the rotation rate. Dual-return mode
+    /// sends twice as many packets.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use velodyne::timing;
+    /// use velodyne::vlp_16::ReturnMode;
+    /// let rate = timing::VLP_16.packets_per_second(ReturnMode::StrongestReturn);
+    /// assert_eq!(753, rate as usize);
+    /// ```
+    pub fn packets_per_second(&self, return_mode: ReturnMode) -> f64 {
+        1e9 / self.packet_duration_ns(return_mode) as f64
+    }
+
+    /// Returns the number of data packets in one rotation at a rotation rate, in revolutions per
+    /// minute.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use velodyne::timing;
+    /// use velodyne::vlp_16::ReturnMode;
+    /// let packets = timing::VLP_16.packets_per_rotation(600., ReturnMode::DualReturn);
+    /// assert_eq!(151, packets.round() as usize);
+    /// ```
+    pub fn packets_per_rotation(&self, rpm: f64, return_mode: ReturnMode) -> f64 {
+        self.packets_per_second(return_mode) * 60. / rpm
+    }
+
+    /// Returns the number of points in one rotation at a rotation rate, in revolutions per minute.
+    ///
+    /// Points without a return are counted, so this is the size of a full frame.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use velodyne::timing;
+    /// use velodyne::vlp_16::ReturnMode;
+    /// let points = timing::VLP_16.points_per_rotation(600., ReturnMode::StrongestReturn);
+    /// assert_eq!(28_935, points.round() as usize);
+    /// ```
+    pub fn points_per_rotation(&self, rpm: f64, return_mode: ReturnMode) -> f64 {
+        self.packets_per_rotation(rpm, return_mode) * POINTS_PER_PACKET as f64
+    }
+
+    /// Returns the time from a packet's timestamp to a laser firing.
+    ///
+    /// In dual-return mode, data blocks come in pairs that report the same firings, so
+    /// `data_block` is the index of the pair.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # extern crate chrono;
+    /// # extern crate velodyne;
+    /// # fn main() {
+    /// use chrono::Duration;
+    /// use velodyne::timing;
+    /// assert_eq!(Duration::nanoseconds(1_306_368), timing::VLP_16.firing_offset(11, 1, 15));
+    /// # }
+    /// ```
+    pub fn firing_offset(&self, data_block: usize, sequence: usize, channel: usize) -> Duration {
+        let sequence = data_block * self.sequences_per_data_block + sequence;
+        Duration::nanoseconds(self.sequence_offset_ns(sequence, channel))
+    }
+
+    /// The nanoseconds from the start of a packet to a firing, with `sequence` counted from the
+    /// first firing sequence of the packet.
+    pub(crate) fn sequence_offset_ns(&self, sequence: usize, channel: usize) -> i64 {
+        sequence as i64 * self.firing_cycle_ns + channel as i64 * self.firing_ns
+    }
+
+    pub(crate) fn packet_duration_ns(&self, return_mode: ReturnMode) -> i64 {
+        self.sequences_per_packet(return_mode) as i64 * self.firing_cycle_ns
+    }
+
+    /// The nanoseconds between consecutive data blocks of a single-return packet, or pairs of a
+    /// dual-return packet.
+    pub(crate) fn data_block_duration_ns(&self) -> i64 {
+        self.sequences_per_data_block as i64 * self.firing_cycle_ns
+    }
+
+    /// The most microseconds between consecutive data packets' timestamps for them to still count
+    /// as consecutive, so that azimuths can be interpolated between them.
+    pub(crate) fn max_packet_gap_us(&self) -> i64 {
+        2 * self.packet_duration_ns(ReturnMode::StrongestReturn) / 1000
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use spec;
+
+    #[test]
+    fn matches_spec() {
+        for &(timing, spec) in &[(VLP_16, spec::VLP_16), (HDL_32E, spec::HDL_32E)] {
+            assert_eq!(spec.firing_cycle_us, timing.firing_cycle_ns as f32 / 1000.);
+            assert_eq!(spec.firing_us, timing.firing_ns as f32 / 1000.);
+            let lasers = spec.num_lasers() * timing.sequences_per_data_block;
+            assert_eq!(32, lasers);
+            assert_eq!(POINTS_PER_PACKET, NUM_DATA_BLOCKS * lasers);
+        }
+        let strongest = VLP_16.packets_per_second(ReturnMode::StrongestReturn);
+        assert_eq!(2. * strongest, VLP_16.packets_per_second(ReturnMode::DualReturn));
+    }
+}
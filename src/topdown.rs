@@ -0,0 +1,223 @@
+//! Render points as top-down (bird's-eye) PNG images.
+//!
+//! A top-down image is often all that's needed to check that a capture covers what it should.
+
+use Result;
+use cloud::Bounds;
+use panorama::Colormap;
+use png::{BitDepth, ColorType, Encoder};
+use point::Point;
+use std::fs::File;
+use std::io::{self, BufWriter, Write};
+use std::path::Path;
+
+const DEFAULT_RESOLUTION: f32 = 0.1;
+/// Images are limited to this many pixels on a side, so a stray point can't exhaust memory.
+const MAX_PIXELS: usize = 16_384;
+
+/// Renders points into top-down PNG images.
+///
+/// Each pixel shows the highest point that falls into it. Pixels without any points are black.
+#[derive(Clone, Copy, Debug)]
+pub struct TopDown {
+    value: Value,
+    colormap: Colormap,
+    resolution: f32,
+    extent: Option<Bounds>,
+}
+
+/// The value that is rendered into each pixel.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Value {
+    /// The calibrated reflectivity, normalized as with `Point::intensity`.
+    Intensity,
+    /// The height, from the bottom of the extent to its top.
+    Height,
+}
+
+impl TopDown {
+    /// Creates a new grayscale renderer for the given value, with ten centimeter pixels.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use velodyne::topdown::{TopDown, Value};
+    /// let top_down = TopDown::new(Value::Height);
+    /// ```
+    pub fn new(value: Value) -> TopDown {
+        TopDown {
+            value,
+            colormap: Colormap::Grayscale,
+            resolution: DEFAULT_RESOLUTION,
+            extent: None,
+        }
+    }
+
+    /// Sets the colormap.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use velodyne::topdown::{TopDown, Value};
+    /// use velodyne::panorama::Colormap;
+    /// let mut top_down = TopDown::new(Value::Height);
+    /// top_down.colormap(Colormap::Jet);
+    /// ```
+    pub fn colormap(&mut self, colormap: Colormap) -> &mut TopDown {
+        self.colormap = colormap;
+        self
+    }
+
+    /// Sets the size of a pixel, in meters.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use velodyne::topdown::{TopDown, Value};
+    /// let mut top_down = TopDown::new(Value::Height);
+    /// top_down.resolution(0.25);
+    /// ```
+    pub fn resolution(&mut self, resolution: f32) -> &mut TopDown {
+        self.resolution = resolution;
+        self
+    }
+
+    /// Sets the extent of the image.
+    ///
+    /// Points outside of the extent are skipped, and heights are colored from `min_z` to `max_z`.
+    /// By default, the extent is the bounds of the points with a return.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use velodyne::topdown::{TopDown, Value};
+    /// use velodyne::cloud::Bounds;
+    /// let mut top_down = TopDown::new(Value::Height);
+    /// top_down.extent(Bounds::new([-50., -50., -3.], [50., 50., 10.]));
+    /// ```
+    pub fn extent(&mut self, extent: Bounds) -> &mut TopDown {
+        self.extent = Some(extent);
+        self
+    }
+
+    /// Renders points into a PNG.
+    ///
+    /// The top of the image is the positive y direction. It is an error if the image would be
+    /// more than 16,384 pixels on a side, which usually means that the resolution is too fine for
+    /// the extent.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use velodyne::topdown::{TopDown, Value};
+    /// use velodyne::fixtures::VLP_16_DATA_PACKET;
+    /// use velodyne::vlp_16::Packet;
+    /// let points = Packet::new(&VLP_16_DATA_PACKET).unwrap().points().unwrap();
+    /// let mut png = Vec::new();
+    /// TopDown::new(Value::Intensity).write(&points, &mut png).unwrap();
+    /// ```
+    pub fn write<W: Write>(&self, points: &[Point], write: W) -> Result<()> {
+        let mut returns = points.iter().filter(|point| point.range() > 0.);
+        let extent = match self.extent {
+            Some(extent) => extent,
+            None => {
+                match returns.next() {
+                    Some(first) => {
+                        let mut bounds = Bounds::from_point(first);
+                        for point in returns {
+                            bounds.grow(point);
+                        }
+                        bounds
+                    }
+                    None => Bounds::new([0.; 3], [0.; 3]),
+                }
+            }
+        };
+        let pixels = |min: f32, max: f32| {
+            (((max - min) / self.resolution).floor() as usize).saturating_add(1)
+        };
+        let width = pixels(extent.min_x, extent.max_x);
+        let height = pixels(extent.min_y, extent.max_y);
+        if self.resolution.is_nan() || self.resolution <= 0. || width > MAX_PIXELS ||
+           height > MAX_PIXELS {
+            let message = format!("a {} by {} meter image at {} meters per pixel is too large",
+                                  extent.max_x - extent.min_x,
+                                  extent.max_y - extent.min_y,
+                                  self.resolution);
+            return Err(io::Error::new(io::ErrorKind::InvalidInput, message).into());
+        }
+        let mut highest: Vec<Option<&Point>> = vec![None; width * height];
+        for point in points.iter().filter(|point| point.range() > 0. && extent.contains(point)) {
+            let column = ((point.x - extent.min_x) / self.resolution) as usize;
+            let row = height - 1 - ((point.y - extent.min_y) / self.resolution) as usize;
+            let pixel = &mut highest[row * width + column];
+            if pixel.is_none_or(|highest| point.z > highest.z) {
+                *pixel = Some(point);
+            }
+        }
+        let mut data = Vec::with_capacity(width * height * 3);
+        for pixel in highest {
+            let rgb = pixel.map(|point| self.colormap.rgb(self.normalize(point, &extent)))
+                .unwrap_or([0, 0, 0]);
+            data.extend_from_slice(&rgb);
+        }
+        let mut encoder = Encoder::new(write, width as u32, height as u32);
+        encoder.set_color(ColorType::Rgb);
+        encoder.set_depth(BitDepth::Eight);
+        let mut writer = encoder.write_header()?;
+        writer.write_image_data(&data)?;
+        writer.finish()?;
+        Ok(())
+    }
+
+    /// Renders points into a PNG file.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// # use velodyne::topdown::{TopDown, Value};
+    /// TopDown::new(Value::Height).write_to_path(&[], "top-down.png").unwrap();
+    /// ```
+    pub fn write_to_path<P: AsRef<Path>>(&self, points: &[Point], path: P) -> Result<()> {
+        self.write(points, BufWriter::new(File::create(path)?))
+    }
+
+    fn normalize(&self, point: &Point, extent: &Bounds) -> f32 {
+        match self.value {
+            Value::Intensity => point.intensity(),
+            Value::Height => {
+                let range = extent.max_z - extent.min_z;
+                if range > 0. { (point.z - extent.min_z) / range } else { 1. }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use fixtures::VLP_16_DATA_PACKET;
+    use vlp_16::Packet;
+
+    #[test]
+    fn size_from_extent() {
+        let points = Packet::new(&VLP_16_DATA_PACKET).unwrap().points().unwrap();
+        let mut top_down = TopDown::new(Value::Height);
+        top_down.resolution(0.5).extent(Bounds::new([-10., -5., -3.], [10., 5., 3.]));
+        let mut png = Vec::new();
+        top_down.write(&points, &mut png).unwrap();
+        assert_eq!(b"\x89PNG", &png[0..4]);
+        assert_eq!(&[0, 0, 0, 41], &png[16..20]);
+        assert_eq!(&[0, 0, 0, 21], &png[20..24]);
+    }
+
+    #[test]
+    fn too_large() {
+        let points = Packet::new(&VLP_16_DATA_PACKET).unwrap().points().unwrap();
+        let mut top_down = TopDown::new(Value::Intensity);
+        top_down.resolution(0.001);
+        assert!(top_down.write(&points, Vec::new()).is_err());
+        top_down.resolution(0.);
+        assert!(top_down.write(&points, Vec::new()).is_err());
+    }
+}
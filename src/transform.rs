@@ -0,0 +1,293 @@
+//! Rigid transformations, e.g. from a sensor's frame into a vehicle's frame.
+//!
+//! A `Transform` is also a pose: the sensor's pose in the vehicle's frame is the transform from
+//! the sensor's frame into the vehicle's. The same type is used for sensor extrinsics, for
+//! georeferencing, and for merging sensors.
+
+use Point;
+
+/// A rotation followed by a translation.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Transform {
+    rotation: [[f32; 3]; 3],
+    translation: [f32; 3],
+}
+
+impl Transform {
+    /// Creates a transform from a row-major rotation matrix and a translation.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use velodyne::transform::Transform;
+    /// let transform = Transform::new([[1., 0., 0.], [0., 1., 0.], [0., 0., 1.]], [0., 0., 1.5]);
+    /// ```
+    pub fn new(rotation: [[f32; 3]; 3], translation: [f32; 3]) -> Transform {
+        Transform {
+            rotation,
+            translation,
+        }
+    }
+
+    /// Creates a transform that does nothing.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use velodyne::transform::Transform;
+    /// assert_eq!([0., 0., 0.], Transform::identity().translation());
+    /// ```
+    pub fn identity() -> Transform {
+        Transform::new([[1., 0., 0.], [0., 1., 0.], [0., 0., 1.]], [0., 0., 0.])
+    }
+
+    /// Creates a transform from roll, pitch, and yaw, in degrees, and a translation.
+    ///
+    /// Roll is about the x axis, pitch is about the y axis, and yaw is about the z axis, all
+    /// counter-clockwise when looking down the axis. Roll is applied first and yaw last.
+    ///
+    /// # Examples
+    ///
+    /// A sensor mounted upside down, 1.5 meters above the vehicle's origin:
+    ///
+    /// ```
+    /// use velodyne::transform::Transform;
+    /// let transform = Transform::from_euler(180., 0., 0., [0., 0., 1.5]);
+    /// ```
+    pub fn from_euler(roll: f32, pitch: f32, yaw: f32, translation: [f32; 3]) -> Transform {
+        let (sr, cr) = roll.to_radians().sin_cos();
+        let (sp, cp) = pitch.to_radians().sin_cos();
+        let (sy, cy) = yaw.to_radians().sin_cos();
+        Transform::new([[cy * cp, cy * sp * sr - sy * cr, cy * sp * cr + sy * sr],
+                        [sy * cp, sy * sp * sr + cy * cr, sy * sp * cr - cy * sr],
+                        [-sp, cp * sr, cp * cr]],
+                       translation)
+    }
+
+    /// Creates a transform from a rotation quaternion and a translation.
+    ///
+    /// The quaternion is normalized, so it doesn't have to be a unit quaternion.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the quaternion is zero.
+    ///
+    /// # Examples
+    ///
+    /// A quarter turn about the z axis:
+    ///
+    /// ```
+    /// use velodyne::transform::Transform;
+    /// let half = 0.5f32.sqrt();
+    /// let transform = Transform::from_quaternion([half, 0., 0., half], [0., 0., 0.]);
+    /// ```
+    pub fn from_quaternion(wxyz: [f32; 4], translation: [f32; 3]) -> Transform {
+        let norm = wxyz.iter().map(|n| n * n).sum::<f32>().sqrt();
+        assert!(norm > 0., "a rotation quaternion can't be zero");
+        let [w, x, y, z] = [wxyz[0] / norm, wxyz[1] / norm, wxyz[2] / norm, wxyz[3] / norm];
+        Transform::new([[1. - 2. * (y * y + z * z), 2. * (x * y - z * w), 2. * (x * z + y * w)],
+                        [2. * (x * y + z * w), 1. - 2. * (x * x + z * z), 2. * (y * z - x * w)],
+                        [2. * (x * z - y * w), 2. * (y * z + x * w), 1. - 2. * (x * x + y * y)]],
+                       translation)
+    }
+
+    /// Returns the row-major rotation matrix.
+    pub fn rotation(&self) -> [[f32; 3]; 3] {
+        self.rotation
+    }
+
+    /// Returns the translation.
+    pub fn translation(&self) -> [f32; 3] {
+        self.translation
+    }
+
+    /// Returns the transform that applies this transform and then `other`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use velodyne::transform::Transform;
+    /// let up = Transform::from_euler(0., 0., 0., [0., 0., 1.]);
+    /// assert_eq!([0., 0., 2.], up.then(&up).translation());
+    /// ```
+    pub fn then(&self, other: &Transform) -> Transform {
+        let mut rotation = [[0.; 3]; 3];
+        for (i, row) in rotation.iter_mut().enumerate() {
+            for (j, value) in row.iter_mut().enumerate() {
+                *value = (0..3).map(|k| other.rotation[i][k] * self.rotation[k][j]).sum();
+            }
+        }
+        Transform::new(rotation, other.apply_xyz(self.translation))
+    }
+
+    /// Returns the transform that undoes this one.
+    ///
+    /// This assumes that the rotation matrix is orthonormal, which it is unless the transform was
+    /// created with `new` from a matrix that isn't.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use velodyne::transform::Transform;
+    /// let transform = Transform::from_euler(0., 0., 90., [1., 0., 0.]);
+    /// let xyz = transform.inverse().apply_xyz(transform.apply_xyz([1., 2., 3.]));
+    /// assert!((xyz[1] - 2.).abs() < 1e-6);
+    /// ```
+    pub fn inverse(&self) -> Transform {
+        let mut rotation = [[0.; 3]; 3];
+        for (i, row) in rotation.iter_mut().enumerate() {
+            for (j, value) in row.iter_mut().enumerate() {
+                *value = self.rotation[j][i];
+            }
+        }
+        let mut translation = [0.; 3];
+        for (i, value) in translation.iter_mut().enumerate() {
+            *value = -(0..3).map(|j| rotation[i][j] * self.translation[j]).sum::<f32>();
+        }
+        Transform::new(rotation, translation)
+    }
+
+    /// Transforms a point's coordinates in place.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use velodyne::fixtures::VLP_16_DATA_PACKET;
+    /// use velodyne::transform::Transform;
+    /// use velodyne::vlp_16::Packet;
+    /// let mut point = Packet::new(&VLP_16_DATA_PACKET).unwrap().points().unwrap()[0];
+    /// let z = point.z;
+    /// Transform::from_euler(0., 0., 0., [0., 0., 1.]).apply(&mut point);
+    /// assert_eq!(z + 1., point.z);
+    /// ```
+    pub fn apply(&self, point: &mut Point) {
+        let [x, y, z] = self.apply_xyz([point.x, point.y, point.z]);
+        point.x = x;
+        point.y = y;
+        point.z = z;
+    }
+
+    /// Transforms x, y, and z coordinates.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use velodyne::transform::Transform;
+    /// let transform = Transform::from_euler(0., 0., 0., [1., 2., 3.]);
+    /// assert_eq!([1., 2., 4.], transform.apply_xyz([0., 0., 1.]));
+    /// ```
+    pub fn apply_xyz(&self, xyz: [f32; 3]) -> [f32; 3] {
+        let mut out = self.translation;
+        for (i, value) in out.iter_mut().enumerate() {
+            *value += (0..3).map(|j| self.rotation[i][j] * xyz[j]).sum::<f32>();
+        }
+        out
+    }
+}
+
+impl Default for Transform {
+    fn default() -> Transform {
+        Transform::identity()
+    }
+}
+
+#[cfg(feature = "nalgebra")]
+impl From<nalgebra::Isometry3<f32>> for Transform {
+    fn from(isometry: nalgebra::Isometry3<f32>) -> Transform {
+        let rotation = isometry.rotation.to_rotation_matrix();
+        let m = rotation.matrix();
+        let t = isometry.translation.vector;
+        Transform::new([[m[(0, 0)], m[(0, 1)], m[(0, 2)]],
+                        [m[(1, 0)], m[(1, 1)], m[(1, 2)]],
+                        [m[(2, 0)], m[(2, 1)], m[(2, 2)]]],
+                       [t.x, t.y, t.z])
+    }
+}
+
+/// The rotation is projected onto the closest proper rotation, in case the transform was created
+/// from a matrix that isn't quite orthonormal.
+#[cfg(feature = "nalgebra")]
+impl From<Transform> for nalgebra::Isometry3<f32> {
+    fn from(transform: Transform) -> nalgebra::Isometry3<f32> {
+        let r = transform.rotation;
+        let matrix = nalgebra::Matrix3::new(r[0][0],
+                                            r[0][1],
+                                            r[0][2],
+                                            r[1][0],
+                                            r[1][1],
+                                            r[1][2],
+                                            r[2][0],
+                                            r[2][1],
+                                            r[2][2]);
+        let rotation = nalgebra::Rotation3::from_matrix(&matrix);
+        let [x, y, z] = transform.translation;
+        nalgebra::Isometry3::from_parts(nalgebra::Translation3::new(x, y, z),
+                                        nalgebra::UnitQuaternion::from_rotation_matrix(&rotation))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn assert_close(expected: [f32; 3], actual: [f32; 3]) {
+        for axis in 0..3 {
+            assert!((expected[axis] - actual[axis]).abs() < 1e-5,
+                    "{:?} != {:?}",
+                    expected,
+                    actual);
+        }
+    }
+
+    #[test]
+    fn euler() {
+        assert_close([0., 1., 0.],
+                     Transform::from_euler(0., 0., 90., [0.; 3]).apply_xyz([1., 0., 0.]));
+        assert_close([0., 0., -1.],
+                     Transform::from_euler(0., 90., 0., [0.; 3]).apply_xyz([1., 0., 0.]));
+        assert_close([0., -1., -1.],
+                     Transform::from_euler(180., 0., 0., [0.; 3]).apply_xyz([0., 1., 1.]));
+    }
+
+    #[test]
+    #[cfg(feature = "nalgebra")]
+    fn nalgebra() {
+        let transform = Transform::from_euler(10., 20., 30., [1., 2., 3.]);
+        let isometry = nalgebra::Isometry3::from(transform);
+        let xyz = [0.5, -2., 4.];
+        let point = isometry * nalgebra::Point3::new(xyz[0], xyz[1], xyz[2]);
+        assert_close(transform.apply_xyz(xyz), [point.x, point.y, point.z]);
+        assert_close(transform.apply_xyz(xyz), Transform::from(isometry).apply_xyz(xyz));
+    }
+
+    #[test]
+    fn quaternion() {
+        // Roll, pitch, and yaw of 10, 20, and 30 degrees.
+        let (sr, cr) = 5f32.to_radians().sin_cos();
+        let (sp, cp) = 10f32.to_radians().sin_cos();
+        let (sy, cy) = 15f32.to_radians().sin_cos();
+        let wxyz = [cr * cp * cy + sr * sp * sy,
+                    sr * cp * cy - cr * sp * sy,
+                    cr * sp * cy + sr * cp * sy,
+                    cr * cp * sy - sr * sp * cy];
+        let xyz = [0.5, -2., 4.];
+        assert_close(Transform::from_euler(10., 20., 30., [1., 2., 3.]).apply_xyz(xyz),
+                     Transform::from_quaternion(wxyz, [1., 2., 3.]).apply_xyz(xyz));
+    }
+
+    #[test]
+    fn inverse() {
+        let transform = Transform::from_euler(10., 20., 30., [1., 2., 3.]);
+        let xyz = [0.5, -2., 4.];
+        assert_close(xyz, transform.inverse().apply_xyz(transform.apply_xyz(xyz)));
+        assert_close(xyz, transform.then(&transform.inverse()).apply_xyz(xyz));
+    }
+
+    #[test]
+    fn then() {
+        let a = Transform::from_euler(10., 20., 30., [1., 2., 3.]);
+        let b = Transform::from_euler(-40., 5., 60., [-3., 0., 1.]);
+        let xyz = [0.5, -2., 4.];
+        assert_close(b.apply_xyz(a.apply_xyz(xyz)), a.then(&b).apply_xyz(xyz));
+    }
+}
@@ -0,0 +1,143 @@
+//! Strongly-typed units for distances and angles.
+//!
+//! Raw `f32`s don't say whether they're degrees or radians, which makes it easy to mix them up.
+//! Configuration and packet-level values that don't name their unit use these types instead, and
+//! conversions between them are explicit:
+//!
+//! ```
+//! use velodyne::units::{Degrees, Radians};
+//! let radians = Radians::from(Degrees(180.));
+//! assert!((radians.0 - std::f32::consts::PI).abs() < 1e-6);
+//! ```
+//!
+//! Point coordinates are always in meters, so they stay plain `f32`s for speed and convenience.
+
+use core::fmt;
+use core::ops::{Add, Div, Mul, Neg, Sub};
+#[cfg(feature = "std")]
+use point::Centidegrees;
+
+/// A distance in meters.
+#[derive(Clone, Copy, Debug, Default, PartialEq, PartialOrd)]
+pub struct Meters(pub f32);
+
+/// An angle in degrees.
+#[derive(Clone, Copy, Debug, Default, PartialEq, PartialOrd)]
+pub struct Degrees(pub f32);
+
+/// An angle in radians.
+#[derive(Clone, Copy, Debug, Default, PartialEq, PartialOrd)]
+pub struct Radians(pub f32);
+
+macro_rules! unit {
+    ($unit:ident, $suffix:expr) => {
+        impl Add for $unit {
+            type Output = $unit;
+            fn add(self, other: $unit) -> $unit {
+                $unit(self.0 + other.0)
+            }
+        }
+
+        impl Sub for $unit {
+            type Output = $unit;
+            fn sub(self, other: $unit) -> $unit {
+                $unit(self.0 - other.0)
+            }
+        }
+
+        impl Neg for $unit {
+            type Output = $unit;
+            fn neg(self) -> $unit {
+                $unit(-self.0)
+            }
+        }
+
+        impl Mul<f32> for $unit {
+            type Output = $unit;
+            fn mul(self, factor: f32) -> $unit {
+                $unit(self.0 * factor)
+            }
+        }
+
+        impl Div<f32> for $unit {
+            type Output = $unit;
+            fn div(self, divisor: f32) -> $unit {
+                $unit(self.0 / divisor)
+            }
+        }
+
+        impl fmt::Display for $unit {
+            fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+                fmt::Display::fmt(&self.0, f)?;
+                f.write_str($suffix)
+            }
+        }
+    }
+}
+
+unit!(Meters, " m");
+unit!(Degrees, "°");
+unit!(Radians, " rad");
+
+impl Degrees {
+    /// Converts these degrees to radians.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use velodyne::units::Degrees;
+    /// assert_eq!(0., Degrees(0.).radians().0);
+    /// ```
+    pub fn radians(self) -> Radians {
+        Radians(self.0.to_radians())
+    }
+}
+
+impl Radians {
+    /// Converts these radians to degrees.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use std::f32::consts::PI;
+    /// use velodyne::units::Radians;
+    /// assert_eq!(180., Radians(PI).degrees().0);
+    /// ```
+    pub fn degrees(self) -> Degrees {
+        Degrees(self.0.to_degrees())
+    }
+}
+
+impl From<Radians> for Degrees {
+    fn from(radians: Radians) -> Degrees {
+        radians.degrees()
+    }
+}
+
+impl From<Degrees> for Radians {
+    fn from(degrees: Degrees) -> Radians {
+        degrees.radians()
+    }
+}
+
+#[cfg(feature = "std")]
+impl From<Centidegrees> for Degrees {
+    fn from(centidegrees: Centidegrees) -> Degrees {
+        Degrees(centidegrees.degrees())
+    }
+}
+
+#[cfg(all(test, feature = "std"))]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn conversions() {
+        assert_eq!(Degrees(229.7), Degrees::from(Centidegrees(22_970)));
+        let degrees = Degrees(-15.);
+        assert!((Degrees::from(Radians::from(degrees)) - degrees).0.abs() < 1e-5);
+        assert_eq!(Meters(3.), Meters(1.) + Meters(4.) / 2.);
+        assert_eq!("1.5 m", Meters(1.5).to_string());
+        assert_eq!("90°", Degrees(90.).to_string());
+    }
+}
@@ -0,0 +1,238 @@
+//! The sensor's own velocity, estimated from the GNSS fixes in position packets.
+//!
+//! Without an IMU, the $GPRMC speed and course are the best available estimate of how the sensor
+//! moves during a rotation. An `Estimator` turns consecutive fixes into velocities and yaw rates,
+//! falling back to finite differences of the positions when the receiver leaves the speed or
+//! course empty:
+//!
+//! ```
+//! use velodyne::fixtures::VLP_16_POSITION_PACKET;
+//! use velodyne::velocity::Estimator;
+//! use velodyne::vlp_16::Packet;
+//! let mut estimator = Estimator::new();
+//! let velocity = estimator.push_packet(&Packet::new(&VLP_16_POSITION_PACKET).unwrap());
+//! assert!(velocity.is_none()); // The first fix has nothing to be compared with.
+//! ```
+
+use chrono::{DateTime, UTC};
+use nmea::Position;
+use units::Degrees;
+use vlp_16::Packet;
+
+/// The mean radius of the earth, in meters, which is plenty for differences between fixes.
+const EARTH_RADIUS: f64 = 6_371_000.;
+
+const DEFAULT_MIN_SPEED: f64 = 0.5;
+
+/// An estimate of the sensor's velocity.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Velocity {
+    /// The time of the fix that this velocity was estimated at.
+    pub time: DateTime<UTC>,
+    /// The velocity towards the east, in meters per second.
+    pub east: f64,
+    /// The velocity towards the north, in meters per second.
+    pub north: f64,
+    /// The course over ground, clockwise from true north.
+    pub course: Degrees,
+    /// The rate of change of the course, in degrees per second, positive clockwise.
+    ///
+    /// `None` when moving too slowly for the course to mean anything.
+    pub yaw_rate: Option<f64>,
+}
+
+/// Estimates velocities from consecutive fixes.
+#[derive(Clone, Copy, Debug)]
+pub struct Estimator {
+    min_speed: f64,
+    last: Option<Position>,
+    last_velocity: Option<Velocity>,
+}
+
+impl Velocity {
+    /// Returns the speed, in meters per second.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # extern crate chrono;
+    /// # extern crate velodyne;
+    /// # fn main() {
+    /// use chrono::{TimeZone, UTC};
+    /// use velodyne::units::Degrees;
+    /// use velodyne::velocity::Velocity;
+    /// let velocity = Velocity {
+    ///     time: UTC.ymd(2015, 7, 23).and_hms(21, 41, 6),
+    ///     east: 3.,
+    ///     north: 4.,
+    ///     course: Degrees(36.87),
+    ///     yaw_rate: None,
+    /// };
+    /// assert_eq!(5., velocity.speed());
+    /// # }
+    /// ```
+    pub fn speed(&self) -> f64 {
+        self.east.hypot(self.north)
+    }
+}
+
+impl Estimator {
+    /// Creates an estimator without any fixes.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use velodyne::velocity::Estimator;
+    /// let estimator = Estimator::new();
+    /// ```
+    pub fn new() -> Estimator {
+        Estimator::default()
+    }
+
+    /// Sets the speed, in meters per second, below which yaw rates aren't estimated.
+    ///
+    /// The course of a slow or stationary receiver is mostly noise. The default is half a meter
+    /// per second.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use velodyne::velocity::Estimator;
+    /// let mut estimator = Estimator::new();
+    /// estimator.min_speed(1.);
+    /// ```
+    pub fn min_speed(&mut self, min_speed: f64) -> &mut Estimator {
+        self.min_speed = min_speed;
+        self
+    }
+
+    /// Adds a fix, returning the velocity at it if it's newer than the previous fix.
+    ///
+    /// Position packets repeat each fix until the receiver sends the next one, so repeated fixes
+    /// are ignored, and so are invalid fixes. The reported speed and course are used when they
+    /// are present, otherwise the velocity is the difference from the previous fix.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use velodyne::velocity::Estimator;
+    /// use velodyne::nmea::{Position, Validation};
+    /// let mut estimator = Estimator::new();
+    /// let first = "$GPRMC,214106,A,3707.8178,N,12139.2690,W,,,230715,,";
+    /// let second = "$GPRMC,214107,A,3707.8178,N,12139.2684,W,,,230715,,";
+    /// assert!(estimator.push(&Position::parse(first, Validation::Lenient).unwrap()).is_none());
+    /// let second = Position::parse(second, Validation::Lenient).unwrap();
+    /// assert!((estimator.push(&second).unwrap().east - 0.89).abs() < 0.01);
+    /// ```
+    pub fn push(&mut self, position: &Position) -> Option<Velocity> {
+        if !position.valid {
+            return None;
+        }
+        let last = match self.last {
+            Some(last) if last.datetime >= position.datetime => return None,
+            last => last,
+        };
+        self.last = Some(*position);
+        let last = last?;
+        let seconds = seconds_between(last.datetime, position.datetime)?;
+        let speed = f64::from(position.speed.meters_per_second());
+        let course = f64::from(position.true_course.0);
+        let (east, north) = if speed.is_finite() && course.is_finite() {
+            let course = course.to_radians();
+            (speed * course.sin(), speed * course.cos())
+        } else {
+            let latitude = position.latitude.to_radians();
+            let east = (position.longitude - last.longitude).to_radians() * latitude.cos();
+            let north = (position.latitude - last.latitude).to_radians();
+            (east * EARTH_RADIUS / seconds, north * EARTH_RADIUS / seconds)
+        };
+        let mut velocity = Velocity {
+            time: position.datetime,
+            east,
+            north,
+            course: Degrees((east.atan2(north).to_degrees().rem_euclid(360.)) as f32),
+            yaw_rate: None,
+        };
+        if let Some(last_velocity) = self.last_velocity {
+            if velocity.speed() >= self.min_speed && last_velocity.speed() >= self.min_speed {
+                let turn = (velocity.course.0 - last_velocity.course.0 + 180.).rem_euclid(360.) -
+                           180.;
+                let seconds = seconds_between(last_velocity.time, velocity.time)?;
+                velocity.yaw_rate = Some(f64::from(turn) / seconds);
+            }
+        }
+        self.last_velocity = Some(velocity);
+        Some(velocity)
+    }
+
+    /// Adds a packet's fix, if it's a position packet with a $GPRMC sentence.
+    ///
+    /// See `push`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use velodyne::velocity::Estimator;
+    /// use velodyne::fixtures::VLP_16_DATA_PACKET;
+    /// use velodyne::vlp_16::Packet;
+    /// let mut estimator = Estimator::new();
+    /// assert!(estimator.push_packet(&Packet::new(&VLP_16_DATA_PACKET).unwrap()).is_none());
+    /// ```
+    pub fn push_packet(&mut self, packet: &Packet) -> Option<Velocity> {
+        let position = packet.position()?.ok()?;
+        self.push(&position)
+    }
+}
+
+fn seconds_between(earlier: DateTime<UTC>, later: DateTime<UTC>) -> Option<f64> {
+    let microseconds = later.signed_duration_since(earlier).num_microseconds()?;
+    Some(microseconds as f64 / 1e6)
+}
+
+impl Default for Estimator {
+    fn default() -> Estimator {
+        Estimator {
+            min_speed: DEFAULT_MIN_SPEED,
+            last: None,
+            last_velocity: None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use nmea::Validation;
+
+    fn position(time: &str, speed: &str, course: &str) -> Position {
+        let nmea = format!("$GPRMC,{},A,3707.8178,N,12139.2690,W,{},{},230715,,",
+                           time,
+                           speed,
+                           course);
+        Position::parse(&nmea, Validation::Lenient).unwrap()
+    }
+
+    #[test]
+    fn reported() {
+        let mut estimator = Estimator::new();
+        assert!(estimator.push(&position("214106", "010.0", "090.0")).is_none());
+        assert!(estimator.push(&position("214106", "010.0", "090.0")).is_none());
+        let velocity = estimator.push(&position("214107", "010.0", "090.0")).unwrap();
+        assert!((velocity.east - 10. * 0.514444).abs() < 1e-3);
+        assert!(velocity.north.abs() < 1e-3);
+        assert_eq!(None, velocity.yaw_rate);
+        let velocity = estimator.push(&position("214109", "010.0", "350.0")).unwrap();
+        assert!((velocity.yaw_rate.unwrap() + 50.).abs() < 1e-3);
+        let velocity = estimator.push(&position("214110", "000.1", "010.0")).unwrap();
+        assert_eq!(None, velocity.yaw_rate);
+    }
+
+    #[test]
+    fn invalid() {
+        let mut estimator = Estimator::new();
+        let mut invalid = position("214106", "010.0", "090.0");
+        invalid.valid = false;
+        assert!(estimator.push(&invalid).is_none());
+        assert!(estimator.push(&position("214107", "010.0", "090.0")).is_none());
+    }
+}
@@ -1,64 +1,106 @@
 //! Velodyne Puck 16.
+//!
+//! This is the crate's one packet decoder. Despite the name, it also decodes the HDL-32E, which is
+//! identified from each packet's factory bytes. Both sensors' packets have twelve data blocks of
+//! thirty-two data records, but the VLP-16 fires two sequences of sixteen lasers per data block and
+//! the HDL-32E one sequence of thirty-two, so points get each sensor's own channels, timing, and
+//! elevations. Range images, ground segmentation, and the other per-ring tools assume sixteen
+//! lasers.
 
 use {Error, Result, Point};
-use byteorder::{ReadBytesExt, LittleEndian};
-use chrono::Duration;
-use io::Read as VelodyneRead;
-use nmea::Position;
-use point::{Azimuth, ReturnType, Time};
+use batch;
+use byteorder::{ByteOrder, LittleEndian};
+use chrono::{DateTime, UTC};
+use correction::DistanceCorrection;
+use filter::Filter;
+use frame::Frame;
+use io::{PcapStream, Read as VelodyneRead};
+use nmea::{self, ParseError, Position, Sentence, Validation};
+use point::{Azimuth, Centidegrees, ReturnType, Time};
+use raw;
+use reconstruct;
+use spec::{self, SensorSpec};
+use time::{HourOffset, TimeQuality, TimeSource};
+use timing::{self, Timing};
+use transform::Transform;
+use units::{Degrees, Meters};
+use warning::DecodeWarning;
+use wire::{self, NUM_DATA_BLOCKS, START_IDENTIFIER};
 use std::f32;
-use std::io::{Cursor, Read};
+use std::fs::File;
+use std::io;
+use std::mem;
+use std::path::Path;
+use std::time::Instant;
 
-const AZIMUTH_SCALE_FACTOR: f32 = 100.;
-const DISTANCE_SCALE_FACTOR: f32 = 0.002;
-const NUM_LASERS: usize = 16;
-const NUM_DATA_BLOCKS: usize = 12;
-const PACKET_HEADER_LEN: usize = 42;
-const START_IDENTIFIER: u16 = 0xeeff;
-const FIRING_CYCLE_RATE_US: f32 = 55.296;
-const FIRING_RATE_US: f32 = 2.304;
+pub(crate) const NUM_LASERS: usize = 16;
+/// The most lasers that any supported sensor has, e.g. the HDL-32E.
+pub(crate) const MAX_LASERS: usize = 32;
+const NUM_POINTS: usize = NUM_DATA_BLOCKS * 2 * NUM_LASERS;
+pub use wire::{DATA_PAYLOAD_LEN, PACKET_HEADER_LEN, POSITION_PAYLOAD_LEN};
+/// The NMEA buffer runs from the end of the status fields to the end of the payload.
+const POSITION_NMEA_OFFSET: usize = wire::NMEA.offset;
+pub(crate) const POSITION_NMEA_LEN: usize = wire::NMEA.len;
+/// A full turn, in centidegrees.
+const FULL_TURN: i64 = 36_000;
 
 /// A Velodyne information packet.
-#[derive(Clone, Debug)]
+// The NMEA buffer is inline so that position packets don't allocate.
+#[allow(clippy::large_enum_variant)]
+#[derive(Clone, Debug, PartialEq)]
 pub enum Packet {
     /// Data packets contain laser range measurements.
-    Data {
-        /// A fixed-size array of data blocks.
-        data_blocks: Box<[DataBlock; NUM_DATA_BLOCKS]>,
-        /// The duration from the top of the hour to the first laser firing in the packet.
-        timestamp: Duration,
-        /// The return mode of the sensor.
-        return_mode: ReturnMode,
-        /// The sensor type.
-        sensor: Sensor,
-    },
+    Data(DataPacket),
     /// A position measurement, really just an echoing of information from a GNSS system.
-    Position {
-        /// The duration from the top of the hour that the NMEA string was received.
-        timestamp: Duration,
-        /// The NMA $GPRMC message as received from an external GNSS system.
-        nmea: String,
-    },
+    Position(PositionPacket),
+}
+
+/// A data packet, which contains laser range measurements.
+#[derive(Clone, Debug, PartialEq)]
+pub struct DataPacket {
+    /// A fixed-size array of data blocks.
+    pub data_blocks: Box<[DataBlock; NUM_DATA_BLOCKS]>,
+    /// The time from the top of the hour to the first laser firing in the packet.
+    pub timestamp: HourOffset,
+    /// The return mode of the sensor.
+    pub return_mode: ReturnMode,
+    /// The sensor type.
+    pub sensor: Sensor,
+}
+
+/// A position packet, really just an echoing of information from a GNSS system.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct PositionPacket {
+    /// The time from the top of the hour that the NMEA string was received.
+    pub timestamp: HourOffset,
+    /// The NMEA sentences as received from an external GNSS system, usually just $GPRMC.
+    ///
+    /// If there is more than one sentence, they are separated by CRLF.
+    pub nmea: nmea::Buffer,
+    /// The sensor's status when the packet was sent.
+    pub status: PositionStatus,
 }
 
 /// A block of laser measurements.
-#[derive(Clone, Copy, Debug, Default)]
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
 pub struct DataBlock {
-    /// The reported azimuth assocaited with the first laser shot.
+    /// The reported azimuth assocaited with the first laser shot, exactly as sent.
     ///
     /// This value often needs to be interpolated for the second set of data records.
-    pub azimuth: f32,
+    pub azimuth: Centidegrees,
     /// Two sets of sixteen data records.
     ///
-    /// Each laser has it's value recorded twice in each data block.
+    /// On a VLP-16, each laser has it's value recorded twice in each data block, once per firing
+    /// sequence. On an HDL-32E, the two sets are the first and last sixteen of its thirty-two
+    /// lasers, see `Timing::data_record`.
     pub data_records: [[DataRecord; NUM_LASERS]; 2],
 }
 
 /// A measurement of range and reflectivity.
-#[derive(Clone, Copy, Debug, Default)]
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
 pub struct DataRecord {
     /// The distance of the reflective object.
-    pub return_distance: f32,
+    pub return_distance: Meters,
     /// The calibrated reflectivity.
     ///
     /// A black, absorbent diffuse reflector is zero. A white, reflective diffuse reflector is 100.
@@ -67,8 +109,73 @@ pub struct DataRecord {
     pub calibrated_reflectivity: u8,
 }
 
-/// The modes by which the instrument can report reutrns.
+/// A data record that has a return.
 #[derive(Clone, Copy, Debug, PartialEq)]
+pub struct ReturnValue {
+    /// The distance of the reflective object.
+    pub distance: Meters,
+    /// The calibrated reflectivity.
+    pub reflectivity: u8,
+}
+
+/// The two returns of a single laser pulse, from a dual-return packet.
+#[derive(Clone, Copy, Debug)]
+pub struct Firing {
+    /// The strongest return.
+    ///
+    /// If the last return was also the strongest, this is the second-strongest return instead.
+    pub strongest: DataRecord,
+    /// The last return.
+    pub last: DataRecord,
+    /// The azimuth of the pulse.
+    pub azimuth: Azimuth,
+    /// The laser channel.
+    pub channel: u8,
+    /// The time of the pulse.
+    pub time: Time,
+    /// The sensor that fired, for the laser's elevation.
+    pub sensor: Sensor,
+}
+
+/// One return of one laser firing, with its azimuth and time but before any geometry.
+///
+/// This is what a point is made from, for users who want to do their own geometry while reusing
+/// this crate's timing and azimuth interpolation.
+#[derive(Clone, Copy, Debug)]
+pub struct FiringRecord {
+    /// The data block that the return is in, from zero to eleven.
+    pub data_block: u8,
+    /// The firing sequence within the data block, zero or one.
+    pub sequence: u8,
+    /// The laser channel.
+    pub channel: u8,
+    /// The type of return.
+    pub return_type: ReturnType,
+    /// The distance, exactly as sent, i.e. zero if there wasn't a return.
+    pub distance: Meters,
+    /// The calibrated reflectivity.
+    pub reflectivity: u8,
+    /// The azimuth of the firing, interpolated or extrapolated from the data blocks' azimuths.
+    pub azimuth: Azimuth,
+    /// The time of the firing.
+    pub time: Time,
+    /// The sensor that fired, for the laser's elevation.
+    pub sensor: Sensor,
+}
+
+/// The azimuths of a data packet's first and last data blocks.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct AzimuthSpan {
+    /// The azimuth of the first data block.
+    pub first: Centidegrees,
+    /// The azimuth of the last data block.
+    pub last: Centidegrees,
+    /// The angle swept clockwise from the first to the last data block, wrapping through zero.
+    pub coverage: Centidegrees,
+}
+
+/// The modes by which the instrument can report reutrns.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
 pub enum ReturnMode {
     /// The strongest return by light energy.
     StrongestReturn,
@@ -79,40 +186,420 @@ pub enum ReturnMode {
     /// If the last return is the strongest, returns the second-strongest return and the last
     /// return.
     DualReturn,
+    /// A factory byte that this crate doesn't know, e.g. from newer firmware.
+    ///
+    /// Only `Packet::new_lenient` reports unknown return modes. Their points are decoded as dual
+    /// returns if the data blocks come in pairs that share an azimuth, and as strongest returns
+    /// otherwise.
+    Unknown(u8),
 }
 
 /// The sensor that produced the data.
-#[derive(Clone, Copy, Debug, PartialEq)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
 #[allow(non_camel_case_types)]
 pub enum Sensor {
     /// HDL-32E.
     HDL_32E,
     /// VLP-16.
     VLP_16,
+    /// A factory byte that this crate doesn't know, e.g. from newer firmware.
+    ///
+    /// Only `Packet::new_lenient` reports unknown sensors, and their points are decoded as if
+    /// they came from a VLP-16.
+    Unknown(u8),
+}
+
+/// The diagnostic fields of a position packet.
+///
+/// Older firmware leaves most of these as zero. Temperatures are in degrees Celsius.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct PositionStatus {
+    /// The temperature of the top board.
+    pub top_board_temperature: u8,
+    /// The temperature of the bottom board.
+    pub bottom_board_temperature: u8,
+    /// The temperature when the ADC calibration last ran.
+    pub adc_calibration_temperature: u8,
+    /// The change in temperature since the last ADC calibration.
+    pub adc_calibration_temperature_change: u16,
+    /// The number of seconds since the last ADC calibration.
+    pub seconds_since_adc_calibration: u32,
+    /// The reason for the last ADC calibration, as a raw code.
+    pub adc_calibration_reason: u8,
+    /// A bitmask of the ADC calibration's current status.
+    pub adc_calibration_status: u8,
+    /// The state of the pulse per second signal.
+    pub pps: PpsStatus,
+    /// Is the sensor shut down because it is too hot?
+    pub thermal_shutdown: bool,
+    /// The temperature at the last thermal shutdown.
+    pub last_shutdown_temperature: u8,
+    /// The temperature of the unit when it powered up.
+    pub power_up_temperature: u8,
+}
+
+/// The state of the pulse per second (PPS) signal from a GNSS receiver.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum PpsStatus {
+    /// No PPS signal.
+    Absent,
+    /// The sensor is synchronizing to the PPS signal.
+    Synchronizing,
+    /// The sensor is locked to the PPS signal.
+    Locked,
+    /// The PPS signal is in error.
+    Error,
+}
+
+/// Decodes a stream of packets into points, interpolating across packet boundaries.
+///
+/// `Packet::points` has to extrapolate the azimuths of a packet's last data block, since the
+/// next packet isn't available. A decoder holds on to each data packet until the next one arrives
+/// and uses the next packet's first azimuth instead, which removes the small seam in the
+/// azimuths at the end of every packet.
+///
+/// If the decoder has a time source, every packet is shown to it and points are stamped with
+/// absolute times.
+#[derive(Debug, Default)]
+pub struct Decoder {
+    pending: Option<Packet>,
+    interpolation: Interpolation,
+    time_source: Option<Box<dyn TimeSource>>,
+    correction: Option<Box<dyn DistanceCorrection>>,
+    filters: Vec<Box<dyn Filter>>,
+    transform: Option<Transform>,
+    sensor_id: Option<u16>,
+    settings: Settings,
+    /// The number of firing sequences decoded so far, used to decimate across packets.
+    sequences: usize,
+    /// Whether a packet's data blocks have already been found at odds with its return mode.
+    return_mode_mismatch: bool,
+    metrics: DecodeMetrics,
+    warnings: Vec<DecodeWarning>,
+}
+
+/// Counts of what a decoder has decoded, and how long it took.
+///
+/// Only the time spent inside the decoder is counted, so the rates are what the decoder could
+/// sustain rather than what the source delivered. See `monitor::Monitor` for the throughput of a
+/// whole pipeline.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct DecodeMetrics {
+    /// The number of packets pushed, including position packets.
+    pub packets: usize,
+    /// The number of points returned.
+    pub points: usize,
+    /// The number of payload bytes in the pushed packets.
+    pub bytes: usize,
+    /// The time spent decoding.
+    pub elapsed: std::time::Duration,
+}
+
+/// How a decoder computes the rotation rate that is used to interpolate azimuths.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub enum Interpolation {
+    /// Interpolate between data blocks, assuming the nominal firing cycle duration.
+    #[default]
+    Nominal,
+    /// Use the first azimuths and timestamps of consecutive packets to measure the rotation rate.
+    ///
+    /// This stays accurate when the sensor's actual timing differs from the nominal timing, e.g.
+    /// at non-standard rotation rates. The last packet, and packets that are followed by a gap,
+    /// fall back to `Nominal`.
+    Timestamp,
+    /// Estimate the angular velocity at every data block from the azimuths on both sides of it,
+    /// and follow it smoothly through each laser's exact firing time.
+    ///
+    /// `Nominal` turns at one rate from one data block to the next, so the rate jumps at every
+    /// data block when the sensor speeds up or slows down. At high rotation rates, where the
+    /// sensor turns almost half a degree during one firing sequence, this sharpens edges. A
+    /// steady rotation decodes the same as with `Nominal`.
+    Local,
+}
+
+/// Which points a decoder keeps, for previews and consumers that can't keep up with every point.
+///
+/// Decimation happens before points are constructed, so skipped points cost next to nothing.
+/// Firing sequences are counted across packets, so the kept points are evenly spaced.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub enum Decimation {
+    /// Keep every point.
+    #[default]
+    None,
+    /// Keep every nth firing sequence, i.e. every nth column of the scan.
+    ///
+    /// Columns stay aligned, which suits range images.
+    Firings(usize),
+    /// Keep every nth point of each laser, staggering the lasers so every firing sequence
+    /// contributes some points.
+    ///
+    /// This spreads the kept points more evenly than `Firings`.
+    Points(usize),
+}
+
+/// What a decoder does with the two firing sequences of each single-return data block.
+///
+/// In the strongest and last return modes every data block holds two firing sequences, distinct
+/// firings 55.296 µs apart. Dual-return packets hold one sequence per block, so they always keep
+/// both.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum FiringSequences {
+    /// Keep the points of both sequences, telling them apart by `Point::sequence`.
+    #[default]
+    Both,
+    /// Keep only the points of each block's first sequence.
+    First,
+    /// Average each laser's two returns into one point, at the mean azimuth and time of the
+    /// two firings.
+    ///
+    /// A firing without a return doesn't count towards the average.
+    Averaged,
+}
+
+/// The floating point precision of a decoder's azimuths and coordinates.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub enum Precision {
+    /// Round interpolated azimuths to the nearest centidegree, the resolution of the azimuths in a
+    /// packet, and compute coordinates in `f32`.
+    #[default]
+    Single,
+    /// Keep interpolated azimuths exact and compute coordinates in `f64`, rounding to `f32` only
+    /// once, when the points are built.
+    ///
+    /// A centidegree is 1.7 centimeters across at 100 meters, so this removes a small, systematic
+    /// error that can bend long, straight features after georeferencing.
+    Double,
+}
+
+/// The axes of the decoded points' coordinates, relative to the sensor.
+///
+/// All conventions are right-handed with z up the sensor's spin axis. They only differ in which
+/// way x and y point, relative to the sensor's zero azimuth, which is usually the cable side.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Axes {
+    /// The frame in Velodyne's manual: y towards zero azimuth and x to its right, i.e.
+    /// `x = r·cos(ω)·sin(α)` and `y = r·cos(ω)·cos(α)`.
+    #[default]
+    Velodyne,
+    /// ROS's REP-103 body frame, forward-left-up: x towards zero azimuth and y to its left.
+    ForwardLeftUp,
+    /// East-north-up, with zero azimuth facing north.
+    ///
+    /// This is the same as `Velodyne`, spelled out for local tangent plane workflows.
+    EastNorthUp,
+}
+
+/// Which way decoded azimuths increase, seen from above.
+///
+/// Only the reported azimuths change; the points' coordinates are the same either way.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum AzimuthDirection {
+    /// Clockwise from the sensor's zero azimuth, as the sensor spins and sends them.
+    #[default]
+    Clockwise,
+    /// Counterclockwise from the sensor's zero azimuth, the mathematical convention.
+    ///
+    /// With `Axes::ForwardLeftUp`, this is the angle of a point's `atan2(y, x)`.
+    Counterclockwise,
+}
+
+/// How a decoder builds points, copied into every call that decodes a packet.
+#[derive(Clone, Copy, Debug, Default)]
+struct Settings {
+    return_mode: Option<ReturnMode>,
+    decimation: Decimation,
+    firing_sequences: FiringSequences,
+    channels: Option<u32>,
+    deterministic: bool,
+    precision: Precision,
+    azimuth_offsets: Option<[Degrees; MAX_LASERS]>,
+    elevations: Option<ElevationTable>,
+    axes: Axes,
+    azimuth_direction: AzimuthDirection,
+}
+
+impl Settings {
+    /// Returns a channel's elevation, from the elevation table or else the sensor's specification.
+    fn elevation(&self, spec: &SensorSpec, channel: usize) -> f32 {
+        self.elevations
+            .and_then(|table| table[channel])
+            .unwrap_or_else(|| spec.elevation(channel))
+            .0
+    }
+}
+
+/// Per-laser elevations that replace a sensor's nominal ones, where there's an entry.
+pub(crate) type ElevationTable = [Option<Degrees>; MAX_LASERS];
+
+/// What is known about the next packet when decoding a packet's azimuths.
+#[derive(Clone, Copy, Debug, Default)]
+struct Hint {
+    /// The first azimuth of the next packet.
+    next_azimuth: Option<Centidegrees>,
+    /// The measured rotation rate, as the centidegrees turned in a number of nanoseconds.
+    rate: Option<(i64, i64)>,
+    /// Whether to estimate the rotation rate at each data block, see `Interpolation::Local`.
+    local: bool,
+}
+
+/// The kinds of packets that a sensor sends.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum PacketKind {
+    /// A data packet, usually sent to port 2368.
+    Data,
+    /// A position packet, usually sent to port 8308.
+    Position,
 }
 
 /// An iterator over VLP-16 packets.
+///
+/// Captures taken on a shared network also hold ARP, mDNS, and other traffic, so bytes that
+/// `classify` doesn't recognize as a Velodyne packet are skipped and counted, unless the iterator
+/// is strict. If a Velodyne packet can't be parsed, the iterator returns an error for that packet
+/// and then continues with the next one. Errors from the underlying reader are passed through,
+/// and whether more packets follow is up to the reader, e.g. a truncated pcap record ends a
+/// `PcapStream`.
 #[derive(Clone, Copy, Debug)]
 pub struct Packets<R: VelodyneRead> {
     read: R,
+    strict: bool,
+    skipped: usize,
 }
 
 impl Packet {
     /// Creates a new packet from bytes.
     ///
+    /// The bytes can either be a full Ethernet frame, as stored in a pcap file, or just the UDP
+    /// payload, as received from a socket.
+    ///
+    /// This never panics, since the bytes usually come straight off of the network: anything that
+    /// isn't a valid packet, of any length, is an error. The `fuzz` directory has fuzz targets
+    /// that check this.
+    ///
     /// # Examples
     ///
     /// ```
     /// # use velodyne::vlp_16::Packet;
     /// use velodyne::fixtures::VLP_16_DATA_PACKET;
     /// let packet = Packet::new(&VLP_16_DATA_PACKET).unwrap();
+    /// let packet = Packet::new(&VLP_16_DATA_PACKET[42..]).unwrap();
+    /// assert!(Packet::new(&VLP_16_DATA_PACKET[..100]).is_err());
     /// ```
     pub fn new(bytes: &[u8]) -> Result<Packet> {
-        if &bytes[248..254] == b"$GPRMC" {
-            Packet::new_position(bytes)
+        Packet::parse(bytes, None, false)
+    }
+
+    /// Creates a new packet from bytes, accepting factory bytes that this crate doesn't know.
+    ///
+    /// Unknown sensor and return mode bytes become `Sensor::Unknown` and `ReturnMode::Unknown`,
+    /// so that captures from new firmware can still be decoded. Everything else is parsed as
+    /// with `new`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use velodyne::fixtures::VLP_16_DATA_PACKET;
+    /// use velodyne::vlp_16::{Packet, ReturnMode, Sensor};
+    /// use velodyne::wire;
+    /// let mut bytes = VLP_16_DATA_PACKET;
+    /// bytes[wire::PACKET_HEADER_LEN + wire::RETURN_MODE.offset] = 0x3a;
+    /// bytes[wire::PACKET_HEADER_LEN + wire::SENSOR.offset] = 0x28;
+    /// assert!(Packet::new(&bytes).is_err());
+    /// let packet = Packet::new_lenient(&bytes).unwrap();
+    /// assert_eq!(Some(ReturnMode::Unknown(0x3a)), packet.return_mode());
+    /// assert_eq!(Some(Sensor::Unknown(0x28)), packet.sensor());
+    /// assert_eq!(384, packet.points().unwrap().len());
+    /// ```
+    pub fn new_lenient(bytes: &[u8]) -> Result<Packet> {
+        Packet::parse(bytes, None, true)
+    }
+
+    /// Creates a new packet from bytes, decoding a data packet as if it came from `sensor`.
+    ///
+    /// The sensor factory byte is ignored, so captures whose byte is corrupt or comes from
+    /// unsupported firmware can still be decoded. The packet reports `sensor` as its sensor, and
+    /// position packets are parsed as with `new`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use velodyne::fixtures::VLP_16_DATA_PACKET;
+    /// use velodyne::vlp_16::{Packet, Sensor};
+    /// use velodyne::wire;
+    /// let mut bytes = VLP_16_DATA_PACKET;
+    /// bytes[wire::PACKET_HEADER_LEN + wire::SENSOR.offset] = 0x99;
+    /// assert!(Packet::new(&bytes).is_err());
+    /// let packet = Packet::with_sensor(&bytes, Sensor::VLP_16).unwrap();
+    /// assert_eq!(Sensor::VLP_16, packet.as_data().unwrap().sensor);
+    /// ```
+    pub fn with_sensor(bytes: &[u8], sensor: Sensor) -> Result<Packet> {
+        Packet::parse(bytes, Some(sensor), false)
+    }
+
+    /// Creates a new data packet from bytes that might be misaligned, e.g. because the capture
+    /// has the wrong header length or a record was cut short.
+    ///
+    /// Scans forward for the first data payload, see `resync`, and returns its packet along with
+    /// the number of bytes skipped to find it. For a well-formed Ethernet frame that's the
+    /// header's length.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use velodyne::fixtures::VLP_16_DATA_PACKET;
+    /// use velodyne::vlp_16::Packet;
+    /// let mut bytes = vec![0; 5];
+    /// bytes.extend_from_slice(&VLP_16_DATA_PACKET[42..]);
+    /// assert!(Packet::new(&bytes).is_err());
+    /// let (packet, skipped) = Packet::resync(&bytes).unwrap();
+    /// assert_eq!(5, skipped);
+    /// assert_eq!(384, packet.points().unwrap().len());
+    /// ```
+    pub fn resync(bytes: &[u8]) -> Result<(Packet, usize)> {
+        match resync(bytes) {
+            Some(offset) => {
+                let payload = &bytes[offset..offset + DATA_PAYLOAD_LEN];
+                Packet::new_data(payload, None, false).map(|packet| (packet, offset))
+            }
+            None if bytes.len() < DATA_PAYLOAD_LEN => {
+                Err(io::Error::from(io::ErrorKind::UnexpectedEof).into())
+            }
+            None => Err(Error::InvalidStartIdentifier(LittleEndian::read_u16(bytes))),
+        }
+    }
+
+    /// Parses a packet, with an optional sensor override, and unknown factory bytes if lenient.
+    pub(crate) fn parse(bytes: &[u8], sensor: Option<Sensor>, lenient: bool) -> Result<Packet> {
+        if classify(bytes) == Some(PacketKind::Position) {
+            Packet::new_position(payload(bytes))
         } else {
-            Packet::new_data(bytes)
+            Packet::new_data(payload(bytes), sensor, lenient)
+        }
+    }
+
+    /// Reads every Velodyne packet in a pcap file.
+    ///
+    /// Records that aren't Velodyne packets are skipped, and a Velodyne packet that can't be
+    /// parsed is an error. This is for quick scripts; use `io::Read::vlp_16_packets` to read
+    /// packets one at a time.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use velodyne::vlp_16::Packet;
+    /// let packets = Packet::from_pcap_path("data/single.pcap").unwrap();
+    /// assert_eq!(1, packets.len());
+    /// ```
+    pub fn from_pcap_path<P: AsRef<Path>>(path: P) -> Result<Vec<Packet>> {
+        let mut stream = PcapStream::new(io::BufReader::new(File::open(path)?))?;
+        let mut packets = Vec::new();
+        while let Some(bytes) = stream.read() {
+            let bytes = bytes?;
+            if classify(bytes).is_some() {
+                packets.push(Packet::new(bytes)?);
+            }
         }
+        Ok(packets)
     }
 
     /// Returns true if this is a data packet.
@@ -127,8 +614,8 @@ impl Packet {
     /// ```
     pub fn is_data(&self) -> bool {
         match *self {
-            Packet::Data { .. } => true,
-            Packet::Position { .. } => false,
+            Packet::Data(_) => true,
+            Packet::Position(_) => false,
         }
     }
 
@@ -146,17 +633,85 @@ impl Packet {
         !self.is_data()
     }
 
-    /// Returns this packet's data blocks, or none if it is a position packet.
-    pub fn data_blocks(&self) -> Option<[DataBlock; 12]> {
+    /// Returns this packet as a data packet, or an error if it is a position packet.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use velodyne::vlp_16::Packet;
+    /// use velodyne::fixtures::{VLP_16_DATA_PACKET, VLP_16_POSITION_PACKET};
+    /// let packet = Packet::new(&VLP_16_DATA_PACKET).unwrap();
+    /// let data_packet = packet.as_data().unwrap();
+    /// assert!(Packet::new(&VLP_16_POSITION_PACKET).unwrap().as_data().is_err());
+    /// ```
+    pub fn as_data(&self) -> Result<&DataPacket> {
+        match *self {
+            Packet::Data(ref data_packet) => Ok(data_packet),
+            Packet::Position(_) => Err(Error::NotADataPacket),
+        }
+    }
+
+    /// Converts this packet into a data packet, or returns an error if it is a position packet.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use velodyne::vlp_16::Packet;
+    /// use velodyne::fixtures::VLP_16_DATA_PACKET;
+    /// let data_packet = Packet::new(&VLP_16_DATA_PACKET).unwrap().into_data().unwrap();
+    /// assert_eq!(12, data_packet.data_blocks.len());
+    /// ```
+    pub fn into_data(self) -> Result<DataPacket> {
+        match self {
+            Packet::Data(data_packet) => Ok(data_packet),
+            Packet::Position(_) => Err(Error::NotADataPacket),
+        }
+    }
+
+    /// Returns this packet as a position packet, or an error if it is a data packet.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use velodyne::vlp_16::Packet;
+    /// use velodyne::fixtures::{VLP_16_DATA_PACKET, VLP_16_POSITION_PACKET};
+    /// let packet = Packet::new(&VLP_16_POSITION_PACKET).unwrap();
+    /// let position_packet = packet.as_position().unwrap();
+    /// assert!(Packet::new(&VLP_16_DATA_PACKET).unwrap().as_position().is_err());
+    /// ```
+    pub fn as_position(&self) -> Result<&PositionPacket> {
         match *self {
-            Packet::Data { ref data_blocks, .. } => Some(**data_blocks),
-            Packet::Position { .. } => None,
+            Packet::Data(_) => Err(Error::NotAPositionPacket),
+            Packet::Position(ref position_packet) => Ok(position_packet),
         }
     }
 
+    /// Converts this packet into a position packet, or returns an error if it is a data packet.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use velodyne::vlp_16::Packet;
+    /// use velodyne::fixtures::VLP_16_POSITION_PACKET;
+    /// let packet = Packet::new(&VLP_16_POSITION_PACKET).unwrap();
+    /// assert!(packet.into_position().unwrap().nmea.starts_with("$GPRMC"));
+    /// ```
+    pub fn into_position(self) -> Result<PositionPacket> {
+        match self {
+            Packet::Data(_) => Err(Error::NotAPositionPacket),
+            Packet::Position(position_packet) => Ok(position_packet),
+        }
+    }
+
+    /// Returns this packet's data blocks, or none if it is a position packet.
+    pub fn data_blocks(&self) -> Option<[DataBlock; 12]> {
+        self.as_data().ok().map(|data_packet| *data_packet.data_blocks)
+    }
+
     /// Returns this packet's timestamp.
     ///
-    /// A timestamp is a duration from the last UTC hour.
+    /// A timestamp is an offset from the last UTC hour, which a `time::TimeSource` can convert
+    /// into an absolute time.
     ///
     /// # Examples
     ///
@@ -165,11 +720,12 @@ impl Packet {
     /// # use velodyne::fixtures::VLP_16_DATA_PACKET;
     /// let packet = Packet::new(&VLP_16_DATA_PACKET).unwrap();
     /// let timestamp = packet.timestamp();
+    /// assert_eq!(2_467_108_343, timestamp.micros());
     /// ```
-    pub fn timestamp(&self) -> Duration {
+    pub fn timestamp(&self) -> HourOffset {
         match *self {
-            Packet::Data { timestamp, .. } |
-            Packet::Position { timestamp, .. } => timestamp,
+            Packet::Data(DataPacket { timestamp, .. }) |
+            Packet::Position(PositionPacket { timestamp, .. }) => timestamp,
         }
     }
 
@@ -184,10 +740,7 @@ impl Packet {
     /// let return_mode = packet.return_mode().unwrap();
     /// ```
     pub fn return_mode(&self) -> Option<ReturnMode> {
-        match *self {
-            Packet::Data { return_mode, .. } => Some(return_mode),
-            Packet::Position { .. } => None,
-        }
+        self.as_data().ok().map(|data_packet| data_packet.return_mode)
     }
 
     /// Returns this packet's sensor, or none if it's a position packet.
@@ -201,14 +754,13 @@ impl Packet {
     /// let sensor = packet.sensor().unwrap();
     /// ```
     pub fn sensor(&self) -> Option<Sensor> {
-        match *self {
-            Packet::Data { sensor, .. } => Some(sensor),
-            Packet::Position { .. } => None,
-        }
+        self.as_data().ok().map(|data_packet| data_packet.sensor)
     }
 
     /// Returns this packet's NMEA string, or none if it's a data packet.
     ///
+    /// If the packet has more than one sentence, they are separated by CRLF.
+    ///
     /// # Examples
     ///
     /// ```
@@ -218,10 +770,21 @@ impl Packet {
     /// let nmea = packet.nmea().unwrap();
     /// ```
     pub fn nmea(&self) -> Option<&str> {
-        match *self {
-            Packet::Data { .. } => None,
-            Packet::Position { ref nmea, .. } => Some(nmea),
-        }
+        self.as_position().ok().map(|position_packet| position_packet.nmea.as_str())
+    }
+
+    /// Returns this packet's diagnostic fields, or none if it's a data packet.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use velodyne::vlp_16::{Packet, PpsStatus};
+    /// # use velodyne::fixtures::VLP_16_POSITION_PACKET;
+    /// let packet = Packet::new(&VLP_16_POSITION_PACKET).unwrap();
+    /// assert_eq!(PpsStatus::Locked, packet.position_status().unwrap().pps);
+    /// ```
+    pub fn position_status(&self) -> Option<&PositionStatus> {
+        self.as_position().ok().map(|position_packet| &position_packet.status)
     }
 
     /// Returns the points contained within this data packet.
@@ -237,55 +800,263 @@ impl Packet {
     /// let points = packet.points().unwrap();
     /// ```
     pub fn points(&self) -> Option<Vec<Point>> {
-        match *self {
-            Packet::Data { ref data_blocks, timestamp, return_mode, .. } => {
-                if return_mode == ReturnMode::DualReturn {
-                    unimplemented!()
+        self.points_with(Hint::default(), Settings::default(), None, 0)
+    }
+
+    /// Returns the number of firing sequences in this packet, or zero for a position packet.
+    fn sequences(&self, return_mode: Option<ReturnMode>) -> usize {
+        let data = match self.as_data() {
+            Ok(data) => data,
+            Err(_) => return 0,
+        };
+        let return_mode = data.resolve(return_mode.unwrap_or(data.return_mode));
+        Timing::for_sensor(data.sensor).sequences_per_packet(return_mode)
+    }
+
+    /// Returns the firing timing of this packet's sensor, or the VLP-16's for a position packet.
+    fn timing(&self) -> &'static Timing {
+        self.sensor().map_or(&timing::VLP_16, Timing::for_sensor)
+    }
+
+    /// `first` is the number of the packet's first firing sequence, for decimation.
+    fn points_with(&self,
+                   hint: Hint,
+                   settings: Settings,
+                   correction: Option<&dyn DistanceCorrection>,
+                   first: usize)
+                   -> Option<Vec<Point>> {
+        let data = self.as_data().ok()?;
+        let DataPacket { ref data_blocks, timestamp, return_mode, .. } = *data;
+        let timestamp = timestamp.duration();
+        let return_mode = data.resolve(settings.return_mode.unwrap_or(return_mode));
+        let (stride, return_types) = return_mode.return_types();
+        let double = settings.precision == Precision::Double;
+        let timing = Timing::for_sensor(data.sensor);
+        let sequences = timing.sequences_per_data_block;
+        // Sensors with one firing sequence per data block have no second sequence to pick.
+        let firing_sequences = if stride == 1 && sequences == 2 {
+            settings.firing_sequences
+        } else {
+            FiringSequences::Both
+        };
+        let averaged = firing_sequences == FiringSequences::Averaged;
+        let spec = data.sensor.spec();
+        let azimuth_model = AzimuthModel::new(**data_blocks, stride, timing, hint);
+        let azimuth_at = |data_block, sequence, channel| if double {
+            azimuth_model.azimuth_f64(data_block, sequence, channel)
+        } else {
+            let azimuth = azimuth_model.azimuth(data_block, sequence, channel);
+            (azimuth, f64::from(azimuth.degrees()))
+        };
+        let mut points = Vec::with_capacity(NUM_POINTS);
+        let mut ranges = [0.; NUM_POINTS];
+        let mut azimuths = [0.; NUM_POINTS];
+        let mut elevations = [0.; NUM_POINTS];
+        let mut precise_azimuths = [0.; NUM_POINTS];
+        for (group, data_blocks) in data_blocks.chunks(stride).enumerate() {
+            for sequence in 0..sequences {
+                if sequence == 1 && firing_sequences != FiringSequences::Both {
+                    continue;
+                }
+                for channel in 0..timing.lasers_per_sequence() {
+                    if !settings.decimation.keeps(first + sequences * group + sequence, channel) ||
+                       settings.channels.is_some_and(|mask| mask & 1 << channel == 0) {
+                        continue;
+                    }
+                    let (mut azimuth, mut degrees) = azimuth_at(group * stride, sequence, channel);
+                    let mut offset = timing.firing_offset(group, sequence, channel);
+                    if averaged {
+                        let second = azimuth_at(group, 1, channel);
+                        degrees = mean_azimuth(degrees, second.1, double);
+                        // Rounding to an `f32` can give exactly 360°, as with any azimuth.
+                        let mean = Degrees(degrees as f32 % 360.);
+                        azimuth = match second.0 {
+                            Azimuth::Extrapolated(_) => Azimuth::Extrapolated(mean),
+                            _ => Azimuth::Interpolated(mean),
+                        };
+                        offset = (offset + timing.firing_offset(group, 1, channel)) / 2;
+                    }
+                    // Corrections only change the direction, so frames are still cut on the
+                    // encoder's azimuth.
+                    let azimuth_correction = settings.azimuth_offsets
+                        .map_or(0., |offsets| offsets[channel].0);
+                    let time = Time::Offset(timestamp + offset);
+                    let (half, index) = timing.data_record(sequence, channel);
+                    for (data_block, &return_type) in data_blocks.iter().zip(return_types) {
+                        let mut data_record = data_block.data_records[half][index];
+                        if averaged {
+                            data_record = data_record.average(&data_block.data_records[1][index]);
+                        }
+                        let mut distance = data_record.return_distance;
+                        if let Some(correction) = correction.filter(|_| distance.0 > 0.) {
+                            distance = correction.correct(channel, distance);
+                        }
+                        ranges[points.len()] = distance.0;
+                        azimuths[points.len()] = azimuth.degrees() + azimuth_correction;
+                        precise_azimuths[points.len()] =
+                            degrees + f64::from(azimuth_correction);
+                        elevations[points.len()] = settings.elevation(spec, channel);
+                        points.push(Point {
+                                        x: 0.,
+                                        y: 0.,
+                                        z: 0.,
+                                        reflectivity: data_record.calibrated_reflectivity,
+                                        channel: channel as u8,
+                                        sequence: sequence as u8,
+                                        return_type,
+                                        azimuth,
+                                        time,
+                                        sensor_id: None,
+                                    });
+                    }
                 }
-                let azimuth_model = AzimuthModel::new(**data_blocks);
-                let mut points = Vec::new();
-                for (i, data_block) in data_blocks.iter().enumerate() {
-                    for (j, sequence) in data_block.data_records.iter().enumerate() {
-                        for (channel, data_record) in sequence.iter().enumerate() {
-                            let azimuth = azimuth_model.predict(i, j, channel);
-                            let azimuth_rad = azimuth.to_radians();
-                            let azimuth = if j == 0 && channel == 0 {
-                                Azimuth::Measured(azimuth)
-                            } else if i < NUM_DATA_BLOCKS - 1 {
-                                Azimuth::Interpolated(azimuth)
-                            } else {
-                                Azimuth::Extrapolated(azimuth)
-                            };
-                            let vertical_angle = vertical_angle(channel).to_radians();
-                            let return_type = match return_mode {
-                                ReturnMode::StrongestReturn => ReturnType::Strongest,
-                                ReturnMode::LastReturn => ReturnType::Last,
-                                ReturnMode::DualReturn => unimplemented!(),
-                            };
-                            points.push(Point {
-                                            x: data_record.return_distance * vertical_angle.cos() *
-                                               azimuth_rad.sin(),
-                                            y: data_record.return_distance * vertical_angle.cos() *
-                                               azimuth_rad.cos(),
-                                            z: data_record.return_distance * vertical_angle.sin(),
-                                            reflectivity: data_record.calibrated_reflectivity,
-                                            channel: channel as u8,
-                                            azimuth: azimuth,
-                                            return_type: return_type,
-                                            time: Time::Offset(timestamp +
-                                                               time_offset(i, j, channel)),
-                                        });
+            }
+        }
+        let n = points.len();
+        let mut xyz = [[0.; 3]; NUM_POINTS];
+        if settings.deterministic {
+            reconstruct::to_cartesian_portable(&ranges[..n],
+                                               &azimuths[..n],
+                                               &elevations[..n],
+                                               &mut xyz[..n]);
+        } else if double {
+            let mut ranges_f64 = [0.; NUM_POINTS];
+            let mut elevations_f64 = [0.; NUM_POINTS];
+            for i in 0..n {
+                ranges_f64[i] = f64::from(ranges[i]);
+                elevations_f64[i] = f64::from(elevations[i]);
+            }
+            let mut xyz_f64 = [[0.; 3]; NUM_POINTS];
+            reconstruct::to_cartesian_f64(&ranges_f64[..n],
+                                          &precise_azimuths[..n],
+                                          &elevations_f64[..n],
+                                          &mut xyz_f64[..n]);
+            for (xyz, xyz_f64) in xyz.iter_mut().zip(&xyz_f64[..n]) {
+                *xyz = [xyz_f64[0] as f32, xyz_f64[1] as f32, xyz_f64[2] as f32];
+            }
+        } else {
+            reconstruct::to_cartesian(&ranges[..n],
+                                      &azimuths[..n],
+                                      &elevations[..n],
+                                      &mut xyz[..n]);
+        }
+        for (point, &xyz) in points.iter_mut().zip(&xyz) {
+            let [x, y, z] = settings.axes.convert(xyz);
+            point.x = x;
+            point.y = y;
+            point.z = z;
+            point.azimuth = settings.azimuth_direction.convert(point.azimuth);
+        }
+        Some(points)
+    }
+
+    /// Returns the firings in this packet, pairing up the two returns of each laser pulse.
+    ///
+    /// In dual-return mode the data blocks come in pairs that share an azimuth: the first block
+    /// holds the last returns and the second holds the strongest returns. Returns `None` if this
+    /// isn't a dual-return data packet.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use velodyne::vlp_16::Packet;
+    /// # use velodyne::fixtures::VLP_16_DATA_PACKET;
+    /// let packet = Packet::new(&VLP_16_DATA_PACKET).unwrap();
+    /// assert!(packet.firings().is_none());
+    /// ```
+    pub fn firings(&self) -> Option<Vec<Firing>> {
+        match *self {
+            Packet::Data(DataPacket { ref data_blocks,
+                                      timestamp,
+                                      return_mode: ReturnMode::DualReturn,
+                                      sensor,
+                                      .. }) => {
+                let timing = Timing::for_sensor(sensor);
+                let azimuth_model = AzimuthModel::new(**data_blocks, 2, timing, Hint::default());
+                let mut firings = Vec::new();
+                for (pair, data_blocks) in data_blocks.chunks(2).enumerate() {
+                    for sequence in 0..timing.sequences_per_data_block {
+                        for channel in 0..timing.lasers_per_sequence() {
+                            let (half, index) = timing.data_record(sequence, channel);
+                            let last = data_blocks[0].data_records[half][index];
+                            let strongest = data_blocks[1].data_records[half][index];
+                            let time = timestamp.duration() +
+                                       timing.firing_offset(pair, sequence, channel);
+                            firings.push(Firing {
+                                             strongest,
+                                             last,
+                                             azimuth: azimuth_model.azimuth(pair * 2,
+                                                                            sequence,
+                                                                            channel),
+                                             channel: channel as u8,
+                                             time: Time::Offset(time),
+                                             sensor,
+                                         });
                         }
                     }
                 }
-                Some(points)
+                Some(firings)
+            }
+            _ => None,
+        }
+    }
+
+    /// Returns every return of every firing in this packet, in the same order as `points`.
+    ///
+    /// Azimuths and times are as `points` computes them, without a decoder's settings or hints.
+    /// Returns `None` if this is a position packet.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use velodyne::vlp_16::Packet;
+    /// # use velodyne::fixtures::VLP_16_DATA_PACKET;
+    /// let packet = Packet::new(&VLP_16_DATA_PACKET).unwrap();
+    /// let firing_records = packet.firing_records().unwrap();
+    /// assert_eq!(384, firing_records.len());
+    /// assert_eq!((11, 1, 15), {
+    ///     let last = firing_records[383];
+    ///     (last.data_block, last.sequence, last.channel)
+    /// });
+    /// ```
+    pub fn firing_records(&self) -> Option<Vec<FiringRecord>> {
+        let data = self.as_data().ok()?;
+        let (stride, return_types) = data.resolve(data.return_mode).return_types();
+        let timing = Timing::for_sensor(data.sensor);
+        let azimuth_model = AzimuthModel::new(*data.data_blocks, stride, timing, Hint::default());
+        let mut firing_records = Vec::with_capacity(NUM_POINTS);
+        for (group, data_blocks) in data.data_blocks.chunks(stride).enumerate() {
+            for sequence in 0..timing.sequences_per_data_block {
+                for channel in 0..timing.lasers_per_sequence() {
+                    let azimuth = azimuth_model.azimuth(group * stride, sequence, channel);
+                    let time = data.timestamp.duration() +
+                               timing.firing_offset(group, sequence, channel);
+                    let (half, index) = timing.data_record(sequence, channel);
+                    let returns = data_blocks.iter().zip(return_types).enumerate();
+                    for (i, (data_block, &return_type)) in returns {
+                        let data_record = data_block.data_records[half][index];
+                        firing_records.push(FiringRecord {
+                                                data_block: (group * stride + i) as u8,
+                                                sequence: sequence as u8,
+                                                channel: channel as u8,
+                                                return_type,
+                                                distance: data_record.return_distance,
+                                                reflectivity: data_record.calibrated_reflectivity,
+                                                azimuth,
+                                                time: Time::Offset(time),
+                                                sensor: data.sensor,
+                                            });
+                    }
+                }
             }
-            Packet::Position { .. } => None,
         }
+        Some(firing_records)
     }
 
     /// Returns the position as specified by the NMEA string, or none if this is a data packet.
     ///
+    /// The position is stamped with this packet's timestamp as the time it was received.
+    ///
     /// # Examples
     ///
     /// ```
@@ -293,83 +1064,1088 @@ impl Packet {
     /// # use velodyne::fixtures::VLP_16_POSITION_PACKET;
     /// let packet = Packet::new(&VLP_16_POSITION_PACKET).unwrap();
     /// let position = packet.position().unwrap().unwrap();
+    /// assert!(position.latency().is_some());
     /// ```
     pub fn position(&self) -> Option<Result<Position>> {
-        self.nmea().map(|nmea| Position::new(nmea))
+        let packet = self.as_position().ok()?;
+        let position = match nmea::split(&packet.nmea).find(|s| s.get(3..6) == Some("RMC")) {
+            Some(sentence) => Position::new(sentence),
+            None => Err(ParseError::MissingSentence("RMC").into()),
+        };
+        Some(position.map(|position| packet.received(position)))
     }
 
-    fn new_position(bytes: &[u8]) -> Result<Packet> {
-        let mut cursor = Cursor::new(&bytes[PACKET_HEADER_LEN + 198..]);
-        let timestamp = Duration::microseconds(cursor.read_u32::<LittleEndian>()? as i64);
-        let mut nmea = String::new();
-        cursor.set_position(8);
-        cursor.take(72).read_to_string(&mut nmea)?;
-        Ok(Packet::Position {
-               timestamp: timestamp,
-               nmea: nmea,
-           })
+    /// Returns all of the NMEA sentences in this packet, or none if this is a data packet.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use velodyne::vlp_16::Packet;
+    /// # use velodyne::fixtures::VLP_16_POSITION_PACKET;
+    /// use velodyne::nmea::Sentence;
+    /// let packet = Packet::new(&VLP_16_POSITION_PACKET).unwrap();
+    /// let sentences = packet.sentences().unwrap().unwrap();
+    /// match sentences[0] {
+    ///     Sentence::Rmc(position) => assert!(position.valid),
+    ///     _ => panic!("should be a $GPRMC sentence"),
+    /// }
+    /// ```
+    pub fn sentences(&self) -> Option<Result<Vec<Sentence>>> {
+        let packet = self.as_position().ok()?;
+        Some(nmea::split(&packet.nmea)
+                 .map(|sentence| match Sentence::new(sentence)? {
+                          Sentence::Rmc(position) => Ok(Sentence::Rmc(packet.received(position))),
+                          sentence => Ok(sentence),
+                      })
+                 .collect())
     }
 
-    fn new_data(bytes: &[u8]) -> Result<Packet> {
-        let mut data_blocks: [DataBlock; NUM_DATA_BLOCKS] = Default::default();
-        let mut cursor = Cursor::new(&bytes[PACKET_HEADER_LEN..]);
-        for mut data_block in &mut data_blocks {
-            *data_block = DataBlock::read_from(&mut cursor)?;
-        }
-        let timestamp = Duration::microseconds(cursor.read_u32::<LittleEndian>()? as i64);
-        let return_mode = ReturnMode::from_u8(cursor.read_u8()?)?;
-        let sensor = Sensor::from_u8(cursor.read_u8()?)?;
-        Ok(Packet::Data {
-               data_blocks: Box::new(data_blocks),
-               timestamp: timestamp,
-               return_mode: return_mode,
-               sensor: sensor,
-           })
+    /// Returns a warning for each NMEA sentence whose checksum is wrong, if this is a position
+    /// packet.
+    pub(crate) fn checksum_warnings(&self) -> Vec<DecodeWarning> {
+        let packet = match self.as_position() {
+            Ok(packet) => packet,
+            Err(_) => return Vec::new(),
+        };
+        nmea::split(&packet.nmea)
+            .filter_map(|sentence| match nmea::check(sentence, Validation::Lenient) {
+                            Err(Error::Nmea(ParseError::BadChecksum { expected, calculated })) => {
+                                Some(DecodeWarning::NmeaChecksum {
+                                         sentence: sentence.to_string(),
+                                         expected,
+                                         calculated,
+                                     })
+                            }
+                            _ => None,
+                        })
+            .collect()
     }
-}
 
-impl DataBlock {
-    fn read_from<R: Read>(mut read: R) -> Result<DataBlock> {
-        let start_identifier = read.read_u16::<LittleEndian>()?;
-        if start_identifier != START_IDENTIFIER {
-            return Err(Error::InvalidStartIdentifier(start_identifier));
-        }
-        let azimuth = read.read_u16::<LittleEndian>()? as f32 / AZIMUTH_SCALE_FACTOR;
-        let mut data_records: [[DataRecord; NUM_LASERS]; 2] = Default::default();
-        for data_set in &mut data_records {
-            for mut data_record in data_set {
-                *data_record = DataRecord::read_from(&mut read)?;
-            }
-        }
-        Ok(DataBlock {
-               azimuth: azimuth,
-               data_records: data_records,
-           })
+    fn new_position(payload: &[u8]) -> Result<Packet> {
+        let payload = raw::PositionPayload::parse(payload)?;
+        Ok(Packet::Position(PositionPacket {
+               timestamp: HourOffset::from_micros(payload.timestamp),
+               nmea: nmea::Buffer::from_bytes(payload.nmea),
+               status: PositionStatus {
+                   top_board_temperature: payload.top_board_temperature,
+                   bottom_board_temperature: payload.bottom_board_temperature,
+                   adc_calibration_temperature: payload.adc_calibration_temperature,
+                   adc_calibration_temperature_change: payload.adc_calibration_temperature_change,
+                   seconds_since_adc_calibration: payload.seconds_since_adc_calibration,
+                   adc_calibration_reason: payload.adc_calibration_reason,
+                   adc_calibration_status: payload.adc_calibration_status,
+                   pps: PpsStatus::from_u8(payload.pps_status)?,
+                   thermal_shutdown: payload.thermal_shutdown != 0,
+                   last_shutdown_temperature: payload.last_shutdown_temperature,
+                   power_up_temperature: payload.power_up_temperature,
+               },
+           }))
     }
-}
 
-impl DataRecord {
-    fn read_from<R: Read>(mut read: R) -> Result<DataRecord> {
-        Ok(DataRecord {
-               return_distance: read.read_u16::<LittleEndian>()? as f32 * DISTANCE_SCALE_FACTOR,
-               calibrated_reflectivity: read.read_u8()?,
-           })
+    fn new_data(payload: &[u8], sensor: Option<Sensor>, lenient: bool) -> Result<Packet> {
+        // The factory bytes come last, but the sensor determines how distances are scaled.
+        let sensor = match (payload.get(wire::SENSOR.offset), sensor) {
+            (Some(_), Some(sensor)) => sensor,
+            (Some(&n), None) if lenient => Sensor::from_u8(n).unwrap_or(Sensor::Unknown(n)),
+            (Some(&n), None) => Sensor::from_u8(n)?,
+            (None, _) => return Err(io::Error::from(io::ErrorKind::UnexpectedEof).into()),
+        };
+        let distance_resolution = sensor.spec().distance_resolution;
+        let payload = raw::DataPayload::parse(payload)?;
+        let mut data_blocks: [DataBlock; NUM_DATA_BLOCKS] = Default::default();
+        for (data_block, block) in data_blocks.iter_mut().zip(&payload.data_blocks) {
+            *data_block = DataBlock::from_raw(block, distance_resolution);
+        }
+        let return_mode = match payload.return_mode {
+            n if lenient => ReturnMode::from_u8(n).unwrap_or(ReturnMode::Unknown(n)),
+            n => ReturnMode::from_u8(n)?,
+        };
+        Ok(Packet::Data(DataPacket {
+               data_blocks: Box::new(data_blocks),
+               timestamp: HourOffset::from_micros(payload.timestamp),
+               return_mode,
+               sensor,
+           }))
     }
 }
 
-impl ReturnMode {
-    fn from_u8(n: u8) -> Result<ReturnMode> {
-        match n {
-            0x37 => Ok(ReturnMode::StrongestReturn),
+impl DataPacket {
+    /// Returns the number of points that this packet decodes into, without decoding them.
+    ///
+    /// Every data record becomes a point whatever the return mode, since dual-return packets
+    /// hold half as many firing sequences. If `skip_no_return` is true, records without a return,
+    /// i.e. with a zero distance, aren't counted.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use velodyne::fixtures::VLP_16_DATA_PACKET;
+    /// use velodyne::vlp_16::Packet;
+    /// let packet = Packet::new(&VLP_16_DATA_PACKET).unwrap();
+    /// let points = packet.points().unwrap();
+    /// let data_packet = packet.into_data().unwrap();
+    /// assert_eq!(points.len(), data_packet.point_count(false));
+    /// assert!(data_packet.point_count(true) < points.len());
+    /// ```
+    pub fn point_count(&self, skip_no_return: bool) -> usize {
+        if !skip_no_return {
+            return NUM_POINTS;
+        }
+        self.data_blocks
+            .iter()
+            .flat_map(|data_block| data_block.data_records.iter().flat_map(|set| set.iter()))
+            .filter(|data_record| data_record.return_value().is_some())
+            .count()
+    }
+
+    /// Returns the azimuths that the data blocks span, without decoding any points.
+    ///
+    /// The span ends at the last data block's azimuth, so it doesn't include the second firing
+    /// sequence of that block.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use velodyne::fixtures::VLP_16_DATA_PACKET;
+    /// use velodyne::vlp_16::Packet;
+    /// let packet = Packet::new(&VLP_16_DATA_PACKET).unwrap().into_data().unwrap();
+    /// let span = packet.azimuth_span();
+    /// assert_eq!(packet.data_blocks[0].azimuth, span.first);
+    /// assert!(span.coverage.0 > 0 && span.coverage.0 < 1000);
+    /// ```
+    pub fn azimuth_span(&self) -> AzimuthSpan {
+        AzimuthSpan::new(self.data_blocks[0].azimuth.0,
+                         self.data_blocks[NUM_DATA_BLOCKS - 1].azimuth.0)
+    }
+
+    /// Returns false if the data blocks are laid out differently than a return mode's.
+    ///
+    /// Dual-return data blocks come in pairs that share an azimuth, and single-return data blocks
+    /// don't, unless the sensor isn't spinning. Some firmware reports the wrong return mode after
+    /// its settings change, which this catches. Every layout is consistent with an unknown return
+    /// mode.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use velodyne::fixtures::VLP_16_DATA_PACKET;
+    /// use velodyne::vlp_16::{Packet, ReturnMode};
+    /// let packet = Packet::new(&VLP_16_DATA_PACKET).unwrap().into_data().unwrap();
+    /// assert!(packet.is_consistent_with(ReturnMode::StrongestReturn));
+    /// assert!(!packet.is_consistent_with(ReturnMode::DualReturn));
+    /// ```
+    pub fn is_consistent_with(&self, return_mode: ReturnMode) -> bool {
+        let paired = self.data_blocks.chunks(2).all(|pair| pair[0].azimuth == pair[1].azimuth);
+        let first = self.data_blocks[0].azimuth;
+        let spinning = self.data_blocks.iter().any(|block| block.azimuth != first);
+        !spinning || matches!(return_mode, ReturnMode::Unknown(_)) ||
+        paired == (return_mode == ReturnMode::DualReturn)
+    }
+
+    /// Replaces an unknown return mode with the one that the data blocks are laid out for.
+    fn resolve(&self, return_mode: ReturnMode) -> ReturnMode {
+        match return_mode {
+            ReturnMode::Unknown(_) if self.is_consistent_with(ReturnMode::StrongestReturn) => {
+                ReturnMode::StrongestReturn
+            }
+            ReturnMode::Unknown(_) => ReturnMode::DualReturn,
+            return_mode => return_mode,
+        }
+    }
+}
+
+impl PositionPacket {
+    /// Stamps a position parsed from this packet with the time it was received.
+    fn received(&self, mut position: Position) -> Position {
+        position.received = Some(self.timestamp);
+        position
+    }
+}
+
+impl Decoder {
+    /// Creates a new decoder.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use velodyne::vlp_16::Decoder;
+    /// let decoder = Decoder::new();
+    /// ```
+    pub fn new() -> Decoder {
+        Decoder::default()
+    }
+
+    /// Sets how azimuths are interpolated.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use velodyne::vlp_16::{Decoder, Interpolation};
+    /// let mut decoder = Decoder::new();
+    /// decoder.interpolation(Interpolation::Timestamp);
+    /// ```
+    pub fn interpolation(&mut self, interpolation: Interpolation) -> &mut Decoder {
+        self.interpolation = interpolation;
+        self
+    }
+
+    /// Sets the time source used to stamp points with absolute times.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use velodyne::vlp_16::Decoder;
+    /// use velodyne::time::GpsTime;
+    /// let mut decoder = Decoder::new();
+    /// decoder.time_source(GpsTime::new());
+    /// ```
+    pub fn time_source<T: TimeSource + 'static>(&mut self, time_source: T) -> &mut Decoder {
+        self.time_source = Some(Box::new(time_source));
+        self
+    }
+
+    /// Returns the time quality reported by the time source, as of the last pushed packet.
+    ///
+    /// Returns `None` if there's no time source, or if it can't tell.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use velodyne::vlp_16::{Decoder, Packet};
+    /// # use velodyne::fixtures::VLP_16_POSITION_PACKET;
+    /// use velodyne::time::{GpsTime, TimeQuality};
+    /// let mut decoder = Decoder::new();
+    /// decoder.time_source(GpsTime::new());
+    /// decoder.push(Packet::new(&VLP_16_POSITION_PACKET).unwrap());
+    /// assert_eq!(Some(TimeQuality::Locked), decoder.time_quality());
+    /// ```
+    pub fn time_quality(&self) -> Option<TimeQuality> {
+        self.time_source.as_ref().and_then(|time_source| time_source.quality())
+    }
+
+    /// Sets which points are kept.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use velodyne::vlp_16::{Decoder, Packet};
+    /// use velodyne::fixtures::VLP_16_DATA_PACKET;
+    /// use velodyne::vlp_16::Decimation;
+    /// let mut decoder = Decoder::new();
+    /// decoder.decimation(Decimation::Firings(4));
+    /// decoder.push(Packet::new(&VLP_16_DATA_PACKET).unwrap());
+    /// assert_eq!(96, decoder.finish().unwrap().len());
+    /// ```
+    pub fn decimation(&mut self, decimation: Decimation) -> &mut Decoder {
+        self.settings.decimation = decimation;
+        self
+    }
+
+    /// Sets what happens to the two firing sequences of single-return data blocks.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use velodyne::vlp_16::{Decoder, Packet};
+    /// use velodyne::fixtures::VLP_16_DATA_PACKET;
+    /// use velodyne::vlp_16::FiringSequences;
+    /// let mut decoder = Decoder::new();
+    /// decoder.firing_sequences(FiringSequences::First);
+    /// decoder.push(Packet::new(&VLP_16_DATA_PACKET).unwrap());
+    /// let points = decoder.finish().unwrap();
+    /// assert_eq!(192, points.len());
+    /// assert!(points.iter().all(|point| point.sequence == 0));
+    /// ```
+    pub fn firing_sequences(&mut self, firing_sequences: FiringSequences) -> &mut Decoder {
+        self.settings.firing_sequences = firing_sequences;
+        self
+    }
+
+    /// Only decodes these channels, i.e. rings.
+    ///
+    /// Like decimation, this happens before points are constructed, so it is much cheaper than
+    /// `PointStream::rings`. Channels that the sensor doesn't have are ignored.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use velodyne::vlp_16::{Decoder, Packet};
+    /// use velodyne::fixtures::VLP_16_DATA_PACKET;
+    /// let mut decoder = Decoder::new();
+    /// decoder.channels(&[4, 5, 6, 7, 8, 9, 10, 11]);
+    /// decoder.push(Packet::new(&VLP_16_DATA_PACKET).unwrap());
+    /// assert_eq!(192, decoder.finish().unwrap().len());
+    /// ```
+    pub fn channels(&mut self, channels: &[u8]) -> &mut Decoder {
+        let mask = channels.iter()
+            .filter(|&&channel| (channel as usize) < MAX_LASERS)
+            .fold(0, |mask, &channel| mask | 1 << channel);
+        self.settings.channels = Some(mask);
+        self
+    }
+
+    /// Overrides the return mode that packets report in their factory bytes.
+    ///
+    /// Some firmware reports the wrong return mode after its settings change. Without an
+    /// override, the decoder warns once if a packet's data blocks don't match its return mode,
+    /// see `DataPacket::is_consistent_with`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use velodyne::vlp_16::{Decoder, Packet};
+    /// use velodyne::fixtures::VLP_16_DATA_PACKET;
+    /// use velodyne::vlp_16::ReturnMode;
+    /// let mut decoder = Decoder::new();
+    /// decoder.return_mode(ReturnMode::LastReturn);
+    /// decoder.push(Packet::new(&VLP_16_DATA_PACKET).unwrap());
+    /// assert_eq!(384, decoder.finish().unwrap().len());
+    /// ```
+    pub fn return_mode(&mut self, return_mode: ReturnMode) -> &mut Decoder {
+        self.settings.return_mode = Some(return_mode);
+        self
+    }
+
+    /// Returns true if a pushed data packet's data blocks didn't match its return mode.
+    ///
+    /// Packets aren't checked if the return mode is overridden.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use velodyne::vlp_16::{Decoder, Packet};
+    /// use velodyne::fixtures::VLP_16_DATA_PACKET;
+    /// let mut decoder = Decoder::new();
+    /// decoder.push(Packet::new(&VLP_16_DATA_PACKET).unwrap());
+    /// assert!(!decoder.return_mode_mismatch());
+    /// ```
+    pub fn return_mode_mismatch(&self) -> bool {
+        self.return_mode_mismatch
+    }
+
+    /// Sets whether points are bit-identical on every platform and every run.
+    ///
+    /// Azimuths and times are always computed with integers. Deterministic decoding also converts
+    /// points to cartesian coordinates with `reconstruct::to_cartesian_portable` instead of the
+    /// platform's sine and cosine or the SIMD code, which is a little slower. Transforms, filters,
+    /// and time sources are applied with plain arithmetic in a fixed order, but configuration that
+    /// is built with trigonometry, e.g. `Transform::from_euler` or `CropBox::yaw`, can still
+    /// differ between platforms.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use velodyne::vlp_16::{Decoder, Packet};
+    /// use velodyne::fixtures::VLP_16_DATA_PACKET;
+    /// let mut decoder = Decoder::new();
+    /// decoder.deterministic(true);
+    /// decoder.push(Packet::new(&VLP_16_DATA_PACKET).unwrap());
+    /// assert_eq!(384, decoder.finish().unwrap().len());
+    /// ```
+    pub fn deterministic(&mut self, deterministic: bool) -> &mut Decoder {
+        self.settings.deterministic = deterministic;
+        self
+    }
+
+    /// Sets the precision of azimuths and coordinates.
+    ///
+    /// Points are always stored in `f32`. Deterministic decoding uses exact azimuths with double
+    /// precision, but still computes coordinates with its portable `f32` code.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use velodyne::vlp_16::{Decoder, Packet};
+    /// use velodyne::fixtures::VLP_16_DATA_PACKET;
+    /// use velodyne::vlp_16::Precision;
+    /// let mut decoder = Decoder::new();
+    /// decoder.precision(Precision::Double);
+    /// decoder.push(Packet::new(&VLP_16_DATA_PACKET).unwrap());
+    /// assert_eq!(384, decoder.finish().unwrap().len());
+    /// ```
+    pub fn precision(&mut self, precision: Precision) -> &mut Decoder {
+        self.settings.precision = precision;
+        self
+    }
+
+    /// Sets per-laser azimuth corrections, indexed by channel, from a sensor's calibration.
+    ///
+    /// Each correction is added to the azimuth of its laser's points before they are converted to
+    /// cartesian coordinates. The points' `azimuth` fields keep the encoder's azimuth, so frames
+    /// are still cut where the sensor was pointing. Channels without a correction aren't
+    /// corrected, and corrections past the 32nd channel are ignored.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use velodyne::vlp_16::{Decoder, Packet};
+    /// use velodyne::fixtures::VLP_16_DATA_PACKET;
+    /// use velodyne::units::Degrees;
+    /// let mut decoder = Decoder::new();
+    /// decoder.azimuth_offsets(&[Degrees(-1.2), Degrees(1.2)]);
+    /// decoder.push(Packet::new(&VLP_16_DATA_PACKET).unwrap());
+    /// assert_eq!(384, decoder.finish().unwrap().len());
+    /// ```
+    pub fn azimuth_offsets(&mut self, offsets: &[Degrees]) -> &mut Decoder {
+        let mut table = [Degrees(0.); MAX_LASERS];
+        for (entry, &offset) in table.iter_mut().zip(offsets) {
+            *entry = offset;
+        }
+        self.settings.azimuth_offsets = Some(table);
+        self
+    }
+
+    /// Sets per-laser elevations, indexed by channel, e.g. from a sensor's calibration sheet.
+    ///
+    /// These replace the nominal elevations of the sensor's specification. Channels without an
+    /// entry keep their nominal elevation, and entries past the 32nd channel are ignored.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use velodyne::vlp_16::{Decoder, Packet};
+    /// use velodyne::fixtures::VLP_16_DATA_PACKET;
+    /// use velodyne::units::Degrees;
+    /// let mut decoder = Decoder::new();
+    /// decoder.elevations(&[Degrees(-15.1), Degrees(1.02)]);
+    /// decoder.push(Packet::new(&VLP_16_DATA_PACKET).unwrap());
+    /// assert_eq!(384, decoder.finish().unwrap().len());
+    /// ```
+    pub fn elevations(&mut self, elevations: &[Degrees]) -> &mut Decoder {
+        self.elevation_table(elevation_table(elevations))
+    }
+
+    pub(crate) fn elevation_table(&mut self, table: ElevationTable) -> &mut Decoder {
+        self.settings.elevations = Some(table);
+        self
+    }
+
+    /// Sets the axes of the decoded points' coordinates.
+    ///
+    /// The axes are applied before the decoder's transform, so the transform is the pose of the
+    /// sensor in these axes.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use velodyne::vlp_16::{Decoder, Packet};
+    /// use velodyne::fixtures::VLP_16_DATA_PACKET;
+    /// use velodyne::vlp_16::Axes;
+    /// let mut decoder = Decoder::new();
+    /// decoder.axes(Axes::ForwardLeftUp);
+    /// decoder.push(Packet::new(&VLP_16_DATA_PACKET).unwrap());
+    /// assert_eq!(384, decoder.finish().unwrap().len());
+    /// ```
+    pub fn axes(&mut self, axes: Axes) -> &mut Decoder {
+        self.settings.axes = axes;
+        self
+    }
+
+    /// Sets which way the decoded points' azimuths increase.
+    ///
+    /// Azimuths are modeled and interpolated as the sensor sends them, and only converted when
+    /// the points are built. Frames are cut on the points' azimuths, so give assemblers the same
+    /// direction with `Assembler::azimuth_direction`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use velodyne::vlp_16::{Decoder, Packet};
+    /// use velodyne::fixtures::VLP_16_DATA_PACKET;
+    /// use velodyne::vlp_16::AzimuthDirection;
+    /// let mut decoder = Decoder::new();
+    /// decoder.azimuth_direction(AzimuthDirection::Counterclockwise);
+    /// decoder.push(Packet::new(&VLP_16_DATA_PACKET).unwrap());
+    /// assert_eq!(360. - 229.7, decoder.finish().unwrap()[0].azimuth.degrees());
+    /// ```
+    pub fn azimuth_direction(&mut self, azimuth_direction: AzimuthDirection) -> &mut Decoder {
+        self.settings.azimuth_direction = azimuth_direction;
+        self
+    }
+
+    /// Stamps every decoded point with a sensor id, e.g. when a capture with several sensors is
+    /// split up by source address and each sensor gets its own decoder.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use velodyne::vlp_16::{Decoder, Packet};
+    /// use velodyne::fixtures::VLP_16_DATA_PACKET;
+    /// let mut decoder = Decoder::new();
+    /// decoder.sensor_id(2);
+    /// decoder.push(Packet::new(&VLP_16_DATA_PACKET).unwrap());
+    /// assert_eq!(Some(2), decoder.finish().unwrap()[0].sensor_id);
+    /// ```
+    pub fn sensor_id(&mut self, sensor_id: u16) -> &mut Decoder {
+        self.sensor_id = Some(sensor_id);
+        self
+    }
+
+    /// Pushes a packet into the decoder, returning the points of the previous data packet.
+    ///
+    /// Position packets are ignored.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use velodyne::vlp_16::{Decoder, Packet};
+    /// use velodyne::fixtures::VLP_16_DATA_PACKET;
+    /// let mut decoder = Decoder::new();
+    /// let packet = Packet::new(&VLP_16_DATA_PACKET).unwrap();
+    /// assert!(decoder.push(packet.clone()).is_none());
+    /// assert_eq!(384, decoder.push(packet).unwrap().len());
+    /// ```
+    pub fn push(&mut self, packet: Packet) -> Option<Vec<Point>> {
+        self.push_at(packet, None)
+    }
+
+    /// Pushes a packet into the decoder along with the time it was captured.
+    ///
+    /// The capture time is only used by the time source.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use velodyne::vlp_16::{Decoder, Packet};
+    /// use velodyne::fixtures::VLP_16_DATA_PACKET;
+    /// use velodyne::io::{Pcap, Read};
+    /// use velodyne::time::CaptureTime;
+    /// let mut pcap = Pcap::open("data/single.pcap").unwrap();
+    /// let packet = Packet::new(pcap.read().unwrap().unwrap()).unwrap();
+    /// let mut decoder = Decoder::new();
+    /// decoder.time_source(CaptureTime::new());
+    /// decoder.push_captured(packet, pcap.timestamp().unwrap());
+    /// ```
+    pub fn push_captured(&mut self,
+                         packet: Packet,
+                         capture_time: DateTime<UTC>)
+                         -> Option<Vec<Point>> {
+        self.push_at(packet, Some(capture_time))
+    }
+
+    fn push_at(&mut self,
+               packet: Packet,
+               capture_time: Option<DateTime<UTC>>)
+               -> Option<Vec<Point>> {
+        let start = Instant::now();
+        let bytes = if packet.is_position() {
+            POSITION_PAYLOAD_LEN
+        } else {
+            DATA_PAYLOAD_LEN
+        };
+        let points = self.decode_at(packet, capture_time);
+        self.metrics.record(start, 1, bytes, points.as_ref());
+        points
+    }
+
+    fn decode_at(&mut self,
+                 packet: Packet,
+                 capture_time: Option<DateTime<UTC>>)
+                 -> Option<Vec<Point>> {
+        if let Some(ref mut time_source) = self.time_source {
+            time_source.observe(&packet, capture_time);
+        }
+        if let Some(ref mut correction) = self.correction {
+            correction.observe(&packet);
+        }
+        if packet.is_position() {
+            self.warnings.extend(packet.checksum_warnings());
+            return None;
+        }
+        self.check_return_mode(&packet);
+        let interpolation = self.interpolation;
+        let settings = self.settings;
+        let first = self.start_pending();
+        let correction = self.correction.as_deref();
+        let warnings = &mut self.warnings;
+        let points = self.pending.take().and_then(|pending| {
+            let max_gap = pending.timing().max_packet_gap_us();
+            let gap = (packet.timestamp().duration() - pending.timestamp().duration())
+                .num_microseconds();
+            match gap {
+                Some(gap) if gap < -1_800_000_000 => {
+                    debug!("packet timestamps rolled over at the top of the hour");
+                    warnings.push(DecodeWarning::HourRollover);
+                }
+                Some(gap) if gap > max_gap || gap <= 0 => {
+                    warn!("{} microsecond gap between data packets, extrapolating azimuths",
+                          gap);
+                    warnings.push(DecodeWarning::AzimuthGap { microseconds: gap });
+                }
+                _ => {}
+            }
+            let mut hint = Hint {
+                local: interpolation == Interpolation::Local,
+                ..Hint::default()
+            };
+            if let Some(gap) = gap.filter(|&gap| gap > 0 && gap <= max_gap) {
+                let azimuth = |packet: &Packet| packet.data_blocks().map(|blocks| blocks[0].azimuth);
+                hint.next_azimuth = azimuth(&packet);
+                if interpolation == Interpolation::Timestamp {
+                    if let (Some(start), Some(end)) = (azimuth(&pending), hint.next_azimuth) {
+                        let turn = (i64::from(end.0) - i64::from(start.0)).rem_euclid(FULL_TURN);
+                        hint.rate = Some((turn, gap * 1000));
+                    }
+                }
+            }
+            pending.points_with(hint, settings, correction, first)
+        });
+        self.pending = Some(packet);
+        points.map(|points| self.postprocess(points))
+    }
+
+    /// Returns the points of the last data packet, with its last azimuths extrapolated.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use velodyne::vlp_16::{Decoder, Packet};
+    /// use velodyne::fixtures::VLP_16_DATA_PACKET;
+    /// let mut decoder = Decoder::new();
+    /// decoder.push(Packet::new(&VLP_16_DATA_PACKET).unwrap());
+    /// assert_eq!(384, decoder.finish().unwrap().len());
+    /// assert!(decoder.finish().is_none());
+    /// ```
+    pub fn finish(&mut self) -> Option<Vec<Point>> {
+        let start = Instant::now();
+        let settings = self.settings;
+        let hint = Hint { local: self.interpolation == Interpolation::Local, ..Hint::default() };
+        let first = self.start_pending();
+        let correction = self.correction.as_deref();
+        let points = self.pending
+            .take()
+            .and_then(|pending| pending.points_with(hint, settings, correction, first))
+            .map(|points| self.postprocess(points));
+        self.metrics.record(start, 0, 0, points.as_ref());
+        points
+    }
+
+    /// Returns what this decoder has decoded so far, and how long it took.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use velodyne::vlp_16::{Decoder, Packet};
+    /// use velodyne::fixtures::VLP_16_DATA_PACKET;
+    /// let mut decoder = Decoder::new();
+    /// decoder.push(Packet::new(&VLP_16_DATA_PACKET).unwrap());
+    /// decoder.finish();
+    /// let metrics = decoder.metrics();
+    /// assert_eq!((1, 384, 1206), (metrics.packets, metrics.points, metrics.bytes));
+    /// ```
+    pub fn metrics(&self) -> DecodeMetrics {
+        self.metrics
+    }
+
+    /// Returns the warnings about the data that have been recorded so far, in order.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use velodyne::vlp_16::{Decoder, Packet};
+    /// use velodyne::fixtures::VLP_16_DATA_PACKET;
+    /// let mut decoder = Decoder::new();
+    /// decoder.push(Packet::new(&VLP_16_DATA_PACKET).unwrap());
+    /// assert!(decoder.warnings().is_empty());
+    /// ```
+    pub fn warnings(&self) -> &[DecodeWarning] {
+        &self.warnings
+    }
+
+    /// Removes and returns the warnings recorded so far, e.g. to report them while decoding.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use velodyne::vlp_16::Decoder;
+    /// use velodyne::warning::DecodeWarning;
+    /// let mut decoder = Decoder::new();
+    /// decoder.warn(DecodeWarning::SkippedPacket);
+    /// assert_eq!(vec![DecodeWarning::SkippedPacket], decoder.take_warnings());
+    /// assert!(decoder.warnings().is_empty());
+    /// ```
+    pub fn take_warnings(&mut self) -> Vec<DecodeWarning> {
+        mem::take(&mut self.warnings)
+    }
+
+    /// Records a warning that was found outside of the decoder, e.g. by the reader that feeds it,
+    /// so that all of a stream's warnings are in one place.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use velodyne::vlp_16::Decoder;
+    /// use velodyne::warning::DecodeWarning;
+    /// let mut decoder = Decoder::new();
+    /// decoder.warn(DecodeWarning::Resync { skipped: 42 });
+    /// assert_eq!(1, decoder.warnings().len());
+    /// ```
+    pub fn warn(&mut self, warning: DecodeWarning) {
+        self.warnings.push(warning);
+    }
+
+    /// Sets a correction that is applied to every return's distance before it becomes a point.
+    ///
+    /// The correction observes every pushed packet, including position packets.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use velodyne::vlp_16::{Decoder, Packet};
+    /// use velodyne::correction::RangeBias;
+    /// use velodyne::fixtures::VLP_16_DATA_PACKET;
+    /// use velodyne::units::Meters;
+    /// let mut decoder = Decoder::new();
+    /// decoder.distance_correction(RangeBias::new(&[Meters(0.01); 16]));
+    /// decoder.push(Packet::new(&VLP_16_DATA_PACKET).unwrap());
+    /// assert_eq!(384, decoder.finish().unwrap().len());
+    /// ```
+    pub fn distance_correction<C>(&mut self, correction: C) -> &mut Decoder
+        where C: DistanceCorrection + 'static
+    {
+        self.correction = Some(Box::new(correction));
+        self
+    }
+
+    /// Adds a filter that is applied to every packet's points.
+    ///
+    /// Filters are applied in the order they were added, after points are stamped by the time
+    /// source.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use velodyne::vlp_16::{Decoder, Packet};
+    /// use velodyne::cloud::Bounds;
+    /// use velodyne::filter::CropBox;
+    /// use velodyne::fixtures::VLP_16_DATA_PACKET;
+    /// let mut decoder = Decoder::new();
+    /// decoder.filter(CropBox::new(Bounds::new([-10., -10., -1.], [10., 10., 1.])));
+    /// decoder.push(Packet::new(&VLP_16_DATA_PACKET).unwrap());
+    /// assert!(decoder.finish().unwrap().len() < 384);
+    /// ```
+    pub fn filter<F: Filter + 'static>(&mut self, filter: F) -> &mut Decoder {
+        self.filters.push(Box::new(filter));
+        self
+    }
+
+    /// Sets the sensor's pose, which transforms every point out of the sensor's frame.
+    ///
+    /// Use this for sensors that are mounted tilted or upside down. The transform is applied after
+    /// the filters, so filters still work in the sensor's frame. Points without a return are left
+    /// at the origin, so they can still be told apart. With the `nalgebra` feature, this also
+    /// accepts an `Isometry3<f32>`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use velodyne::vlp_16::{Decoder, Packet};
+    /// use velodyne::fixtures::VLP_16_DATA_PACKET;
+    /// use velodyne::transform::Transform;
+    /// let mut decoder = Decoder::new();
+    /// decoder.transform(Transform::from_euler(180., 0., 0., [0., 0., 2.]));
+    /// decoder.push(Packet::new(&VLP_16_DATA_PACKET).unwrap());
+    /// let point = decoder.finish().unwrap()[0];
+    /// assert!(point.z > 2.);
+    /// ```
+    pub fn transform<T: Into<Transform>>(&mut self, transform: T) -> &mut Decoder {
+        self.transform = Some(transform.into());
+        self
+    }
+
+    /// Warns, once, if a data packet's data blocks don't match its return mode, unless the return
+    /// mode is overridden.
+    fn check_return_mode(&mut self, packet: &Packet) {
+        if self.return_mode_mismatch || self.settings.return_mode.is_some() {
+            return;
+        }
+        if let Ok(data) = packet.as_data() {
+            if !data.is_consistent_with(data.return_mode) {
+                warn!("data blocks don't match the {:?} return mode, consider overriding it",
+                      data.return_mode);
+                self.return_mode_mismatch = true;
+            }
+        }
+    }
+
+    /// Returns the number of the pending packet's first firing sequence and counts its sequences.
+    fn start_pending(&mut self) -> usize {
+        let first = self.sequences;
+        if let Some(ref pending) = self.pending {
+            self.sequences = first.wrapping_add(pending.sequences(self.settings.return_mode));
+        }
+        first
+    }
+
+    fn postprocess(&self, mut points: Vec<Point>) -> Vec<Point> {
+        if let Some(ref time_source) = self.time_source {
+            time_source.stamp(&mut points);
+        }
+        if self.sensor_id.is_some() {
+            for point in &mut points {
+                point.sensor_id = self.sensor_id;
+            }
+        }
+        for filter in &self.filters {
+            filter.apply(&mut points);
+        }
+        if let Some(transform) = self.transform {
+            for point in points.iter_mut().filter(|point| point.range() > 0.) {
+                transform.apply(point);
+            }
+        }
+        points
+    }
+}
+
+impl Decimation {
+    fn keeps(self, sequence: usize, channel: usize) -> bool {
+        match self {
+            Decimation::None => true,
+            Decimation::Firings(n) => n <= 1 || sequence.is_multiple_of(n),
+            Decimation::Points(n) => n <= 1 || (sequence + channel).is_multiple_of(n),
+        }
+    }
+}
+
+impl DecodeMetrics {
+    /// Returns the packets decoded per second of decoding, or `None` if no time was spent.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use std::time::Duration;
+    /// use velodyne::vlp_16::DecodeMetrics;
+    /// let metrics = DecodeMetrics {
+    ///     packets: 10,
+    ///     points: 3840,
+    ///     bytes: 12060,
+    ///     elapsed: Duration::from_millis(2),
+    /// };
+    /// assert_eq!(Some(5000.), metrics.packets_per_second());
+    /// ```
+    pub fn packets_per_second(&self) -> Option<f64> {
+        self.per_second(self.packets)
+    }
+
+    /// Returns the points decoded per second of decoding, or `None` if no time was spent.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use velodyne::vlp_16::DecodeMetrics;
+    /// assert_eq!(None, DecodeMetrics::default().points_per_second());
+    /// ```
+    pub fn points_per_second(&self) -> Option<f64> {
+        self.per_second(self.points)
+    }
+
+    /// Returns the payload bytes decoded per second of decoding, or `None` if no time was spent.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use velodyne::vlp_16::DecodeMetrics;
+    /// assert_eq!(None, DecodeMetrics::default().bytes_per_second());
+    /// ```
+    pub fn bytes_per_second(&self) -> Option<f64> {
+        self.per_second(self.bytes)
+    }
+
+    fn per_second(&self, count: usize) -> Option<f64> {
+        let seconds = self.elapsed.as_secs_f64();
+        if seconds > 0. {
+            Some(count as f64 / seconds)
+        } else {
+            None
+        }
+    }
+
+    fn record(&mut self,
+              start: Instant,
+              packets: usize,
+              bytes: usize,
+              points: Option<&Vec<Point>>) {
+        self.packets += packets;
+        self.bytes += bytes;
+        self.points += points.map_or(0, Vec::len);
+        self.elapsed += start.elapsed();
+    }
+}
+
+impl AzimuthSpan {
+    fn new(first: u16, last: u16) -> AzimuthSpan {
+        AzimuthSpan {
+            first: Centidegrees(first),
+            last: Centidegrees(last),
+            coverage: Centidegrees((i32::from(last) - i32::from(first)).rem_euclid(36_000) as u16),
+        }
+    }
+
+    /// Reads the span of a data packet's payload.
+    pub(crate) fn from_payload(payload: &[u8]) -> AzimuthSpan {
+        let first = LittleEndian::read_u16(&payload[wire::azimuth(0).range()]);
+        let last = LittleEndian::read_u16(&payload[wire::azimuth(NUM_DATA_BLOCKS - 1).range()]);
+        AzimuthSpan::new(first, last)
+    }
+}
+
+impl DataBlock {
+    fn from_raw(block: &raw::DataBlock, distance_resolution: Meters) -> DataBlock {
+        let mut data_records: [[DataRecord; NUM_LASERS]; 2] = Default::default();
+        let records = block.data_records.chunks(NUM_LASERS);
+        for (data_set, records) in data_records.iter_mut().zip(records) {
+            for (data_record, record) in data_set.iter_mut().zip(records) {
+                *data_record = DataRecord::from_raw(record, distance_resolution);
+            }
+        }
+        DataBlock {
+            azimuth: Centidegrees(block.azimuth),
+            data_records,
+        }
+    }
+}
+
+impl DataRecord {
+    /// Returns this record's measurement, or `None` if the laser didn't get a return.
+    ///
+    /// The sensor reports "no return" as a distance of zero, which is easy to mistake for a real
+    /// measurement.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use velodyne::vlp_16::DataRecord;
+    /// assert!(DataRecord::default().return_value().is_none());
+    /// use velodyne::units::Meters;
+    /// let data_record = DataRecord {
+    ///     return_distance: Meters(6.524),
+    ///     calibrated_reflectivity: 4,
+    /// };
+    /// assert_eq!(Meters(6.524), data_record.return_value().unwrap().distance);
+    /// ```
+    pub fn return_value(&self) -> Option<ReturnValue> {
+        if self.return_distance == Meters(0.) {
+            None
+        } else {
+            Some(ReturnValue {
+                     distance: self.return_distance,
+                     reflectivity: self.calibrated_reflectivity,
+                 })
+        }
+    }
+
+    /// Averages two firings' records, ignoring a firing without a return.
+    fn average(&self, other: &DataRecord) -> DataRecord {
+        match (self.return_value(), other.return_value()) {
+            (Some(_), Some(_)) => {
+                let reflectivity = u16::from(self.calibrated_reflectivity) +
+                                   u16::from(other.calibrated_reflectivity);
+                DataRecord {
+                    return_distance: Meters((self.return_distance.0 + other.return_distance.0) /
+                                            2.),
+                    calibrated_reflectivity: reflectivity.div_ceil(2) as u8,
+                }
+            }
+            (Some(_), None) => *self,
+            (None, _) => *other,
+        }
+    }
+
+    fn from_raw(record: &raw::DataRecord, distance_resolution: Meters) -> DataRecord {
+        DataRecord {
+            return_distance: distance_resolution * f32::from(record.distance),
+            calibrated_reflectivity: record.reflectivity,
+        }
+    }
+}
+
+impl Firing {
+    /// Returns this firing's two points, the last return followed by the strongest.
+    ///
+    /// The strongest return's point has `ReturnType::Secondary`, since it is the second-strongest
+    /// return when the last return was the strongest.
+    pub fn points(&self) -> [Point; 2] {
+        let point = |data_record: &DataRecord, return_type| {
+            Point::from_spherical(data_record.return_distance,
+                                  self.azimuth,
+                                  self.sensor.spec().elevation(self.channel as usize),
+                                  data_record.calibrated_reflectivity,
+                                  self.channel,
+                                  return_type,
+                                  self.time)
+        };
+        [point(&self.last, ReturnType::Last), point(&self.strongest, ReturnType::Secondary)]
+    }
+}
+
+impl FiringRecord {
+    /// Returns this return's point, at the laser's nominal elevation.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use velodyne::vlp_16::Packet;
+    /// # use velodyne::fixtures::VLP_16_DATA_PACKET;
+    /// let packet = Packet::new(&VLP_16_DATA_PACKET).unwrap();
+    /// let point = packet.firing_records().unwrap()[20].point();
+    /// assert_eq!(1, point.sequence);
+    /// ```
+    pub fn point(&self) -> Point {
+        let mut point = Point::from_spherical(self.distance,
+                                              self.azimuth,
+                                              self.sensor.spec().elevation(self.channel as usize),
+                                              self.reflectivity,
+                                              self.channel,
+                                              self.return_type,
+                                              self.time);
+        point.sequence = self.sequence;
+        point
+    }
+}
+
+impl ReturnMode {
+    /// Returns the number of data blocks per pair of firing sequences, and the types of their
+    /// returns.
+    ///
+    /// Dual-return data blocks come in pairs, the last returns and then the strongest.
+    fn return_types(self) -> (usize, &'static [ReturnType]) {
+        match self {
+            ReturnMode::StrongestReturn | ReturnMode::Unknown(_) => (1, &[ReturnType::Strongest]),
+            ReturnMode::LastReturn => (1, &[ReturnType::Last]),
+            ReturnMode::DualReturn => (2, &[ReturnType::Last, ReturnType::Secondary]),
+        }
+    }
+
+    pub(crate) fn from_u8(n: u8) -> Result<ReturnMode> {
+        match n {
+            0x37 => Ok(ReturnMode::StrongestReturn),
             0x38 => Ok(ReturnMode::LastReturn),
             0x39 => Ok(ReturnMode::DualReturn),
             _ => Err(Error::InvalidReturnMode(n)),
         }
     }
-}
+}
+
+impl Sensor {
+    /// Returns this sensor's published specification.
+    ///
+    /// Unknown sensors are decoded as VLP-16s, so they get its specification.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use velodyne::vlp_16::Sensor;
+    /// assert_eq!(16, Sensor::VLP_16.spec().num_lasers());
+    /// ```
+    pub fn spec(&self) -> &'static SensorSpec {
+        match *self {
+            Sensor::HDL_32E => &spec::HDL_32E,
+            Sensor::VLP_16 | Sensor::Unknown(_) => &spec::VLP_16,
+        }
+    }
 
-impl Sensor {
-    fn from_u8(n: u8) -> Result<Sensor> {
+    pub(crate) fn from_u8(n: u8) -> Result<Sensor> {
         match n {
             0x21 => Ok(Sensor::HDL_32E),
             0x22 => Ok(Sensor::VLP_16),
@@ -378,74 +2154,352 @@ impl Sensor {
     }
 }
 
+impl Axes {
+    /// Converts coordinates in Velodyne's frame to these axes.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use velodyne::vlp_16::Axes;
+    /// assert_eq!([2., -1., 3.], Axes::ForwardLeftUp.convert([1., 2., 3.]));
+    /// ```
+    pub fn convert(self, xyz: [f32; 3]) -> [f32; 3] {
+        let [x, y, z] = xyz;
+        match self {
+            Axes::Velodyne | Axes::EastNorthUp => [x, y, z],
+            Axes::ForwardLeftUp => [y, -x, z],
+        }
+    }
+}
+
+impl AzimuthDirection {
+    /// Converts a clockwise azimuth, as sent by the sensor, to this direction.
+    ///
+    /// Whether the azimuth was measured, interpolated, or extrapolated is kept. Converting twice
+    /// gives back the original azimuth.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use velodyne::point::Azimuth;
+    /// use velodyne::units::Degrees;
+    /// use velodyne::vlp_16::AzimuthDirection;
+    /// let azimuth = AzimuthDirection::Counterclockwise.convert(Azimuth::Measured(Degrees(90.)));
+    /// assert_eq!(270., azimuth.degrees());
+    /// ```
+    pub fn convert(self, azimuth: Azimuth) -> Azimuth {
+        let degrees = Degrees(self.convert_degrees(azimuth.degrees()));
+        match azimuth {
+            Azimuth::Measured(_) => Azimuth::Measured(degrees),
+            Azimuth::Interpolated(_) => Azimuth::Interpolated(degrees),
+            Azimuth::Extrapolated(_) => Azimuth::Extrapolated(degrees),
+        }
+    }
+
+    /// Converts degrees between clockwise and this direction, which works both ways.
+    pub(crate) fn convert_degrees(self, degrees: f32) -> f32 {
+        match self {
+            AzimuthDirection::Clockwise => degrees,
+            AzimuthDirection::Counterclockwise => (360. - degrees).rem_euclid(360.),
+        }
+    }
+}
+
+impl PpsStatus {
+    fn from_u8(n: u8) -> Result<PpsStatus> {
+        match n {
+            0 => Ok(PpsStatus::Absent),
+            1 => Ok(PpsStatus::Synchronizing),
+            2 => Ok(PpsStatus::Locked),
+            3 => Ok(PpsStatus::Error),
+            _ => Err(Error::InvalidPpsStatus(n)),
+        }
+    }
+}
+
 impl<R: VelodyneRead> Packets<R> {
     /// Creates a new packets iterator.
     pub fn new(read: R) -> Packets<R> {
-        Packets { read: read }
+        Packets {
+            read,
+            strict: false,
+            skipped: 0,
+        }
+    }
+
+    /// Sets whether every group of bytes is parsed, so that anything but a Velodyne packet is an
+    /// error, instead of being skipped.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use velodyne::io::{Pcap, Read};
+    /// let mut packets = Pcap::open("data/single.pcap").unwrap().vlp_16_packets();
+    /// packets.strict(true);
+    /// assert!(packets.next().unwrap().is_ok());
+    /// ```
+    pub fn strict(&mut self, strict: bool) -> &mut Packets<R> {
+        self.strict = strict;
+        self
+    }
+
+    /// Returns the number of non-Velodyne packets that have been skipped.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use velodyne::io::{Pcap, Read};
+    /// let mut packets = Pcap::open("data/single.pcap").unwrap().vlp_16_packets();
+    /// assert_eq!(1, packets.by_ref().count());
+    /// assert_eq!(0, packets.skipped());
+    /// ```
+    pub fn skipped(&self) -> usize {
+        self.skipped
     }
 }
 
 impl<R: VelodyneRead> Iterator for Packets<R> {
     type Item = Result<Packet>;
     fn next(&mut self) -> Option<Self::Item> {
-        self.read.read().map(|result| result.and_then(|bytes| Packet::new(bytes)))
+        loop {
+            match self.read.read()? {
+                Ok(bytes) if !self.strict && classify(bytes).is_none() => {
+                    debug!("skipping {} byte non-Velodyne packet", bytes.len());
+                    self.skipped += 1;
+                }
+                result => return Some(result.and_then(Packet::new)),
+            }
+        }
+    }
+}
+
+/// Reads every frame in a pcap file, with the default decoder and assembler.
+///
+/// See `batch::read_pcap_frames` to configure the decoding.
+///
+/// # Examples
+///
+/// ```
+/// use velodyne::vlp_16;
+/// let frames = vlp_16::frames_from_pcap_path("data/single.pcap").unwrap();
+/// assert_eq!(1, frames.len());
+/// ```
+pub fn frames_from_pcap_path<P: AsRef<Path>>(path: P) -> Result<Vec<Frame>> {
+    batch::read_pcap_frames(path, &batch::Config::new())
+}
+
+/// Classifies bytes by their payload size and contents, without parsing them.
+///
+/// The bytes can either be a full Ethernet frame or just the UDP payload. Since packets are
+/// classified by what they contain rather than which port they arrived on, sensors can be
+/// configured to use any ports. Returns `None` if the bytes aren't a VLP-16 packet.
+///
+/// # Examples
+///
+/// ```
+/// use velodyne::fixtures::{VLP_16_DATA_PACKET, VLP_16_POSITION_PACKET};
+/// use velodyne::vlp_16::{self, PacketKind};
+/// assert_eq!(Some(PacketKind::Data), vlp_16::classify(&VLP_16_DATA_PACKET));
+/// assert_eq!(Some(PacketKind::Position), vlp_16::classify(&VLP_16_POSITION_PACKET[42..]));
+/// assert_eq!(None, vlp_16::classify(&[0; 100]));
+/// ```
+pub fn classify(bytes: &[u8]) -> Option<PacketKind> {
+    let payload = payload(bytes);
+    match payload.len() {
+        DATA_PAYLOAD_LEN if LittleEndian::read_u16(payload) == START_IDENTIFIER => {
+            Some(PacketKind::Data)
+        }
+        // The NMEA buffer is empty if there isn't a GNSS receiver.
+        POSITION_PAYLOAD_LEN if payload[POSITION_NMEA_OFFSET] == b'$' ||
+                                payload[POSITION_NMEA_OFFSET] == 0 => Some(PacketKind::Position),
+        _ => None,
+    }
+}
+
+/// Returns the offset of the first data payload in bytes that might be misaligned.
+///
+/// A data payload is found where all twelve data blocks start with `0xEEFF` and the whole payload
+/// fits in the bytes. Returns `None` if there isn't one.
+///
+/// # Examples
+///
+/// ```
+/// use velodyne::fixtures::VLP_16_DATA_PACKET;
+/// use velodyne::vlp_16;
+/// assert_eq!(Some(42), vlp_16::resync(&VLP_16_DATA_PACKET));
+/// assert_eq!(None, vlp_16::resync(&VLP_16_DATA_PACKET[43..]));
+/// ```
+pub fn resync(bytes: &[u8]) -> Option<usize> {
+    let end = bytes.len().checked_sub(DATA_PAYLOAD_LEN)?;
+    (0..=end).find(|&offset| {
+        (0..NUM_DATA_BLOCKS).all(|i| {
+            let flag = &bytes[offset + wire::data_block(i).offset..];
+            LittleEndian::read_u16(flag) == START_IDENTIFIER
+        })
+    })
+}
+
+/// Strips the Ethernet, IP, and UDP headers from a packet, if they are present.
+pub(crate) fn payload(bytes: &[u8]) -> &[u8] {
+    if bytes.len() == DATA_PAYLOAD_LEN || bytes.len() == POSITION_PAYLOAD_LEN {
+        bytes
+    } else {
+        // Packets that are too short for a header fail to parse as data packets.
+        bytes.get(PACKET_HEADER_LEN..).unwrap_or(&[])
     }
 }
 
-fn vertical_angle(channel: usize) -> f32 {
-    assert!(channel < 16);
-    if channel % 2 == 1 {
-        channel as f32
+/// Returns the azimuth halfway from `first` to `second`, in degrees, going around through 360°
+/// if need be. Unless `exact`, the mean is rounded to centidegrees, like any decoded azimuth.
+fn mean_azimuth(first: f64, second: f64, exact: bool) -> f64 {
+    let second = if second < first { second + 360. } else { second };
+    let mean = ((first + second) / 2.) % 360.;
+    if exact {
+        mean
     } else {
-        -15. + channel as f32
+        (mean * 100.).round() / 100. % 360.
     }
 }
 
-fn time_offset(data_block: usize, sequence: usize, channel: usize) -> Duration {
-    let nanoseconds = ((data_block * 2 + sequence) as f32 * FIRING_CYCLE_RATE_US +
-                       channel as f32 * FIRING_RATE_US) * 1000.;
-    Duration::nanoseconds(nanoseconds.round() as i64)
+/// Returns a channel's nominal elevation on the VLP-16, for code that only handles its sixteen
+/// lasers.
+pub(crate) fn vertical_angle(channel: usize) -> f32 {
+    spec::VLP_16.elevation(channel).0
+}
+
+/// Fills an elevation table, leaving the channels without an entry at the sensor's nominal
+/// elevations.
+pub(crate) fn elevation_table(elevations: &[Degrees]) -> ElevationTable {
+    let mut table = [None; MAX_LASERS];
+    for (entry, &elevation) in table.iter_mut().zip(elevations) {
+        *entry = Some(elevation);
+    }
+    table
 }
 
 struct AzimuthModel {
     data_blocks: [DataBlock; NUM_DATA_BLOCKS],
+    stride: usize,
+    timing: &'static Timing,
+    hint: Hint,
 }
 
 impl AzimuthModel {
-    fn new(data_blocks: [DataBlock; NUM_DATA_BLOCKS]) -> AzimuthModel {
-        AzimuthModel { data_blocks: data_blocks }
+    /// Creates a new model, where `stride` is the number of data blocks per set of firings, i.e.
+    /// one for single-return packets and two for dual-return packets, and `timing` is the
+    /// sensor's.
+    ///
+    /// If the first azimuth of the next packet is known, it is used to interpolate the last data
+    /// block instead of extrapolating from the previous one. If the rotation rate is known, it is
+    /// used for every data block. Otherwise, if the hint asks for local rates, the rate at each
+    /// data block is estimated from its neighbors.
+    fn new(data_blocks: [DataBlock; NUM_DATA_BLOCKS],
+           stride: usize,
+           timing: &'static Timing,
+           hint: Hint)
+           -> AzimuthModel {
+        AzimuthModel {
+            data_blocks,
+            stride,
+            timing,
+            hint,
+        }
     }
 
-    fn predict(&self, data_block: usize, sequence: usize, channel: usize) -> f32 {
-        let mut base_azimuth = self.data_blocks[data_block].azimuth;
-        let rate = if data_block < NUM_DATA_BLOCKS - 1 {
-            let mut other_azimuth = self.data_blocks[data_block + 1].azimuth;
-            if other_azimuth < base_azimuth {
-                other_azimuth += 360.
-            }
-            (other_azimuth - base_azimuth) / FIRING_CYCLE_RATE_US / 2.
+    fn azimuth(&self, data_block: usize, sequence: usize, channel: usize) -> Azimuth {
+        let azimuth = self.predict(data_block, sequence, channel).degrees();
+        self.kind(data_block, sequence, channel, azimuth)
+    }
+
+    /// Returns an azimuth without rounding it to centidegrees, along with its value in `f64`.
+    fn azimuth_f64(&self, data_block: usize, sequence: usize, channel: usize) -> (Azimuth, f64) {
+        let (centidegrees, duration) = self.predict_exact(data_block, sequence, channel);
+        let degrees = centidegrees as f64 / (100 * duration) as f64;
+        // Just short of a full turn, rounding to an `f32` can give exactly 360°.
+        let rounded = degrees as f32 % 360.;
+        (self.kind(data_block, sequence, channel, rounded), degrees)
+    }
+
+    fn kind(&self, data_block: usize, sequence: usize, channel: usize, azimuth: f32) -> Azimuth {
+        if sequence == 0 && channel == 0 {
+            Azimuth::Measured(Degrees(azimuth))
+        } else if data_block < NUM_DATA_BLOCKS - self.stride || self.hint.next_azimuth.is_some() {
+            Azimuth::Interpolated(Degrees(azimuth))
         } else {
-            let other_azimuth = self.data_blocks[data_block - 1].azimuth;
-            if other_azimuth > base_azimuth {
-                base_azimuth += 360.;
-            }
-            (base_azimuth - other_azimuth) / FIRING_CYCLE_RATE_US / 2.
-        };
-        let azimuth = ((base_azimuth + rate * sequence as f32 * FIRING_CYCLE_RATE_US +
-                        rate * channel as f32 * FIRING_RATE_US) * 100.)
-                .round() / 100.;
-        if azimuth > 360. {
-            azimuth - 360.
+            Azimuth::Extrapolated(Degrees(azimuth))
+        }
+    }
+
+    /// Predicts an azimuth with integer math, so the result is the same on every platform.
+    fn predict(&self, data_block: usize, sequence: usize, channel: usize) -> Centidegrees {
+        let (centidegrees, duration) = self.predict_exact(data_block, sequence, channel);
+        let rounded = (centidegrees + duration / 2) / duration;
+        Centidegrees(rounded.rem_euclid(FULL_TURN) as u16)
+    }
+
+    /// Predicts an azimuth as an exact fraction, in centidegrees times a duration, and that
+    /// duration. The fraction is in `[0, 36_000)`.
+    fn predict_exact(&self, data_block: usize, sequence: usize, channel: usize) -> (i64, i64) {
+        let azimuth = |data_block: usize| i64::from(self.data_blocks[data_block].azimuth.0);
+        let base = azimuth(data_block);
+        let next = if data_block < NUM_DATA_BLOCKS - self.stride {
+            Some(azimuth(data_block + self.stride))
         } else {
-            azimuth
+            self.hint.next_azimuth.map(|azimuth| i64::from(azimuth.0))
+        };
+        let elapsed = self.timing.sequence_offset_ns(sequence, channel);
+        if let (Some(next), true, None) = (next, self.hint.local, self.hint.rate) {
+            return self.predict_local(data_block, base, next, elapsed);
         }
+        let (turn, duration) = if let Some(rate) = self.hint.rate {
+            rate
+        } else if let Some(next) = next {
+            ((next - base).rem_euclid(FULL_TURN), self.timing.data_block_duration_ns())
+        } else {
+            let previous = azimuth(data_block - self.stride);
+            ((base - previous).rem_euclid(FULL_TURN), self.timing.data_block_duration_ns())
+        };
+        ((base * duration + turn * elapsed).rem_euclid(FULL_TURN * duration), duration)
+    }
+
+    /// Predicts an azimuth on a cubic Hermite curve between a data block and the next one, whose
+    /// slopes at either end are the average rates of the intervals on both sides.
+    ///
+    /// The result is in units of a duration, like `predict_exact`'s, which is the time between the
+    /// data blocks. With the same rate on every interval, this is the straight line between them.
+    fn predict_local(&self, data_block: usize, base: i64, next: i64, elapsed: i64) -> (i64, i64) {
+        let stride = self.stride;
+        // The azimuth of a data block, where the one past the last is the next packet's first.
+        let azimuth = |data_block: usize| match data_block {
+            i if i < NUM_DATA_BLOCKS => Some(i64::from(self.data_blocks[i].azimuth.0)),
+            NUM_DATA_BLOCKS => self.hint.next_azimuth.map(|azimuth| i64::from(azimuth.0)),
+            _ => None,
+        };
+        let turn = |from: i64, to: i64| i128::from((to - from).rem_euclid(FULL_TURN));
+        let turn_next = turn(base, next);
+        let turn_previous = data_block.checked_sub(stride)
+            .and_then(azimuth)
+            .map_or(turn_next, |previous| turn(previous, base));
+        let turn_after = azimuth(data_block + 2 * stride)
+            .map_or(turn_next, |after| turn(next, after));
+        let duration = self.timing.data_block_duration_ns();
+        let (d, e) = (i128::from(duration), i128::from(elapsed));
+        // The Hermite basis functions times d³, with the slopes at either end doubled to keep the
+        // averages whole, so everything is over 2d³.
+        let numerator = (e * e * e - 2 * e * e * d + e * d * d) * (turn_previous + turn_next) +
+                        (-2 * e * e * e + 3 * e * e * d) * 2 * turn_next +
+                        (e * e * e - e * e * d) * (turn_next + turn_after);
+        let delta = (numerator + d * d).div_euclid(2 * d * d) as i64;
+        ((base * duration + delta).rem_euclid(FULL_TURN * duration), duration)
     }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use chrono::{Duration, TimeZone};
     use fixtures::{VLP_16_DATA_PACKET, VLP_16_POSITION_PACKET};
+    use time::GpsTime;
 
     #[test]
     fn data_packet() {
@@ -459,30 +2513,92 @@ mod tests {
         assert!(packet.is_position());
     }
 
+    #[test]
+    fn payload_only() {
+        let packet = Packet::new(&VLP_16_DATA_PACKET[PACKET_HEADER_LEN..]).unwrap();
+        assert_eq!(2_467_108_343, packet.timestamp().micros());
+        let packet = Packet::new(&VLP_16_POSITION_PACKET[PACKET_HEADER_LEN..]).unwrap();
+        assert!(packet.is_position());
+    }
+
     #[test]
     fn azimuth() {
         let data_blocks = Packet::new(&VLP_16_DATA_PACKET).unwrap().data_blocks().unwrap();
-        assert_eq!(229.70, data_blocks[0].azimuth);
-        assert_eq!(234.08, data_blocks[11].azimuth);
+        assert_eq!(Centidegrees(22_970), data_blocks[0].azimuth);
+        assert_eq!(Centidegrees(23_408), data_blocks[11].azimuth);
     }
 
     #[test]
     fn data_record() {
         let data_blocks = Packet::new(&VLP_16_DATA_PACKET).unwrap().data_blocks().unwrap();
         let data_record = data_blocks[0].data_records[0][0];
-        assert_eq!(6.524, data_record.return_distance);
+        assert_eq!(Meters(6.524), data_record.return_distance);
         assert_eq!(4, data_record.calibrated_reflectivity);
         let data_record = data_blocks[11].data_records[1][12];
-        assert_eq!(51.470, data_record.return_distance);
+        assert_eq!(Meters(51.470), data_record.return_distance);
         assert_eq!(9, data_record.calibrated_reflectivity);
+        let no_returns = data_blocks
+            .iter()
+            .flat_map(|data_block| data_block.data_records.iter().flat_map(|set| set.iter()))
+            .filter(|data_record| data_record.return_value().is_none())
+            .count();
+        assert!(no_returns > 0);
+    }
+
+    #[test]
+    fn packet_equality() {
+        let packet = Packet::new(&VLP_16_DATA_PACKET).unwrap();
+        assert_eq!(packet, Packet::new(&VLP_16_DATA_PACKET).unwrap());
+        assert_ne!(packet, Packet::new(&VLP_16_POSITION_PACKET).unwrap());
+        let mut bytes = VLP_16_DATA_PACKET;
+        bytes[PACKET_HEADER_LEN + 4] += 1;
+        let other = Packet::new(&bytes).unwrap();
+        assert_ne!(packet, other);
+        assert_eq!(packet.data_blocks().unwrap()[1..], other.data_blocks().unwrap()[1..]);
+    }
+
+    #[test]
+    fn typed_packets() {
+        let packet = Packet::new(&VLP_16_POSITION_PACKET).unwrap();
+        match packet.as_data() {
+            Err(Error::NotADataPacket) => {}
+            result => panic!("unexpected result: {:?}", result),
+        }
+        let position_packet = packet.into_position().unwrap();
+        assert_eq!(PpsStatus::Locked, position_packet.status.pps);
+        let packet = Packet::new(&VLP_16_DATA_PACKET).unwrap();
+        match packet.clone().into_position() {
+            Err(Error::NotAPositionPacket) => {}
+            result => panic!("unexpected result: {:?}", result),
+        }
+        assert_eq!(Sensor::VLP_16, packet.as_data().unwrap().sensor);
+    }
+
+    #[test]
+    fn from_pcap_path() {
+        let packets = Packet::from_pcap_path("data/position.pcap").unwrap();
+        assert!(packets.iter().all(Packet::is_position));
+        let frames = frames_from_pcap_path("data/single.pcap").unwrap();
+        assert_eq!(384, frames[0].points.len());
+        assert!(Packet::from_pcap_path("data/missing.pcap").is_err());
+    }
+
+    #[test]
+    fn distance_resolution() {
+        let record = raw::DataRecord {
+            distance: 1000,
+            reflectivity: 4,
+        };
+        let data_record = DataRecord::from_raw(&record, Meters(0.004));
+        assert_eq!(Meters(4.), data_record.return_distance);
     }
 
     #[test]
     fn timestamp() {
         let packet = Packet::new(&VLP_16_DATA_PACKET).unwrap();
-        assert_eq!(Duration::microseconds(2_467_108_343), packet.timestamp());
+        assert_eq!(2_467_108_343, packet.timestamp().micros());
         let packet = Packet::new(&VLP_16_POSITION_PACKET).unwrap();
-        assert_eq!(Duration::microseconds(2_467_110_195), packet.timestamp());
+        assert_eq!(2_467_110_195, packet.timestamp().micros());
     }
 
     #[test]
@@ -495,13 +2611,579 @@ mod tests {
     #[test]
     fn azimuth_model() {
         let packet = Packet::new(&VLP_16_DATA_PACKET).unwrap();
-        let azimuth_model = AzimuthModel::new(packet.data_blocks().unwrap());
-        assert_eq!(229.70, azimuth_model.predict(0, 0, 0));
-        assert_eq!(229.71, azimuth_model.predict(0, 0, 1));
-        assert_eq!(229.89, azimuth_model.predict(0, 1, 0));
-        assert_eq!(234.00, azimuth_model.predict(10, 1, 15));
-        assert_eq!(234.08, azimuth_model.predict(11, 0, 0));
-        assert_eq!(234.09, azimuth_model.predict(11, 0, 1));
+        let azimuth_model = AzimuthModel::new(packet.data_blocks().unwrap(),
+                                              1,
+                                              &timing::VLP_16,
+                                              Hint::default());
+        assert_eq!(Centidegrees(22_970), azimuth_model.predict(0, 0, 0));
+        assert_eq!(Centidegrees(22_971), azimuth_model.predict(0, 0, 1));
+        // Exactly halfway between 229.89 and 229.90, which floats used to round either way.
+        assert_eq!(Centidegrees(22_990), azimuth_model.predict(0, 1, 0));
+        assert_eq!(Centidegrees(23_009), packet.data_blocks().unwrap()[1].azimuth);
+        assert_eq!(Centidegrees(23_400), azimuth_model.predict(10, 1, 15));
+        assert_eq!(Centidegrees(23_408), azimuth_model.predict(11, 0, 0));
+        assert_eq!(Centidegrees(23_409), azimuth_model.predict(11, 0, 1));
+    }
+
+    #[test]
+    fn local_azimuth_model() {
+        // A rotation that speeds up steadily, in centidegrees after t intervals between blocks.
+        let truth = |t: f64| 35_000. + 38. * t + 20. * t * t;
+        let mut data_blocks = [DataBlock::default(); NUM_DATA_BLOCKS];
+        for (i, data_block) in data_blocks.iter_mut().enumerate() {
+            data_block.azimuth = Centidegrees((truth(i as f64) as i64 % FULL_TURN) as u16);
+        }
+        let linear = AzimuthModel::new(data_blocks, 1, &timing::VLP_16, Hint::default());
+        let local = AzimuthModel::new(data_blocks,
+                                      1,
+                                      &timing::VLP_16,
+                                      Hint { local: true, ..Hint::default() });
+        let error = |model: &AzimuthModel, data_block: usize, channel: usize| {
+            let (centidegrees, duration) = model.predict_exact(data_block, 1, channel);
+            let elapsed = timing::VLP_16.sequence_offset_ns(1, channel) as f64 /
+                          (2 * timing::VLP_16.firing_cycle_ns) as f64;
+            let expected = truth(data_block as f64 + elapsed) % FULL_TURN as f64;
+            (centidegrees as f64 / duration as f64 - expected).abs()
+        };
+        for data_block in 1..NUM_DATA_BLOCKS - 2 {
+            for channel in 0..NUM_LASERS {
+                assert!(error(&local, data_block, channel) < 0.01);
+            }
+            assert!(error(&linear, data_block, 0) > 4.);
+        }
+    }
+
+    #[test]
+    fn azimuth_model_wraps() {
+        let firing_cycles = |sequence, channel| {
+            timing::VLP_16.sequence_offset_ns(sequence, channel) as f64 /
+            (2 * timing::VLP_16.firing_cycle_ns) as f64
+        };
+        // Starts just before and at the seam, including the 360.00° that some firmware sends, and
+        // steps from a slow rotation up to gaps of half a turn.
+        for start in (35_800..36_001).step_by(7).chain(Some(36_000)) {
+            for &step in &[1, 20, 39, 40, 41, 600, 17_999] {
+                let mut data_blocks = [DataBlock::default(); NUM_DATA_BLOCKS];
+                for (i, data_block) in data_blocks.iter_mut().enumerate() {
+                    data_block.azimuth = Centidegrees(((start + i * step) % 36_000) as u16);
+                }
+                data_blocks[0].azimuth = Centidegrees(start as u16);
+                let next = Centidegrees(((start + NUM_DATA_BLOCKS * step) % 36_000) as u16);
+                for &stride in &[1, 2] {
+                    let rate = ((step * stride) as i64, 2 * timing::VLP_16.firing_cycle_ns);
+                    let hints = [Hint::default(),
+                                 Hint { next_azimuth: Some(next), ..Hint::default() },
+                                 Hint { rate: Some(rate), ..Hint::default() },
+                                 Hint { local: true, ..Hint::default() },
+                                 Hint { next_azimuth: Some(next), local: true, ..Hint::default() }];
+                    for &hint in &hints {
+                        let model = AzimuthModel::new(data_blocks, stride, &timing::VLP_16, hint);
+                        for data_block in (0..NUM_DATA_BLOCKS).step_by(stride) {
+                            let base = (start + data_block * step) as f64 / 100.;
+                            for sequence in 0..2 {
+                                for channel in 0..NUM_LASERS {
+                                    let rate = step as f64 * stride as f64 / 100.;
+                                    let expected = (base + rate * firing_cycles(sequence, channel))
+                                        .rem_euclid(360.);
+                                    let azimuth = model.azimuth(data_block, sequence, channel);
+                                    let (precise, degrees) =
+                                        model.azimuth_f64(data_block, sequence, channel);
+                                    for &actual in &[azimuth.degrees(), precise.degrees()] {
+                                        assert!((0. ..360.).contains(&actual), "{}", actual);
+                                        let error = (f64::from(actual) - expected)
+                                            .rem_euclid(360.);
+                                        assert!(error.min(360. - error) < 0.0051,
+                                                "{} {} {} {}",
+                                                start,
+                                                step,
+                                                actual,
+                                                expected);
+                                    }
+                                    assert!((0. ..360.).contains(&degrees));
+                                }
+                            }
+                        }
+                    }
+                }
+            }
+        }
+        // A rate that puts the second laser a few millionths of a degree short of 360°.
+        let mut data_blocks = [DataBlock::default(); NUM_DATA_BLOCKS];
+        data_blocks[0].azimuth = Centidegrees(35_999);
+        let hint = Hint { rate: Some((1, 2_305)), ..Hint::default() };
+        let model = AzimuthModel::new(data_blocks, 1, &timing::VLP_16, hint);
+        let (azimuth, degrees) = model.azimuth_f64(0, 0, 1);
+        assert!(degrees < 360. && degrees as f32 == 360.);
+        assert_eq!(0., azimuth.degrees());
+    }
+
+    /// Returns a copy of the fixture packet that starts at 234.5 degrees, `gap` microseconds after
+    /// the fixture.
+    fn next_packet(gap: i64) -> Packet {
+        let mut bytes = VLP_16_DATA_PACKET.to_vec();
+        for data_block in 0..NUM_DATA_BLOCKS {
+            let offset = PACKET_HEADER_LEN + wire::azimuth(data_block).offset;
+            let azimuth = 23_450 + data_block as u16 * 40;
+            bytes[offset] = azimuth as u8;
+            bytes[offset + 1] = (azimuth >> 8) as u8;
+        }
+        let timestamp = (2_467_108_343 + gap) as u32;
+        for i in 0..4 {
+            let offset = PACKET_HEADER_LEN + wire::DATA_TIMESTAMP.offset + i;
+            bytes[offset] = (timestamp >> (8 * i)) as u8;
+        }
+        Packet::new(&bytes).unwrap()
+    }
+
+    #[test]
+    fn decoder_interpolates_across_packets() {
+        let packet = Packet::new(&VLP_16_DATA_PACKET).unwrap();
+        let next = next_packet(1327);
+        let mut decoder = Decoder::new();
+        assert!(decoder.push(packet.clone()).is_none());
+        let points = decoder.push(next).unwrap();
+        let last = points[points.len() - 1];
+        match last.azimuth {
+            Azimuth::Interpolated(azimuth) => assert_eq!(Degrees(234.42), azimuth),
+            azimuth => panic!("unexpected azimuth: {:?}", azimuth),
+        }
+        let extrapolated = packet.points().unwrap();
+        match extrapolated[extrapolated.len() - 1].azimuth {
+            Azimuth::Extrapolated(azimuth) => assert_eq!(Degrees(234.41), azimuth),
+            azimuth => panic!("unexpected azimuth: {:?}", azimuth),
+        }
+    }
+
+    #[test]
+    fn timestamp_interpolation() {
+        let mut decoder = Decoder::new();
+        decoder.interpolation(Interpolation::Timestamp);
+        decoder.push(Packet::new(&VLP_16_DATA_PACKET).unwrap());
+        let points = decoder.push(next_packet(2000)).unwrap();
+        assert_eq!(229.70, points[0].azimuth.degrees());
+        assert_eq!(229.83, points[NUM_LASERS].azimuth.degrees());
+        let points = decoder.finish().unwrap();
+        assert_eq!(234.5, points[0].azimuth.degrees());
+    }
+
+    #[test]
+    fn malformed_packets() {
+        for fixture in &[&VLP_16_DATA_PACKET[..], &VLP_16_POSITION_PACKET[..]] {
+            for len in 0..fixture.len() {
+                let _ = Packet::new(&fixture[..len]);
+                let _ = Packet::new(&fixture[PACKET_HEADER_LEN.min(len)..len]);
+            }
+            for i in 0..fixture.len() {
+                for &byte in &[0, b'$', b'*', 0xff] {
+                    let mut bytes = fixture.to_vec();
+                    bytes[i] = byte;
+                    if let Ok(packet) = Packet::new(&bytes) {
+                        let _ = packet.points();
+                        let _ = packet.firings();
+                        let _ = packet.sentences();
+                    }
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn decoder_decimation() {
+        let packet = Packet::new(&VLP_16_DATA_PACKET).unwrap();
+        let all = packet.points().unwrap();
+        let mut decoder = Decoder::new();
+        decoder.decimation(Decimation::Firings(5));
+        decoder.push(packet.clone());
+        let first = decoder.push(packet.clone()).unwrap();
+        assert_eq!(80, first.len());
+        assert_eq!(all[0].x, first[0].x);
+        // The second packet starts at sequence 24, so its first kept sequence is its second.
+        let second = decoder.finish().unwrap();
+        assert_eq!(80, second.len());
+        assert_eq!(all[NUM_LASERS].x, second[0].x);
+
+        decoder.decimation(Decimation::Points(3));
+        decoder.push(packet);
+        let points = decoder.finish().unwrap();
+        assert_eq!(128, points.len());
+        assert_eq!(all[3].x, points[1].x);
+    }
+
+    #[test]
+    fn decoder_channels() {
+        let packet = Packet::new(&VLP_16_DATA_PACKET).unwrap();
+        let all = packet.points().unwrap();
+        let mut decoder = Decoder::new();
+        decoder.channels(&[1, 3, 40]).decimation(Decimation::Firings(2));
+        decoder.push(packet);
+        let points = decoder.finish().unwrap();
+        assert_eq!(24, points.len());
+        assert!(points.iter().all(|point| point.channel == 1 || point.channel == 3));
+        assert_eq!(all[1].x, points[0].x);
+        assert_eq!(all[2 * NUM_LASERS + 3].x, points[3].x);
+    }
+
+    #[test]
+    fn point_count() {
+        use fixtures::VLP_16_DUAL_RETURN_PACKET;
+        for bytes in &[&VLP_16_DATA_PACKET[..], &VLP_16_DUAL_RETURN_PACKET[..]] {
+            let packet = Packet::new(bytes).unwrap();
+            let points = packet.points().unwrap();
+            let data_packet = packet.into_data().unwrap();
+            assert_eq!(points.len(), data_packet.point_count(false));
+            let returns = points.iter().filter(|point| point.range() > 0.).count();
+            assert_eq!(returns, data_packet.point_count(true));
+        }
+    }
+
+    #[test]
+    fn azimuth_span() {
+        let mut packet = Packet::new(&VLP_16_DATA_PACKET).unwrap().into_data().unwrap();
+        packet.data_blocks[0].azimuth = Centidegrees(35_900);
+        packet.data_blocks[NUM_DATA_BLOCKS - 1].azimuth = Centidegrees(100);
+        assert_eq!(Centidegrees(200), packet.azimuth_span().coverage);
+        let payload = &VLP_16_DATA_PACKET[PACKET_HEADER_LEN..];
+        let packet = Packet::new(&VLP_16_DATA_PACKET).unwrap().into_data().unwrap();
+        assert_eq!(packet.azimuth_span(), AzimuthSpan::from_payload(payload));
+    }
+
+    #[test]
+    fn lenient_dual_return() {
+        use fixtures::VLP_16_DUAL_RETURN_PACKET;
+        let mut bytes = VLP_16_DUAL_RETURN_PACKET;
+        bytes[PACKET_HEADER_LEN + wire::RETURN_MODE.offset] = 0x3a;
+        assert!(Packet::new(&bytes).is_err());
+        let packet = Packet::new_lenient(&bytes).unwrap();
+        assert_eq!(Some(ReturnMode::Unknown(0x3a)), packet.return_mode());
+        assert!(packet.as_data().unwrap().is_consistent_with(ReturnMode::Unknown(0x3a)));
+        let expected = Packet::new(&VLP_16_DUAL_RETURN_PACKET).unwrap().points().unwrap();
+        let points = packet.points().unwrap();
+        assert_eq!(expected.len(), points.len());
+        for (expected, point) in expected.iter().zip(&points) {
+            assert_eq!(expected.x, point.x);
+            assert_eq!(expected.return_type as u8, point.return_type as u8);
+        }
+    }
+
+    #[test]
+    fn decoder_return_mode() {
+        use fixtures::VLP_16_DUAL_RETURN_PACKET;
+        let dual = Packet::new(&VLP_16_DUAL_RETURN_PACKET).unwrap();
+        let mut bytes = VLP_16_DUAL_RETURN_PACKET;
+        bytes[PACKET_HEADER_LEN + wire::RETURN_MODE.offset] = 0x37;
+        let mislabeled = Packet::new(&bytes).unwrap();
+        assert!(!mislabeled.as_data().unwrap().is_consistent_with(ReturnMode::StrongestReturn));
+        assert!(mislabeled.as_data().unwrap().is_consistent_with(ReturnMode::DualReturn));
+
+        let mut decoder = Decoder::new();
+        decoder.push(mislabeled.clone());
+        assert!(decoder.return_mode_mismatch());
+        let mut decoder = Decoder::new();
+        decoder.return_mode(ReturnMode::DualReturn).push(mislabeled);
+        assert!(!decoder.return_mode_mismatch());
+        let points = decoder.finish().unwrap();
+        for (expected, point) in dual.points().unwrap().iter().zip(&points) {
+            assert_eq!(expected.x, point.x);
+            assert_eq!(expected.reflectivity, point.reflectivity);
+        }
+        let secondary = points.iter()
+            .filter(|point| matches!(point.return_type, ReturnType::Secondary))
+            .count();
+        assert_eq!(points.len() / 2, secondary);
+    }
+
+    #[test]
+    fn decoder_deterministic() {
+        let mut bytes = VLP_16_DATA_PACKET;
+        let decode = |bytes: &[u8], deterministic| {
+            let mut decoder = Decoder::new();
+            decoder.deterministic(deterministic);
+            decoder.push(Packet::new(bytes).unwrap());
+            decoder.finish().unwrap()
+        };
+        for &return_mode in &[0x37, 0x39] {
+            bytes[PACKET_HEADER_LEN + wire::RETURN_MODE.offset] = return_mode;
+            let points = decode(&bytes, false);
+            let deterministic = decode(&bytes, true);
+            assert_eq!(points.len(), deterministic.len());
+            for (point, deterministic) in points.iter().zip(&deterministic) {
+                assert!((point.x - deterministic.x).abs() < 1e-4);
+                assert_eq!(point.channel, deterministic.channel);
+            }
+        }
+    }
+
+    #[test]
+    fn decoder_double_precision() {
+        let packet = Packet::new(&VLP_16_DATA_PACKET).unwrap();
+        let single = packet.points().unwrap();
+        let mut decoder = Decoder::new();
+        decoder.precision(Precision::Double);
+        decoder.push(packet);
+        let double = decoder.finish().unwrap();
+        assert_eq!(single.len(), double.len());
+        assert_eq!(single[0].azimuth.degrees(), double[0].azimuth.degrees());
+        for (single, double) in single.iter().zip(&double) {
+            let error = single.azimuth.degrees() - double.azimuth.degrees();
+            assert!(error.abs() <= 0.005 + 1e-4, "{}", error);
+            assert!((single.x - double.x).abs() < 0.01);
+        }
+        // Channel 1 fires 2.304 microseconds in, which isn't a whole number of centidegrees.
+        let centidegrees = double[1].azimuth.degrees() * 100.;
+        assert!((centidegrees - centidegrees.round()).abs() > 0.01);
+    }
+
+    #[test]
+    fn decoder_azimuth_offsets() {
+        let packet = Packet::new(&VLP_16_DATA_PACKET).unwrap();
+        let uncorrected = packet.points().unwrap();
+        let mut decoder = Decoder::new();
+        decoder.azimuth_offsets(&[Degrees(90.)]);
+        decoder.push(packet);
+        let corrected = decoder.finish().unwrap();
+        for (before, after) in uncorrected.iter().zip(&corrected).filter(|(p, _)| p.range() > 0.) {
+            assert_eq!(before.azimuth.degrees(), after.azimuth.degrees());
+            let turn = (after.x.atan2(after.y) - before.x.atan2(before.y)).to_degrees();
+            let expected = if before.channel == 0 { 90. } else { 0. };
+            assert!(((turn - expected).rem_euclid(360.) + 1e-2) % 360. < 2e-2, "{}", turn);
+        }
+    }
+
+    #[test]
+    fn decoder_elevations() {
+        let packet = Packet::new(&VLP_16_DATA_PACKET).unwrap();
+        let nominal = packet.points().unwrap();
+        let mut decoder = Decoder::new();
+        decoder.elevations(&[Degrees(0.)]);
+        decoder.push(packet);
+        let calibrated = decoder.finish().unwrap();
+        for (before, after) in nominal.iter().zip(&calibrated).filter(|(p, _)| p.range() > 0.) {
+            if before.channel == 0 {
+                assert_eq!(0., after.z);
+                assert!((before.range() - after.range()).abs() < 1e-3);
+            } else {
+                assert_eq!(before.z, after.z);
+            }
+        }
+    }
+
+    #[test]
+    fn sensor_timing() {
+        use fixtures::RELABELLED_HDL_32E_DATA_PACKET;
+        let packet = Packet::new(&RELABELLED_HDL_32E_DATA_PACKET).unwrap();
+        let timestamp = Time::Offset(packet.timestamp().duration());
+        let points = packet.points().unwrap();
+        assert_eq!(NUM_DATA_BLOCKS * 32, points.len());
+        for (i, point) in points.iter().enumerate() {
+            let (data_block, channel) = (i / 32, i % 32);
+            assert_eq!(channel, point.channel as usize);
+            assert_eq!(0, point.sequence);
+            let offset = point.time.duration_since(timestamp).unwrap();
+            assert_eq!(Some(data_block as i64 * 46_080 + channel as i64 * 1_152),
+                       offset.num_nanoseconds());
+        }
+        let firing_records = packet.firing_records().unwrap();
+        for (point, firing_record) in points.iter().zip(&firing_records) {
+            assert_eq!(point.channel, firing_record.channel);
+            assert_eq!(point.time.duration_since(firing_record.time), Some(Duration::zero()));
+        }
+        let data_block = packet.data_blocks().unwrap()[0];
+        assert_eq!(data_block.data_records[1][0].return_distance, firing_records[16].distance);
+    }
+
+    #[test]
+    fn sensor_elevations() {
+        use fixtures::RELABELLED_HDL_32E_DATA_PACKET;
+        let packet = Packet::new(&RELABELLED_HDL_32E_DATA_PACKET).unwrap();
+        let points = packet.points().unwrap();
+        let firing_records = packet.firing_records().unwrap();
+        for (point, firing_record) in points.iter().zip(&firing_records) {
+            if point.range() == 0. {
+                continue;
+            }
+            let expected = spec::HDL_32E.elevation(point.channel as usize).0;
+            assert!((expected - point.elevation()).abs() < 1e-3);
+            assert!((expected - firing_record.point().elevation()).abs() < 1e-3);
+        }
+    }
+
+    #[test]
+    fn decoder_firing_sequences() {
+        let packet = Packet::new(&VLP_16_DATA_PACKET).unwrap();
+        let both = packet.points().unwrap();
+        assert_eq!(both.len() / 2, both.iter().filter(|point| point.sequence == 1).count());
+        let mut decoder = Decoder::new();
+        decoder.firing_sequences(FiringSequences::Averaged);
+        decoder.push(packet.clone());
+        let averaged = decoder.finish().unwrap();
+        assert_eq!(both.len() / 2, averaged.len());
+        let pairs = both.chunks(2 * NUM_LASERS).flat_map(|block| {
+            let (first, second) = block.split_at(NUM_LASERS);
+            first.iter().zip(second)
+        });
+        for (point, (first, second)) in averaged.iter().zip(pairs) {
+            assert_eq!((first.channel, 0), (point.channel, point.sequence));
+            let expected = match (first.range() > 0., second.range() > 0.) {
+                (true, true) => (first.range() + second.range()) / 2.,
+                (false, _) => second.range(),
+                (true, false) => first.range(),
+            };
+            assert!((expected - point.range()).abs() < 1e-3);
+            let azimuth = (first.azimuth.degrees() + second.azimuth.degrees()) / 2.;
+            assert!((azimuth - point.azimuth.degrees()).abs() < 0.011);
+        }
+    }
+
+    #[test]
+    fn firing_records() {
+        let packet = Packet::new(&VLP_16_DATA_PACKET).unwrap();
+        let points = packet.points().unwrap();
+        let firing_records = packet.firing_records().unwrap();
+        assert_eq!(points.len(), firing_records.len());
+        for (point, firing_record) in points.iter().zip(&firing_records) {
+            let other = firing_record.point();
+            assert_eq!((point.channel, point.sequence), (other.channel, other.sequence));
+            assert_eq!(point.azimuth.degrees(), other.azimuth.degrees());
+            assert!((point.x - other.x).abs() < 1e-4);
+            assert!((point.y - other.y).abs() < 1e-4);
+            assert!((point.z - other.z).abs() < 1e-4);
+        }
+    }
+
+    #[test]
+    fn decoder_metrics() {
+        let mut decoder = Decoder::new();
+        assert_eq!(None, decoder.metrics().points_per_second());
+        decoder.push(Packet::new(&VLP_16_DATA_PACKET).unwrap());
+        decoder.push(Packet::new(&VLP_16_POSITION_PACKET).unwrap());
+        decoder.push(Packet::new(&VLP_16_DATA_PACKET).unwrap());
+        let count = decoder.finish().map_or(0, |points| points.len());
+        let metrics = decoder.metrics();
+        assert_eq!(3, metrics.packets);
+        assert_eq!(2 * DATA_PAYLOAD_LEN + POSITION_PAYLOAD_LEN, metrics.bytes);
+        assert_eq!(768, metrics.points);
+        assert!(count > 0);
+        assert!(metrics.elapsed > std::time::Duration::from_secs(0));
+        assert!(metrics.bytes_per_second().unwrap() > 0.);
+    }
+
+    #[test]
+    fn decoder_axes() {
+        let packet = Packet::new(&VLP_16_DATA_PACKET).unwrap();
+        let velodyne = packet.points().unwrap();
+        let mut decoder = Decoder::new();
+        decoder.axes(Axes::ForwardLeftUp);
+        decoder.push(packet);
+        let ros = decoder.finish().unwrap();
+        for (velodyne, ros) in velodyne.iter().zip(&ros) {
+            assert_eq!((velodyne.y, -velodyne.x, velodyne.z), (ros.x, ros.y, ros.z));
+        }
+    }
+
+    #[test]
+    fn decoder_azimuth_direction() {
+        let packet = Packet::new(&VLP_16_DATA_PACKET).unwrap();
+        let clockwise = packet.points().unwrap();
+        for &axes in &[Axes::Velodyne, Axes::ForwardLeftUp] {
+            let mut decoder = Decoder::new();
+            decoder.axes(axes).azimuth_direction(AzimuthDirection::Counterclockwise);
+            decoder.push(packet.clone());
+            let counterclockwise = decoder.finish().unwrap();
+            for (clockwise, counterclockwise) in clockwise.iter().zip(&counterclockwise) {
+                let sum = clockwise.azimuth.degrees() + counterclockwise.azimuth.degrees();
+                assert!(sum.abs() < 1e-3 || (sum - 360.).abs() < 1e-3);
+                assert_eq!(axes.convert([clockwise.x, clockwise.y, clockwise.z]),
+                           [counterclockwise.x, counterclockwise.y, counterclockwise.z]);
+                if axes == Axes::ForwardLeftUp && counterclockwise.range() > 1. {
+                    let angle = counterclockwise.y.atan2(counterclockwise.x).to_degrees();
+                    let error = (angle - counterclockwise.azimuth.degrees()).rem_euclid(360.);
+                    assert!(error.min(360. - error) < 0.1, "{}", error);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn decoder_time_source() {
+        let mut decoder = Decoder::new();
+        decoder.time_source(GpsTime::new());
+        decoder.push(Packet::new(&VLP_16_POSITION_PACKET).unwrap());
+        decoder.push(Packet::new(&VLP_16_DATA_PACKET).unwrap());
+        match decoder.finish().unwrap()[0].time {
+            Time::Absolute(time) => {
+                assert_eq!(UTC.ymd(2015, 7, 23).and_hms_micro(21, 41, 7, 108343), time)
+            }
+            Time::Offset(_) => panic!("point wasn't stamped"),
+        }
+    }
+
+    #[test]
+    fn decoder_does_not_interpolate_across_gaps() {
+        let packet = Packet::new(&VLP_16_DATA_PACKET).unwrap();
+        let mut decoder = Decoder::new();
+        decoder.push(packet.clone());
+        let points = decoder.push(packet).unwrap();
+        match points[points.len() - 1].azimuth {
+            Azimuth::Extrapolated(_) => {}
+            azimuth => panic!("unexpected azimuth: {:?}", azimuth),
+        }
+    }
+
+    #[test]
+    fn decoder_warnings() {
+        let packet = |timestamp: u32| {
+            let mut bytes = VLP_16_DATA_PACKET.to_vec();
+            LittleEndian::write_u32(&mut bytes[PACKET_HEADER_LEN + 1200..], timestamp);
+            Packet::new(&bytes).unwrap()
+        };
+        let mut decoder = Decoder::new();
+        decoder.push(packet(3_599_999_000));
+        decoder.push(packet(100));
+        decoder.push(packet(100));
+        let mut bytes = VLP_16_POSITION_PACKET.to_vec();
+        let start = bytes.iter().position(|&b| b == b'$').unwrap();
+        bytes[start + 10] ^= 1;
+        decoder.push(Packet::new(&bytes).unwrap());
+        let warnings = decoder.take_warnings();
+        assert_eq!(DecodeWarning::HourRollover, warnings[0]);
+        assert_eq!(DecodeWarning::AzimuthGap { microseconds: 0 }, warnings[1]);
+        match warnings[2] {
+            DecodeWarning::NmeaChecksum { ref sentence, .. } => assert!(sentence.contains("RMC")),
+            ref warning => panic!("unexpected warning: {:?}", warning),
+        }
+        assert_eq!(3, warnings.len());
+    }
+
+    #[test]
+    fn dual_return() {
+        let mut bytes = VLP_16_DATA_PACKET.to_vec();
+        bytes[PACKET_HEADER_LEN + wire::RETURN_MODE.offset] = 0x39;
+        let packet = Packet::new(&bytes).unwrap();
+        let data_blocks = packet.data_blocks().unwrap();
+        let firings = packet.firings().unwrap();
+        assert_eq!(NUM_DATA_BLOCKS * NUM_LASERS, firings.len());
+        let firing = firings[NUM_LASERS + 3];
+        assert_eq!(data_blocks[0].data_records[1][3].return_distance,
+                   firing.last.return_distance);
+        assert_eq!(data_blocks[1].data_records[1][3].return_distance,
+                   firing.strongest.return_distance);
+        assert_eq!(3, firing.channel);
+        let firing = firings[2 * NUM_LASERS];
+        assert_eq!(data_blocks[2].azimuth, firing.azimuth.centidegrees());
+        let points = packet.points().unwrap();
+        assert_eq!(2 * firings.len(), points.len());
+    }
+
+    #[test]
+    fn classify_packets() {
+        let mut bytes = VLP_16_DATA_PACKET;
+        bytes[PACKET_HEADER_LEN] = 0;
+        assert_eq!(None, classify(&bytes));
+        let mut bytes = VLP_16_POSITION_PACKET;
+        bytes[PACKET_HEADER_LEN + POSITION_NMEA_OFFSET] = b'G';
+        assert_eq!(None, classify(&bytes));
+        assert_eq!(None, classify(&VLP_16_DATA_PACKET[..PACKET_HEADER_LEN + 512]));
+        assert_eq!(None, classify(&[]));
     }
 
     #[test]
@@ -512,8 +3194,54 @@ mod tests {
     }
 
     #[test]
-    fn time_offset_examples() {
-        assert_eq!(Duration::nanoseconds(389_376), time_offset(3, 1, 1));
-        assert_eq!(Duration::nanoseconds(1_306_368), time_offset(11, 1, 15));
+    fn position_layout() {
+        let mut bytes = VLP_16_POSITION_PACKET.to_vec();
+        let payload = PACKET_HEADER_LEN;
+        bytes[payload + 187] = 41;
+        bytes[payload + 190] = 3;
+        bytes[payload + 192] = 0x10;
+        bytes[payload + 192 + 1] = 0x0e;
+        bytes[payload + 203] = 1;
+        // A sentence longer than the 72 bytes that older firmware uses.
+        let long = "$GPGGA,214106.00,3707.8178,N,12139.2690,W,2,09,0.9,12.3,M,-25.6,M,1.0,0000*4F";
+        for (i, n) in long.bytes().enumerate() {
+            bytes[payload + POSITION_NMEA_OFFSET + i] = n;
+        }
+        bytes[payload + POSITION_NMEA_OFFSET + long.len()] = 0;
+        let packet = Packet::new(&bytes).unwrap();
+        assert_eq!(long, packet.nmea().unwrap());
+        let status = packet.position_status().unwrap();
+        assert_eq!(41, status.top_board_temperature);
+        assert_eq!(3, status.adc_calibration_temperature_change);
+        assert_eq!(3600, status.seconds_since_adc_calibration);
+        assert_eq!(PpsStatus::Locked, status.pps);
+        assert!(status.thermal_shutdown);
+        assert_eq!(0x930d1d33, packet.timestamp().micros());
+        bytes[payload + 202] = 4;
+        assert!(Packet::new(&bytes).is_err());
+    }
+
+    #[test]
+    fn sentences() {
+        let mut bytes = VLP_16_POSITION_PACKET.to_vec();
+        let gga = "$GPGGA,214106.00,3707.8178,N,12139.2690,W,2,09,0.9,12.3,M,-25.6,M,1.0,0000*4E";
+        let rmc = Packet::new(&bytes).unwrap().nmea().unwrap().to_string();
+        let text = format!("{}\r\n{}\r\n\0$GPGSA,A,3,04,05,,09,12,,,24,,,,,2.5,1.3,2.1*39\0",
+                           gga,
+                           rmc);
+        let start = PACKET_HEADER_LEN + POSITION_NMEA_OFFSET;
+        bytes[start..start + text.len()].copy_from_slice(text.as_bytes());
+        let packet = Packet::new(&bytes).unwrap();
+        let sentences = packet.sentences().unwrap().unwrap();
+        assert_eq!(3, sentences.len());
+        match sentences[0] {
+            Sentence::Gga(fix) => assert_eq!(9, fix.satellites),
+            _ => panic!("should be a fix"),
+        }
+        match sentences[2] {
+            Sentence::Other(ref sentence) => assert!(sentence.starts_with("$GPGSA")),
+            _ => panic!("should be unparsed"),
+        }
+        assert!(packet.position().unwrap().unwrap().valid);
     }
 }
@@ -0,0 +1,446 @@
+//! Voxel-grid downsampling.
+//!
+//! Space is divided into cubes of a fixed size, and each occupied cube is reduced to a single
+//! point. A `VoxelGrid` downsamples one batch of points, and an `Accumulator` keeps a downsampled
+//! map of many frames:
+//!
+//! ```
+//! use velodyne::transform::Transform;
+//! use velodyne::voxel::Accumulator;
+//! let frames = velodyne::read_pcap_frames("data/single.pcap", &Default::default()).unwrap();
+//! let mut accumulator = Accumulator::new(0.5);
+//! for frame in &frames {
+//!     accumulator.add_at(frame, &Transform::from_euler(0., 0., 0., [100., 200., 0.]));
+//! }
+//! let map = accumulator.points();
+//! ```
+
+use Point;
+use frame::Frame;
+use std::collections::HashMap;
+use std::collections::hash_map::Entry;
+use transform::Transform;
+
+/// Downsamples points onto a voxel grid.
+#[derive(Clone, Copy, Debug)]
+pub struct VoxelGrid {
+    size: f32,
+    policy: Policy,
+}
+
+/// How the points in a voxel are reduced to a single point.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Policy {
+    /// Use the centroid of the points in the voxel.
+    ///
+    /// The reflectivity is averaged, and all other attributes come from the first point.
+    Centroid,
+    /// Use the first point in the voxel.
+    First,
+}
+
+/// Accumulates frames into a downsampled map with a bounded size.
+///
+/// Frames should already be in the map's frame, e.g. georeferenced by the decoder's transform,
+/// or are moved there with `add_at`. Each voxel keeps one point, reduced with the voxel's policy
+/// from every point that landed in it. When the map holds more voxels than its budget, the voxels
+/// that were updated least recently are evicted, so the map rolls along with the sensor.
+#[derive(Clone, Debug)]
+pub struct Accumulator {
+    grid: VoxelGrid,
+    max_voxels: usize,
+    voxels: HashMap<(i64, i64, i64), Voxel>,
+    frames: u64,
+    evicted: usize,
+}
+
+#[derive(Clone, Copy, Debug)]
+struct Voxel {
+    cell: Cell,
+    frame: u64,
+}
+
+#[derive(Clone, Copy, Debug)]
+struct Cell {
+    point: Point,
+    x: f64,
+    y: f64,
+    z: f64,
+    reflectivity: u32,
+    count: u32,
+}
+
+impl VoxelGrid {
+    /// Creates a new voxel grid with the given voxel size, in meters.
+    ///
+    /// The default policy is `Policy::Centroid`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the size is not positive.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use velodyne::voxel::VoxelGrid;
+    /// let voxel_grid = VoxelGrid::new(0.1);
+    /// ```
+    pub fn new(size: f32) -> VoxelGrid {
+        assert!(size > 0., "voxel size must be positive");
+        VoxelGrid {
+            size,
+            policy: Policy::Centroid,
+        }
+    }
+
+    /// Sets the policy used to reduce each voxel to a single point.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use velodyne::voxel::VoxelGrid;
+    /// use velodyne::voxel::Policy;
+    /// let mut voxel_grid = VoxelGrid::new(0.1);
+    /// voxel_grid.policy(Policy::First);
+    /// ```
+    pub fn policy(&mut self, policy: Policy) -> &mut VoxelGrid {
+        self.policy = policy;
+        self
+    }
+
+    /// Downsamples points, returning one point per occupied voxel.
+    ///
+    /// Output points are ordered by the first point that landed in each voxel.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use velodyne::voxel::VoxelGrid;
+    /// use velodyne::fixtures::VLP_16_DATA_PACKET;
+    /// use velodyne::vlp_16::Packet;
+    /// let points = Packet::new(&VLP_16_DATA_PACKET).unwrap().points().unwrap();
+    /// let downsampled = VoxelGrid::new(1.).downsample(&points);
+    /// assert!(downsampled.len() < points.len());
+    /// ```
+    pub fn downsample(&self, points: &[Point]) -> Vec<Point> {
+        let mut indices: HashMap<(i64, i64, i64), usize> = HashMap::new();
+        let mut cells: Vec<Cell> = Vec::new();
+        for point in points {
+            let key = self.key(point);
+            match indices.get(&key) {
+                Some(&index) => {
+                    if self.policy == Policy::Centroid {
+                        cells[index].add(point);
+                    }
+                }
+                None => {
+                    indices.insert(key, cells.len());
+                    cells.push(Cell::new(*point));
+                }
+            }
+        }
+        cells.into_iter().map(|cell| cell.point()).collect()
+    }
+
+    fn index(&self, n: f32) -> i64 {
+        (n / self.size).floor() as i64
+    }
+
+    fn key(&self, point: &Point) -> (i64, i64, i64) {
+        (self.index(point.x), self.index(point.y), self.index(point.z))
+    }
+}
+
+impl Accumulator {
+    /// The default budget, in voxels.
+    pub const DEFAULT_MAX_VOXELS: usize = 1 << 20;
+
+    /// Creates an empty accumulator with the given voxel size, in meters.
+    ///
+    /// The default policy is `Policy::Centroid`, and the default budget is
+    /// `Accumulator::DEFAULT_MAX_VOXELS`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the size is not positive.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use velodyne::voxel::Accumulator;
+    /// let accumulator = Accumulator::new(0.1);
+    /// assert!(accumulator.is_empty());
+    /// ```
+    pub fn new(size: f32) -> Accumulator {
+        Accumulator {
+            grid: VoxelGrid::new(size),
+            max_voxels: Accumulator::DEFAULT_MAX_VOXELS,
+            voxels: HashMap::new(),
+            frames: 0,
+            evicted: 0,
+        }
+    }
+
+    /// Sets the policy used to reduce each voxel to a single point.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use velodyne::voxel::Accumulator;
+    /// use velodyne::voxel::Policy;
+    /// let mut accumulator = Accumulator::new(0.1);
+    /// accumulator.policy(Policy::First);
+    /// ```
+    pub fn policy(&mut self, policy: Policy) -> &mut Accumulator {
+        self.grid.policy(policy);
+        self
+    }
+
+    /// Sets the most voxels that the map holds, which bounds its memory.
+    ///
+    /// If the map is already larger, it shrinks when the next frame is added.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use velodyne::voxel::Accumulator;
+    /// let mut accumulator = Accumulator::new(0.1);
+    /// accumulator.max_voxels(10_000);
+    /// ```
+    pub fn max_voxels(&mut self, max_voxels: usize) -> &mut Accumulator {
+        self.max_voxels = max_voxels;
+        self
+    }
+
+    /// Adds a frame whose points are already in the map's frame.
+    ///
+    /// Points without a return are skipped.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use velodyne::voxel::Accumulator;
+    /// let frames = velodyne::read_pcap_frames("data/single.pcap", &Default::default()).unwrap();
+    /// let mut accumulator = Accumulator::new(1.);
+    /// accumulator.add(&frames[0]);
+    /// assert!(!accumulator.is_empty());
+    /// ```
+    pub fn add(&mut self, frame: &Frame) {
+        self.add_points(frame.points.iter().cloned());
+    }
+
+    /// Adds a frame in the sensor's frame, moving it into the map's frame with the sensor's pose.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use velodyne::voxel::Accumulator;
+    /// use velodyne::transform::Transform;
+    /// let frames = velodyne::read_pcap_frames("data/single.pcap", &Default::default()).unwrap();
+    /// let mut accumulator = Accumulator::new(1.);
+    /// accumulator.add_at(&frames[0], &Transform::from_euler(0., 0., 90., [10., 0., 0.]));
+    /// ```
+    pub fn add_at(&mut self, frame: &Frame, pose: &Transform) {
+        let points = frame.points.iter().map(|&point| {
+            let mut point = point;
+            pose.apply(&mut point);
+            point
+        });
+        self.add_points(points);
+    }
+
+    /// Returns the number of occupied voxels.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use velodyne::voxel::Accumulator;
+    /// assert_eq!(0, Accumulator::new(1.).len());
+    /// ```
+    pub fn len(&self) -> usize {
+        self.voxels.len()
+    }
+
+    /// Returns true if no points have been accumulated.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use velodyne::voxel::Accumulator;
+    /// assert!(Accumulator::new(1.).is_empty());
+    /// ```
+    pub fn is_empty(&self) -> bool {
+        self.voxels.is_empty()
+    }
+
+    /// Returns the number of voxels that were evicted to stay within the budget.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use velodyne::voxel::Accumulator;
+    /// assert_eq!(0, Accumulator::new(1.).evicted());
+    /// ```
+    pub fn evicted(&self) -> usize {
+        self.evicted
+    }
+
+    /// Returns the map, one point per voxel.
+    ///
+    /// Points are ordered by voxel, so the same map is always exported the same way.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use velodyne::voxel::Accumulator;
+    /// use velodyne::cloud::PointCloud;
+    /// let accumulator = Accumulator::new(1.);
+    /// let cloud = PointCloud::from(accumulator.points());
+    /// ```
+    pub fn points(&self) -> Vec<Point> {
+        let mut voxels = self.voxels.iter().collect::<Vec<_>>();
+        voxels.sort_unstable_by_key(|&(key, _)| *key);
+        voxels.into_iter().map(|(_, voxel)| voxel.cell.point()).collect()
+    }
+
+    /// Removes every voxel, keeping the configuration.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use velodyne::voxel::Accumulator;
+    /// let mut accumulator = Accumulator::new(1.);
+    /// accumulator.clear();
+    /// ```
+    pub fn clear(&mut self) {
+        self.voxels.clear();
+        self.evicted = 0;
+    }
+
+    fn add_points<I: Iterator<Item = Point>>(&mut self, points: I) {
+        self.frames += 1;
+        let frame = self.frames;
+        for point in points.filter(|point| point.range() > 0.) {
+            match self.voxels.entry(self.grid.key(&point)) {
+                Entry::Occupied(mut entry) => {
+                    let voxel = entry.get_mut();
+                    if self.grid.policy == Policy::Centroid {
+                        voxel.cell.add(&point);
+                    }
+                    voxel.frame = frame;
+                }
+                Entry::Vacant(entry) => {
+                    entry.insert(Voxel { cell: Cell::new(point), frame });
+                }
+            }
+        }
+        if self.voxels.len() > self.max_voxels {
+            // The least recently updated voxels are the ones the sensor has moved away from.
+            let excess = self.voxels.len() - self.max_voxels;
+            let mut ages = self.voxels
+                .iter()
+                .map(|(&key, voxel)| (voxel.frame, key))
+                .collect::<Vec<_>>();
+            ages.sort_unstable();
+            for &(_, key) in &ages[..excess] {
+                self.voxels.remove(&key);
+            }
+            self.evicted += excess;
+        }
+    }
+}
+
+impl Cell {
+    fn new(point: Point) -> Cell {
+        Cell {
+            point,
+            x: f64::from(point.x),
+            y: f64::from(point.y),
+            z: f64::from(point.z),
+            reflectivity: u32::from(point.reflectivity),
+            count: 1,
+        }
+    }
+
+    fn add(&mut self, point: &Point) {
+        self.x += f64::from(point.x);
+        self.y += f64::from(point.y);
+        self.z += f64::from(point.z);
+        self.reflectivity += u32::from(point.reflectivity);
+        self.count += 1;
+    }
+
+    fn point(&self) -> Point {
+        let count = f64::from(self.count);
+        Point {
+            x: (self.x / count) as f32,
+            y: (self.y / count) as f32,
+            z: (self.z / count) as f32,
+            reflectivity: ((self.reflectivity as f32 / self.count as f32).round()) as u8,
+            ..self.point
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::Duration;
+    use point::{Azimuth, ReturnType, Time};
+    use units::Degrees;
+
+    fn point(x: f32, y: f32, z: f32, reflectivity: u8) -> Point {
+        Point {
+            x,
+            y,
+            z,
+            reflectivity,
+            channel: 0,
+            sequence: 0,
+            return_type: ReturnType::Strongest,
+            azimuth: Azimuth::Measured(Degrees(0.)),
+            time: Time::Offset(Duration::zero()),
+            sensor_id: None,
+        }
+    }
+
+    #[test]
+    fn centroid() {
+        let points = [point(0.1, 0.1, 0.1, 10),
+                      point(0.3, 0.5, 0.1, 20),
+                      point(-0.1, 0.1, 0.1, 30)];
+        let downsampled = VoxelGrid::new(1.).downsample(&points);
+        assert_eq!(2, downsampled.len());
+        assert!((downsampled[0].x - 0.2).abs() < 1e-6);
+        assert!((downsampled[0].y - 0.3).abs() < 1e-6);
+        assert_eq!(15, downsampled[0].reflectivity);
+        assert_eq!(-0.1, downsampled[1].x);
+    }
+
+    #[test]
+    fn first() {
+        let points = [point(0.1, 0.1, 0.1, 10), point(0.3, 0.5, 0.1, 20)];
+        let downsampled = VoxelGrid::new(1.).policy(Policy::First).downsample(&points);
+        assert_eq!(1, downsampled.len());
+        assert_eq!(0.1, downsampled[0].x);
+        assert_eq!(10, downsampled[0].reflectivity);
+    }
+
+    #[test]
+    fn accumulator() {
+        let mut accumulator = Accumulator::new(1.);
+        accumulator.max_voxels(2);
+        accumulator.add(&Frame::new(vec![point(0.1, 0.1, 0.1, 10), point(0.3, 0.5, 0.1, 20)]));
+        accumulator.add(&Frame::new(vec![point(5.5, 0.1, 0.1, 10)]));
+        let points = accumulator.points();
+        assert_eq!(2, points.len());
+        assert!((points[0].x - 0.2).abs() < 1e-6);
+        assert_eq!(5.5, points[1].x);
+
+        accumulator.add_at(&Frame::new(vec![point(0.5, 0.5, 0.5, 30)]),
+                           &Transform::from_euler(0., 0., 0., [9., 0., 0.]));
+        let points = accumulator.points();
+        assert_eq!(1, accumulator.evicted());
+        assert_eq!(vec![5.5, 9.5], points.iter().map(|point| point.x).collect::<Vec<_>>());
+    }
+}
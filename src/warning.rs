@@ -0,0 +1,70 @@
+//! Data-quality warnings, as values.
+//!
+//! Decoding keeps going through most problems in a capture, e.g. a gap between packets or a
+//! garbled NMEA sentence, and only logs them. Applications that keep a quality report with each
+//! processed capture need them as data instead, so decoders and pipelines also record a
+//! `DecodeWarning` for each one, which can be read during or after a run:
+//!
+//! ```
+//! use velodyne::io::{Pcap, Read};
+//! let mut points = Pcap::open("data/single.pcap").unwrap().points();
+//! let count = points.by_ref().count();
+//! for warning in points.warnings() {
+//!     println!("{}", warning);
+//! }
+//! ```
+
+use std::fmt;
+
+/// Something that was wrong with the data, which decoding worked around.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum DecodeWarning {
+    /// A packet that wasn't a Velodyne packet, e.g. ARP or mDNS traffic, was skipped.
+    SkippedPacket,
+    /// A NMEA sentence in a position packet didn't match its checksum.
+    NmeaChecksum {
+        /// The sentence.
+        sentence: String,
+        /// The checksum at the end of the sentence.
+        expected: String,
+        /// The checksum of the sentence's body.
+        calculated: u8,
+    },
+    /// Consecutive data packets were too far apart, or out of order, to interpolate azimuths
+    /// between them, e.g. because packets were dropped.
+    AzimuthGap {
+        /// The time between the packets' timestamps, in microseconds.
+        microseconds: i64,
+    },
+    /// The packet timestamps rolled over at the top of the hour.
+    HourRollover,
+    /// Misaligned bytes were skipped to find a data packet, see `vlp_16::Packet::resync`.
+    Resync {
+        /// The number of bytes skipped.
+        skipped: usize,
+    },
+}
+
+impl fmt::Display for DecodeWarning {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            DecodeWarning::SkippedPacket => f.write_str("skipped a non-Velodyne packet"),
+            DecodeWarning::NmeaChecksum { ref sentence, ref expected, calculated } => {
+                write!(f,
+                       "NMEA checksum failure, expected {}, got {:02X}: {}",
+                       expected,
+                       calculated,
+                       sentence)
+            }
+            DecodeWarning::AzimuthGap { microseconds } => {
+                write!(f, "{} microsecond gap between data packets", microseconds)
+            }
+            DecodeWarning::HourRollover => {
+                f.write_str("packet timestamps rolled over at the top of the hour")
+            }
+            DecodeWarning::Resync { skipped } => {
+                write!(f, "skipped {} bytes to resynchronize a data packet", skipped)
+            }
+        }
+    }
+}
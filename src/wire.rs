@@ -0,0 +1,235 @@
+//! The layout of packets on the wire.
+//!
+//! Tools that craft or patch packets, e.g. simulators, anonymizers, and fuzz harnesses, can use
+//! these instead of copying magic numbers. Unless noted, offsets are into a packet's UDP payload,
+//! i.e. after the `PACKET_HEADER_LEN` bytes of Ethernet, IPv4, and UDP headers, and multi-byte
+//! fields are little-endian:
+//!
+//! ```
+//! use velodyne::fixtures::VLP_16_DATA_PACKET;
+//! use velodyne::wire;
+//! let payload = &VLP_16_DATA_PACKET[wire::PACKET_HEADER_LEN..];
+//! assert_eq!(wire::DATA_PAYLOAD_LEN, payload.len());
+//! assert_eq!(0x22, payload[wire::SENSOR.offset]); // A VLP-16.
+//! ```
+
+use core::ops::Range;
+use units::Meters;
+
+/// The length of the Ethernet, IPv4, and UDP headers in front of every payload.
+pub const PACKET_HEADER_LEN: usize = 42;
+
+/// The length of a data packet's payload.
+pub const DATA_PAYLOAD_LEN: usize = 1206;
+
+/// The length of a position packet's payload.
+pub const POSITION_PAYLOAD_LEN: usize = 512;
+
+/// The number of data blocks at the start of a data packet.
+pub const NUM_DATA_BLOCKS: usize = 12;
+
+/// The length of a data block: a flag, an azimuth, and the data records.
+pub const DATA_BLOCK_LEN: usize = 100;
+
+/// The number of data records in a data block, i.e. two firing sequences of sixteen lasers or one
+/// of thirty-two.
+pub const DATA_RECORDS_PER_BLOCK: usize = 32;
+
+/// The length of a data record: a distance and a calibrated reflectivity.
+pub const DATA_RECORD_LEN: usize = 3;
+
+/// The flag that starts every data block, as a `u16`.
+pub const START_IDENTIFIER: u16 = 0xeeff;
+
+/// The degrees of each unit of a data block's azimuth, i.e. azimuths are in centidegrees.
+pub const AZIMUTH_RESOLUTION: f32 = 0.01;
+
+/// The distance of each unit of a data record's distance, for every supported sensor.
+///
+/// Each sensor's resolution is also in its `spec::SensorSpec`.
+pub const DISTANCE_RESOLUTION: Meters = Meters(0.002);
+
+/// A data packet's timestamp, a `u32` of microseconds past the hour.
+pub const DATA_TIMESTAMP: Field = Field {
+    offset: 1200,
+    len: 4,
+};
+
+/// A data packet's return mode factory byte.
+pub const RETURN_MODE: Field = Field {
+    offset: 1204,
+    len: 1,
+};
+
+/// A data packet's sensor factory byte.
+pub const SENSOR: Field = Field {
+    offset: 1205,
+    len: 1,
+};
+
+/// A position packet's status fields, from the top board temperature to the power-up
+/// temperature, which follow reserved bytes.
+pub const POSITION_STATUS: Field = Field {
+    offset: 187,
+    len: 19,
+};
+
+/// A position packet's timestamp, a `u32` of microseconds past the hour.
+pub const POSITION_TIMESTAMP: Field = Field {
+    offset: 198,
+    len: 4,
+};
+
+/// A position packet's PPS status byte.
+pub const PPS_STATUS: Field = Field {
+    offset: 202,
+    len: 1,
+};
+
+/// A position packet's NMEA buffer, which is padded with NULs.
+pub const NMEA: Field = Field {
+    offset: 206,
+    len: POSITION_PAYLOAD_LEN - 206,
+};
+
+/// A field of a payload.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Field {
+    /// The offset of the field's first byte.
+    pub offset: usize,
+    /// The number of bytes in the field.
+    pub len: usize,
+}
+
+impl Field {
+    /// Returns the offset just past the field.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use velodyne::wire;
+    /// assert_eq!(wire::RETURN_MODE.offset, wire::DATA_TIMESTAMP.end());
+    /// ```
+    pub fn end(&self) -> usize {
+        self.offset + self.len
+    }
+
+    /// Returns the field's bytes as a range, for indexing a payload.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use velodyne::fixtures::VLP_16_POSITION_PACKET;
+    /// use velodyne::wire;
+    /// let payload = &VLP_16_POSITION_PACKET[wire::PACKET_HEADER_LEN..];
+    /// assert!(payload[wire::NMEA.range()].starts_with(b"$GPRMC"));
+    /// ```
+    pub fn range(&self) -> Range<usize> {
+        self.offset..self.end()
+    }
+}
+
+/// Returns a data block, starting with its flag.
+///
+/// # Panics
+///
+/// Panics if `data_block` isn't less than `NUM_DATA_BLOCKS`.
+///
+/// # Examples
+///
+/// ```
+/// use velodyne::wire;
+/// assert_eq!(wire::DATA_TIMESTAMP.offset, wire::data_block(11).end());
+/// ```
+pub fn data_block(data_block: usize) -> Field {
+    assert!(data_block < NUM_DATA_BLOCKS, "no data block {}", data_block);
+    Field {
+        offset: data_block * DATA_BLOCK_LEN,
+        len: DATA_BLOCK_LEN,
+    }
+}
+
+/// Returns a data block's azimuth, a `u16` of centidegrees.
+///
+/// # Panics
+///
+/// Panics if `data_block` isn't less than `NUM_DATA_BLOCKS`.
+///
+/// # Examples
+///
+/// ```
+/// use velodyne::wire;
+/// assert_eq!(102, wire::azimuth(1).offset);
+/// ```
+pub fn azimuth(data_block: usize) -> Field {
+    Field {
+        offset: self::data_block(data_block).offset + 2,
+        len: 2,
+    }
+}
+
+/// Returns a data record, a `u16` distance followed by a `u8` calibrated reflectivity.
+///
+/// Records are in firing order, so the VLP-16's second firing sequence starts at record sixteen.
+///
+/// # Panics
+///
+/// Panics if `data_block` isn't less than `NUM_DATA_BLOCKS` or `data_record` isn't less than
+/// `DATA_RECORDS_PER_BLOCK`.
+///
+/// # Examples
+///
+/// ```
+/// use velodyne::wire;
+/// assert_eq!(wire::data_block(1).offset - 3, wire::data_record(0, 31).offset);
+/// ```
+pub fn data_record(data_block: usize, data_record: usize) -> Field {
+    assert!(data_record < DATA_RECORDS_PER_BLOCK,
+            "no data record {}",
+            data_record);
+    Field {
+        offset: self::data_block(data_block).offset + 4 + data_record * DATA_RECORD_LEN,
+        len: DATA_RECORD_LEN,
+    }
+}
+
+#[cfg(all(test, feature = "std"))]
+mod tests {
+    use super::*;
+    use byteorder::{ByteOrder, LittleEndian};
+    use fixtures::{VLP_16_DATA_PACKET, VLP_16_POSITION_PACKET};
+    use spec;
+    use vlp_16::Packet;
+
+    #[test]
+    fn fixtures() {
+        let payload = &VLP_16_DATA_PACKET[PACKET_HEADER_LEN..];
+        let packet = Packet::new(&VLP_16_DATA_PACKET).unwrap();
+        let data_blocks = packet.data_blocks().unwrap();
+        for (i, block) in data_blocks.iter().enumerate() {
+            let offset = data_block(i).offset;
+            assert_eq!(START_IDENTIFIER, LittleEndian::read_u16(&payload[offset..]));
+            let offset = azimuth(i).offset;
+            assert_eq!(block.azimuth.0, LittleEndian::read_u16(&payload[offset..]));
+            for (j, record) in block.data_records.iter().flat_map(|set| set.iter()).enumerate() {
+                let field = &payload[data_record(i, j).range()];
+                let distance = DISTANCE_RESOLUTION * f32::from(LittleEndian::read_u16(field));
+                assert_eq!(record.return_distance, distance);
+                assert_eq!(record.calibrated_reflectivity, field[2]);
+            }
+        }
+        let timestamp = LittleEndian::read_u32(&payload[DATA_TIMESTAMP.range()]);
+        assert_eq!(packet.timestamp().micros(), timestamp);
+        assert_eq!(DATA_PAYLOAD_LEN, SENSOR.end());
+
+        let payload = &VLP_16_POSITION_PACKET[PACKET_HEADER_LEN..];
+        let packet = Packet::new(&VLP_16_POSITION_PACKET).unwrap();
+        let timestamp = LittleEndian::read_u32(&payload[POSITION_TIMESTAMP.range()]);
+        assert_eq!(packet.timestamp().micros(), timestamp);
+        assert_eq!(NMEA.offset, POSITION_STATUS.end());
+        assert_eq!(POSITION_PAYLOAD_LEN, NMEA.end());
+        for spec in &[spec::VLP_16, spec::HDL_32E] {
+            assert_eq!(DISTANCE_RESOLUTION, spec.distance_resolution);
+        }
+    }
+}
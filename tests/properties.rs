@@ -0,0 +1,91 @@
+//! Properties that hold for any packet, not just the fixtures.
+//!
+//! Packets come straight off of the network, so the parser has to cope with anything. These
+//! complement the fuzz targets by running on every `cargo test`.
+
+extern crate byteorder;
+extern crate proptest;
+extern crate velodyne;
+
+use byteorder::{ByteOrder, LittleEndian};
+use proptest::collection::vec;
+use proptest::prelude::*;
+use velodyne::fixtures::{VLP_16_DATA_PACKET, VLP_16_POSITION_PACKET};
+use velodyne::vlp_16::{Decoder, Packet};
+use velodyne::wire::{self, DATA_PAYLOAD_LEN, PACKET_HEADER_LEN, POSITION_PAYLOAD_LEN};
+
+/// Decodes a packet every way there is, ignoring the results.
+fn decode(bytes: &[u8]) {
+    for packet in Packet::new(bytes).into_iter().chain(Packet::new_lenient(bytes)) {
+        let _ = packet.points();
+        let _ = packet.firings();
+        let _ = packet.position();
+        let _ = packet.sentences();
+        let _ = packet.position_status();
+        let mut decoder = Decoder::new();
+        decoder.push(packet.clone());
+        decoder.push(packet);
+        decoder.finish();
+    }
+}
+
+/// Returns a packet with the fixture's header in front of a payload.
+fn with_header(header: &[u8], payload: &[u8]) -> Vec<u8> {
+    let mut bytes = header[..PACKET_HEADER_LEN].to_vec();
+    bytes.extend_from_slice(payload);
+    bytes
+}
+
+proptest! {
+    #[test]
+    fn any_bytes(bytes in vec(any::<u8>(), 0..2 * DATA_PAYLOAD_LEN)) {
+        decode(&bytes);
+    }
+
+    #[test]
+    fn any_data_payload(mut payload in vec(any::<u8>(), DATA_PAYLOAD_LEN)) {
+        for i in 0..wire::NUM_DATA_BLOCKS {
+            let offset = wire::data_block(i).offset;
+            LittleEndian::write_u16(&mut payload[offset..], wire::START_IDENTIFIER);
+        }
+        decode(&with_header(&VLP_16_DATA_PACKET, &payload));
+    }
+
+    #[test]
+    fn any_position_payload(payload in vec(any::<u8>(), POSITION_PAYLOAD_LEN)) {
+        decode(&with_header(&VLP_16_POSITION_PACKET, &payload));
+    }
+
+    #[test]
+    fn data_round_trip(azimuths in vec(0..36_000u16, wire::NUM_DATA_BLOCKS),
+                       records in vec(any::<(u16, u8)>(),
+                                      wire::NUM_DATA_BLOCKS * wire::DATA_RECORDS_PER_BLOCK),
+                       timestamp in 0..3_600_000_000u32) {
+        let mut bytes = VLP_16_DATA_PACKET;
+        {
+            let payload = &mut bytes[PACKET_HEADER_LEN..];
+            for (i, &azimuth) in azimuths.iter().enumerate() {
+                LittleEndian::write_u16(&mut payload[wire::azimuth(i).range()], azimuth);
+                for j in 0..wire::DATA_RECORDS_PER_BLOCK {
+                    let (distance, reflectivity) = records[i * wire::DATA_RECORDS_PER_BLOCK + j];
+                    let field = &mut payload[wire::data_record(i, j).range()];
+                    LittleEndian::write_u16(field, distance);
+                    field[2] = reflectivity;
+                }
+            }
+            LittleEndian::write_u32(&mut payload[wire::DATA_TIMESTAMP.range()], timestamp);
+        }
+        let packet = Packet::new(&bytes).unwrap().into_data().unwrap();
+        prop_assert_eq!(timestamp, packet.timestamp.micros());
+        for (i, data_block) in packet.data_blocks.iter().enumerate() {
+            prop_assert_eq!(azimuths[i], data_block.azimuth.0);
+            let data_records = data_block.data_records.iter().flat_map(|set| set.iter());
+            for (j, data_record) in data_records.enumerate() {
+                let (distance, reflectivity) = records[i * wire::DATA_RECORDS_PER_BLOCK + j];
+                prop_assert_eq!(wire::DISTANCE_RESOLUTION * f32::from(distance),
+                                data_record.return_distance);
+                prop_assert_eq!(reflectivity, data_record.calibrated_reflectivity);
+            }
+        }
+    }
+}
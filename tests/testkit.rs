@@ -0,0 +1,44 @@
+//! Decoding generated streams end to end, the way a downstream crate would test against them.
+
+extern crate velodyne;
+
+use velodyne::frame::Assembler;
+use velodyne::info::Loss;
+use velodyne::io::{PcapSlice, Read};
+use velodyne::testkit;
+use velodyne::vlp_16::{Decoder, Packet};
+
+#[test]
+fn assembles_a_complete_frame() {
+    let stream = testkit::full_rotation();
+    let pcap = stream.to_pcap().unwrap();
+    let mut assembler = Assembler::new();
+    let mut frames = Vec::new();
+    for point in PcapSlice::new(&pcap[..]).unwrap().points() {
+        frames.extend(assembler.push(point));
+    }
+    frames.extend(assembler.finish());
+    assert_eq!(1, frames.iter().filter(|frame| frame.complete).count());
+    assert_eq!(stream.data_packets() * 384, frames.iter().map(|frame| frame.points.len()).sum::<usize>());
+}
+
+#[test]
+fn decodes_dual_returns() {
+    let mut stream = testkit::dual_return_rotation();
+    let mut decoder = Decoder::new();
+    while let Some(bytes) = stream.read() {
+        decoder.push(Packet::new(bytes.unwrap()).unwrap());
+    }
+    decoder.finish();
+    assert!(!decoder.return_mode_mismatch());
+}
+
+#[test]
+fn lossy_stream_reports_its_losses() {
+    let mut stream = testkit::lossy_stream();
+    let mut loss = Loss::new();
+    while let Some(bytes) = stream.read() {
+        loss.add(bytes.unwrap(), None);
+    }
+    assert_eq!(stream.lost(), loss.missing);
+}